@@ -0,0 +1,49 @@
+#!/usr/bin/perl
+
+# Copyright (C) 2017 Free Software Foundation, Inc.
+
+# This file is part of GNU Emacs.
+
+# GNU Emacs is free software: you can redistribute it and/or modify
+# it under the terms of the GNU General Public License as published by
+# the Free Software Foundation, either version 3 of the License, or
+# (at your option) any later version.
+
+# GNU Emacs is distributed in the hope that it will be useful,
+# but WITHOUT ANY WARRANTY; without even the implied warranty of
+# MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+# GNU General Public License for more details.
+
+# You should have received a copy of the GNU General Public License
+# along with GNU Emacs.  If not, see <https://www.gnu.org/licenses/>.
+
+# Convert the locale tailorings of Perl's Unicode::Collate::Locale to
+# the format of CollationTailorings.txt.  Usage:
+#   perl tailorings.pl LOCALE-DIR > CollationTailorings.txt
+# where LOCALE-DIR is the Unicode/Collate/Locale directory of the
+# module.  See README for what the output contains.
+
+use strict;
+use warnings;
+
+my $dir = shift or die "usage: $0 LOCALE-DIR\n";
+opendir (my $dh, $dir) or die "$dir: $!\n";
+for my $file (sort grep { /\.pl$/ } readdir $dh)
+  {
+    (my $locale = $file) =~ s/\.pl$//;
+    # The CJK tailorings are too large to include.
+    next if $locale =~ /^(ja|ko|zh)(_|$)/;
+    my $tailoring = do "$dir/$file" or die "$dir/$file: $@$!\n";
+    # Leave out the tailorings which need other options.
+    next if grep { !/^(locale_version|entry|upper_before_lower)$/ }
+      keys %$tailoring;
+    print "\@locale $locale\n";
+    print "\@upper_before_lower\n" if $tailoring->{upper_before_lower};
+    for (split /\n/, $tailoring->{entry} // '')
+      {
+	s/\s*#.*//;
+	next unless /\S/;
+	my ($chars, $elements) = split /\s*;\s*/, $_, 2;
+	print "$chars ; $elements\n";
+      }
+  }
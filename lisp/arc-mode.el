@@ -52,17 +52,17 @@
 ;; ARCHIVE TYPES: Currently only the archives below are handled, but the
 ;; structure for handling just about anything is in place.
 ;;
-;;			Arc	Lzh	Zip	Zoo	Rar	7z
-;;			--------------------------------------------
-;; View listing		Intern	Intern	Intern	Intern	Y	Y
-;; Extract member	Y	Y	Y	Y	Y	Y
-;; Save changed member	Y	Y	Y	Y	N	Y
-;; Add new member	N	N	N	N	N	N
-;; Delete member	Y	Y	Y	Y	N	Y
-;; Rename member	Y	Y	N	N	N	N
-;; Chmod		-	Y	Y	-	N	N
-;; Chown		-	Y	-	-	N	N
-;; Chgrp		-	Y	-	-	N	N
+;;			Arc	Lzh	Zip	Zoo	Rar	7z	Tar
+;;			----------------------------------------------------
+;; View listing		Intern	Intern	Intern	Intern	Y	Y	Intern
+;; Extract member	Y	Y	Y	Y	Y	Y	Intern
+;; Save changed member	Y	Y	Y	Y	N	Y	N
+;; Add new member	N	N	N	N	N	N	N
+;; Delete member	Y	Y	Y	Y	N	Y	N
+;; Rename member	Y	Y	N	N	N	N	N
+;; Chmod		-	Y	Y	-	N	N	N
+;; Chown		-	Y	-	-	N	N	N
+;; Chgrp		-	Y	-	-	N	N	N
 ;;
 ;; Special thanks to Bill Brodie <wbrodie@panix.com> for very useful tips
 ;; on the first released version of this package.
@@ -639,7 +639,7 @@ the mode is invalid.  If ERROR is nil then nil will be returned."
 
 (defun archive-unixdate (low high)
   "Stringify Unix (LOW HIGH) date."
-  (let* ((time (cons high low))
+  (let* ((time (list high low))
 	 (str (current-time-string time)))
     (format "%s-%s-%s"
 	    (substring str 8 10)
@@ -648,9 +648,21 @@ the mode is invalid.  If ERROR is nil then nil will be returned."
 
 (defun archive-unixtime (low high)
   "Stringify Unix (LOW HIGH) time."
-  (let ((str (current-time-string (cons high low))))
+  (let ((str (current-time-string (list high low))))
     (substring str 11 19)))
 
+(defun archive-read-entries ()
+  "Return the members of the archive in the current buffer.
+They are as returned by `archive-list-entries', which reads the
+archive file itself, so this returns nil if the buffer might not
+be the same as the file, or if the archive can't be read that way."
+  (and (fboundp 'archive-list-entries)
+       (not archive-remote)
+       (not (buffer-modified-p))
+       buffer-file-name
+       (file-regular-p buffer-file-name)
+       (archive-list-entries buffer-file-name)))
+
 (defun archive-get-lineno ()
   (if (>= (point) archive-file-list-start)
       (count-lines archive-file-list-start
@@ -788,6 +800,9 @@ archive.
                 (re-search-forward "Rar!" (+ (point) 100000) t))
            'rar-exe)
 	  ((looking-at "7z\274\257\047\034") '7z)
+	  ((and (fboundp 'archive-list-entries)
+		(looking-at "\\(.\\|\n\\)\\{257\\}ustar"))
+	   'tar)
 	  (t (error "Buffer format not recognized")))))
 ;; -------------------------------------------------------------------------
 
@@ -1809,14 +1824,13 @@ This doesn't recover lost files, it just undoes changes in the buffer itself."
 ;; -------------------------------------------------------------------------
 ;;; Section: Zip Archives
 
-(defun archive-zip-summarize ()
+(defun archive-zip-central-headers ()
+  "Return the positions of the central directory headers of the zip
+archive in the current buffer."
   (goto-char (- (point-max) (- 22 18)))
   (search-backward-regexp "[P]K\005\006")
   (let ((p (archive-l-e (+ (point) 16) 4))
-        (maxlen 8)
-	(totalsize 0)
-        files
-	visual
+        headers
         emacs-int-has-32bits)
     (when (= p -1)
       ;; If the offset of end-of-central-directory is -1, this is a
@@ -1845,22 +1859,47 @@ This doesn't recover lost files, it just undoes changes in the buffer itself."
       (setq p (archive-l-e (+ p 48) (if emacs-int-has-32bits 4 8))))
     (setq p (+ p (point-min)))
     (while (string= "PK\001\002" (buffer-substring p (+ p 4)))
-      (let* ((creator (byte-after (+ p 5)))
+      (push p headers)
+      (setq p (+ p 46
+                 (archive-l-e (+ p 28) 2)
+                 (archive-l-e (+ p 30) 2)
+                 (archive-l-e (+ p 32) 2))))
+    (nreverse headers)))
+
+(defun archive-zip-summarize ()
+  ;; Each element of HEADERS is the position of a central directory
+  ;; header, and the member as listed by `archive-list-entries' after
+  ;; it if that could read the archive.
+  (let ((headers (or (mapcar (lambda (entry)
+                               (cons (+ (point-min) (nth 4 entry)) entry))
+                             (archive-read-entries))
+                     (mapcar #'list (archive-zip-central-headers))))
+        (maxlen 8)
+	(totalsize 0)
+        files
+	visual)
+    (dolist (header headers)
+      (let* ((p (car header))
+             (entry (cdr header))
+             (creator (byte-after (+ p 5)))
 	     ;; (method  (archive-l-e (+ p 10) 2))
              (modtime (archive-l-e (+ p 12) 2))
              (moddate (archive-l-e (+ p 14) 2))
 	     ;; Convert to float to avoid overflow for very large files.
-             (ucsize  (archive-l-e (+ p 24) 4 'float))
+             (ucsize  (if entry
+                          (float (nth 1 entry))
+                        (archive-l-e (+ p 24) 4 'float)))
              (fnlen   (archive-l-e (+ p 28) 2))
-             (exlen   (archive-l-e (+ p 30) 2))
-             (fclen   (archive-l-e (+ p 32) 2))
              (lheader (archive-l-e (+ p 42) 4))
              (efnname (let ((str (buffer-substring (+ p 46) (+ p 46 fnlen))))
 			(decode-coding-string
 			 str archive-file-name-coding-system)))
-	     (isdir   (and (= ucsize 0)
-			   (string= (file-name-nondirectory efnname) "")))
-	     (mode    (cond ((memq creator '(2 3)) ; Unix
+	     (isdir   (if entry
+                          (= (logand (nth 3 entry) #o170000) #o40000)
+                        (and (= ucsize 0)
+			     (string= (file-name-nondirectory efnname) ""))))
+	     (mode    (cond (entry (nth 3 entry))
+                            ((memq creator '(2 3)) ; Unix
 			     (archive-l-e (+ p 40) 2))
 			    ((memq creator '(0 5 6 7 10 11 15)) ; Dos etc.
 			     (logior ?\444
@@ -1875,11 +1914,16 @@ This doesn't recover lost files, it just undoes changes in the buffer itself."
 			   (string= (upcase efnname) efnname)))
              (ifnname (if fiddle (downcase efnname) efnname))
 	     (width (string-width ifnname))
+             (mtime (nth 2 entry))
              (text    (format "  %10s  %8.0f  %-11s  %-8s  %s"
 			      modestr
                               ucsize
-                              (archive-dosdate moddate)
-                              (archive-dostime modtime)
+                              (if mtime
+                                  (archive-unixdate (cadr mtime) (car mtime))
+                                (archive-dosdate moddate))
+                              (if mtime
+                                  (archive-unixtime (cadr mtime) (car mtime))
+                                (archive-dostime modtime))
                               ifnname)))
         (setq maxlen (max maxlen width)
 	      totalsize (+ totalsize ucsize)
@@ -1891,8 +1935,7 @@ This doesn't recover lost files, it just undoes changes in the buffer itself."
 			      nil
 			    (vector efnname ifnname fiddle mode
 				    (list (1- p) lheader)))
-                          files)
-              p (+ p 46 fnlen exlen fclen))))
+                          files))))
     (goto-char (point-min))
     (let ((dash (concat "- ----------  --------  -----------  --------  "
 			(make-string maxlen ?-)
@@ -1910,6 +1953,11 @@ This doesn't recover lost files, it just undoes changes in the buffer itself."
 
 (defun archive-zip-extract (archive name)
   (cond
+   ;; Members which are stored or deflated can be read without
+   ;; running any program.
+   ((and (fboundp 'archive-extract-entry)
+         (archive-extract-entry
+          archive (encode-coding-string name archive-file-name-coding-system))))
    ((member-ignore-case (car archive-zip-extract) '("pkunzip" "pkzip"))
     (archive-*-extract archive name archive-zip-extract))
    ((equal (car archive-zip-extract) archive-7z-program)
@@ -2186,6 +2234,67 @@ This doesn't recover lost files, it just undoes changes in the buffer itself."
    descr
    archive-7z-update))
 
+;; -------------------------------------------------------------------------
+;;; Section: Tar Archives
+
+;; Tar archives are only read in-process, by `archive-list-entries' and
+;; `archive-extract-entry'.  Use `tar-mode' to change them.
+
+(defun archive-tar-summarize ()
+  (let ((entries (or (archive-read-entries)
+		     (error "Can't read this tar archive; try `tar-mode'")))
+	(maxlen 8)
+	(totalsize 0)
+	files
+	visual)
+    (dolist (entry entries)
+      (let* ((efnname (decode-coding-string (car entry)
+					    archive-file-name-coding-system))
+	     (size (float (nth 1 entry)))
+	     (mtime (nth 2 entry))
+	     (mode (nth 3 entry))
+	     (text (format "  %10s  %8.0f  %-11s  %-8s  %s"
+			   (archive-int-to-mode mode)
+			   size
+			   (archive-unixdate (cadr mtime) (car mtime))
+			   (archive-unixtime (cadr mtime) (car mtime))
+			   efnname)))
+	(setq maxlen (max maxlen (string-width efnname))
+	      totalsize (+ totalsize size)
+	      visual (cons (vector text
+				   (- (length text) (length efnname))
+				   (length text))
+			   visual)
+	      ;; Only regular files can be extracted.
+	      files (cons (and (= (logand mode #o170000) #o100000)
+			       (vector efnname efnname nil mode (nth 4 entry)))
+			  files))))
+    (goto-char (point-min))
+    (let ((dash (concat "- ----------  --------  -----------  --------  "
+			(make-string maxlen ?-)
+			"\n")))
+      (insert "M Filemode      Length  Date         Time      File\n"
+	      dash)
+      (archive-summarize-files (nreverse visual))
+      (insert dash
+	      (format "              %8.0f                         %d file%s"
+		      totalsize
+		      (length files)
+		      (if (= 1 (length files)) "" "s"))
+	      "\n"))
+    (apply #'vector (nreverse files))))
+
+(defun archive-tar-extract (archive name)
+  (or (archive-extract-entry
+       archive (encode-coding-string name archive-file-name-coding-system))
+      (error "Can't extract %s from this tar archive" name)))
+
+(defun archive-tar-write-file-member (_archive _descr)
+  (error "Saving members of tar archives is not supported; try `tar-mode'"))
+
+(defun archive-tar-expunge (_archive _files)
+  (error "Deleting members of tar archives is not supported; try `tar-mode'"))
+
 ;; -------------------------------------------------------------------------
 ;;; Section `ar' archives.
 
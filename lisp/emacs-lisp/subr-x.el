@@ -201,10 +201,6 @@ Like `when-let*' except SPEC can have the form (SYMBOL VALUEFORM)."
   "Check whether STRING is empty."
   (string= string ""))
 
-(defsubst string-join (strings &optional separator)
-  "Join all STRINGS using SEPARATOR."
-  (mapconcat 'identity strings separator))
-
 (define-obsolete-function-alias 'string-reverse 'reverse "25.1")
 
 (defsubst string-trim-left (string &optional regexp)
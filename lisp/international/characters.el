@@ -876,7 +876,7 @@ with L, LRE, or LRO Unicode bidi character type.")
 ;;; Setting char-width-table.  The default is 1.
 
 ;; 0: non-spacing, enclosing combining, formatting, Hangul Jamo medial
-;;    and final characters.
+;;    and final characters, except SOFT HYPHEN, which is displayed.
 (let ((l '((#x0300 . #x036F)
 	   (#x0483 . #x0489)
 	   (#x0591 . #x05BD)
@@ -885,10 +885,12 @@ with L, LRE, or LRO Unicode bidi character type.")
 	   (#x05C4 . #x05C5)
 	   (#x05C7 . #x05C7)
 	   (#x0600 . #x0605)
-	   (#x0610 . #x061C)
+	   (#x0610 . #x061A)
+	   (#x061C . #x061C)
 	   (#x064B . #x065F)
 	   (#x0670 . #x0670)
-	   (#x06D6 . #x06E4)
+	   (#x06D6 . #x06DD)
+	   (#x06DF . #x06E4)
 	   (#x06E7 . #x06E8)
 	   (#x06EA . #x06ED)
 	   (#x070F . #x070F)
@@ -896,8 +898,10 @@ with L, LRE, or LRO Unicode bidi character type.")
 	   (#x0730 . #x074A)
 	   (#x07A6 . #x07B0)
 	   (#x07EB . #x07F3)
-	   (#x0816 . #x0823)
-	   (#x0825 . #x082D)
+	   (#x0816 . #x0819)
+	   (#x081B . #x0823)
+	   (#x0825 . #x0827)
+	   (#x0829 . #x082D)
 	   (#x0859 . #x085B)
 	   (#x08D4 . #x0902)
 	   (#x093A . #x093A)
@@ -913,7 +917,6 @@ with L, LRE, or LRO Unicode bidi character type.")
 	   (#x09E2 . #x09E3)
 	   (#x0A01 . #x0A02)
 	   (#x0A3C . #x0A3C)
-	   (#x0A41 . #x0A4D)
 	   (#x0A41 . #x0A42)
 	   (#x0A47 . #x0A48)
 	   (#x0A4B . #x0A4D)
@@ -922,37 +925,47 @@ with L, LRE, or LRO Unicode bidi character type.")
 	   (#x0A75 . #x0A75)
 	   (#x0A81 . #x0A82)
 	   (#x0ABC . #x0ABC)
-	   (#x0AC1 . #x0AC8)
+	   (#x0AC1 . #x0AC5)
+	   (#x0AC7 . #x0AC8)
 	   (#x0ACD . #x0ACD)
 	   (#x0AE2 . #x0AE3)
+	   (#x0AFA . #x0AFF)
 	   (#x0B01 . #x0B01)
 	   (#x0B3C . #x0B3C)
 	   (#x0B3F . #x0B3F)
 	   (#x0B41 . #x0B44)
-	   (#x0B4D . #x0B56)
+	   (#x0B4D . #x0B4D)
+	   (#x0B56 . #x0B56)
 	   (#x0B62 . #x0B63)
 	   (#x0B82 . #x0B82)
 	   (#x0BC0 . #x0BC0)
 	   (#x0BCD . #x0BCD)
 	   (#x0C00 . #x0C00)
 	   (#x0C3E . #x0C40)
-	   (#x0C46 . #x0C56)
+	   (#x0C46 . #x0C48)
+	   (#x0C4A . #x0C4D)
+	   (#x0C55 . #x0C56)
 	   (#x0C62 . #x0C63)
 	   (#x0C81 . #x0C81)
 	   (#x0CBC . #x0CBC)
+	   (#x0CBF . #x0CBF)
+	   (#x0CC6 . #x0CC6)
 	   (#x0CCC . #x0CCD)
 	   (#x0CE2 . #x0CE3)
-	   (#x0D01 . #x0D01)
+	   (#x0D00 . #x0D01)
+	   (#x0D3B . #x0D3C)
 	   (#x0D41 . #x0D44)
 	   (#x0D4D . #x0D4D)
 	   (#x0D62 . #x0D63)
 	   (#x0DCA . #x0DCA)
-	   (#x0DD2 . #x0DD6)
+	   (#x0DD2 . #x0DD4)
+	   (#x0DD6 . #x0DD6)
 	   (#x0E31 . #x0E31)
 	   (#x0E34 . #x0E3A)
 	   (#x0E47 . #x0E4E)
 	   (#x0EB1 . #x0EB1)
-	   (#x0EB4 . #x0EBC)
+	   (#x0EB4 . #x0EB9)
+	   (#x0EBB . #x0EBC)
 	   (#x0EC8 . #x0ECD)
 	   (#x0F18 . #x0F19)
 	   (#x0F35 . #x0F35)
@@ -961,19 +974,21 @@ with L, LRE, or LRO Unicode bidi character type.")
 	   (#x0F71 . #x0F7E)
 	   (#x0F80 . #x0F84)
 	   (#x0F86 . #x0F87)
-	   (#x0F8D . #x0FBC)
+	   (#x0F8D . #x0F97)
+	   (#x0F99 . #x0FBC)
 	   (#x0FC6 . #x0FC6)
 	   (#x102D . #x1030)
 	   (#x1032 . #x1037)
 	   (#x1039 . #x103A)
 	   (#x103D . #x103E)
 	   (#x1058 . #x1059)
-	   (#x105E . #x1160)
-	   (#x1171 . #x1074)
+	   (#x105E . #x1060)
+	   (#x1071 . #x1074)
 	   (#x1082 . #x1082)
 	   (#x1085 . #x1086)
 	   (#x108D . #x108D)
 	   (#x109D . #x109D)
+	   (#x1160 . #x11FF)
 	   (#x135D . #x135F)
 	   (#x1712 . #x1714)
 	   (#x1732 . #x1734)
@@ -985,7 +1000,6 @@ with L, LRE, or LRO Unicode bidi character type.")
 	   (#x17C9 . #x17D3)
 	   (#x17DD . #x17DD)
 	   (#x180B . #x180E)
-	   (#x18A9 . #x18A9)
 	   (#x1885 . #x1886)
 	   (#x18A9 . #x18A9)
 	   (#x1920 . #x1922)
@@ -1024,16 +1038,21 @@ with L, LRE, or LRO Unicode bidi character type.")
 	   (#x1CED . #x1CED)
 	   (#x1CF4 . #x1CF4)
 	   (#x1CF8 . #x1CF9)
-	   (#x1DC0 . #x1DFF)
+	   (#x1DC0 . #x1DF9)
+	   (#x1DFB . #x1DFF)
 	   (#x200B . #x200F)
 	   (#x202A . #x202E)
-	   (#x2060 . #x206F)
+	   (#x2060 . #x2064)
+	   (#x2066 . #x206F)
 	   (#x20D0 . #x20F0)
 	   (#x2CEF . #x2CF1)
 	   (#x2D7F . #x2D7F)
 	   (#x2DE0 . #x2DFF)
+	   (#x302A . #x302D)
+	   (#x3099 . #x309A)
 	   (#xA66F . #xA672)
-	   (#xA674 . #xA69F)
+	   (#xA674 . #xA67D)
+	   (#xA69E . #xA69F)
 	   (#xA6F0 . #xA6F1)
 	   (#xA802 . #xA802)
 	   (#xA806 . #xA806)
@@ -1043,7 +1062,8 @@ with L, LRE, or LRO Unicode bidi character type.")
 	   (#xA8E0 . #xA8F1)
 	   (#xA926 . #xA92D)
 	   (#xA947 . #xA951)
-	   (#xA980 . #xA9B3)
+	   (#xA980 . #xA982)
+	   (#xA9B3 . #xA9B3)
 	   (#xA9B6 . #xA9B9)
 	   (#xA9BC . #xA9BC)
 	   (#xA9E5 . #xA9E5)
@@ -1063,6 +1083,7 @@ with L, LRE, or LRO Unicode bidi character type.")
 	   (#xABE5 . #xABE5)
 	   (#xABE8 . #xABE8)
 	   (#xABED . #xABED)
+	   (#xD7B0 . #xD7FF)
 	   (#xFB1E . #xFB1E)
 	   (#xFE00 . #xFE0F)
 	   (#xFE20 . #xFE2F)
@@ -1071,8 +1092,11 @@ with L, LRE, or LRO Unicode bidi character type.")
 	   (#x101FD . #x101FD)
 	   (#x102E0 . #x102E0)
 	   (#x10376 . #x1037A)
-	   (#x10A01 . #x10A0F)
-	   (#x10A38 . #x10A3F)
+	   (#x10A01 . #x10A03)
+	   (#x10A05 . #x10A06)
+	   (#x10A0C . #x10A0F)
+	   (#x10A38 . #x10A3A)
+	   (#x10A3F . #x10A3F)
 	   (#x10AE5 . #x10AE6)
 	   (#x11001 . #x11001)
 	   (#x11038 . #x11046)
@@ -1102,7 +1126,8 @@ with L, LRE, or LRO Unicode bidi character type.")
 	   (#x11442 . #x11444)
 	   (#x11446 . #x11446)
 	   (#x114B3 . #x114B8)
-	   (#x114BA . #x114C0)
+	   (#x114BA . #x114BA)
+	   (#x114BF . #x114C0)
 	   (#x114C2 . #x114C3)
 	   (#x115B2 . #x115B5)
 	   (#x115BC . #x115BD)
@@ -1118,12 +1143,27 @@ with L, LRE, or LRO Unicode bidi character type.")
 	   (#x1171D . #x1171F)
 	   (#x11722 . #x11725)
 	   (#x11727 . #x1172B)
+	   (#x11A01 . #x11A06)
+	   (#x11A09 . #x11A0A)
+	   (#x11A33 . #x11A38)
+	   (#x11A3B . #x11A3E)
+	   (#x11A47 . #x11A47)
+	   (#x11A51 . #x11A56)
+	   (#x11A59 . #x11A5B)
+	   (#x11A8A . #x11A96)
+	   (#x11A98 . #x11A99)
 	   (#x11C30 . #x11C36)
 	   (#x11C38 . #x11C3D)
+	   (#x11C3F . #x11C3F)
 	   (#x11C92 . #x11CA7)
 	   (#x11CAA . #x11CB0)
 	   (#x11CB2 . #x11CB3)
 	   (#x11CB5 . #x11CB6)
+	   (#x11D31 . #x11D36)
+	   (#x11D3A . #x11D3A)
+	   (#x11D3C . #x11D3D)
+	   (#x11D3F . #x11D45)
+	   (#x11D47 . #x11D47)
 	   (#x16AF0 . #x16AF4)
 	   (#x16B30 . #x16B36)
 	   (#x16F8F . #x16F92)
@@ -1147,11 +1187,14 @@ with L, LRE, or LRO Unicode bidi character type.")
 	   (#x1E026 . #x1E02A)
 	   (#x1E8D0 . #x1E8D6)
 	   (#x1E944 . #x1E94A)
-	   (#xE0001 . #xE01EF))))
+	   (#xE0001 . #xE0001)
+	   (#xE0020 . #xE007F)
+	   (#xE0100 . #xE01EF))))
   (dolist (elt l)
     (set-char-table-range char-width-table elt 0)))
 
-;; 2: East Asian Wide and Full-width characters.
+;; 2: East Asian Wide and Full-width characters, and emoji with the
+;;    Emoji_Presentation property, except those set to 0 above.
 (let ((l '((#x1100 . #x115F)
 	   (#x231A . #x231B)
 	   (#x2329 . #x232A)
@@ -1187,28 +1230,53 @@ with L, LRE, or LRO Unicode bidi character type.")
 	   (#x2B1B . #x2B1C)
 	   (#x2B50 . #x2B50)
 	   (#x2B55 . #x2B55)
-	   (#x2E80 . #x303E)
-	   (#x3040 . #x3247)
+	   (#x2E80 . #x2E99)
+	   (#x2E9B . #x2EF3)
+	   (#x2F00 . #x2FD5)
+	   (#x2FF0 . #x2FFB)
+	   (#x3000 . #x3029)
+	   (#x302E . #x303E)
+	   (#x3041 . #x3096)
+	   (#x309B . #x30FF)
+	   (#x3105 . #x312F)
+	   (#x3131 . #x318E)
+	   (#x3190 . #x31E3)
+	   (#x31F0 . #x321E)
+	   (#x3220 . #x3247)
 	   (#x3250 . #x4DBF)
-	   (#x4E00 . #xA4CF)
+	   (#x4E00 . #xA48C)
 	   (#xA490 . #xA4C6)
-	   (#xA960 . #xA97F)
+	   (#xA960 . #xA97C)
 	   (#xAC00 . #xD7A3)
 	   (#xF900 . #xFAFF)
 	   (#xFE10 . #xFE19)
-	   (#xFE30 . #xFE6F)
+	   (#xFE30 . #xFE52)
+	   (#xFE54 . #xFE66)
+	   (#xFE68 . #xFE6B)
 	   (#xFF01 . #xFF60)
 	   (#xFFE0 . #xFFE6)
-	   (#x16FE0 . #x16FE1)
-	   (#x17000 . #x187EC)
-	   (#x18800 . #x18AF2)
-	   (#x1B000 . #x1B11E)
-           (#x1B170 . #x1B2FB)
+	   (#x16FE0 . #x16FE4)
+	   (#x16FF0 . #x16FF1)
+	   (#x17000 . #x187F7)
+	   (#x18800 . #x18CD5)
+	   (#x18D00 . #x18D08)
+	   (#x1AFF0 . #x1AFF3)
+	   (#x1AFF5 . #x1AFFB)
+	   (#x1AFFD . #x1AFFE)
+	   (#x1B000 . #x1B122)
+	   (#x1B150 . #x1B152)
+	   (#x1B164 . #x1B167)
+	   (#x1B170 . #x1B2FB)
 	   (#x1F004 . #x1F004)
 	   (#x1F0CF . #x1F0CF)
 	   (#x1F18E . #x1F18E)
 	   (#x1F191 . #x1F19A)
-	   (#x1F200 . #x1F320)
+	   (#x1F1E6 . #x1F202)
+	   (#x1F210 . #x1F23B)
+	   (#x1F240 . #x1F248)
+	   (#x1F250 . #x1F251)
+	   (#x1F260 . #x1F265)
+	   (#x1F300 . #x1F320)
 	   (#x1F32D . #x1F335)
 	   (#x1F337 . #x1F37C)
 	   (#x1F37E . #x1F393)
@@ -1216,9 +1284,7 @@ with L, LRE, or LRO Unicode bidi character type.")
 	   (#x1F3CF . #x1F3D3)
 	   (#x1F3E0 . #x1F3F0)
 	   (#x1F3F4 . #x1F3F4)
-	   (#x1F3F8 . #x1F3FA)
-	   (#x1F3FB . #x1F3FF)
-	   (#x1F400 . #x1F43E)
+	   (#x1F3F8 . #x1F43E)
 	   (#x1F440 . #x1F440)
 	   (#x1F442 . #x1F4FC)
 	   (#x1F4FF . #x1F53D)
@@ -1227,21 +1293,30 @@ with L, LRE, or LRO Unicode bidi character type.")
 	   (#x1F57A . #x1F57A)
 	   (#x1F595 . #x1F596)
 	   (#x1F5A4 . #x1F5A4)
-	   (#x1F5FB . #x1F5FF)
-	   (#x1F600 . #x1F64F)
+	   (#x1F5FB . #x1F64F)
 	   (#x1F680 . #x1F6C5)
 	   (#x1F6CC . #x1F6CC)
 	   (#x1F6D0 . #x1F6D2)
+	   (#x1F6D5 . #x1F6D7)
+	   (#x1F6DD . #x1F6DF)
 	   (#x1F6EB . #x1F6EC)
-	   (#x1F6F4 . #x1F6F8)
-	   (#x1F910 . #x1F93E)
-	   (#x1F940 . #x1F94C)
-	   (#x1F950 . #x1F96B)
-	   (#x1F980 . #x1F997)
-	   (#x1F9C0 . #x1F9C0)
-           (#x1F9D0 . #x1F9E6)
-	   (#x20000 . #x2FFFF)
-	   (#x30000 . #x3FFFF))))
+	   (#x1F6F4 . #x1F6FC)
+	   (#x1F7E0 . #x1F7EB)
+	   (#x1F7F0 . #x1F7F0)
+	   (#x1F90C . #x1F93A)
+	   (#x1F93C . #x1F945)
+	   (#x1F947 . #x1F9FF)
+	   (#x1FA70 . #x1FA74)
+	   (#x1FA78 . #x1FA7C)
+	   (#x1FA80 . #x1FA86)
+	   (#x1FA90 . #x1FAAC)
+	   (#x1FAB0 . #x1FABA)
+	   (#x1FAC0 . #x1FAC5)
+	   (#x1FAD0 . #x1FAD9)
+	   (#x1FAE0 . #x1FAE7)
+	   (#x1FAF0 . #x1FAF6)
+	   (#x20000 . #x2FFFD)
+	   (#x30000 . #x3FFFD))))
   (dolist (elt l)
     (set-char-table-range char-width-table elt 2)))
 
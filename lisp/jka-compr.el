@@ -141,13 +141,52 @@ data appears to be compressed already.")
 (defvar jka-compr-dd-blocksize 256)
 
 
+(defcustom jka-compr-use-builtin-gzip t
+  "Non-nil means to compress and uncompress gzip files within Emacs.
+This is done instead of running the \"gzip\" program, when that is
+the compression program of a file and it is given no other options
+than \"-c\", \"-q\" and \"-d\"."
+  :type 'boolean
+  :version "27.1"
+  :group 'jka-compr)
+
+(defun jka-compr-builtin-gzip-p (prog args)
+  "Return non-nil if running PROG with ARGS can be done within Emacs."
+  (and jka-compr-use-builtin-gzip
+       (fboundp 'gzip-compress-string)
+       (equal prog "gzip")
+       (null (delete "-c" (delete "-q" (remove "-d" args))))))
+
+(defun jka-compr-builtin-gzip (message infile output args)
+  "Do what gzip would with ARGS to INFILE, within Emacs.
+MESSAGE and OUTPUT are as for `jka-compr-call-process'."
+  (let ((data (with-temp-buffer
+		(set-buffer-multibyte nil)
+		(insert-file-contents-literally infile)
+		(buffer-string))))
+    (setq data (condition-case nil
+		   (if (member "-d" args)
+		       (gzip-decompress-string data)
+		     (gzip-compress-string data))
+		 (error (jka-compr-error "gzip" args infile message))))
+    (cond
+     ((stringp output)
+      (let ((coding-system-for-write 'no-conversion))
+	(write-region data nil output nil 'silent)))
+     ((bufferp output)
+      (with-current-buffer output
+	(insert data)))
+     (t
+      (insert data)))))
+
 (defun jka-compr-partial-uncompress (prog message args infile beg len)
   "Call program PROG with ARGS args taking input from INFILE.
 Fourth and fifth args, BEG and LEN, specify which part of the output
 to keep: LEN chars starting BEG chars from the beginning."
   (let ((start (point))
 	(prefix beg))
-    (if (and jka-compr-use-shell jka-compr-dd-program)
+    (if (and jka-compr-use-shell jka-compr-dd-program
+	     (not (jka-compr-builtin-gzip-p prog args)))
 	;; Put the uncompression output through dd
 	;; to discard the part we don't want.
 	(let ((skip (/ beg jka-compr-dd-blocksize))
@@ -200,42 +239,44 @@ to keep: LEN chars starting BEG chars from the beginning."
 
 
 (defun jka-compr-call-process (prog message infile output temp args)
-  ;; call-process barfs if default-directory is inaccessible.
-  (let ((default-directory
-	  (if (and default-directory
-		   (not (file-remote-p default-directory))
-		   (file-accessible-directory-p default-directory))
-	      default-directory
-	    (file-name-directory infile))))
-    (if jka-compr-use-shell
-	(let ((err-file (jka-compr-make-temp-name))
-	      (coding-system-for-read (or coding-system-for-read 'undecided))
-	      (coding-system-for-write 'no-conversion))
-	  (unwind-protect
-	      (or (memq
-		   (call-process jka-compr-shell infile
-				 (if (stringp output) nil output)
-				 nil
-				 "-c"
-				 (format "%s %s 2> %s %s"
-					 prog
-					 (mapconcat 'identity args " ")
-					 err-file
-					 (if (stringp output)
-					     (concat "> " output)
-					   "")))
-		   jka-compr-acceptable-retval-list)
-		  (jka-compr-error prog args infile message err-file))
-	    (delete-file err-file)))
-      (or (eq 0
-	      (apply 'call-process
-		     prog infile (if (stringp output) temp output)
-		     nil args))
-	  (jka-compr-error prog args infile message))
-      (and (stringp output)
-	   (with-current-buffer temp
-	     (write-region (point-min) (point-max) output)
-	     (erase-buffer))))))
+  (if (jka-compr-builtin-gzip-p prog args)
+      (jka-compr-builtin-gzip message infile output args)
+    ;; call-process barfs if default-directory is inaccessible.
+    (let ((default-directory
+	    (if (and default-directory
+		     (not (file-remote-p default-directory))
+		     (file-accessible-directory-p default-directory))
+		default-directory
+	      (file-name-directory infile))))
+      (if jka-compr-use-shell
+	  (let ((err-file (jka-compr-make-temp-name))
+		(coding-system-for-read (or coding-system-for-read 'undecided))
+		(coding-system-for-write 'no-conversion))
+	    (unwind-protect
+		(or (memq
+		     (call-process jka-compr-shell infile
+				   (if (stringp output) nil output)
+				   nil
+				   "-c"
+				   (format "%s %s 2> %s %s"
+					   prog
+					   (mapconcat 'identity args " ")
+					   err-file
+					   (if (stringp output)
+					       (concat "> " output)
+					     "")))
+		     jka-compr-acceptable-retval-list)
+		    (jka-compr-error prog args infile message err-file))
+	      (delete-file err-file)))
+	(or (eq 0
+		(apply 'call-process
+		       prog infile (if (stringp output) temp output)
+		       nil args))
+	    (jka-compr-error prog args infile message))
+	(and (stringp output)
+	     (with-current-buffer temp
+	       (write-region (point-min) (point-max) output)
+	       (erase-buffer)))))))
 
 
 ;; Support for temp files.  Much of this was inspired if not lifted
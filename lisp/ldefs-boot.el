@@ -26476,26 +26476,6 @@ Major mode for editing Python files.
 
 ;;;***
 
-;;;### (autoloads nil "qp" "mail/qp.el" (0 0 0 0))
-;;; Generated autoloads from mail/qp.el
-
-(autoload 'quoted-printable-decode-region "qp" "\
-Decode quoted-printable in the region between FROM and TO, per RFC 2045.
-If CODING-SYSTEM is non-nil, decode bytes into characters with that
-coding-system.
-
-Interactively, you can supply the CODING-SYSTEM argument
-with \\[universal-coding-system-argument].
-
-The CODING-SYSTEM argument is a historical hangover and is deprecated.
-QP encodes raw bytes and should be decoded into raw bytes.  Decoding
-them into characters should be done separately.
-
-\(fn FROM TO &optional CODING-SYSTEM)" t nil)
-
-(if (fboundp 'register-definition-prefixes) (register-definition-prefixes "qp" '("quoted-printable-")))
-
-;;;***
 
 ;;;### (autoloads nil "quail" "international/quail.el" (0 0 0 0))
 ;;; Generated autoloads from international/quail.el
@@ -118,18 +118,15 @@ update the dependent variables."
 (defcustom ls-lisp-use-string-collate
   (cond ((memq ls-lisp-emulation '(MacOS UNIX)) nil)
 	(t t))		; GNU/Linux or MS-Windows emulate GNU ls
-  "Non-nil causes ls-lisp to sort files in locale-dependent collation order.
+  "Non-nil causes ls-lisp to sort files in Unicode collation order.
 
 A value of nil means use ordinary string comparison (see `compare-strings')
 for sorting files.  A non-nil value uses `string-collate-lessp' instead,
 which more closely emulates what GNU `ls' does.
 
-On GNU/Linux systems, if the locale's codeset specifies UTF-8, as
-in \"en_US.UTF-8\", the collation order follows the Unicode
-Collation Algorithm (UCA), which places together file names that
-differ only in punctuation characters.  On MS-Windows, customize
-the option `ls-lisp-UCA-like-collation' to a non-nil value to get
-similar behavior."
+The collation order follows the Unicode Collation Algorithm (UCA),
+which places together file names that differ only in punctuation
+characters, and is the same on all systems."
   :version "25.1"
   :set-after '(ls-lisp-emulation)
   :type 'boolean
@@ -138,20 +135,9 @@ similar behavior."
 (defcustom ls-lisp-UCA-like-collation t
   "Non-nil means force ls-lisp use a collation order compatible with UCA.
 
-UCA is the Unicode Collation Algorithm.  GNU/Linux systems automatically
-follow it in their string-collation routines if the locale specifies
-UTF-8 as its codeset.  On MS-Windows, customize this option to a non-nil
-value to get similar behavior.
-
-When this option is non-nil, and `ls-lisp-use-string-collate' is also
-non-nil, the collation order produced on MS-Windows will ignore
-punctuation and symbol characters, which will, for example, place
-`.foo' near `foo'.  See the documentation of `string-collate-lessp'
-and `w32-collate-ignore-punctuation' for more details.
-
-This option is ignored on platforms other than MS-Windows; to
-control the collation ordering of the file names on those other
-systems, set your locale instead."
+UCA is the Unicode Collation Algorithm.  `string-collate-lessp'
+always follows it, so this option no longer has any effect; it is
+kept for compatibility with older customizations."
   :version "25.1"
   :type 'boolean
   :group 'ls-lisp)
@@ -572,16 +558,12 @@ Responds to the window width as ls should but may not!"
 Case is significant if `ls-lisp-ignore-case' is nil.
 Uses `string-collate-lessp' if `ls-lisp-use-string-collate' is non-nil,
 `compare-strings' otherwise.
-On GNU/Linux systems, if the locale specifies UTF-8 as the codeset,
-the sorting order will place together file names that differ only
-by punctuation characters, like `.emacs' and `emacs'.  To have a
-similar behavior on MS-Windows, customize `ls-lisp-UCA-like-collation'
-to a non-nil value."
-  (let ((w32-collate-ignore-punctuation ls-lisp-UCA-like-collation))
-    (if ls-lisp-use-string-collate
-	(string-collate-lessp s1 s2 nil ls-lisp-ignore-case)
-      (let ((u (compare-strings s1 0 nil s2 0 nil ls-lisp-ignore-case)))
-	(and (numberp u) (< u 0))))))
+The collation order places together file names that differ only by
+punctuation characters, like `.emacs' and `emacs'."
+  (if ls-lisp-use-string-collate
+      (string-collate-lessp s1 s2 nil ls-lisp-ignore-case)
+    (let ((u (compare-strings s1 0 nil s2 0 nil ls-lisp-ignore-case)))
+      (and (numberp u) (< u 0)))))
 
 (defun ls-lisp-version-lessp (s1 s2)
   "Return t if versioned string S1 should sort before versioned string S2.
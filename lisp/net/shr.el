@@ -1109,11 +1109,12 @@ Return a string with image data."
     ;; and remove anything that looks like a blocked bit.
     (when (and shr-blocked-images
                (eq content-type 'image/svg+xml))
-      (setq data
-            ;; Note that libxml2 doesn't parse everything perfectly,
-            ;; so glitches may occur during this transformation.
-	    (shr-dom-to-xml
-	     (libxml-parse-xml-region (point) (point-max)))))
+      ;; Data which isn't well-formed XML can't be displayed as an
+      ;; SVG image anyway, so it is left as it is.
+      (ignore-errors
+        (setq data
+              (shr-dom-to-xml
+               (libxml-parse-xml-region (point) (point-max))))))
     (list data content-type)))
 
 (defun shr-image-displayer (content-function)
@@ -337,14 +337,7 @@ The string is based on `url-privacy-level' and `url-user-agent'."
                  (concat
                   "From: " url-personal-mail-address "\r\n"))
              ;; Encodings we understand
-             (if (or url-mime-encoding-string
-		     ;; MS-Windows loads zlib dynamically, so recheck
-		     ;; in case they made it available since
-		     ;; initialization in url-vars.el.
-		     (and (eq 'system-type 'windows-nt)
-			  (fboundp 'zlib-available-p)
-			  (zlib-available-p)
-			  (setq url-mime-encoding-string "gzip")))
+             (if url-mime-encoding-string
                  (concat
                   "Accept-encoding: " url-mime-encoding-string "\r\n"))
              (if url-mime-charset-string
@@ -905,8 +898,6 @@ should be shown to the user."
     (goto-char (point-min))
     success))
 
-(declare-function zlib-decompress-region "decompress.c" (start end))
-
 (defun url-handle-content-transfer-encoding ()
   (let ((encoding (mail-fetch-field "content-encoding")))
     (when (and encoding
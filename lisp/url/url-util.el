@@ -409,16 +409,7 @@ The allowed characters are specified by ALLOWED-CHARS.  If this
 argument is nil, the list `url-unreserved-chars' determines the
 allowed characters.  Otherwise, ALLOWED-CHARS should be a vector
 whose Nth element is non-nil if character N is allowed."
-  (unless allowed-chars
-    (setq allowed-chars (url--allowed-chars url-unreserved-chars)))
-  (mapconcat (lambda (byte)
-	       (if (aref allowed-chars byte)
-		   (char-to-string byte)
-		 (aref url-encoding-table byte)))
-	     (if (multibyte-string-p string)
-		 (encode-coding-string string 'utf-8)
-	       string)
-	     ""))
+  (url-percent-encode string allowed-chars))
 
 (defconst url-host-allowed-chars
   ;; Allow % to avoid re-encoding %-encoded sequences.
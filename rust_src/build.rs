@@ -1,10 +1,11 @@
+use std::collections::HashSet;
 use std::env;
 use std::ffi::OsStr;
 use std::fs;
 use std::fs::File;
 use std::io;
 use std::io::{BufRead, BufReader, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Eq, PartialEq)]
 enum ParseState {
@@ -230,8 +231,799 @@ fn generate_c_exports() -> Result<(), io::Error> {
     Ok(())
 }
 
+/// Characters excluded from composition although they have a
+/// canonical decomposition into a starter and another character.
+/// These are the script-specific and post composition version entries
+/// of CompositionExclusions.txt; singletons and non-starter
+/// decompositions are excluded by looking at UnicodeData.txt.
+static COMPOSITION_EXCLUSIONS: &[u32] = &[
+    0x0958, 0x0959, 0x095A, 0x095B, 0x095C, 0x095D, 0x095E, 0x095F, 0x09DC, 0x09DD, 0x09DF,
+    0x0A33, 0x0A36, 0x0A59, 0x0A5A, 0x0A5B, 0x0A5E, 0x0B5C, 0x0B5D, 0x0F43, 0x0F4D, 0x0F52,
+    0x0F57, 0x0F5C, 0x0F69, 0x0F76, 0x0F78, 0x0F93, 0x0F9D, 0x0FA2, 0x0FA7, 0x0FAC, 0x0FB9,
+    0x2ADC, 0xFB1D, 0xFB1F, 0xFB2A, 0xFB2B, 0xFB2C, 0xFB2D, 0xFB2E, 0xFB2F, 0xFB30, 0xFB31,
+    0xFB32, 0xFB33, 0xFB34, 0xFB35, 0xFB36, 0xFB38, 0xFB39, 0xFB3A, 0xFB3B, 0xFB3C, 0xFB3E,
+    0xFB40, 0xFB41, 0xFB43, 0xFB44, 0xFB46, 0xFB47, 0xFB48, 0xFB49, 0xFB4A, 0xFB4B, 0xFB4C,
+    0xFB4D, 0xFB4E, 0x1D15E, 0x1D15F, 0x1D160, 0x1D161, 0x1D162, 0x1D163, 0x1D164, 0x1D1BB,
+    0x1D1BC, 0x1D1BD, 0x1D1BE, 0x1D1BF, 0x1D1C0,
+];
+
+/// The fields of UnicodeData.txt we are interested in.
+struct UnicodeDataEntry {
+    code: u32,
+    /// Whether this is the first character of a range, whose last
+    /// character is the next entry.
+    range_start: bool,
+    general_category: String,
+    combining_class: u8,
+    bidi_class: String,
+    decomposition: Vec<u32>,
+    compatibility: bool,
+    uppercase: Option<u32>,
+    lowercase: Option<u32>,
+    titlecase: Option<u32>,
+}
+
+fn unidata_file(name: &str) -> PathBuf {
+    [&env_var("CARGO_MANIFEST_DIR"), "..", "admin", "unidata", name]
+        .iter()
+        .collect()
+}
+
+fn parse_code(s: &str) -> u32 {
+    u32::from_str_radix(s.trim(), 16)
+        .unwrap_or_else(|_| panic!(format!("Invalid code point: {:?}", s)))
+}
+
+fn parse_optional_code(s: &str) -> Option<u32> {
+    if s.trim().is_empty() {
+        None
+    } else {
+        Some(parse_code(s))
+    }
+}
+
+fn read_unicode_data(path: &Path) -> Result<Vec<UnicodeDataEntry>, io::Error> {
+    let mut entries = Vec::new();
+
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        let fields: Vec<&str> = line.split(';').collect();
+        if fields.len() < 15 {
+            continue;
+        }
+
+        let mut decomposition = fields[5];
+        let compatibility = decomposition.starts_with('<');
+        if compatibility {
+            decomposition = &decomposition[(decomposition.find('>').unwrap() + 1)..];
+        }
+
+        entries.push(UnicodeDataEntry {
+            code: parse_code(fields[0]),
+            range_start: fields[1].ends_with(", First>"),
+            general_category: fields[2].to_string(),
+            combining_class: fields[3].parse().unwrap_or(0),
+            bidi_class: fields[4].to_string(),
+            decomposition: decomposition.split_whitespace().map(parse_code).collect(),
+            compatibility,
+            uppercase: parse_optional_code(fields[12]),
+            lowercase: parse_optional_code(fields[13]),
+            titlecase: parse_optional_code(fields[14]),
+        });
+    }
+
+    Ok(entries)
+}
+
+fn write_normalization_tables(
+    out_file: &mut File,
+    entries: &[UnicodeDataEntry],
+) -> Result<(), io::Error> {
+    write!(
+        out_file,
+        "/// Canonical combining classes of the characters whose class is not 0.\n\
+         pub static COMBINING_CLASSES: &[(u32, u8)] = &[\n"
+    )?;
+    for e in entries.iter().filter(|e| e.combining_class != 0) {
+        write!(out_file, "    ({:#x}, {}),\n", e.code, e.combining_class)?;
+    }
+    write!(out_file, "];\n\n")?;
+
+    write!(
+        out_file,
+        "/// Decomposition mappings, and whether they are compatibility mappings.\n\
+         pub static DECOMPOSITIONS: &[(u32, bool, &[u32])] = &[\n"
+    )?;
+    for e in entries.iter().filter(|e| !e.decomposition.is_empty()) {
+        let mapping: Vec<String> = e.decomposition
+            .iter()
+            .map(|c| format!("{:#x}", c))
+            .collect();
+        write!(
+            out_file,
+            "    ({:#x}, {}, &[{}]),\n",
+            e.code,
+            e.compatibility,
+            mapping.join(", ")
+        )?;
+    }
+    write!(out_file, "];\n\n")?;
+
+    let non_starters: HashSet<u32> = entries
+        .iter()
+        .filter(|e| e.combining_class != 0)
+        .map(|e| e.code)
+        .collect();
+    let mut compositions: Vec<(u32, u32, u32)> = entries
+        .iter()
+        .filter(|e| {
+            !e.compatibility && e.decomposition.len() == 2 && e.combining_class == 0
+                && !non_starters.contains(&e.decomposition[0])
+                && !COMPOSITION_EXCLUSIONS.contains(&e.code)
+        })
+        .map(|e| (e.decomposition[0], e.decomposition[1], e.code))
+        .collect();
+    compositions.sort();
+
+    write!(
+        out_file,
+        "/// Primary composites, sorted by the pair of characters they\n\
+         /// canonically decompose to.\n\
+         pub static COMPOSITIONS: &[(u32, u32, u32)] = &[\n"
+    )?;
+    for &(first, second, composite) in &compositions {
+        write!(
+            out_file,
+            "    ({:#x}, {:#x}, {:#x}),\n",
+            first,
+            second,
+            composite
+        )?;
+    }
+    write!(out_file, "];\n\n")?;
+
+    Ok(())
+}
+
+/// Read a UCD file of lines of the form `XXXX..YYYY ; Value # comment'.
+fn read_property_ranges(path: &Path) -> Result<Vec<(u32, u32, String)>, io::Error> {
+    let mut ranges = Vec::new();
+
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        let data = line.split('#').next().unwrap().trim();
+        if data.is_empty() {
+            continue;
+        }
+
+        let fields: Vec<&str> = data.split(';').map(|f| f.trim()).collect();
+        let (start, end) = match fields[0].find("..") {
+            Some(pos) => (
+                parse_code(&fields[0][..pos]),
+                parse_code(&fields[0][(pos + 2)..]),
+            ),
+            None => (parse_code(fields[0]), parse_code(fields[0])),
+        };
+        ranges.push((start, end, fields[1].to_string()));
+    }
+
+    ranges.sort();
+    Ok(ranges)
+}
+
+/// Return the ranges in RANGES whose value is one of VALUES, merging
+/// adjacent ranges.
+fn select_ranges(ranges: &[(u32, u32, String)], values: &[&str]) -> Vec<(u32, u32)> {
+    let mut selected: Vec<(u32, u32)> = ranges
+        .iter()
+        .filter(|r| values.contains(&r.2.as_str()))
+        .map(|r| (r.0, r.1))
+        .collect();
+    merge_ranges(&mut selected);
+    selected
+}
+
+fn merge_ranges(ranges: &mut Vec<(u32, u32)>) {
+    ranges.sort();
+    let mut merged: Vec<(u32, u32)> = Vec::with_capacity(ranges.len());
+    for &(start, end) in ranges.iter() {
+        if let Some(last) = merged.last_mut() {
+            if start <= last.1 + 1 {
+                last.1 = last.1.max(end);
+                continue;
+            }
+        }
+        merged.push((start, end));
+    }
+    *ranges = merged;
+}
+
+fn write_ranges(
+    out_file: &mut File,
+    name: &str,
+    doc: &str,
+    ranges: &[(u32, u32)],
+) -> Result<(), io::Error> {
+    write!(
+        out_file,
+        "/// {}\npub static {}: &[(u32, u32)] = &[\n",
+        doc,
+        name
+    )?;
+    for &(start, end) in ranges {
+        write!(out_file, "    ({:#x}, {:#x}),\n", start, end)?;
+    }
+    write!(out_file, "];\n\n")
+}
+
+fn write_segmentation_tables(out_file: &mut File) -> Result<(), io::Error> {
+    let grapheme_break = read_property_ranges(&unidata_file("GraphemeBreakProperty.txt"))?;
+    write!(
+        out_file,
+        "/// Grapheme_Cluster_Break property values other than Other.\n\
+         pub static GRAPHEME_BREAK_RANGES: &[(u32, u32, GraphemeBreak)] = &[\n"
+    )?;
+    for &(start, end, ref value) in &grapheme_break {
+        write!(
+            out_file,
+            "    ({:#x}, {:#x}, GraphemeBreak::{}),\n",
+            start,
+            end,
+            value.replace("_", "")
+        )?;
+    }
+    write!(out_file, "];\n\n")?;
+
+    let emoji = read_property_ranges(&unidata_file("emoji-data.txt"))?;
+    write_ranges(
+        out_file,
+        "EXTENDED_PICTOGRAPHIC_RANGES",
+        "Characters with the Extended_Pictographic property.",
+        &select_ranges(&emoji, &["Extended_Pictographic"]),
+    )
+}
+
+fn write_case_tables(out_file: &mut File, entries: &[UnicodeDataEntry]) -> Result<(), io::Error> {
+    write!(
+        out_file,
+        "/// Simple case mappings, as (character, uppercase, lowercase,\n\
+         /// titlecase).\n\
+         pub static CASE_MAPPINGS: &[(u32, u32, u32, u32)] = &[\n"
+    )?;
+    for e in entries {
+        if e.uppercase.is_none() && e.lowercase.is_none() && e.titlecase.is_none() {
+            continue;
+        }
+        let upper = e.uppercase.unwrap_or(e.code);
+        write!(
+            out_file,
+            "    ({:#x}, {:#x}, {:#x}, {:#x}),\n",
+            e.code,
+            upper,
+            e.lowercase.unwrap_or(e.code),
+            e.titlecase.unwrap_or(upper)
+        )?;
+    }
+    write!(out_file, "];\n\n")?;
+
+    // Conditional mappings are language- or context-sensitive, and
+    // are left to the case tables and to the casing code itself.
+    write!(
+        out_file,
+        "/// Unconditional mappings from SpecialCasing.txt, as (character,\n\
+         /// lowercase, titlecase, uppercase), sorted by character.\n\
+         pub static SPECIAL_CASINGS: &[(u32, &[u32], &[u32], &[u32])] = &[\n"
+    )?;
+    let mut special_casings = Vec::new();
+    let path = unidata_file("SpecialCasing.txt");
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        let data = line.split('#').next().unwrap();
+        let fields: Vec<&str> = data.split(';').map(|f| f.trim()).collect();
+        if fields.len() != 5 || !fields[4].is_empty() {
+            continue;
+        }
+        let mappings: Vec<String> = fields[1..4]
+            .iter()
+            .map(|mapping| {
+                let chars: Vec<String> = mapping
+                    .split_whitespace()
+                    .map(|c| format!("{:#x}", parse_code(c)))
+                    .collect();
+                format!("&[{}]", chars.join(", "))
+            })
+            .collect();
+        special_casings.push((parse_code(fields[0]), mappings.join(", ")));
+    }
+    special_casings.sort();
+    for &(code, ref mappings) in &special_casings {
+        write!(out_file, "    ({:#x}, {}),\n", code, mappings)?;
+    }
+    write!(out_file, "];\n\n")?;
+
+    Ok(())
+}
+
+/// Write the tables used by the IDNA processing of Unicode Technical
+/// Standard #46, for nontransitional processing without the STD3 ASCII
+/// rules, as used by the URL standard.
+fn write_idna_tables(out_file: &mut File, entries: &[UnicodeDataEntry]) -> Result<(), io::Error> {
+    // Deviations are valid in nontransitional processing, and the
+    // STD3 statuses are those of the characters without the STD3
+    // rules.  Only the characters which aren't valid are listed.
+    let mut mappings: Vec<(u32, u32, &str, Vec<u32>)> = Vec::new();
+    for line in BufReader::new(File::open(unidata_file("IdnaMappingTable.txt"))?).lines() {
+        let line = line?;
+        let data = line.split('#').next().unwrap();
+        if data.trim().is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = data.split(';').map(|f| f.trim()).collect();
+        let (start, end) = match fields[0].find("..") {
+            Some(pos) => (
+                parse_code(&fields[0][..pos]),
+                parse_code(&fields[0][(pos + 2)..]),
+            ),
+            None => (parse_code(fields[0]), parse_code(fields[0])),
+        };
+        let status = match fields[1] {
+            "valid" | "deviation" | "disallowed_STD3_valid" => continue,
+            "mapped" | "disallowed_STD3_mapped" => "Mapped",
+            "ignored" => "Ignored",
+            "disallowed" => "Disallowed",
+            status => panic!(format!("Invalid IDNA status: {:?}", status)),
+        };
+        let mapping = match fields.get(2) {
+            Some(mapping) if status == "Mapped" => {
+                mapping.split_whitespace().map(parse_code).collect()
+            }
+            _ => Vec::new(),
+        };
+        if let Some(last) = mappings.last_mut() {
+            if last.1 + 1 == start && last.2 == status && status != "Mapped" {
+                last.1 = end;
+                continue;
+            }
+        }
+        mappings.push((start, end, status, mapping));
+    }
+    write!(
+        out_file,
+        "/// The IDNA status of the characters which aren't valid, and the\n\
+         /// mapping of the mapped ones.\n\
+         pub static IDNA_MAPPINGS: &[(u32, u32, IdnaStatus, &[u32])] = &[\n"
+    )?;
+    for &(start, end, status, ref mapping) in &mappings {
+        let mapping: Vec<String> = mapping.iter().map(|c| format!("{:#x}", c)).collect();
+        write!(
+            out_file,
+            "    ({:#x}, {:#x}, IdnaStatus::{}, &[{}]),\n",
+            start,
+            end,
+            status,
+            mapping.join(", ")
+        )?;
+    }
+    write!(out_file, "];\n\n")?;
+
+    let mut bidi_classes: Vec<(u32, u32, &str)> = Vec::new();
+    for e in entries.iter().filter(|e| e.bidi_class != "L") {
+        if let Some(last) = bidi_classes.last_mut() {
+            if last.1 + 1 == e.code && last.2 == e.bidi_class {
+                last.1 = e.code;
+                continue;
+            }
+        }
+        bidi_classes.push((e.code, e.code, e.bidi_class.as_str()));
+    }
+    write!(
+        out_file,
+        "/// Bidi_Class property values other than L.\n\
+         pub static BIDI_CLASS_RANGES: &[(u32, u32, BidiClass)] = &[\n"
+    )?;
+    for &(start, end, class) in &bidi_classes {
+        write!(
+            out_file,
+            "    ({:#x}, {:#x}, BidiClass::{}),\n",
+            start,
+            end,
+            class
+        )?;
+    }
+    write!(out_file, "];\n\n")?;
+
+    let mut marks: Vec<(u32, u32)> = entries
+        .iter()
+        .filter(|e| e.general_category.starts_with('M'))
+        .map(|e| (e.code, e.code))
+        .collect();
+    merge_ranges(&mut marks);
+    write_ranges(
+        out_file,
+        "MARK_RANGES",
+        "Characters whose General_Category is Mark.",
+        &marks,
+    )
+}
+
+fn write_general_category_ranges(
+    out_file: &mut File,
+    entries: &[UnicodeDataEntry],
+) -> Result<(), io::Error> {
+    let select = |category: &Fn(&str) -> bool| {
+        let mut ranges = Vec::new();
+        for (i, e) in entries.iter().enumerate() {
+            if category(&e.general_category) {
+                let end = if e.range_start { entries[i + 1].code } else { e.code };
+                ranges.push((e.code, end));
+            }
+        }
+        merge_ranges(&mut ranges);
+        ranges
+    };
+    write_ranges(
+        out_file,
+        "DECIMAL_DIGIT_RANGES",
+        "Characters whose General_Category is Nd.",
+        &select(&|category| category == "Nd"),
+    )?;
+    write_ranges(
+        out_file,
+        "LETTER_RANGES",
+        "Characters whose General_Category is a letter category.",
+        &select(&|category| category.starts_with('L')),
+    )
+}
+
+fn generate_unicode_tables() -> Result<(), io::Error> {
+    let out_path: PathBuf = [&env_var("OUT_DIR"), "unicode_tables.rs"].iter().collect();
+    let mut out_file = File::create(out_path)?;
+
+    let entries = read_unicode_data(&unidata_file("UnicodeData.txt"))?;
+
+    write_normalization_tables(&mut out_file, &entries)?;
+    write_segmentation_tables(&mut out_file)?;
+    write_case_tables(&mut out_file, &entries)?;
+    write_idna_tables(&mut out_file, &entries)?;
+    write_general_category_ranges(&mut out_file, &entries)?;
+
+    Ok(())
+}
+
+/// A line of allkeys.txt: a sequence of characters and its collation
+/// elements.
+type CollationEntry = (Vec<u32>, Vec<u32>);
+
+/// Parse a line of allkeys.txt, like `0061 ; [.1FA2.0020.0002]'.  Each
+/// collation element is packed in a u32, with the primary weight in the
+/// top 16 bits, the secondary weight in the next 9 bits, the tertiary
+/// weight in the next 5 bits, and the lowest bit set for variable
+/// collation elements.
+fn parse_collation_entry(line: &str) -> CollationEntry {
+    let fields: Vec<&str> = line.split(';').collect();
+    let chars = fields[0].split_whitespace().map(parse_code).collect();
+    let elements = fields[1]
+        .split('[')
+        .skip(1)
+        .map(|element| {
+            let element = element.trim().trim_right_matches(']');
+            let weights: Vec<u32> = element[1..].split('.').map(parse_code).collect();
+            assert!(weights[1] < 1 << 9 && weights[2] < 1 << 5);
+            weights[0] << 16 | weights[1] << 7 | weights[2] << 2 | element.starts_with('*') as u32
+        })
+        .collect();
+    (chars, elements)
+}
+
+fn write_collation_entries(
+    out_file: &mut File,
+    entries: &mut Vec<CollationEntry>,
+) -> Result<(), io::Error> {
+    entries.sort();
+    for &(ref chars, ref elements) in entries.iter() {
+        let chars: Vec<String> = chars.iter().map(|c| format!("{:#x}", c)).collect();
+        let elements: Vec<String> = elements.iter().map(|e| format!("{:#x}", e)).collect();
+        write!(
+            out_file,
+            "    (&[{}], &[{}]),\n",
+            chars.join(", "),
+            elements.join(", ")
+        )?;
+    }
+    Ok(())
+}
+
+/// Write the tables of the Unicode Collation Algorithm in collate.rs:
+/// the Default Unicode Collation Element Table of allkeys.txt, and the
+/// locale tailorings of CollationTailorings.txt.
+fn generate_collation_tables() -> Result<(), io::Error> {
+    let out_path: PathBuf = [&env_var("OUT_DIR"), "collation_tables.rs"].iter().collect();
+    let mut out_file = File::create(out_path)?;
+
+    let mut ducet = Vec::new();
+    let mut implicit_weights = Vec::new();
+    for line in BufReader::new(File::open(unidata_file("allkeys.txt"))?).lines() {
+        let line = line?;
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        } else if line.starts_with("@implicitweights ") {
+            // Like `@implicitweights 17000..18AFF; FB00'.
+            let fields: Vec<&str> = line["@implicitweights ".len()..].split(';').collect();
+            let range: Vec<u32> = fields[0].split("..").map(parse_code).collect();
+            implicit_weights.push((range[0], range[1], parse_code(fields[1])));
+        } else if !line.starts_with('@') {
+            ducet.push(parse_collation_entry(line));
+        }
+    }
+
+    // Numeric collation relies on the digits 0 to 9 having consecutive
+    // primary weights.
+    let zero_primary = {
+        let digit_primary = |digit: u32| {
+            let &(_, ref elements) = ducet
+                .iter()
+                .find(|&&(ref chars, _)| chars[..] == [0x30 + digit])
+                .expect("allkeys.txt has no entry for a digit");
+            elements[0] >> 16
+        };
+        let zero_primary = digit_primary(0);
+        assert!((1..10).all(|digit| digit_primary(digit) == zero_primary + digit));
+        zero_primary
+    };
+    write!(
+        out_file,
+        "/// The primary weights of the digits 0 to 9.\n\
+         pub const DIGIT_ZERO_PRIMARY: u32 = {:#x};\n\
+         pub const DIGIT_NINE_PRIMARY: u32 = {:#x};\n\n",
+        zero_primary,
+        zero_primary + 9
+    )?;
+
+    write!(
+        out_file,
+        "/// The ranges of characters whose implicit weights have a base of\n\
+         /// their own, and that base.\n\
+         pub static IMPLICIT_WEIGHTS: &[(u32, u32, u32)] = &[\n"
+    )?;
+    for &(start, end, base) in &implicit_weights {
+        write!(out_file, "    ({:#x}, {:#x}, {:#x}),\n", start, end, base)?;
+    }
+    write!(out_file, "];\n\n")?;
+
+    write!(
+        out_file,
+        "/// The Default Unicode Collation Element Table, sorted by character\n\
+         /// sequence.\n\
+         pub static DUCET: &[(&[u32], &[u32])] = &[\n"
+    )?;
+    write_collation_entries(&mut out_file, &mut ducet)?;
+    write!(out_file, "];\n\n")?;
+
+    let mut tailorings: Vec<(String, bool, Vec<CollationEntry>)> = Vec::new();
+    let path = unidata_file("CollationTailorings.txt");
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        if line.starts_with("@locale ") {
+            tailorings.push((line["@locale ".len()..].to_string(), false, Vec::new()));
+        } else if line == "@upper_before_lower" {
+            tailorings.last_mut().unwrap().1 = true;
+        } else {
+            tailorings
+                .last_mut()
+                .unwrap()
+                .2
+                .push(parse_collation_entry(&line));
+        }
+    }
+    tailorings.sort();
+
+    write!(
+        out_file,
+        "/// The locale tailorings, sorted by name, with whether uppercase\n\
+         /// letters sort first and the entries which replace those of DUCET.\n\
+         pub static TAILORINGS: &[(&str, bool, &[(&[u32], &[u32])])] = &[\n"
+    )?;
+    for &mut (ref name, upper_before_lower, ref mut entries) in &mut tailorings {
+        write!(out_file, "    ({:?}, {}, &[\n", name, upper_before_lower)?;
+        write_collation_entries(&mut out_file, entries)?;
+        write!(out_file, "    ]),\n")?;
+    }
+    write!(out_file, "];\n")
+}
+
+/// Write the named character references of HTML, from
+/// admin/html/entities.json, as a table sorted by name.
+fn generate_html_entities() -> Result<(), io::Error> {
+    let in_path: PathBuf = [
+        &env_var("CARGO_MANIFEST_DIR"),
+        "..",
+        "admin",
+        "html",
+        "entities.json",
+    ].iter()
+        .collect();
+    let mut entities = Vec::new();
+    for line in BufReader::new(File::open(in_path)?).lines() {
+        let line = line?;
+        // Each entity is on a line of its own, like
+        //   "&amp;": { "codepoints": [38], "characters": "\u0026" },
+        let name = match line.trim().split('"').nth(1) {
+            Some(name) if name.starts_with('&') => name[1..].to_string(),
+            _ => continue,
+        };
+        let start = line.find('[').unwrap() + 1;
+        let end = line.find(']').unwrap();
+        let codepoints: Vec<u32> = line[start..end]
+            .split(',')
+            .map(|code| code.trim().parse().unwrap())
+            .collect();
+        entities.push((name, codepoints));
+    }
+    entities.sort();
+
+    let out_path: PathBuf = [&env_var("OUT_DIR"), "html_entities.rs"].iter().collect();
+    let mut out_file = File::create(out_path)?;
+    let longest = entities.iter().map(|e| e.0.len()).max().unwrap_or(0);
+    write!(
+        out_file,
+        "/// The length of the longest name of a named character reference.\n\
+         pub const MAX_NAMED_REFERENCE_LENGTH: usize = {};\n\n\
+         /// The named character references, without their \"&\", and their\n\
+         /// replacement text, sorted by name.\n\
+         pub static NAMED_REFERENCES: &[(&str, &str)] = &[\n",
+        longest
+    )?;
+    for &(ref name, ref codepoints) in &entities {
+        let text: Vec<String> = codepoints
+            .iter()
+            .map(|c| format!("\\u{{{:x}}}", c))
+            .collect();
+        write!(out_file, "    ({:?}, \"{}\"),\n", name, text.concat())?;
+    }
+    write!(out_file, "];\n")
+}
+
+/// Multiply the little-endian big number N by the small factor F.
+fn big_mul_small(n: &mut Vec<u32>, f: u32) {
+    let mut carry = 0u64;
+    for limb in n.iter_mut() {
+        let product = u64::from(*limb) * u64::from(f) + carry;
+        *limb = product as u32;
+        carry = product >> 32;
+    }
+    if carry != 0 {
+        n.push(carry as u32);
+    }
+}
+
+fn big_bit_length(n: &[u32]) -> usize {
+    let top = n.len() - 1;
+    top * 32 + 32 - n[top].leading_zeros() as usize
+}
+
+fn big_bit(n: &[u32], bit: usize) -> u64 {
+    n.get(bit / 32).map_or(0, |limb| u64::from(limb >> (bit % 32) & 1))
+}
+
+/// Whether the big number A is at least B; both are normalized, with
+/// no high zero limbs.
+fn big_at_least(a: &[u32], b: &[u32]) -> bool {
+    if a.len() != b.len() {
+        return a.len() > b.len();
+    }
+    for (x, y) in a.iter().rev().zip(b.iter().rev()) {
+        if x != y {
+            return x > y;
+        }
+    }
+    true
+}
+
+fn big_sub(a: &mut Vec<u32>, b: &[u32]) {
+    let mut borrow = 0i64;
+    for i in 0..a.len() {
+        let difference = i64::from(a[i]) - i64::from(*b.get(i).unwrap_or(&0)) - borrow;
+        a[i] = difference as u32;
+        borrow = if difference < 0 { 1 } else { 0 };
+    }
+    while a.len() > 1 && a[a.len() - 1] == 0 {
+        a.pop();
+    }
+}
+
+fn big_shl1(n: &mut Vec<u32>) {
+    let mut carry = 0;
+    for limb in n.iter_mut() {
+        let next = *limb >> 31;
+        *limb = *limb << 1 | carry;
+        carry = next;
+    }
+    if carry != 0 {
+        n.push(carry);
+    }
+}
+
+/// The number of bits kept of the powers of 5 in the float tables.
+const FLOAT_POW5_BITS: usize = 125;
+/// The float tables cover 5^0 to 5^325 and 5^-0 to 5^-341, enough for
+/// the exponents of all doubles.
+const FLOAT_POW5_SPLIT_LENGTH: usize = 326;
+const FLOAT_POW5_INV_SPLIT_LENGTH: usize = 342;
+
+/// Write the tables of the shortest float formatter in
+/// floatconv/ryu.rs: the top 125 bits of 5^I, and 2^(L-1+125) / 5^I
+/// rounded up, where L is the bit length of 5^I.
+fn generate_float_tables() -> Result<(), io::Error> {
+    let out_path: PathBuf = [&env_var("OUT_DIR"), "float_tables.rs"].iter().collect();
+    let mut out_file = File::create(out_path)?;
+
+    write!(
+        out_file,
+        "pub static POW5_SPLIT: [[u64; 2]; {}] = [\n",
+        FLOAT_POW5_SPLIT_LENGTH
+    )?;
+    let mut power = vec![1u32];
+    for _ in 0..FLOAT_POW5_SPLIT_LENGTH {
+        let length = big_bit_length(&power);
+        let mut split = [0u64; 2];
+        for bit in 0..FLOAT_POW5_BITS {
+            if bit + length >= FLOAT_POW5_BITS {
+                split[bit / 64] |= big_bit(&power, bit + length - FLOAT_POW5_BITS) << (bit % 64);
+            }
+        }
+        write!(out_file, "    [{}, {}],\n", split[0], split[1])?;
+        big_mul_small(&mut power, 5);
+    }
+    write!(out_file, "];\n\n")?;
+
+    write!(
+        out_file,
+        "pub static POW5_INV_SPLIT: [[u64; 2]; {}] = [\n",
+        FLOAT_POW5_INV_SPLIT_LENGTH
+    )?;
+    write!(out_file, "    [1, {}],\n", 1u64 << (FLOAT_POW5_BITS - 64))?;
+    let mut power = vec![5u32];
+    for _ in 1..FLOAT_POW5_INV_SPLIT_LENGTH {
+        // Long division of 2^(L-1+125) by 5^I, whose quotient has 125
+        // bits since 2^(L-1) < 5^I < 2^L.
+        let mut remainder = vec![0u32; big_bit_length(&power) / 32 + 1];
+        let top = big_bit_length(&power) - 1;
+        remainder[top / 32] = 1 << (top % 32);
+        while remainder.len() > 1 && remainder[remainder.len() - 1] == 0 {
+            remainder.pop();
+        }
+        let mut quotient = [0u64; 2];
+        for _ in 0..FLOAT_POW5_BITS {
+            big_shl1(&mut remainder);
+            quotient[1] = quotient[1] << 1 | quotient[0] >> 63;
+            quotient[0] <<= 1;
+            if big_at_least(&remainder, &power) {
+                big_sub(&mut remainder, &power);
+                quotient[0] |= 1;
+            }
+        }
+        let (low, carry) = quotient[0].overflowing_add(1);
+        write!(out_file, "    [{}, {}],\n", low, quotient[1] + carry as u64)?;
+        big_mul_small(&mut power, 5);
+    }
+    write!(out_file, "];\n")
+}
+
 fn main() {
     if let Err(e) = generate_c_exports() {
         panic!(format!("Errors occurred:\n{}", e));
     }
+    if let Err(e) = generate_unicode_tables() {
+        panic!(format!("Errors occurred generating Unicode tables:\n{}", e));
+    }
+    if let Err(e) = generate_collation_tables() {
+        panic!(format!("Errors occurred generating collation tables:\n{}", e));
+    }
+    if let Err(e) = generate_html_entities() {
+        panic!(format!("Errors occurred generating HTML entities:\n{}", e));
+    }
+    if let Err(e) = generate_float_tables() {
+        panic!(format!("Errors occurred generating float tables:\n{}", e));
+    }
 }
@@ -608,6 +608,109 @@ pub struct Lisp_Cons {
     pub cdr: Lisp_Object,
 }
 
+/// Syntax codes, from `enum syntaxcode` in syntax.h.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyntaxCode {
+    Whitespace,
+    Punct,
+    Word,
+    Symbol,
+    Open,
+    Close,
+    Quote,
+    String,
+    Math,
+    Escape,
+    Charquote,
+    Comment,
+    Endcomment,
+    Inherit,
+    CommentFence,
+    StringFence,
+    Max,
+}
+
+/// Mask bits for the `check_mask` argument of `update_compositions()`.
+pub const CHECK_HEAD: c_int = 1;
+pub const CHECK_TAIL: c_int = 2;
+pub const CHECK_INSIDE: c_int = 4;
+pub const CHECK_ALL: c_int = CHECK_HEAD | CHECK_TAIL | CHECK_INSIDE;
+
+/// The leading members of `struct composition` in composite.h, up to
+/// the number of columns the composition occupies.
+#[repr(C)]
+pub struct composition {
+    pub glyph_len: c_int,
+    pub pixel_width: c_short,
+    pub ascent: c_short,
+    pub descent: c_short,
+    pub lbearing: c_short,
+    pub rbearing: c_short,
+    pub width: c_int,
+}
+
+/// Coding systems with a native coder, from `enum fast_coding_type`
+/// in coding.h.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FastCodingType {
+    None,
+    RawText,
+    Latin1,
+    Utf8,
+    Utf16,
+}
+
+/// From `enum utf_bom_type` in coding.h.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UtfBomType {
+    Detect,
+    Without,
+    With,
+}
+
+/// From `enum utf_16_endian_type` in coding.h.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Utf16Endian {
+    Big,
+    Little,
+}
+
+/// From `enum fast_coding_eol` in coding.h.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FastCodingEol {
+    Undecided,
+    Unix,
+    Dos,
+    Mac,
+}
+
+/// The parameters of a native coder, see `fast_coding_spec_for()`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct fast_coding_spec {
+    pub coding_type: FastCodingType,
+    pub bom: UtfBomType,
+    pub endian: Utf16Endian,
+    pub eol: FastCodingEol,
+}
+
+/// From `enum text_quoting_style` in lisp.h.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextQuotingStyle {
+    /// Use curved single quotes ‘like this’.
+    Curve,
+    /// Use grave accent and apostrophe `like this'.
+    Grave,
+    /// Use apostrophes 'like this'.
+    Straight,
+}
+
 /// Type of comparison for `internal_equal()`.
 #[repr(C)]
 pub enum EqualKind {
@@ -763,6 +866,27 @@ pub struct Lisp_Char_Table {
     pub extras: [Lisp_Object; 1],
 }
 
+#[repr(C)]
+pub struct Lisp_Sub_Char_Table {
+    /// HEADER.SIZE is the vector's size field, which also holds the
+    /// pseudovector type information.  It holds the size, too.
+    pub header: Lisp_Vectorlike_Header,
+
+    /// Depth of this sub char-table.  It should be 1, 2, or 3.  A sub
+    /// char-table of depth 1 contains 16 elements, and each element
+    /// covers 4096 (128*32) characters.  A sub char-table of depth 2
+    /// contains 32 elements, and each element covers 128 characters.  A
+    /// sub char-table of depth 3 contains 128 elements, and each element
+    /// is for one character.
+    pub depth: c_int,
+
+    /// Minimum character covered by the sub char-table.
+    pub min_char: c_int,
+
+    // actually any number of items
+    pub contents: [Lisp_Object; 1],
+}
+
 #[repr(C)]
 pub struct Lisp_Process {
     pub header: Lisp_Vectorlike_Header,
@@ -987,9 +1111,11 @@ extern "C" {
     pub static mut globals: emacs_globals;
     pub static mut current_global_map: Lisp_Object;
     pub static current_thread: *mut thread_state;
+    pub static composition_table: *mut *mut composition;
 
     pub static lispsym: Lisp_Symbol;
     pub static Vbuffer_alist: Lisp_Object;
+    pub static buffer_defaults: Lisp_Buffer;
     pub static Vprocess_alist: Lisp_Object;
     pub static Vminibuffer_list: Lisp_Object;
     pub static Vfeatures: Lisp_Object;
@@ -1001,9 +1127,17 @@ extern "C" {
     pub static selected_frame: Lisp_Object;
 
     pub fn Faref(array: Lisp_Object, idx: Lisp_Object) -> Lisp_Object;
+    pub fn Frecord(nargs: ptrdiff_t, args: *mut Lisp_Object) -> Lisp_Object;
     pub fn Fcons(car: Lisp_Object, cdr: Lisp_Object) -> Lisp_Object;
     pub fn Fsignal(error_symbol: Lisp_Object, data: Lisp_Object) -> !;
     pub fn Fcopy_sequence(seq: Lisp_Object) -> Lisp_Object;
+    pub fn Fall_completions(
+        string: Lisp_Object,
+        collection: Lisp_Object,
+        predicate: Lisp_Object,
+        hide_spaces: Lisp_Object,
+    ) -> Lisp_Object;
+    pub fn Fsubstring(string: Lisp_Object, from: Lisp_Object, to: Lisp_Object) -> Lisp_Object;
     pub fn Ffind_operation_coding_system(nargs: ptrdiff_t, args: *mut Lisp_Object) -> Lisp_Object;
     pub fn Flocal_variable_p(variable: Lisp_Object, buffer: Lisp_Object) -> Lisp_Object;
     pub fn Flookup_key(
@@ -1012,9 +1146,59 @@ extern "C" {
         accept_default: Lisp_Object,
     ) -> Lisp_Object;
     pub fn Ffuncall(nargs: ptrdiff_t, args: *mut Lisp_Object) -> Lisp_Object;
+    pub fn Fexpand_file_name(name: Lisp_Object, default_directory: Lisp_Object) -> Lisp_Object;
+    pub fn Ffind_file_name_handler(filename: Lisp_Object, operation: Lisp_Object) -> Lisp_Object;
+    pub fn encode_file_name(fname: Lisp_Object) -> Lisp_Object;
+    pub fn report_file_errno(string: *const c_char, name: Lisp_Object, errorno: c_int) -> !;
+    pub fn maybe_quit();
     pub fn Fpurecopy(string: Lisp_Object) -> Lisp_Object;
     pub fn Fmapcar(function: Lisp_Object, sequence: Lisp_Object) -> Lisp_Object;
     pub fn Fset(symbol: Lisp_Object, newval: Lisp_Object) -> Lisp_Object;
+    pub fn Fmake_vector(length: Lisp_Object, init: Lisp_Object) -> Lisp_Object;
+    pub fn Fmake_bool_vector(length: Lisp_Object, init: Lisp_Object) -> Lisp_Object;
+    pub fn Fmake_hash_table(nargs: ptrdiff_t, args: *mut Lisp_Object) -> Lisp_Object;
+    pub fn Fmake_record(
+        record_type: Lisp_Object,
+        slots: Lisp_Object,
+        init: Lisp_Object,
+    ) -> Lisp_Object;
+    pub fn Fmake_symbol(name: Lisp_Object) -> Lisp_Object;
+    pub fn Faset(array: Lisp_Object, idx: Lisp_Object, newelt: Lisp_Object) -> Lisp_Object;
+    pub fn Ftext_properties_at(position: Lisp_Object, object: Lisp_Object) -> Lisp_Object;
+    pub fn Fnext_property_change(
+        position: Lisp_Object,
+        object: Lisp_Object,
+        limit: Lisp_Object,
+    ) -> Lisp_Object;
+    pub fn Fset_text_properties(
+        start: Lisp_Object,
+        end: Lisp_Object,
+        properties: Lisp_Object,
+        object: Lisp_Object,
+    ) -> Lisp_Object;
+    pub fn inhibit_garbage_collection() -> ptrdiff_t;
+    pub fn Fmake_char_table(purpose: Lisp_Object, init: Lisp_Object) -> Lisp_Object;
+    pub fn Fset_char_table_extra_slot(
+        char_table: Lisp_Object,
+        n: Lisp_Object,
+        value: Lisp_Object,
+    ) -> Lisp_Object;
+    pub fn copy_char_table(table: Lisp_Object) -> Lisp_Object;
+    pub fn char_table_ref(table: Lisp_Object, c: c_int) -> Lisp_Object;
+    pub fn char_table_ref_and_range(
+        table: Lisp_Object,
+        c: c_int,
+        from: *mut c_int,
+        to: *mut c_int,
+    ) -> Lisp_Object;
+    pub fn char_table_set(table: Lisp_Object, c: c_int, val: Lisp_Object);
+    pub fn char_table_set_range(table: Lisp_Object, from: c_int, to: c_int, val: Lisp_Object);
+    pub fn map_char_table(
+        c_function: extern "C" fn(Lisp_Object, Lisp_Object, Lisp_Object),
+        function: Lisp_Object,
+        table: Lisp_Object,
+        arg: Lisp_Object,
+    );
     pub fn make_float(float_value: c_double) -> Lisp_Object;
     pub fn make_string(s: *const c_char, length: ptrdiff_t) -> Lisp_Object;
     pub fn make_lisp_ptr(ptr: *const c_void, ty: Lisp_Type) -> Lisp_Object;
@@ -1041,6 +1225,7 @@ extern "C" {
         nocopy: bool,
         norecord: bool,
     ) -> Lisp_Object;
+    pub fn fast_encoding_spec(coding_system: Lisp_Object, spec: *mut fast_coding_spec) -> bool;
     pub fn validate_subarray(
         array: Lisp_Object,
         from: Lisp_Object,
@@ -1053,12 +1238,24 @@ extern "C" {
         -> libc::ptrdiff_t;
 
     pub fn record_unwind_current_buffer();
+    pub fn c_specpdl_index() -> ptrdiff_t;
+    pub fn record_unwind_protect(function: extern "C" fn(Lisp_Object), arg: Lisp_Object);
+    pub fn unbind_to(count: ptrdiff_t, value: Lisp_Object) -> Lisp_Object;
     pub fn set_buffer_internal(buffer: *mut Lisp_Buffer);
     pub fn make_buffer_string(
         start: libc::ptrdiff_t,
         end: libc::ptrdiff_t,
         props: bool,
     ) -> Lisp_Object;
+    pub fn replace_range(
+        from: libc::ptrdiff_t,
+        to: libc::ptrdiff_t,
+        new: Lisp_Object,
+        prepare: bool,
+        inherit: bool,
+        markers: bool,
+        adjust_match_data: bool,
+    );
 
     pub fn check_obarray(obarray: Lisp_Object) -> Lisp_Object;
     pub fn check_vobarray() -> Lisp_Object;
@@ -1089,21 +1286,6 @@ extern "C" {
 
     pub fn emacs_abort() -> !;
 
-    pub fn base64_encode_1(
-        from: *const c_char,
-        to: *mut c_char,
-        length: ptrdiff_t,
-        line_break: bool,
-        multibyte: bool,
-    ) -> ptrdiff_t;
-    pub fn base64_decode_1(
-        from: *const c_char,
-        to: *mut c_char,
-        length: ptrdiff_t,
-        multibyte: bool,
-        nchars_return: *mut ptrdiff_t,
-    ) -> ptrdiff_t;
-
     pub fn allocate_pseudovector(
         vecsize: c_int,
         offset1: c_int,
@@ -1138,6 +1320,45 @@ extern "C" {
     pub fn set_point(charpos: ptrdiff_t);
     pub fn Fline_beginning_position(n: Lisp_Object) -> Lisp_Object;
     pub fn buf_charpos_to_bytepos(buffer: *const Lisp_Buffer, charpos: ptrdiff_t) -> ptrdiff_t;
+    pub fn buffer_display_table() -> *mut Lisp_Char_Table;
+    pub fn temp_set_point_both(buffer: *mut Lisp_Buffer, charpos: ptrdiff_t, bytepos: ptrdiff_t);
+    pub fn modify_text(start: ptrdiff_t, end: ptrdiff_t);
+    pub fn signal_after_change(charpos: ptrdiff_t, lendel: ptrdiff_t, lenins: ptrdiff_t);
+    pub fn update_compositions(from: ptrdiff_t, to: ptrdiff_t, check_mask: c_int);
+    pub fn replace_range_2(
+        from: ptrdiff_t,
+        from_byte: ptrdiff_t,
+        to: ptrdiff_t,
+        to_byte: ptrdiff_t,
+        ins: *const c_char,
+        inschars: ptrdiff_t,
+        insbytes: ptrdiff_t,
+        markers: bool,
+    );
+    pub fn move_gap_both(charpos: ptrdiff_t, bytepos: ptrdiff_t);
+    pub fn insert_1_both(
+        string: *const c_char,
+        nchars: ptrdiff_t,
+        nbytes: ptrdiff_t,
+        inherit: bool,
+        prepare: bool,
+        before_markers: bool,
+    );
+    pub fn del_range_both(
+        from: ptrdiff_t,
+        from_byte: ptrdiff_t,
+        to: ptrdiff_t,
+        to_byte: ptrdiff_t,
+        prepare: bool,
+    );
+    pub fn record_insert(beg: ptrdiff_t, length: ptrdiff_t);
+    pub fn record_delete(beg: ptrdiff_t, string: Lisp_Object, record_markers: bool);
+    pub fn Fset_case_table(table: Lisp_Object) -> Lisp_Object;
+
+    pub fn SYNTAX(c: c_int) -> SyntaxCode;
+    pub fn syntax_prefix_flag_p(c: c_int) -> bool;
+    pub fn SETUP_BUFFER_SYNTAX_TABLE();
+    pub fn scan_words(from: ptrdiff_t, count: EmacsInt) -> ptrdiff_t;
 
     pub fn Finsert_char(
         character: Lisp_Object,
@@ -1159,6 +1380,8 @@ extern "C" {
     pub fn current_timespec() -> timespec;
     pub fn timespec_sub(a: timespec, b: timespec) -> timespec;
     pub fn timespec_add(a: timespec, b: timespec) -> timespec;
+    pub fn emacs_getenv_TZ() -> *mut c_char;
+    pub fn synchronize_system_time_locale();
 
     pub fn current_column() -> Lisp_Object;
 
@@ -1198,6 +1421,41 @@ extern "C" {
         inhibit_capture_property: Lisp_Object,
     ) -> Lisp_Object;
     pub fn Fline_end_position(n: Lisp_Object) -> Lisp_Object;
+    pub fn Fprin1_to_string(object: Lisp_Object, noescape: Lisp_Object) -> Lisp_Object;
+    pub fn Fchar_to_string(character: Lisp_Object) -> Lisp_Object;
+    pub fn text_quoting_style() -> TextQuotingStyle;
+    pub fn text_property_list(
+        object: Lisp_Object,
+        start: Lisp_Object,
+        end: Lisp_Object,
+        prop: Lisp_Object,
+    ) -> Lisp_Object;
+    pub fn add_text_properties_from_list(
+        object: Lisp_Object,
+        list: Lisp_Object,
+        delta: Lisp_Object,
+    );
+    pub fn extend_property_ranges(
+        list: Lisp_Object,
+        old_end: Lisp_Object,
+        new_end: Lisp_Object,
+    ) -> Lisp_Object;
+    pub fn make_composition_value_copy(list: Lisp_Object);
+    pub fn find_composition(
+        pos: ptrdiff_t,
+        limit: ptrdiff_t,
+        start: *mut ptrdiff_t,
+        end: *mut ptrdiff_t,
+        prop: *mut Lisp_Object,
+        object: Lisp_Object,
+    ) -> bool;
+    pub fn get_composition_id(
+        charpos: ptrdiff_t,
+        bytepos: ptrdiff_t,
+        nchars: ptrdiff_t,
+        prop: Lisp_Object,
+        string: Lisp_Object,
+    ) -> ptrdiff_t;
 }
 
 /// Contains C definitions from the font.h header.
@@ -0,0 +1,827 @@
+//! Reading the members of zip and tar archives.
+//!
+//! This covers zip archives whose members are stored or deflated, and
+//! tar archives, possibly gzipped.  Other archives, and members which
+//! are compressed or encrypted some other way, are reported as
+//! unsupported, so that callers can fall back on external programs.
+//!
+//! Only the headers and the members asked for are read from the file,
+//! except that a gzipped tar archive has to be decompressed in memory.
+
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::os::unix::ffi::OsStrExt;
+use std::str;
+use std::usize;
+
+use libc::{c_char, ptrdiff_t};
+
+use remacs_macros::lisp_fn;
+use remacs_sys::{encode_file_name, insert_1_both, report_file_errno, EmacsInt,
+                 Fexpand_file_name, Ffind_file_name_handler};
+
+use codec::{bytes_to_multibyte, make_unibyte};
+use lisp::{intern, LispObject};
+use lisp::defsubr;
+use multibyte::{multibyte_char_at, raw_byte_from_codepoint, LispStringRef, MAX_5_BYTE_CHAR};
+use threads::ThreadState;
+use timefns::{lisp_zone, local_seconds};
+use tzfile::Zone;
+use zlib::checksum::crc32;
+use zlib::inflate::{gunzip, inflate};
+
+const S_IFIFO: u32 = 0o010_000;
+const S_IFCHR: u32 = 0o020_000;
+const S_IFDIR: u32 = 0o040_000;
+const S_IFBLK: u32 = 0o060_000;
+const S_IFREG: u32 = 0o100_000;
+const S_IFLNK: u32 = 0o120_000;
+
+/// Where the contents of a member are in the archive.
+#[derive(Clone, Copy)]
+enum Contents {
+    /// The bytes from START to END, as they are.
+    Tar { start: u64, end: u64 },
+    /// The member of a zip archive with the local header at OFFSET.
+    Zip {
+        offset: u64,
+        method: u16,
+        encrypted: bool,
+        compressed_size: u64,
+        crc: u32,
+    },
+}
+
+/// A member of an archive.
+struct Entry {
+    name: Vec<u8>,
+    size: u64,
+    /// The modification time in seconds since the epoch.
+    mtime: i64,
+    /// The file type and permission bits, as in `st_mode'.
+    mode: u32,
+    /// The position of the header of the member: its central directory
+    /// record in a zip archive, or its header block in a tar archive.
+    header: u64,
+    contents: Contents,
+}
+
+/// Why the members of an archive can't be read.
+#[derive(Debug, PartialEq)]
+enum Error {
+    /// The archive, or the member, is in a format which isn't known.
+    Unsupported,
+    /// The archive is corrupt.
+    Invalid,
+    /// Reading the archive failed with this errno.
+    Io(i32),
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        // The archive is shorter than its headers say.
+        if err.kind() == io::ErrorKind::UnexpectedEof {
+            Error::Invalid
+        } else {
+            Error::Io(err.raw_os_error().unwrap_or(0))
+        }
+    }
+}
+
+fn read_le(bytes: &[u8]) -> u64 {
+    bytes.iter().rev().fold(0, |value, &byte| value << 8 | u64::from(byte))
+}
+
+/// Return the bytes of DATA from START on which are LEN long, or an
+/// error if DATA is too short.
+fn field(data: &[u8], start: usize, len: usize) -> Result<&[u8], Error> {
+    data.get(start..start.checked_add(len).ok_or(Error::Invalid)?)
+        .ok_or(Error::Invalid)
+}
+
+fn le_field(data: &[u8], start: usize, len: usize) -> Result<u64, Error> {
+    Ok(read_le(field(data, start, len)?))
+}
+
+/// Something an archive can be read from.
+trait Source: Read + Seek {}
+
+impl<T: Read + Seek> Source for T {}
+
+/// An archive, which is read only where its headers and the members
+/// asked for are.
+struct Reader {
+    source: Box<Source>,
+    len: u64,
+}
+
+impl Reader {
+    fn new(mut source: Box<Source>) -> Result<Reader, Error> {
+        let len = source.seek(SeekFrom::End(0))?;
+        Ok(Reader { source, len })
+    }
+
+    /// Return the LEN bytes from POS on, or an error if the archive is
+    /// too short.
+    fn read_at(&mut self, pos: u64, len: u64) -> Result<Vec<u8>, Error> {
+        if pos.checked_add(len).map_or(true, |end| end > self.len) {
+            return Err(Error::Invalid);
+        }
+        if len > usize::MAX as u64 {
+            return Err(Error::Unsupported);
+        }
+        self.source.seek(SeekFrom::Start(pos))?;
+        let mut data = vec![0; len as usize];
+        self.source.read_exact(&mut data)?;
+        Ok(data)
+    }
+}
+
+/// Return the time in seconds since the epoch of the MS-DOS DATE and
+/// TIME, which are local time in ZONE.
+fn dos_time(date: u16, time: u16, zone: &Zone) -> i64 {
+    let fields = [
+        i64::from(time & 0x1F) * 2,
+        i64::from(time >> 5 & 0x3F),
+        i64::from(time >> 11),
+        i64::from(date & 0x1F),
+        i64::from(date >> 5 & 0xF),
+        i64::from(date >> 9) + 1980,
+    ];
+    // The fields are too small to overflow.
+    local_seconds(fields)
+        .and_then(|local| zone.from_local(local))
+        .unwrap()
+}
+
+const ZIP_LOCAL_HEADER: &[u8] = b"PK\x03\x04";
+const ZIP_CENTRAL_HEADER: &[u8] = b"PK\x01\x02";
+const ZIP_END: &[u8] = b"PK\x05\x06";
+const ZIP64_END: &[u8] = b"PK\x06\x06";
+const ZIP64_END_LOCATOR: &[u8] = b"PK\x06\x07";
+
+/// Return the position and the bytes of the end of central directory
+/// record of the zip archive READER, which is followed by a comment of
+/// up to 64K.
+fn find_zip_end(reader: &mut Reader) -> Result<Option<(u64, Vec<u8>)>, Error> {
+    let start = reader.len.saturating_sub(22 + 0xFFFF);
+    let len = reader.len - start;
+    let tail = reader.read_at(start, len)?;
+    let end = (0..tail.len().saturating_sub(21))
+        .rev()
+        .find(|&pos| tail[pos..].starts_with(ZIP_END));
+    Ok(end.map(|pos| (start + pos as u64, tail[pos..pos + 22].to_vec())))
+}
+
+/// Return the entries of the zip archive READER, whose end of central
+/// directory record is RECORD at END.  Times without a zone are taken
+/// as local time in ZONE.
+fn zip_entries(
+    reader: &mut Reader,
+    end: u64,
+    record: &[u8],
+    zone: &Zone,
+) -> Result<Vec<Entry>, Error> {
+    let mut count = le_field(record, 10, 2)?;
+    let mut size = le_field(record, 12, 4)?;
+    let mut offset = le_field(record, 16, 4)?;
+    if count == 0xFFFF || size == 0xFFFF_FFFF || offset == 0xFFFF_FFFF {
+        if end < 20 {
+            return Err(Error::Invalid);
+        }
+        let locator = reader.read_at(end - 20, 20)?;
+        if !locator.starts_with(ZIP64_END_LOCATOR) {
+            return Err(Error::Invalid);
+        }
+        let record64 = reader.read_at(le_field(&locator, 8, 8)?, 56)?;
+        if !record64.starts_with(ZIP64_END) {
+            return Err(Error::Invalid);
+        }
+        count = le_field(&record64, 32, 8)?;
+        size = le_field(&record64, 40, 8)?;
+        offset = le_field(&record64, 48, 8)?;
+    }
+
+    let directory = reader.read_at(offset, size)?;
+    let data = &directory[..];
+    let mut entries = Vec::new();
+    let mut pos = 0;
+    for _ in 0..count {
+        if field(data, pos, 46)?[..4] != *ZIP_CENTRAL_HEADER {
+            return Err(Error::Invalid);
+        }
+        let creator = data[pos + 5];
+        let flags = le_field(data, pos + 8, 2)?;
+        let method = le_field(data, pos + 10, 2)? as u16;
+        let time = le_field(data, pos + 12, 2)? as u16;
+        let date = le_field(data, pos + 14, 2)? as u16;
+        let crc = le_field(data, pos + 16, 4)? as u32;
+        let mut compressed_size = le_field(data, pos + 20, 4)?;
+        let mut size = le_field(data, pos + 24, 4)?;
+        let name_len = le_field(data, pos + 28, 2)? as usize;
+        let extra_len = le_field(data, pos + 30, 2)? as usize;
+        let comment_len = le_field(data, pos + 32, 2)? as usize;
+        let attributes = le_field(data, pos + 38, 4)? as u32;
+        let mut local_offset = le_field(data, pos + 42, 4)?;
+        let name = field(data, pos + 46, name_len)?.to_vec();
+        let mut extra = field(data, pos + 46 + name_len, extra_len)?;
+        let mut mtime = dos_time(date, time, zone);
+        while extra.len() >= 4 {
+            let id = read_le(&extra[..2]);
+            let len = read_le(&extra[2..4]) as usize;
+            let value = field(extra, 4, len)?;
+            match id {
+                // The 64-bit sizes and offset of a Zip64 archive, for
+                // those which don't fit.
+                0x0001 => {
+                    let mut values = value.chunks(8).map(read_le);
+                    for slot in &mut [&mut size, &mut compressed_size, &mut local_offset] {
+                        if **slot == 0xFFFF_FFFF {
+                            **slot = values.next().ok_or(Error::Invalid)?;
+                        }
+                    }
+                }
+                // An extended timestamp, whose modification time is
+                // in Unix time.
+                0x5455 if len >= 5 && value[0] & 1 != 0 => {
+                    mtime = i64::from(read_le(&value[1..5]) as u32 as i32);
+                }
+                _ => {}
+            }
+            extra = &extra[4 + len..];
+        }
+
+        let directory = name.last() == Some(&b'/');
+        let unix_mode = attributes >> 16;
+        let mode = if creator == 3 && unix_mode != 0 {
+            if unix_mode & 0o170_000 == 0 {
+                unix_mode | if directory { S_IFDIR } else { S_IFREG }
+            } else {
+                unix_mode
+            }
+        } else {
+            // The MS-DOS read-only attribute.
+            let write = if attributes & 1 == 0 { 0o222 } else { 0 };
+            if directory {
+                S_IFDIR | 0o555 | write
+            } else {
+                S_IFREG | 0o444 | write
+            }
+        };
+
+        entries.push(Entry {
+            name,
+            size,
+            mtime,
+            mode,
+            header: offset + pos as u64,
+            contents: Contents::Zip {
+                offset: local_offset,
+                method,
+                encrypted: flags & 1 != 0,
+                compressed_size,
+                crc,
+            },
+        });
+        pos += 46 + name_len + extra_len + comment_len;
+    }
+    Ok(entries)
+}
+
+/// Return the contents of the zip member of READER whose local header
+/// is at OFFSET.
+fn zip_contents(
+    reader: &mut Reader,
+    offset: u64,
+    method: u16,
+    compressed_size: u64,
+    crc: u32,
+) -> Result<Vec<u8>, Error> {
+    let header = reader.read_at(offset, 30)?;
+    if header[..4] != *ZIP_LOCAL_HEADER {
+        return Err(Error::Invalid);
+    }
+    let name_len = le_field(&header, 26, 2)?;
+    let extra_len = le_field(&header, 28, 2)?;
+    let start = offset.saturating_add(30 + name_len + extra_len);
+    let compressed = reader.read_at(start, compressed_size)?;
+    let contents = match method {
+        0 => compressed,
+        8 => {
+            let mut out = Vec::with_capacity(compressed.len().saturating_mul(3));
+            inflate(&compressed, 0, &mut out).ok_or(Error::Invalid)?;
+            out
+        }
+        _ => return Err(Error::Unsupported),
+    };
+    if crc32(0, &contents) != crc {
+        return Err(Error::Invalid);
+    }
+    Ok(contents)
+}
+
+/// Return the string at the start of FIELD, which ends at the first
+/// null byte if any.
+fn tar_string(field: &[u8]) -> &[u8] {
+    match field.iter().position(|&byte| byte == 0) {
+        Some(end) => &field[..end],
+        None => field,
+    }
+}
+
+/// Return the number in the tar header FIELD, which is in octal or
+/// in the base-256 form of GNU tar.
+fn tar_number(field: &[u8]) -> Result<u64, Error> {
+    if field[0] & 0x80 != 0 {
+        let mut value = u64::from(field[0] & 0x3F);
+        for &byte in &field[1..] {
+            value = value.checked_mul(256).ok_or(Error::Invalid)? | u64::from(byte);
+        }
+        return Ok(value);
+    }
+    let digits = tar_string(field);
+    let digits = str::from_utf8(digits).map_err(|_| Error::Invalid)?;
+    let digits = digits.trim_matches(' ');
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(digits, 8).map_err(|_| Error::Invalid)
+}
+
+/// Return whether the checksum of the tar HEADER block is right.
+fn tar_checksum_ok(header: &[u8]) -> bool {
+    let sum: u64 = header
+        .iter()
+        .enumerate()
+        .map(|(idx, &byte)| if idx >= 148 && idx < 156 { 32 } else { u64::from(byte) })
+        .sum();
+    tar_number(&header[148..156]) == Ok(sum)
+}
+
+/// Return whether READER starts with a tar header.
+fn is_tar(reader: &mut Reader) -> Result<bool, Error> {
+    if reader.len < 512 {
+        return Ok(false);
+    }
+    let header = reader.read_at(0, 512)?;
+    Ok(header.iter().any(|&byte| byte != 0) && tar_checksum_ok(&header))
+}
+
+/// Return the entries of the tar archive READER.
+fn tar_entries(reader: &mut Reader) -> Result<Vec<Entry>, Error> {
+    let mut entries = Vec::new();
+    let mut pos = 0;
+    // The name, size and modification time given by GNU or pax
+    // extended headers for the next entry.
+    let mut long_name: Option<Vec<u8>> = None;
+    let mut pax_size: Option<u64> = None;
+    let mut pax_mtime: Option<i64> = None;
+
+    while pos + 512 <= reader.len {
+        let header = reader.read_at(pos, 512)?;
+        if header.iter().all(|&byte| byte == 0) {
+            break;
+        }
+        if !tar_checksum_ok(&header) {
+            return Err(Error::Invalid);
+        }
+        let type_flag = header[156];
+        let mut size = pax_size.take().map_or_else(|| tar_number(&header[124..136]), Ok)?;
+        let header_pos = pos;
+        let start = pos + 512;
+        let end = start.checked_add(size).ok_or(Error::Invalid)?;
+        if end > reader.len {
+            return Err(Error::Invalid);
+        }
+        pos = start + (size + 511) / 512 * 512;
+
+        match type_flag {
+            b'L' => {
+                let contents = reader.read_at(start, size)?;
+                long_name = Some(tar_string(&contents).to_vec());
+                continue;
+            }
+            b'x' => {
+                let contents = reader.read_at(start, size)?;
+                for (key, value) in pax_records(&contents)? {
+                    match key {
+                        b"path" => long_name = Some(value.to_vec()),
+                        b"size" => pax_size = Some(pax_number(value)? as u64),
+                        b"mtime" => pax_mtime = Some(pax_number(value)?),
+                        _ => {}
+                    }
+                }
+                continue;
+            }
+            // Global pax headers, and GNU long link names.
+            b'g' | b'K' => continue,
+            _ => {}
+        }
+
+        let name = match long_name.take() {
+            Some(name) => name,
+            None => {
+                let name = tar_string(&header[..100]);
+                let prefix = tar_string(&header[345..500]);
+                if &header[257..263] == b"ustar\0" && !prefix.is_empty() {
+                    [prefix, b"/", name].concat()
+                } else {
+                    name.to_vec()
+                }
+            }
+        };
+        let file_type = match type_flag {
+            b'2' => S_IFLNK,
+            b'3' => S_IFCHR,
+            b'4' => S_IFBLK,
+            b'5' => S_IFDIR,
+            b'6' => S_IFIFO,
+            _ if name.last() == Some(&b'/') => S_IFDIR,
+            _ => S_IFREG,
+        };
+        if file_type != S_IFREG {
+            size = 0;
+        }
+        let mtime = match pax_mtime.take() {
+            Some(mtime) => mtime,
+            None => tar_number(&header[136..148])? as i64,
+        };
+        entries.push(Entry {
+            name,
+            size,
+            mtime,
+            mode: file_type | tar_number(&header[100..108])? as u32 & 0o7777,
+            header: header_pos,
+            contents: Contents::Tar {
+                start,
+                end: start + size,
+            },
+        });
+    }
+    Ok(entries)
+}
+
+/// Return the key and value of each record of the pax extended header
+/// DATA, which are of the form "LENGTH KEY=VALUE\n".
+fn pax_records(mut data: &[u8]) -> Result<Vec<(&[u8], &[u8])>, Error> {
+    let mut records = Vec::new();
+    while !data.is_empty() {
+        let space = data.iter().position(|&byte| byte == b' ').ok_or(Error::Invalid)?;
+        let len = str::from_utf8(&data[..space])
+            .ok()
+            .and_then(|len| len.parse::<usize>().ok())
+            .ok_or(Error::Invalid)?;
+        if len <= space + 1 || len > data.len() || data[len - 1] != b'\n' {
+            return Err(Error::Invalid);
+        }
+        let record = &data[space + 1..len - 1];
+        let equals = record.iter().position(|&byte| byte == b'=').ok_or(Error::Invalid)?;
+        records.push((&record[..equals], &record[equals + 1..]));
+        data = &data[len..];
+    }
+    Ok(records)
+}
+
+/// Return the integral part of the decimal number VALUE of a pax
+/// record.
+fn pax_number(value: &[u8]) -> Result<i64, Error> {
+    let value = str::from_utf8(value).map_err(|_| Error::Invalid)?;
+    let integral = value.split('.').next().unwrap();
+    integral.parse().map_err(|_| Error::Invalid)
+}
+
+/// An archive and the entries of its members.
+struct Archive {
+    reader: Reader,
+    entries: Vec<Entry>,
+}
+
+impl Archive {
+    /// Read the entries of the archive SOURCE.  Zip times without a
+    /// zone are taken as local time in ZONE.
+    fn open(source: Box<Source>, zone: &Zone) -> Result<Archive, Error> {
+        let mut reader = Reader::new(source)?;
+        if reader.len >= 2 && reader.read_at(0, 2)?.starts_with(&[0x1F, 0x8B]) {
+            // A gzip stream can't be read from the middle, so the tar
+            // archive in it is decompressed into memory.
+            let len = reader.len;
+            let tar = gunzip(&reader.read_at(0, len)?).ok_or(Error::Invalid)?;
+            reader = Reader::new(Box::new(io::Cursor::new(tar)))?;
+            if !is_tar(&mut reader)? {
+                return Err(Error::Unsupported);
+            }
+        }
+        let entries = if is_tar(&mut reader)? {
+            tar_entries(&mut reader)?
+        } else if let Some((end, record)) = find_zip_end(&mut reader)? {
+            zip_entries(&mut reader, end, &record, zone)?
+        } else {
+            return Err(Error::Unsupported);
+        };
+        Ok(Archive { reader, entries })
+    }
+
+    /// Return the contents of the last member called NAME, or None if
+    /// there is no such member.
+    fn contents(&mut self, name: &[u8]) -> Option<Result<Vec<u8>, Error>> {
+        let entry = self.entries.iter().rev().find(|entry| entry.name == name)?;
+        let reader = &mut self.reader;
+        Some(match entry.contents {
+            Contents::Tar { start, end } => reader.read_at(start, end - start),
+            Contents::Zip { encrypted: true, .. } => Err(Error::Unsupported),
+            Contents::Zip {
+                offset,
+                method,
+                compressed_size,
+                crc,
+                ..
+            } => zip_contents(reader, offset, method, compressed_size, crc),
+        })
+    }
+}
+
+/// Open the archive FILE, or return the result of the handler of
+/// OPERATION for it if it has one, called with NAME after FILE unless
+/// it is None.
+fn read_archive(
+    file: LispObject,
+    operation: &str,
+    name: Option<LispObject>,
+) -> Result<Result<Archive, Error>, LispObject> {
+    file.as_string_or_error();
+    let file = LispObject::from(unsafe {
+        Fexpand_file_name(file.to_raw(), LispObject::constant_nil().to_raw())
+    });
+
+    // If the file name has special constructs in it, call the
+    // corresponding file handler.
+    let operation = intern(operation);
+    let handler =
+        LispObject::from(unsafe { Ffind_file_name_handler(file.to_raw(), operation.to_raw()) });
+    if handler.is_not_nil() {
+        return Err(match name {
+            Some(name) => call!(handler, operation, file, name),
+            None => call!(handler, operation, file),
+        });
+    }
+
+    let encoded = LispObject::from(unsafe { encode_file_name(file.to_raw()) });
+    let path = OsStr::from_bytes(encoded.as_string_or_error().as_slice());
+    let opened = match File::open(path) {
+        Ok(opened) => opened,
+        Err(err) => archive_error(file, Error::from(err)),
+    };
+    let zone = lisp_zone(LispObject::constant_nil());
+    Ok(Archive::open(Box::new(opened), &zone))
+}
+
+/// Signal an error for the archive FILE, which is corrupt if ERR is
+/// `Error::Invalid', or couldn't be read.
+fn archive_error(file: LispObject, err: Error) -> ! {
+    if let Error::Io(errno) = err {
+        unsafe {
+            report_file_errno(
+                b"Reading archive file\0".as_ptr() as *const c_char,
+                file.to_raw(),
+                errno,
+            )
+        }
+    }
+    let file = file.as_string_or_error();
+    error!("Invalid archive: {}", String::from_utf8_lossy(file.as_slice()))
+}
+
+/// Return a list of the members of the archive FILE.
+/// FILE can be a zip archive, or a tar archive which may be gzipped.
+/// Each element of the list has the form (NAME SIZE MTIME MODE OFFSET),
+/// where NAME is the name of the member as a unibyte string, without
+/// any decoding, SIZE is its size in bytes, MTIME is its last
+/// modification time in the format of `current-time', and MODE is its
+/// file type and permissions, as an integer like the mode of
+/// `file-modes' with the file type bits of the `st_mode' of stat(2).
+/// OFFSET is the position in FILE of the header of the member: its
+/// central directory record in a zip archive, or its header block in a
+/// tar archive, counted after decompressing a gzipped one.
+///
+/// Return nil if FILE is in some other format.  Signal an error if FILE
+/// is corrupt.
+#[lisp_fn]
+pub fn archive_list_entries(file: LispObject) -> LispObject {
+    let archive = match read_archive(file, "archive-list-entries", None) {
+        Ok(Ok(archive)) => archive,
+        Ok(Err(Error::Unsupported)) => return LispObject::constant_nil(),
+        Ok(Err(err)) => archive_error(file, err),
+        Err(handled) => return handled,
+    };
+    archive
+        .entries
+        .iter()
+        .rev()
+        .fold(LispObject::constant_nil(), |list, entry| {
+            let item = list!(
+                make_unibyte(&entry.name),
+                LispObject::from_natnum(entry.size as EmacsInt),
+                list!(
+                    LispObject::from_fixnum(entry.mtime >> 16),
+                    LispObject::from_fixnum(entry.mtime & 0xFFFF)
+                ),
+                LispObject::from_natnum(EmacsInt::from(entry.mode)),
+                LispObject::from_natnum(entry.header as EmacsInt)
+            );
+            LispObject::cons(item, list)
+        })
+}
+
+/// Return the bytes of NAME, with a multibyte NAME encoded in UTF-8.
+fn name_bytes(name: LispStringRef) -> Vec<u8> {
+    let text = name.as_slice();
+    if !name.is_multibyte() {
+        return text.to_vec();
+    }
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut idx = 0;
+    while idx < text.len() {
+        let (cp, len) = multibyte_char_at(&text[idx..]);
+        if cp > MAX_5_BYTE_CHAR {
+            bytes.push(raw_byte_from_codepoint(cp));
+        } else {
+            bytes.extend_from_slice(&text[idx..idx + len]);
+        }
+        idx += len;
+    }
+    bytes
+}
+
+/// Insert the contents of the member NAME of the archive FILE at point.
+/// FILE is an archive of a kind which `archive-list-entries' can read,
+/// and NAME is the name of the member as it is in FILE.  If NAME is a
+/// multibyte string, it is encoded in UTF-8 first.  The contents are
+/// inserted without any decoding, as by `insert-file-contents-literally',
+/// and point is left after them.
+///
+/// Return t if the member was inserted, or nil if FILE, or the way the
+/// member is compressed, isn't supported.  Signal an error if there is
+/// no member NAME, or if FILE is corrupt.
+#[lisp_fn]
+pub fn archive_extract_entry(file: LispObject, name: LispObject) -> LispObject {
+    let name_string = name.as_string_or_error();
+    let mut archive = match read_archive(file, "archive-extract-entry", Some(name)) {
+        Ok(Ok(archive)) => archive,
+        Ok(Err(Error::Unsupported)) => return LispObject::constant_nil(),
+        Ok(Err(err)) => archive_error(file, err),
+        Err(handled) => return handled,
+    };
+    let contents = match archive.contents(&name_bytes(name_string)) {
+        Some(Ok(contents)) => contents,
+        Some(Err(Error::Unsupported)) => return LispObject::constant_nil(),
+        Some(Err(err)) => archive_error(file, err),
+        None => error!(
+            "No member {} in archive",
+            String::from_utf8_lossy(name_string.as_slice())
+        ),
+    };
+
+    let buffer = ThreadState::current_buffer();
+    let nchars = contents.len() as ptrdiff_t;
+    let text = if LispObject::from(buffer.enable_multibyte_characters).is_not_nil() {
+        bytes_to_multibyte(&contents)
+    } else {
+        contents
+    };
+    unsafe {
+        insert_1_both(
+            text.as_ptr() as *const c_char,
+            nchars,
+            text.len() as ptrdiff_t,
+            false,
+            true,
+            false,
+        )
+    };
+    LispObject::constant_t()
+}
+
+#[cfg(test)]
+use zlib::inflate::from_hex;
+
+#[cfg(test)]
+fn open(data: Vec<u8>) -> Result<Archive, Error> {
+    Archive::open(Box::new(io::Cursor::new(data)), &Zone::utc())
+}
+
+/// A zip archive with "a.txt", stored and made on Unix, and
+/// "b/c.txt", deflated and read-only on MS-DOS.
+#[cfg(test)]
+const ZIP: &str = "504b0304140000000000c563b15020303a36060000000600000005000000612e747874\
+                   68656c6c6f0a504b0304140000000800c563b1504ae46635080000001300000007000000\
+                   622f632e7478744b4c4a4e44455c00504b01021403140000000000c563b15020303a3606\
+                   00000006000000050000000000000000000000a48100000000612e747874504b01021400\
+                   140000000800c563b1504ae46635080000001300000007000000000000000000010000\
+                   0029000000622f632e747874504b0506000000000200020068000000560000000000";
+
+#[cfg(test)]
+fn tar_header(name: &str, type_flag: u8, size: usize) -> Vec<u8> {
+    let mut header = vec![0; 512];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    header[100..108].copy_from_slice(b"0000644\0");
+    header[124..136].copy_from_slice(format!("{:011o}\0", size).as_bytes());
+    header[136..148].copy_from_slice(b"13651234567\0");
+    header[156] = type_flag;
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[148..156].copy_from_slice(b"        ");
+    let sum: u32 = header.iter().map(|&byte| u32::from(byte)).sum();
+    header[148..156].copy_from_slice(format!("{:06o}\0 ", sum).as_bytes());
+    header
+}
+
+#[cfg(test)]
+fn tar_member(name: &str, type_flag: u8, contents: &[u8]) -> Vec<u8> {
+    let mut member = tar_header(name, type_flag, contents.len());
+    member.extend_from_slice(contents);
+    let padded = (member.len() + 511) / 512 * 512;
+    member.resize(padded, 0);
+    member
+}
+
+#[cfg(test)]
+fn names(archive: &Archive) -> Vec<&[u8]> {
+    archive.entries.iter().map(|entry| &entry.name[..]).collect()
+}
+
+#[test]
+fn test_zip() {
+    let mut archive = open(from_hex(ZIP)).unwrap();
+    assert_eq!(names(&archive), vec![&b"a.txt"[..], b"b/c.txt"]);
+    assert_eq!(archive.entries[0].size, 6);
+    assert_eq!(archive.entries[0].mode, 0o100_644);
+    assert_eq!(archive.entries[0].mtime, 1_589_718_610);
+    assert_eq!(archive.entries[1].size, 19);
+    assert_eq!(archive.entries[1].mode, 0o100_444);
+    // The central directory starts at 0x56.
+    assert_eq!(archive.entries[0].header, 0x56);
+    assert_eq!(archive.entries[1].header, 0x56 + 46 + 5);
+    assert_eq!(archive.contents(b"a.txt"), Some(Ok(b"hello\n".to_vec())));
+    assert_eq!(
+        archive.contents(b"b/c.txt"),
+        Some(Ok(b"abcabcabcabcabcabc\n".to_vec()))
+    );
+    assert_eq!(archive.contents(b"c.txt"), None);
+}
+
+#[test]
+fn test_zip_corrupt() {
+    let mut data = from_hex(ZIP);
+    // The first byte of the contents of "a.txt".
+    data[35] = b'j';
+    let mut archive = open(data).unwrap();
+    assert_eq!(archive.contents(b"a.txt"), Some(Err(Error::Invalid)));
+
+    let data = from_hex(ZIP);
+    let truncated = data[..60].iter().chain(&data[150..]).cloned().collect();
+    assert!(open(truncated).is_err());
+}
+
+#[test]
+fn test_tar() {
+    let long_name = "dir/".repeat(30) + "file";
+    let mut data = tar_member("a.txt", b'0', b"hello\n");
+    data.extend(tar_member("d/", b'5', b""));
+    data.extend(tar_member("././@LongLink", b'L', long_name.as_bytes()));
+    data.extend(tar_member("dir/dir/", b'0', b"long"));
+    data.extend(tar_member("pax", b'x', b"13 size=1000\n20 path=renamed.txt\n"));
+    data.extend(tar_header("name.txt", b'0', 0));
+    data.extend(vec![b'x'; 1024]);
+    data.extend(vec![0; 1024]);
+
+    let mut archive = open(data).unwrap();
+    assert_eq!(
+        names(&archive),
+        vec![&b"a.txt"[..], b"d/", long_name.as_bytes(), b"renamed.txt"]
+    );
+    assert_eq!(archive.entries[0].mtime, 0o13_651_234_567);
+    assert_eq!(archive.entries[0].mode, 0o100_644);
+    assert_eq!(archive.entries[1].mode, 0o40_644);
+    assert_eq!(archive.entries[2].header, 2560);
+    assert_eq!(archive.entries[3].size, 1000);
+    assert_eq!(archive.contents(b"a.txt"), Some(Ok(b"hello\n".to_vec())));
+    assert_eq!(archive.contents(long_name.as_bytes()), Some(Ok(b"long".to_vec())));
+    assert_eq!(archive.contents(b"renamed.txt"), Some(Ok(vec![b'x'; 1000])));
+}
+
+#[test]
+fn test_tar_number() {
+    assert_eq!(tar_number(b"0000644 "), Ok(0o644));
+    assert_eq!(tar_number(b"  1234\0\0"), Ok(0o1234));
+    assert_eq!(tar_number(b"\0\0\0\0"), Ok(0));
+    assert_eq!(tar_number(&[0x80, 0, 0, 1, 0]), Ok(256));
+    assert_eq!(tar_number(b"0009"), Err(Error::Invalid));
+}
+
+#[test]
+fn test_unsupported() {
+    assert_eq!(open(b"plain text".to_vec()).err(), Some(Error::Unsupported));
+    assert_eq!(open(vec![0; 1024]).err(), Some(Error::Unsupported));
+}
+
+include!(concat!(env!("OUT_DIR"), "/archive_exports.rs"));
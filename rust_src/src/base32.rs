@@ -0,0 +1,326 @@
+//! Base32 de- and encoding functions.
+//!
+//! Both alphabets of RFC 4648 are supported: base32 proper, and
+//! base32hex, which preserves the sort order of the encoded data.
+
+use remacs_macros::lisp_fn;
+
+use base64::is_ignorable;
+use codec::{self, Decoder, Encoder, Output};
+use lisp::LispObject;
+use lisp::defsubr;
+
+const BASE32_CHARS: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+const BASE32HEX_CHARS: &[u8; 32] = b"0123456789ABCDEFGHIJKLMNOPQRSTUV";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Alphabet {
+    Base32,
+    Base32Hex,
+}
+
+impl Alphabet {
+    fn chars(self) -> &'static [u8; 32] {
+        match self {
+            Alphabet::Base32 => BASE32_CHARS,
+            Alphabet::Base32Hex => BASE32HEX_CHARS,
+        }
+    }
+
+    /// Return the 5-bit value of the character BYTE, if it belongs to
+    /// the alphabet.  Lower case letters are accepted unless STRICT.
+    fn value(self, byte: u8, strict: bool) -> Option<u64> {
+        let byte = if strict {
+            byte
+        } else {
+            byte.to_ascii_uppercase()
+        };
+        let value = match (self, byte) {
+            (Alphabet::Base32, b'A'...b'Z') => byte - b'A',
+            (Alphabet::Base32, b'2'...b'7') => byte - b'2' + 26,
+            (Alphabet::Base32Hex, b'0'...b'9') => byte - b'0',
+            (Alphabet::Base32Hex, b'A'...b'V') => byte - b'A' + 10,
+            _ => return None,
+        };
+        Some(u64::from(value))
+    }
+}
+
+/// Return the number of bytes encoded by a final group of NCHARS
+/// characters, if it is a valid number.
+fn group_bytes(nchars: usize) -> Option<usize> {
+    match nchars {
+        2 => Some(1),
+        4 => Some(2),
+        5 => Some(3),
+        7 => Some(4),
+        _ => None,
+    }
+}
+
+pub struct Base32Encoder {
+    chars: &'static [u8; 32],
+    pad: bool,
+    /// Input bytes not yet encoded, and their number.
+    pending: u64,
+    npending: usize,
+}
+
+impl Base32Encoder {
+    /// Make an encoder for ALPHABET, which pads the output with `='
+    /// to a multiple of eight characters if PAD.
+    pub fn new(alphabet: Alphabet, pad: bool) -> Base32Encoder {
+        Base32Encoder {
+            chars: alphabet.chars(),
+            pad,
+            pending: 0,
+            npending: 0,
+        }
+    }
+
+    /// Append the first NCHARS characters of the encoding of the 40
+    /// bits of GROUP to OUT.
+    fn emit(&self, group: u64, nchars: usize, out: &mut Vec<u8>) {
+        for idx in 0..nchars {
+            out.push(self.chars[((group >> (35 - 5 * idx)) & 0x1F) as usize]);
+        }
+    }
+}
+
+impl Encoder for Base32Encoder {
+    fn push(&mut self, byte: u8, out: &mut Vec<u8>) {
+        self.pending = (self.pending << 8) | u64::from(byte);
+        self.npending += 1;
+        if self.npending == 5 {
+            self.emit(self.pending, 8, out);
+            self.pending = 0;
+            self.npending = 0;
+        }
+    }
+
+    fn finish(self, out: &mut Vec<u8>) {
+        if self.npending > 0 {
+            let nchars = (self.npending * 8 + 4) / 5;
+            self.emit(self.pending << (8 * (5 - self.npending)), nchars, out);
+            if self.pad {
+                out.extend_from_slice(&b"======"[..8 - nchars]);
+            }
+        }
+    }
+}
+
+/// A base32 decoder.
+///
+/// Unless it is strict, the decoder accepts lower case letters, and
+/// skips whitespace and accepts missing padding.  A strict decoder
+/// rejects any character outside of the alphabet, missing padding, and
+/// encodings whose unused trailing bits aren't zero.
+pub struct Base32Decoder {
+    alphabet: Alphabet,
+    strict: bool,
+    /// The values of the characters of the current group, and their
+    /// number.
+    group: u64,
+    ngroup: usize,
+    /// The number of padding characters seen so far.
+    padding: usize,
+}
+
+impl Base32Decoder {
+    pub fn new(alphabet: Alphabet, strict: bool) -> Base32Decoder {
+        Base32Decoder {
+            alphabet,
+            strict,
+            group: 0,
+            ngroup: 0,
+            padding: 0,
+        }
+    }
+}
+
+impl Decoder for Base32Decoder {
+    fn push(&mut self, byte: u8, out: &mut Output) -> Option<()> {
+        if let Some(value) = self.alphabet.value(byte, self.strict) {
+            if self.padding > 0 {
+                return None;
+            }
+            self.group = (self.group << 5) | value;
+            self.ngroup += 1;
+            if self.ngroup == 8 {
+                for idx in 0..5 {
+                    out.push((self.group >> (32 - 8 * idx)) as u8);
+                }
+                self.group = 0;
+                self.ngroup = 0;
+            }
+        } else if byte == b'=' {
+            self.padding += 1;
+            if group_bytes(self.ngroup).is_none() || self.ngroup + self.padding > 8 {
+                return None;
+            }
+        } else if self.strict || !is_ignorable(byte) {
+            return None;
+        }
+        Some(())
+    }
+
+    fn finish(self, out: &mut Output) -> Option<()> {
+        let (group, ngroup) = (self.group, self.ngroup);
+        if ngroup == 0 {
+            return Some(());
+        }
+        let nbytes = group_bytes(ngroup)?;
+        let unused_bits = 5 * ngroup - 8 * nbytes;
+        if self.strict && (self.padding != 8 - ngroup || group & ((1 << unused_bits) - 1) != 0) {
+            return None;
+        }
+        let group = group << (5 * (8 - ngroup));
+        for idx in 0..nbytes {
+            out.push((group >> (32 - 8 * idx)) as u8);
+        }
+        Some(())
+    }
+}
+
+#[cfg(test)]
+fn encode_all(input: &[u8], alphabet: Alphabet, pad: bool) -> Vec<u8> {
+    codec::encode(Base32Encoder::new(alphabet, pad), &[input], false).unwrap()
+}
+
+#[cfg(test)]
+fn decode_all(input: &[u8], alphabet: Alphabet, strict: bool) -> Option<Vec<u8>> {
+    let decoder = Base32Decoder::new(alphabet, strict);
+    codec::decode(decoder, &[input], false).map(|output| output.bytes)
+}
+
+#[test]
+fn test_base32_rfc4648() {
+    let vectors: [(&[u8], &[u8], &[u8]); 7] = [
+        (b"", b"", b""),
+        (b"f", b"MY======", b"CO======"),
+        (b"fo", b"MZXQ====", b"CPNG===="),
+        (b"foo", b"MZXW6===", b"CPNMU==="),
+        (b"foob", b"MZXW6YQ=", b"CPNMUOG="),
+        (b"fooba", b"MZXW6YTB", b"CPNMUOJ1"),
+        (b"foobar", b"MZXW6YTBOI======", b"CPNMUOJ1E8======"),
+    ];
+    for &(input, base32, base32hex) in &vectors {
+        assert_eq!(encode_all(input, Alphabet::Base32, true), base32);
+        assert_eq!(encode_all(input, Alphabet::Base32Hex, true), base32hex);
+        assert_eq!(decode_all(base32, Alphabet::Base32, true).unwrap(), input);
+        assert_eq!(decode_all(base32hex, Alphabet::Base32Hex, true).unwrap(), input);
+    }
+}
+
+#[test]
+fn test_base32_decode_errors() {
+    let decode = |input: &[u8], strict| decode_all(input, Alphabet::Base32, strict);
+    assert_eq!(decode(b"my======", false).unwrap(), b"f");
+    assert_eq!(decode(b"my======", true), None);
+    assert_eq!(decode(b"MZXW 6YQ\n", false).unwrap(), b"foob");
+    assert_eq!(decode(b"MZXW6YQ", true), None);
+    // Non-zero trailing bits.
+    assert_eq!(decode(b"MZ======", false).unwrap(), b"f");
+    assert_eq!(decode(b"MZ======", true), None);
+    for &strict in &[false, true] {
+        assert_eq!(decode(b"M=======", strict), None);
+        assert_eq!(decode(b"MZX=====", strict), None);
+        assert_eq!(decode(b"MY=======", strict), None);
+        assert_eq!(decode(b"MY======MY======", strict), None);
+        assert_eq!(decode(b"MZXW1YQ=", strict), None);
+    }
+}
+
+#[test]
+fn test_base32_round_trip() {
+    for &alphabet in &[Alphabet::Base32, Alphabet::Base32Hex] {
+        for &pad in &[true, false] {
+            codec::check_round_trip(
+                || Base32Encoder::new(alphabet, pad),
+                || Base32Decoder::new(alphabet, false),
+            );
+        }
+        codec::check_round_trip(
+            || Base32Encoder::new(alphabet, true),
+            || Base32Decoder::new(alphabet, true),
+        );
+    }
+}
+
+fn decode_alphabet(base32hex: LispObject) -> Alphabet {
+    if base32hex.is_nil() {
+        Alphabet::Base32
+    } else {
+        Alphabet::Base32Hex
+    }
+}
+
+/// Base32-encode STRING and return the result.
+/// Optional second argument NO-PAD means do not add `=' padding.
+#[lisp_fn(min = "1")]
+fn base32_encode_string(string: LispObject, no_pad: LispObject) -> LispObject {
+    let encoder = Base32Encoder::new(Alphabet::Base32, no_pad.is_nil());
+    codec::encode_string(string, encoder, "base32")
+}
+
+/// Base32hex-encode STRING and return the result.
+/// This uses the extended hex alphabet of RFC 4648, `0'-`9' and
+/// `A'-`V', which preserves the sort order of the encoded data.
+/// Optional second argument NO-PAD means do not add `=' padding.
+#[lisp_fn(min = "1")]
+fn base32hex_encode_string(string: LispObject, no_pad: LispObject) -> LispObject {
+    let encoder = Base32Encoder::new(Alphabet::Base32Hex, no_pad.is_nil());
+    codec::encode_string(string, encoder, "base32")
+}
+
+/// Base32-decode STRING and return the result as a unibyte string.
+/// Lower case letters and whitespace in STRING are accepted, and so is
+/// missing `=' padding.
+/// Optional argument BASE32HEX means use the extended hex alphabet.
+/// Optional argument STRICT means signal an error for any character
+/// outside of the alphabet including whitespace and lower case letters,
+/// for missing padding, and for non-zero unused bits in the last group.
+#[lisp_fn(min = "1")]
+fn base32_decode_string(
+    string: LispObject,
+    base32hex: LispObject,
+    strict: LispObject,
+) -> LispObject {
+    let decoder = Base32Decoder::new(decode_alphabet(base32hex), strict.is_not_nil());
+    codec::decode_string(string, decoder, "base32")
+}
+
+/// Base32-encode the region between BEG and END.
+/// Return the length of the encoded text.
+/// Optional third argument NO-PAD means do not add `=' padding.
+#[lisp_fn(min = "2", intspec = "r")]
+fn base32_encode_region(beg: LispObject, end: LispObject, no_pad: LispObject) -> LispObject {
+    let encoder = Base32Encoder::new(Alphabet::Base32, no_pad.is_nil());
+    codec::encode_region(beg, end, encoder, "base32")
+}
+
+/// Base32hex-encode the region between BEG and END.
+/// Return the length of the encoded text.
+/// Optional third argument NO-PAD means do not add `=' padding.
+#[lisp_fn(min = "2", intspec = "r")]
+fn base32hex_encode_region(beg: LispObject, end: LispObject, no_pad: LispObject) -> LispObject {
+    let encoder = Base32Encoder::new(Alphabet::Base32Hex, no_pad.is_nil());
+    codec::encode_region(beg, end, encoder, "base32")
+}
+
+/// Base32-decode the region between BEG and END.
+/// Return the length of the decoded text.
+/// If the region can't be decoded, signal an error and don't modify the buffer.
+/// Optional arguments BASE32HEX and STRICT are as for `base32-decode-string'.
+#[lisp_fn(min = "2", intspec = "r")]
+fn base32_decode_region(
+    beg: LispObject,
+    end: LispObject,
+    base32hex: LispObject,
+    strict: LispObject,
+) -> LispObject {
+    let decoder = Base32Decoder::new(decode_alphabet(base32hex), strict.is_not_nil());
+    codec::decode_region(beg, end, decoder, "base32")
+}
+
+include!(concat!(env!("OUT_DIR"), "/base32_exports.rs"));
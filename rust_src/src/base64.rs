@@ -1,140 +1,297 @@
 //! Base64 de- and encoding functions.
-
-use libc::{c_char, c_uchar, ptrdiff_t};
-use std::ptr;
-use std::slice;
-
-use base64_crate;
+//!
+//! Both the standard alphabet of RFC 4648 (as used by MIME) and its
+//! URL and filename safe variant are supported.
 
 use remacs_macros::lisp_fn;
-use remacs_sys::make_unibyte_string;
 
+use codec::{self, Decoder, Encoder, Output};
 use lisp::LispObject;
 use lisp::defsubr;
-use multibyte::{multibyte_char_at, raw_byte_from_codepoint, MAX_5_BYTE_CHAR};
 use strings::MIME_LINE_LENGTH;
 
-#[no_mangle]
-pub extern "C" fn base64_encode_1(
-    from: *const c_char,
-    to: *mut c_char,
-    length: ptrdiff_t,
+const STANDARD_CHARS: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const URL_SAFE_CHARS: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Alphabet {
+    /// The standard alphabet, ending with `+' and `/'.
+    Standard,
+    /// The URL and filename safe alphabet, ending with `-' and `_'.
+    UrlSafe,
+}
+
+impl Alphabet {
+    fn chars(self) -> &'static [u8; 64] {
+        match self {
+            Alphabet::Standard => STANDARD_CHARS,
+            Alphabet::UrlSafe => URL_SAFE_CHARS,
+        }
+    }
+
+    /// Return the 6-bit value of the character BYTE, if it belongs to
+    /// the alphabet.
+    fn value(self, byte: u8) -> Option<u32> {
+        let value = match byte {
+            b'A'...b'Z' => byte - b'A',
+            b'a'...b'z' => byte - b'a' + 26,
+            b'0'...b'9' => byte - b'0' + 52,
+            b'+' if self == Alphabet::Standard => 62,
+            b'/' if self == Alphabet::Standard => 63,
+            b'-' if self == Alphabet::UrlSafe => 62,
+            b'_' if self == Alphabet::UrlSafe => 63,
+            _ => return None,
+        };
+        Some(u32::from(value))
+    }
+}
+
+/// Characters which the decoders skip unless they are strict.
+pub fn is_ignorable(byte: u8) -> bool {
+    match byte {
+        b' ' | b'\t' | b'\n' | b'\x0C' | b'\r' => true,
+        _ => false,
+    }
+}
+
+pub struct Base64Encoder {
+    chars: &'static [u8; 64],
+    pad: bool,
     line_break: bool,
-    multibyte: bool,
-) -> ptrdiff_t {
-    let config = if line_break {
-        base64_crate::MIME
-    } else {
-        base64_crate::STANDARD
-    };
-    let bytes = unsafe { slice::from_raw_parts(from as *const u8, length as usize) };
-
-    let output = if multibyte {
-        // Transform non-ASCII characters in multibyte string to Latin1,
-        // erroring out for non-Latin1 codepoints, and resolve raw 8-bit bytes.
-        let mut input = Vec::with_capacity(bytes.len());
-        let mut i = 0;
-        while i < bytes.len() {
-            let (cp, len) = multibyte_char_at(&bytes[i..]);
-            if cp > MAX_5_BYTE_CHAR {
-                input.push(raw_byte_from_codepoint(cp));
-            } else if cp < 256 {
-                input.push(cp as c_uchar);
-            } else {
-                return -1;
-            }
-            i += len;
+    /// Input bytes not yet encoded, and their number.
+    pending: u32,
+    npending: usize,
+    /// The number of characters on the current output line.
+    column: isize,
+}
+
+impl Base64Encoder {
+    /// Make an encoder for ALPHABET, which pads the output with `='
+    /// to a multiple of four characters if PAD, and breaks it into
+    /// lines of `MIME_LINE_LENGTH` characters if LINE_BREAK.
+    pub fn new(alphabet: Alphabet, pad: bool, line_break: bool) -> Base64Encoder {
+        Base64Encoder {
+            chars: alphabet.chars(),
+            pad,
+            line_break,
+            pending: 0,
+            npending: 0,
+            column: 0,
         }
-        base64_crate::encode_config(&input, config)
-    } else {
-        // Just encode the raw bytes.
-        base64_crate::encode_config(bytes, config)
-    };
-    let size = output.len();
-    unsafe {
-        ptr::copy_nonoverlapping(output.as_ptr(), to as *mut u8, size);
     }
-    size as ptrdiff_t
-}
-
-/// Base64-decode the data at FROM of LENGTH bytes into TO.  If MULTIBYTE, the
-/// decoded result should be in multibyte form.  If `NCHARS_RETURN` is not NULL,
-/// store the number of produced characters in `*NCHARS_RETURN`.
-#[no_mangle]
-pub extern "C" fn base64_decode_1(
-    from: *const c_char,
-    to: *mut c_char,
-    length: ptrdiff_t,
-    multibyte: bool,
-    nchars_return: *mut ptrdiff_t,
-) -> ptrdiff_t {
-    let encoded = unsafe { slice::from_raw_parts(from as *const u8, length as usize) };
-
-    // Use the MIME config to allow embedded newlines.
-    if let Ok(decoded) = base64_crate::decode_config(encoded, base64_crate::MIME) {
-        if !nchars_return.is_null() {
-            unsafe {
-                *nchars_return = decoded.len() as ptrdiff_t;
-            }
+
+    fn emit(&mut self, value: u32, out: &mut Vec<u8>) {
+        if self.line_break && self.column == MIME_LINE_LENGTH {
+            out.push(b'\n');
+            self.column = 0;
         }
-        if multibyte {
-            // Decode non-ASCII bytes into UTF-8 pairs.
-            let s: String = decoded.iter().map(|&byte| byte as char).collect();
-            unsafe {
-                ptr::copy_nonoverlapping(s.as_ptr(), to as *mut u8, s.len());
+        out.push(self.chars[(value & 0x3F) as usize]);
+        self.column += 1;
+    }
+}
+
+impl Encoder for Base64Encoder {
+    fn push(&mut self, byte: u8, out: &mut Vec<u8>) {
+        self.pending = (self.pending << 8) | u32::from(byte);
+        self.npending += 1;
+        if self.npending == 3 {
+            let group = self.pending;
+            self.emit(group >> 18, out);
+            self.emit(group >> 12, out);
+            self.emit(group >> 6, out);
+            self.emit(group, out);
+            self.pending = 0;
+            self.npending = 0;
+        }
+    }
+
+    fn finish(mut self, out: &mut Vec<u8>) {
+        let (group, npending) = (self.pending, self.npending);
+        if npending > 0 {
+            let group = group << (8 * (3 - npending));
+            self.emit(group >> 18, out);
+            self.emit(group >> 12, out);
+            if npending == 2 {
+                self.emit(group >> 6, out);
             }
-            s.len() as ptrdiff_t
-        } else {
-            unsafe {
-                ptr::copy_nonoverlapping(decoded.as_ptr(), to as *mut u8, decoded.len());
+            if self.pad {
+                out.push(b'=');
+                if npending == 1 {
+                    out.push(b'=');
+                }
             }
-            decoded.len() as ptrdiff_t
         }
-    } else {
-        -1
     }
 }
 
-#[test]
-fn test_base64_encode_1() {
-    let input = "hello world";
-    let mut encoded = [0u8; 20];
+/// A base64 decoder.
+///
+/// Unless it is strict, the decoder skips whitespace and accepts
+/// missing padding.  A strict decoder rejects any character outside
+/// of the alphabet, missing padding with the standard alphabet (the
+/// URL safe alphabet is commonly used without padding), and encodings
+/// whose unused trailing bits aren't zero.
+pub struct Base64Decoder {
+    alphabet: Alphabet,
+    strict: bool,
+    /// The values of the characters of the current group, and their
+    /// number.
+    group: u32,
+    ngroup: usize,
+    /// The number of padding characters seen so far.
+    padding: usize,
+}
 
-    let length = base64_encode_1(
-        input.as_ptr() as *mut c_char,
-        encoded.as_mut_ptr() as *mut c_char,
-        input.as_bytes().len() as ptrdiff_t,
-        false,
-        false,
-    );
+impl Base64Decoder {
+    pub fn new(alphabet: Alphabet, strict: bool) -> Base64Decoder {
+        Base64Decoder {
+            alphabet,
+            strict,
+            group: 0,
+            ngroup: 0,
+            padding: 0,
+        }
+    }
+}
+
+impl Decoder for Base64Decoder {
+    fn push(&mut self, byte: u8, out: &mut Output) -> Option<()> {
+        if let Some(value) = self.alphabet.value(byte) {
+            if self.padding > 0 {
+                return None;
+            }
+            self.group = (self.group << 6) | value;
+            self.ngroup += 1;
+            if self.ngroup == 4 {
+                let group = self.group;
+                out.push((group >> 16) as u8);
+                out.push((group >> 8) as u8);
+                out.push(group as u8);
+                self.group = 0;
+                self.ngroup = 0;
+            }
+        } else if byte == b'=' {
+            self.padding += 1;
+            if self.ngroup < 2 || self.ngroup + self.padding > 4 {
+                return None;
+            }
+        } else if self.strict || !is_ignorable(byte) {
+            return None;
+        }
+        Some(())
+    }
 
-    assert!(length != -1);
-    assert_eq!(b"aGVsbG8gd29ybGQ=", &encoded[..length as usize]);
+    fn finish(self, out: &mut Output) -> Option<()> {
+        let (group, ngroup) = (self.group, self.ngroup);
+        if ngroup == 0 {
+            return Some(());
+        }
+        // A final group of two characters encodes one byte, and one of
+        // three characters two bytes.
+        if ngroup == 1 {
+            return None;
+        }
+        if self.strict {
+            let padded = self.padding == 4 - ngroup;
+            if (self.alphabet == Alphabet::Standard && !padded)
+                || (self.padding > 0 && !padded)
+                || group & ((1 << (2 * (4 - ngroup))) - 1) != 0
+            {
+                return None;
+            }
+        }
+        let group = group << (6 * (4 - ngroup));
+        out.push((group >> 16) as u8);
+        if ngroup == 3 {
+            out.push((group >> 8) as u8);
+        }
+        Some(())
+    }
 }
 
-#[test]
-fn test_base64_decode_1() {
-    use std::str;
+#[cfg(test)]
+fn encode_all(input: &[u8], alphabet: Alphabet, pad: bool, line_break: bool) -> Vec<u8> {
+    let encoder = Base64Encoder::new(alphabet, pad, line_break);
+    // Feed the input in two parts, as for a region split by the gap.
+    let (before, after) = input.split_at(input.len() / 2);
+    codec::encode(encoder, &[before, after], false).unwrap()
+}
 
-    let input = "aGVsbG8gd29ybGQ=";
-    let mut decoded = [0u8; 20];
+#[cfg(test)]
+fn decode_all(input: &[u8], alphabet: Alphabet, strict: bool) -> Option<Vec<u8>> {
+    let decoder = Base64Decoder::new(alphabet, strict);
+    let (before, after) = input.split_at(input.len() / 3);
+    codec::decode(decoder, &[before, after], false).map(|output| output.bytes)
+}
 
-    let mut n: isize = 0;
-    let nchars: *mut isize = &mut n;
+#[test]
+fn test_base64_hello() {
+    let encoded = encode_all(b"hello world", Alphabet::Standard, true, false);
+    assert_eq!(encoded, b"aGVsbG8gd29ybGQ=");
+    let decoder = Base64Decoder::new(Alphabet::Standard, false);
+    let output = codec::decode(decoder, &[&encoded[..]], true).unwrap();
+    assert_eq!(output.bytes, b"hello world");
+    assert_eq!(output.nchars, 11);
+}
 
-    let length = base64_decode_1(
-        input.as_ptr() as *mut c_char,
-        decoded.as_mut_ptr() as *mut c_char,
-        input.as_bytes().len() as ptrdiff_t,
-        true,
-        nchars,
-    );
-    assert!(length != -1);
+#[test]
+fn test_base64url() {
+    let input = b"\xFB\xFF\xBF?";
+    assert_eq!(encode_all(input, Alphabet::Standard, true, false), b"+/+/Pw==");
+    assert_eq!(encode_all(input, Alphabet::UrlSafe, true, false), b"-_-_Pw==");
+    assert_eq!(encode_all(input, Alphabet::UrlSafe, false, false), b"-_-_Pw");
+    for &strict in &[false, true] {
+        assert_eq!(decode_all(b"-_-_Pw==", Alphabet::UrlSafe, strict).unwrap(), input);
+        assert_eq!(decode_all(b"-_-_Pw", Alphabet::UrlSafe, strict).unwrap(), input);
+        assert_eq!(decode_all(b"-_-_Pw", Alphabet::Standard, strict), None);
+        assert_eq!(decode_all(b"+/+/Pw==", Alphabet::UrlSafe, strict), None);
+    }
+}
 
-    let answer = str::from_utf8(&decoded[..length as usize]).unwrap();
+#[test]
+fn test_base64_round_trip() {
+    for &alphabet in &[Alphabet::Standard, Alphabet::UrlSafe] {
+        for &(pad, line_break) in &[(true, false), (true, true), (false, false)] {
+            codec::check_round_trip(
+                || Base64Encoder::new(alphabet, pad, line_break),
+                || Base64Decoder::new(alphabet, false),
+            );
+        }
+        codec::check_round_trip(
+            || Base64Encoder::new(alphabet, true, false),
+            || Base64Decoder::new(alphabet, true),
+        );
+    }
+    let input: Vec<u8> = (0..1000u32).map(|i| (i * 7 % 256) as u8).collect();
+    let encoded = encode_all(&input, Alphabet::Standard, true, true);
+    let lines: Vec<&[u8]> = encoded.split(|&byte| byte == b'\n').collect();
+    assert!(lines[..lines.len() - 1].iter().all(|line| line.len() == 76));
+    assert!(lines[lines.len() - 1].len() <= 76);
+    assert_eq!(decode_all(&encoded, Alphabet::Standard, false).unwrap(), input);
+    assert_eq!(decode_all(&encoded, Alphabet::Standard, true), None);
+}
 
-    assert_eq!(n, length);
-    assert_eq!("hello world", answer);
+#[test]
+fn test_base64_decode_errors() {
+    let decode = |input: &[u8], strict| decode_all(input, Alphabet::Standard, strict);
+    assert_eq!(decode(b"YQ", false).unwrap(), b"a");
+    assert_eq!(decode(b"YQ", true), None);
+    assert_eq!(decode(b"YQ=", false).unwrap(), b"a");
+    assert_eq!(decode(b"YQ=", true), None);
+    assert_eq!(decode(b" YW\r\nJj ", false).unwrap(), b"abc");
+    assert_eq!(decode(b" YWJj", true), None);
+    // Non-zero trailing bits.
+    assert_eq!(decode(b"YR==", false).unwrap(), b"a");
+    assert_eq!(decode(b"YR==", true), None);
+    for &strict in &[false, true] {
+        assert_eq!(decode(b"Y", strict), None);
+        assert_eq!(decode(b"Y===", strict), None);
+        assert_eq!(decode(b"YQ==YQ==", strict), None);
+        assert_eq!(decode(b"YW!j", strict), None);
+        assert_eq!(decode(b"YQ===", strict), None);
+    }
 }
 
 /// Base64-encode STRING and return the result.
@@ -142,56 +299,84 @@ fn test_base64_decode_1() {
 /// into shorter lines.
 #[lisp_fn(min = "1")]
 fn base64_encode_string(string: LispObject, no_line_break: LispObject) -> LispObject {
-    let mut string = string.as_string_or_error();
-
-    // We need to allocate enough room for the encoded text
-    // We will need 33 1/3% more space, plus a newline every 76 characters(MIME_LINE_LENGTH)
-    // and then round up
-    let length = string.len_bytes();
-    let mut allength: ptrdiff_t = length + length / 3 + 1;
-    allength += allength / MIME_LINE_LENGTH + 1 + 6;
-
-    // This function uses SAFE_ALLOCA in the c layer, however I cannot find an equivalent
-    // for rust. Instead, we will use a Vec to store the temporary char buffer.
-    let mut buffer: Vec<c_char> = Vec::with_capacity(allength as usize);
-    let encoded = buffer.as_mut_ptr();
-    let encoded_length = base64_encode_1(
-        string.sdata_ptr(),
-        encoded,
-        length,
-        no_line_break.is_nil(),
-        string.is_multibyte(),
-    );
-
-    if encoded_length > allength {
-        panic!("base64 encoded length is larger then allocated buffer");
-    }
+    let encoder = Base64Encoder::new(Alphabet::Standard, true, no_line_break.is_nil());
+    codec::encode_string(string, encoder, "base64")
+}
 
-    if encoded_length < 0 {
-        error!("Multibyte character in data for base64 encoding");
-    }
+/// Base64url-encode STRING and return the result.
+/// This uses the URL and filename safe alphabet of RFC 4648, where `-'
+/// and `_' replace `+' and `/', and never breaks lines.
+/// Optional second argument NO-PAD means do not add `=' padding.
+#[lisp_fn(min = "1")]
+fn base64url_encode_string(string: LispObject, no_pad: LispObject) -> LispObject {
+    let encoder = Base64Encoder::new(Alphabet::UrlSafe, no_pad.is_nil(), false);
+    codec::encode_string(string, encoder, "base64")
+}
 
-    unsafe { LispObject::from(make_unibyte_string(encoded, encoded_length)) }
+fn decode_alphabet(base64url: LispObject) -> Alphabet {
+    if base64url.is_nil() {
+        Alphabet::Standard
+    } else {
+        Alphabet::UrlSafe
+    }
 }
 
-/// Base64-decode STRING and return the result.
-#[lisp_fn]
-fn base64_decode_string(string: LispObject) -> LispObject {
-    let mut string = string.as_string_or_error();
+/// Base64-decode STRING and return the result as a unibyte string.
+/// Whitespace in STRING is ignored, and so is missing `=' padding.
+/// Optional argument BASE64URL means use the URL and filename safe
+/// alphabet, where `-' and `_' replace `+' and `/'.
+/// Optional argument STRICT means signal an error for any character
+/// outside of the alphabet including whitespace, for missing padding
+/// (which is always optional with BASE64URL), and for non-zero unused
+/// bits in the last group.
+#[lisp_fn(min = "1")]
+fn base64_decode_string(
+    string: LispObject,
+    base64url: LispObject,
+    strict: LispObject,
+) -> LispObject {
+    let decoder = Base64Decoder::new(decode_alphabet(base64url), strict.is_not_nil());
+    codec::decode_string(string, decoder, "base64")
+}
 
-    let length = string.len_bytes();
-    let mut buffer: Vec<c_char> = Vec::with_capacity(length as usize);
+/// Base64-encode the region between BEG and END.
+/// Return the length of the encoded text.
+/// Optional third argument NO-LINE-BREAK means do not break long lines
+/// into shorter lines.
+#[lisp_fn(min = "2", intspec = "r")]
+fn base64_encode_region(
+    beg: LispObject,
+    end: LispObject,
+    no_line_break: LispObject,
+) -> LispObject {
+    let encoder = Base64Encoder::new(Alphabet::Standard, true, no_line_break.is_nil());
+    codec::encode_region(beg, end, encoder, "base64")
+}
 
-    let decoded = buffer.as_mut_ptr();
-    let decoded_length =
-        base64_decode_1(string.sdata_ptr(), decoded, length, false, ptr::null_mut());
+/// Base64url-encode the region between BEG and END.
+/// Return the length of the encoded text.
+/// This uses the URL and filename safe alphabet of RFC 4648, where `-'
+/// and `_' replace `+' and `/', and never breaks lines.
+/// Optional third argument NO-PAD means do not add `=' padding.
+#[lisp_fn(min = "2", intspec = "r")]
+fn base64url_encode_region(beg: LispObject, end: LispObject, no_pad: LispObject) -> LispObject {
+    let encoder = Base64Encoder::new(Alphabet::UrlSafe, no_pad.is_nil(), false);
+    codec::encode_region(beg, end, encoder, "base64")
+}
 
-    if decoded_length > length {
-        panic!("Decoded length is above length");
-    } else if decoded_length < 0 {
-        error!("Invalid base64 data");
-    }
-    unsafe { LispObject::from(make_unibyte_string(decoded, decoded_length)) }
+/// Base64-decode the region between BEG and END.
+/// Return the length of the decoded text.
+/// If the region can't be decoded, signal an error and don't modify the buffer.
+/// Optional arguments BASE64URL and STRICT are as for `base64-decode-string'.
+#[lisp_fn(min = "2", intspec = "r")]
+fn base64_decode_region(
+    beg: LispObject,
+    end: LispObject,
+    base64url: LispObject,
+    strict: LispObject,
+) -> LispObject {
+    let decoder = Base64Decoder::new(decode_alphabet(base64url), strict.is_not_nil());
+    codec::decode_region(beg, end, decoder, "base64")
 }
 
 include!(concat!(env!("OUT_DIR"), "/base64_exports.rs"));
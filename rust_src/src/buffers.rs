@@ -1,7 +1,7 @@
 //! Functions operating on buffers.
 
 use libc::{c_int, c_uchar, c_void, ptrdiff_t};
-use std::{mem, ptr};
+use std::{mem, ptr, slice};
 
 use remacs_macros::lisp_fn;
 use remacs_sys::{EmacsInt, Lisp_Buffer, Lisp_Object, Lisp_Overlay, Lisp_Type, Vbuffer_alist};
@@ -11,7 +11,7 @@ use lisp::{ExternalPtr, LispObject};
 use lisp::defsubr;
 use lists::{car, cdr};
 use marker::{marker_buffer, marker_position};
-use multibyte::string_char;
+use multibyte::{string_char, Codepoint};
 use strings::string_equal;
 use threads::ThreadState;
 
@@ -124,6 +124,32 @@ impl LispBufferRef {
         LispObject::from(self.name).is_not_nil()
     }
 
+    /// Return the address of byte position N.  Same as the
+    /// `BUF_BYTE_ADDRESS` macro.
+    #[inline]
+    pub fn byte_pos_addr(&self, n: ptrdiff_t) -> *mut c_uchar {
+        let offset = if n >= self.gpt_byte() {
+            self.gap_size()
+        } else {
+            0
+        };
+
+        unsafe { self.beg_addr().offset(offset + n - self.beg_byte()) }
+    }
+
+    /// Return the bytes between byte positions BEG and END as two
+    /// slices, the parts before and after the gap, without moving the
+    /// gap.
+    pub fn byte_slices(&self, beg: ptrdiff_t, end: ptrdiff_t) -> (&[u8], &[u8]) {
+        let split = beg.max(end.min(self.gpt_byte()));
+        unsafe {
+            (
+                slice::from_raw_parts(self.byte_pos_addr(beg), (split - beg) as usize),
+                slice::from_raw_parts(self.byte_pos_addr(split), (end - split) as usize),
+            )
+        }
+    }
+
     #[inline]
     pub fn fetch_byte(&self, n: ptrdiff_t) -> u8 {
         let offset = if n >= self.gpt_byte() {
@@ -159,6 +185,30 @@ impl LispBufferRef {
             self.fetch_byte(n) as c_int
         }
     }
+
+    /// Downcase character C according to the buffer's case table, or
+    /// return C if it has no lower case.
+    pub fn downcase(self, c: Codepoint) -> Codepoint {
+        let table = LispObject::from(self.downcase_table).as_char_table_or_error();
+        let down = table.get(c);
+        if down.is_natnum() {
+            down.as_natnum_or_error() as Codepoint
+        } else {
+            c
+        }
+    }
+
+    /// Upcase character C according to the buffer's case table, or
+    /// return C if it has no upper case.
+    pub fn upcase(self, c: Codepoint) -> Codepoint {
+        let table = LispObject::from(self.upcase_table).as_char_table_or_error();
+        let up = table.get(c);
+        if up.is_natnum() {
+            up.as_natnum_or_error() as Codepoint
+        } else {
+            c
+        }
+    }
 }
 
 impl LispOverlayRef {
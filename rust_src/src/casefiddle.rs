@@ -0,0 +1,607 @@
+//! Case conversion functions.
+//!
+//! Characters are cased with the current buffer's case table, so that
+//! language environments such as Turkish can override the default
+//! mappings.  Unless the case table overrides them, the unconditional
+//! one-to-many mappings of SpecialCasing.txt are applied as well, except
+//! when casing a single character.
+
+use std::ptr;
+
+use libc::{c_char, c_int, ptrdiff_t};
+
+use remacs_macros::lisp_fn;
+use remacs_sys::{buf_charpos_to_bytepos, make_buffer_string, modify_text, record_delete,
+                 record_insert, replace_range_2, scan_words, set_point, signal_after_change,
+                 syntax_prefix_flag_p, temp_set_point_both, update_compositions, EmacsInt,
+                 Fcopy_sequence, Fset_case_table, Lisp_Object, Qchar_or_string_p,
+                 SETUP_BUFFER_SYNTAX_TABLE, SYNTAX, CHAR_MODIFIER_MASK, CHECK_ALL};
+use remacs_sys::SyntaxCode;
+
+use buffers::{validate_region, LispBufferRef};
+use lisp::{intern, LispObject};
+use lisp::defsubr;
+use lists::{car, cdr};
+use multibyte::{make_char_multibyte, make_char_unibyte, make_string_from_codepoints, string_char,
+                write_codepoints, Codepoint};
+use symbols::symbol_value;
+use threads::ThreadState;
+use unidata::{simple_case_mapping, special_case_mapping, Case};
+
+const GREEK_CAPITAL_LETTER_SIGMA: Codepoint = 0x3A3;
+const GREEK_SMALL_LETTER_FINAL_SIGMA: Codepoint = 0x3C2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CaseAction {
+    Up,
+    Down,
+    Capitalize,
+    CapitalizeUp,
+}
+
+/// State for casing individual characters.
+struct CasingContext {
+    buffer: LispBufferRef,
+    /// User-requested action.
+    action: CaseAction,
+    /// If true, the text being cased is in a buffer rather than a
+    /// string or character, and `syntax_prefix_flag_p` is taken into
+    /// account when determining whether the context is within a word.
+    inbuffer: bool,
+    /// Whether the context is within a word.
+    inword: bool,
+}
+
+impl CasingContext {
+    fn new(action: CaseAction, inbuffer: bool) -> CasingContext {
+        let buffer = ThreadState::current_buffer();
+
+        // If the case table is flagged as modified, rescan it.
+        let downcase_table = LispObject::from(buffer.downcase_table);
+        if downcase_table.as_char_table_or_error().get_extra(1).is_nil() {
+            unsafe { Fset_case_table(downcase_table.to_raw()) };
+        }
+
+        if inbuffer && (action == CaseAction::Capitalize || action == CaseAction::CapitalizeUp) {
+            // For syntax_prefix_flag_p.
+            unsafe { SETUP_BUFFER_SYNTAX_TABLE() };
+        }
+
+        CasingContext {
+            buffer,
+            action,
+            inbuffer,
+            inword: false,
+        }
+    }
+
+    /// Update the word state for character CH, and return the kind of
+    /// mapping to apply to it, or None if it should be left alone.
+    fn case_for(&mut self, ch: Codepoint) -> Option<Case> {
+        let was_inword = self.inword;
+        self.inword = unsafe {
+            SYNTAX(ch as c_int) == SyntaxCode::Word
+                && (!self.inbuffer || was_inword || !syntax_prefix_flag_p(ch as c_int))
+        };
+
+        match self.action {
+            CaseAction::Up => Some(Case::Upper),
+            CaseAction::Down => Some(Case::Lower),
+            CaseAction::Capitalize if was_inword => Some(Case::Lower),
+            CaseAction::CapitalizeUp if was_inword => None,
+            CaseAction::Capitalize | CaseAction::CapitalizeUp => Some(Case::Title),
+        }
+    }
+
+    /// Return true if the case table's mapping of CH for CASE takes
+    /// precedence over Unicode.  This is so if the case table maps CH
+    /// to another character than Unicode does, or if it maps the result
+    /// back differently: a Turkish case table, for instance, downcases
+    /// dotted capital I to i like Unicode, but also upcases i to dotted
+    /// capital I.  A case table that leaves CH alone does not override
+    /// anything.
+    fn is_overridden(&self, ch: Codepoint, case: Case) -> bool {
+        let buffer = self.buffer;
+        let (target, back, direct, inverse) = match case {
+            Case::Lower => {
+                let target = buffer.downcase(ch);
+                (target, buffer.upcase(target), Case::Lower, Case::Upper)
+            }
+            Case::Upper | Case::Title => {
+                let target = buffer.upcase(ch);
+                (target, buffer.downcase(target), Case::Upper, Case::Lower)
+            }
+        };
+
+        target != ch
+            && (target != simple_case_mapping(ch, direct)
+                || back != simple_case_mapping(target, inverse))
+    }
+
+    /// Return the one-to-one CASE mapping of CH.
+    fn simple_case(&self, ch: Codepoint, case: Case) -> Codepoint {
+        match case {
+            Case::Lower => self.buffer.downcase(ch),
+            Case::Upper => self.buffer.upcase(ch),
+            Case::Title if self.is_overridden(ch, case) => self.buffer.upcase(ch),
+            Case::Title => simple_case_mapping(ch, Case::Title),
+        }
+    }
+
+    /// Case character CH, ignoring mappings to several characters, and
+    /// return the result.
+    fn case_single_character(&mut self, ch: Codepoint) -> Codepoint {
+        match self.case_for(ch) {
+            Some(case) => self.simple_case(ch, case),
+            None => ch,
+        }
+    }
+
+    /// Case character CH, appending the result to CASED, and return
+    /// whether it changed.  NEXT is the character after CH, or None if
+    /// CH is the last one being cased.
+    fn case_character(
+        &mut self,
+        ch: Codepoint,
+        next: Option<Codepoint>,
+        cased: &mut Vec<Codepoint>,
+    ) -> bool {
+        let was_inword = self.inword;
+        let case = match self.case_for(ch) {
+            Some(case) => case,
+            None => {
+                cased.push(ch);
+                return false;
+            }
+        };
+
+        if !self.is_overridden(ch, case) {
+            if let Some(mapping) = special_case_mapping(ch, case) {
+                cased.extend_from_slice(mapping);
+                return mapping != [ch];
+            }
+        }
+
+        let mut result = self.simple_case(ch, case);
+
+        // In Greek, lower case sigma has a special form at the end of a
+        // word.  If we have just downcased a capital sigma and the next
+        // character does not have word syntax, use final sigma.  This
+        // rule is conditional, but it is independent of the language.
+        if was_inword && ch == GREEK_CAPITAL_LETTER_SIGMA && result != ch
+            && next.map_or(true, |c| unsafe { SYNTAX(c as c_int) } != SyntaxCode::Word)
+        {
+            result = GREEK_SMALL_LETTER_FINAL_SIGMA;
+        }
+
+        cased.push(result);
+        result != ch
+    }
+}
+
+fn casify_natnum(ctx: &mut CasingContext, obj: LispObject) -> LispObject {
+    let flagbits = EmacsInt::from(CHAR_MODIFIER_MASK);
+    let mut ch = obj.as_natnum_or_error();
+
+    // If the character has higher bits set above the flags, return it
+    // unchanged.  It is not a real character.
+    if ch > flagbits {
+        return obj;
+    }
+
+    let flags = ch & flagbits;
+    ch &= !flagbits;
+
+    // FIXME: Even if enable-multibyte-characters is nil, we may
+    // manipulate multibyte chars.  This means we have a bug for latin-1
+    // chars since when we receive an int 128-255 we can't tell whether
+    // it's an eight-bit byte or a latin-1 char.
+    let multibyte = ch >= 256
+        || LispObject::from(ctx.buffer.enable_multibyte_characters).is_not_nil();
+    let mut ch = ch as Codepoint;
+    if !multibyte {
+        ch = make_char_multibyte(ch);
+    }
+    let cased = ctx.case_single_character(ch);
+    if cased == ch {
+        return obj;
+    }
+
+    let cased = if multibyte {
+        cased
+    } else {
+        make_char_unibyte(cased)
+    };
+    LispObject::from_natnum(EmacsInt::from(cased) | flags)
+}
+
+fn casify_multibyte_string(ctx: &mut CasingContext, obj: LispObject) -> LispObject {
+    let chars: Vec<Codepoint> = obj.as_string_or_error().chars().collect();
+    let mut cased = Vec::with_capacity(chars.len());
+
+    for (idx, &ch) in chars.iter().enumerate() {
+        ctx.case_character(ch, chars.get(idx + 1).cloned(), &mut cased);
+    }
+
+    make_string_from_codepoints(&cased)
+}
+
+fn casify_unibyte_string(ctx: &mut CasingContext, obj: LispObject) -> LispObject {
+    let copy = LispObject::from(unsafe { Fcopy_sequence(obj.to_raw()) });
+
+    for byte in copy.as_string_or_error().as_mut_slice() {
+        let ch = make_char_multibyte(Codepoint::from(*byte));
+        let cased = ctx.case_single_character(ch);
+        if cased == ch {
+            continue;
+        }
+        // If the char can't be converted to a valid byte, just don't
+        // change it.
+        let cased = make_char_unibyte(cased);
+        if cased < 256 {
+            *byte = cased as u8;
+        }
+    }
+
+    copy
+}
+
+fn casify_object(action: CaseAction, obj: LispObject) -> LispObject {
+    let mut ctx = CasingContext::new(action, false);
+
+    if obj.is_natnum() {
+        casify_natnum(&mut ctx, obj)
+    } else if let Some(string) = obj.as_string() {
+        if string.len_chars() == 0 {
+            obj
+        } else if string.is_multibyte() {
+            casify_multibyte_string(&mut ctx, obj)
+        } else {
+            casify_unibyte_string(&mut ctx, obj)
+        }
+    } else {
+        wrong_type!(Qchar_or_string_p, obj)
+    }
+}
+
+/// Convert argument to upper case and return that.
+/// The argument may be a character or string.  The result has the same type.
+/// The argument object is not altered--the value is a copy.  If argument
+/// is a character, characters which map to multiple code points when
+/// cased, e.g. ﬁ, are returned unchanged.
+/// See also `capitalize', `downcase' and `upcase-initials'.
+#[lisp_fn]
+pub fn upcase(obj: LispObject) -> LispObject {
+    casify_object(CaseAction::Up, obj)
+}
+
+/// Convert argument to lower case and return that.
+/// The argument may be a character or string.  The result has the same type.
+/// The argument object is not altered--the value is a copy.
+#[lisp_fn]
+pub fn downcase(obj: LispObject) -> LispObject {
+    casify_object(CaseAction::Down, obj)
+}
+
+/// Convert argument to capitalized form and return that.
+/// This means that each word's first character is converted to either
+/// title case or upper case, and the rest to lower case.
+/// The argument may be a character or string.  The result has the same type.
+/// The argument object is not altered--the value is a copy.  If argument
+/// is a character, characters which map to multiple code points when
+/// cased, e.g. ﬁ, are returned unchanged.
+#[lisp_fn]
+pub fn capitalize(obj: LispObject) -> LispObject {
+    casify_object(CaseAction::Capitalize, obj)
+}
+
+/// Convert the initial of each word in the argument to upper case.
+/// This means that each word's first character is converted to either
+/// title case or upper case, and the rest are left unchanged.
+/// The argument may be a character or string.  The result has the same type.
+/// The argument object is not altered--the value is a copy.  If argument
+/// is a character, characters which map to multiple code points when
+/// cased, e.g. ﬁ, are returned unchanged.
+#[lisp_fn]
+pub fn upcase_initials(obj: LispObject) -> LispObject {
+    casify_object(CaseAction::CapitalizeUp, obj)
+}
+
+/// The result of casing a region: the positions of the first and last
+/// changes, if anything changed, and the number of characters added.
+type RegionChanges = (Option<(ptrdiff_t, ptrdiff_t)>, ptrdiff_t);
+
+/// Case the region from START to END in a unibyte buffer.  No
+/// characters are ever added.
+fn casify_unibyte_region(
+    ctx: &mut CasingContext,
+    start: ptrdiff_t,
+    end: ptrdiff_t,
+) -> RegionChanges {
+    let mut changed = None;
+
+    for pos in start..end {
+        let addr = ctx.buffer.byte_pos_addr(pos);
+        let ch = make_char_multibyte(Codepoint::from(unsafe { *addr }));
+        let cased = ctx.case_single_character(ch);
+        if cased == ch {
+            continue;
+        }
+
+        let first = changed.map_or(pos, |(first, _)| first);
+        changed = Some((first, pos + 1));
+        unsafe { *addr = make_char_unibyte(cased) as u8 };
+    }
+
+    (changed, 0)
+}
+
+/// Case the region from START to END in a multibyte buffer.  Characters
+/// that are cased to the same number of bytes are changed in place;
+/// others are replaced, keeping their text properties.
+fn casify_multibyte_region(
+    ctx: &mut CasingContext,
+    start: ptrdiff_t,
+    end: ptrdiff_t,
+) -> RegionChanges {
+    let mut buffer = ctx.buffer;
+    let mut changed = None;
+    let mut added = 0;
+    let mut opoint = buffer.pt();
+    let mut pos = start;
+    let mut pos_byte = unsafe { buf_charpos_to_bytepos(buffer.as_ptr(), pos) };
+    let mut cased = Vec::new();
+    let mut bytes = Vec::new();
+
+    for remaining in (0..end - start).rev() {
+        let mut len: c_int = 0;
+        let ch = unsafe { string_char(buffer.byte_pos_addr(pos_byte), ptr::null_mut(), &mut len) };
+        let len = len as ptrdiff_t;
+        let next = if remaining > 0 {
+            Some(buffer.fetch_multibyte_char(pos_byte + len) as Codepoint)
+        } else {
+            None
+        };
+
+        cased.clear();
+        if !ctx.case_character(ch as Codepoint, next, &mut cased) {
+            pos += 1;
+            pos_byte += len;
+            continue;
+        }
+
+        bytes.clear();
+        write_codepoints(&mut bytes, cased.iter().cloned());
+        let nchars = cased.len() as ptrdiff_t;
+        let nbytes = bytes.len() as ptrdiff_t;
+
+        let first = changed.map_or(pos, |(first, _)| first);
+        changed = Some((first, pos + nchars));
+
+        if nchars == 1 && nbytes == len {
+            let addr = buffer.byte_pos_addr(pos_byte);
+            unsafe { ptr::copy_nonoverlapping(bytes.as_ptr(), addr, bytes.len()) };
+        } else {
+            // Replace one character with the other(s), keeping text
+            // properties the same.
+            unsafe {
+                replace_range_2(
+                    pos,
+                    pos_byte,
+                    pos + 1,
+                    pos_byte + len,
+                    bytes.as_ptr() as *const c_char,
+                    nchars,
+                    nbytes,
+                    false,
+                )
+            };
+            added += nchars - 1;
+            if opoint > pos {
+                opoint += nchars - 1;
+            }
+        }
+
+        pos += nchars;
+        pos_byte += nbytes;
+    }
+
+    if buffer.pt() != opoint {
+        unsafe {
+            let opoint_byte = buf_charpos_to_bytepos(buffer.as_ptr(), opoint);
+            temp_set_point_both(buffer.as_mut(), opoint, opoint_byte);
+        }
+    }
+
+    (changed, added)
+}
+
+/// Case the region of the current buffer between B and E according to
+/// ACTION.  Return the position of the end of the region after the
+/// changes.
+fn casify_region(action: CaseAction, b: LispObject, e: LispObject) -> ptrdiff_t {
+    let mut b_raw: Lisp_Object = b.to_raw();
+    let mut e_raw: Lisp_Object = e.to_raw();
+    validate_region(&mut b_raw, &mut e_raw);
+    let start = LispObject::from(b_raw).as_fixnum_or_error() as ptrdiff_t;
+    let end = LispObject::from(e_raw).as_fixnum_or_error() as ptrdiff_t;
+    if start == end {
+        // Not modifying because nothing marked.
+        return end;
+    }
+
+    unsafe { modify_text(start, end) };
+    let mut ctx = CasingContext::new(action, true);
+
+    unsafe { record_delete(start, make_buffer_string(start, end, true), false) };
+    let (changed, added) =
+        if LispObject::from(ctx.buffer.enable_multibyte_characters).is_nil() {
+            unsafe { record_insert(start, end - start) };
+            casify_unibyte_region(&mut ctx, start, end)
+        } else {
+            let result = casify_multibyte_region(&mut ctx, start, end);
+            unsafe { record_insert(start, end - start + result.1) };
+            result
+        };
+
+    if let Some((first, last)) = changed {
+        unsafe {
+            signal_after_change(first, last - first - added, last - first);
+            update_compositions(first, last, CHECK_ALL);
+        }
+    }
+
+    end + added
+}
+
+/// Case each of the regions returned by `region-extract-function' if
+/// REGION_NONCONTIGUOUS_P is non-nil, or else the region between BEG
+/// and END.
+fn casify_regions(
+    action: CaseAction,
+    beg: LispObject,
+    end: LispObject,
+    region_noncontiguous_p: LispObject,
+) -> LispObject {
+    if region_noncontiguous_p.is_not_nil() {
+        let bounds = call!(
+            symbol_value(intern("region-extract-function")),
+            intern("bounds")
+        );
+        for tail in bounds.iter_tails_safe() {
+            let bound = tail.car();
+            casify_region(action, car(bound), cdr(bound));
+        }
+    } else {
+        casify_region(action, beg, end);
+    }
+
+    LispObject::constant_nil()
+}
+
+/// Convert the region to upper case.  In programs, wants two arguments.
+/// These arguments specify the starting and ending character numbers of
+/// the region to operate on.  When used as a command, the text between
+/// point and the mark is operated on.
+/// See also `capitalize-region'.
+#[lisp_fn(min = "2", intspec = "(list (region-beginning) (region-end) (region-noncontiguous-p))")]
+pub fn upcase_region(
+    beg: LispObject,
+    end: LispObject,
+    region_noncontiguous_p: LispObject,
+) -> LispObject {
+    casify_regions(CaseAction::Up, beg, end, region_noncontiguous_p)
+}
+
+/// Convert the region to lower case.  In programs, wants two arguments.
+/// These arguments specify the starting and ending character numbers of
+/// the region to operate on.  When used as a command, the text between
+/// point and the mark is operated on.
+#[lisp_fn(min = "2", intspec = "(list (region-beginning) (region-end) (region-noncontiguous-p))")]
+pub fn downcase_region(
+    beg: LispObject,
+    end: LispObject,
+    region_noncontiguous_p: LispObject,
+) -> LispObject {
+    casify_regions(CaseAction::Down, beg, end, region_noncontiguous_p)
+}
+
+/// Convert the region to capitalized form.
+/// This means that each word's first character is converted to either
+/// title case or upper case, and the rest to lower case.
+/// In programs, give two arguments, the starting and ending
+/// character positions to operate on.
+#[lisp_fn(intspec = "r")]
+pub fn capitalize_region(beg: LispObject, end: LispObject) -> LispObject {
+    casify_region(CaseAction::Capitalize, beg, end);
+    LispObject::constant_nil()
+}
+
+/// Upcase the initial of each word in the region.
+/// This means that each word's first character is converted to either
+/// title case or upper case, and the rest are left unchanged.
+/// In programs, give two arguments, the starting and ending
+/// character positions to operate on.
+#[lisp_fn(intspec = "r")]
+pub fn upcase_initials_region(beg: LispObject, end: LispObject) -> LispObject {
+    casify_region(CaseAction::CapitalizeUp, beg, end);
+    LispObject::constant_nil()
+}
+
+fn casify_word(action: CaseAction, arg: LispObject) -> LispObject {
+    let count = arg.as_fixnum_or_error();
+    let buffer = ThreadState::current_buffer();
+    let mut farend = unsafe { scan_words(buffer.pt(), count) };
+    if farend == 0 {
+        farend = if count <= 0 { buffer.begv } else { buffer.zv() };
+    }
+
+    let end = casify_region(
+        action,
+        LispObject::from_natnum(buffer.pt() as EmacsInt),
+        LispObject::from_natnum(farend as EmacsInt),
+    );
+    unsafe { set_point(end) };
+    LispObject::constant_nil()
+}
+
+/// Convert to upper case from point to end of word, moving over.
+///
+/// If point is in the middle of a word, the part of that word before point
+/// is ignored when moving forward.
+///
+/// With negative argument, convert previous words but do not move.
+/// See also `capitalize-word'.
+#[lisp_fn(intspec = "p")]
+pub fn upcase_word(arg: LispObject) -> LispObject {
+    casify_word(CaseAction::Up, arg)
+}
+
+/// Convert to lower case from point to end of word, moving over.
+///
+/// If point is in the middle of a word, the part of that word before point
+/// is ignored when moving forward.
+///
+/// With negative argument, convert previous words but do not move.
+#[lisp_fn(intspec = "p")]
+pub fn downcase_word(arg: LispObject) -> LispObject {
+    casify_word(CaseAction::Down, arg)
+}
+
+/// Capitalize from point to the end of word, moving over.
+/// With numerical argument ARG, capitalize the next ARG-1 words as well.
+/// This gives the word(s) a first character in upper case
+/// and the rest lower case.
+///
+/// If point is in the middle of a word, the part of that word before point
+/// is ignored when moving forward.
+///
+/// With negative argument, capitalize previous words but do not move.
+#[lisp_fn(intspec = "p")]
+pub fn capitalize_word(arg: LispObject) -> LispObject {
+    casify_word(CaseAction::Capitalize, arg)
+}
+
+include!(concat!(env!("OUT_DIR"), "/casefiddle_exports.rs"));
+
+#[test]
+fn test_special_case_mappings() {
+    assert_eq!(special_case_mapping(0xDF, Case::Upper), Some(&[0x53, 0x53][..]));
+    assert_eq!(special_case_mapping(0xDF, Case::Title), Some(&[0x53, 0x73][..]));
+    assert_eq!(special_case_mapping(0x130, Case::Lower), Some(&[0x69, 0x307][..]));
+    assert_eq!(special_case_mapping(0xFB01, Case::Upper), Some(&[0x46, 0x49][..]));
+    // Language-sensitive mappings are left to the case tables.
+    assert_eq!(special_case_mapping(0x49, Case::Lower), None);
+    assert_eq!(special_case_mapping(0x3A3, Case::Lower), None);
+}
+
+#[test]
+fn test_simple_case_mappings() {
+    assert_eq!(simple_case_mapping(0x61, Case::Upper), 0x41);
+    assert_eq!(simple_case_mapping(0x1C6, Case::Title), 0x1C5);
+    assert_eq!(simple_case_mapping(0x1C6, Case::Upper), 0x1C4);
+    assert_eq!(simple_case_mapping(0x1E9E, Case::Lower), 0xDF);
+    // Title case defaults to upper case.
+    assert_eq!(simple_case_mapping(0x3C3, Case::Title), 0x3A3);
+    assert_eq!(simple_case_mapping(0x3F_FFC3, Case::Upper), 0x3F_FFC3);
+}
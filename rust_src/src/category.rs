@@ -1,12 +1,125 @@
 //! Routines to deal with category tables.
+//!
+//! A category is represented by a mnemonic character of the range
+//! ` '(32)..`~'(126).  A category set is a bool-vector of length 128,
+//! and a category table is a char-table whose values are category
+//! sets.  Its first extra slot is a vector of the docstrings of the
+//! defined categories, its second one a hash table used to share
+//! identical category sets.  See category.h for details.
+
+use libc::{c_char, c_int, ptrdiff_t};
 
 use remacs_macros::lisp_fn;
-use remacs_sys::Qcategory_table;
+use remacs_sys::{EmacsInt, EmacsUint, Lisp_Object, QCtest, Qcategory_table, Qcategory_table_p,
+                 Qcategoryp, Qcategorysetp, Qequal, Qnil};
+use remacs_sys::{Fcopy_sequence, Fmake_bool_vector, Fmake_char_table, Fmake_hash_table,
+                 Fmake_vector, Fpurecopy, Fset_char_table_extra_slot};
+use remacs_sys::{buffer_defaults, copy_char_table, globals, make_unibyte_string, map_char_table};
 
+use chartable::{LispCharTableRef, CHARTAB_SIZE_BITS};
 use lisp::LispObject;
 use lisp::defsubr;
+use multibyte::Codepoint;
 use threads::ThreadState;
 
+/// Number of elements of a category set.
+const CATEGORY_SET_SIZE: EmacsInt = 128;
+
+/// The number of possible categories, ` ' to `~'.
+const NUM_CATEGORIES: EmacsInt = 95;
+
+/// Same as `CHECK_CATEGORY`; return the category as a byte.
+fn check_category(category: LispObject) -> u8 {
+    match category.as_fixnum() {
+        Some(c) if 0x20 <= c && c <= 0x7E => c as u8,
+        _ => wrong_type!(Qcategoryp, category),
+    }
+}
+
+/// Same as `CATEGORY_SET_P`.
+fn is_category_set(object: LispObject) -> bool {
+    object
+        .as_bool_vector()
+        .map_or(false, |bv| bv.len() == CATEGORY_SET_SIZE as usize)
+}
+
+/// Same as `MAKE_CATEGORY_SET`; return a new empty category set.
+fn make_category_set() -> LispObject {
+    LispObject::from(unsafe {
+        Fmake_bool_vector(
+            LispObject::from_natnum(CATEGORY_SET_SIZE).to_raw(),
+            Qnil,
+        )
+    })
+}
+
+/// Same as `CATEGORY_MEMBER`; return true if CATEGORY_SET contains
+/// CATEGORY.
+#[inline]
+fn category_member(category: u8, category_set: LispObject) -> bool {
+    category_set
+        .as_bool_vector()
+        .map_or(false, |bv| bv.get(category as usize))
+}
+
+/// If TABLE is nil, return the current category table.  Otherwise,
+/// check that TABLE is a category table and return it.
+fn check_category_table(table: LispObject) -> LispCharTableRef {
+    if table.is_nil() {
+        LispObject::from(ThreadState::current_buffer().category_table).as_char_table_or_error()
+    } else if category_table_p(table).is_nil() {
+        wrong_type!(Qcategory_table_p, table)
+    } else {
+        table.as_char_table_or_error()
+    }
+}
+
+/// Same as `CATEGORY_DOCSTRING`.
+fn category_docstring(table: LispCharTableRef, category: u8) -> LispObject {
+    let docstrings = table.get_extra(0).as_vector_or_error();
+    docstrings.get((category - b' ') as isize)
+}
+
+/// Return the category set in TABLE that is `equal' to CATEGORY_SET,
+/// registering CATEGORY_SET if there is none yet, so that identical
+/// category sets are shared between characters.
+fn hash_get_category_set(mut table: LispCharTableRef, category_set: LispObject) -> LispObject {
+    if table.get_extra(1).is_nil() {
+        let mut args = [QCtest, Qequal];
+        let hash_table = LispObject::from(unsafe {
+            Fmake_hash_table(args.len() as ptrdiff_t, args.as_mut_ptr())
+        });
+        table.set_extra(1, hash_table);
+    }
+    let hash_table = table.get_extra(1).as_hash_table_or_error();
+    let mut hash: EmacsUint = 0;
+    let idx = hash_table.lookup(category_set, &mut hash);
+    if idx >= 0 {
+        return hash_table.get_hash_key(idx);
+    }
+    hash_table.put(category_set, LispObject::constant_nil(), hash);
+    category_set
+}
+
+/// Return the category set of character C in the current buffer's
+/// category table.  This is what the `CATEGORY_SET` macro expands to.
+#[no_mangle]
+pub extern "C" fn char_category_set(c: c_int) -> Lisp_Object {
+    let table = LispObject::from(ThreadState::current_buffer().category_table);
+    table
+        .as_char_table_or_error()
+        .get(c as Codepoint)
+        .to_raw()
+}
+
+/// Return true if the category set of character C in the current
+/// buffer's category table contains CATEGORY.  This is the lookup
+/// done when matching `\cX' in regular expressions.
+#[no_mangle]
+pub extern "C" fn char_has_category(c: c_int, category: c_int) -> bool {
+    category_member(category as u8, LispObject::from(char_category_set(c)))
+}
+
 /// Return t if ARG is a category table.
 #[lisp_fn]
 fn category_table_p(arg: LispObject) -> LispObject {
@@ -23,4 +136,180 @@ fn category_table() -> LispObject {
     LispObject::from(buffer_ref.category_table)
 }
 
+/// Define CATEGORY as a category which is described by DOCSTRING.
+/// CATEGORY should be an ASCII printing character in the range ` ' to `~'.
+/// DOCSTRING is the documentation string of the category.  The first line
+/// should be a terse text (preferably less than 16 characters),
+/// and the rest lines should be the full description.
+/// The category is defined only in category table TABLE, which defaults to
+/// the current buffer's category table.
+#[lisp_fn(min = "2")]
+fn define_category(category: LispObject, docstring: LispObject, table: LispObject) -> LispObject {
+    let category = check_category(category);
+    docstring.as_string_or_error();
+    let table = check_category_table(table);
+
+    if category_docstring(table, category).is_not_nil() {
+        error!("Category `{}' is already defined", category as char);
+    }
+    let docstring = if LispObject::from(unsafe { globals.f_Vpurify_flag }).is_not_nil() {
+        LispObject::from(unsafe { Fpurecopy(docstring.to_raw()) })
+    } else {
+        docstring
+    };
+    let docstrings = table.get_extra(0).as_vector_or_error();
+    docstrings.set((category - b' ') as isize, docstring);
+
+    LispObject::constant_nil()
+}
+
+extern "C" fn copy_category_entry(table: Lisp_Object, c: Lisp_Object, val: Lisp_Object) {
+    let table = LispObject::from(table).as_char_table_or_error();
+    let val = LispObject::from(unsafe { Fcopy_sequence(val) });
+    let c = LispObject::from(c);
+    if let Some(range) = c.as_cons() {
+        table.set_range(
+            range.car().as_character_or_error(),
+            range.cdr().as_character_or_error(),
+            val,
+        );
+    } else {
+        table.set(c.as_character_or_error(), val);
+    }
+}
+
+/// Return a copy of category table TABLE.  We can't simply use the
+/// function copy-sequence because no contents should be shared between
+/// the original and the copy.
+fn copy_category_table_internal(table: LispCharTableRef) -> LispObject {
+    let copy = LispObject::from(unsafe { copy_char_table(table.as_lisp_obj().to_raw()) });
+    let mut copy_table = copy.as_char_table_or_error();
+
+    if LispObject::from(copy_table.default).is_not_nil() {
+        copy_table.default = unsafe { Fcopy_sequence(copy_table.default) };
+    }
+    let docstrings = copy_table.get_extra(0);
+    copy_table.set_extra(0, LispObject::from(unsafe { Fcopy_sequence(docstrings.to_raw()) }));
+    unsafe { map_char_table(copy_category_entry, Qnil, copy.to_raw(), copy.to_raw()) };
+
+    copy
+}
+
+/// Construct a new category table and return it.
+/// It is a copy of the TABLE, which defaults to the standard category table.
+#[lisp_fn(min = "0")]
+fn copy_category_table(table: LispObject) -> LispObject {
+    let table = if table.is_nil() {
+        LispObject::from(unsafe { buffer_defaults.category_table }).as_char_table_or_error()
+    } else {
+        check_category_table(table)
+    };
+
+    copy_category_table_internal(table)
+}
+
+/// Construct a new and empty category table and return it.
+#[lisp_fn]
+fn make_category_table() -> LispObject {
+    let val = LispObject::from(unsafe { Fmake_char_table(Qcategory_table, Qnil) });
+    let mut table = val.as_char_table_or_error();
+
+    table.default = make_category_set().to_raw();
+    for i in 0..(1 << CHARTAB_SIZE_BITS[0]) {
+        table.contents[i] = make_category_set().to_raw();
+    }
+    unsafe {
+        Fset_char_table_extra_slot(
+            val.to_raw(),
+            LispObject::from_natnum(0).to_raw(),
+            Fmake_vector(LispObject::from_natnum(NUM_CATEGORIES).to_raw(), Qnil),
+        )
+    };
+    val
+}
+
+/// Return the category set of CHAR.
+/// usage: (char-category-set CHAR)
+#[lisp_fn(name = "char-category-set", c_name = "char_category_set")]
+fn char_category_set_lisp(ch: LispObject) -> LispObject {
+    let c = ch.as_character_or_error();
+    LispObject::from(char_category_set(c as c_int))
+}
+
+/// Return a string containing mnemonics of the categories in CATEGORY-SET.
+/// CATEGORY-SET is a bool-vector, and the categories "in" it are those
+/// that are indexes where t occurs in the bool-vector.
+/// The return value is a string containing those same categories.
+#[lisp_fn]
+fn category_set_mnemonics(category_set: LispObject) -> LispObject {
+    if !is_category_set(category_set) {
+        wrong_type!(Qcategorysetp, category_set);
+    }
+
+    let mnemonics: String = (b' '..b'\x7F')
+        .filter(|&c| category_member(c, category_set))
+        .map(|c| c as char)
+        .collect();
+
+    LispObject::from(unsafe {
+        make_unibyte_string(
+            mnemonics.as_ptr() as *const c_char,
+            mnemonics.len() as ptrdiff_t,
+        )
+    })
+}
+
+/// Modify the category set of CHARACTER by adding CATEGORY to it.
+/// The category is changed only for table TABLE, which defaults to
+/// the current buffer's category table.
+/// CHARACTER can be either a single character or a cons representing the
+/// lower and upper ends of an inclusive character range to modify.
+/// CATEGORY must be a category name (a character between ` ' and `~').
+/// Use `describe-categories' to see existing category names.
+/// If optional fourth argument RESET is non-nil,
+/// then delete CATEGORY from the category set instead of adding it.
+#[lisp_fn(min = "2")]
+fn modify_category_entry(
+    character: LispObject,
+    category: LispObject,
+    table: LispObject,
+    reset: LispObject,
+) -> LispObject {
+    let (mut start, end) = if character.is_integer() {
+        let c = character.as_character_or_error();
+        (c, c)
+    } else {
+        let range = character.as_cons_or_error();
+        (
+            range.car().as_character_or_error(),
+            range.cdr().as_character_or_error(),
+        )
+    };
+
+    let category = check_category(category);
+    let table = check_category_table(table);
+
+    if category_docstring(table, category).is_nil() {
+        error!("Undefined category: {}", category as char);
+    }
+
+    let set_value = reset.is_nil();
+
+    while start <= end {
+        let (category_set, _, to) = table.get_with_range(start, start, end);
+        if category_member(category, category_set) != set_value {
+            let new_set = LispObject::from(unsafe { Fcopy_sequence(category_set.to_raw()) });
+            new_set
+                .as_bool_vector()
+                .unwrap()
+                .set(category as usize, set_value);
+            let new_set = hash_get_category_set(table, new_set);
+            table.set_range(start, to, new_set);
+        }
+        start = to + 1;
+    }
+
+    LispObject::constant_nil()
+}
+
 include!(concat!(env!("OUT_DIR"), "/category_exports.rs"));
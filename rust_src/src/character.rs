@@ -1,12 +1,31 @@
 //! Operations on characters.
 
+use libc::{c_int, c_uchar, ptrdiff_t};
+use std::slice;
+
 use remacs_macros::lisp_fn;
-use remacs_sys::EmacsInt;
+use remacs_sys::{buffer_display_table, composition_table, find_composition, get_composition_id,
+                 globals, EmacsInt, Qnil, CHARACTERBITS};
 
-use lisp::LispObject;
+use buffers::LispBufferRef;
+use chartable::LispCharTableRef;
+use grapheme::grapheme_boundaries;
+use lisp::{ExternalPtr, LispObject};
 use lisp::defsubr;
-use multibyte::{make_char_multibyte, raw_byte_from_codepoint_safe};
-use multibyte::MAX_CHAR;
+use multibyte::{make_char_multibyte, multibyte_char_at, raw_byte_from_codepoint_safe,
+                string_overflow, Codepoint};
+use multibyte::{MAX_1_BYTE_CHAR, MAX_CHAR};
+use threads::ThreadState;
+
+/// Same as `MAX_FACE_ID' in dispextern.h.
+const MAX_FACE_ID: EmacsInt = (1 << 20) - 1;
+
+/// The largest glyph code that is an integer.
+const MAX_GLYPH_CODE: EmacsInt = (MAX_FACE_ID << CHARACTERBITS) | MAX_CHAR as EmacsInt;
+
+/// VARIATION SELECTOR-16, which requests the emoji presentation of the
+/// preceding character.
+const EMOJI_PRESENTATION_SELECTOR: Codepoint = 0xFE0F;
 
 /// Return the character of the maximum code.
 #[lisp_fn]
@@ -54,4 +73,250 @@ fn multibyte_char_to_unibyte(ch: LispObject) -> LispObject {
     }
 }
 
+/// Same as `SANE_TAB_WIDTH`.
+fn sane_tab_width(buffer: LispBufferRef) -> EmacsInt {
+    match LispObject::from(buffer.tab_width).as_fixnum() {
+        Some(width) if 0 < width && width <= 1000 => width,
+        _ => 8,
+    }
+}
+
+/// Return the width of character C when displayed in BUFFER, not
+/// considering display tables.  Same as the `CHARACTER_WIDTH' macro:
+/// the widths of non-ASCII characters come from `char-width-table'.
+pub fn character_width(c: Codepoint, buffer: LispBufferRef) -> EmacsInt {
+    let ctl_width = if LispObject::from(buffer.ctl_arrow).is_nil() {
+        4
+    } else {
+        2
+    };
+
+    if c < 0x20 {
+        match c {
+            0x09 => sane_tab_width(buffer),
+            0x0A => 0,
+            _ => ctl_width,
+        }
+    } else if c < 0x7F {
+        1
+    } else if c == MAX_1_BYTE_CHAR {
+        ctl_width
+    } else {
+        let table = LispObject::from(unsafe { globals.f_Vchar_width_table });
+        match table.as_char_table_or_error().get(c).as_fixnum() {
+            Some(width) if 0 <= width && width <= 1000 => width,
+            Some(_) => 1000,
+            None => 1,
+        }
+    }
+}
+
+/// Return the character of GLYPH, an element of a display table
+/// vector, if it is a glyph code: a character with a face ID in the
+/// bits above it, or a cons (CHAR . FACE-ID).  Same as `GLYPH_CODE_P'
+/// and `GLYPH_CODE_CHAR'.
+fn glyph_code_char(glyph: LispObject) -> Option<Codepoint> {
+    match glyph.as_cons() {
+        Some(cons) => match cons.cdr().as_fixnum() {
+            Some(face) if cons.car().is_character() && 0 <= face && face <= MAX_FACE_ID => {
+                Some(cons.car().as_character_or_error())
+            }
+            _ => None,
+        },
+        None => match glyph.as_fixnum() {
+            Some(code) if 0 <= code && code <= MAX_GLYPH_CODE => {
+                Some((code & MAX_CHAR as EmacsInt) as Codepoint)
+            }
+            _ => None,
+        },
+    }
+}
+
+/// Return the width of C considering the display table DP.  Same as
+/// `char_width' in character.c.
+fn char_width(c: Codepoint, dp: Option<LispCharTableRef>, buffer: LispBufferRef) -> EmacsInt {
+    if let Some(disp) = dp.and_then(|dp| dp.get(c).as_vector()) {
+        return disp.as_slice()
+            .iter()
+            .filter_map(|&glyph| glyph_code_char(glyph))
+            .map(|c| character_width(c, buffer))
+            .sum();
+    }
+    character_width(c, buffer)
+}
+
+/// Return the display table of the current buffer, if any.
+fn current_display_table() -> Option<LispCharTableRef> {
+    let dp = unsafe { buffer_display_table() };
+    if dp.is_null() {
+        None
+    } else {
+        Some(ExternalPtr::new(dp))
+    }
+}
+
+/// Return the width of the grapheme cluster CLUSTER: that of its first
+/// character, or two if it requests emoji presentation.
+fn cluster_width(
+    cluster: &[Codepoint],
+    dp: Option<LispCharTableRef>,
+    buffer: LispBufferRef,
+) -> EmacsInt {
+    let width = char_width(cluster[0], dp, buffer);
+    if width < 2 && cluster[1..].contains(&EMOJI_PRESENTATION_SELECTOR) {
+        2
+    } else {
+        width
+    }
+}
+
+/// Return the width of CHARS when displayed in the current buffer,
+/// the sum of the widths of its grapheme clusters, and the number of
+/// characters counted.  COMPOSITION returns the end and the width of
+/// a composition starting at a position, which counts as a unit of
+/// its own.  If PRECISION is positive, stop before the first unit
+/// that would make the width exceed it.
+fn text_width<F>(
+    chars: &[Codepoint],
+    precision: ptrdiff_t,
+    mut composition: F,
+) -> (ptrdiff_t, usize)
+where
+    F: FnMut(usize) -> Option<(usize, ptrdiff_t)>,
+{
+    let buffer = ThreadState::current_buffer();
+    let dp = current_display_table();
+    let boundaries = grapheme_boundaries(chars);
+    let mut next_boundary = 0;
+    let mut width: ptrdiff_t = 0;
+    let mut pos = 0;
+    while pos < chars.len() {
+        let (end, unit_width) = match composition(pos) {
+            Some(found) => found,
+            None => {
+                while boundaries[next_boundary] <= pos {
+                    next_boundary += 1;
+                }
+                let end = boundaries[next_boundary];
+                (end, cluster_width(&chars[pos..end], dp, buffer) as ptrdiff_t)
+            }
+        };
+        if 0 < precision && precision - width < unit_width {
+            break;
+        }
+        width = width.checked_add(unit_width).unwrap_or_else(|| string_overflow());
+        pos = end;
+    }
+    (width, pos)
+}
+
+/// Return the width of the Lisp string STRING when displayed in the
+/// current buffer, with each composition counting as a unit, along
+/// with its length in characters and bytes.  If PRECISION is
+/// positive, return those of the longest prefix of STRING whose width
+/// doesn't exceed PRECISION instead.
+pub fn lisp_string_columns(
+    string: LispObject,
+    precision: ptrdiff_t,
+) -> (ptrdiff_t, ptrdiff_t, ptrdiff_t) {
+    let s = string.as_string_or_error();
+    let (offsets, chars): (Vec<usize>, Vec<Codepoint>) = s.char_indices().unzip();
+    let composition = |pos: usize| {
+        let (mut start, mut end, mut prop) = (0, 0, Qnil);
+        let pos = pos as ptrdiff_t;
+        unsafe {
+            if !find_composition(pos, -1, &mut start, &mut end, &mut prop, string.to_raw()) {
+                return None;
+            }
+            let bytepos = offsets[pos as usize] as ptrdiff_t;
+            let id = get_composition_id(pos, bytepos, end - pos, prop, string.to_raw());
+            if id < 0 {
+                return None;
+            }
+            Some((end as usize, (**composition_table.offset(id)).width as ptrdiff_t))
+        }
+    };
+    let (width, end) = text_width(&chars, precision, composition);
+    let nbytes = offsets.get(end).map_or(s.len_bytes(), |&offset| offset as ptrdiff_t);
+    (width, end as ptrdiff_t, nbytes)
+}
+
+/// Same as `lisp_string_columns', for C: if PRECISION is positive,
+/// set *NCHARS and *NBYTES to the length of the prefix measured.
+#[no_mangle]
+pub extern "C" fn lisp_string_width(
+    string: LispObject,
+    precision: ptrdiff_t,
+    nchars: *mut ptrdiff_t,
+    nbytes: *mut ptrdiff_t,
+) -> ptrdiff_t {
+    let (width, prefix_chars, prefix_bytes) = lisp_string_columns(string, precision);
+    if 0 < precision {
+        unsafe {
+            *nchars = prefix_chars;
+            *nbytes = prefix_bytes;
+        }
+    }
+    width
+}
+
+/// Return the width of the LEN bytes of multibyte text at TEXT when
+/// displayed in the current buffer.  If PRECISION is positive, return
+/// the width of the longest prefix whose width doesn't exceed it, and
+/// set *NCHARS and *NBYTES to its length in characters and bytes.
+#[no_mangle]
+pub extern "C" fn c_string_width(
+    text: *const c_uchar,
+    len: ptrdiff_t,
+    precision: c_int,
+    nchars: *mut ptrdiff_t,
+    nbytes: *mut ptrdiff_t,
+) -> ptrdiff_t {
+    let bytes = unsafe { slice::from_raw_parts(text, len as usize) };
+    let mut offsets = Vec::new();
+    let mut chars = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let (c, length) = multibyte_char_at(&bytes[pos..]);
+        offsets.push(pos);
+        chars.push(c);
+        pos += length;
+    }
+    let (width, end) = text_width(&chars, precision as ptrdiff_t, |_| None);
+    if 0 < precision {
+        unsafe {
+            *nchars = end as ptrdiff_t;
+            *nbytes = offsets.get(end).map_or(len, |&offset| offset as ptrdiff_t);
+        }
+    }
+    width
+}
+
+/// Return width of CHAR when displayed in the current buffer.
+/// The width is measured by how many columns it occupies on the screen.
+/// Wide and fullwidth East Asian characters and emoji occupy two
+/// columns, combining marks none.  Tab is taken to occupy `tab-width'
+/// columns.
+/// usage: (char-width CHAR)
+#[lisp_fn(name = "char-width", c_name = "char_width")]
+fn char_width_lisp(ch: LispObject) -> LispObject {
+    let c = ch.as_character_or_error();
+    let buffer = ThreadState::current_buffer();
+    LispObject::from_natnum(char_width(c, current_display_table(), buffer))
+}
+
+/// Return width of STRING when displayed in the current buffer.
+/// Width is measured by how many columns it occupies on the screen.
+/// Each grapheme cluster, such as a character followed by combining
+/// marks or an emoji ZWJ sequence, takes as many columns as its first
+/// character, or two if it requests emoji presentation, and each
+/// composition as many as its glyphs.  Tabs in STRING are always taken
+/// to occupy `tab-width' columns.
+/// usage: (string-width STRING)
+#[lisp_fn]
+fn string_width(string: LispObject) -> LispObject {
+    let (width, _, _) = lisp_string_columns(string, -1);
+    LispObject::from_natnum(width as EmacsInt)
+}
+
 include!(concat!(env!("OUT_DIR"), "/character_exports.rs"));
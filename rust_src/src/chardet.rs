@@ -0,0 +1,527 @@
+//! Statistical detection of the coding system of 8-bit text.
+//!
+//! Each candidate encoding has a model of the text typically found in
+//! it: the text is split into characters according to the encoding's
+//! byte structure, and the non-ASCII characters are sorted into a few
+//! classes (e.g. hiragana, level 1 kanji and punctuation for Japanese)
+//! with a typical frequency each.  Taking the characters of a class as
+//! equally likely, this gives the probability of the whole text under
+//! each model.  The confidence of an encoding is then its posterior
+//! probability among all models, plus a model of random bytes that
+//! stands for binary data and encodings we don't know about.
+
+use libc::{c_uchar, ptrdiff_t};
+use std::f64;
+use std::slice;
+
+use remacs_sys::Lisp_Object;
+
+use coding::utf_8_sequence_length;
+use lisp::{intern, LispObject};
+use multibyte::{multibyte_char_at, raw_byte_from_codepoint, Codepoint, MAX_5_BYTE_CHAR};
+
+/// The result of looking at a character which starts with a non-ASCII
+/// byte.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Step {
+    /// A valid character of the given class and byte length.
+    Char(usize, usize),
+    /// An invalid byte.
+    Invalid,
+    /// The text ends in the middle of a character.
+    Truncated,
+}
+
+/// A character class: its typical relative frequency among the
+/// non-ASCII characters of a text, and the number of characters in
+/// it.
+type Class = (f64, f64);
+
+/// A model of the text in one ASCII-compatible encoding.
+struct Model {
+    coding_system: &'static str,
+    /// The classes of non-ASCII characters; their frequencies add up
+    /// to 1.
+    classes: &'static [Class],
+    /// Classify the character starting at the given position.
+    step: fn(&[u8], usize) -> Step,
+}
+
+const UTF_8_CLASSES: [Class; 3] = [(0.50, 1920.0), (0.49, 30000.0), (0.01, 1_048_576.0)];
+
+/// Classes of non-ASCII characters in Japanese text.
+const JA_HIRAGANA: usize = 0;
+const JA_KATAKANA: usize = 1;
+const JA_PUNCT: usize = 2;
+const JA_KANJI_1: usize = 3;
+const JA_KANJI_2: usize = 4;
+const JA_FULLWIDTH: usize = 5;
+const JA_HALFWIDTH_KANA: usize = 6;
+const JA_OTHER: usize = 7;
+const JAPANESE: [Class; 8] = [
+    (0.40, 83.0),
+    (0.12, 86.0),
+    (0.12, 108.0),
+    (0.30, 2965.0),
+    (0.03, 3390.0),
+    (0.01, 62.0),
+    (0.005, 63.0),
+    (0.015, 8000.0),
+];
+
+/// Classes of non-ASCII characters in simplified Chinese text.
+const ZH_HANZI_1: usize = 0;
+const ZH_HANZI_2: usize = 1;
+const ZH_PUNCT: usize = 2;
+const ZH_FULLWIDTH: usize = 3;
+const ZH_OTHER: usize = 4;
+const CHINESE: [Class; 5] = [
+    (0.76, 3755.0),
+    (0.06, 3008.0),
+    (0.11, 94.0),
+    (0.06, 94.0),
+    (0.01, 20000.0),
+];
+
+/// Classes of non-ASCII characters in Western European text.
+const WE_LOWER: usize = 0;
+const WE_UPPER: usize = 1;
+const WE_PUNCT: usize = 2;
+const WE_SYMBOL: usize = 3;
+/// A byte in a run of three or more non-ASCII bytes, which is typical
+/// of multibyte encodings but rare in Western text.
+const WE_RUN: usize = 4;
+const WESTERN: [Class; 5] = [
+    (0.72, 35.0),
+    (0.06, 34.0),
+    (0.17, 13.0),
+    (0.04, 40.0),
+    (0.01, 123.0),
+];
+
+static MODELS: [Model; 5] = [
+    Model {
+        coding_system: "utf-8",
+        classes: &UTF_8_CLASSES,
+        step: step_utf_8,
+    },
+    Model {
+        coding_system: "windows-1252",
+        classes: &WESTERN,
+        step: step_windows_1252,
+    },
+    Model {
+        coding_system: "shift_jis",
+        classes: &JAPANESE,
+        step: step_shift_jis,
+    },
+    Model {
+        coding_system: "euc-jp",
+        classes: &JAPANESE,
+        step: step_euc_jp,
+    },
+    Model {
+        coding_system: "gb18030",
+        classes: &CHINESE,
+        step: step_gb18030,
+    },
+];
+
+/// Classes of UTF-16 code units.
+const UTF_16_CLASSES: [Class; 10] = [
+    // ASCII
+    (0.40, 98.0),
+    // Latin
+    (0.05, 944.0),
+    // Greek, Cyrillic, Hebrew, Arabic, Indic and Thai
+    (0.05, 3400.0),
+    // Punctuation and symbols
+    (0.03, 672.0),
+    // Kana
+    (0.15, 192.0),
+    // CJK ideographs
+    (0.20, 20992.0),
+    // CJK punctuation and fullwidth forms
+    (0.05, 304.0),
+    // Hangul
+    (0.05, 11172.0),
+    // Surrogate pairs
+    (0.01, 1_048_576.0),
+    // Anything else, including control characters
+    (0.01, 65536.0),
+];
+
+/// Log-probability of a printable ASCII character in an
+/// ASCII-compatible encoding, where three out of four characters are
+/// taken to be ASCII.
+const ASCII_LOG_PROB: f64 = -4.872_649_551_122_353; // ln(3/4 * 1/98)
+/// Log-probability that a character is non-ASCII, to be added to that
+/// of its class.
+const NON_ASCII_LOG_PROB: f64 = -1.386_294_361_119_890_6; // ln(1/4)
+/// Log-probability of a random byte.
+const BYTE_LOG_PROB: f64 = -5.545_177_444_479_562; // ln(1/256)
+/// Log-probability of an invalid byte or an ASCII control character
+/// in text; a few are tolerated, but not many.
+const INVALID_LOG_PROB: f64 = BYTE_LOG_PROB - 6.907_755_278_982_137; // ln(1/256 * 1/1000)
+/// Log-probability that the text ends in the middle of a character.
+const TRUNCATED_LOG_PROB: f64 = -2.302_585_092_994_045_5; // ln(1/10)
+/// Log-probability of a byte of data that isn't text in any of the
+/// encodings we know, which is taken to be less likely than text.
+const RANDOM_LOG_PROB: f64 = BYTE_LOG_PROB - 0.105_360_515_657_826_28; // ln(1/256 * 9/10)
+
+fn class_log_prob(&(frequency, size): &Class) -> f64 {
+    (frequency / size).ln()
+}
+
+fn in_range(byte: u8, low: u8, high: u8) -> bool {
+    low <= byte && byte <= high
+}
+
+fn step_utf_8(text: &[u8], pos: usize) -> Step {
+    let rest = &text[pos..];
+    if let Some(len) = utf_8_sequence_length(rest) {
+        return Step::Char(if len < 4 { len - 2 } else { 2 }, len);
+    }
+    let len = match rest[0] {
+        0xC2...0xDF => 2,
+        0xE0...0xEF => 3,
+        0xF0...0xF7 => 4,
+        0xF8 => 5,
+        _ => return Step::Invalid,
+    };
+    if rest.len() < len && rest[1..].iter().all(|&byte| byte & 0xC0 == 0x80) {
+        Step::Truncated
+    } else {
+        Step::Invalid
+    }
+}
+
+fn step_windows_1252(text: &[u8], pos: usize) -> Step {
+    let high = |idx: Option<usize>| {
+        idx.and_then(|idx| text.get(idx))
+            .map_or(false, |&byte| byte >= 0x80)
+    };
+    let (prev, next) = (pos.checked_sub(1), Some(pos + 1));
+    let in_run = (high(prev) && high(next)) || (high(prev) && high(pos.checked_sub(2)))
+        || (high(next) && high(Some(pos + 2)));
+    let byte = text[pos];
+    let class = match byte {
+        0x81 | 0x8D | 0x8F | 0x90 | 0x9D => return Step::Invalid,
+        _ if in_run => WE_RUN,
+        0xDF...0xFF if byte != 0xF7 => WE_LOWER,
+        0x9A | 0x9C | 0x9E => WE_LOWER,
+        0xC0...0xDE if byte != 0xD7 => WE_UPPER,
+        0x8A | 0x8C | 0x8E | 0x9F => WE_UPPER,
+        0x80 | 0x85 | 0x91...0x94 | 0x96 | 0x97 | 0xA0 | 0xAB | 0xB0 | 0xBB => WE_PUNCT,
+        _ => WE_SYMBOL,
+    };
+    Step::Char(class, 1)
+}
+
+fn step_shift_jis(text: &[u8], pos: usize) -> Step {
+    let lead = text[pos];
+    if in_range(lead, 0xA1, 0xDF) {
+        return Step::Char(JA_HALFWIDTH_KANA, 1);
+    }
+    if !(in_range(lead, 0x81, 0x9F) || in_range(lead, 0xE0, 0xFC)) {
+        return Step::Invalid;
+    }
+    let trail = match text.get(pos + 1) {
+        Some(&trail) => trail,
+        None => return Step::Truncated,
+    };
+    if !(in_range(trail, 0x40, 0x7E) || in_range(trail, 0x80, 0xFC)) {
+        return Step::Invalid;
+    }
+    let code = (u16::from(lead) << 8) | u16::from(trail);
+    let class = match code {
+        0x829F...0x82F1 => JA_HIRAGANA,
+        0x8340...0x8396 => JA_KATAKANA,
+        0x8140...0x81AC => JA_PUNCT,
+        0x824F...0x829A => JA_FULLWIDTH,
+        0x889F...0x9872 => JA_KANJI_1,
+        0x989F...0x9FFC | 0xE040...0xEAA4 => JA_KANJI_2,
+        _ => JA_OTHER,
+    };
+    Step::Char(class, 2)
+}
+
+fn step_euc_jp(text: &[u8], pos: usize) -> Step {
+    let lead = text[pos];
+    let len = match lead {
+        0x8E => 2,
+        0x8F => 3,
+        0xA1...0xFE => 2,
+        _ => return Step::Invalid,
+    };
+    if text.len() < pos + len {
+        return if text[pos + 1..]
+            .iter()
+            .all(|&byte| in_range(byte, 0xA1, 0xFE))
+        {
+            Step::Truncated
+        } else {
+            Step::Invalid
+        };
+    }
+    let trail = &text[pos + 1..pos + len];
+    if lead == 0x8E {
+        return if in_range(trail[0], 0xA1, 0xDF) {
+            Step::Char(JA_HALFWIDTH_KANA, 2)
+        } else {
+            Step::Invalid
+        };
+    }
+    if !trail.iter().all(|&byte| in_range(byte, 0xA1, 0xFE)) {
+        return Step::Invalid;
+    }
+    let class = match lead {
+        // JIS X 0212 supplementary kanji.
+        0x8F => JA_OTHER,
+        0xA4 => JA_HIRAGANA,
+        0xA5 => JA_KATAKANA,
+        0xA1 => JA_PUNCT,
+        0xA3 => JA_FULLWIDTH,
+        0xB0...0xCF => JA_KANJI_1,
+        0xD0...0xF4 => JA_KANJI_2,
+        _ => JA_OTHER,
+    };
+    Step::Char(class, len)
+}
+
+fn step_gb18030(text: &[u8], pos: usize) -> Step {
+    let lead = text[pos];
+    if !in_range(lead, 0x81, 0xFE) {
+        return Step::Invalid;
+    }
+    let second = match text.get(pos + 1) {
+        Some(&second) => second,
+        None => return Step::Truncated,
+    };
+    if in_range(second, 0x30, 0x39) {
+        // A four-byte sequence, for characters beyond GBK.
+        return match (text.get(pos + 2), text.get(pos + 3)) {
+            (Some(&third), Some(&fourth)) => {
+                if in_range(third, 0x81, 0xFE) && in_range(fourth, 0x30, 0x39) {
+                    Step::Char(ZH_OTHER, 4)
+                } else {
+                    Step::Invalid
+                }
+            }
+            _ => Step::Truncated,
+        };
+    }
+    if !(in_range(second, 0x40, 0x7E) || in_range(second, 0x80, 0xFE)) {
+        return Step::Invalid;
+    }
+    let class = if second < 0xA1 {
+        // GBK extensions outside of GB2312.
+        ZH_OTHER
+    } else {
+        match lead {
+            0xB0...0xD7 => ZH_HANZI_1,
+            0xD8...0xF7 => ZH_HANZI_2,
+            0xA1 => ZH_PUNCT,
+            0xA3 => ZH_FULLWIDTH,
+            _ => ZH_OTHER,
+        }
+    };
+    Step::Char(class, 2)
+}
+
+/// Return the log-probability of TEXT under MODEL.
+fn model_log_prob(model: &Model, text: &[u8]) -> f64 {
+    let mut log_prob = 0.0;
+    let mut pos = 0;
+    while pos < text.len() {
+        if text[pos] < 0x80 {
+            log_prob += match text[pos] {
+                b'\t' | b'\n' | b'\r' | 0x20...0x7E => ASCII_LOG_PROB,
+                _ => INVALID_LOG_PROB,
+            };
+            pos += 1;
+            continue;
+        }
+        match (model.step)(text, pos) {
+            Step::Char(class, len) => {
+                log_prob += NON_ASCII_LOG_PROB + class_log_prob(&model.classes[class]);
+                pos += len;
+            }
+            Step::Invalid => {
+                log_prob += INVALID_LOG_PROB;
+                pos += 1;
+            }
+            Step::Truncated => {
+                log_prob += TRUNCATED_LOG_PROB + BYTE_LOG_PROB * (text.len() - pos) as f64;
+                break;
+            }
+        }
+    }
+    log_prob
+}
+
+fn utf_16_unit_class(unit: Codepoint) -> usize {
+    match unit {
+        0x09 | 0x0A | 0x0D | 0x20...0x7E => 0,
+        0xA0...0x24F | 0x1E00...0x1FFF => 1,
+        0x370...0x52F | 0x590...0x6FF | 0x900...0xDFF => 2,
+        0x2000...0x206F | 0x20A0...0x20CF | 0x2100...0x22FF => 3,
+        0x3040...0x30FF => 4,
+        0x4E00...0x9FFF => 5,
+        0x3000...0x303F | 0xFF00...0xFFEF => 6,
+        0xAC00...0xD7A3 => 7,
+        _ => 9,
+    }
+}
+
+/// Return the log-probability of TEXT as UTF-16 without a BOM.
+fn utf_16_log_prob(text: &[u8], big_endian: bool) -> f64 {
+    let units: Vec<Codepoint> = text
+        .chunks(2)
+        .filter(|pair| pair.len() == 2)
+        .map(|pair| {
+            let (high, low) = if big_endian {
+                (pair[0], pair[1])
+            } else {
+                (pair[1], pair[0])
+            };
+            (Codepoint::from(high) << 8) | Codepoint::from(low)
+        })
+        .collect();
+    let is_high = |unit: Codepoint| unit >= 0xD800 && unit < 0xDC00;
+    let is_low = |unit: Codepoint| unit >= 0xDC00 && unit < 0xE000;
+    // A trailing odd byte.
+    let mut log_prob = if text.len() % 2 == 0 {
+        0.0
+    } else {
+        TRUNCATED_LOG_PROB + BYTE_LOG_PROB
+    };
+    let mut idx = 0;
+    while idx < units.len() {
+        let class = if is_high(units[idx]) && idx + 1 < units.len() && is_low(units[idx + 1]) {
+            idx += 1;
+            8
+        } else {
+            utf_16_unit_class(units[idx])
+        };
+        log_prob += class_log_prob(&UTF_16_CLASSES[class]);
+        idx += 1;
+    }
+    log_prob
+}
+
+/// Return the candidate coding systems for TEXT with their confidence
+/// between 0 and 1, best first.  Candidates with a negligible
+/// confidence are left out.  Return None if TEXT is plain ASCII.
+pub fn detect(text: &[u8]) -> Option<Vec<(&'static str, f64)>> {
+    if text.iter().all(|&byte| byte != 0 && byte < 0x80) {
+        return None;
+    }
+    let mut ranked: Vec<(&'static str, f64)> = MODELS
+        .iter()
+        .map(|model| (model.coding_system, model_log_prob(model, text)))
+        .collect();
+    ranked.push(("utf-16le", utf_16_log_prob(text, false)));
+    ranked.push(("utf-16be", utf_16_log_prob(text, true)));
+    let random = RANDOM_LOG_PROB * text.len() as f64;
+
+    // Normalize the probabilities to posteriors, avoiding underflow.
+    let max = ranked
+        .iter()
+        .fold(random, |max, &(_, log_prob)| f64::max(max, log_prob));
+    let total = ranked
+        .iter()
+        .fold((random - max).exp(), |total, &(_, log_prob)| {
+            total + (log_prob - max).exp()
+        });
+    for candidate in &mut ranked {
+        candidate.1 = (candidate.1 - max).exp() / total;
+    }
+    ranked.retain(|&(_, confidence)| confidence >= 0.001);
+    // A stable sort keeps the order of MODELS for ties.
+    ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+    Some(ranked)
+}
+
+/// Return the bytes that the multibyte TEXT would have in a file:
+/// raw 8-bit characters are converted back to bytes, everything else
+/// is kept in the internal representation.
+fn multibyte_text_bytes(text: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut idx = 0;
+    while idx < text.len() {
+        let (cp, len) = multibyte_char_at(&text[idx..]);
+        if cp > MAX_5_BYTE_CHAR {
+            bytes.push(raw_byte_from_codepoint(cp));
+        } else {
+            bytes.extend_from_slice(&text[idx..idx + len]);
+        }
+        idx += len;
+    }
+    bytes
+}
+
+/// Detect the coding system of the NBYTES bytes at SRC statistically,
+/// which are multibyte text if MULTIBYTE.  Return an alist of
+/// candidate coding systems and their confidence, a float between 0
+/// and 1, best first.  Plain ASCII text gives `((undecided . 1.0))'.
+#[no_mangle]
+pub extern "C" fn detect_coding_confidence(
+    src: *const c_uchar,
+    nbytes: ptrdiff_t,
+    multibyte: bool,
+) -> Lisp_Object {
+    let text = unsafe { slice::from_raw_parts(src, nbytes as usize) };
+    let ranked = if multibyte {
+        detect(&multibyte_text_bytes(text))
+    } else {
+        detect(text)
+    };
+    let ranked = ranked.unwrap_or_else(|| vec![("undecided", 1.0)]);
+    ranked
+        .iter()
+        .rev()
+        .fold(LispObject::constant_nil(), |list, &(name, confidence)| {
+            let entry = LispObject::cons(intern(name), LispObject::from_float(confidence));
+            LispObject::cons(entry, list)
+        })
+        .to_raw()
+}
+
+#[test]
+fn test_detect_corpus() {
+    use std::fs::{read_dir, File};
+    use std::io::Read;
+
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/data/chardet");
+    for entry in read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().map_or(true, |extension| extension != "txt") {
+            continue;
+        }
+        // Files are named LANGUAGE.CODING-SYSTEM.txt.
+        let name = path.file_stem().unwrap().to_str().unwrap().to_string();
+        let expected = name.splitn(2, '.').nth(1).unwrap();
+        let mut text = Vec::new();
+        File::open(&path).unwrap().read_to_end(&mut text).unwrap();
+        let ranked = detect(&text).unwrap();
+        assert_eq!(ranked[0].0, expected, "{}: {:?}", name, ranked);
+        assert!(ranked[0].1 >= 0.95, "{}: {:?}", name, ranked);
+    }
+}
+
+#[test]
+fn test_detect_short_texts() {
+    assert_eq!(detect(b"plain ASCII"), None);
+    assert_eq!(detect(b"caf\xC3\xA9").unwrap()[0].0, "utf-8");
+    assert_eq!(detect(b"caf\xE9").unwrap()[0].0, "windows-1252");
+    assert_eq!(detect(b"\xE2\x80\x9Cquoted\xE2\x80\x9D").unwrap()[0].0, "utf-8");
+    assert_eq!(detect(b"\x93quoted\x94").unwrap()[0].0, "windows-1252");
+    // A truncated sequence at the end doesn't count as invalid.
+    assert_eq!(detect(b"caf\xC3\xA9 \xE2\x82").unwrap()[0].0, "utf-8");
+    assert_eq!(detect(b"a\x00b\x00c\x00").unwrap()[0].0, "utf-16le");
+    assert_eq!(detect(b"\x00a\x00b\x00c").unwrap()[0].0, "utf-16be");
+    // Random binary data matches no encoding well.
+    let binary: Vec<u8> = (0..64u32).map(|i| (i * 97 % 256) as u8).collect();
+    assert_eq!(detect(&binary), Some(vec![]));
+}
@@ -1,12 +1,167 @@
 //! char table related functions
 
+use libc::c_int;
+use std::ptr;
+
 use remacs_macros::lisp_fn;
-use remacs_sys::Lisp_Char_Table;
+use remacs_sys::{Lisp_Char_Table, Lisp_Sub_Char_Table, Lisp_Type, PseudovecType,
+                 Qchar_code_property_table, PSEUDOVECTOR_SIZE_MASK};
+use remacs_sys::{char_table_ref, char_table_ref_and_range, char_table_set, char_table_set_range};
 
 use lisp::{ExternalPtr, LispObject};
 use lisp::defsubr;
+use multibyte::{Codepoint, MAX_1_BYTE_CHAR, MAX_CHAR};
 
 pub type LispCharTableRef = ExternalPtr<Lisp_Char_Table>;
+pub type LispSubCharTableRef = ExternalPtr<Lisp_Sub_Char_Table>;
+
+/// Number of bits used to index the contents of a char-table (depth 0)
+/// and of sub char-tables of depth 1, 2 and 3.
+pub const CHARTAB_SIZE_BITS: [u32; 4] = [6, 4, 5, 7];
+
+/// Number of characters each element of a (sub) char-table of a given
+/// depth covers.  Same as `chartab_chars` in chartab.c.
+const CHARTAB_CHARS: [Codepoint; 4] = [1 << 16, 1 << 12, 1 << 7, 1];
+
+/// Number of bits to shift a character to get its index in a (sub)
+/// char-table of a given depth.  Same as `chartab_bits` in chartab.c.
+const CHARTAB_BITS: [u32; 4] = [16, 12, 7, 0];
+
+/// The number of slots every char-table has before its extra slots:
+/// defalt, parent, purpose, ascii and the contents.
+const CHAR_TABLE_STANDARD_SLOTS: usize = 4 + (1 << 6);
+
+/// Same as the `CHARTAB_IDX` macro.
+#[inline]
+fn chartab_idx(c: Codepoint, depth: usize, min_char: Codepoint) -> usize {
+    ((c - min_char) >> CHARTAB_BITS[depth]) as usize
+}
+
+impl LispObject {
+    pub fn is_sub_char_table(self) -> bool {
+        self.as_vectorlike().map_or(false, |v| {
+            v.is_pseudovector(PseudovecType::PVEC_SUB_CHAR_TABLE)
+        })
+    }
+
+    pub fn as_sub_char_table(self) -> Option<LispSubCharTableRef> {
+        if self.is_sub_char_table() {
+            Some(LispSubCharTableRef::new(self.get_untaggedptr() as *mut _))
+        } else {
+            None
+        }
+    }
+}
+
+impl LispCharTableRef {
+    pub fn as_lisp_obj(self) -> LispObject {
+        LispObject::tag_ptr(self, Lisp_Type::Lisp_Vectorlike)
+    }
+
+    /// Return the number of extra slots of this char-table.
+    pub fn extra_slots(&self) -> usize {
+        (self.header.size & PSEUDOVECTOR_SIZE_MASK) as usize - CHAR_TABLE_STANDARD_SLOTS
+    }
+
+    pub fn get_extra(&self, idx: usize) -> LispObject {
+        assert!(idx < self.extra_slots());
+        LispObject::from(unsafe { ptr::read(self.extras.as_ptr().offset(idx as isize)) })
+    }
+
+    pub fn set_extra(&mut self, idx: usize, value: LispObject) {
+        assert!(idx < self.extra_slots());
+        unsafe {
+            ptr::write(
+                self.extras.as_mut_ptr().offset(idx as isize),
+                value.to_raw(),
+            )
+        };
+    }
+
+    /// Return true if this is a char-table of Unicode character
+    /// properties, whose contents may be stored in compressed form.
+    /// Same as the `UNIPROP_TABLE_P` macro.
+    pub fn is_uniprop(&self) -> bool {
+        LispObject::from(self.purpose).eq(LispObject::from(Qchar_code_property_table))
+            && self.extra_slots() == 5
+    }
+
+    /// Return the value for character C, inheriting from the default
+    /// value and the parent table as necessary.  Same as the
+    /// `CHAR_TABLE_REF` macro, but without going through C unless the
+    /// table holds compressed Unicode property data.
+    pub fn get(self, c: Codepoint) -> LispObject {
+        let mut val = if c <= MAX_1_BYTE_CHAR {
+            let ascii = LispObject::from(self.ascii);
+            ascii.as_sub_char_table().map_or(ascii, |sub| sub.get_contents(c as usize))
+        } else if self.is_uniprop() {
+            return LispObject::from(unsafe {
+                char_table_ref(self.as_lisp_obj().to_raw(), c as c_int)
+            });
+        } else {
+            let val = LispObject::from(self.contents[chartab_idx(c, 0, 0)]);
+            val.as_sub_char_table().map_or(val, |sub| sub.get(c))
+        };
+
+        if val.is_nil() {
+            val = LispObject::from(self.default);
+            if val.is_nil() {
+                if let Some(parent) = LispObject::from(self.parent).as_char_table() {
+                    val = parent.get(c);
+                }
+            }
+        }
+        val
+    }
+
+    /// Return the value for character C, together with the range of
+    /// characters around C, clipped to FROM..TO, that share this value.
+    pub fn get_with_range(
+        self,
+        c: Codepoint,
+        from: Codepoint,
+        to: Codepoint,
+    ) -> (LispObject, Codepoint, Codepoint) {
+        let mut from = from as c_int;
+        let mut to = to as c_int;
+        let val = unsafe {
+            char_table_ref_and_range(self.as_lisp_obj().to_raw(), c as c_int, &mut from, &mut to)
+        };
+        (LispObject::from(val), from as Codepoint, to as Codepoint)
+    }
+
+    pub fn set(self, c: Codepoint, val: LispObject) {
+        debug_assert!(c <= MAX_CHAR);
+        unsafe { char_table_set(self.as_lisp_obj().to_raw(), c as c_int, val.to_raw()) };
+    }
+
+    pub fn set_range(self, from: Codepoint, to: Codepoint, val: LispObject) {
+        debug_assert!(from <= to && to <= MAX_CHAR);
+        unsafe {
+            char_table_set_range(
+                self.as_lisp_obj().to_raw(),
+                from as c_int,
+                to as c_int,
+                val.to_raw(),
+            )
+        };
+    }
+}
+
+impl LispSubCharTableRef {
+    #[inline]
+    fn get_contents(self, idx: usize) -> LispObject {
+        LispObject::from(unsafe { ptr::read(self.contents.as_ptr().offset(idx as isize)) })
+    }
+
+    /// Same as `sub_char_table_ref` for tables that are not Unicode
+    /// property tables.
+    fn get(self, c: Codepoint) -> LispObject {
+        let idx = chartab_idx(c, self.depth as usize, self.min_char as Codepoint);
+        let val = self.get_contents(idx);
+        val.as_sub_char_table().map_or(val, |sub| sub.get(c))
+    }
+}
 
 /// Return the subtype of char-table CHARTABLE.  The value is a symbol.
 #[lisp_fn]
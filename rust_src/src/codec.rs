@@ -0,0 +1,352 @@
+//! Machinery shared by the binary-to-text codecs.
+//!
+//! The base64, base32, hex and quoted-printable codecs are written as
+//! incremental encoders and decoders, which are fed one byte at a time.
+//! This module runs them over strings and buffer regions; a region is
+//! read directly from both sides of the gap.
+
+use libc::{c_char, ptrdiff_t};
+
+use remacs_sys::{buf_charpos_to_bytepos, del_range_both, insert_1_both, make_unibyte_string,
+                 set_point, temp_set_point_both, EmacsInt, Lisp_Object};
+
+use buffers::validate_region;
+use lisp::LispObject;
+use multibyte::{multibyte_char_at, raw_byte_codepoint, raw_byte_from_codepoint,
+                write_codepoints, LispStringRef, MAX_5_BYTE_CHAR};
+use threads::ThreadState;
+
+pub trait Encoder {
+    /// Encode BYTE, appending the output which is complete to OUT.
+    fn push(&mut self, byte: u8, out: &mut Vec<u8>);
+
+    /// Append the rest of the output to OUT at the end of the input.
+    fn finish(self, out: &mut Vec<u8>);
+}
+
+pub trait Decoder {
+    /// Decode BYTE, appending the output which is complete to OUT.
+    /// Return None if the input is invalid.
+    fn push(&mut self, byte: u8, out: &mut Output) -> Option<()>;
+
+    /// Append the rest of the output to OUT at the end of the input.
+    /// Return None if the input is incomplete.
+    fn finish(self, out: &mut Output) -> Option<()>;
+}
+
+/// The text produced by a decoder, in unibyte or multibyte form.
+pub struct Output {
+    pub bytes: Vec<u8>,
+    pub nchars: usize,
+    multibyte: bool,
+}
+
+impl Output {
+    pub fn new(multibyte: bool) -> Output {
+        Output {
+            bytes: Vec::new(),
+            nchars: 0,
+            multibyte,
+        }
+    }
+
+    /// Append the decoded BYTE.  In multibyte form, a non-ASCII byte is
+    /// stored as a raw 8-bit character.
+    pub fn push(&mut self, byte: u8) {
+        if self.multibyte && byte >= 0x80 {
+            write_codepoints(&mut self.bytes, Some(raw_byte_codepoint(byte)));
+        } else {
+            self.bytes.push(byte);
+        }
+        self.nchars += 1;
+    }
+
+    /// Append BYTE of the input text, which the decoder passes through
+    /// unchanged.
+    pub fn push_literal(&mut self, byte: u8) {
+        self.bytes.push(byte);
+        if !self.multibyte || byte & 0xC0 != 0x80 {
+            self.nchars += 1;
+        }
+    }
+}
+
+/// Call F with each byte of TEXT.  In multibyte TEXT, raw 8-bit
+/// characters stand for themselves, and the other characters must be
+/// Latin-1.  Return None if some character is not, or if F does.
+fn for_each_byte<F>(text: &[u8], multibyte: bool, mut f: F) -> Option<()>
+where
+    F: FnMut(u8) -> Option<()>,
+{
+    if !multibyte {
+        for &byte in text {
+            f(byte)?;
+        }
+        return Some(());
+    }
+    let mut idx = 0;
+    while idx < text.len() {
+        let (cp, len) = multibyte_char_at(&text[idx..]);
+        if cp > MAX_5_BYTE_CHAR {
+            f(raw_byte_from_codepoint(cp))?;
+        } else if cp < 256 {
+            f(cp as u8)?;
+        } else {
+            return None;
+        }
+        idx += len;
+    }
+    Some(())
+}
+
+/// Encode the text split into PARTS with ENCODER.  Return None if the
+/// text is MULTIBYTE and has characters which aren't bytes.
+pub fn encode<E: Encoder>(mut encoder: E, parts: &[&[u8]], multibyte: bool) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(parts.iter().map(|part| part.len()).sum::<usize>());
+    for part in parts {
+        // The gap never splits a character.
+        for_each_byte(part, multibyte, |byte| {
+            encoder.push(byte, &mut out);
+            Some(())
+        })?;
+    }
+    encoder.finish(&mut out);
+    Some(out)
+}
+
+/// Decode the text split into PARTS with DECODER.  If MULTIBYTE, the
+/// text is multibyte and so is the output.  Return None if the text
+/// is invalid.
+pub fn decode<D: Decoder>(mut decoder: D, parts: &[&[u8]], multibyte: bool) -> Option<Output> {
+    let mut out = Output::new(multibyte);
+    for part in parts {
+        for &byte in *part {
+            decoder.push(byte, &mut out)?;
+        }
+    }
+    decoder.finish(&mut out)?;
+    Some(out)
+}
+
+/// Return the multibyte form of the raw BYTES, with 8-bit bytes as
+/// raw 8-bit characters.
+pub fn bytes_to_multibyte(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Output::new(true);
+    for &byte in bytes {
+        out.push(byte);
+    }
+    out.bytes
+}
+
+pub fn make_unibyte(bytes: &[u8]) -> LispObject {
+    unsafe {
+        LispObject::from(make_unibyte_string(
+            bytes.as_ptr() as *const c_char,
+            bytes.len() as ptrdiff_t,
+        ))
+    }
+}
+
+/// Encode STRING with ENCODER and return the result as a unibyte
+/// string.  NAME is the name of the encoding for error messages.
+pub fn encode_string<E: Encoder>(string: LispObject, encoder: E, name: &str) -> LispObject {
+    let string = string.as_string_or_error();
+    match encode(encoder, &[string.as_slice()], string.is_multibyte()) {
+        Some(encoded) => make_unibyte(&encoded),
+        None => error!("Multibyte character in data for {} encoding", name),
+    }
+}
+
+/// Return the bytes of STRING, or None if it is multibyte and has
+/// characters which aren't bytes.
+pub fn string_bytes(string: LispStringRef) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(string.len_bytes() as usize);
+    for_each_byte(string.as_slice(), string.is_multibyte(), |byte| {
+        bytes.push(byte);
+        Some(())
+    })?;
+    Some(bytes)
+}
+
+/// Decode STRING with DECODER and return the result as a unibyte
+/// string.  NAME is the name of the encoding for error messages.
+pub fn decode_string<D: Decoder>(string: LispObject, decoder: D, name: &str) -> LispObject {
+    let string = string.as_string_or_error();
+    match string_bytes(string).and_then(|bytes| decode(decoder, &[&bytes], false)) {
+        Some(decoded) => make_unibyte(&decoded.bytes),
+        None => error!("Invalid {} data", name),
+    }
+}
+
+/// Validate the region between BEG and END of the current buffer, and
+/// return its bounds as character and byte positions.
+pub fn region_bounds(
+    beg: LispObject,
+    end: LispObject,
+) -> (ptrdiff_t, ptrdiff_t, ptrdiff_t, ptrdiff_t) {
+    let mut beg_raw: Lisp_Object = beg.to_raw();
+    let mut end_raw: Lisp_Object = end.to_raw();
+    validate_region(&mut beg_raw, &mut end_raw);
+    let beg = LispObject::from(beg_raw).as_fixnum_or_error() as ptrdiff_t;
+    let end = LispObject::from(end_raw).as_fixnum_or_error() as ptrdiff_t;
+    let buffer = ThreadState::current_buffer();
+    unsafe {
+        (
+            beg,
+            buf_charpos_to_bytepos(buffer.as_ptr(), beg),
+            end,
+            buf_charpos_to_bytepos(buffer.as_ptr(), end),
+        )
+    }
+}
+
+/// Replace the text of the current buffer between BEG and END with
+/// TEXT, which is NCHARS characters long, and return NCHARS.
+pub fn replace_region(
+    (beg, beg_byte, end, end_byte): (ptrdiff_t, ptrdiff_t, ptrdiff_t, ptrdiff_t),
+    text: &[u8],
+    nchars: ptrdiff_t,
+) -> LispObject {
+    let mut buffer = ThreadState::current_buffer();
+    let old_pos = buffer.pt();
+    let nbytes = text.len() as ptrdiff_t;
+
+    // Insert first in order to preserve markers, then delete the
+    // original text.
+    unsafe {
+        temp_set_point_both(buffer.as_mut(), beg, beg_byte);
+        insert_1_both(text.as_ptr() as *const c_char, nchars, nbytes, false, true, false);
+        del_range_both(
+            beg + nchars,
+            beg_byte + nbytes,
+            end + nchars,
+            end_byte + nbytes,
+            true,
+        );
+    }
+
+    // If point was outside of the region, restore it exactly; else just
+    // move to the beginning of the region.
+    let pos = if old_pos >= end {
+        old_pos + nchars - (end - beg)
+    } else if old_pos > beg {
+        beg
+    } else {
+        old_pos
+    };
+    unsafe { set_point(pos.min(buffer.zv())) };
+
+    LispObject::from_natnum(nchars as EmacsInt)
+}
+
+/// Encode the region between BEG and END with ENCODER, and return the
+/// length of the encoded text.  NAME is the name of the encoding for
+/// error messages.
+pub fn encode_region<E: Encoder>(
+    beg: LispObject,
+    end: LispObject,
+    encoder: E,
+    name: &str,
+) -> LispObject {
+    let bounds = region_bounds(beg, end);
+    let buffer = ThreadState::current_buffer();
+    let multibyte = LispObject::from(buffer.enable_multibyte_characters).is_not_nil();
+    let (before_gap, after_gap) = buffer.byte_slices(bounds.1, bounds.3);
+    let encoded = match encode(encoder, &[before_gap, after_gap], multibyte) {
+        Some(encoded) => encoded,
+        None => error!("Multibyte character in data for {} encoding", name),
+    };
+    let nchars = encoded.len() as ptrdiff_t;
+    if multibyte {
+        replace_region(bounds, &bytes_to_multibyte(&encoded), nchars)
+    } else {
+        replace_region(bounds, &encoded, nchars)
+    }
+}
+
+/// Decode the region between BEG and END with DECODER, and return the
+/// length of the decoded text.  If the region can't be decoded, signal
+/// an error and don't modify the buffer.  NAME is the name of the
+/// encoding for error messages.
+pub fn decode_region<D: Decoder>(
+    beg: LispObject,
+    end: LispObject,
+    decoder: D,
+    name: &str,
+) -> LispObject {
+    let bounds = region_bounds(beg, end);
+    let buffer = ThreadState::current_buffer();
+    let multibyte = LispObject::from(buffer.enable_multibyte_characters).is_not_nil();
+    let (before_gap, after_gap) = buffer.byte_slices(bounds.1, bounds.3);
+    match decode(decoder, &[before_gap, after_gap], multibyte) {
+        Some(decoded) => replace_region(bounds, &decoded.bytes, decoded.nchars as ptrdiff_t),
+        None => error!("Invalid {} data", name),
+    }
+}
+
+/// A xorshift generator, to make up test data.
+#[cfg(test)]
+pub struct TestRng(u32);
+
+#[cfg(test)]
+impl TestRng {
+    pub fn new() -> TestRng {
+        TestRng(2_463_534_242)
+    }
+
+    pub fn next(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+
+    /// Return some random bytes, biased towards the ASCII characters
+    /// which matter to text codecs.
+    pub fn bytes(&mut self) -> Vec<u8> {
+        let len = self.next() as usize % 200;
+        (0..len)
+            .map(|_| match self.next() % 4 {
+                0 => b" \t\r\n="[self.next() as usize % 5],
+                1 => (self.next() % 0x60) as u8 + 0x20,
+                _ => self.next() as u8,
+            })
+            .collect()
+    }
+}
+
+/// Check that decoding the output of ENCODER gives back random
+/// inputs, fed to both in random chunks.
+#[cfg(test)]
+pub fn check_round_trip<E, D, F, G>(new_encoder: F, new_decoder: G)
+where
+    E: Encoder,
+    D: Decoder,
+    F: Fn() -> E,
+    G: Fn() -> D,
+{
+    let mut rng = TestRng::new();
+    for _ in 0..500 {
+        let input = rng.bytes();
+        let split = rng.next() as usize % (input.len() + 1);
+        let (before, after) = input.split_at(split);
+        let encoded = encode(new_encoder(), &[before, after], false).unwrap();
+        let split = rng.next() as usize % (encoded.len() + 1);
+        let (before, after) = encoded.split_at(split);
+        let decoded = decode(new_decoder(), &[before, after], false).unwrap();
+        assert_eq!(decoded.bytes, input, "encoded as {:?}", encoded);
+        assert_eq!(decoded.nchars, input.len());
+    }
+}
+
+#[test]
+fn test_output_multibyte() {
+    let mut out = Output::new(true);
+    out.push(b'a');
+    out.push(0xE9);
+    // A multibyte `é' passed through.
+    out.push_literal(0xC3);
+    out.push_literal(0xA9);
+    assert_eq!(out.bytes, [b'a', 0xC1, 0xA9, 0xC3, 0xA9]);
+    assert_eq!(out.nchars, 3);
+}
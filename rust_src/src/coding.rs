@@ -0,0 +1,578 @@
+//! Native decoders and encoders for the most common coding systems.
+//!
+//! `decode-coding-string`, `encode-coding-string` and
+//! `insert-file-contents` dispatch here for raw-text, Latin-1, UTF-8
+//! and UTF-16 coding systems, as long as they need no pre/post
+//! conversion or translation (see `fast_coding_spec_for()` in
+//! coding.c).  Everything else still goes through the generic
+//! machinery in coding.c.
+//!
+//! Bytes that are invalid in the source encoding are preserved as
+//! raw 8-bit characters, and characters the target encoding can't
+//! represent make the encoder bail out, so that the C encoder can
+//! apply `default-char` and friends.
+
+use libc::{c_char, c_uchar, ptrdiff_t};
+use std::slice;
+
+use remacs_sys::{emacs_abort, make_specified_string, make_unibyte_string};
+use remacs_sys::{fast_coding_spec, FastCodingEol, FastCodingType, Lisp_Object, Utf16Endian,
+                 UtfBomType};
+
+use lisp::LispObject;
+use multibyte::{count_size_as_multibyte, multibyte_char_at, multibyte_chars_in_text,
+                multibyte_length, raw_byte_codepoint, raw_byte_from_codepoint, str_to_multibyte,
+                write_codepoints, Codepoint, MAX_2_BYTE_CHAR, MAX_3_BYTE_CHAR, MAX_4_BYTE_CHAR,
+                MAX_5_BYTE_CHAR};
+
+const UTF_8_BOM: [u8; 3] = [0xEF, 0xBB, 0xBF];
+const MAX_UNICODE_CHAR: Codepoint = 0x10_FFFF;
+
+/// Return the length of the ASCII-only prefix of BYTES.
+fn ascii_prefix_len(bytes: &[u8]) -> usize {
+    // Or-ing whole chunks together is much faster than looking at
+    // each byte on its own, and the common case is long ASCII runs.
+    let mut len = 0;
+    for chunk in bytes.chunks(16) {
+        if chunk.iter().fold(0, |acc, &byte| acc | byte) >= 0x80 {
+            break;
+        }
+        len += chunk.len();
+    }
+    len + bytes[len..].iter().take_while(|&&byte| byte < 0x80).count()
+}
+
+/// Append the multibyte form of the raw 8-bit byte BYTE to OUT.
+fn push_raw_byte(out: &mut Vec<u8>, byte: u8) {
+    write_codepoints(out, Some(raw_byte_codepoint(byte)));
+}
+
+/// Return the length of the valid UTF-8 sequence of a non-ASCII
+/// character at the start of BYTES, if any.  Like the C decoder, this
+/// accepts 5-byte sequences for characters beyond Unicode, but
+/// rejects overlong forms and surrogates.
+pub fn utf_8_sequence_length(bytes: &[u8]) -> Option<usize> {
+    // This already rejects the overlong 2-byte forms C0 and C1.
+    let len = multibyte_length(bytes, false)?;
+    let (cp, _) = multibyte_char_at(bytes);
+    let valid = match len {
+        3 => cp > MAX_2_BYTE_CHAR && (cp < 0xD800 || cp > 0xDFFF),
+        4 => cp > MAX_3_BYTE_CHAR,
+        5 => cp > MAX_4_BYTE_CHAR,
+        _ => true,
+    };
+    if valid {
+        Some(len)
+    } else {
+        None
+    }
+}
+
+fn decode_utf_8(mut source: &[u8], bom: UtfBomType, out: &mut Vec<u8>) {
+    if bom == UtfBomType::With && source.starts_with(&UTF_8_BOM) {
+        source = &source[UTF_8_BOM.len()..];
+    }
+    let mut idx = 0;
+    while idx < source.len() {
+        let ascii = ascii_prefix_len(&source[idx..]);
+        out.extend_from_slice(&source[idx..idx + ascii]);
+        idx += ascii;
+        if idx == source.len() {
+            break;
+        }
+        match utf_8_sequence_length(&source[idx..]) {
+            Some(len) => {
+                let (cp, _) = multibyte_char_at(&source[idx..]);
+                if cp > MAX_5_BYTE_CHAR {
+                    // Raw bytes have a shorter internal form.
+                    write_codepoints(out, Some(cp));
+                } else {
+                    out.extend_from_slice(&source[idx..idx + len]);
+                }
+                idx += len;
+            }
+            None => {
+                push_raw_byte(out, source[idx]);
+                idx += 1;
+            }
+        }
+    }
+}
+
+fn utf_16_unit(pair: &[u8], endian: Utf16Endian) -> Codepoint {
+    let (high, low) = match endian {
+        Utf16Endian::Big => (pair[0], pair[1]),
+        Utf16Endian::Little => (pair[1], pair[0]),
+    };
+    (Codepoint::from(high) << 8) | Codepoint::from(low)
+}
+
+fn decode_utf_16(mut source: &[u8], bom: UtfBomType, endian: Utf16Endian, out: &mut Vec<u8>) {
+    if bom == UtfBomType::With && source.len() >= 2 && utf_16_unit(source, endian) == 0xFEFF {
+        source = &source[2..];
+    }
+    let is_high = |unit: Codepoint| unit >= 0xD800 && unit < 0xDC00;
+    let is_low = |unit: Codepoint| unit >= 0xDC00 && unit < 0xE000;
+    // Like the C decoder, a high surrogate which isn't followed by a
+    // low one becomes the characters of its two bytes, a lone low
+    // surrogate becomes the character of its code, and a high
+    // surrogate at the end of the text is dropped.
+    let mut surrogate: Option<(Codepoint, (u8, u8))> = None;
+    let mut idx = 0;
+    while idx + 1 < source.len() {
+        let unit = utf_16_unit(&source[idx..], endian);
+        let bytes = (source[idx], source[idx + 1]);
+        idx += 2;
+        match surrogate.take() {
+            Some((high, _)) if is_low(unit) => {
+                write_codepoints(out, Some(0x10000 + ((high - 0xD800) << 10) + (unit - 0xDC00)));
+                continue;
+            }
+            Some((_, (first, second))) => {
+                let bytes = [Codepoint::from(first), Codepoint::from(second)];
+                write_codepoints(out, bytes.iter().cloned());
+            }
+            None => {}
+        }
+        if is_high(unit) {
+            surrogate = Some((unit, bytes));
+        } else {
+            write_codepoints(out, Some(unit));
+        }
+    }
+    if idx < source.len() {
+        // A trailing odd byte.
+        push_raw_byte(out, source[idx]);
+    }
+}
+
+fn decode_latin_1(source: &[u8], out: &mut Vec<u8>) {
+    let mut idx = 0;
+    while idx < source.len() {
+        let ascii = ascii_prefix_len(&source[idx..]);
+        out.extend_from_slice(&source[idx..idx + ascii]);
+        idx += ascii;
+        if idx < source.len() {
+            write_codepoints(out, Some(Codepoint::from(source[idx])));
+            idx += 1;
+        }
+    }
+}
+
+fn decode_raw_text(source: &[u8], multibyte: bool, out: &mut Vec<u8>) {
+    if !multibyte {
+        out.extend_from_slice(source);
+        return;
+    }
+    let start = out.len();
+    let size = count_size_as_multibyte(source.as_ptr(), source.len() as ptrdiff_t);
+    out.resize(start + size as usize, 0);
+    out[start..start + source.len()].copy_from_slice(source);
+    let len = str_to_multibyte(
+        out[start..].as_mut_ptr(),
+        size,
+        source.len() as ptrdiff_t,
+    );
+    out.truncate(start + len as usize);
+}
+
+/// Detect the EOL format of the decoded TEXT.  Like `decode_eol()` in
+/// coding.c, CRLF with stray CRs counts as DOS, and any other mix
+/// counts as Unix.
+fn detect_eol(text: &[u8]) -> FastCodingEol {
+    let (mut lf, mut cr, mut crlf) = (false, false, false);
+    let mut idx = 0;
+    while let Some(pos) = text[idx..]
+        .iter()
+        .position(|&byte| byte == b'\n' || byte == b'\r')
+    {
+        idx += pos;
+        if text[idx] == b'\n' {
+            lf = true;
+        } else if idx + 1 < text.len() && text[idx + 1] == b'\n' {
+            crlf = true;
+            idx += 1;
+        } else {
+            cr = true;
+        }
+        idx += 1;
+        if lf && (cr || crlf) {
+            break;
+        }
+    }
+    match (lf, crlf, cr) {
+        (false, false, false) => FastCodingEol::Undecided,
+        (false, true, _) => FastCodingEol::Dos,
+        (false, false, true) => FastCodingEol::Mac,
+        _ => FastCodingEol::Unix,
+    }
+}
+
+/// Convert the line ends of the decoded TEXT from EOL to line feeds.
+fn decode_eol(text: &mut Vec<u8>, eol: FastCodingEol) {
+    match eol {
+        FastCodingEol::Mac => for byte in text.iter_mut() {
+            if *byte == b'\r' {
+                *byte = b'\n';
+            }
+        },
+        FastCodingEol::Dos => {
+            let len = text.len();
+            let mut to = 0;
+            for from in 0..len {
+                let byte = text[from];
+                if byte == b'\r' && from + 1 < len && text[from + 1] == b'\n' {
+                    continue;
+                }
+                text[to] = byte;
+                to += 1;
+            }
+            text.truncate(to);
+        }
+        _ => {}
+    }
+}
+
+/// Decode SOURCE according to SPEC into the internal representation,
+/// multibyte if MULTIBYTE.  If the EOL format of SPEC is undecided,
+/// store the detected one in SPEC.
+fn decode(source: &[u8], spec: &mut fast_coding_spec, multibyte: bool) -> Vec<u8> {
+    let mut text = Vec::with_capacity(source.len());
+    match spec.coding_type {
+        FastCodingType::RawText => decode_raw_text(source, multibyte, &mut text),
+        FastCodingType::Latin1 => decode_latin_1(source, &mut text),
+        FastCodingType::Utf8 => decode_utf_8(source, spec.bom, &mut text),
+        FastCodingType::Utf16 => decode_utf_16(source, spec.bom, spec.endian, &mut text),
+        FastCodingType::None => unsafe { emacs_abort() },
+    }
+    if spec.eol == FastCodingEol::Undecided {
+        spec.eol = detect_eol(&text);
+    }
+    decode_eol(&mut text, spec.eol);
+    text
+}
+
+/// Append the bytes RUN to OUT, converting line feeds to EOL.
+fn encode_eol_bytes(run: &[u8], eol: FastCodingEol, out: &mut Vec<u8>) {
+    let newline: &[u8] = match eol {
+        FastCodingEol::Dos => b"\r\n",
+        FastCodingEol::Mac => b"\r",
+        _ => {
+            out.extend_from_slice(run);
+            return;
+        }
+    };
+    for (i, line) in run.split(|&byte| byte == b'\n').enumerate() {
+        if i > 0 {
+            out.extend_from_slice(newline);
+        }
+        out.extend_from_slice(line);
+    }
+}
+
+/// Encode the multibyte TEXT byte by byte.  ENCODE_CHAR appends the
+/// encoding of a non-ASCII character to OUT, or returns None if it
+/// can't be encoded.
+fn encode_multibyte<F>(
+    text: &[u8],
+    eol: FastCodingEol,
+    out: &mut Vec<u8>,
+    mut encode_char: F,
+) -> Option<()>
+where
+    F: FnMut(Codepoint, &[u8], &mut Vec<u8>) -> Option<()>,
+{
+    let mut idx = 0;
+    while idx < text.len() {
+        let ascii = ascii_prefix_len(&text[idx..]);
+        encode_eol_bytes(&text[idx..idx + ascii], eol, out);
+        idx += ascii;
+        if idx < text.len() {
+            let (cp, len) = multibyte_char_at(&text[idx..]);
+            encode_char(cp, &text[idx..idx + len], out)?;
+            idx += len;
+        }
+    }
+    Some(())
+}
+
+/// Encode TEXT as UTF-8, or as raw text.  Both keep the internal
+/// representation of characters, except that raw bytes are emitted
+/// as such.
+fn encode_utf_8(
+    text: &[u8],
+    multibyte: bool,
+    eol: FastCodingEol,
+    out: &mut Vec<u8>,
+) -> Option<()> {
+    if !multibyte {
+        encode_eol_bytes(text, eol, out);
+        return Some(());
+    }
+    encode_multibyte(text, eol, out, |cp, bytes, out| {
+        if cp > MAX_5_BYTE_CHAR {
+            out.push(raw_byte_from_codepoint(cp));
+        } else {
+            out.extend_from_slice(bytes);
+        }
+        Some(())
+    })
+}
+
+/// Return TEXT encoded as UTF-8, with raw bytes emitted as such and
+/// line ends left alone.
+pub fn encode_utf_8_text(text: &[u8], multibyte: bool) -> Vec<u8> {
+    let mut out = Vec::with_capacity(text.len());
+    encode_utf_8(text, multibyte, FastCodingEol::Unix, &mut out);
+    out
+}
+
+fn encode_latin_1(
+    text: &[u8],
+    multibyte: bool,
+    eol: FastCodingEol,
+    out: &mut Vec<u8>,
+) -> Option<()> {
+    if !multibyte {
+        encode_eol_bytes(text, eol, out);
+        return Some(());
+    }
+    encode_multibyte(text, eol, out, |cp, _, out| {
+        if cp > MAX_5_BYTE_CHAR {
+            out.push(raw_byte_from_codepoint(cp));
+        } else if cp < 0x100 {
+            out.push(cp as u8);
+        } else {
+            return None;
+        }
+        Some(())
+    })
+}
+
+fn push_utf_16_unit(out: &mut Vec<u8>, unit: Codepoint, endian: Utf16Endian) {
+    let (high, low) = ((unit >> 8) as u8, unit as u8);
+    match endian {
+        Utf16Endian::Big => out.extend_from_slice(&[high, low]),
+        Utf16Endian::Little => out.extend_from_slice(&[low, high]),
+    }
+}
+
+fn encode_utf_16(
+    text: &[u8],
+    multibyte: bool,
+    spec: &fast_coding_spec,
+    out: &mut Vec<u8>,
+) -> Option<()> {
+    if spec.bom == UtfBomType::With {
+        push_utf_16_unit(out, 0xFEFF, spec.endian);
+    }
+    let mut idx = 0;
+    while idx < text.len() {
+        let (cp, len) = if multibyte {
+            multibyte_char_at(&text[idx..])
+        } else {
+            (raw_byte_codepoint(text[idx]), 1)
+        };
+        idx += len;
+        if cp == Codepoint::from(b'\n') {
+            match spec.eol {
+                FastCodingEol::Dos => {
+                    push_utf_16_unit(out, Codepoint::from(b'\r'), spec.endian);
+                    push_utf_16_unit(out, cp, spec.endian);
+                }
+                FastCodingEol::Mac => push_utf_16_unit(out, Codepoint::from(b'\r'), spec.endian),
+                _ => push_utf_16_unit(out, cp, spec.endian),
+            }
+        } else if cp <= MAX_3_BYTE_CHAR {
+            push_utf_16_unit(out, cp, spec.endian);
+        } else if cp <= MAX_UNICODE_CHAR {
+            let cp = cp - 0x10000;
+            push_utf_16_unit(out, 0xD800 + (cp >> 10), spec.endian);
+            push_utf_16_unit(out, 0xDC00 + (cp & 0x3FF), spec.endian);
+        } else {
+            return None;
+        }
+    }
+    Some(())
+}
+
+/// Encode TEXT, which is multibyte if MULTIBYTE, according to SPEC.
+/// Return None if TEXT contains a character that can't be encoded.
+pub fn encode(text: &[u8], multibyte: bool, spec: &fast_coding_spec) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() + UTF_8_BOM.len());
+    match spec.coding_type {
+        FastCodingType::RawText => encode_utf_8(text, multibyte, spec.eol, &mut out)?,
+        FastCodingType::Utf8 => {
+            if spec.bom == UtfBomType::With {
+                out.extend_from_slice(&UTF_8_BOM);
+            }
+            encode_utf_8(text, multibyte, spec.eol, &mut out)?;
+        }
+        FastCodingType::Latin1 => encode_latin_1(text, multibyte, spec.eol, &mut out)?,
+        FastCodingType::Utf16 => encode_utf_16(text, multibyte, spec, &mut out)?,
+        FastCodingType::None => unsafe { emacs_abort() },
+    }
+    Some(out)
+}
+
+/// Decode the NBYTES bytes at SOURCE according to SPEC, and return the
+/// result as a new string, multibyte if MULTIBYTE.  If the EOL format
+/// of SPEC is undecided, the detected format is stored in SPEC.
+#[no_mangle]
+pub extern "C" fn fast_decode_string(
+    source: *const c_uchar,
+    nbytes: ptrdiff_t,
+    spec: *mut fast_coding_spec,
+    multibyte: bool,
+) -> Lisp_Object {
+    let source = unsafe { slice::from_raw_parts(source, nbytes as usize) };
+    let text = decode(source, unsafe { &mut *spec }, multibyte);
+    let nbytes = text.len() as ptrdiff_t;
+    let nchars = if multibyte {
+        multibyte_chars_in_text(text.as_ptr(), nbytes)
+    } else {
+        nbytes
+    };
+    unsafe { make_specified_string(text.as_ptr() as *const c_char, nchars, nbytes, multibyte) }
+}
+
+/// Encode STRING according to SPEC, and return the result as a new
+/// unibyte string.  Return nil if STRING contains a character that
+/// can't be encoded.
+#[no_mangle]
+pub extern "C" fn fast_encode_string(
+    string: Lisp_Object,
+    spec: *const fast_coding_spec,
+) -> Lisp_Object {
+    let string = LispObject::from(string).as_string_or_error();
+    match encode(string.as_slice(), string.is_multibyte(), unsafe { &*spec }) {
+        Some(bytes) => unsafe {
+            make_unibyte_string(bytes.as_ptr() as *const c_char, bytes.len() as ptrdiff_t)
+        },
+        None => LispObject::constant_nil().to_raw(),
+    }
+}
+
+#[cfg(test)]
+fn spec(coding_type: FastCodingType, eol: FastCodingEol) -> fast_coding_spec {
+    fast_coding_spec {
+        coding_type,
+        bom: UtfBomType::Without,
+        endian: Utf16Endian::Big,
+        eol,
+    }
+}
+
+#[test]
+fn test_decode_utf_8() {
+    let mut utf_8 = spec(FastCodingType::Utf8, FastCodingEol::Unix);
+    assert_eq!(decode(b"abc \xC3\xA9\xE2\x82\xAC", &mut utf_8, true), b"abc \xC3\xA9\xE2\x82\xAC");
+    // Overlong forms, surrogates and stray bytes become raw bytes.
+    assert_eq!(decode(b"\xC0\xAF", &mut utf_8, true), b"\xC1\x80\xC0\xAF");
+    assert_eq!(
+        decode(b"\xED\xA0\x80", &mut utf_8, true),
+        b"\xC1\xAD\xC0\xA0\xC0\x80"
+    );
+    assert_eq!(decode(b"a\xFFb\xE2\x82", &mut utf_8, true), b"a\xC1\xBFb\xC1\xA2\xC0\x82");
+    // The BOM is only stripped for utf-8-with-signature.
+    assert_eq!(decode(b"\xEF\xBB\xBFx", &mut utf_8, true), b"\xEF\xBB\xBFx");
+    utf_8.bom = UtfBomType::With;
+    assert_eq!(decode(b"\xEF\xBB\xBFx", &mut utf_8, true), b"x");
+}
+
+#[test]
+fn test_decode_utf_16() {
+    let mut utf_16 = spec(FastCodingType::Utf16, FastCodingEol::Unix);
+    utf_16.bom = UtfBomType::With;
+    assert_eq!(
+        decode(b"\xFE\xFF\x00a\xD8\x3D\xDE\x00", &mut utf_16, true),
+        b"a\xF0\x9F\x98\x80"
+    );
+    utf_16.endian = Utf16Endian::Little;
+    assert_eq!(decode(b"\xFF\xFEa\x00\xE9\x00", &mut utf_16, true), b"a\xC3\xA9");
+    // A high surrogate without a low one becomes the characters of its
+    // bytes, a lone low surrogate is kept as a character, a trailing
+    // high surrogate is dropped, and a trailing odd byte is kept as a
+    // raw byte.
+    assert_eq!(decode(b"\x3D\xD8a\x00z", &mut utf_16, true), b"=\xC3\x98az");
+    assert_eq!(
+        decode(b"\x3D\xD8\x3D\xD8\x00\xDE", &mut utf_16, true),
+        b"=\xC3\x98\xF0\x9F\x98\x80"
+    );
+    assert_eq!(decode(b"\x00\xDCa\x00", &mut utf_16, true), b"\xED\xB0\x80a");
+    assert_eq!(decode(b"a\x00\x3D\xD8", &mut utf_16, true), b"a");
+    assert_eq!(decode(b"a\x00\x3D\xD8z", &mut utf_16, true), b"az");
+}
+
+#[test]
+fn test_decode_latin_1_and_raw_text() {
+    let mut latin_1 = spec(FastCodingType::Latin1, FastCodingEol::Unix);
+    assert_eq!(decode(b"a\xE9\x80", &mut latin_1, true), b"a\xC3\xA9\xC2\x80");
+    let mut raw_text = spec(FastCodingType::RawText, FastCodingEol::Unix);
+    assert_eq!(decode(b"a\xE9", &mut raw_text, true), b"a\xC1\xA9");
+    assert_eq!(decode(b"a\xE9", &mut raw_text, false), b"a\xE9");
+}
+
+#[test]
+fn test_eol() {
+    let mut utf_8 = spec(FastCodingType::Utf8, FastCodingEol::Undecided);
+    assert_eq!(decode(b"a\r\nb\r\n", &mut utf_8, true), b"a\nb\n");
+    assert_eq!(utf_8.eol, FastCodingEol::Dos);
+    utf_8.eol = FastCodingEol::Undecided;
+    assert_eq!(decode(b"a\rb\r\n\r", &mut utf_8, true), b"a\rb\n\r");
+    assert_eq!(utf_8.eol, FastCodingEol::Dos);
+    utf_8.eol = FastCodingEol::Undecided;
+    assert_eq!(decode(b"a\rb", &mut utf_8, true), b"a\nb");
+    assert_eq!(utf_8.eol, FastCodingEol::Mac);
+    utf_8.eol = FastCodingEol::Undecided;
+    assert_eq!(decode(b"a\r\nb\n", &mut utf_8, true), b"a\r\nb\n");
+    assert_eq!(utf_8.eol, FastCodingEol::Unix);
+    utf_8.eol = FastCodingEol::Undecided;
+    assert_eq!(decode(b"abc", &mut utf_8, true), b"abc");
+    assert_eq!(utf_8.eol, FastCodingEol::Undecided);
+
+    let mut utf_16 = spec(FastCodingType::Utf16, FastCodingEol::Undecided);
+    assert_eq!(decode(b"\x00a\x00\r\x00\n", &mut utf_16, true), b"a\n");
+    assert_eq!(utf_16.eol, FastCodingEol::Dos);
+    assert_eq!(encode(b"a\n", true, &utf_16), Some(b"\x00a\x00\r\x00\n".to_vec()));
+
+    let dos = spec(FastCodingType::Utf8, FastCodingEol::Dos);
+    assert_eq!(encode(b"a\nb\xC3\xA9\n", true, &dos), Some(b"a\r\nb\xC3\xA9\r\n".to_vec()));
+    let mac = spec(FastCodingType::Latin1, FastCodingEol::Mac);
+    assert_eq!(encode(b"a\n\n", false, &mac), Some(b"a\r\r".to_vec()));
+}
+
+#[test]
+fn test_encode() {
+    let mut utf_8 = spec(FastCodingType::Utf8, FastCodingEol::Unix);
+    assert_eq!(encode(b"a\xC3\xA9\xC1\xBF", true, &utf_8), Some(b"a\xC3\xA9\xFF".to_vec()));
+    assert_eq!(encode(b"a\xFF", false, &utf_8), Some(b"a\xFF".to_vec()));
+    utf_8.bom = UtfBomType::With;
+    assert_eq!(encode(b"x", true, &utf_8), Some(b"\xEF\xBB\xBFx".to_vec()));
+
+    let latin_1 = spec(FastCodingType::Latin1, FastCodingEol::Unix);
+    assert_eq!(encode(b"a\xC3\xA9\xC1\xBF", true, &latin_1), Some(b"a\xE9\xFF".to_vec()));
+    assert_eq!(encode(b"\xE2\x82\xAC", true, &latin_1), None);
+
+    let mut utf_16 = spec(FastCodingType::Utf16, FastCodingEol::Unix);
+    utf_16.endian = Utf16Endian::Little;
+    assert_eq!(
+        encode(b"a\xF0\x9F\x98\x80", true, &utf_16),
+        Some(b"a\x00\x3D\xD8\x00\xDE".to_vec())
+    );
+    // Raw bytes can't be encoded in UTF-16.
+    assert_eq!(encode(b"\xC1\xBF", true, &utf_16), None);
+    assert_eq!(encode(b"\xFF", false, &utf_16), None);
+}
+
+#[test]
+fn test_round_trip() {
+    let text = b"line \xC3\xA9\xE2\x82\xAC\xF0\x9F\x98\x80\nnext\n";
+    for &coding_type in &[FastCodingType::Utf8, FastCodingType::Utf16] {
+        for &eol in &[FastCodingEol::Unix, FastCodingEol::Dos, FastCodingEol::Mac] {
+            let mut spec = spec(coding_type, eol);
+            let encoded = encode(text, true, &spec).unwrap();
+            assert_eq!(decode(&encoded, &mut spec, true), text.to_vec());
+        }
+    }
+}
@@ -0,0 +1,682 @@
+//! Locale-independent string collation, with the Unicode Collation
+//! Algorithm of Unicode Technical Standard #10.
+//!
+//! Strings are collated with the Default Unicode Collation Element
+//! Table of allkeys.txt, or with the tailoring of a locale from
+//! CollationTailorings.txt, so that they sort the same way on all
+//! systems whatever their locale settings are.  Variable collation
+//! elements, those of whitespace, punctuation and symbols, are
+//! shifted to the fourth level, and trailing non-variable weights are
+//! trimmed from it, like the C libraries of GNU/Linux do.
+
+use std::cmp::Ordering;
+
+use remacs_macros::lisp_fn;
+
+use lisp::LispObject;
+use lisp::defsubr;
+use multibyte::Codepoint;
+use normalize::{normalize, NormalizationForm};
+use unidata::{canonical_combining_class, is_decimal_digit, simple_case_mapping, Case};
+
+include!(concat!(env!("OUT_DIR"), "/collation_tables.rs"));
+
+/// A character sequence and its collation elements, as in `DUCET'.
+type Entry = (&'static [Codepoint], &'static [u32]);
+
+/// The Unified_Ideograph characters, whose implicit weights come
+/// before those of other unassigned characters.  The first two and
+/// those in the CJK Compatibility Ideographs block are core Han
+/// ideographs, whose weights come first.
+const UNIFIED_IDEOGRAPH_RANGES: &[(u32, u32)] = &[
+    (0x3400, 0x4DBF),
+    (0x4E00, 0x9FFC),
+    (0xFA0E, 0xFA0F),
+    (0xFA11, 0xFA11),
+    (0xFA13, 0xFA14),
+    (0xFA1F, 0xFA1F),
+    (0xFA21, 0xFA21),
+    (0xFA23, 0xFA24),
+    (0xFA27, 0xFA29),
+    (0x20000, 0x2A6DD),
+    (0x2A700, 0x2B734),
+    (0x2B740, 0x2B81D),
+    (0x2B820, 0x2CEA1),
+    (0x2CEB0, 0x2EBE0),
+    (0x30000, 0x3134A),
+];
+
+/// A collation element, with the primary weight shifted left by 16
+/// bits to make room for the lengths of numbers in numeric collation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Element {
+    primary: u32,
+    secondary: u32,
+    tertiary: u32,
+    variable: bool,
+}
+
+impl Element {
+    /// Unpack a collation element of the tables, which has the primary
+    /// weight in its top 16 bits, the secondary weight in the next 9,
+    /// the tertiary weight in the next 5, and the variable flag in the
+    /// lowest bit.
+    fn unpack(packed: u32) -> Element {
+        Element {
+            primary: packed >> 16 << 16,
+            secondary: (packed >> 7) & 0x1FF,
+            tertiary: (packed >> 2) & 0x1F,
+            variable: packed & 1 != 0,
+        }
+    }
+
+    fn digit_value(&self) -> Option<u32> {
+        let primary = self.primary >> 16;
+        if DIGIT_ZERO_PRIMARY <= primary && primary <= DIGIT_NINE_PRIMARY {
+            Some(primary - DIGIT_ZERO_PRIMARY)
+        } else {
+            None
+        }
+    }
+}
+
+/// Return the implicit collation elements of C, which has no entry in
+/// the tables.
+fn implicit_elements(c: Codepoint) -> [Element; 2] {
+    let (base, low) = match IMPLICIT_WEIGHTS
+        .iter()
+        .find(|&&(start, end, _)| start <= c && c <= end)
+    {
+        Some(&(start, _, base)) => (base, c - start),
+        None => {
+            let ideograph = UNIFIED_IDEOGRAPH_RANGES
+                .iter()
+                .any(|&(start, end)| start <= c && c <= end);
+            let core = (0x4E00 <= c && c <= 0x9FFF) || (0xF900 <= c && c <= 0xFAFF);
+            let base = match (ideograph, core) {
+                (true, true) => 0xFB40,
+                (true, false) => 0xFB80,
+                _ => 0xFBC0,
+            };
+            (base + (c >> 15), c & 0x7FFF)
+        }
+    };
+    [
+        Element {
+            primary: base << 16,
+            secondary: 0x20,
+            tertiary: 0x02,
+            variable: false,
+        },
+        Element {
+            primary: (low | 0x8000) << 16,
+            secondary: 0,
+            tertiary: 0,
+            variable: false,
+        },
+    ]
+}
+
+/// Return the entries of TABLE whose character sequence starts with C.
+fn entries_starting_with(table: &'static [Entry], c: Codepoint) -> &'static [Entry] {
+    let position = |after: bool| {
+        table
+            .binary_search_by(|&(sequence, _)| {
+                if sequence[0] < c || (after && sequence[0] == c) {
+                    Ordering::Less
+                } else {
+                    Ordering::Greater
+                }
+            })
+            .unwrap_err()
+    };
+    &table[position(false)..position(true)]
+}
+
+/// The options of a collation.
+struct Collator {
+    /// The entries of the tailoring, which take precedence over those
+    /// of `DUCET'.
+    tailoring: &'static [Entry],
+    /// Whether to sort uppercase letters before lowercase ones.
+    upper_before_lower: bool,
+    ignore_case: bool,
+    ignore_accents: bool,
+    numeric: bool,
+    /// Whether variable collation elements are shifted to the fourth
+    /// level, rather than weighed like the others.
+    shift_variable: bool,
+}
+
+impl Collator {
+    fn root() -> Collator {
+        Collator {
+            tailoring: &[],
+            upper_before_lower: false,
+            ignore_case: false,
+            ignore_accents: false,
+            numeric: false,
+            shift_variable: true,
+        }
+    }
+
+    /// Return the collation elements of SEQUENCE, if it has an entry.
+    fn lookup(&self, sequence: &[Codepoint]) -> Option<&'static [u32]> {
+        for &table in [self.tailoring, DUCET].iter() {
+            if let Ok(idx) = table.binary_search_by(|&(entry, _)| entry.cmp(sequence)) {
+                return Some(table[idx].1);
+            }
+        }
+        None
+    }
+
+    /// Return the length of the longest sequence at the start of CHARS
+    /// which has an entry, and its collation elements.
+    fn longest_match(&self, chars: &[Codepoint]) -> Option<(usize, &'static [u32])> {
+        let mut longest: Option<(usize, &'static [u32])> = None;
+        for &table in [self.tailoring, DUCET].iter() {
+            for &(sequence, elements) in entries_starting_with(table, chars[0]) {
+                let longer = longest.map_or(true, |(length, _)| sequence.len() > length);
+                if longer && chars.starts_with(sequence) {
+                    longest = Some((sequence.len(), elements));
+                }
+            }
+        }
+        longest
+    }
+
+    /// Return the collation elements of CHARS, which are in NFD.
+    fn elements(&self, mut chars: Vec<Codepoint>) -> Vec<Element> {
+        let mut elements = Vec::with_capacity(chars.len() * 2);
+        // The elements of the digits before POS, in numeric collation.
+        let mut digits = Vec::new();
+        let mut pos = 0;
+        while pos < chars.len() {
+            let (length, mut packed) = match self.longest_match(&chars[pos..]) {
+                Some(found) => found,
+                None => {
+                    self.flush_digits(&mut digits, &mut elements);
+                    elements.extend_from_slice(&implicit_elements(chars[pos]));
+                    pos += 1;
+                    continue;
+                }
+            };
+
+            // Extend the match with the unblocked non-starters which
+            // follow it, removing those that match.
+            let mut sequence = chars[pos..pos + length].to_vec();
+            let mut next = pos + length;
+            let mut blocking_class = 0;
+            while next < chars.len() {
+                let class = canonical_combining_class(chars[next]);
+                if class == 0 {
+                    break;
+                }
+                if class > blocking_class {
+                    sequence.push(chars[next]);
+                    if let Some(found) = self.lookup(&sequence) {
+                        packed = found;
+                        chars.remove(next);
+                        continue;
+                    }
+                    sequence.pop();
+                }
+                blocking_class = class;
+                next += 1;
+            }
+            pos += length;
+
+            if self.numeric && length == 1 && packed.len() == 1 && is_decimal_digit(sequence[0]) {
+                let element = Element::unpack(packed[0]);
+                if element.digit_value().is_some() {
+                    digits.push(element);
+                    continue;
+                }
+            }
+            self.flush_digits(&mut digits, &mut elements);
+            elements.extend(packed.iter().map(|&element| Element::unpack(element)));
+        }
+        self.flush_digits(&mut digits, &mut elements);
+        elements
+    }
+
+    /// Move the elements of a number from DIGITS to ELEMENTS, after an
+    /// element whose primary weight holds the number of significant
+    /// digits, so that larger numbers sort after smaller ones.  The
+    /// leading zeros keep only their secondary and tertiary weights.
+    fn flush_digits(&self, digits: &mut Vec<Element>, elements: &mut Vec<Element>) {
+        if digits.is_empty() {
+            return;
+        }
+        let zeros = digits
+            .iter()
+            .take_while(|element| element.digit_value() == Some(0))
+            .count();
+        let significant = (digits.len() - zeros).min(0xFFFF) as u32;
+        elements.push(Element {
+            primary: DIGIT_ZERO_PRIMARY << 16 | significant,
+            secondary: 0,
+            tertiary: 0,
+            variable: false,
+        });
+        for (i, mut digit) in digits.drain(..).enumerate() {
+            if i < zeros {
+                digit.primary = 0;
+            }
+            elements.push(digit);
+        }
+    }
+
+    /// Return CHARS in NFD, and in lowercase if case is ignored, which
+    /// is the form that `sort_key' takes.
+    fn decompose(&self, chars: &[Codepoint]) -> Vec<Codepoint> {
+        if self.ignore_case {
+            let lowercase: Vec<Codepoint> = chars
+                .iter()
+                .map(|&c| simple_case_mapping(c, Case::Lower))
+                .collect();
+            normalize(&lowercase, NormalizationForm::NFD)
+        } else {
+            normalize(chars, NormalizationForm::NFD)
+        }
+    }
+
+    /// Return the weights of the four levels of the collation elements
+    /// of the decomposed CHARS, zero where an element has no weight at
+    /// a level.
+    fn sort_key(&self, chars: Vec<Codepoint>) -> Vec<[u32; 4]> {
+        let mut after_variable = false;
+        let mut key = Vec::new();
+        for element in self.elements(chars) {
+            let weights = if element.variable && self.shift_variable {
+                after_variable = true;
+                [0, 0, 0, element.primary]
+            } else if element.primary == 0 && (after_variable || self.ignore_accents) {
+                [0, 0, 0, 0]
+            } else {
+                if element.primary != 0 {
+                    after_variable = false;
+                }
+                let fourth =
+                    if element.primary == 0 && element.secondary == 0 && element.tertiary == 0 {
+                        0
+                    } else {
+                        0xFFFF_FFFF
+                    };
+                [
+                    element.primary,
+                    element.secondary,
+                    self.tertiary_weight(element.tertiary),
+                    fourth,
+                ]
+            };
+            key.push(weights);
+        }
+        // Trim the trailing weights of non-variable elements from the
+        // fourth level.
+        for weights in key.iter_mut().rev() {
+            match weights[3] {
+                0 => continue,
+                0xFFFF_FFFF => weights[3] = 0,
+                _ => break,
+            }
+        }
+        key
+    }
+
+    fn tertiary_weight(&self, tertiary: u32) -> u32 {
+        if !self.upper_before_lower {
+            return tertiary;
+        }
+        // Swap the weights of lowercase and uppercase variants.
+        match tertiary {
+            0x02...0x06 => tertiary + 6,
+            0x08...0x0C => tertiary - 6,
+            0x1C => 0x1D,
+            0x1D => 0x1C,
+            _ => tertiary,
+        }
+    }
+
+    fn compare(&self, chars1: &[Codepoint], chars2: &[Codepoint]) -> Ordering {
+        let key1 = self.sort_key(self.decompose(chars1));
+        let key2 = self.sort_key(self.decompose(chars2));
+        self.compare_keys(&key1, &key2)
+    }
+
+    fn compare_keys(&self, key1: &[[u32; 4]], key2: &[[u32; 4]]) -> Ordering {
+        for level in 0..4 {
+            if level == 1 && self.ignore_accents {
+                continue;
+            }
+            let ordering = key1
+                .iter()
+                .map(|weights| weights[level])
+                .filter(|&weight| weight != 0)
+                .cmp(
+                    key2.iter()
+                        .map(|weights| weights[level])
+                        .filter(|&weight| weight != 0),
+                );
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+        Ordering::Equal
+    }
+}
+
+/// Return the tailoring of LOCALE, as its entries and whether it sorts
+/// uppercase letters first, or None if LOCALE is "C" or "POSIX".
+/// Locales without a tailoring of their own use that of their
+/// language, if any, and `DUCET' otherwise.
+fn find_tailoring(locale: &str) -> Option<(&'static [Entry], bool)> {
+    let name = locale
+        .split(|c| c == '.' || c == '@')
+        .next()
+        .unwrap()
+        .to_lowercase()
+        .replace('-', "_");
+    if name == "c" || name == "posix" {
+        return None;
+    }
+
+    let language = name.split('_').next().unwrap();
+    if language.len() < 2
+        || language.len() > 3
+        || !language.bytes().all(|byte| b'a' <= byte && byte <= b'z')
+    {
+        error!("Invalid locale: {}", locale);
+    }
+
+    let mut candidate = &name[..];
+    loop {
+        if let Ok(idx) = TAILORINGS.binary_search_by(|&(name, _, _)| name.cmp(candidate)) {
+            let (_, upper_before_lower, entries) = TAILORINGS[idx];
+            return Some((entries, upper_before_lower));
+        }
+        match candidate.rfind('_') {
+            Some(end) => candidate = &candidate[..end],
+            None => return Some((&[], false)),
+        }
+    }
+}
+
+/// Compare S1 and S2 according to the arguments of
+/// `string-collate-lessp'.
+fn collate(
+    s1: LispObject,
+    s2: LispObject,
+    locale: LispObject,
+    ignore_case: LispObject,
+    ignore_accents: LispObject,
+    numeric: LispObject,
+) -> Ordering {
+    let chars1: Vec<Codepoint> = s1.symbol_or_string_as_string().chars().collect();
+    let chars2: Vec<Codepoint> = s2.symbol_or_string_as_string().chars().collect();
+    let mut collator = Collator::root();
+    collator.ignore_case = ignore_case.is_not_nil();
+    if locale.is_not_nil() {
+        let locale = locale.as_string_or_error();
+        match find_tailoring(&String::from_utf8_lossy(locale.as_slice())) {
+            Some((tailoring, upper_before_lower)) => {
+                collator.tailoring = tailoring;
+                collator.upper_before_lower = upper_before_lower;
+            }
+            None => {
+                let fold = |c| {
+                    if collator.ignore_case {
+                        simple_case_mapping(c, Case::Lower)
+                    } else {
+                        c
+                    }
+                };
+                return chars1
+                    .iter()
+                    .map(|&c| fold(c))
+                    .cmp(chars2.iter().map(|&c| fold(c)));
+            }
+        }
+    }
+    collator.ignore_accents = ignore_accents.is_not_nil();
+    collator.numeric = numeric.is_not_nil();
+    collator.compare(&chars1, &chars2)
+}
+
+/// Return t if first arg string is less than second in collation order.
+/// Symbols are also allowed; their print names are used instead.
+///
+/// Strings are collated with the Unicode Collation Algorithm, so the
+/// order is the same on all systems, whatever their locale settings.
+/// Differences in letters and digits are more significant than those
+/// in accents, which are more significant than those in case.  The
+/// differences in whitespace and punctuation characters come last:
+///
+/// (sort \\='("11" "12" "1 1" "1 2" "1.1" "1.2") \\='string-collate-lessp)
+///   => ("11" "1 1" "1.1" "12" "1 2" "1.2")
+///
+/// The optional argument LOCALE, a string like "sv" or "de_DE.UTF-8",
+/// selects the collation rules of a language, like Swedish sorting "ä"
+/// after "z".  Only the language part and the variant before any
+/// codeset matter, as in "de_phone" for the German phonebook order or
+/// "es_trad" for traditional Spanish.  Languages without rules of
+/// their own use the default order.  If LOCALE is "C" or "POSIX",
+/// strings are compared by their characters' code points, like
+/// `string-lessp' does, and IGNORE-ACCENTS and NUMERIC are ignored.
+///
+/// If IGNORE-CASE is non-nil, characters are converted to lower-case
+/// before comparing them.
+///
+/// If IGNORE-ACCENTS is non-nil, accents and other diacritics are
+/// ignored.
+///
+/// If NUMERIC is non-nil, sequences of decimal digits are compared by
+/// their numeric values, so that "file2" sorts before "file10".
+#[lisp_fn(min = "2")]
+fn string_collate_lessp(
+    s1: LispObject,
+    s2: LispObject,
+    locale: LispObject,
+    ignore_case: LispObject,
+    ignore_accents: LispObject,
+    numeric: LispObject,
+) -> LispObject {
+    LispObject::from_bool(
+        collate(s1, s2, locale, ignore_case, ignore_accents, numeric) == Ordering::Less,
+    )
+}
+
+/// Return t if two strings are equal in collation order.
+/// Symbols are also allowed; their print names are used instead.
+///
+/// Strings are collated with the Unicode Collation Algorithm, as
+/// described in `string-collate-lessp', which see for the meaning of
+/// the optional arguments.  Strings may be equal without having
+/// identical contents, when they only differ in characters which are
+/// ignored, or in characters with different code points but the same
+/// meaning, like different grave accent Unicode characters:
+///
+/// (string-collate-equalp (string ?\\uFF40) (string ?\\u1FEF))
+///   => t
+///
+/// Do NOT use this function to compare file names for equality.
+#[lisp_fn(min = "2")]
+fn string_collate_equalp(
+    s1: LispObject,
+    s2: LispObject,
+    locale: LispObject,
+    ignore_case: LispObject,
+    ignore_accents: LispObject,
+    numeric: LispObject,
+) -> LispObject {
+    LispObject::from_bool(
+        collate(s1, s2, locale, ignore_case, ignore_accents, numeric) == Ordering::Equal,
+    )
+}
+
+/// Compare CHARS1 and CHARS2 as version strings, for
+/// `string-version-lessp'.
+fn compare_versions(chars1: &[Codepoint], chars2: &[Codepoint]) -> Ordering {
+    let collator = Collator {
+        numeric: true,
+        shift_variable: false,
+        ..Collator::root()
+    };
+    collator
+        .compare(chars1, chars2)
+        .then_with(|| chars1.cmp(chars2))
+}
+
+/// Return non-nil if S1 is less than S2, as version strings.
+/// Symbols are also allowed; their print names are used instead.
+///
+/// Sequences of decimal digits are compared by their numeric values,
+/// with leading zeros ignored, so that "foo2.png" is less than
+/// "foo12.png".  Other characters are compared as by
+/// `string-collate-lessp' with the default order, except that
+/// punctuation is as significant as letters and digits and comes
+/// before them, so that "foo.png" is less than "foo2.png".  Strings
+/// that are still equal, such as ones differing only in leading zeros,
+/// are compared by their characters' code points, so the order does
+/// not depend on the locale.  Case is significant.
+#[lisp_fn]
+pub fn string_version_lessp(s1: LispObject, s2: LispObject) -> LispObject {
+    let chars1: Vec<Codepoint> = s1.symbol_or_string_as_string().chars().collect();
+    let chars2: Vec<Codepoint> = s2.symbol_or_string_as_string().chars().collect();
+    LispObject::from_bool(compare_versions(&chars1, &chars2) == Ordering::Less)
+}
+
+include!(concat!(env!("OUT_DIR"), "/collate_exports.rs"));
+
+#[cfg(test)]
+fn codepoints(s: &str) -> Vec<Codepoint> {
+    s.chars().map(|c| c as Codepoint).collect()
+}
+
+/// Sort STRINGS with COLLATOR, computing the sort key of each once.
+#[cfg(test)]
+fn sorted(collator: &Collator, strings: &[&str]) -> Vec<String> {
+    let mut keyed: Vec<(Vec<[u32; 4]>, &str)> = strings
+        .iter()
+        .map(|&s| (collator.sort_key(collator.decompose(&codepoints(s))), s))
+        .collect();
+    keyed.sort_by(|a, b| collator.compare_keys(&a.0, &b.0));
+    keyed.iter().map(|&(_, s)| s.to_string()).collect()
+}
+
+#[cfg(test)]
+fn tailored(locale: &str) -> Collator {
+    let (tailoring, upper_before_lower) = find_tailoring(locale).unwrap();
+    Collator {
+        tailoring,
+        upper_before_lower,
+        ..Collator::root()
+    }
+}
+
+#[test]
+fn test_collation_order() {
+    let root = Collator::root();
+    assert_eq!(
+        sorted(&root, &["11", "12", "1 1", "1 2", "1.1", "1.2"]),
+        ["11", "1 1", "1.1", "12", "1 2", "1.2"]
+    );
+    assert_eq!(
+        sorted(&root, &["Eli", "Agustín", "Ævar", "Adrian"]),
+        ["Adrian", "Ævar", "Agustín", "Eli"]
+    );
+    assert_eq!(
+        sorted(&root, &["XYZZY", "xyzzy", "xyzzý", "xyzzx"]),
+        ["xyzzx", "xyzzy", "XYZZY", "xyzzý"]
+    );
+    // Han ideographs sort before unassigned characters, and those
+    // before raw 8-bit bytes.
+    assert_eq!(
+        sorted(&root, &["\u{E0080}", "\u{9FA0}", "\u{20000}", "z"]),
+        ["z", "\u{9FA0}", "\u{20000}", "\u{E0080}"]
+    );
+    assert_eq!(root.compare(&[0x3FFF80], &[0xE0080]), Ordering::Greater);
+    assert_eq!(
+        root.compare(&[0x3FFF80, 0x301], &[0x3FFF80]),
+        Ordering::Greater
+    );
+}
+
+#[test]
+fn test_collation_equality() {
+    let root = Collator::root();
+    let equal = |collator: &Collator, a: &str, b: &str| {
+        collator.compare(&codepoints(a), &codepoints(b)) == Ordering::Equal
+    };
+    assert!(equal(&root, "\u{FF40}", "\u{1FEF}"));
+    assert!(equal(&root, "\u{E9}", "e\u{301}"));
+    assert!(!equal(&root, "e", "\u{E9}"));
+    assert!(!equal(&root, "a-b", "ab"));
+    assert!(equal(&root, "ab\u{0}", "ab"));
+    let ignore_accents = Collator {
+        ignore_accents: true,
+        ..Collator::root()
+    };
+    assert!(equal(&ignore_accents, "r\u{E9}sum\u{E9}", "resume"));
+    assert!(!equal(&ignore_accents, "R\u{E9}sum\u{E9}", "resume"));
+    let ignore_case = Collator {
+        ignore_case: true,
+        ..ignore_accents
+    };
+    assert!(equal(&ignore_case, "R\u{E9}sum\u{E9}", "resume"));
+}
+
+#[test]
+fn test_collation_tailorings() {
+    assert_eq!(sorted(&Collator::root(), &["z", "ä"]), ["ä", "z"]);
+    assert_eq!(sorted(&tailored("sv"), &["ä", "z"]), ["z", "ä"]);
+    assert_eq!(sorted(&tailored("sv_SE.UTF-8"), &["ä", "z"]), ["z", "ä"]);
+    assert_eq!(sorted(&tailored("de"), &["ad", "ä"]), ["ä", "ad"]);
+    assert_eq!(sorted(&tailored("de_phone"), &["ad", "ä"]), ["ad", "ä"]);
+    assert_eq!(sorted(&tailored("cs"), &["i", "ch", "h"]), ["h", "ch", "i"]);
+    assert_eq!(sorted(&tailored("da"), &["a", "A"]), ["A", "a"]);
+    assert_eq!(sorted(&tailored("en_US"), &["A", "a"]), ["a", "A"]);
+    assert!(find_tailoring("POSIX").is_none());
+    assert!(find_tailoring("C.UTF-8").is_none());
+}
+
+#[test]
+fn test_numeric_collation() {
+    let numeric = Collator {
+        numeric: true,
+        ..Collator::root()
+    };
+    assert_eq!(
+        sorted(
+            &numeric,
+            &["file10", "file9", "file010", "file1", "file", "file 2"]
+        ),
+        ["file", "file1", "file 2", "file9", "file10", "file010"]
+    );
+    assert_eq!(sorted(&numeric, &["100", "20", "3"]), ["3", "20", "100"]);
+    assert_eq!(sorted(&numeric, &["\u{663}", "12"]), ["\u{663}", "12"]);
+    assert_eq!(
+        sorted(&Collator::root(), &["100", "20", "3"]),
+        ["100", "20", "3"]
+    );
+}
+
+#[test]
+fn test_version_order() {
+    let mut versions = vec![
+        "foo12.png", "foo2.png", "foo.png", "v010", "v9", "v10", "foo1.125.5", "foo1.25.5.png",
+        "foo1.2-3",
+    ];
+    versions.sort_by(|a, b| compare_versions(&codepoints(a), &codepoints(b)));
+    assert_eq!(
+        versions,
+        [
+            "foo.png", "foo1.2-3", "foo1.25.5.png", "foo1.125.5", "foo2.png", "foo12.png", "v9",
+            "v10", "v010",
+        ]
+    );
+    let compare = |a: &str, b: &str| compare_versions(&codepoints(a), &codepoints(b));
+    assert_eq!(compare("x\u{663}", "x12"), Ordering::Less);
+    assert_eq!(compare("\u{E9}", "e\u{301}"), Ordering::Greater);
+    assert_eq!(compare("1.0", "1.0"), Ordering::Equal);
+}
@@ -0,0 +1,212 @@
+//! The BLAKE2b and BLAKE2s hash functions of RFC 7693, unkeyed and
+//! with their full digest length.
+
+/// The message word permutations of the rounds.
+const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+/// Define an incremental BLAKE2 hash named $name, whose words are
+/// $word, $word_len bytes long, and which has the given initialization
+/// vector, number of rounds and rotations of the mixing function.
+macro_rules! blake2 {
+    ($name:ident, $word:ty, $word_len:expr, $iv:expr, $rounds:expr, $rotations:expr) => {
+        pub struct $name {
+            state: [$word; 8],
+            block: [u8; 16 * $word_len],
+            /// The number of bytes in BLOCK.
+            block_len: usize,
+            /// The number of bytes compressed so far.
+            count: u64,
+        }
+
+        impl $name {
+            const WORD_LEN: usize = $word_len;
+
+            /// The length of the digest in bytes.
+            pub const DIGEST_LEN: usize = 8 * $name::WORD_LEN;
+
+            /// The block size of the hash, as used by HMAC.
+            pub const BLOCK_LEN: usize = 16 * $name::WORD_LEN;
+
+            pub fn new() -> $name {
+                let mut state = $iv;
+                state[0] ^= 0x0101_0000 ^ $name::DIGEST_LEN as $word;
+                $name {
+                    state,
+                    block: [0; 16 * $word_len],
+                    block_len: 0,
+                    count: 0,
+                }
+            }
+
+            fn compress(&mut self, last: bool) {
+                let mut message = [0 as $word; 16];
+                for (idx, word) in message.iter_mut().enumerate() {
+                    for byte in (0..$name::WORD_LEN).rev() {
+                        let byte = self.block[idx * $name::WORD_LEN + byte];
+                        *word = *word << 8 | <$word>::from(byte);
+                    }
+                }
+                let mut v = [0 as $word; 16];
+                v[..8].copy_from_slice(&self.state);
+                v[8..].copy_from_slice(&$iv);
+                // The byte count is 128 bits long, but its high bits can't
+                // be set in practice.
+                v[12] ^= self.count as $word;
+                v[13] ^= (self.count >> 32 >> (8 * $name::WORD_LEN - 32)) as $word;
+                if last {
+                    v[14] = !v[14];
+                }
+
+                let (r1, r2, r3, r4) = $rotations;
+                {
+                    let mut mix = |a: usize, b: usize, c: usize, d: usize, x: $word, y: $word| {
+                        v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+                        v[d] = (v[d] ^ v[a]).rotate_right(r1);
+                        v[c] = v[c].wrapping_add(v[d]);
+                        v[b] = (v[b] ^ v[c]).rotate_right(r2);
+                        v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+                        v[d] = (v[d] ^ v[a]).rotate_right(r3);
+                        v[c] = v[c].wrapping_add(v[d]);
+                        v[b] = (v[b] ^ v[c]).rotate_right(r4);
+                    };
+                    for round in 0..$rounds {
+                        let s = &SIGMA[round % 10];
+                        mix(0, 4, 8, 12, message[s[0]], message[s[1]]);
+                        mix(1, 5, 9, 13, message[s[2]], message[s[3]]);
+                        mix(2, 6, 10, 14, message[s[4]], message[s[5]]);
+                        mix(3, 7, 11, 15, message[s[6]], message[s[7]]);
+                        mix(0, 5, 10, 15, message[s[8]], message[s[9]]);
+                        mix(1, 6, 11, 12, message[s[10]], message[s[11]]);
+                        mix(2, 7, 8, 13, message[s[12]], message[s[13]]);
+                        mix(3, 4, 9, 14, message[s[14]], message[s[15]]);
+                    }
+                }
+                for idx in 0..8 {
+                    self.state[idx] ^= v[idx] ^ v[idx + 8];
+                }
+            }
+
+            pub fn update(&mut self, mut data: &[u8]) {
+                while !data.is_empty() {
+                    // The last block is compressed differently, so a full
+                    // block is kept until more data comes.
+                    if self.block_len == $name::BLOCK_LEN {
+                        self.count += $name::BLOCK_LEN as u64;
+                        self.compress(false);
+                        self.block_len = 0;
+                    }
+                    let len = data.len().min($name::BLOCK_LEN - self.block_len);
+                    let start = self.block_len;
+                    self.block[start..start + len].copy_from_slice(&data[..len]);
+                    self.block_len += len;
+                    data = &data[len..];
+                }
+            }
+
+            /// Write the digest to the start of DEST.
+            pub fn finish(mut self, dest: &mut [u8]) {
+                self.count += self.block_len as u64;
+                for byte in &mut self.block[self.block_len..] {
+                    *byte = 0;
+                }
+                self.compress(true);
+                for (idx, byte) in dest[..$name::DIGEST_LEN].iter_mut().enumerate() {
+                    let word = self.state[idx / $name::WORD_LEN];
+                    *byte = (word >> (8 * (idx % $name::WORD_LEN))) as u8;
+                }
+            }
+        }
+    };
+}
+
+blake2!(
+    Blake2b,
+    u64,
+    8,
+    [
+        0x6A09_E667_F3BC_C908,
+        0xBB67_AE85_84CA_A73B,
+        0x3C6E_F372_FE94_F82B,
+        0xA54F_F53A_5F1D_36F1,
+        0x510E_527F_ADE6_82D1,
+        0x9B05_688C_2B3E_6C1F,
+        0x1F83_D9AB_FB41_BD6B,
+        0x5BE0_CD19_137E_2179,
+    ],
+    12,
+    (32, 24, 16, 63)
+);
+
+blake2!(
+    Blake2s,
+    u32,
+    4,
+    [
+        0x6A09_E667,
+        0xBB67_AE85,
+        0x3C6E_F372,
+        0xA54F_F53A,
+        0x510E_527F,
+        0x9B05_688C,
+        0x1F83_D9AB,
+        0x5BE0_CD19,
+    ],
+    10,
+    (16, 12, 8, 7)
+);
+
+#[cfg(test)]
+fn to_hex(digest: &[u8]) -> String {
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[test]
+fn test_blake2b() {
+    let hash = |parts: &[&[u8]]| {
+        let mut hash = Blake2b::new();
+        for part in parts {
+            hash.update(part);
+        }
+        let mut digest = [0; 64];
+        hash.finish(&mut digest);
+        to_hex(&digest)
+    };
+    assert_eq!(
+        hash(&[b"abc"]),
+        "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d1\
+         7d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923"
+    );
+    assert_eq!(&hash(&[])[..16], "786a02f742015903");
+    // Exactly a block, and more.
+    assert_eq!(&hash(&[&[b'a'; 128], &[b'a'; 72]])[..16], "932355851d75f09c");
+}
+
+#[test]
+fn test_blake2s() {
+    let hash = |parts: &[&[u8]]| {
+        let mut hash = Blake2s::new();
+        for part in parts {
+            hash.update(part);
+        }
+        let mut digest = [0; 32];
+        hash.finish(&mut digest);
+        to_hex(&digest)
+    };
+    assert_eq!(
+        hash(&[b"a", b"bc"]),
+        "508c5e8c327c14e2e1a72ba34eeb452f37458b209ed63a294d999b4c86675982"
+    );
+    assert_eq!(&hash(&[b""])[..16], "69217a3079908094");
+    assert_eq!(&hash(&[&[b'a'; 64], &[b'a'; 136]])[..16], "2b033f9f5ba9cf20");
+}
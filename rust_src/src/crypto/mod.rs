@@ -6,24 +6,43 @@ use md5;
 use sha1;
 use sha2::{Digest, Sha224, Sha256, Sha384, Sha512};
 use std;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::PathBuf;
 use std::slice;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::thread;
 
 use remacs_macros::lisp_fn;
-use remacs_sys::{make_specified_string, make_uninit_string, nsberror, EmacsInt};
+use remacs_sys::{make_specified_string, make_string, make_uninit_string, nsberror, EmacsInt};
+use remacs_sys::{encode_file_name, maybe_quit, report_file_errno, Fexpand_file_name,
+                 Ffind_file_name_handler};
 use remacs_sys::{code_convert_string, extract_data_from_object, preferred_coding_system,
                  string_char_to_byte, validate_subarray, Fcoding_system_p};
 use remacs_sys::{globals, Ffind_operation_coding_system, Flocal_variable_p};
-use remacs_sys::{Qbuffer_file_coding_system, Qcoding_system_error, Qmd5, Qraw_text, Qsha1,
-                 Qsha224, Qsha256, Qsha384, Qsha512, Qstringp, Qwrite_region};
-use remacs_sys::{current_thread, make_buffer_string, record_unwind_current_buffer,
-                 set_buffer_internal};
+use remacs_sys::{Qblake2b, Qblake2s, Qbuffer_file_coding_system, Qcoding_system_error, Qmd5,
+                 Qraw_text, Qsha1, Qsha224, Qsha256, Qsha384, Qsha3_224, Qsha3_256, Qsha3_384,
+                 Qsha3_512, Qsha512, Qstringp, Qwrite_region};
+use remacs_sys::{buf_charpos_to_bytepos, c_specpdl_index, record_unwind_current_buffer,
+                 record_unwind_protect, set_buffer_internal, unbind_to, Lisp_Object};
+use remacs_sys::{fast_coding_spec, fast_encoding_spec, FastCodingEol, FastCodingType,
+                 Utf16Endian, UtfBomType};
 
 use buffers::{buffer_file_name, current_buffer, get_buffer, LispBufferRef};
-use lisp::{LispNumber, LispObject};
+use coding;
+use lisp::{intern, LispObject};
 use lisp::defsubr;
-use multibyte::LispStringRef;
+use multibyte::{char_head_p, LispStringRef};
 use symbols::{fboundp, symbol_name};
-use threads::ThreadState;
+
+mod blake2;
+mod sha3;
+
+use self::blake2::{Blake2b, Blake2s};
+use self::sha3::Sha3;
 
 #[derive(Clone, Copy)]
 enum HashAlg {
@@ -33,32 +52,176 @@ enum HashAlg {
     SHA256,
     SHA384,
     SHA512,
+    SHA3_224,
+    SHA3_256,
+    SHA3_384,
+    SHA3_512,
+    BLAKE2B,
+    BLAKE2S,
 }
 
-static MD5_DIGEST_LEN: usize = 16;
-static SHA1_DIGEST_LEN: usize = 20;
-static SHA224_DIGEST_LEN: usize = 224 / 8;
-static SHA256_DIGEST_LEN: usize = 256 / 8;
-static SHA384_DIGEST_LEN: usize = 384 / 8;
-static SHA512_DIGEST_LEN: usize = 512 / 8;
+/// The algorithms in the order listed by `secure-hash-algorithms'.
+static HASH_ALGS: [HashAlg; 12] = [
+    HashAlg::MD5,
+    HashAlg::SHA1,
+    HashAlg::SHA224,
+    HashAlg::SHA256,
+    HashAlg::SHA384,
+    HashAlg::SHA512,
+    HashAlg::SHA3_224,
+    HashAlg::SHA3_256,
+    HashAlg::SHA3_384,
+    HashAlg::SHA3_512,
+    HashAlg::BLAKE2B,
+    HashAlg::BLAKE2S,
+];
+
+impl HashAlg {
+    fn symbol(self) -> LispObject {
+        LispObject::from(unsafe {
+            match self {
+                HashAlg::MD5 => Qmd5,
+                HashAlg::SHA1 => Qsha1,
+                HashAlg::SHA224 => Qsha224,
+                HashAlg::SHA256 => Qsha256,
+                HashAlg::SHA384 => Qsha384,
+                HashAlg::SHA512 => Qsha512,
+                HashAlg::SHA3_224 => Qsha3_224,
+                HashAlg::SHA3_256 => Qsha3_256,
+                HashAlg::SHA3_384 => Qsha3_384,
+                HashAlg::SHA3_512 => Qsha3_512,
+                HashAlg::BLAKE2B => Qblake2b,
+                HashAlg::BLAKE2S => Qblake2s,
+            }
+        })
+    }
+
+    fn digest_len(self) -> usize {
+        match self {
+            HashAlg::MD5 => 16,
+            HashAlg::SHA1 => 20,
+            HashAlg::SHA224 | HashAlg::SHA3_224 => 224 / 8,
+            HashAlg::SHA256 | HashAlg::SHA3_256 => 256 / 8,
+            HashAlg::SHA384 | HashAlg::SHA3_384 => 384 / 8,
+            HashAlg::SHA512 | HashAlg::SHA3_512 => 512 / 8,
+            HashAlg::BLAKE2B => Blake2b::DIGEST_LEN,
+            HashAlg::BLAKE2S => Blake2s::DIGEST_LEN,
+        }
+    }
+
+    /// Return the size of the blocks the hash works on, which is the
+    /// length HMAC pads its key to.
+    fn block_len(self) -> usize {
+        match self {
+            HashAlg::MD5 | HashAlg::SHA1 | HashAlg::SHA224 | HashAlg::SHA256 => 64,
+            HashAlg::SHA384 | HashAlg::SHA512 => 128,
+            // The rate of the sponge.
+            HashAlg::SHA3_224 | HashAlg::SHA3_256 | HashAlg::SHA3_384 | HashAlg::SHA3_512 => {
+                200 - 2 * self.digest_len()
+            }
+            HashAlg::BLAKE2B => Blake2b::BLOCK_LEN,
+            HashAlg::BLAKE2S => Blake2s::BLOCK_LEN,
+        }
+    }
+
+    fn hasher(self) -> Hasher {
+        match self {
+            HashAlg::MD5 => Hasher::MD5(md5::Context::new()),
+            HashAlg::SHA1 => Hasher::SHA1(sha1::Sha1::new()),
+            HashAlg::SHA224 => Hasher::SHA224(Sha224::new()),
+            HashAlg::SHA256 => Hasher::SHA256(Sha256::new()),
+            HashAlg::SHA384 => Hasher::SHA384(Sha384::new()),
+            HashAlg::SHA512 => Hasher::SHA512(Sha512::new()),
+            HashAlg::SHA3_224 | HashAlg::SHA3_256 | HashAlg::SHA3_384 | HashAlg::SHA3_512 => {
+                Hasher::SHA3(Sha3::new(self.digest_len()))
+            }
+            HashAlg::BLAKE2B => Hasher::BLAKE2B(Blake2b::new()),
+            HashAlg::BLAKE2S => Hasher::BLAKE2S(Blake2s::new()),
+        }
+    }
+
+    /// Hash the concatenation of PARTS, and write the digest to the
+    /// start of DEST_BUF, which must be long enough to hold it.
+    fn hash(self, parts: &[&[u8]], dest_buf: &mut [u8]) {
+        let mut hasher = self.hasher();
+        for part in parts {
+            hasher.update(part);
+        }
+        hasher.finish(dest_buf);
+    }
+}
+
+/// A hash in progress, which is fed its input in pieces.
+enum Hasher {
+    MD5(md5::Context),
+    SHA1(sha1::Sha1),
+    SHA224(Sha224),
+    SHA256(Sha256),
+    SHA384(Sha384),
+    SHA512(Sha512),
+    SHA3(Sha3),
+    BLAKE2B(Blake2b),
+    BLAKE2S(Blake2s),
+}
+
+impl Hasher {
+    fn update(&mut self, data: &[u8]) {
+        match *self {
+            Hasher::MD5(ref mut ctx) => ctx.consume(data),
+            Hasher::SHA1(ref mut hasher) => hasher.update(data),
+            Hasher::SHA224(ref mut hasher) => hasher.input(data),
+            Hasher::SHA256(ref mut hasher) => hasher.input(data),
+            Hasher::SHA384(ref mut hasher) => hasher.input(data),
+            Hasher::SHA512(ref mut hasher) => hasher.input(data),
+            Hasher::SHA3(ref mut hasher) => hasher.update(data),
+            Hasher::BLAKE2B(ref mut hasher) => hasher.update(data),
+            Hasher::BLAKE2S(ref mut hasher) => hasher.update(data),
+        }
+    }
+
+    /// Write the digest to the start of DEST_BUF, which must be long
+    /// enough to hold it.
+    fn finish(self, dest_buf: &mut [u8]) {
+        match self {
+            Hasher::MD5(ctx) => {
+                let output = ctx.compute();
+                dest_buf[..output.len()].copy_from_slice(&*output)
+            }
+            Hasher::SHA1(hasher) => {
+                let output = hasher.digest().bytes();
+                dest_buf[..output.len()].copy_from_slice(&output)
+            }
+            Hasher::SHA224(hasher) => sha2_finish(hasher, dest_buf),
+            Hasher::SHA256(hasher) => sha2_finish(hasher, dest_buf),
+            Hasher::SHA384(hasher) => sha2_finish(hasher, dest_buf),
+            Hasher::SHA512(hasher) => sha2_finish(hasher, dest_buf),
+            Hasher::SHA3(hasher) => hasher.finish(dest_buf),
+            Hasher::BLAKE2B(hasher) => hasher.finish(dest_buf),
+            Hasher::BLAKE2S(hasher) => hasher.finish(dest_buf),
+        }
+    }
+}
+
+/// Given an instance of `Digest`, write its hash to `dest_buf`.
+fn sha2_finish<D>(hasher: D, dest_buf: &mut [u8])
+where
+    D: Digest,
+{
+    let output = hasher.result();
+    dest_buf[..output.len()].copy_from_slice(&output)
+}
 
 fn hash_alg(algorithm: LispObject) -> HashAlg {
     algorithm.as_symbol_or_error();
-    if algorithm.to_raw() == unsafe { Qmd5 } {
-        HashAlg::MD5
-    } else if algorithm.to_raw() == unsafe { Qsha1 } {
-        HashAlg::SHA1
-    } else if algorithm.to_raw() == unsafe { Qsha224 } {
-        HashAlg::SHA224
-    } else if algorithm.to_raw() == unsafe { Qsha256 } {
-        HashAlg::SHA256
-    } else if algorithm.to_raw() == unsafe { Qsha384 } {
-        HashAlg::SHA384
-    } else if algorithm.to_raw() == unsafe { Qsha512 } {
-        HashAlg::SHA512
-    } else {
-        let name = symbol_name(algorithm).as_string_or_error();
-        error!("Invalid algorithm arg: {:?}\0", &name.as_slice());
+    match HASH_ALGS
+        .iter()
+        .find(|alg| alg.symbol().to_raw() == algorithm.to_raw())
+    {
+        Some(&alg) => alg,
+        None => {
+            let name = symbol_name(algorithm).as_string_or_error();
+            error!("Invalid algorithm arg: {:?}\0", &name.as_slice());
+        }
     }
 }
 
@@ -89,66 +252,76 @@ fn get_coding_system_for_string(string: LispStringRef, coding_system: LispObject
     }
 }
 
+/// Decide the coding system to encode the text of BUFFER between the
+/// character positions B and E with, as `write-region' would, unless
+/// CODING-SYSTEM is given.  If the choice is invalid, signal an error,
+/// or use `raw-text' if NOERROR is non-nil.  BUFFER must be current.
 fn get_coding_system_for_buffer(
     object: LispObject,
     buffer: LispBufferRef,
     start: LispObject,
     end: LispObject,
-    start_byte: ptrdiff_t,
-    end_byte: ptrdiff_t,
+    b: ptrdiff_t,
+    e: ptrdiff_t,
     coding_system: LispObject,
+    noerror: LispObject,
 ) -> LispObject {
-    /* Decide the coding-system to encode the data with.
-       See fileio.c:Fwrite-region */
     if coding_system.is_not_nil() {
         return coding_system;
     }
-    if LispObject::from(unsafe { globals.f_Vcoding_system_for_write }).is_not_nil() {
-        return LispObject::from(unsafe { globals.f_Vcoding_system_for_write });
+    let nil = LispObject::constant_nil();
+    let coding_system_for_write = LispObject::from(unsafe { globals.f_Vcoding_system_for_write });
+    if coding_system_for_write.is_not_nil() {
+        return check_coding_system_or_error(coding_system_for_write, noerror);
     }
-    if LispObject::from(buffer.buffer_file_coding_system).is_nil() || LispObject::from(unsafe {
-        Flocal_variable_p(
-            Qbuffer_file_coding_system,
-            LispObject::constant_nil().to_raw(),
-        )
-    }).is_nil()
+
+    /* See fileio.c:Fwrite-region */
+    let buffer_coding_system = LispObject::from(buffer.buffer_file_coding_system);
+    let mut coding_system = buffer_coding_system;
+    let mut force_raw_text = false;
+    if coding_system.is_nil()
+        || LispObject::from(unsafe {
+            Flocal_variable_p(Qbuffer_file_coding_system, nil.to_raw())
+        }).is_nil()
     {
-        if LispObject::from(buffer.enable_multibyte_characters).is_nil() {
-            return LispObject::from(unsafe { Qraw_text });
-        }
+        coding_system = nil;
+        force_raw_text = LispObject::from(buffer.enable_multibyte_characters).is_nil();
     }
-    if buffer_file_name(object).is_not_nil() {
+    let file_name = buffer_file_name(object);
+    if coding_system.is_nil() && file_name.is_not_nil() {
         /* Check file-coding-system-alist. */
         let mut args = [
             unsafe { Qwrite_region },
             start.to_raw(),
             end.to_raw(),
-            buffer_file_name(object).to_raw(),
+            file_name.to_raw(),
         ];
         let val = LispObject::from(unsafe {
             Ffind_operation_coding_system(4, args.as_mut_ptr())
         });
         if val.is_cons() && val.as_cons_or_error().cdr().is_not_nil() {
-            return val.as_cons_or_error().cdr();
+            coding_system = val.as_cons_or_error().cdr();
         }
     }
-    if LispObject::from(buffer.buffer_file_coding_system).is_not_nil() {
+    if coding_system.is_nil() && buffer_coding_system.is_not_nil() {
         /* If we still have not decided a coding system, use the
            default value of buffer-file-coding-system. */
-        return LispObject::from(buffer.buffer_file_coding_system);
+        coding_system = buffer_coding_system;
     }
     let sscsf = LispObject::from(unsafe { globals.f_Vselect_safe_coding_system_function });
-    if fboundp(sscsf).is_not_nil() {
+    if force_raw_text {
+        coding_system = LispObject::from(unsafe { Qraw_text });
+    } else if fboundp(sscsf).is_not_nil() {
         /* Confirm that VAL can surely encode the current region. */
-        return call!(
+        coding_system = call!(
             sscsf,
-            LispObject::from_natnum(start_byte as EmacsInt),
-            LispObject::from_natnum(end_byte as EmacsInt),
+            LispObject::from_natnum(b as EmacsInt),
+            LispObject::from_natnum(e as EmacsInt),
             coding_system,
-            LispObject::constant_nil()
+            nil
         );
     }
-    LispObject::constant_nil()
+    check_coding_system_or_error(coding_system, noerror)
 }
 
 fn get_input_from_string(
@@ -198,110 +371,6 @@ fn get_input_from_string(
     }
 }
 
-fn get_input_from_buffer(
-    mut buffer: LispBufferRef,
-    start: LispObject,
-    end: LispObject,
-    start_byte: &mut ptrdiff_t,
-    end_byte: &mut ptrdiff_t,
-) -> LispObject {
-    let mut prev_buffer = ThreadState::current_buffer().as_mut();
-    unsafe { record_unwind_current_buffer() };
-    unsafe { set_buffer_internal(buffer.as_mut()) };
-    *start_byte = if start.is_nil() {
-        buffer.begv
-    } else {
-        match start.as_number_coerce_marker_or_error() {
-            LispNumber::Fixnum(n) => n as ptrdiff_t,
-            LispNumber::Float(n) => n as ptrdiff_t,
-        }
-    };
-    *end_byte = if end.is_nil() {
-        buffer.zv
-    } else {
-        match end.as_number_coerce_marker_or_error() {
-            LispNumber::Fixnum(n) => n as ptrdiff_t,
-            LispNumber::Float(n) => n as ptrdiff_t,
-        }
-    };
-    if start_byte > end_byte {
-        std::mem::swap(start_byte, end_byte);
-    }
-    if !(buffer.begv <= *start_byte && *end_byte <= buffer.zv) {
-        args_out_of_range!(start, end);
-    }
-    let string = LispObject::from(unsafe { make_buffer_string(*start_byte, *end_byte, false) });
-    unsafe { set_buffer_internal(prev_buffer) };
-    // TODO: this needs to be std::mem::size_of<specbinding>()
-    unsafe { (*current_thread).m_specpdl_ptr = (*current_thread).m_specpdl_ptr.offset(-40) };
-    string
-}
-
-fn get_input(
-    object: LispObject,
-    string: &mut Option<LispStringRef>,
-    buffer: &Option<LispBufferRef>,
-    start: LispObject,
-    end: LispObject,
-    coding_system: LispObject,
-    noerror: LispObject,
-) -> LispStringRef {
-    if object.is_string() {
-        if string.unwrap().is_multibyte() {
-            let coding_system = check_coding_system_or_error(
-                get_coding_system_for_string(string.unwrap(), coding_system),
-                noerror,
-            );
-            *string = Some(
-                LispObject::from(unsafe {
-                    code_convert_string(
-                        object.to_raw(),
-                        coding_system.to_raw(),
-                        LispObject::constant_nil().to_raw(),
-                        true,
-                        false,
-                        true,
-                    )
-                }).as_string_or_error(),
-            )
-        }
-        get_input_from_string(object, string.unwrap(), start, end).as_string_or_error()
-    } else if object.is_buffer() {
-        let mut start_byte: ptrdiff_t = 0;
-        let mut end_byte: ptrdiff_t = 0;
-        let s = get_input_from_buffer(buffer.unwrap(), start, end, &mut start_byte, &mut end_byte);
-        let ss = s.as_string_or_error();
-        if ss.is_multibyte() {
-            let coding_system = check_coding_system_or_error(
-                get_coding_system_for_buffer(
-                    object,
-                    buffer.unwrap(),
-                    start,
-                    end,
-                    start_byte,
-                    end_byte,
-                    coding_system,
-                ),
-                noerror,
-            );
-            LispObject::from(unsafe {
-                code_convert_string(
-                    s.to_raw(),
-                    coding_system.to_raw(),
-                    LispObject::constant_nil().to_raw(),
-                    true,
-                    false,
-                    false,
-                )
-            }).as_string_or_error()
-        } else {
-            ss
-        }
-    } else {
-        wrong_type!(Qstringp, object);
-    }
-}
-
 /// Return MD5 message digest of OBJECT, a buffer or string.
 ///
 /// A message digest is a cryptographic checksum of a document, and the
@@ -349,7 +418,8 @@ fn md5(
 
 /// Return the secure hash of OBJECT, a buffer or string.
 /// ALGORITHM is a symbol specifying the hash to use:
-/// md5, sha1, sha224, sha256, sha384 or sha512.
+/// md5, sha1, sha224, sha256, sha384, sha512, sha3-224, sha3-256,
+/// sha3-384, sha3-512, blake2b or blake2s.
 ///
 /// The two optional arguments START and END are positions specifying for
 /// which part of OBJECT to compute the hash.  If nil or omitted, uses the
@@ -377,15 +447,73 @@ fn secure_hash(
     )
 }
 
-fn _secure_hash(
-    algorithm: HashAlg,
+/// Return the HMAC of OBJECT, a buffer or string, keyed with KEY.
+/// ALGORITHM is a symbol specifying the hash HMAC is built on, as for
+/// `secure-hash'.  KEY is a string or buffer; like OBJECT, it is first
+/// encoded if it is multibyte.
+///
+/// The two optional arguments START and END are positions specifying for
+/// which part of OBJECT to compute the HMAC.  If nil or omitted, uses the
+/// whole OBJECT.
+///
+/// If BINARY is non-nil, returns a string in binary form.
+#[lisp_fn(min = "3")]
+fn secure_hmac(
+    algorithm: LispObject,
+    key: LispObject,
     object: LispObject,
     start: LispObject,
     end: LispObject,
-    coding_system: LispObject,
-    noerror: LispObject,
     binary: LispObject,
 ) -> LispObject {
+    let algorithm = hash_alg(algorithm);
+    let (digest_len, block_len) = (algorithm.digest_len(), algorithm.block_len());
+    let nil = LispObject::constant_nil();
+
+    // Keys longer than a block are hashed first; the key is then padded
+    // to a block with zeros.
+    let mut key_block = vec![0; block_len];
+    let mut key_data = Vec::new();
+    extract_data_pieces(key, nil, nil, nil, nil, |data| key_data.extend_from_slice(data));
+    if key_data.len() > block_len {
+        algorithm.hash(&[&key_data], &mut key_block);
+    } else {
+        key_block[..key_data.len()].copy_from_slice(&key_data);
+    }
+    let inner_pad: Vec<u8> = key_block.iter().map(|byte| byte ^ 0x36).collect();
+    let outer_pad: Vec<u8> = key_block.iter().map(|byte| byte ^ 0x5C).collect();
+
+    let mut inner = algorithm.hasher();
+    inner.update(&inner_pad);
+    extract_data_pieces(object, start, end, nil, nil, |data| inner.update(data));
+    let mut inner_digest = vec![0; digest_len];
+    inner.finish(&mut inner_digest);
+    make_digest(digest_len, binary, |dest_buf| {
+        algorithm.hash(&[&outer_pad, &inner_digest], dest_buf)
+    })
+}
+
+/// Return a list of all the supported `secure-hash' algorithms.
+#[lisp_fn]
+fn secure_hash_algorithms() -> LispObject {
+    HASH_ALGS
+        .iter()
+        .rev()
+        .fold(LispObject::constant_nil(), |list, alg| {
+            LispObject::cons(alg.symbol(), list)
+        })
+}
+
+/// Return the text of OBJECT, a buffer or string, between START and
+/// END, encoded with CODING-SYSTEM if it is multibyte.  The text is
+/// only valid until the next garbage collection.
+fn extract_data<'a>(
+    object: LispObject,
+    start: LispObject,
+    end: LispObject,
+    coding_system: LispObject,
+    noerror: LispObject,
+) -> &'a [u8] {
     let spec = list!(object, start, end, coding_system, noerror);
     let mut start_byte: ptrdiff_t = 0;
     let mut end_byte: ptrdiff_t = 0;
@@ -395,23 +523,165 @@ fn _secure_hash(
         error!("secure_hash: failed to extract data from object, aborting!");
     }
 
-    let input_slice = unsafe {
+    unsafe {
         slice::from_raw_parts(
             input.offset(start_byte) as *mut u8,
             (end_byte - start_byte) as usize,
         )
+    }
+}
+
+/// Pass the text of OBJECT, a buffer or string, between START and END
+/// to CONSUMER in one or more pieces, encoded as by `extract_data`.
+fn extract_data_pieces<F>(
+    object: LispObject,
+    start: LispObject,
+    end: LispObject,
+    coding_system: LispObject,
+    noerror: LispObject,
+    mut consumer: F,
+) where
+    F: FnMut(&[u8]),
+{
+    if object.is_buffer() {
+        extract_buffer_data(object, start, end, coding_system, noerror, &mut consumer);
+    } else {
+        consumer(extract_data(object, start, end, coding_system, noerror));
+    }
+}
+
+/// The maximal size of the pieces in which the text of a buffer is
+/// encoded by `extract_buffer_data`.
+const BUFFER_DATA_CHUNK_SIZE: ptrdiff_t = 64 * 1024;
+
+/// Like `extract_data_pieces`, for a buffer.  The text is read in place
+/// on both sides of the gap.  If it needs encoding and its coding
+/// system has a native encoder, it is encoded a chunk at a time, so
+/// that the buffer text is never copied as a whole.
+fn extract_buffer_data<F>(
+    object: LispObject,
+    start: LispObject,
+    end: LispObject,
+    coding_system: LispObject,
+    noerror: LispObject,
+    consumer: &mut F,
+) where
+    F: FnMut(&[u8]),
+{
+    let nil = LispObject::constant_nil();
+    let mut buffer = object.as_buffer_or_error();
+
+    let count = unsafe { c_specpdl_index() };
+    unsafe {
+        record_unwind_current_buffer();
+        set_buffer_internal(buffer.as_mut());
+    }
+    let mut b = if start.is_nil() {
+        buffer.begv
+    } else {
+        start.as_fixnum_coerce_marker_or_error() as ptrdiff_t
+    };
+    let mut e = if end.is_nil() {
+        buffer.zv
+    } else {
+        end.as_fixnum_coerce_marker_or_error() as ptrdiff_t
     };
+    if b > e {
+        std::mem::swap(&mut b, &mut e);
+    }
+    if !(buffer.begv <= b && e <= buffer.zv) {
+        args_out_of_range!(start, end);
+    }
+    let coding_system =
+        get_coding_system_for_buffer(object, buffer, start, end, b, e, coding_system, noerror);
+    unsafe { unbind_to(count, nil.to_raw()) };
+
+    let beg_byte = unsafe { buf_charpos_to_bytepos(buffer.as_ptr(), b) };
+    let end_byte = unsafe { buf_charpos_to_bytepos(buffer.as_ptr(), e) };
+    if LispObject::from(buffer.enable_multibyte_characters).is_nil() {
+        let (before_gap, after_gap) = buffer.byte_slices(beg_byte, end_byte);
+        consumer(before_gap);
+        consumer(after_gap);
+        return;
+    }
 
-    type HashFn = fn(&[u8], &mut [u8]);
-    let (digest_size, hash_func) = match algorithm {
-        HashAlg::MD5 => (MD5_DIGEST_LEN, md5_buffer as HashFn),
-        HashAlg::SHA1 => (SHA1_DIGEST_LEN, sha1_buffer as HashFn),
-        HashAlg::SHA224 => (SHA224_DIGEST_LEN, sha224_buffer as HashFn),
-        HashAlg::SHA256 => (SHA256_DIGEST_LEN, sha256_buffer as HashFn),
-        HashAlg::SHA384 => (SHA384_DIGEST_LEN, sha384_buffer as HashFn),
-        HashAlg::SHA512 => (SHA512_DIGEST_LEN, sha512_buffer as HashFn),
+    let mut spec = fast_coding_spec {
+        coding_type: FastCodingType::None,
+        bom: UtfBomType::Without,
+        endian: Utf16Endian::Big,
+        eol: FastCodingEol::Unix,
+    };
+    if !unsafe { fast_encoding_spec(coding_system.to_raw(), &mut spec) } {
+        // The generic encoders may keep state across the text, so they
+        // get all of it at once.
+        consumer(extract_data(object, start, end, coding_system, noerror));
+        return;
+    }
+    let bom_len = match (spec.bom, spec.coding_type) {
+        (UtfBomType::With, FastCodingType::Utf8) => 3,
+        (UtfBomType::With, FastCodingType::Utf16) => 2,
+        _ => 0,
     };
 
+    let mut pos = beg_byte;
+    while pos < end_byte {
+        // Chunks end at a character boundary, and don't span the gap.
+        let limit = if pos < buffer.gpt_byte() {
+            end_byte.min(buffer.gpt_byte())
+        } else {
+            end_byte
+        };
+        let mut chunk_end = limit.min(pos + BUFFER_DATA_CHUNK_SIZE);
+        while chunk_end < limit && !char_head_p(buffer.fetch_byte(chunk_end)) {
+            chunk_end += 1;
+        }
+        let len = (chunk_end - pos) as usize;
+        let text = unsafe { slice::from_raw_parts(buffer.byte_pos_addr(pos), len) };
+        match coding::encode(text, true, &spec) {
+            Some(encoded) => consumer(&encoded),
+            None => {
+                // The generic encoder deals with the characters the
+                // native one can't encode, but it starts each piece
+                // with a byte order mark.
+                let encoded = LispObject::from(unsafe {
+                    let ptr = text.as_ptr() as *const libc::c_char;
+                    let string = make_specified_string(ptr, -1, len as ptrdiff_t, true);
+                    let coding_system = coding_system.to_raw();
+                    code_convert_string(string, coding_system, nil.to_raw(), true, false, true)
+                }).as_string_or_error();
+                let skip = if pos == beg_byte { 0 } else { bom_len };
+                consumer(&encoded.as_slice()[skip..]);
+            }
+        }
+        // Only the start of the text gets a byte order mark.
+        spec.bom = UtfBomType::Without;
+        pos = chunk_end;
+    }
+}
+
+fn _secure_hash(
+    algorithm: HashAlg,
+    object: LispObject,
+    start: LispObject,
+    end: LispObject,
+    coding_system: LispObject,
+    noerror: LispObject,
+    binary: LispObject,
+) -> LispObject {
+    let mut hasher = algorithm.hasher();
+    extract_data_pieces(object, start, end, coding_system, noerror, |data| {
+        hasher.update(data)
+    });
+    make_digest(algorithm.digest_len(), binary, |dest_buf| hasher.finish(dest_buf))
+}
+
+/// Return a new string holding the digest of DIGEST_SIZE bytes which
+/// HASH_FUNC writes to the start of its argument.  The string is in hex
+/// form unless BINARY is non-nil.
+fn make_digest<F>(digest_size: usize, binary: LispObject, hash_func: F) -> LispObject
+where
+    F: FnOnce(&mut [u8]),
+{
     let buffer_size = if binary.is_nil() {
         (digest_size * 2) as EmacsInt
     } else {
@@ -419,7 +689,7 @@ fn _secure_hash(
     };
     let digest = LispObject::from(unsafe { make_uninit_string(buffer_size as EmacsInt) });
     let digest_str = digest.as_string_or_error();
-    hash_func(input_slice, digest_str.as_mut_slice());
+    hash_func(digest_str.as_mut_slice());
     if binary.is_nil() {
         hexify_digest_string(digest_str.as_mut_slice(), digest_size);
     }
@@ -443,88 +713,280 @@ fn hexify_digest_string(buffer: &mut [u8], len: usize) {
     }
 }
 
-// For the following hash functions, the caller must ensure that the
-// destination buffer is at least long enough to hold the
-// digest. Additionally, the caller may have been asked to return a
-// hex string, in which case dest_buf will be twice as long as the
-// digest.
+/// Return a hash of the contents of BUFFER-OR-NAME.
+/// This hash is performed on the raw internal format of the buffer,
+/// disregarding any coding systems.  If nil, use the current buffer.
+///
+/// Optional argument ALGORITHM is a symbol specifying the hash to use,
+/// as for `secure-hash'.  It defaults to sha1.
+#[lisp_fn(min = "0")]
+fn buffer_hash(buffer_or_name: LispObject, algorithm: LispObject) -> LispObject {
+    let buffer = if buffer_or_name.is_nil() {
+        current_buffer()
+    } else {
+        get_buffer(buffer_or_name)
+    };
 
-fn md5_buffer(buffer: &[u8], dest_buf: &mut [u8]) {
-    let output = md5::compute(buffer);
-    dest_buf[..output.len()].copy_from_slice(&*output)
+    if buffer.is_nil() {
+        unsafe { nsberror(buffer_or_name.to_raw()) };
+    }
+    let algorithm = if algorithm.is_nil() {
+        HashAlg::SHA1
+    } else {
+        hash_alg(algorithm)
+    };
+    let b = buffer.as_buffer().unwrap();
+    let mut hasher = algorithm.hasher();
+    let (before_gap, after_gap) = b.byte_slices(b.beg_byte(), b.z_byte());
+    hasher.update(before_gap);
+    hasher.update(after_gap);
+    make_digest(algorithm.digest_len(), LispObject::constant_nil(), |dest_buf| {
+        hasher.finish(dest_buf)
+    })
 }
 
-fn sha1_buffer(buffer: &[u8], dest_buf: &mut [u8]) {
-    let mut hasher = sha1::Sha1::new();
-    hasher.update(buffer);
-    let output = hasher.digest().bytes();
-    dest_buf[..output.len()].copy_from_slice(&output)
+/// The size of the chunks in which `file-hash' reads files.
+const FILE_HASH_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The number of seconds between checks for the result of a `file-hash'
+/// running in the background, by a timer which is cancelled when the
+/// job is done or cancelled.
+const FILE_HASH_POLL_INTERVAL: f64 = 0.05;
+
+type FileHashResult = io::Result<Vec<u8>>;
+
+/// A `file-hash' running in the background.
+struct FileHashJob {
+    receiver: Receiver<FileHashResult>,
+    /// Set to make the thread give up.
+    cancelled: Arc<AtomicBool>,
+    /// The timer which runs `file-hash--poll' for the job.
+    timer: LispObject,
+}
+
+lazy_static! {
+    /// The `file-hash' jobs running in the background, by number.
+    static ref FILE_HASH_JOBS: Mutex<HashMap<usize, FileHashJob>> = Mutex::new(HashMap::new());
 }
 
-/// Given an instance of `Digest`, and `buffer` write its hash to `dest_buf`.
-fn sha2_hash_buffer<D>(hasher: D, buffer: &[u8], dest_buf: &mut [u8])
+static FILE_HASH_JOB_COUNT: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// Hash the contents of FILE with ALGORITHM, a chunk at a time.  Give up
+/// with an `Interrupted' error if STOP returns true before a chunk.
+fn hash_file<F>(algorithm: HashAlg, mut file: File, stop: F) -> FileHashResult
 where
-    D: Digest,
+    F: Fn() -> bool,
 {
-    let mut hasher = hasher;
-    hasher.input(buffer);
-    let output = hasher.result();
-    dest_buf[..output.len()].copy_from_slice(&output)
+    let mut hasher = algorithm.hasher();
+    let mut chunk = vec![0; FILE_HASH_CHUNK_SIZE];
+    loop {
+        if stop() {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "Quit"));
+        }
+        match file.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(len) => hasher.update(&chunk[..len]),
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    let mut digest = vec![0; algorithm.digest_len()];
+    hasher.finish(&mut digest);
+    Ok(digest)
 }
 
-fn sha224_buffer(buffer: &[u8], dest_buf: &mut [u8]) {
-    sha2_hash_buffer(Sha224::new(), buffer, dest_buf);
+/// Return whether the user asked to quit, and quitting is allowed.
+fn quit_pending() -> bool {
+    unsafe {
+        LispObject::from(globals.f_Vquit_flag).is_not_nil()
+            && LispObject::from(globals.f_Vinhibit_quit).is_nil()
+    }
 }
 
-fn sha256_buffer(buffer: &[u8], dest_buf: &mut [u8]) {
-    sha2_hash_buffer(Sha256::new(), buffer, dest_buf);
+/// Return the path of the file whose encoded name is ENCODED.
+#[cfg(unix)]
+fn file_name_path(encoded: LispStringRef) -> PathBuf {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    PathBuf::from(OsStr::from_bytes(encoded.as_slice()))
 }
 
-fn sha384_buffer(buffer: &[u8], dest_buf: &mut [u8]) {
-    sha2_hash_buffer(Sha384::new(), buffer, dest_buf);
+/// Return the path of the file whose encoded name is ENCODED.  File
+/// names are encoded in UTF-8 where they aren't plain bytes.
+#[cfg(not(unix))]
+fn file_name_path(encoded: LispStringRef) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(encoded.as_slice()).into_owned())
 }
 
-fn sha512_buffer(buffer: &[u8], dest_buf: &mut [u8]) {
-    sha2_hash_buffer(Sha512::new(), buffer, dest_buf);
+fn make_error_message(err: &io::Error) -> LispObject {
+    let message = err.to_string();
+    LispObject::from(unsafe {
+        make_string(
+            message.as_ptr() as *const libc::c_char,
+            message.len() as ptrdiff_t,
+        )
+    })
 }
 
-/// Return a hash of the contents of BUFFER-OR-NAME.
-/// This hash is performed on the raw internal format of the buffer,
-/// disregarding any coding systems.  If nil, use the current buffer.
-#[lisp_fn(min = "0")]
-fn buffer_hash(buffer_or_name: LispObject) -> LispObject {
-    let buffer = if buffer_or_name.is_nil() {
-        current_buffer()
-    } else {
-        get_buffer(buffer_or_name)
+/// Return the hash of the contents of the file FILENAME.
+/// ALGORITHM is a symbol specifying the hash to use, as for `secure-hash'.
+///
+/// The file is read in chunks, so it need not fit in memory, and its
+/// contents are hashed as they are, without any decoding.
+///
+/// If BINARY is non-nil, returns a string in binary form.
+///
+/// If CALLBACK is non-nil, hash the file in a background thread and
+/// return a job number at once.  CALLBACK is called with two arguments
+/// when it is done: the hash and nil, or nil and an error message if
+/// reading the file failed.  Passing the job number to
+/// `file-hash-cancel' stops the thread, and CALLBACK is not called.
+#[lisp_fn(min = "2")]
+fn file_hash(
+    algorithm: LispObject,
+    filename: LispObject,
+    binary: LispObject,
+    callback: LispObject,
+) -> LispObject {
+    let nil = LispObject::constant_nil();
+    let alg = hash_alg(algorithm);
+    filename.as_string_or_error();
+    let filename = LispObject::from(unsafe { Fexpand_file_name(filename.to_raw(), nil.to_raw()) });
+
+    // If the file name has special constructs in it, call the
+    // corresponding file handler.
+    let operation = intern("file-hash");
+    let handler =
+        LispObject::from(unsafe { Ffind_file_name_handler(filename.to_raw(), operation.to_raw()) });
+    if handler.is_not_nil() {
+        return call!(handler, operation, algorithm, filename, binary, callback);
+    }
+
+    let encoded = LispObject::from(unsafe { encode_file_name(filename.to_raw()) });
+    let encoded = encoded.as_string_or_error();
+    let file = match File::open(file_name_path(encoded)) {
+        Ok(file) => file,
+        Err(err) => unsafe {
+            report_file_errno(
+                b"Opening input file\0".as_ptr() as *const libc::c_char,
+                filename.to_raw(),
+                err.raw_os_error().unwrap_or(0),
+            )
+        },
     };
 
-    if buffer.is_nil() {
-        unsafe { nsberror(buffer_or_name.to_raw()) };
+    if callback.is_nil() {
+        return match hash_file(alg, file, quit_pending) {
+            Ok(digest) => make_digest(digest.len(), binary, |dest_buf| {
+                dest_buf[..digest.len()].copy_from_slice(&digest)
+            }),
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {
+                unsafe { maybe_quit() };
+                error!("Quit");
+            }
+            Err(err) => unsafe {
+                report_file_errno(
+                    b"Reading input file\0".as_ptr() as *const libc::c_char,
+                    filename.to_raw(),
+                    err.raw_os_error().unwrap_or(0),
+                )
+            },
+        };
     }
-    let b = buffer.as_buffer().unwrap();
-    let mut ctx = sha1::Sha1::new();
 
-    ctx.update(unsafe {
-        slice::from_raw_parts(b.beg_addr(), (b.gpt_byte() - b.beg_byte()) as usize)
-    });
-    if b.gpt_byte() < b.z_byte() {
-        ctx.update(unsafe {
-            slice::from_raw_parts(
-                b.gap_end_addr(),
-                (b.z_addr() as usize - b.gap_end_addr() as usize),
-            )
+    let (sender, receiver) = channel();
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let stop = Arc::clone(&cancelled);
+    let spawned = thread::Builder::new()
+        .name("file-hash".to_string())
+        .spawn(move || {
+            // The receiver is gone if the job was cancelled.
+            let _ = sender.send(hash_file(alg, file, || stop.load(Ordering::SeqCst)));
         });
+    if let Err(err) = spawned {
+        error!("Cannot start a thread to hash the file: {}", err);
+    }
+    let job = FILE_HASH_JOB_COUNT.fetch_add(1, Ordering::SeqCst);
+    // Timers don't run before this function returns, so the job is
+    // there when the timer first fires.
+    let timer = call!(
+        intern("run-with-timer"),
+        LispObject::from_float(FILE_HASH_POLL_INTERVAL),
+        LispObject::from_float(FILE_HASH_POLL_INTERVAL),
+        intern("file-hash--poll"),
+        LispObject::from_natnum(job as EmacsInt),
+        callback,
+        binary
+    );
+    FILE_HASH_JOBS.lock().unwrap().insert(
+        job,
+        FileHashJob {
+            receiver,
+            cancelled,
+            timer,
+        },
+    );
+    LispObject::from_natnum(job as EmacsInt)
+}
+
+/// Stop the background `file-hash' JOB, the number it returned.
+/// Its callback is not called.  Return t if JOB was still running,
+/// nil if it was done or cancelled already.
+#[lisp_fn]
+fn file_hash_cancel(job: LispObject) -> LispObject {
+    let job = job.as_natnum_or_error() as usize;
+    // The lock is released before running Lisp code.
+    let removed = FILE_HASH_JOBS.lock().unwrap().remove(&job);
+    match removed {
+        Some(job) => {
+            job.cancelled.store(true, Ordering::SeqCst);
+            call!(intern("cancel-timer"), job.timer);
+            LispObject::constant_t()
+        }
+        None => LispObject::constant_nil(),
     }
+}
 
-    let formatted = ctx.digest().to_string();
-    let digest = LispObject::from(unsafe { make_uninit_string(formatted.len() as EmacsInt) });
-    digest
-        .as_string()
-        .unwrap()
-        .as_mut_slice()
-        .copy_from_slice(formatted.as_bytes());
-    digest
+extern "C" fn cancel_file_hash_timer(timer: Lisp_Object) {
+    call!(intern("cancel-timer"), LispObject::from(timer));
+}
+
+/// Call CALLBACK with the result of the background `file-hash' JOB if
+/// it is done, and cancel the timer that runs this function for JOB
+/// then, even if CALLBACK signals an error.  BINARY is as for
+/// `file-hash'.
+#[lisp_fn(name = "file-hash--poll")]
+fn file_hash_poll(job: LispObject, callback: LispObject, binary: LispObject) -> LispObject {
+    let job = job.as_natnum_or_error() as usize;
+    let (received, timer) = {
+        let mut jobs = FILE_HASH_JOBS.lock().unwrap();
+        let received = match jobs.get(&job) {
+            Some(running) => running.receiver.try_recv(),
+            None => return LispObject::constant_nil(),
+        };
+        if let Err(TryRecvError::Empty) = received {
+            return LispObject::constant_nil();
+        }
+        (received, jobs.remove(&job).unwrap().timer)
+    };
+    // The lock is released before running Lisp code.
+    let count = unsafe { c_specpdl_index() };
+    unsafe { record_unwind_protect(cancel_file_hash_timer, timer.to_raw()) };
+    let result = match received {
+        Ok(Ok(digest)) => call!(
+            callback,
+            make_digest(digest.len(), binary, |dest_buf| {
+                dest_buf[..digest.len()].copy_from_slice(&digest)
+            }),
+            LispObject::constant_nil()
+        ),
+        Ok(Err(err)) => call!(callback, LispObject::constant_nil(), make_error_message(&err)),
+        Err(_) => {
+            error!("The thread hashing the file died");
+        }
+    };
+    LispObject::from(unsafe { unbind_to(count, result.to_raw()) })
 }
 
 include!(concat!(env!("OUT_DIR"), "/crypto_exports.rs"));
@@ -0,0 +1,161 @@
+//! The SHA-3 hash functions of FIPS 202.
+
+const ROUND_CONSTANTS: [u64; 24] = [
+    0x0000_0000_0000_0001,
+    0x0000_0000_0000_8082,
+    0x8000_0000_0000_808A,
+    0x8000_0000_8000_8000,
+    0x0000_0000_0000_808B,
+    0x0000_0000_8000_0001,
+    0x8000_0000_8000_8081,
+    0x8000_0000_0000_8009,
+    0x0000_0000_0000_008A,
+    0x0000_0000_0000_0088,
+    0x0000_0000_8000_8009,
+    0x0000_0000_8000_000A,
+    0x0000_0000_8000_808B,
+    0x8000_0000_0000_008B,
+    0x8000_0000_0000_8089,
+    0x8000_0000_0000_8003,
+    0x8000_0000_0000_8002,
+    0x8000_0000_0000_0080,
+    0x0000_0000_0000_800A,
+    0x8000_0000_8000_000A,
+    0x8000_0000_8000_8081,
+    0x8000_0000_0000_8080,
+    0x0000_0000_8000_0001,
+    0x8000_0000_8000_8008,
+];
+
+/// The rotation of each lane by the rho step, in the order in which
+/// the pi step visits the lanes.
+const RHO: [u32; 24] = [
+    1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14, 27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44
+];
+const PI: [usize; 24] = [
+    10, 7, 11, 17, 18, 3, 5, 16, 8, 21, 24, 4, 15, 23, 19, 13, 12, 2, 20, 14, 22, 9, 6, 1
+];
+
+/// Apply the Keccak-f[1600] permutation to STATE.
+fn keccak_f(state: &mut [u64; 25]) {
+    for &round_constant in &ROUND_CONSTANTS {
+        // Theta.
+        let mut parity = [0u64; 5];
+        for x in 0..5 {
+            parity[x] = state[x] ^ state[x + 5] ^ state[x + 10] ^ state[x + 15] ^ state[x + 20];
+        }
+        for x in 0..5 {
+            let d = parity[(x + 4) % 5] ^ parity[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                state[5 * y + x] ^= d;
+            }
+        }
+        // Rho and pi.
+        let mut last = state[1];
+        for i in 0..24 {
+            let lane = state[PI[i]];
+            state[PI[i]] = last.rotate_left(RHO[i]);
+            last = lane;
+        }
+        // Chi.
+        for y in 0..5 {
+            let mut row = [0u64; 5];
+            row.copy_from_slice(&state[5 * y..5 * y + 5]);
+            for x in 0..5 {
+                state[5 * y + x] = row[x] ^ (!row[(x + 1) % 5] & row[(x + 2) % 5]);
+            }
+        }
+        // Iota.
+        state[0] ^= round_constant;
+    }
+}
+
+/// An incremental SHA-3 hash.
+pub struct Sha3 {
+    state: [u64; 25],
+    /// The number of bytes absorbed by each permutation.
+    rate: usize,
+    /// The number of bytes absorbed since the last permutation.
+    pos: usize,
+    digest_len: usize,
+}
+
+impl Sha3 {
+    /// Make a SHA-3 hash whose digest is DIGEST_LEN bytes long, which
+    /// must be one of 28, 32, 48 and 64.
+    pub fn new(digest_len: usize) -> Sha3 {
+        Sha3 {
+            state: [0; 25],
+            rate: 200 - 2 * digest_len,
+            pos: 0,
+            digest_len,
+        }
+    }
+
+    fn xor_byte(&mut self, pos: usize, byte: u8) {
+        self.state[pos / 8] ^= u64::from(byte) << (8 * (pos % 8));
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            let pos = self.pos;
+            self.xor_byte(pos, byte);
+            self.pos += 1;
+            if self.pos == self.rate {
+                keccak_f(&mut self.state);
+                self.pos = 0;
+            }
+        }
+    }
+
+    /// Write the digest to the start of DEST.
+    pub fn finish(mut self, dest: &mut [u8]) {
+        let (pos, rate) = (self.pos, self.rate);
+        self.xor_byte(pos, 0x06);
+        self.xor_byte(rate - 1, 0x80);
+        keccak_f(&mut self.state);
+        for (idx, byte) in dest[..self.digest_len].iter_mut().enumerate() {
+            *byte = (self.state[idx / 8] >> (8 * (idx % 8))) as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+fn sha3_hex(digest_len: usize, parts: &[&[u8]]) -> String {
+    let mut hash = Sha3::new(digest_len);
+    for part in parts {
+        hash.update(part);
+    }
+    let mut digest = vec![0; digest_len];
+    hash.finish(&mut digest);
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[test]
+fn test_sha3() {
+    assert_eq!(
+        sha3_hex(32, &[b""]),
+        "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a"
+    );
+    assert_eq!(
+        sha3_hex(28, &[b"ab", b"c"]),
+        "e642824c3f8cf24ad09234ee7d3c766fc9a3a5168d0c94ad73b46fdf"
+    );
+    assert_eq!(
+        sha3_hex(32, &[b"abc"]),
+        "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532"
+    );
+    assert_eq!(
+        sha3_hex(48, &[b"abc"]),
+        "ec01498288516fc926459f58e2c6ad8df9b473cb0fc08c2596da7cf0\
+         e49be4b298d88cea927ac7f539f1edf228376d25"
+    );
+    assert_eq!(
+        sha3_hex(64, &[b"abc"]),
+        "b751850b1a57168a5693cd924b6b096e08f621827444f70d884f5d0240d2712e\
+         10e116e9192af3c91a7ec57647e3934057340b4cf408d5a56592f8274eec53f0"
+    );
+    // Longer than a block, which is 136 bytes for SHA3-256.
+    assert_eq!(&sha3_hex(32, &[&[b'a'; 100], &[b'a'; 100]])[..16], "cce34485baf2bf2a");
+    assert_eq!(&sha3_hex(64, &[&[b'a'; 200]])[..16], "eae6c85c6904f110");
+}
@@ -1,17 +1,24 @@
 //! Lisp functions pertaining to editing.
 
-use libc::{c_uchar, ptrdiff_t};
+use libc::{c_char, c_uchar, ptrdiff_t};
+use std::iter;
 
 use remacs_macros::lisp_fn;
-use remacs_sys::{EmacsInt, Fadd_text_properties, Fcons, Fcopy_sequence, Finsert_char,
-                 Qinteger_or_marker_p, Qmark_inactive, Qnil};
-use remacs_sys::{buf_charpos_to_bytepos, globals, set_point_both};
+use remacs_sys::{EmacsInt, Fadd_text_properties, Fchar_to_string, Fcons, Fcopy_sequence,
+                 Finsert_char, Fprin1_to_string, Qinteger_or_marker_p, Qmark_inactive, Qnil,
+                 TextQuotingStyle, INTMASK, MOST_POSITIVE_FIXNUM};
+use remacs_sys::{add_text_properties_from_list, buf_charpos_to_bytepos, extend_property_ranges,
+                 globals, make_composition_value_copy, make_specified_string, set_point_both,
+                 text_property_list, text_quoting_style};
 
 use buffers::get_buffer;
+use character::lisp_string_columns;
+use floatconv::{format_printf, Decimal};
 use lisp::LispObject;
 use lisp::defsubr;
 use marker::{marker_position, set_point_from_marker};
-use multibyte::raw_byte_codepoint;
+use multibyte::{char_head_p, multibyte_char_at, multibyte_chars_in_text, raw_byte_codepoint,
+                string_overflow, write_codepoints, Codepoint};
 use threads::ThreadState;
 use util::clip_to_bounds;
 
@@ -291,4 +298,896 @@ pub fn propertize(args: &mut [LispObject]) -> LispObject {
     copy
 }
 
+/// The largest precision of %e, %f and %g which can make a digit
+/// other than a trailing zero appear: the number of fraction digits of
+/// the smallest long double.  A larger precision appends zeros.
+const USEFUL_PRECISION_MAX: usize = 16_382;
+
+/// The most bytes a string can hold, `STRING_BYTES_BOUND` in C.
+const STRING_BYTES_BOUND: usize = MOST_POSITIVE_FIXNUM as usize;
+
+/// A %-sequence of a format string, with its argument.
+struct FormatSpec {
+    /// The conversion character, which is b's' for the %S, %s and %c
+    /// conversions whose argument was converted to a string.
+    conversion: u8,
+    argument: LispObject,
+    minus: bool,
+    plus: bool,
+    space: bool,
+    sharp: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+    /// The number of characters of the %-sequence.
+    length: usize,
+}
+
+enum FormatPiece {
+    /// A character copied from the format string, as its byte range.
+    Char(usize, usize),
+    /// A grave accent or an apostrophe, which `format-message' requotes.
+    Quote(u8),
+    /// A %-sequence producing a single %, and its number of characters.
+    Percent(usize),
+    Spec(FormatSpec),
+}
+
+/// Where the output of a %-sequence is in the result, in characters.
+struct Field {
+    argument: LispObject,
+    /// The start of the output, or for a string argument the start of
+    /// its text, after any padding on its left.
+    text_start: usize,
+    end: usize,
+    /// Whether the argument is a string with text properties.
+    intervals: bool,
+}
+
+/// Parse the decimal number at the start of TEXT, which saturates at
+/// `PTRDIFF_MAX`, and return it with the number of digits.
+fn parse_count(text: &[u8]) -> (usize, usize) {
+    let digits = text.iter()
+        .take_while(|&&byte| b'0' <= byte && byte <= b'9')
+        .count();
+    let n = text[..digits].iter().fold(0usize, |n, &byte| {
+        n.checked_mul(10)
+            .and_then(|n| n.checked_add((byte - b'0') as usize))
+            .map_or(isize::max_value() as usize, |n| n.min(isize::max_value() as usize))
+    });
+    (n, digits)
+}
+
+/// Convert the ARGUMENT of a %-sequence with CONVERSION for printing
+/// it, where FORMAT starts at the conversion: %S and %s print their
+/// argument to a string, %c makes a string of a non-ASCII character,
+/// and symbols stand for their names.  Return the argument and the
+/// conversion to use.
+fn convert_format_argument(
+    mut argument: LispObject,
+    mut conversion: u8,
+    format: &[u8],
+    multibyte_format: bool,
+) -> (LispObject, u8) {
+    if conversion == b'S' || (conversion == b's' && !argument.is_string() && !argument.is_symbol())
+    {
+        let noescape = LispObject::from_bool(conversion == b's');
+        argument =
+            LispObject::from(unsafe { Fprin1_to_string(argument.to_raw(), noescape.to_raw()) });
+        conversion = b's';
+    } else if conversion == b'c' {
+        if let Some(n) = argument.as_fixnum() {
+            if n < 0 || n >= 0x80 {
+                argument = LispObject::from(unsafe { Fchar_to_string(argument.to_raw()) });
+                conversion = b's';
+            }
+        }
+    }
+
+    if let Some(symbol) = argument.as_symbol() {
+        argument = symbol.symbol_name();
+    }
+
+    if conversion != b's' {
+        match conversion {
+            b'c' | b'd' | b'i' | b'o' | b'x' | b'X' | b'e' | b'f' | b'g' => {}
+            _ => {
+                let c = if multibyte_format {
+                    multibyte_char_at(format).0
+                } else {
+                    Codepoint::from(format[0])
+                };
+                error!(
+                    "Invalid format operation %{}",
+                    char::from_u32(c).unwrap_or('\u{fffd}')
+                );
+            }
+        }
+        if !(argument.is_integer() || (argument.is_float() && conversion != b'c')) {
+            error!("Format specifier doesn't match argument type");
+        }
+    }
+    (argument, conversion)
+}
+
+/// Parse the format string ARGS[0], converting the arguments of its
+/// %-sequences and signaling errors in the order of the format string.
+/// Return the pieces of the format string, and whether the result must
+/// be multibyte because of an argument.
+fn parse_format(args: &[LispObject]) -> (Vec<FormatPiece>, bool) {
+    let format_string = args[0].as_string_or_error();
+    let format = format_string.as_slice();
+    let multibyte_format = format_string.is_multibyte();
+    let mut multibyte = false;
+    let mut pieces = Vec::new();
+    // The index in ARGS of the argument of the next unnumbered
+    // %-sequence.
+    let mut next_argument = 1;
+    let mut pos = 0;
+
+    while pos < format.len() {
+        let start = pos;
+        let byte = format[pos];
+        pos += 1;
+        if byte != b'%' {
+            pieces.push(if byte == b'`' || byte == b'\'' {
+                FormatPiece::Quote(byte)
+            } else {
+                if multibyte_format {
+                    while pos < format.len() && !char_head_p(format[pos]) {
+                        pos += 1;
+                    }
+                }
+                FormatPiece::Char(start, pos)
+            });
+            continue;
+        }
+
+        // %[FIELD$][FLAGS][WIDTH][.PRECISION]CONVERSION, where a
+        // numbered FIELD says which argument to use.
+        let (field, digits) = parse_count(&format[pos..]);
+        if digits > 0 && format.get(pos + digits) == Some(&b'$') {
+            next_argument = field;
+            pos += digits + 1;
+        }
+
+        let (mut minus, mut plus, mut space, mut sharp, mut zero) =
+            (false, false, false, false, false);
+        while pos < format.len() {
+            match format[pos] {
+                b'-' => minus = true,
+                b'+' => plus = true,
+                b' ' => space = true,
+                b'#' => sharp = true,
+                b'0' => zero = true,
+                _ => break,
+            }
+            pos += 1;
+        }
+        // Ignore flags when sprintf ignores them.
+        space &= !plus;
+        zero &= !minus;
+
+        let (width, digits) = parse_count(&format[pos..]);
+        if width > STRING_BYTES_BOUND {
+            string_overflow();
+        }
+        pos += digits;
+
+        let mut precision = None;
+        if format.get(pos) == Some(&b'.') {
+            let (n, digits) = parse_count(&format[pos + 1..]);
+            precision = Some(n);
+            pos += 1 + digits;
+        }
+
+        if pos == format.len() {
+            error!("Format string ends in middle of format specifier");
+        }
+        let conversion = format[pos];
+        pos += 1;
+        if conversion == b'%' {
+            pieces.push(FormatPiece::Percent(pos - start));
+            continue;
+        }
+
+        let index = next_argument;
+        next_argument = next_argument.saturating_add(1);
+        if index >= args.len() {
+            error!("Not enough arguments for format string");
+        }
+        let (argument, conversion) =
+            convert_format_argument(args[index], conversion, &format[pos - 1..], multibyte_format);
+        if conversion == b'c' {
+            zero = false;
+        } else if conversion == b's' {
+            multibyte |= argument.as_string().unwrap().is_multibyte();
+        } else if conversion != b'e' && conversion != b'f' && conversion != b'g' {
+            zero &= precision.is_none();
+        }
+        if conversion == b'd' || conversion == b'i' {
+            // sprintf has no alternate form for these.
+            sharp = false;
+        }
+
+        pieces.push(FormatPiece::Spec(FormatSpec {
+            conversion,
+            argument,
+            minus,
+            plus,
+            space,
+            sharp,
+            zero,
+            width,
+            precision,
+            length: pos - start,
+        }));
+    }
+    (pieces, multibyte)
+}
+
+/// The argument of a %-sequence for a number or a character.
+#[derive(Clone, Copy)]
+enum FormatNumber {
+    Fixnum(EmacsInt),
+    Float(f64),
+}
+
+impl FormatNumber {
+    fn from_lisp(arg: LispObject) -> FormatNumber {
+        match arg.as_fixnum() {
+            Some(n) => FormatNumber::Fixnum(n),
+            None => FormatNumber::Float(arg.as_float().unwrap()),
+        }
+    }
+}
+
+/// Return the value of NUMBER for %d, truncating a float and limiting
+/// it to the range of i64 like a C conversion would.
+fn format_integer_value(number: FormatNumber) -> i64 {
+    match number {
+        FormatNumber::Fixnum(n) => n,
+        FormatNumber::Float(d) => {
+            if d < 0.0 {
+                if (i64::min_value() as f64) < d {
+                    d as i64
+                } else {
+                    i64::min_value()
+                }
+            } else if d < i64::max_value() as f64 {
+                d as i64
+            } else {
+                i64::max_value()
+            }
+        }
+    }
+}
+
+/// Return the value of NUMBER for %o, %x and %X: a fixnum's bits
+/// without sign extension, or a float truncated and limited to the
+/// range of u64.
+fn format_unsigned_value(number: FormatNumber) -> u64 {
+    match number {
+        FormatNumber::Fixnum(n) => n as u64 & INTMASK as u64,
+        FormatNumber::Float(d) => {
+            if d < 0.0 {
+                0
+            } else if d < u64::max_value() as f64 {
+                d as u64
+            } else {
+                u64::max_value()
+            }
+        }
+    }
+}
+
+/// Return what sprintf prints for the NUMBER of SPEC with PRECISION,
+/// without padding.
+fn sprintf_number(spec: &FormatSpec, number: FormatNumber, precision: Option<usize>) -> String {
+    let mut out = String::new();
+    let push_sign = |out: &mut String, negative: bool| {
+        if negative {
+            out.push('-');
+        } else if spec.plus {
+            out.push('+');
+        } else if spec.space {
+            out.push(' ');
+        }
+    };
+    // Pad DIGITS with zeros to the precision, which defaults to 1, and
+    // print nothing for 0 with the precision 0.
+    let with_precision = |digits: String, zero: bool| {
+        let minimum = precision.unwrap_or(1);
+        if zero && minimum == 0 {
+            String::new()
+        } else if digits.len() < minimum {
+            "0".repeat(minimum - digits.len()) + &digits
+        } else {
+            digits
+        }
+    };
+
+    match spec.conversion {
+        b'c' => {
+            if let FormatNumber::Fixnum(c) = number {
+                if precision != Some(0) {
+                    out.push(c as u8 as char);
+                }
+            }
+        }
+        b'd' | b'i' => {
+            let n = format_integer_value(number);
+            push_sign(&mut out, n < 0);
+            let magnitude = if n < 0 {
+                (n as u64).wrapping_neg()
+            } else {
+                n as u64
+            };
+            out.push_str(&with_precision(magnitude.to_string(), n == 0));
+        }
+        b'o' | b'x' | b'X' => {
+            let n = format_unsigned_value(number);
+            let digits = match spec.conversion {
+                b'o' => format!("{:o}", n),
+                b'x' => format!("{:x}", n),
+                _ => format!("{:X}", n),
+            };
+            let digits = with_precision(digits, n == 0);
+            if spec.sharp {
+                if spec.conversion == b'o' && !digits.starts_with('0') {
+                    out.push('0');
+                } else if spec.conversion != b'o' && n != 0 {
+                    out.push_str(if spec.conversion == b'x' { "0x" } else { "0X" });
+                }
+            }
+            out.push_str(&digits);
+        }
+        conversion => {
+            let (negative, mut decimal) = match number {
+                FormatNumber::Fixnum(n) => (n < 0, Decimal::from_integer(n.abs() as u64)),
+                FormatNumber::Float(d) => {
+                    if !d.is_finite() {
+                        push_sign(&mut out, d.is_sign_negative());
+                        out.push_str(if d.is_nan() { "nan" } else { "inf" });
+                        return out;
+                    }
+                    (d.is_sign_negative(), Decimal::from_float(d))
+                }
+            };
+            push_sign(&mut out, negative);
+            let precision = precision.unwrap_or(6) as i32;
+            format_printf(&mut decimal, conversion, precision, spec.sharp, &mut out);
+        }
+    }
+    out
+}
+
+/// Append the output of SPEC for NUMBER to BUF, and return its number
+/// of bytes, which is also its number of characters.
+fn format_number(spec: &FormatSpec, number: FormatNumber, buf: &mut Vec<u8>) -> usize {
+    let float_conversion =
+        spec.conversion == b'e' || spec.conversion == b'f' || spec.conversion == b'g';
+    let precision = spec.precision.map(|p| p.min(USEFUL_PRECISION_MAX));
+    let text = sprintf_number(spec, number, precision);
+    let text = text.as_bytes();
+
+    // A precision too large to pass to sprintf still pads integers
+    // with leading zeros, and floats with trailing zeros.
+    let mut excess_precision = spec.precision.map_or(0, |p| p - precision.unwrap());
+    let (mut leading_zeros, mut trailing_zeros) = (0, 0);
+    if excess_precision > 0 {
+        if float_conversion {
+            let ends_in_digit = text.last().map_or(false, |&b| b'0' <= b && b <= b'9');
+            if (spec.conversion == b'g' && (!spec.sharp || !text.contains(&b'.')))
+                || !ends_in_digit
+            {
+                excess_precision = 0;
+            }
+            trailing_zeros = excess_precision;
+        } else {
+            leading_zeros = excess_precision;
+        }
+    }
+
+    let numwidth = text.len().saturating_add(excess_precision);
+    let mut padding = spec.width.saturating_sub(numwidth);
+    check_string_size(buf.len(), numwidth.saturating_add(padding));
+
+    let signed = match text.first() {
+        Some(&b'-') | Some(&b'+') | Some(&b' ') => 1,
+        _ => 0,
+    };
+    let hex_after_sign = text.get(signed).map_or(false, |&b| {
+        (b'0' <= b && b <= b'9') || (b'a' <= b && b <= b'f') || (b'A' <= b && b <= b'F')
+    });
+    if spec.zero && hex_after_sign {
+        leading_zeros += padding;
+        padding = 0;
+    }
+    let exponent_start = if trailing_zeros > 0 && spec.conversion != b'f' {
+        text.iter().position(|&b| b == b'e').unwrap_or(text.len())
+    } else {
+        text.len()
+    };
+
+    let start = buf.len();
+    if !spec.minus {
+        buf.extend(iter::repeat(b' ').take(padding));
+    }
+    buf.extend_from_slice(&text[..signed]);
+    buf.extend(iter::repeat(b'0').take(leading_zeros));
+    buf.extend_from_slice(&text[signed..exponent_start]);
+    buf.extend(iter::repeat(b'0').take(trailing_zeros));
+    buf.extend_from_slice(&text[exponent_start..]);
+    if spec.minus {
+        buf.extend(iter::repeat(b' ').take(padding));
+    }
+    buf.len() - start
+}
+
+/// Signal an error unless USED bytes and MORE bytes fit in a string.
+fn check_string_size(used: usize, more: usize) {
+    if used.checked_add(more).map_or(true, |total| total > STRING_BYTES_BOUND) {
+        string_overflow();
+    }
+}
+
+/// Implement `format-message' if MESSAGE is true, `format' otherwise.
+fn styled_format(args: &[LispObject], message: bool) -> LispObject {
+    let (pieces, converted_multibyte) = parse_format(args);
+    let format_string = args[0].as_string_or_error();
+    let format = format_string.as_slice();
+    let multibyte_format = format_string.is_multibyte();
+
+    let quoting_style = if message {
+        Some(unsafe { text_quoting_style() })
+    } else {
+        None
+    };
+    let has_quotes = pieces.iter().any(|piece| match *piece {
+        FormatPiece::Quote(_) => true,
+        _ => false,
+    });
+    let curved_quotes = has_quotes && quoting_style == Some(TextQuotingStyle::Curve);
+    let multibyte = multibyte_format || converted_multibyte || curved_quotes
+        || args[1..]
+            .iter()
+            .any(|arg| arg.as_string().map_or(false, |s| s.is_multibyte()));
+
+    let mut buf: Vec<u8> = Vec::with_capacity(format.len());
+    let mut nchars = 0;
+    // DISCARDED[I] says whether character I of the format string was
+    // not copied into the result, for moving its properties.
+    let mut discarded = Vec::with_capacity(format_string.len_chars() as usize);
+    let mut fields = Vec::new();
+
+    for piece in &pieces {
+        match *piece {
+            FormatPiece::Char(start, end) => {
+                discarded.push(false);
+                if multibyte_format || !multibyte || format[start] < 0x80 {
+                    buf.extend_from_slice(&format[start..end]);
+                } else {
+                    write_codepoints(&mut buf, Some(raw_byte_codepoint(format[start])));
+                }
+                nchars += 1;
+            }
+            FormatPiece::Quote(quote) => {
+                discarded.push(false);
+                match quoting_style {
+                    Some(TextQuotingStyle::Curve) => buf.extend_from_slice(if quote == b'`' {
+                        "\u{2018}".as_bytes()
+                    } else {
+                        "\u{2019}".as_bytes()
+                    }),
+                    Some(TextQuotingStyle::Straight) => buf.push(b'\''),
+                    _ => buf.push(quote),
+                }
+                nchars += 1;
+            }
+            FormatPiece::Percent(length) => {
+                // Only the last % is copied.
+                for _ in 1..length {
+                    discarded.push(true);
+                }
+                discarded.push(false);
+                buf.push(b'%');
+                nchars += 1;
+            }
+            FormatPiece::Spec(ref spec) => {
+                let mut text_start = nchars;
+                let mut intervals = false;
+                if spec.conversion == b's' {
+                    let string = spec.argument.as_string().unwrap();
+                    let (width, string_nchars, nbytes) = if spec.precision == Some(0) {
+                        (0, 0, 0)
+                    } else {
+                        let precision = spec.precision.map_or(-1, |p| p as ptrdiff_t);
+                        let (width, nch, nby) = lisp_string_columns(spec.argument, precision);
+                        (width as usize, nch as usize, nby as usize)
+                    };
+                    let bytes = &string.as_slice()[..nbytes];
+                    let convert = multibyte && !string.is_multibyte();
+                    let padding = spec.width.saturating_sub(width);
+                    check_string_size(buf.len(), padding.saturating_add(nbytes * 2));
+
+                    if !spec.minus {
+                        buf.extend(iter::repeat(b' ').take(padding));
+                        nchars += padding;
+                    }
+                    text_start = nchars;
+                    if convert {
+                        for &byte in bytes {
+                            if byte < 0x80 {
+                                buf.push(byte);
+                            } else {
+                                write_codepoints(&mut buf, Some(raw_byte_codepoint(byte)));
+                            }
+                        }
+                    } else {
+                        buf.extend_from_slice(bytes);
+                    }
+                    nchars += string_nchars;
+                    if spec.minus {
+                        buf.extend(iter::repeat(b' ').take(padding));
+                        nchars += padding;
+                    }
+                    intervals = !string.intervals.is_null();
+                } else {
+                    let number = FormatNumber::from_lisp(spec.argument);
+                    nchars += format_number(spec, number, &mut buf);
+                }
+
+                for _ in 0..spec.length {
+                    discarded.push(true);
+                }
+                fields.push(Field {
+                    argument: spec.argument,
+                    text_start,
+                    end: nchars,
+                    intervals,
+                });
+            }
+        }
+    }
+
+    if multibyte {
+        nchars = multibyte_chars_in_text(buf.as_ptr(), buf.len() as ptrdiff_t) as usize;
+    }
+    let val = LispObject::from(unsafe {
+        make_specified_string(
+            buf.as_ptr() as *const c_char,
+            nchars as ptrdiff_t,
+            buf.len() as ptrdiff_t,
+            multibyte,
+        )
+    });
+
+    if !format_string.intervals.is_null() {
+        // Copy the text properties of the format string, moving them
+        // to where its characters went.  As in the C implementation,
+        // a character position that reaches the start of the next
+        // field skips over all its output, and other characters of
+        // %-sequences take no room.
+        let mut positions = Vec::with_capacity(discarded.len() + 1);
+        let (mut translated, mut field) = (0, 0);
+        positions.push(translated);
+        for &discarded in &discarded {
+            if !discarded {
+                translated += 1;
+            } else if field < fields.len() && translated == fields[field].text_start {
+                translated = fields[field].end;
+                field += 1;
+            }
+            positions.push(translated);
+        }
+        let len = LispObject::from_natnum(format_string.len_chars() as EmacsInt);
+        let props = LispObject::from(unsafe {
+            text_property_list(
+                args[0].to_raw(),
+                LispObject::from_natnum(0).to_raw(),
+                len.to_raw(),
+                Qnil,
+            )
+        });
+        for tail in props.iter_tails() {
+            // Each item is (START END PLIST).
+            let item = tail.car().as_cons_or_error();
+            let end = item.cdr().as_cons_or_error();
+            let translate = |pos: LispObject| {
+                LispObject::from_natnum(positions[pos.as_fixnum_or_error() as usize] as EmacsInt)
+            };
+            item.set_car(translate(item.car()));
+            end.set_car(translate(end.car()));
+        }
+        unsafe {
+            add_text_properties_from_list(
+                val.to_raw(),
+                props.to_raw(),
+                LispObject::from_natnum(0).to_raw(),
+            )
+        };
+    }
+
+    // Copy the text properties of string arguments, stretching those
+    // at their end over any padding on their right.
+    for (i, field) in fields.iter().enumerate() {
+        if !field.intervals {
+            continue;
+        }
+        let string = field.argument.as_string().unwrap();
+        let len = LispObject::from_natnum(string.len_chars() as EmacsInt);
+        let new_len = LispObject::from_natnum((field.end - field.text_start) as EmacsInt);
+        unsafe {
+            let props = text_property_list(
+                field.argument.to_raw(),
+                LispObject::from_natnum(0).to_raw(),
+                len.to_raw(),
+                Qnil,
+            );
+            let props = extend_property_ranges(props, len.to_raw(), new_len.to_raw());
+            // If successive arguments have properties, make sure that
+            // the value of the `composition' property is a copy.
+            if 1 < i && fields[i - 1].end != 0 {
+                make_composition_value_copy(props);
+            }
+            add_text_properties_from_list(
+                val.to_raw(),
+                props,
+                LispObject::from_natnum(field.text_start as EmacsInt).to_raw(),
+            );
+        }
+    }
+
+    val
+}
+
+/// Format a string out of a format-string and arguments.
+/// The first argument is a format control string.
+/// The other arguments are substituted into it to make the result, a string.
+///
+/// The format control string may contain %-sequences meaning to substitute
+/// the next available argument, or the argument explicitly specified:
+///
+/// %s means print a string argument.  Actually, prints any object, with `princ'.
+/// %d means print as signed number in decimal.
+/// %o means print as unsigned number in octal, %x as unsigned number in hex.
+/// %X is like %x, but uses upper case.
+/// %e means print a number in exponential notation.
+/// %f means print a number in decimal-point notation.
+/// %g means print a number in exponential notation if the exponent would be
+///    less than -4 or greater than or equal to the precision (default: 6);
+///    otherwise it prints in decimal-point notation.
+/// %c means print a number as a single character.
+/// %S means print any object as an s-expression (using `prin1').
+///
+/// The argument used for %d, %o, %x, %e, %f, %g or %c must be a number.
+/// Use %% to put a single % into the output.
+///
+/// A %-sequence other than %% may contain optional field number, flag,
+/// width, and precision specifiers, as follows:
+///
+///   %<field><flags><width><precision>character
+///
+/// where field is [0-9]+ followed by a literal dollar "$", flags is
+/// [+ #-0]+, width is [0-9]+, and precision is a literal period "."
+/// followed by [0-9]+.
+///
+/// If a %-sequence is numbered with a field with positive value N, the
+/// Nth argument is substituted instead of the next one.  A format can
+/// contain either numbered or unnumbered %-sequences but not both, except
+/// that %% can be mixed with numbered %-sequences.
+///
+/// The + flag character inserts a + before any positive number, while a
+/// space inserts a space before any positive number; these flags only
+/// affect %d, %e, %f, and %g sequences, and the + flag takes precedence.
+/// The - and 0 flags affect the width specifier, as described below.
+///
+/// The # flag means to use an alternate display form for %o, %x, %X, %e,
+/// %f, and %g sequences: for %o, it ensures that the result begins with
+/// "0"; for %x and %X, it prefixes the result with "0x" or "0X";
+/// for %e and %f, it causes a decimal point to be included even if the
+/// the precision is zero; for %g, it causes a decimal point to be
+/// included even if the the precision is zero, and also forces trailing
+/// zeros after the decimal point to be left in place.
+///
+/// The width specifier supplies a lower limit for the length of the
+/// printed representation.  The padding, if any, normally goes on the
+/// left, but it goes on the right if the - flag is present.  The padding
+/// character is normally a space, but it is 0 if the 0 flag is present.
+/// The 0 flag is ignored if the - flag is present, or the format sequence
+/// is something other than %d, %e, %f, and %g.
+///
+/// For %e and %f sequences, the number after the "." in the precision
+/// specifier says how many decimal places to show; if zero, the decimal
+/// point itself is omitted.  For %g, the precision specifies how many
+/// significant digits to print; zero or omitted are treated as 1.
+/// For %s and %S, the precision specifier truncates the string to the
+/// given width.
+///
+/// Text properties, if any, are copied from the format-string to the
+/// produced text.
+///
+/// usage: (format STRING &rest OBJECTS)
+#[lisp_fn(min = "1")]
+pub fn format(args: &mut [LispObject]) -> LispObject {
+    styled_format(args, false)
+}
+
+/// Format a string out of a format-string and arguments.
+/// The first argument is a format control string.
+/// The other arguments are substituted into it to make the result, a string.
+///
+/// This acts like `format', except it also replaces each grave accent (\\=`)
+/// by a left quote, and each apostrophe (\\=') by a right quote.  The left
+/// and right quote replacement characters are specified by
+/// `text-quoting-style'.
+///
+/// usage: (format-message STRING &rest OBJECTS)
+#[lisp_fn(min = "1")]
+pub fn format_message(args: &mut [LispObject]) -> LispObject {
+    styled_format(args, true)
+}
+
 include!(concat!(env!("OUT_DIR"), "/editfns_exports.rs"));
+
+#[cfg(test)]
+extern "C" {
+    fn snprintf(buf: *mut c_char, size: usize, format: *const c_char, ...) -> ::libc::c_int;
+}
+
+/// Return a %-sequence of a number for CONVERSION with FLAGS, WIDTH and
+/// PRECISION, with the flags adjusted like `parse_format' does.
+#[cfg(test)]
+fn number_spec(conversion: u8, flags: &str, width: usize, precision: Option<usize>) -> FormatSpec {
+    let float_conversion = conversion == b'e' || conversion == b'f' || conversion == b'g';
+    let plus = flags.contains('+');
+    let minus = flags.contains('-');
+    FormatSpec {
+        conversion,
+        argument: LispObject::constant_nil(),
+        minus,
+        plus,
+        space: flags.contains(' ') && !plus,
+        sharp: flags.contains('#') && conversion != b'd',
+        zero: flags.contains('0') && !minus && (float_conversion || precision.is_none()),
+        width,
+        precision,
+        length: 0,
+    }
+}
+
+#[cfg(test)]
+fn rust_format(spec: &FormatSpec, number: FormatNumber) -> String {
+    let mut buf = Vec::new();
+    format_number(spec, number, &mut buf);
+    String::from_utf8(buf).unwrap()
+}
+
+/// Return what the C library prints for NUMBER with the %-sequence
+/// made of CONVERSION, FLAGS, WIDTH and PRECISION, passing it the
+/// value that the C implementation of `format' passed to sprintf.
+#[cfg(test)]
+fn c_format(
+    conversion: u8,
+    flags: &str,
+    width: usize,
+    precision: Option<usize>,
+    number: FormatNumber,
+) -> String {
+    let mut format = format!("%{}{}", flags, width);
+    if let Some(precision) = precision {
+        format.push_str(&format!(".{}", precision));
+    }
+    if conversion != b'e' && conversion != b'f' && conversion != b'g' {
+        format.push_str("ll");
+    }
+    format.push(conversion as char);
+    format.push('\0');
+    let format = format.as_ptr() as *const c_char;
+    let mut buf = vec![0u8; 4096];
+    let buf_ptr = buf.as_mut_ptr() as *mut c_char;
+    let length = unsafe {
+        match (conversion, number) {
+            (b'd', number) => snprintf(buf_ptr, 4096, format, format_integer_value(number)),
+            (b'e', FormatNumber::Float(d))
+            | (b'f', FormatNumber::Float(d))
+            | (b'g', FormatNumber::Float(d)) => snprintf(buf_ptr, 4096, format, d),
+            (b'e', FormatNumber::Fixnum(n))
+            | (b'f', FormatNumber::Fixnum(n))
+            | (b'g', FormatNumber::Fixnum(n)) => snprintf(buf_ptr, 4096, format, n as f64),
+            (_, number) => snprintf(buf_ptr, 4096, format, format_unsigned_value(number)),
+        }
+    };
+    buf.truncate(length as usize);
+    String::from_utf8(buf).unwrap()
+}
+
+#[test]
+fn test_format_numbers_like_c() {
+    let fixnums = [0, 1, -1, 7, 42, -42, 255, -4096, 123_456_789, 1 << 53];
+    let floats = [
+        0.0,
+        -0.0,
+        1.0,
+        -2.5,
+        0.1,
+        3.14159,
+        1234.5,
+        -0.000123,
+        123_456.789,
+        1e-5,
+        1e300,
+        -1e-300,
+        5e-324,
+        ::std::f64::MAX,
+        -9.2e18,
+        2e19,
+        ::std::f64::INFINITY,
+        ::std::f64::NEG_INFINITY,
+        ::std::f64::NAN,
+        -::std::f64::NAN,
+    ];
+    let mut numbers: Vec<FormatNumber> = fixnums.iter().map(|&n| FormatNumber::Fixnum(n)).collect();
+    numbers.extend(floats.iter().map(|&d| FormatNumber::Float(d)));
+
+    for flag_bits in 0..32 {
+        let flags: String = "-+ #0"
+            .chars()
+            .enumerate()
+            .filter(|&(i, _)| flag_bits & (1 << i) != 0)
+            .map(|(_, flag)| flag)
+            .collect();
+        for &conversion in b"doxXefg" {
+            // Emacs puts the zeros of the 0 flag before the 0x of %#x.
+            if flags.contains('#') && flags.contains('0') && !flags.contains('-')
+                && (conversion == b'x' || conversion == b'X')
+            {
+                continue;
+            }
+            for &width in &[0, 1, 5, 12, 25] {
+                for &precision in &[None, Some(0), Some(1), Some(3), Some(8), Some(17), Some(30)] {
+                    for &number in &numbers {
+                        let spec = number_spec(conversion, &flags, width, precision);
+                        assert_eq!(
+                            rust_format(&spec, number),
+                            c_format(conversion, &flags, width, precision, number),
+                            "%{}{}{:?}{}",
+                            flags,
+                            width,
+                            precision,
+                            conversion as char
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_format_number_quirks() {
+    let format = |conversion, flags, width, precision, number| {
+        rust_format(&number_spec(conversion, flags, width, precision), number)
+    };
+    assert_eq!(format(b'x', "#0", 7, None, FormatNumber::Fixnum(31)), "0000x1f");
+    assert_eq!(format(b'x', "", 0, None, FormatNumber::Fixnum(-1)), "3fffffffffffffff");
+    assert_eq!(format(b'd', "", 0, None, FormatNumber::Float(-1.9)), "-1");
+    assert_eq!(format(b'd', "", 0, None, FormatNumber::Float(1e30)), "9223372036854775807");
+    assert_eq!(format(b'o', "", 0, None, FormatNumber::Float(-5.0)), "0");
+    assert_eq!(format(b'c', "", 3, None, FormatNumber::Fixnum(97)), "  a");
+    assert_eq!(format(b'c', "", 0, Some(0), FormatNumber::Fixnum(97)), "");
+    assert_eq!(
+        format(b'f', "", 0, Some(20_000), FormatNumber::Float(0.5)).len(),
+        20_002
+    );
+    assert_eq!(
+        format(b'd', "", 0, Some(20_000), FormatNumber::Fixnum(-7)).len(),
+        20_001
+    );
+    assert_eq!(
+        format(b'e', "", 0, Some(20_000), FormatNumber::Float(1.5)),
+        format!("1.5{}e+00", "0".repeat(19_999))
+    );
+    assert_eq!(format(b'g', "", 0, Some(20_000), FormatNumber::Float(1.5)), "1.5");
+}
@@ -0,0 +1,360 @@
+//! Correctly rounded conversion of decimal numbers to doubles.
+
+/// Decimal digits after this many significant digits only matter for
+/// whether the number is above a halfway point between two doubles,
+/// which needs at most 767 digits to decide.
+const MAX_DIGITS: usize = 800;
+
+/// The powers of 10 which are exact doubles, for the fast path.
+static EXACT_POWERS_OF_10: [f64; 23] = [
+    1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10, 1e11, 1e12, 1e13, 1e14, 1e15, 1e16,
+    1e17, 1e18, 1e19, 1e20, 1e21, 1e22,
+];
+
+/// An unsigned integer of any size, as 32-bit limbs from the least
+/// significant one, without high zero limbs.
+#[derive(Clone)]
+pub struct Big(Vec<u32>);
+
+impl Big {
+    pub fn from_u64(n: u64) -> Big {
+        let mut big = Big(vec![n as u32, (n >> 32) as u32]);
+        while big.0.last() == Some(&0) {
+            big.0.pop();
+        }
+        big
+    }
+
+    fn from_digits(digits: &[u8]) -> Big {
+        let mut n = Big(Vec::with_capacity(digits.len() / 9 + 1));
+        for chunk in digits.chunks(9) {
+            let mut value = 0;
+            let mut scale = 1;
+            for &digit in chunk {
+                value = value * 10 + u32::from(digit);
+                scale *= 10;
+            }
+            n.mul_add(scale, value);
+        }
+        n
+    }
+
+    fn mul_add(&mut self, factor: u32, addend: u32) {
+        let mut carry = u64::from(addend);
+        for limb in &mut self.0 {
+            let product = u64::from(*limb) * u64::from(factor) + carry;
+            *limb = product as u32;
+            carry = product >> 32;
+        }
+        if carry != 0 {
+            self.0.push(carry as u32);
+        }
+    }
+
+    fn mul_pow10(&mut self, mut exponent: u32) {
+        while exponent >= 9 {
+            self.mul_add(1_000_000_000, 0);
+            exponent -= 9;
+        }
+        self.mul_add(10u32.pow(exponent), 0);
+    }
+
+    pub fn mul_pow5(&mut self, mut exponent: u32) {
+        while exponent >= 13 {
+            self.mul_add(1_220_703_125, 0);
+            exponent -= 13;
+        }
+        self.mul_add(5u32.pow(exponent), 0);
+    }
+
+    /// Return the decimal digits, as numbers from 0 to 9, without
+    /// leading zeros.
+    pub fn to_digits(&self) -> Vec<u8> {
+        let mut n = self.0.clone();
+        let mut chunks = Vec::new();
+        while !n.is_empty() {
+            let mut remainder = 0u64;
+            for limb in n.iter_mut().rev() {
+                let value = remainder << 32 | u64::from(*limb);
+                *limb = (value / 1_000_000_000) as u32;
+                remainder = value % 1_000_000_000;
+            }
+            while n.last() == Some(&0) {
+                n.pop();
+            }
+            chunks.push(remainder as u32);
+        }
+        let mut digits = Vec::with_capacity(chunks.len() * 9);
+        for (i, &chunk) in chunks.iter().rev().enumerate() {
+            let text = if i == 0 {
+                chunk.to_string()
+            } else {
+                format!("{:09}", chunk)
+            };
+            digits.extend(text.bytes().map(|byte| byte - b'0'));
+        }
+        digits
+    }
+
+    fn bit_length(&self) -> usize {
+        match self.0.last() {
+            Some(top) => self.0.len() * 32 - top.leading_zeros() as usize,
+            None => 0,
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn shl(&mut self, bits: usize) {
+        if self.is_zero() {
+            return;
+        }
+        let (limbs, bits) = (bits / 32, bits % 32);
+        if bits != 0 {
+            let mut carry = 0;
+            for limb in &mut self.0 {
+                let next = *limb >> (32 - bits);
+                *limb = (*limb << bits) | carry;
+                carry = next;
+            }
+            if carry != 0 {
+                self.0.push(carry);
+            }
+        }
+        for _ in 0..limbs {
+            self.0.insert(0, 0);
+        }
+    }
+
+    fn at_least(&self, other: &Big) -> bool {
+        if self.0.len() != other.0.len() {
+            return self.0.len() > other.0.len();
+        }
+        for (a, b) in self.0.iter().rev().zip(other.0.iter().rev()) {
+            if a != b {
+                return a > b;
+            }
+        }
+        true
+    }
+
+    /// Subtract OTHER, which is at most this number.
+    fn sub(&mut self, other: &Big) {
+        let mut borrow = 0;
+        for i in 0..self.0.len() {
+            let subtrahend = u64::from(*other.0.get(i).unwrap_or(&0)) + borrow;
+            let limb = u64::from(self.0[i]);
+            self.0[i] = limb.wrapping_sub(subtrahend) as u32;
+            borrow = (limb < subtrahend) as u64;
+        }
+        while self.0.last() == Some(&0) {
+            self.0.pop();
+        }
+    }
+
+    /// Return the 64 most significant bits, the exponent of their
+    /// least significant bit, and whether any bit below them is set.
+    fn top_bits(&self) -> (u64, i32, bool) {
+        let length = self.bit_length();
+        let mut bits = 0;
+        let mut sticky = false;
+        for (i, &limb) in self.0.iter().enumerate() {
+            let position = (i * 32) as isize - length as isize + 64;
+            if position >= 0 {
+                bits |= u64::from(limb) << position;
+            } else if position > -32 {
+                bits |= u64::from(limb) >> -position;
+                sticky |= limb << (32 + position) != 0;
+            } else {
+                sticky |= limb != 0;
+            }
+        }
+        (bits, length as i32 - 64, sticky)
+    }
+}
+
+/// Return the double nearest to (BITS + F) * 2^E2, where BITS has its
+/// top bit set and 0 <= F < 1 is non-zero if STICKY, rounding ties to
+/// even.
+fn round_to_double(bits: u64, e2: i32, sticky: bool) -> f64 {
+    let exponent = e2 + 63;
+    if exponent > 1023 {
+        return ::std::f64::INFINITY;
+    }
+    // The number of low bits which don't fit in the double.
+    let shift = if exponent >= -1022 { 11 } else { -1074 - e2 };
+    if shift > 64 {
+        return 0.0;
+    }
+    let (kept, rest, half) = if shift == 64 {
+        (0, bits, 1 << 63)
+    } else {
+        (bits >> shift, bits & ((1 << shift) - 1), 1 << (shift - 1))
+    };
+    let round_up = rest > half || (rest == half && (sticky || kept & 1 == 1));
+    let mut mantissa = kept + round_up as u64;
+    let mut exponent = exponent;
+    if exponent >= -1022 {
+        if mantissa == 1 << 53 {
+            mantissa >>= 1;
+            exponent += 1;
+            if exponent > 1023 {
+                return ::std::f64::INFINITY;
+            }
+        }
+        f64::from_bits(((exponent + 1023) as u64) << 52 | (mantissa & ((1 << 52) - 1)))
+    } else {
+        // A subnormal, or the smallest normal if rounding carried
+        // into the exponent bits.
+        f64::from_bits(mantissa)
+    }
+}
+
+/// Return the double nearest to the decimal number with the
+/// significant DIGITS, as numbers from 0 to 9 without leading zeros,
+/// times 10^EXPONENT.  STICKY says whether non-zero digits were
+/// dropped after DIGITS.
+fn decimal_to_double(digits: &[u8], exponent: i64, sticky: bool) -> f64 {
+    if digits.is_empty() {
+        return 0.0;
+    }
+    // The number is in [10^(MAGNITUDE - 1), 10^MAGNITUDE).
+    let magnitude = exponent + digits.len() as i64;
+    if magnitude > 310 {
+        return ::std::f64::INFINITY;
+    }
+    if magnitude < -324 {
+        return 0.0;
+    }
+    let exponent = exponent as i32;
+
+    // The fast path: when the digits and the power of 10 are exact
+    // doubles, their product or quotient is correctly rounded.
+    if digits.len() <= 15 && !sticky && exponent.abs() <= 22 {
+        let value = digits
+            .iter()
+            .fold(0u64, |value, &digit| value * 10 + u64::from(digit)) as f64;
+        let power = EXACT_POWERS_OF_10[exponent.abs() as usize];
+        return if exponent < 0 {
+            value / power
+        } else {
+            value * power
+        };
+    }
+
+    let mut numerator = Big::from_digits(digits);
+    if sticky {
+        // Any digit after the last one will do.
+        numerator.mul_add(10, 1);
+    }
+    let exponent = exponent - sticky as i32;
+    if exponent >= 0 {
+        numerator.mul_pow10(exponent as u32);
+        let (bits, e2, sticky) = numerator.top_bits();
+        return round_to_double(bits, e2, sticky);
+    }
+
+    // Divide by 10^-EXPONENT, first scaling the numerator or the
+    // denominator by a power of 2 so that their quotient is in [1, 2).
+    let mut denominator = Big(vec![1]);
+    denominator.mul_pow10(-exponent as u32);
+    let mut scale = denominator.bit_length() as i32 - numerator.bit_length() as i32;
+    let (mut scaled_numerator, mut scaled_denominator) = (numerator.clone(), denominator.clone());
+    loop {
+        if scale >= 0 {
+            scaled_numerator.shl(scale as usize);
+        } else {
+            scaled_denominator.shl(-scale as usize);
+        }
+        if scaled_numerator.at_least(&scaled_denominator) {
+            break;
+        }
+        scale += 1;
+        scaled_numerator = numerator.clone();
+        scaled_denominator = denominator.clone();
+    }
+    let mut bits = 0u64;
+    for _ in 0..64 {
+        bits <<= 1;
+        if scaled_numerator.at_least(&scaled_denominator) {
+            scaled_numerator.sub(&scaled_denominator);
+            bits |= 1;
+        }
+        scaled_numerator.shl(1);
+    }
+    round_to_double(bits, -scale - 63, !scaled_numerator.is_zero())
+}
+
+/// Parse the unsigned decimal number at the start of TEXT: digits,
+/// an optional decimal point and more digits, and an optional
+/// exponent.  Return the nearest double and the number of bytes
+/// parsed, or None if TEXT doesn't start with a digit or a decimal
+/// point followed by a digit.
+pub fn parse_decimal(text: &[u8]) -> Option<(f64, usize)> {
+    let mut digits = Vec::new();
+    let mut sticky = false;
+    // The decimal exponent of the digits kept, and the number of
+    // digits seen.
+    let mut exponent: i64 = 0;
+    let mut seen = 0;
+    let mut pos = 0;
+    let mut after_point = false;
+    while pos < text.len() {
+        match text[pos] {
+            digit @ b'0'...b'9' => {
+                let digit = digit - b'0';
+                seen += 1;
+                if digits.is_empty() && digit == 0 {
+                    // A leading zero.
+                    if after_point {
+                        exponent -= 1;
+                    }
+                } else if digits.len() < MAX_DIGITS {
+                    digits.push(digit);
+                    if after_point {
+                        exponent -= 1;
+                    }
+                } else {
+                    sticky |= digit != 0;
+                    if !after_point {
+                        exponent += 1;
+                    }
+                }
+            }
+            b'.' if !after_point => after_point = true,
+            _ => break,
+        }
+        pos += 1;
+    }
+    if seen == 0 {
+        return None;
+    }
+
+    if pos < text.len() && (text[pos] == b'e' || text[pos] == b'E') {
+        let mut end = pos + 1;
+        let negative = end < text.len() && text[end] == b'-';
+        if end < text.len() && (text[end] == b'+' || text[end] == b'-') {
+            end += 1;
+        }
+        let start = end;
+        let mut value: i64 = 0;
+        while end < text.len() && b'0' <= text[end] && text[end] <= b'9' {
+            // Saturate well past the range of doubles.
+            value = (value * 10 + i64::from(text[end] - b'0')).min(1 << 40);
+            end += 1;
+        }
+        if end > start {
+            exponent += if negative { -value } else { value };
+            pos = end;
+        }
+    }
+
+    // Drop trailing zeros, so that they don't defeat the fast path.
+    while digits.last() == Some(&0) {
+        digits.pop();
+        exponent += 1;
+    }
+    Some((decimal_to_double(&digits, exponent, sticky), pos))
+}
@@ -0,0 +1,235 @@
+//! Conversion of doubles to and from their printed representation.
+//!
+//! Doubles print as the shortest decimal number which reads back as
+//! the same double, and decimal numbers read as the nearest double,
+//! so that printing and reading a double never changes it.  This
+//! includes the sign of zeros and NaNs, and the payload of NaNs.
+//!
+//! The %e, %f and %g directives of `format` print exact decimal
+//! expansions instead, rounded like printf does.
+
+mod decimal;
+mod printf;
+mod ryu;
+
+pub use self::printf::{format_printf, Decimal};
+
+const MANTISSA_MASK: u64 = (1 << 52) - 1;
+
+/// The bit of the mantissa of a NaN which makes it quiet.  The payload
+/// of a NaN printed as N.0e+NaN is its mantissa with this bit flipped,
+/// so that the default quiet NaN prints as 0.0e+NaN, and signaling
+/// NaNs have payloads with this bit set.
+const QUIET_NAN_BIT: u64 = 1 << 51;
+
+/// Exponents of 10 from -4 to this one, or to the number of digits
+/// minus one if it is larger, print without an exponent, like the %g
+/// directive of printf with the precision 15 would.
+const MAX_FIXED_EXPONENT: i32 = 14;
+
+/// Append the Lisp representation of VALUE to OUT: the shortest
+/// decimal number which reads back as VALUE, always with a decimal
+/// point or an exponent, like 1.0, 0.001, 1e-05 or 1.5e+300.
+/// Infinities print as 1.0e+INF and -1.0e+INF, and NaNs as N.0e+NaN
+/// or -N.0e+NaN, where N is the payload.
+pub fn format_float(value: f64, out: &mut String) {
+    let bits = value.to_bits();
+    if bits >> 63 != 0 {
+        out.push('-');
+    }
+    if value.is_nan() {
+        let payload = (bits & MANTISSA_MASK) ^ QUIET_NAN_BIT;
+        out.push_str(&payload.to_string());
+        out.push_str(".0e+NaN");
+        return;
+    }
+    if value.is_infinite() {
+        out.push_str("1.0e+INF");
+        return;
+    }
+    if value == 0.0 {
+        out.push_str("0.0");
+        return;
+    }
+
+    let (mut mantissa, mut exponent) =
+        ryu::shortest(bits & MANTISSA_MASK, (bits >> 52) as u32 & 0x7ff);
+    while mantissa % 10 == 0 {
+        mantissa /= 10;
+        exponent += 1;
+    }
+    let digits = mantissa.to_string();
+    let length = digits.len() as i32;
+    // The value is 0.DIGITS * 10^POINT.
+    let point = exponent + length;
+    if point - 1 < -4 || point - 1 > MAX_FIXED_EXPONENT.max(length - 1) {
+        out.push_str(&digits[..1]);
+        if length > 1 {
+            out.push('.');
+            out.push_str(&digits[1..]);
+        }
+        out.push_str(&format!("e{}{:02}", if point > 0 { '+' } else { '-' }, (point - 1).abs()));
+    } else if point <= 0 {
+        out.push_str("0.");
+        for _ in point..0 {
+            out.push('0');
+        }
+        out.push_str(&digits);
+    } else if point >= length {
+        out.push_str(&digits);
+        for _ in length..point {
+            out.push('0');
+        }
+        out.push_str(".0");
+    } else {
+        out.push_str(&digits[..point as usize]);
+        out.push('.');
+        out.push_str(&digits[point as usize..]);
+    }
+}
+
+/// Parse the decimal number at the start of TEXT, with an optional
+/// sign, digits, an optional decimal point and more digits, and an
+/// optional exponent, which may also be e+INF for an infinity or e+NaN
+/// for a NaN whose payload is the integer part.  Return the nearest
+/// double and the number of bytes parsed, or None if TEXT doesn't
+/// start with a number.
+pub fn parse_float(text: &[u8]) -> Option<(f64, usize)> {
+    let negative = text.first() == Some(&b'-');
+    let start = (negative || text.first() == Some(&b'+')) as usize;
+    let (mut value, mut end) = decimal::parse_decimal(&text[start..])?;
+    end += start;
+
+    let rest = &text[end..];
+    if rest.starts_with(b"e+INF") || rest.starts_with(b"E+INF") {
+        value = ::std::f64::INFINITY;
+        end += 5;
+    } else if rest.starts_with(b"e+NaN") || rest.starts_with(b"E+NaN") {
+        let payload = text[start..end]
+            .iter()
+            .take_while(|&&byte| byte != b'.')
+            .fold(Some(0u64), |payload, &byte| {
+                payload
+                    .and_then(|payload| payload.checked_mul(10))
+                    .and_then(|payload| payload.checked_add(u64::from(byte - b'0')))
+            });
+        let mantissa = match payload {
+            Some(payload) if payload <= MANTISSA_MASK && payload != QUIET_NAN_BIT => {
+                payload ^ QUIET_NAN_BIT
+            }
+            _ => QUIET_NAN_BIT,
+        };
+        value = f64::from_bits(0x7ff << 52 | mantissa);
+        end += 5;
+    }
+    Some((if negative { -value } else { value }, end))
+}
+
+#[cfg(test)]
+fn format(value: f64) -> String {
+    let mut out = String::new();
+    format_float(value, &mut out);
+    out
+}
+
+#[cfg(test)]
+fn parse(text: &str) -> u64 {
+    let (value, length) = parse_float(text.as_bytes()).unwrap();
+    assert_eq!(length, text.len(), "{}", text);
+    value.to_bits()
+}
+
+#[test]
+fn test_format_float() {
+    assert_eq!(format(1.0), "1.0");
+    assert_eq!(format(-0.0), "-0.0");
+    assert_eq!(format(0.1), "0.1");
+    assert_eq!(format(100.0), "100.0");
+    assert_eq!(format(1.5e-4), "0.00015");
+    assert_eq!(format(1e-5), "1e-05");
+    assert_eq!(format(1e14), "100000000000000.0");
+    assert_eq!(format(1e15), "1e+15");
+    assert_eq!(format(12345678901234567.0), "12345678901234568.0");
+    assert_eq!(format(1.2345678901234567e18), "1.2345678901234568e+18");
+    assert_eq!(format(0.3), "0.3");
+    assert_eq!(format(0.1 + 0.2), "0.30000000000000004");
+    assert_eq!(format(f64::from_bits(1)), "5e-324");
+    assert_eq!(format(::std::f64::MAX), "1.7976931348623157e+308");
+    assert_eq!(format(::std::f64::MIN_POSITIVE), "2.2250738585072014e-308");
+    assert_eq!(format(::std::f64::INFINITY), "1.0e+INF");
+    assert_eq!(format(::std::f64::NEG_INFINITY), "-1.0e+INF");
+    assert_eq!(format(f64::from_bits(0x7ff8_0000_0000_0000)), "0.0e+NaN");
+    assert_eq!(format(f64::from_bits(0xfff8_0000_0000_0001)), "-1.0e+NaN");
+    assert_eq!(format(f64::from_bits(0x7ff0_0000_0000_0001)), "2251799813685249.0e+NaN");
+}
+
+#[test]
+fn test_parse_float() {
+    assert_eq!(parse("1.5"), 1.5f64.to_bits());
+    assert_eq!(parse("-0.0"), (-0.0f64).to_bits());
+    assert_eq!(parse(".5e1"), 5f64.to_bits());
+    assert_eq!(parse("1e+INF"), ::std::f64::INFINITY.to_bits());
+    assert_eq!(parse("-0.0e+NaN"), 0xfff8_0000_0000_0000);
+    assert_eq!(parse("3.0e+NaN"), 0x7ff8_0000_0000_0003);
+    // Halfway between 1 and the next double, and just above it.
+    let halfway = "1.00000000000000011102230246251565404236316680908203125";
+    assert_eq!(parse(halfway), 1f64.to_bits());
+    assert_eq!(parse(&format!("{}1", halfway)), 1f64.to_bits() + 1);
+    assert_eq!(parse("9007199254740993"), 9007199254740992f64.to_bits());
+    assert_eq!(parse("2.4703282292062327e-324"), 0);
+    assert_eq!(parse("2.4703282292062328e-324"), 1);
+    assert_eq!(parse("2.2250738585072011e-308"), 0x000f_ffff_ffff_ffff);
+    assert_eq!(parse("1.7976931348623158e308"), ::std::f64::MAX.to_bits());
+    assert_eq!(parse("1.7976931348623159e308"), ::std::f64::INFINITY.to_bits());
+    assert_eq!(parse("1e-400"), 0);
+    assert_eq!(parse("0.000e999999999999999999"), 0);
+    assert_eq!(parse_float(b"1.5e"), Some((1.5, 3)));
+    assert_eq!(parse_float(b"."), None);
+    assert_eq!(parse_float(b"-x"), None);
+}
+
+#[test]
+fn test_float_round_trip() {
+    let check = |bits: u64| {
+        let text = format(f64::from_bits(bits));
+        assert_eq!(parse(&text), bits, "{}", text);
+    };
+    for &bits in &[
+        0,
+        1,
+        0x000f_ffff_ffff_ffff,
+        0x0010_0000_0000_0000,
+        0x7fef_ffff_ffff_ffff,
+        0x7ff0_0000_0000_0000,
+        0x7ff0_0000_0000_0001,
+        0x7ff4_0000_0000_0000,
+        0x7ff8_0000_0000_0000,
+        0x7fff_ffff_ffff_ffff,
+    ] {
+        check(bits);
+        check(bits | 1 << 63);
+    }
+    // Doubles spread over all exponents, with pseudo-random mantissas.
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    for _ in 0..200_000 {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        check(state);
+    }
+    // Powers of 2 and 10, and integers, whose neighbors are unevenly
+    // spaced or which have exact short representations.
+    for shift in 0..52 {
+        check(1 << shift);
+    }
+    for exponent in 1..2047 {
+        check(exponent << 52);
+    }
+    for exponent in -330..310 {
+        check(format!("1e{}", exponent).parse::<f64>().unwrap().to_bits());
+    }
+    for n in 0..10_000u64 {
+        check((n as f64).to_bits());
+        check((n as f64 / 1000.0).to_bits());
+    }
+}
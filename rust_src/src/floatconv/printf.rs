@@ -0,0 +1,233 @@
+//! The %e, %f and %g directives of printf, for exact decimal
+//! expansions of doubles and integers.
+
+use super::decimal::Big;
+use super::MANTISSA_MASK;
+
+/// A non-negative number 0.DIGITS * 10^POINT, where DIGITS are numbers
+/// from 0 to 9 without leading or trailing zeros, and are empty for 0.
+pub struct Decimal {
+    digits: Vec<u8>,
+    point: i32,
+}
+
+impl Decimal {
+    /// Return the exact decimal expansion of the absolute value of
+    /// VALUE, which must be finite.
+    pub fn from_float(value: f64) -> Decimal {
+        let bits = value.to_bits();
+        let biased_exponent = (bits >> 52) as i32 & 0x7ff;
+        let (mantissa, e2) = if biased_exponent == 0 {
+            (bits & MANTISSA_MASK, -1074)
+        } else {
+            ((bits & MANTISSA_MASK) | 1 << 52, biased_exponent - 1075)
+        };
+        let mut n = Big::from_u64(mantissa);
+        // MANTISSA * 2^E2 is N * 10^E2 once N is multiplied by 5^-E2.
+        let exponent = if e2 >= 0 {
+            n.shl(e2 as usize);
+            0
+        } else {
+            n.mul_pow5(-e2 as u32);
+            e2
+        };
+        Decimal::new(n.to_digits(), exponent)
+    }
+
+    pub fn from_integer(n: u64) -> Decimal {
+        Decimal::new(Big::from_u64(n).to_digits(), 0)
+    }
+
+    /// Return DIGITS * 10^EXPONENT.
+    fn new(mut digits: Vec<u8>, exponent: i32) -> Decimal {
+        let point = digits.len() as i32 + exponent;
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        if digits.is_empty() {
+            return Decimal {
+                digits,
+                point: 0,
+            };
+        }
+        Decimal { digits, point }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    /// Return the digit at the place 10^(POINT - 1 - INDEX).
+    fn digit(&self, index: i32) -> u8 {
+        if 0 <= index && (index as usize) < self.digits.len() {
+            self.digits[index as usize]
+        } else {
+            0
+        }
+    }
+
+    /// Round to KEPT significant digits, or to zero if KEPT is
+    /// negative, with ties to even like printf does.
+    fn round(&mut self, kept: i32) {
+        if kept >= self.digits.len() as i32 {
+            return;
+        }
+        if kept < 0 {
+            self.digits.clear();
+            self.point = 0;
+            return;
+        }
+        let kept = kept as usize;
+        let first = self.digits[kept];
+        let rest_nonzero = self.digits[kept + 1..].iter().any(|&digit| digit != 0);
+        let odd = kept > 0 && self.digits[kept - 1] % 2 == 1;
+        self.digits.truncate(kept);
+        if first > 5 || (first == 5 && (rest_nonzero || odd)) {
+            while self.digits.last() == Some(&9) {
+                self.digits.pop();
+            }
+            if self.digits.is_empty() {
+                self.digits.push(1);
+                self.point += 1;
+            } else {
+                let last = self.digits.len() - 1;
+                self.digits[last] += 1;
+            }
+        }
+        while self.digits.last() == Some(&0) {
+            self.digits.pop();
+        }
+        if self.digits.is_empty() {
+            self.point = 0;
+        }
+    }
+
+    /// The exponent of 10 of the leading digit, or 0 for zero.
+    fn exponent(&self) -> i32 {
+        if self.is_zero() {
+            0
+        } else {
+            self.point - 1
+        }
+    }
+
+    fn push_digits(&self, from: i32, to: i32, out: &mut String) {
+        for index in from..to {
+            out.push((b'0' + self.digit(index)) as char);
+        }
+    }
+
+    fn format_fixed(&mut self, precision: i32, alternate: bool, out: &mut String) {
+        let point = self.point;
+        self.round(point + precision);
+        if self.point <= 0 {
+            out.push('0');
+        } else {
+            self.push_digits(0, self.point, out);
+        }
+        if precision > 0 || alternate {
+            out.push('.');
+        }
+        self.push_digits(self.point, self.point + precision, out);
+    }
+
+    fn format_exponent(&mut self, precision: i32, alternate: bool, out: &mut String) {
+        self.round(precision + 1);
+        self.push_digits(0, 1, out);
+        if precision > 0 || alternate {
+            out.push('.');
+        }
+        self.push_digits(1, precision + 1, out);
+        let exponent = self.exponent();
+        out.push_str(&format!(
+            "e{}{:02}",
+            if exponent < 0 { '-' } else { '+' },
+            exponent.abs()
+        ));
+    }
+
+    fn format_general(&mut self, precision: i32, alternate: bool, out: &mut String) {
+        let precision = precision.max(1);
+        self.round(precision);
+        let exponent = self.exponent();
+        let start = out.len();
+        if -4 <= exponent && exponent < precision {
+            self.format_fixed(precision - 1 - exponent, alternate, out);
+        } else {
+            self.format_exponent(precision - 1, alternate, out);
+        }
+        if !alternate && out[start..].contains('.') {
+            // Remove the trailing zeros of the fraction, and the
+            // decimal point if nothing is left after it.
+            let exponent_start = out[start..].find('e').map_or(out.len(), |e| start + e);
+            let suffix = out[exponent_start..].to_string();
+            out.truncate(exponent_start);
+            while out.ends_with('0') {
+                out.pop();
+            }
+            if out.ends_with('.') {
+                out.pop();
+            }
+            out.push_str(&suffix);
+        }
+    }
+}
+
+/// Append NUMBER to OUT like the CONVERSION of printf, which is b'e',
+/// b'f' or b'g', with the PRECISION, and the # flag if ALTERNATE.  The
+/// sign is left to the caller.
+pub fn format_printf(
+    number: &mut Decimal,
+    conversion: u8,
+    precision: i32,
+    alternate: bool,
+    out: &mut String,
+) {
+    match conversion {
+        b'e' => number.format_exponent(precision, alternate, out),
+        b'f' => number.format_fixed(precision, alternate, out),
+        _ => number.format_general(precision, alternate, out),
+    }
+}
+
+#[cfg(test)]
+fn printf(value: f64, conversion: u8, precision: i32, alternate: bool) -> String {
+    let mut out = String::new();
+    format_printf(
+        &mut Decimal::from_float(value),
+        conversion,
+        precision,
+        alternate,
+        &mut out,
+    );
+    out
+}
+
+#[test]
+fn test_format_printf() {
+    assert_eq!(printf(0.0, b'e', 6, false), "0.000000e+00");
+    assert_eq!(printf(0.0, b'f', 0, false), "0");
+    assert_eq!(printf(0.0, b'f', 0, true), "0.");
+    assert_eq!(printf(0.0, b'g', 6, false), "0");
+    assert_eq!(printf(0.0, b'g', 3, true), "0.00");
+    assert_eq!(printf(0.5, b'f', 0, false), "0");
+    assert_eq!(printf(1.5, b'f', 0, false), "2");
+    assert_eq!(printf(2.5, b'f', 0, false), "2");
+    assert_eq!(printf(0.125, b'f', 2, false), "0.12");
+    assert_eq!(printf(0.1, b'f', 20, false), "0.10000000000000000555");
+    assert_eq!(printf(9.96, b'f', 1, false), "10.0");
+    assert_eq!(printf(1e300, b'f', 0, false).len(), 301);
+    assert_eq!(printf(123456.0, b'e', 2, false), "1.23e+05");
+    assert_eq!(printf(9.995, b'e', 2, false), "9.99e+00");
+    assert_eq!(printf(9.9951, b'e', 2, false), "1.00e+01");
+    assert_eq!(printf(1e-300, b'e', 0, true), "1.e-300");
+    assert_eq!(printf(100000.0, b'g', 6, false), "100000");
+    assert_eq!(printf(1000000.0, b'g', 6, false), "1e+06");
+    assert_eq!(printf(999999.5, b'g', 6, false), "1e+06");
+    assert_eq!(printf(0.0001, b'g', 6, false), "0.0001");
+    assert_eq!(printf(0.00001, b'g', 6, false), "1e-05");
+    assert_eq!(printf(1.5, b'g', 0, false), "2");
+    assert_eq!(printf(1.5, b'g', 6, true), "1.50000");
+    assert_eq!(printf(f64::from_bits(1), b'e', 3, false), "4.941e-324");
+    assert_eq!(printf(::std::f64::MAX, b'g', 17, false), "1.7976931348623157e+308");
+}
@@ -0,0 +1,181 @@
+//! The shortest decimal representation of a double, with the Ryu
+//! algorithm of Ulf Adams, "Ryū: fast float-to-string conversion"
+//! (PLDI 2018).
+
+include!(concat!(env!("OUT_DIR"), "/float_tables.rs"));
+
+/// The number of bits of the entries of `POW5_SPLIT` and
+/// `POW5_INV_SPLIT`.
+const POW5_BITS: i32 = 125;
+
+const MANTISSA_BITS: i32 = 52;
+const EXPONENT_BIAS: i32 = 1023;
+
+/// Return floor(log10(2^E)), for 0 <= E <= 1650.
+fn log10_pow2(e: i32) -> i32 {
+    ((e as u32 * 78_913) >> 18) as i32
+}
+
+/// Return floor(log10(5^E)), for 0 <= E <= 2620.
+fn log10_pow5(e: i32) -> i32 {
+    ((e as u32 * 732_923) >> 20) as i32
+}
+
+/// Return the bit length of 5^E, or 1 if E is 0.
+fn pow5_bits(e: i32) -> i32 {
+    ((e as u32 * 1_217_359) >> 19) as i32 + 1
+}
+
+fn pow5_factor(mut value: u64) -> i32 {
+    let mut count = 0;
+    while value % 5 == 0 {
+        value /= 5;
+        count += 1;
+    }
+    count
+}
+
+fn is_multiple_of_pow5(value: u64, p: i32) -> bool {
+    pow5_factor(value) >= p
+}
+
+fn is_multiple_of_pow2(value: u64, p: i32) -> bool {
+    value & ((1 << p) - 1) == 0
+}
+
+/// Return the low and the high halves of the product of A and B.
+fn mul_wide(a: u64, b: u64) -> (u64, u64) {
+    let (a_low, a_high) = (a & 0xffff_ffff, a >> 32);
+    let (b_low, b_high) = (b & 0xffff_ffff, b >> 32);
+    let low = a_low * b_low;
+    let middle1 = a_high * b_low + (low >> 32);
+    let middle2 = a_low * b_high + (middle1 & 0xffff_ffff);
+    let high = a_high * b_high + (middle1 >> 32) + (middle2 >> 32);
+    ((middle2 << 32) | (low & 0xffff_ffff), high)
+}
+
+/// Return (M * MUL) >> J, where MUL is a 125-bit table entry and the
+/// result fits in 64 bits.
+fn mul_shift(m: u64, mul: &[u64; 2], j: i32) -> u64 {
+    let (_, high0) = mul_wide(m, mul[0]);
+    let (low1, mut high1) = mul_wide(m, mul[1]);
+    let sum = high0.wrapping_add(low1);
+    if sum < high0 {
+        high1 += 1;
+    }
+    let shift = j - 64;
+    (high1 << (64 - shift)) | (sum >> shift)
+}
+
+/// Return the decimal digits and exponent of the shortest decimal
+/// number which reads back as the finite, non-zero double with the
+/// biased exponent IEEE_EXPONENT and the fraction IEEE_MANTISSA.  If
+/// there are several such numbers, return the closest one.  The
+/// digits may have trailing zeros.
+pub fn shortest(ieee_mantissa: u64, ieee_exponent: u32) -> (u64, i32) {
+    let (e2, m2) = if ieee_exponent == 0 {
+        (1 - EXPONENT_BIAS - MANTISSA_BITS - 2, ieee_mantissa)
+    } else {
+        (
+            ieee_exponent as i32 - EXPONENT_BIAS - MANTISSA_BITS - 2,
+            (1 << MANTISSA_BITS) | ieee_mantissa,
+        )
+    };
+    let accept_bounds = m2 & 1 == 0;
+
+    // The value is MV * 2^E2, and the halfway points to its
+    // neighbors are MP and MM.
+    let mv = 4 * m2;
+    let mm_shift = (ieee_mantissa != 0 || ieee_exponent <= 1) as u64;
+    let mp = 4 * m2 + 2;
+    let mm = 4 * m2 - 1 - mm_shift;
+
+    let mut vm_is_trailing_zeros = false;
+    let mut vr_is_trailing_zeros = false;
+    let (e10, mut vr, mut vp, mut vm);
+    if e2 >= 0 {
+        let q = log10_pow2(e2) - (e2 > 3) as i32;
+        e10 = q;
+        let k = POW5_BITS + pow5_bits(q) - 1;
+        let i = -e2 + q + k;
+        let mul = &POW5_INV_SPLIT[q as usize];
+        vr = mul_shift(mv, mul, i);
+        vp = mul_shift(mp, mul, i);
+        vm = mul_shift(mm, mul, i);
+        if q <= 21 {
+            // Only one of MP, MV and MM can be a multiple of 5.
+            if mv % 5 == 0 {
+                vr_is_trailing_zeros = is_multiple_of_pow5(mv, q);
+            } else if accept_bounds {
+                vm_is_trailing_zeros = is_multiple_of_pow5(mm, q);
+            } else if is_multiple_of_pow5(mp, q) {
+                vp -= 1;
+            }
+        }
+    } else {
+        let q = log10_pow5(-e2) - (-e2 > 1) as i32;
+        e10 = q + e2;
+        let i = -e2 - q;
+        let k = pow5_bits(i) - POW5_BITS;
+        let j = q - k;
+        let mul = &POW5_SPLIT[i as usize];
+        vr = mul_shift(mv, mul, j);
+        vp = mul_shift(mp, mul, j);
+        vm = mul_shift(mm, mul, j);
+        if q <= 1 {
+            // MV has at least Q trailing zero bits, and so do MM and
+            // MP, since they are M2 shifted by 2.
+            vr_is_trailing_zeros = true;
+            if accept_bounds {
+                vm_is_trailing_zeros = mm_shift == 1;
+            } else {
+                vp -= 1;
+            }
+        } else if q < 63 {
+            vr_is_trailing_zeros = is_multiple_of_pow2(mv, q);
+        }
+    }
+
+    // Remove the digits which VP and VM share no more, keeping track
+    // of whether VR is exact and of the last digit removed from it.
+    let mut removed = 0;
+    let mut last_removed_digit = 0;
+    let output = if vm_is_trailing_zeros || vr_is_trailing_zeros {
+        while vp / 10 > vm / 10 {
+            vm_is_trailing_zeros &= vm % 10 == 0;
+            vr_is_trailing_zeros &= last_removed_digit == 0;
+            last_removed_digit = vr % 10;
+            vr /= 10;
+            vp /= 10;
+            vm /= 10;
+            removed += 1;
+        }
+        if vm_is_trailing_zeros {
+            while vm % 10 == 0 {
+                vr_is_trailing_zeros &= last_removed_digit == 0;
+                last_removed_digit = vr % 10;
+                vr /= 10;
+                vm /= 10;
+                removed += 1;
+            }
+        }
+        if vr_is_trailing_zeros && last_removed_digit == 5 && vr % 2 == 0 {
+            // Round an exact tie to even.
+            last_removed_digit = 4;
+        }
+        let round_up = (vr == vm && (!accept_bounds || !vm_is_trailing_zeros))
+            || last_removed_digit >= 5;
+        vr + round_up as u64
+    } else {
+        let mut round_up = false;
+        while vp / 10 > vm / 10 {
+            round_up = vr % 10 >= 5;
+            vr /= 10;
+            vp /= 10;
+            vm /= 10;
+            removed += 1;
+        }
+        vr + (vr == vm || round_up) as u64
+    };
+    (output, e10 + removed)
+}
@@ -1,7 +1,10 @@
 //! Functions operating on float numbers.
 
 use libc;
+use libc::{c_char, c_int, ptrdiff_t};
 use std::mem;
+use std::ptr;
+use std::slice;
 
 use remacs_macros::lisp_fn;
 use remacs_sys::{EmacsDouble, EmacsInt, EmacsUint, Lisp_Object, MOST_NEGATIVE_FIXNUM,
@@ -10,6 +13,7 @@ use remacs_sys::{Qarith_error, Qinteger_or_marker_p, Qnumberp, Qrange_error};
 use remacs_sys::build_string;
 use remacs_sys::libm;
 
+use floatconv::{format_float, parse_float};
 use lisp::{LispNumber, LispObject};
 use lisp::defsubr;
 use math::ArithOp;
@@ -22,6 +26,32 @@ pub extern "C" fn extract_float(f: Lisp_Object) -> EmacsDouble {
     f.any_to_float_or_error()
 }
 
+/// Store the shortest decimal representation of VALUE which reads
+/// back as the same double in BUF, followed by a null byte, and return
+/// its length.  BUF must have room for `FLOAT_TO_STRING_BUFSIZE` bytes.
+/// This is how the printer prints floats when `float-output-format` is
+/// nil.
+#[no_mangle]
+pub extern "C" fn float_to_shortest_string(buf: *mut c_char, value: EmacsDouble) -> c_int {
+    let mut text = String::new();
+    format_float(value, &mut text);
+    unsafe {
+        ptr::copy_nonoverlapping(text.as_ptr() as *const c_char, buf, text.len());
+        *buf.offset(text.len() as isize) = 0;
+    }
+    text.len() as c_int
+}
+
+/// Return the double nearest to the decimal number in the LENGTH
+/// bytes at STRING, which may end in e+INF or e+NaN like the printed
+/// representation of infinities and NaNs.  Return 0 if STRING doesn't
+/// start with a number.
+#[no_mangle]
+pub extern "C" fn string_to_float(string: *const c_char, length: ptrdiff_t) -> EmacsDouble {
+    let text = unsafe { slice::from_raw_parts(string as *const u8, length as usize) };
+    parse_float(text).map_or(0.0, |(value, _)| value)
+}
+
 /// Calculate the modulus of two elisp floats.
 pub fn fmod_float(mut f1: f64, f2: f64) -> LispObject {
     f1 %= f2;
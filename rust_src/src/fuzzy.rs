@@ -0,0 +1,381 @@
+//! Fuzzy matching of completion candidates, scored like the fzf
+//! command-line fuzzy finder does.
+//!
+//! A pattern matches a candidate if its characters appear in the
+//! candidate in the same order.  Among the ways to match them, the one
+//! with the best score is chosen: each matched character scores
+//! points, gaps between them cost some, and characters at the start of
+//! words, after punctuation or at a camelCase hump earn bonuses, as do
+//! runs of consecutive characters.
+
+use libc::c_char;
+
+use remacs_macros::lisp_fn;
+use remacs_sys::{globals, make_unibyte_string, EmacsInt, Fadd_text_properties, Fall_completions,
+                 Fcopy_sequence};
+
+use lisp::{intern, LispObject};
+use lisp::defsubr;
+use multibyte::Codepoint;
+use unidata::{is_decimal_digit, is_letter, simple_case_mapping, Case};
+
+const SCORE_MATCH: i32 = 16;
+const SCORE_GAP_START: i32 = -3;
+const SCORE_GAP_EXTENSION: i32 = -1;
+
+/// The bonus of a character at the start of a word.
+const BONUS_BOUNDARY: i32 = SCORE_MATCH / 2;
+
+/// The bonus of a punctuation or whitespace character, which makes
+/// patterns containing them prefer matching them exactly.
+const BONUS_NON_WORD: i32 = SCORE_MATCH / 2;
+
+/// The bonus of an uppercase letter after a lowercase one, or of a
+/// digit after a non-digit.
+const BONUS_CAMEL_123: i32 = BONUS_BOUNDARY + SCORE_GAP_EXTENSION;
+
+/// The minimum bonus of the characters of a run of consecutive
+/// matches, which makes up for the cost of a gap of one character.
+const BONUS_CONSECUTIVE: i32 = -(SCORE_GAP_START + SCORE_GAP_EXTENSION);
+
+/// The bonus of the first character of the pattern is multiplied by
+/// this, so that matching it at the start of a word matters most.
+const BONUS_FIRST_CHAR_MULTIPLIER: i32 = 2;
+
+#[derive(Clone, Copy, PartialEq)]
+enum CharClass {
+    NonWord,
+    Lower,
+    Upper,
+    Letter,
+    Number,
+}
+
+fn char_class(c: Codepoint) -> CharClass {
+    if is_decimal_digit(c) {
+        CharClass::Number
+    } else if simple_case_mapping(c, Case::Upper) != c {
+        CharClass::Lower
+    } else if simple_case_mapping(c, Case::Lower) != c {
+        CharClass::Upper
+    } else if is_letter(c) {
+        CharClass::Letter
+    } else {
+        CharClass::NonWord
+    }
+}
+
+/// Return the bonus of a character of class CLASS after one of class
+/// PREVIOUS.
+fn bonus(previous: CharClass, class: CharClass) -> i32 {
+    if previous == CharClass::NonWord && class != CharClass::NonWord {
+        BONUS_BOUNDARY
+    } else if (previous == CharClass::Lower && class == CharClass::Upper)
+        || (previous != CharClass::Number && class == CharClass::Number)
+    {
+        BONUS_CAMEL_123
+    } else if class == CharClass::NonWord {
+        BONUS_NON_WORD
+    } else {
+        0
+    }
+}
+
+/// Return the score of the best match of PATTERN in TEXT and the
+/// indices of the characters of TEXT it matches, in increasing order,
+/// or None if PATTERN doesn't match.  If IGNORE_CASE, characters match
+/// regardless of their case.
+pub fn fuzzy_match(
+    pattern: &[Codepoint],
+    text: &[Codepoint],
+    ignore_case: bool,
+) -> Option<(i32, Vec<usize>)> {
+    if pattern.is_empty() {
+        return Some((0, Vec::new()));
+    }
+    let fold = |c| {
+        if ignore_case {
+            simple_case_mapping(c, Case::Lower)
+        } else {
+            c
+        }
+    };
+    let pattern: Vec<Codepoint> = pattern.iter().map(|&c| fold(c)).collect();
+    let folded: Vec<Codepoint> = text.iter().map(|&c| fold(c)).collect();
+
+    // FIRST[I] is the first place where the pattern up to I can be
+    // matched, and LAST is the last place where its last character
+    // can be.  The characters outside of this range don't matter.
+    let mut first = Vec::with_capacity(pattern.len());
+    let mut pos = 0;
+    for &c in &pattern {
+        pos += folded[pos..].iter().position(|&t| t == c)?;
+        first.push(pos);
+        pos += 1;
+    }
+    let last = folded.iter().rposition(|&t| t == pattern[pattern.len() - 1])?;
+
+    let bonuses: Vec<i32> = (0..text.len())
+        .scan(CharClass::NonWord, |previous, i| {
+            let class = char_class(text[i]);
+            let bonus = bonus(*previous, class);
+            *previous = class;
+            Some(bonus)
+        })
+        .collect();
+
+    // SCORES[I][J] is the best score of matching the pattern up to I
+    // in the text up to J, and CONSECUTIVE[I][J] the length of the run
+    // of consecutive matches ending there if the character J matches
+    // the character I, or 0.
+    let width = last + 1;
+    let mut scores = vec![0i32; pattern.len() * width];
+    let mut consecutive = vec![0usize; pattern.len() * width];
+    let (mut max_score, mut max_pos) = (0, first[pattern.len() - 1]);
+    for (i, &c) in pattern.iter().enumerate() {
+        let row = i * width;
+        let mut in_gap = false;
+        for j in first[i]..width {
+            let left = if j > first[i] { scores[row + j - 1] } else { 0 };
+            let mut gap = left + if in_gap {
+                SCORE_GAP_EXTENSION
+            } else {
+                SCORE_GAP_START
+            };
+            let mut matched = 0;
+            let mut run = 0;
+            if folded[j] == c {
+                if i == 0 {
+                    matched = SCORE_MATCH + bonuses[j] * BONUS_FIRST_CHAR_MULTIPLIER;
+                    run = 1;
+                    // A match of the first character starts afresh,
+                    // whatever came before it.
+                    gap = 0;
+                } else {
+                    let mut bonus = bonuses[j];
+                    run = consecutive[row - width + j - 1] + 1;
+                    if run > 1 {
+                        let first_bonus = bonuses[j + 1 - run];
+                        if bonus >= BONUS_BOUNDARY && bonus > first_bonus {
+                            // Start a new run at a word boundary.
+                            run = 1;
+                        } else {
+                            bonus = bonus.max(BONUS_CONSECUTIVE).max(first_bonus);
+                        }
+                    }
+                    matched = scores[row - width + j - 1] + SCORE_MATCH;
+                    if matched + bonus < gap {
+                        matched += bonuses[j];
+                        run = 0;
+                    } else {
+                        matched += bonus;
+                    }
+                }
+            }
+            consecutive[row + j] = run;
+            in_gap = matched < gap;
+            let score = matched.max(gap).max(0);
+            scores[row + j] = score;
+            if i == pattern.len() - 1 && score > max_score {
+                max_score = score;
+                max_pos = j;
+            }
+        }
+    }
+
+    // Trace the best match back, preferring to extend runs of
+    // consecutive matches.
+    let mut positions = Vec::with_capacity(pattern.len());
+    let (mut i, mut j) = (pattern.len() - 1, max_pos);
+    let mut prefer_match = true;
+    loop {
+        let row = i * width;
+        let score = scores[row + j];
+        let diagonal = if i > 0 && j >= first[i] {
+            scores[row - width + j - 1]
+        } else {
+            0
+        };
+        let left = if j > first[i] { scores[row + j - 1] } else { 0 };
+        if score > diagonal && (score > left || (score == left && prefer_match)) {
+            positions.push(j);
+            if i == 0 {
+                break;
+            }
+            i -= 1;
+        }
+        prefer_match = consecutive[row + j] > 1
+            || (row + width + j + 1 < consecutive.len() && consecutive[row + width + j + 1] > 0);
+        j -= 1;
+    }
+    positions.reverse();
+    Some((max_score, positions))
+}
+
+fn has_uppercase(pattern: &[Codepoint]) -> bool {
+    pattern
+        .iter()
+        .any(|&c| simple_case_mapping(c, Case::Lower) != c)
+}
+
+/// Return true if PATTERN should match regardless of case: if
+/// `completion-ignore-case' is non-nil, or if PATTERN has no uppercase
+/// letters.
+fn ignore_case_for(pattern: &[Codepoint]) -> bool {
+    unsafe { globals.f_completion_ignore_case } || !has_uppercase(pattern)
+}
+
+/// Return the list of the character positions in POSITIONS.
+fn positions_list(positions: &[usize]) -> LispObject {
+    positions
+        .iter()
+        .rev()
+        .fold(LispObject::constant_nil(), |list, &pos| {
+            LispObject::cons(LispObject::from_natnum(pos as EmacsInt), list)
+        })
+}
+
+/// Return a copy of CANDIDATE with the characters at POSITIONS
+/// highlighted with the face `completions-common-part'.
+fn highlight(candidate: LispObject, positions: &[usize]) -> LispObject {
+    let copy = LispObject::from(unsafe { Fcopy_sequence(candidate.to_raw()) });
+    let properties = list!(intern("face"), intern("completions-common-part"));
+    let mut idx = 0;
+    while idx < positions.len() {
+        let start = positions[idx];
+        let mut end = start + 1;
+        idx += 1;
+        while idx < positions.len() && positions[idx] == end {
+            end += 1;
+            idx += 1;
+        }
+        unsafe {
+            Fadd_text_properties(
+                LispObject::from_natnum(start as EmacsInt).to_raw(),
+                LispObject::from_natnum(end as EmacsInt).to_raw(),
+                properties.to_raw(),
+                copy.to_raw(),
+            )
+        };
+    }
+    copy
+}
+
+/// Return the fuzzy match score of PATTERN in CANDIDATE.
+/// PATTERN matches CANDIDATE if all its characters appear in CANDIDATE
+/// in the same order, though not necessarily consecutively.  The value
+/// is nil if it doesn't match, and otherwise a list (SCORE . POSITIONS),
+/// where SCORE is an integer which is higher for better matches, and
+/// POSITIONS is the list of the indices of the characters of CANDIDATE
+/// matched by PATTERN, in increasing order.
+///
+/// Matches score higher when the matched characters are consecutive,
+/// and when they are at the start of words, after punctuation, or at
+/// the uppercase letters of camelCase words.  Of the many ways PATTERN
+/// may match CANDIDATE, the one with the highest score is chosen.
+///
+/// Case is ignored if `completion-ignore-case' is non-nil, or if
+/// PATTERN contains no uppercase letters.  Symbols are also allowed;
+/// their print names are used instead.
+#[lisp_fn]
+fn completion_fuzzy_score(pattern: LispObject, candidate: LispObject) -> LispObject {
+    let pattern: Vec<Codepoint> = pattern.symbol_or_string_as_string().chars().collect();
+    let text: Vec<Codepoint> = candidate.symbol_or_string_as_string().chars().collect();
+    match fuzzy_match(&pattern, &text, ignore_case_for(&pattern)) {
+        Some((score, positions)) => LispObject::cons(
+            LispObject::from_fixnum(EmacsInt::from(score)),
+            positions_list(&positions),
+        ),
+        None => LispObject::constant_nil(),
+    }
+}
+
+/// Return the completions in COLLECTION which match PATTERN fuzzily.
+/// COLLECTION may be anything `all-completions' accepts, and each of
+/// its completions is scored like `completion-fuzzy-score' does.  The
+/// value is a list of copies of the matching completions, best matches
+/// first, with the characters matched by PATTERN highlighted with the
+/// face `completions-common-part'.  Completions with the same score
+/// are sorted shortest first, and otherwise keep their order in
+/// COLLECTION.
+///
+/// If LIMIT is non-nil, it is the maximum number of completions to
+/// return.
+#[lisp_fn(min = "2")]
+fn completion_fuzzy_filter(
+    pattern: LispObject,
+    collection: LispObject,
+    limit: LispObject,
+) -> LispObject {
+    let pattern: Vec<Codepoint> = pattern.symbol_or_string_as_string().chars().collect();
+    let limit = if limit.is_nil() {
+        usize::max_value()
+    } else {
+        limit.as_natnum_or_error() as usize
+    };
+    let ignore_case = ignore_case_for(&pattern);
+
+    let completions = LispObject::from(unsafe {
+        Fall_completions(
+            make_unibyte_string(b"".as_ptr() as *const c_char, 0),
+            collection.to_raw(),
+            LispObject::constant_nil().to_raw(),
+            LispObject::constant_nil().to_raw(),
+        )
+    });
+    let mut matches = Vec::new();
+    for tail in completions.iter_tails() {
+        let completion = tail.car();
+        let text: Vec<Codepoint> = completion.as_string_or_error().chars().collect();
+        if let Some((score, positions)) = fuzzy_match(&pattern, &text, ignore_case) {
+            matches.push((score, text.len(), completion, positions));
+        }
+    }
+    matches.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    matches.truncate(limit);
+
+    matches
+        .iter()
+        .rev()
+        .fold(LispObject::constant_nil(), |list, &(_, _, completion, ref positions)| {
+            LispObject::cons(highlight(completion, positions), list)
+        })
+}
+
+include!(concat!(env!("OUT_DIR"), "/fuzzy_exports.rs"));
+
+#[cfg(test)]
+fn score(pattern: &str, text: &str) -> Option<(i32, Vec<usize>)> {
+    let pattern: Vec<Codepoint> = pattern.chars().map(|c| c as Codepoint).collect();
+    let text: Vec<Codepoint> = text.chars().map(|c| c as Codepoint).collect();
+    fuzzy_match(&pattern, &text, !has_uppercase(&pattern))
+}
+
+#[test]
+fn test_fuzzy_match() {
+    assert_eq!(score("", "foo"), Some((0, vec![])));
+    assert_eq!(score("xyz", "foo"), None);
+    assert_eq!(score("oof", "foo"), None);
+    assert_eq!(score("fb", "foo-bar").unwrap().1, [0, 4]);
+    assert_eq!(score("fb", "FooBar").unwrap().1, [0, 3]);
+    assert_eq!(score("FB", "foobar"), None);
+    assert_eq!(score("FB", "FooBar").unwrap().1, [0, 3]);
+    assert_eq!(score("bar", "b_a_r_bar").unwrap().1, [6, 7, 8]);
+    assert_eq!(score("ab", "xaxab").unwrap().1, [3, 4]);
+    assert_eq!(score("ä", "Ä").unwrap().1, [0]);
+}
+
+#[test]
+fn test_fuzzy_score_order() {
+    let value = |pattern, text| score(pattern, text).unwrap().0;
+    // Word boundaries and camelCase humps beat plain matches.
+    assert!(value("fb", "foo-bar") > value("fb", "foobar"));
+    assert!(value("fb", "fooBar") > value("fb", "foobar"));
+    // Consecutive matches beat scattered ones.
+    assert!(value("foo", "xfoo") > value("foo", "xfxoxo"));
+    // Matches at the start beat those in the middle.
+    assert!(value("bar", "barfoo") > value("bar", "foobar"));
+    // Shorter gaps beat longer ones.
+    assert!(value("ab", "a-b") > value("ab", "a--b"));
+    assert!(value("fi", "find-file") > value("fi", "font-lock-mode-in"));
+}
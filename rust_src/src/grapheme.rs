@@ -0,0 +1,264 @@
+//! Extended grapheme clusters, as described in Unicode Standard Annex
+//! #29.
+//!
+//! A grapheme cluster is what a user thinks of as a single character:
+//! a base character with its combining marks, a Hangul syllable made of
+//! conjoining jamo, a flag made of two regional indicators, or an emoji
+//! ZWJ sequence.
+
+use libc::ptrdiff_t;
+
+use remacs_macros::lisp_fn;
+use remacs_sys::{buf_charpos_to_bytepos, set_point, EmacsInt, Fsubstring};
+
+use buffers::LispBufferRef;
+use lisp::LispObject;
+use lisp::defsubr;
+use multibyte::Codepoint;
+use threads::ThreadState;
+use unidata::{grapheme_break, is_extended_pictographic, GraphemeBreak};
+
+/// Finds grapheme cluster boundaries in a sequence of characters that
+/// is fed to it one character at a time.
+pub struct GraphemeSegmenter {
+    prev: Option<GraphemeBreak>,
+    /// Number of regional indicators immediately before the current
+    /// position.
+    regional_indicators: usize,
+    /// True if the characters before the current position are an
+    /// Extended_Pictographic character followed by Extend characters.
+    in_pictographic: bool,
+    /// True if the previous character is a ZWJ that follows such a
+    /// sequence.
+    zwj_after_pictographic: bool,
+}
+
+impl GraphemeSegmenter {
+    pub fn new() -> GraphemeSegmenter {
+        GraphemeSegmenter {
+            prev: None,
+            regional_indicators: 0,
+            in_pictographic: false,
+            zwj_after_pictographic: false,
+        }
+    }
+
+    /// Feed C to the segmenter, and return true if there is a
+    /// grapheme cluster boundary before it.
+    pub fn is_boundary_before(&mut self, c: Codepoint) -> bool {
+        use unidata::GraphemeBreak::*;
+
+        let pictographic = is_extended_pictographic(c);
+        let current = grapheme_break(c);
+        let boundary = match (self.prev, current) {
+            // GB1
+            (None, _) => true,
+            // GB3, GB4, GB5
+            (Some(CR), LF) => false,
+            (Some(CR), _) | (Some(LF), _) | (Some(Control), _) => true,
+            (_, CR) | (_, LF) | (_, Control) => true,
+            // GB6, GB7, GB8
+            (Some(L), L) | (Some(L), V) | (Some(L), LV) | (Some(L), LVT) => false,
+            (Some(LV), V) | (Some(LV), T) | (Some(V), V) | (Some(V), T) => false,
+            (Some(LVT), T) | (Some(T), T) => false,
+            // GB9, GB9a, GB9b
+            (_, Extend) | (_, ZWJ) | (_, SpacingMark) | (Some(Prepend), _) => false,
+            // GB11
+            (Some(ZWJ), _) if self.zwj_after_pictographic && pictographic => false,
+            // GB12, GB13
+            (Some(RegionalIndicator), RegionalIndicator) => self.regional_indicators % 2 == 0,
+            // GB999
+            _ => true,
+        };
+
+        self.regional_indicators = if current == RegionalIndicator {
+            self.regional_indicators + 1
+        } else {
+            0
+        };
+        if pictographic {
+            self.in_pictographic = true;
+            self.zwj_after_pictographic = false;
+        } else if current == ZWJ {
+            self.zwj_after_pictographic = self.in_pictographic;
+            self.in_pictographic = false;
+        } else if current != Extend {
+            self.in_pictographic = false;
+            self.zwj_after_pictographic = false;
+        }
+        self.prev = Some(current);
+
+        boundary
+    }
+}
+
+/// Return the indices in CHARS at which grapheme clusters start,
+/// followed by the length of CHARS.
+pub fn grapheme_boundaries(chars: &[Codepoint]) -> Vec<usize> {
+    let mut segmenter = GraphemeSegmenter::new();
+    let mut boundaries: Vec<usize> = chars
+        .iter()
+        .enumerate()
+        .filter(|&(_, &c)| segmenter.is_boundary_before(c))
+        .map(|(idx, _)| idx)
+        .collect();
+    boundaries.push(chars.len());
+    boundaries
+}
+
+impl LispBufferRef {
+    /// Return the character at character position POS.
+    fn char_at(self, pos: ptrdiff_t) -> Codepoint {
+        let bytepos = unsafe { buf_charpos_to_bytepos(self.as_ptr(), pos) };
+        self.fetch_char(bytepos) as Codepoint
+    }
+}
+
+/// Return the last position before POS, or BEGV, from which a new
+/// `GraphemeSegmenter` finds the same boundaries after it as one that
+/// started at BEGV.  That is the case after any character other than a
+/// regional indicator, an Extend character or a ZWJ, since only those
+/// make a boundary depend on the characters before them.
+fn safe_start(buffer: LispBufferRef, mut pos: ptrdiff_t, begv: ptrdiff_t) -> ptrdiff_t {
+    while pos > begv {
+        pos -= 1;
+        match grapheme_break(buffer.char_at(pos)) {
+            GraphemeBreak::RegionalIndicator | GraphemeBreak::Extend | GraphemeBreak::ZWJ => (),
+            _ => return pos,
+        }
+    }
+    begv
+}
+
+/// Return the last grapheme cluster boundary before POS, which is
+/// after BEGV, in BUFFER.
+fn previous_boundary(buffer: LispBufferRef, pos: ptrdiff_t, begv: ptrdiff_t) -> ptrdiff_t {
+    // Look for the boundaries up to LAST, from a safe start before it,
+    // and further back as long as there are none.
+    let mut last = pos - 1;
+    loop {
+        let start = safe_start(buffer, last, begv);
+        let mut segmenter = GraphemeSegmenter::new();
+        segmenter.is_boundary_before(buffer.char_at(start));
+        let boundary = (start + 1..last + 1)
+            .filter(|&pos| segmenter.is_boundary_before(buffer.char_at(pos)))
+            .last();
+        match boundary {
+            Some(boundary) => return boundary,
+            None if start == begv => return begv,
+            None => last = start,
+        }
+    }
+}
+
+/// Return a list of the grapheme clusters of STRING.
+/// Each element is a substring of STRING that is displayed as a single
+/// character, such as a base character followed by combining marks, a
+/// flag made of two regional indicators, or an emoji ZWJ sequence.
+#[lisp_fn]
+fn string_grapheme_clusters(string: LispObject) -> LispObject {
+    let chars: Vec<Codepoint> = string.as_string_or_error().chars().collect();
+    let boundaries = grapheme_boundaries(&chars);
+
+    boundaries
+        .windows(2)
+        .rev()
+        .fold(LispObject::constant_nil(), |list, range| {
+            let cluster = LispObject::from(unsafe {
+                Fsubstring(
+                    string.to_raw(),
+                    LispObject::from_natnum(range[0] as EmacsInt).to_raw(),
+                    LispObject::from_natnum(range[1] as EmacsInt).to_raw(),
+                )
+            });
+            LispObject::cons(cluster, list)
+        })
+}
+
+/// Move point forward N grapheme clusters (backward if N is negative).
+/// N defaults to 1.  A grapheme cluster is a sequence of characters
+/// displayed as a single character, such as a base character followed
+/// by combining marks, or an emoji ZWJ sequence.
+/// Returns the count of grapheme clusters left to move, which is
+/// non-zero only if the beginning or end of the accessible portion of
+/// the buffer was reached.
+#[lisp_fn(min = "0", intspec = "^p")]
+fn forward_grapheme(n: LispObject) -> LispObject {
+    let mut count = if n.is_nil() {
+        1
+    } else {
+        n.as_fixnum_or_error()
+    };
+    let buffer = ThreadState::current_buffer();
+    let begv = buffer.begv;
+    let zv = buffer.zv();
+    let mut pos = buffer.pt();
+
+    // Whether there is a boundary before a character depends on the
+    // characters before it, but only back to a safe start.
+    if count > 0 && pos < zv {
+        let start = safe_start(buffer, pos, begv);
+        let mut segmenter = GraphemeSegmenter::new();
+        for c in (start..pos + 1).map(|pos| buffer.char_at(pos)) {
+            segmenter.is_boundary_before(c);
+        }
+        while count > 0 && pos < zv {
+            pos += 1;
+            while pos < zv && !segmenter.is_boundary_before(buffer.char_at(pos)) {
+                pos += 1;
+            }
+            count -= 1;
+        }
+    }
+    while count < 0 && pos > begv {
+        pos = previous_boundary(buffer, pos, begv);
+        count += 1;
+    }
+
+    unsafe { set_point(pos) };
+    LispObject::from_fixnum(count.abs())
+}
+
+/// Move point backward N grapheme clusters (forward if N is negative).
+/// N defaults to 1.  See `forward-grapheme' for what a grapheme cluster
+/// is.  Returns the count of grapheme clusters left to move.
+#[lisp_fn(min = "0", intspec = "^p")]
+fn backward_grapheme(n: LispObject) -> LispObject {
+    let count = if n.is_nil() {
+        1
+    } else {
+        n.as_fixnum_or_error()
+    };
+    forward_grapheme(LispObject::from_fixnum(-count))
+}
+
+include!(concat!(env!("OUT_DIR"), "/grapheme_exports.rs"));
+
+#[test]
+fn test_grapheme_break_conformance() {
+    use std::fs::File;
+    use std::io::{BufRead, BufReader};
+
+    let path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../admin/unidata/GraphemeBreakTest.txt"
+    );
+    for line in BufReader::new(File::open(path).unwrap()).lines() {
+        let line = line.unwrap();
+        let data = line.split('#').next().unwrap().trim();
+        if data.is_empty() {
+            continue;
+        }
+
+        let mut chars = Vec::new();
+        let mut expected = Vec::new();
+        for token in data.split_whitespace() {
+            match token {
+                "\u{F7}" => expected.push(chars.len()),
+                "\u{D7}" => (),
+                code => chars.push(Codepoint::from_str_radix(code, 16).unwrap()),
+            }
+        }
+        assert_eq!(grapheme_boundaries(&chars), expected, "{}", line);
+    }
+}
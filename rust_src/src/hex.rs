@@ -0,0 +1,89 @@
+//! Hexadecimal de- and encoding functions.
+
+use remacs_macros::lisp_fn;
+
+use codec::{self, Decoder, Encoder, Output};
+use lisp::LispObject;
+use lisp::defsubr;
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/// An encoder writing each byte as two lower case hex digits.
+pub struct HexEncoder;
+
+impl Encoder for HexEncoder {
+    fn push(&mut self, byte: u8, out: &mut Vec<u8>) {
+        out.push(HEX_DIGITS[(byte >> 4) as usize]);
+        out.push(HEX_DIGITS[(byte & 0xF) as usize]);
+    }
+
+    fn finish(self, _out: &mut Vec<u8>) {}
+}
+
+/// A decoder for pairs of hex digits of either case.  Anything else,
+/// including whitespace, is invalid.
+pub struct HexDecoder {
+    high: Option<u8>,
+}
+
+impl HexDecoder {
+    pub fn new() -> HexDecoder {
+        HexDecoder { high: None }
+    }
+}
+
+/// Return the value of the hex digit BYTE.
+pub fn hex_digit_value(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'...b'9' => Some(byte - b'0'),
+        b'a'...b'f' => Some(byte - b'a' + 10),
+        b'A'...b'F' => Some(byte - b'A' + 10),
+        _ => None,
+    }
+}
+
+impl Decoder for HexDecoder {
+    fn push(&mut self, byte: u8, out: &mut Output) -> Option<()> {
+        let value = hex_digit_value(byte)?;
+        match self.high.take() {
+            Some(high) => out.push(high << 4 | value),
+            None => self.high = Some(value),
+        }
+        Some(())
+    }
+
+    fn finish(self, _out: &mut Output) -> Option<()> {
+        match self.high {
+            Some(_) => None,
+            None => Some(()),
+        }
+    }
+}
+
+#[test]
+fn test_hex() {
+    let encoded = codec::encode(HexEncoder, &[b"\x00\x7F", b"\xAB\xff"], false).unwrap();
+    assert_eq!(encoded, b"007fabff");
+    let decode = |input: &[u8]| codec::decode(HexDecoder::new(), &[input], false);
+    assert_eq!(decode(b"007FabfF").unwrap().bytes, b"\x00\x7F\xAB\xFF");
+    assert!(decode(b"007").is_none());
+    assert!(decode(b"00 7f").is_none());
+    assert!(decode(b"0g").is_none());
+    codec::check_round_trip(|| HexEncoder, HexDecoder::new);
+}
+
+/// Hex-encode STRING and return the result.
+/// Each byte of STRING is written as two lower case hex digits.
+#[lisp_fn]
+fn hex_encode_string(string: LispObject) -> LispObject {
+    codec::encode_string(string, HexEncoder, "hex")
+}
+
+/// Hex-decode STRING and return the result as a unibyte string.
+/// STRING must consist of pairs of hex digits, of either case.
+#[lisp_fn]
+fn hex_decode_string(string: LispObject) -> LispObject {
+    codec::decode_string(string, HexDecoder::new(), "hex")
+}
+
+include!(concat!(env!("OUT_DIR"), "/hex_exports.rs"));
@@ -9,7 +9,6 @@
 #[macro_use]
 extern crate lazy_static;
 
-extern crate base64 as base64_crate;
 extern crate libc;
 extern crate md5;
 extern crate rand;
@@ -39,40 +38,62 @@ mod lisp;
 mod vector_macros;
 mod str2sig;
 
+mod archive;
+mod base32;
 mod base64;
 mod buffers;
+mod casefiddle;
 mod category;
+mod chardet;
 mod character;
 mod chartable;
 mod cmds;
+mod codec;
+mod coding;
+mod collate;
 mod crypto;
 mod data;
 mod dispnew;
 mod editfns;
+mod floatconv;
 mod floatfns;
 mod fns;
 mod fonts;
 mod frames;
+mod fuzzy;
+mod grapheme;
 mod hashtable;
+mod hex;
 mod indent;
 mod interactive;
 mod keyboard;
 mod keymap;
+mod lisp_data;
 mod lists;
 mod marker;
 mod math;
 mod minibuf;
 mod multibyte;
+mod normalize;
 mod numbers;
 mod obarray;
 mod objects;
+mod parse_date;
 mod process;
+mod quoted_printable;
+mod random;
 mod strings;
 mod symbols;
 mod threads;
+mod timefns;
+mod tzfile;
+mod unidata;
+mod url;
 mod util;
 mod vectors;
 mod windows;
+mod xml;
+mod zlib;
 
 #[cfg(all(not(test), target_os = "macos"))]
 use alloc_unexecmacosx::OsxUnexecAlloc;
@@ -21,7 +21,8 @@ use remacs_sys::{Lisp_Cons, Lisp_Float, Lisp_Misc_Any, Lisp_Misc_Type, Lisp_Obje
 use remacs_sys::{Qbufferp, Qchar_table_p, Qcharacterp, Qconsp, Qfloatp, Qframe_live_p, Qframep,
                  Qhash_table_p, Qinteger_or_marker_p, Qintegerp, Qlistp, Qmarkerp, Qnil,
                  Qnumber_or_marker_p, Qnumberp, Qoverlayp, Qplistp, Qprocessp, Qstringp, Qsymbolp,
-                 Qt, Qthreadp, Qunbound, Qwholenump, Qwindow_live_p, Qwindow_valid_p, Qwindowp};
+                 Qt, Qthreadp, Qunbound, Qvectorp, Qwholenump, Qwindow_live_p, Qwindow_valid_p,
+                 Qwindowp};
 use remacs_sys::{internal_equal, lispsym, make_float};
 
 use buffers::{LispBufferRef, LispOverlayRef};
@@ -36,7 +37,7 @@ use obarray::LispObarrayRef;
 use process::LispProcessRef;
 use symbols::LispSymbolRef;
 use threads::ThreadStateRef;
-use vectors::{LispVectorRef, LispVectorlikeRef};
+use vectors::{LispBoolVecRef, LispVectorRef, LispVectorlikeRef};
 use windows::LispWindowRef;
 
 #[cfg(test)]
@@ -433,6 +434,17 @@ impl LispObject {
     }
     */
 
+    #[inline]
+    pub fn as_vector(self) -> Option<LispVectorRef> {
+        self.as_vectorlike().and_then(|v| v.as_vector())
+    }
+
+    #[inline]
+    pub fn as_vector_or_error(self) -> LispVectorRef {
+        self.as_vector()
+            .unwrap_or_else(|| wrong_type!(Qvectorp, self))
+    }
+
     pub unsafe fn as_vectorlike_unchecked(self) -> LispVectorlikeRef {
         LispVectorlikeRef::new(mem::transmute(self.get_untaggedptr()))
     }
@@ -515,6 +527,10 @@ impl LispObject {
         )
     }
 
+    pub fn as_bool_vector(self) -> Option<LispBoolVecRef> {
+        self.as_vectorlike().and_then(|v| v.as_bool_vector())
+    }
+
     pub fn is_array(self) -> bool {
         self.is_vector() || self.is_string() || self.is_char_table() || self.is_bool_vector()
     }
@@ -0,0 +1,643 @@
+//! A compact binary format for Lisp data.
+//!
+//! The data starts with `MAGIC` and a version byte, followed by one
+//! object.  Each object is a tag byte and its contents.  Objects other
+//! than integers are numbered in the order they start, and an object
+//! which was already written is written again as a reference to its
+//! number, so shared structure and cycles are kept.
+
+use std::collections::HashMap;
+
+use libc::{c_char, ptrdiff_t};
+
+use remacs_macros::lisp_fn;
+use remacs_sys::{inhibit_garbage_collection, make_specified_string, unbind_to, EmacsDouble,
+                 EmacsInt, Faref, Faset, Fmake_bool_vector, Fmake_hash_table, Fmake_record,
+                 Fmake_symbol, Fmake_vector, Fnext_property_change, Fset_text_properties,
+                 Ftext_properties_at, Qerror};
+
+use codec::{make_unibyte, string_bytes};
+use lisp::{intern, LispObject};
+use lisp::defsubr;
+use multibyte::{multibyte_length, LispStringRef};
+use obarray::LispObarrayRef;
+
+/// The start of serialized Lisp data.
+const MAGIC: &[u8] = b"\0ELD";
+
+/// The version of the format, which is increased whenever the format
+/// changes, so that data in an old format is rejected.
+const VERSION: u8 = 1;
+
+/// How deeply objects can be nested in each other.  The serializer
+/// and deserializer both recurse, and this keeps them from running out
+/// of stack.
+const MAX_DEPTH: usize = 10_000;
+
+const TAG_REFERENCE: u8 = b'#';
+const TAG_INTEGER: u8 = b'i';
+const TAG_FLOAT: u8 = b'f';
+const TAG_SYMBOL: u8 = b's';
+const TAG_UNINTERNED_SYMBOL: u8 = b'u';
+const TAG_STRING: u8 = b'"';
+const TAG_LIST: u8 = b'(';
+const TAG_VECTOR: u8 = b'[';
+const TAG_RECORD: u8 = b'r';
+const TAG_BOOL_VECTOR: u8 = b'b';
+const TAG_HASH_TABLE: u8 = b'h';
+
+/// Append N to OUT as an unsigned LEB128 number.
+fn write_varint(out: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        out.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+/// Read an unsigned LEB128 number from DATA at *POS.
+fn read_varint(data: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut n = 0u64;
+    let mut shift = 0;
+    loop {
+        let byte = *data.get(*pos)?;
+        *pos += 1;
+        if shift == 63 && byte > 1 {
+            return None;
+        }
+        n |= u64::from(byte & 0x7F) << shift;
+        if byte & 0x80 == 0 {
+            return Some(n);
+        }
+        shift += 7;
+        if shift > 63 {
+            return None;
+        }
+    }
+}
+
+/// Map N to an unsigned number, with small negative numbers small.
+fn zigzag(n: i64) -> u64 {
+    ((n << 1) ^ (n >> 63)) as u64
+}
+
+fn unzigzag(n: u64) -> i64 {
+    (n >> 1) as i64 ^ -((n & 1) as i64)
+}
+
+/// Return the number of characters in the multibyte TEXT, or None if
+/// it isn't valid multibyte text.
+fn multibyte_chars(text: &[u8]) -> Option<usize> {
+    let mut chars = 0;
+    let mut idx = 0;
+    while idx < text.len() {
+        idx += multibyte_length(&text[idx..], true)?;
+        chars += 1;
+    }
+    Some(chars)
+}
+
+struct Serializer {
+    out: Vec<u8>,
+    /// The number of each object written so far.
+    numbers: HashMap<EmacsInt, u64>,
+    depth: usize,
+}
+
+impl Serializer {
+    fn new() -> Serializer {
+        let mut out = MAGIC.to_vec();
+        out.push(VERSION);
+        Serializer {
+            out,
+            numbers: HashMap::new(),
+            depth: 0,
+        }
+    }
+
+    /// Number OBJECT, or write a reference to it and return false if
+    /// it already has a number.
+    fn number(&mut self, object: LispObject) -> bool {
+        let next = self.numbers.len() as u64;
+        let number = *self.numbers.entry(object.to_raw()).or_insert(next);
+        if number == next {
+            return true;
+        }
+        self.out.push(TAG_REFERENCE);
+        write_varint(&mut self.out, number);
+        false
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        write_varint(&mut self.out, bytes.len() as u64);
+        self.out.extend_from_slice(bytes);
+    }
+
+    fn write_string_data(&mut self, string: LispStringRef) {
+        self.out.push(string.is_multibyte() as u8);
+        self.write_bytes(string.as_slice());
+    }
+
+    fn write(&mut self, object: LispObject) {
+        if let Some(n) = object.as_fixnum() {
+            self.out.push(TAG_INTEGER);
+            write_varint(&mut self.out, zigzag(n as i64));
+            return;
+        }
+        if !self.number(object) {
+            return;
+        }
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            error!("Lisp data nested too deeply to serialize");
+        }
+
+        if let Some(f) = object.as_float() {
+            self.out.push(TAG_FLOAT);
+            let bits = f.to_bits();
+            self.out.extend((0..8).map(|idx| (bits >> (8 * idx)) as u8));
+        } else if let Some(symbol) = object.as_symbol() {
+            self.out.push(if symbol.is_interned_in_initial_obarray() {
+                TAG_SYMBOL
+            } else {
+                TAG_UNINTERNED_SYMBOL
+            });
+            self.write_string_data(symbol.symbol_name().as_string_or_error());
+        } else if let Some(string) = object.as_string() {
+            self.out.push(TAG_STRING);
+            self.write_string_data(string);
+            self.write_text_properties(object, string.len_chars());
+        } else if object.is_cons() {
+            self.write_list(object);
+        } else if let Some(vector) = object.as_vector() {
+            self.out.push(TAG_VECTOR);
+            write_varint(&mut self.out, vector.len() as u64);
+            for &item in vector.as_slice() {
+                self.write(item);
+            }
+        } else if object.is_record() {
+            self.out.push(TAG_RECORD);
+            let size = object.as_vectorlike_or_error().pseudovector_size();
+            write_varint(&mut self.out, size as u64);
+            for idx in 0..size {
+                let index = LispObject::from_natnum(idx);
+                self.write(LispObject::from(unsafe { Faref(object.to_raw(), index.to_raw()) }));
+            }
+        } else if let Some(bool_vector) = object.as_bool_vector() {
+            self.out.push(TAG_BOOL_VECTOR);
+            let len = bool_vector.len();
+            write_varint(&mut self.out, len as u64);
+            let mut byte = 0;
+            for idx in 0..len {
+                if bool_vector.get(idx) {
+                    byte |= 1 << (idx % 8);
+                }
+                if idx % 8 == 7 || idx == len - 1 {
+                    self.out.push(byte);
+                    byte = 0;
+                }
+            }
+        } else if object.is_hash_table() {
+            self.write_hash_table(object);
+        } else {
+            xsignal!(Qerror, make_unibyte(b"Cannot serialize object"), object);
+        }
+        self.depth -= 1;
+    }
+
+    /// Write the list which starts with the cons OBJECT, up to the first
+    /// cdr which isn't a cons or which was already written.
+    fn write_list(&mut self, object: LispObject) {
+        let mut conses = vec![object.as_cons_or_error()];
+        let mut tail = conses[0].cdr();
+        while let Some(cons) = tail.as_cons() {
+            let next = self.numbers.len() as u64;
+            if self.numbers.contains_key(&tail.to_raw()) {
+                break;
+            }
+            self.numbers.insert(tail.to_raw(), next);
+            conses.push(cons);
+            tail = cons.cdr();
+        }
+        self.out.push(TAG_LIST);
+        write_varint(&mut self.out, conses.len() as u64);
+        for cons in &conses {
+            self.write(cons.car());
+        }
+        self.write(tail);
+    }
+
+    /// Write the runs of text properties of STRING, which is LENGTH
+    /// characters long.
+    fn write_text_properties(&mut self, string: LispObject, length: ptrdiff_t) {
+        let mut runs = Vec::new();
+        let mut start = 0;
+        while start < length {
+            let position = LispObject::from_natnum(start as EmacsInt);
+            let plist = LispObject::from(unsafe {
+                Ftext_properties_at(position.to_raw(), string.to_raw())
+            });
+            let end = LispObject::from(unsafe {
+                Fnext_property_change(
+                    position.to_raw(),
+                    string.to_raw(),
+                    LispObject::constant_nil().to_raw(),
+                )
+            });
+            let end = end.as_fixnum().map_or(length, |end| end as ptrdiff_t);
+            if plist.is_not_nil() {
+                runs.push((start, end, plist));
+            }
+            start = end;
+        }
+        write_varint(&mut self.out, runs.len() as u64);
+        for (start, end, plist) in runs {
+            write_varint(&mut self.out, start as u64);
+            write_varint(&mut self.out, end as u64);
+            self.write(plist);
+        }
+    }
+
+    fn write_hash_table(&mut self, object: LispObject) {
+        let table = object.as_hash_table_or_error();
+        self.out.push(TAG_HASH_TABLE);
+        self.write(LispObject::from(table.test.name));
+        self.write(table.get_weak());
+        let rehash_size = table.rehash_size;
+        self.write(if rehash_size < 0.0 {
+            LispObject::from_natnum(-rehash_size as EmacsInt)
+        } else {
+            LispObject::from_float(EmacsDouble::from(rehash_size) + 1.0)
+        });
+        self.write(LispObject::from_float(EmacsDouble::from(table.rehash_threshold)));
+        write_varint(&mut self.out, table.count as u64);
+        for (key, value) in table.iter() {
+            self.write(key);
+            self.write(value);
+        }
+    }
+}
+
+fn invalid_data() -> ! {
+    error!("Invalid serialized Lisp data")
+}
+
+struct Deserializer<'a> {
+    data: &'a [u8],
+    pos: usize,
+    /// The objects read so far, by number.
+    objects: Vec<LispObject>,
+    depth: usize,
+}
+
+impl<'a> Deserializer<'a> {
+    fn new(data: &'a [u8]) -> Deserializer<'a> {
+        if !data.starts_with(MAGIC) || data.len() == MAGIC.len() {
+            invalid_data();
+        }
+        let version = data[MAGIC.len()];
+        if version != VERSION {
+            error!("Unsupported serialized Lisp data version {}", version);
+        }
+        Deserializer {
+            data,
+            pos: MAGIC.len() + 1,
+            objects: Vec::new(),
+            depth: 0,
+        }
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        match self.data.get(self.pos) {
+            Some(&byte) => {
+                self.pos += 1;
+                byte
+            }
+            None => invalid_data(),
+        }
+    }
+
+    fn read_varint(&mut self) -> u64 {
+        read_varint(self.data, &mut self.pos).unwrap_or_else(|| invalid_data())
+    }
+
+    /// Read a count of things which each take at least SIZE bytes.
+    fn read_count(&mut self, size: usize) -> usize {
+        let count = self.read_varint();
+        if count > ((self.data.len() - self.pos) / size) as u64 {
+            invalid_data();
+        }
+        count as usize
+    }
+
+    fn read_bytes(&mut self) -> &'a [u8] {
+        let len = self.read_count(1);
+        let data = self.data;
+        let bytes = &data[self.pos..self.pos + len];
+        self.pos += len;
+        bytes
+    }
+
+    fn read_string_data(&mut self) -> LispObject {
+        let multibyte = match self.read_byte() {
+            0 => false,
+            1 => true,
+            _ => invalid_data(),
+        };
+        let bytes = self.read_bytes();
+        let chars = if multibyte {
+            multibyte_chars(bytes).unwrap_or_else(|| invalid_data())
+        } else {
+            bytes.len()
+        };
+        LispObject::from(unsafe {
+            make_specified_string(
+                bytes.as_ptr() as *const c_char,
+                chars as ptrdiff_t,
+                bytes.len() as ptrdiff_t,
+                multibyte,
+            )
+        })
+    }
+
+    fn add(&mut self, object: LispObject) -> LispObject {
+        self.objects.push(object);
+        object
+    }
+
+    fn read(&mut self) -> LispObject {
+        let tag = self.read_byte();
+        match tag {
+            TAG_INTEGER => {
+                let n = unzigzag(self.read_varint()) as EmacsInt;
+                if LispObject::fixnum_overflow(n) {
+                    invalid_data();
+                }
+                return LispObject::from_fixnum(n);
+            }
+            TAG_REFERENCE => {
+                let number = self.read_varint();
+                return match self.objects.get(number as usize) {
+                    Some(&object) => object,
+                    None => invalid_data(),
+                };
+            }
+            _ => {}
+        }
+
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            invalid_data();
+        }
+        let object = match tag {
+            TAG_FLOAT => {
+                if self.data.len() - self.pos < 8 {
+                    invalid_data();
+                }
+                let bytes = &self.data[self.pos..self.pos + 8];
+                self.pos += 8;
+                let bits = bytes
+                    .iter()
+                    .rev()
+                    .fold(0u64, |bits, &byte| bits << 8 | u64::from(byte));
+                self.add(LispObject::from_float(EmacsDouble::from_bits(bits)))
+            }
+            TAG_SYMBOL => {
+                let name = self.read_string_data();
+                let symbol = LispObarrayRef::constant_obarray().intern(name);
+                self.add(symbol)
+            }
+            TAG_UNINTERNED_SYMBOL => {
+                let name = self.read_string_data();
+                self.add(LispObject::from(unsafe { Fmake_symbol(name.to_raw()) }))
+            }
+            TAG_STRING => {
+                let string = self.read_string_data();
+                self.add(string);
+                self.read_text_properties(string);
+                string
+            }
+            TAG_LIST => self.read_list(),
+            TAG_VECTOR => {
+                let len = self.read_count(1);
+                let vector = LispObject::from(unsafe {
+                    Fmake_vector(
+                        LispObject::from_natnum(len as EmacsInt).to_raw(),
+                        LispObject::constant_nil().to_raw(),
+                    )
+                });
+                self.add(vector);
+                let items = vector.as_vector_or_error();
+                for idx in 0..len {
+                    let item = self.read();
+                    items.set(idx as ptrdiff_t, item);
+                }
+                vector
+            }
+            TAG_RECORD => {
+                let len = self.read_count(1);
+                if len == 0 {
+                    invalid_data();
+                }
+                let record = LispObject::from(unsafe {
+                    Fmake_record(
+                        LispObject::constant_nil().to_raw(),
+                        LispObject::from_natnum(len as EmacsInt - 1).to_raw(),
+                        LispObject::constant_nil().to_raw(),
+                    )
+                });
+                self.add(record);
+                for idx in 0..len {
+                    let item = self.read();
+                    let index = LispObject::from_natnum(idx as EmacsInt);
+                    unsafe { Faset(record.to_raw(), index.to_raw(), item.to_raw()) };
+                }
+                record
+            }
+            TAG_BOOL_VECTOR => {
+                let len = self.read_varint();
+                if (len + 7) / 8 > (self.data.len() - self.pos) as u64 {
+                    invalid_data();
+                }
+                let len = len as usize;
+                let object = LispObject::from(unsafe {
+                    Fmake_bool_vector(
+                        LispObject::from_natnum(len as EmacsInt).to_raw(),
+                        LispObject::constant_nil().to_raw(),
+                    )
+                });
+                let mut bool_vector = object.as_bool_vector().unwrap();
+                for idx in 0..len {
+                    let byte = self.data[self.pos + idx / 8];
+                    bool_vector.set(idx, byte & (1 << (idx % 8)) != 0);
+                }
+                self.pos += (len + 7) / 8;
+                self.add(object)
+            }
+            TAG_HASH_TABLE => self.read_hash_table(),
+            _ => invalid_data(),
+        };
+        self.depth -= 1;
+        object
+    }
+
+    fn read_list(&mut self) -> LispObject {
+        let len = self.read_count(1);
+        if len == 0 {
+            invalid_data();
+        }
+        let first = self.objects.len();
+        let mut list = LispObject::constant_nil();
+        for _ in 0..len {
+            list = LispObject::cons(LispObject::constant_nil(), list);
+        }
+        let conses: Vec<_> = list.iter_tails().collect();
+        self.objects.extend(conses.iter().map(|cons| cons.as_obj()));
+        for cons in &conses {
+            let car = self.read();
+            cons.set_car(car);
+        }
+        let tail = self.read();
+        conses[len - 1].set_cdr(tail);
+        self.objects[first]
+    }
+
+    fn read_text_properties(&mut self, string: LispObject) {
+        let length = string.as_string_or_error().len_chars() as u64;
+        let runs = self.read_count(3);
+        for _ in 0..runs {
+            let start = self.read_varint();
+            let end = self.read_varint();
+            if start >= end || end > length {
+                invalid_data();
+            }
+            let plist = self.read();
+            if plist.iter_tails_plist().count() % 2 != 0 {
+                invalid_data();
+            }
+            unsafe {
+                Fset_text_properties(
+                    LispObject::from_natnum(start as EmacsInt).to_raw(),
+                    LispObject::from_natnum(end as EmacsInt).to_raw(),
+                    plist.to_raw(),
+                    string.to_raw(),
+                )
+            };
+        }
+    }
+
+    fn read_hash_table(&mut self) -> LispObject {
+        // The table can only be made once its parameters are known, so
+        // it is numbered with a placeholder until then.
+        let number = self.objects.len();
+        self.objects.push(LispObject::constant_nil());
+        let test = self.read();
+        let weakness = self.read();
+        let rehash_size = self.read();
+        let rehash_threshold = self.read();
+        let count = self.read_count(2);
+        let mut args = [
+            intern(":test").to_raw(),
+            test.to_raw(),
+            intern(":weakness").to_raw(),
+            weakness.to_raw(),
+            intern(":size").to_raw(),
+            LispObject::from_natnum(count as EmacsInt).to_raw(),
+            intern(":rehash-size").to_raw(),
+            rehash_size.to_raw(),
+            intern(":rehash-threshold").to_raw(),
+            rehash_threshold.to_raw(),
+        ];
+        let object = LispObject::from(unsafe {
+            Fmake_hash_table(args.len() as ptrdiff_t, args.as_mut_ptr())
+        });
+        self.objects[number] = object;
+
+        // Keys are only hashed once all of them are read, since a key
+        // can refer to objects which are read later.
+        let entries: Vec<_> = (0..count).map(|_| (self.read(), self.read())).collect();
+        let table = object.as_hash_table_or_error();
+        for (key, value) in entries {
+            let mut hash = 0;
+            let idx = table.lookup(key, &mut hash);
+            if idx >= 0 {
+                table.set_hash_value(idx, value);
+            } else {
+                table.put(key, value, hash);
+            }
+        }
+        object
+    }
+}
+
+/// Return OBJECT serialized as a unibyte string.
+/// `lisp-data-deserialize' turns the string back into a copy of OBJECT,
+/// much faster than `read' would read the printed representation.
+/// OBJECT can be made of numbers, symbols, strings with their text
+/// properties, conses, vectors, records, bool-vectors and hash tables.
+/// Objects which appear more than once in OBJECT, including in cycles,
+/// are shared in the copy as well.  Uninterned symbols are kept
+/// distinct from the interned ones with the same names.
+///
+/// The string starts with a version number, and strings from other
+/// versions of the format are rejected by `lisp-data-deserialize'.
+#[lisp_fn]
+pub fn lisp_data_serialize(object: LispObject) -> LispObject {
+    let mut serializer = Serializer::new();
+    serializer.write(object);
+    make_unibyte(&serializer.out)
+}
+
+/// Return the object serialized in STRING by `lisp-data-serialize'.
+/// Signal an error if STRING isn't serialized data, or if it was
+/// serialized with a different version of the format.
+#[lisp_fn]
+pub fn lisp_data_deserialize(string: LispObject) -> LispObject {
+    let data = match string_bytes(string.as_string_or_error()) {
+        Some(data) => data,
+        None => invalid_data(),
+    };
+    // The objects read so far are only referenced from the Rust stack,
+    // where the garbage collector can't see them.
+    let count = unsafe { inhibit_garbage_collection() };
+    let mut deserializer = Deserializer::new(&data);
+    let object = deserializer.read();
+    if deserializer.pos != data.len() {
+        invalid_data();
+    }
+    LispObject::from(unsafe { unbind_to(count, object.to_raw()) })
+}
+
+#[test]
+fn test_varint() {
+    for &n in &[0, 1, 127, 128, 300, 1 << 35, u64::max_value()] {
+        let mut out = Vec::new();
+        write_varint(&mut out, n);
+        let mut pos = 0;
+        assert_eq!(read_varint(&out, &mut pos), Some(n));
+        assert_eq!(pos, out.len());
+    }
+    assert_eq!(read_varint(&[0x80, 0x80], &mut 0), None);
+    assert_eq!(read_varint(&[0xFF; 10], &mut 0), None);
+}
+
+#[test]
+fn test_zigzag() {
+    for &n in &[0, 1, -1, 63, -64, i64::max_value(), i64::min_value()] {
+        assert_eq!(unzigzag(zigzag(n)), n);
+    }
+    assert_eq!(zigzag(-1), 1);
+    assert_eq!(zigzag(1), 2);
+}
+
+#[test]
+fn test_multibyte_chars() {
+    assert_eq!(multibyte_chars(b"abc"), Some(3));
+    assert_eq!(multibyte_chars("é€".as_bytes()), Some(2));
+    // A raw 8-bit byte.
+    assert_eq!(multibyte_chars(b"\xC1\xBF"), Some(1));
+    assert_eq!(multibyte_chars(b"\xE2\x82"), None);
+    assert_eq!(multibyte_chars(b"\x80"), None);
+}
+
+include!(concat!(env!("OUT_DIR"), "/lisp_data_exports.rs"));
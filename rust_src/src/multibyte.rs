@@ -37,9 +37,9 @@ use std::ptr;
 use std::slice;
 
 use remacs_sys::{EmacsInt, Lisp_String, CHARACTERBITS, CHAR_CTL, CHAR_MODIFIER_MASK, CHAR_SHIFT};
-use remacs_sys::emacs_abort;
+use remacs_sys::{emacs_abort, make_specified_string};
 
-use lisp::ExternalPtr;
+use lisp::{ExternalPtr, LispObject};
 
 pub type LispStringRef = ExternalPtr<Lisp_String>;
 
@@ -165,7 +165,7 @@ impl LispStringRef {
     }
 }
 
-fn string_overflow() -> ! {
+pub fn string_overflow() -> ! {
     error!("Maximum string size exceeded")
 }
 
@@ -181,6 +181,13 @@ pub fn count_size_as_multibyte(ptr: *const c_uchar, len: ptrdiff_t) -> ptrdiff_t
     })
 }
 
+/// Same as the `CHAR_HEAD_P` macro: whether BYTE starts a character
+/// in a multibyte text.
+#[inline]
+pub fn char_head_p(byte: c_uchar) -> bool {
+    byte & 0xC0 != 0x80
+}
+
 /// Same as the `BYTE8_TO_CHAR` macro.
 #[inline]
 pub fn raw_byte_codepoint(byte: c_uchar) -> Codepoint {
@@ -228,6 +235,16 @@ pub fn make_char_multibyte(cp: Codepoint) -> Codepoint {
     unibyte_to_char(cp)
 }
 
+/// `MAKE_CHAR_UNIBYTE` macro
+#[inline]
+pub fn make_char_unibyte(cp: Codepoint) -> Codepoint {
+    if cp > MAX_5_BYTE_CHAR {
+        Codepoint::from(raw_byte_from_codepoint(cp))
+    } else {
+        cp
+    }
+}
+
 /// Same as the `CHAR_STRING` macro.
 #[inline]
 fn write_codepoint(to: &mut [c_uchar], cp: Codepoint) -> usize {
@@ -267,6 +284,32 @@ fn write_codepoint(to: &mut [c_uchar], cp: Codepoint) -> usize {
     }
 }
 
+/// Append the multibyte form of the characters CHARS to DEST.
+pub fn write_codepoints<I>(dest: &mut Vec<c_uchar>, chars: I)
+where
+    I: IntoIterator<Item = Codepoint>,
+{
+    let mut buf = [0; MAX_MULTIBYTE_LENGTH];
+    for cp in chars {
+        let len = write_codepoint(&mut buf, cp);
+        dest.extend_from_slice(&buf[..len]);
+    }
+}
+
+/// Return a new multibyte string containing the characters CHARS.
+pub fn make_string_from_codepoints(chars: &[Codepoint]) -> LispObject {
+    let mut bytes = Vec::with_capacity(chars.len());
+    write_codepoints(&mut bytes, chars.iter().cloned());
+    LispObject::from(unsafe {
+        make_specified_string(
+            bytes.as_ptr() as *const c_char,
+            chars.len() as ptrdiff_t,
+            bytes.len() as ptrdiff_t,
+            true,
+        )
+    })
+}
+
 /// If character code C has modifier masks, reflect them to the
 /// character code if possible.  Return the resulting code.
 #[no_mangle]
@@ -361,7 +404,7 @@ pub fn str_to_multibyte(ptr: *mut c_uchar, len: ptrdiff_t, bytes: ptrdiff_t) ->
 }
 
 /// Same as `MULTIBYTE_LENGTH` macro in C.
-fn multibyte_length(slice: &[c_uchar], allow_encoded_raw: bool) -> Option<usize> {
+pub fn multibyte_length(slice: &[c_uchar], allow_encoded_raw: bool) -> Option<usize> {
     let len = slice.len();
     if len < 1 {
         None
@@ -0,0 +1,320 @@
+//! Unicode normalization forms, as described in Unicode Standard
+//! Annex #15.
+//!
+//! Characters outside the Unicode range, in particular raw 8-bit
+//! bytes, have no decomposition and a combining class of 0, so they
+//! pass through normalization unchanged.
+
+use remacs_macros::lisp_fn;
+use remacs_sys::{make_buffer_string, replace_range, Lisp_Object};
+
+use buffers::validate_region;
+use lisp::LispObject;
+use lisp::defsubr;
+use multibyte::{make_string_from_codepoints, Codepoint};
+use unidata::{canonical_combining_class, composition, decomposition};
+
+const HANGUL_S_BASE: Codepoint = 0xAC00;
+const HANGUL_L_BASE: Codepoint = 0x1100;
+const HANGUL_V_BASE: Codepoint = 0x1161;
+const HANGUL_T_BASE: Codepoint = 0x11A7;
+const HANGUL_L_COUNT: Codepoint = 19;
+const HANGUL_V_COUNT: Codepoint = 21;
+const HANGUL_T_COUNT: Codepoint = 28;
+const HANGUL_N_COUNT: Codepoint = HANGUL_V_COUNT * HANGUL_T_COUNT;
+const HANGUL_S_COUNT: Codepoint = HANGUL_L_COUNT * HANGUL_N_COUNT;
+
+/// Characters below this one are unchanged by all normalization forms,
+/// and never combine with a following character.
+const NORMALIZATION_QUICK_LIMIT: Codepoint = 0xA0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NormalizationForm {
+    NFC,
+    NFD,
+    NFKC,
+    NFKD,
+}
+
+impl NormalizationForm {
+    fn is_compatibility(self) -> bool {
+        self == NormalizationForm::NFKC || self == NormalizationForm::NFKD
+    }
+
+    fn is_composed(self) -> bool {
+        self == NormalizationForm::NFC || self == NormalizationForm::NFKC
+    }
+}
+
+/// Append the full decomposition of C to RESULT.
+fn decompose_char(c: Codepoint, compatibility: bool, result: &mut Vec<Codepoint>) {
+    if c >= HANGUL_S_BASE && c < HANGUL_S_BASE + HANGUL_S_COUNT {
+        let s_index = c - HANGUL_S_BASE;
+        result.push(HANGUL_L_BASE + s_index / HANGUL_N_COUNT);
+        result.push(HANGUL_V_BASE + (s_index % HANGUL_N_COUNT) / HANGUL_T_COUNT);
+        if s_index % HANGUL_T_COUNT != 0 {
+            result.push(HANGUL_T_BASE + s_index % HANGUL_T_COUNT);
+        }
+        return;
+    }
+
+    match decomposition(c) {
+        Some((mapping, is_compat)) if compatibility || !is_compat => for &d in mapping {
+            decompose_char(d, compatibility, result);
+        },
+        _ => result.push(c),
+    }
+}
+
+/// Put each run of non-starters in CHARS into canonical order.
+fn canonical_order(chars: &mut [Codepoint]) {
+    let mut start = 0;
+    while start < chars.len() {
+        if canonical_combining_class(chars[start]) == 0 {
+            start += 1;
+            continue;
+        }
+        let mut end = start + 1;
+        while end < chars.len() && canonical_combining_class(chars[end]) != 0 {
+            end += 1;
+        }
+        // `sort_by_key' is stable, which is what the canonical
+        // ordering algorithm requires.
+        chars[start..end].sort_by_key(|&c| canonical_combining_class(c));
+        start = end;
+    }
+}
+
+/// Return the character FIRST and SECOND compose to, if any.
+fn compose_pair(first: Codepoint, second: Codepoint) -> Option<Codepoint> {
+    if first >= HANGUL_L_BASE && first < HANGUL_L_BASE + HANGUL_L_COUNT && second >= HANGUL_V_BASE
+        && second < HANGUL_V_BASE + HANGUL_V_COUNT
+    {
+        let l_index = first - HANGUL_L_BASE;
+        let v_index = second - HANGUL_V_BASE;
+        return Some(HANGUL_S_BASE + (l_index * HANGUL_V_COUNT + v_index) * HANGUL_T_COUNT);
+    }
+    if first >= HANGUL_S_BASE && first < HANGUL_S_BASE + HANGUL_S_COUNT
+        && (first - HANGUL_S_BASE) % HANGUL_T_COUNT == 0 && second > HANGUL_T_BASE
+        && second < HANGUL_T_BASE + HANGUL_T_COUNT
+    {
+        return Some(first + (second - HANGUL_T_BASE));
+    }
+    composition(first, second)
+}
+
+/// Apply the canonical composition algorithm to the decomposed,
+/// canonically ordered CHARS.
+fn compose(chars: &[Codepoint]) -> Vec<Codepoint> {
+    let mut result: Vec<Codepoint> = Vec::with_capacity(chars.len());
+    let mut starter: Option<usize> = None;
+    // The combining class of the last character after the starter
+    // that did not combine with it, if any.
+    let mut last_class: Option<u8> = None;
+
+    for &c in chars {
+        let class = canonical_combining_class(c);
+        if let Some(idx) = starter {
+            let blocked = last_class.map_or(false, |last| last == 0 || last >= class);
+            if !blocked {
+                if let Some(composite) = compose_pair(result[idx], c) {
+                    result[idx] = composite;
+                    continue;
+                }
+            }
+        }
+        if class == 0 {
+            starter = Some(result.len());
+            last_class = None;
+        } else {
+            last_class = Some(class);
+        }
+        result.push(c);
+    }
+
+    result
+}
+
+/// Return CHARS normalized to FORM.
+pub fn normalize(chars: &[Codepoint], form: NormalizationForm) -> Vec<Codepoint> {
+    let mut decomposed = Vec::with_capacity(chars.len());
+    for &c in chars {
+        decompose_char(c, form.is_compatibility(), &mut decomposed);
+    }
+    canonical_order(&mut decomposed);
+
+    if form.is_composed() {
+        compose(&decomposed)
+    } else {
+        decomposed
+    }
+}
+
+/// Return the normalized form of the Lisp string STRING, or STRING
+/// itself if normalization does not change it.
+fn normalize_string(string: LispObject, form: NormalizationForm) -> LispObject {
+    let s = string.as_string_or_error();
+    // In a unibyte string, all non-ASCII characters are raw bytes.
+    if !s.is_multibyte() {
+        return string;
+    }
+
+    let chars: Vec<Codepoint> = s.chars().collect();
+    if chars.iter().all(|&c| c < NORMALIZATION_QUICK_LIMIT) {
+        return string;
+    }
+    let normalized = normalize(&chars, form);
+    if normalized == chars {
+        string
+    } else {
+        make_string_from_codepoints(&normalized)
+    }
+}
+
+/// Normalize the text between START and END in the current buffer.
+fn normalize_region(start: LispObject, end: LispObject, form: NormalizationForm) -> LispObject {
+    let mut beg_raw: Lisp_Object = start.to_raw();
+    let mut end_raw: Lisp_Object = end.to_raw();
+    validate_region(&mut beg_raw, &mut end_raw);
+    let beg = LispObject::from(beg_raw).as_fixnum_or_error() as isize;
+    let fin = LispObject::from(end_raw).as_fixnum_or_error() as isize;
+
+    let text = LispObject::from(unsafe { make_buffer_string(beg, fin, false) });
+    let normalized = normalize_string(text, form);
+    if !normalized.eq(text) {
+        unsafe { replace_range(beg, fin, normalized.to_raw(), true, false, true, false) };
+    }
+
+    LispObject::constant_nil()
+}
+
+/// Return the Unicode NFC (canonical composition) of STRING.
+/// If STRING is already in NFC, it is returned unchanged.  Raw 8-bit
+/// bytes are left untouched.
+#[lisp_fn]
+fn string_to_nfc(string: LispObject) -> LispObject {
+    normalize_string(string, NormalizationForm::NFC)
+}
+
+/// Return the Unicode NFD (canonical decomposition) of STRING.
+/// If STRING is already in NFD, it is returned unchanged.  Raw 8-bit
+/// bytes are left untouched.
+#[lisp_fn]
+fn string_to_nfd(string: LispObject) -> LispObject {
+    normalize_string(string, NormalizationForm::NFD)
+}
+
+/// Return the Unicode NFKC (compatibility composition) of STRING.
+/// If STRING is already in NFKC, it is returned unchanged.  Raw 8-bit
+/// bytes are left untouched.
+#[lisp_fn]
+fn string_to_nfkc(string: LispObject) -> LispObject {
+    normalize_string(string, NormalizationForm::NFKC)
+}
+
+/// Return the Unicode NFKD (compatibility decomposition) of STRING.
+/// If STRING is already in NFKD, it is returned unchanged.  Raw 8-bit
+/// bytes are left untouched.
+#[lisp_fn]
+fn string_to_nfkd(string: LispObject) -> LispObject {
+    normalize_string(string, NormalizationForm::NFKD)
+}
+
+/// Normalize the region between START and END to Unicode NFC.
+#[lisp_fn(intspec = "r")]
+fn region_to_nfc(start: LispObject, end: LispObject) -> LispObject {
+    normalize_region(start, end, NormalizationForm::NFC)
+}
+
+/// Normalize the region between START and END to Unicode NFD.
+#[lisp_fn(intspec = "r")]
+fn region_to_nfd(start: LispObject, end: LispObject) -> LispObject {
+    normalize_region(start, end, NormalizationForm::NFD)
+}
+
+/// Normalize the region between START and END to Unicode NFKC.
+#[lisp_fn(intspec = "r")]
+fn region_to_nfkc(start: LispObject, end: LispObject) -> LispObject {
+    normalize_region(start, end, NormalizationForm::NFKC)
+}
+
+/// Normalize the region between START and END to Unicode NFKD.
+#[lisp_fn(intspec = "r")]
+fn region_to_nfkd(start: LispObject, end: LispObject) -> LispObject {
+    normalize_region(start, end, NormalizationForm::NFKD)
+}
+
+include!(concat!(env!("OUT_DIR"), "/normalize_exports.rs"));
+
+#[cfg(test)]
+fn parse_test_field(field: &str) -> Vec<Codepoint> {
+    field
+        .split_whitespace()
+        .map(|c| Codepoint::from_str_radix(c, 16).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_normalization_conformance() {
+    use std::collections::HashSet;
+    use std::fs::File;
+    use std::io::{BufRead, BufReader};
+
+    use self::NormalizationForm::*;
+
+    let path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../admin/unidata/NormalizationTest.txt"
+    );
+    let mut part1 = HashSet::new();
+    let mut in_part1 = false;
+
+    for line in BufReader::new(File::open(path).unwrap()).lines() {
+        let line = line.unwrap();
+        if line.starts_with('@') {
+            in_part1 = line.starts_with("@Part1");
+            continue;
+        }
+        let data = line.split('#').next().unwrap().trim();
+        if data.is_empty() {
+            continue;
+        }
+
+        let c: Vec<Vec<Codepoint>> = data.split(';').take(5).map(parse_test_field).collect();
+        if in_part1 {
+            part1.insert(c[0][0]);
+        }
+
+        for source in &c[0..3] {
+            assert_eq!(normalize(source, NFC), c[1], "NFC: {}", line);
+            assert_eq!(normalize(source, NFD), c[2], "NFD: {}", line);
+        }
+        for source in &c[3..5] {
+            assert_eq!(normalize(source, NFC), c[3], "NFC: {}", line);
+            assert_eq!(normalize(source, NFD), c[4], "NFD: {}", line);
+        }
+        for source in &c {
+            assert_eq!(normalize(source, NFKC), c[3], "NFKC: {}", line);
+            assert_eq!(normalize(source, NFKD), c[4], "NFKD: {}", line);
+        }
+    }
+
+    // Every character not listed in part 1 is invariant under all
+    // normalization forms.
+    for c in (0..0x11_0000).filter(|c| !part1.contains(c)) {
+        for &form in &[NFC, NFD, NFKC, NFKD] {
+            assert_eq!(normalize(&[c], form), vec![c], "{:?}: {:#x}", form, c);
+        }
+    }
+}
+
+#[test]
+fn test_normalization_raw_bytes() {
+    // A raw byte blocks composition like any other starter.
+    let chars = [0x41, 0x3F_FFC3, 0x30A];
+    assert_eq!(normalize(&chars, NormalizationForm::NFC), chars.to_vec());
+    assert_eq!(
+        normalize(&[0x41, 0x30A, 0x3F_FF80], NormalizationForm::NFC),
+        vec![0xC5, 0x3F_FF80]
+    );
+}
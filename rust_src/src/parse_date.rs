@@ -0,0 +1,1074 @@
+//! Parsing dates and times: ISO 8601 strings, including the RFC 3339
+//! profile of it, RFC 2822 strings, and strings in an explicit
+//! `strptime'-style format.
+
+use remacs_macros::lisp_fn;
+use remacs_sys::{EmacsInt, MOST_NEGATIVE_FIXNUM, MOST_POSITIVE_FIXNUM};
+
+use lisp::LispObject;
+use lisp::defsubr;
+use timefns::{civil_from_days, days_from_civil, days_in_month, div_floor, is_leap_year,
+              iso_weeks_in_year, make_lisp_ticks, mod_floor, DAY};
+
+/// The most digits of a fraction of a second that are kept, so that
+/// (TICKS . HZ) for the seconds of a minute has fixnums.
+const MAX_FRACTION_DIGITS: usize = 16;
+
+/// The most digits of a fraction of an hour or a minute that are kept,
+/// so that it can be converted to seconds without overflow.
+const MAX_UNIT_FRACTION_DIGITS: usize = 14;
+
+/// A date and time parsed from a string, with the fields that the
+/// string doesn't give left out.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ParsedDate {
+    pub second: Option<i64>,
+    /// The fraction of the second, as (TICKS, HZ).
+    pub fraction: Option<(i64, i64)>,
+    pub minute: Option<i64>,
+    pub hour: Option<i64>,
+    pub day: Option<i64>,
+    /// From 1 to 12.
+    pub month: Option<i64>,
+    pub year: Option<i64>,
+    /// From 0 for Sunday to 6.
+    pub weekday: Option<i64>,
+    pub dst: bool,
+    /// The UTC offset in seconds.
+    pub utoff: Option<i64>,
+}
+
+impl ParsedDate {
+    /// Return whether the fields are in range.
+    fn is_valid(&self) -> bool {
+        let in_range = |field: Option<i64>, min: i64, max: i64| {
+            field.map_or(true, |value| min <= value && value <= max)
+        };
+        let max_day = match self.month {
+            // February 29 is valid in an unknown year.
+            Some(month) => days_in_month(self.year.unwrap_or(2000), month),
+            None => 31,
+        };
+        let after_midnight = self.minute.unwrap_or(0) != 0 || self.second.unwrap_or(0) != 0
+            || self.fraction.is_some();
+        in_range(self.month, 1, 12) && in_range(self.day, 1, max_day)
+            && in_range(self.hour, 0, 24) && !(self.hour == Some(24) && after_midnight)
+            && in_range(self.minute, 0, 59) && in_range(self.second, 0, 60)
+            && in_range(self.weekday, 0, 6)
+    }
+
+    /// Set the fields which are less significant than the ones given
+    /// to their first value, so that the result can be encoded, and
+    /// compute the day of the week.
+    fn finish(mut self) -> Option<ParsedDate> {
+        if !self.is_valid() {
+            return None;
+        }
+        if self.year.is_some() {
+            self.month = Some(self.month.unwrap_or(1));
+            self.day = Some(self.day.unwrap_or(1));
+        }
+        if self.day.is_some() {
+            self.hour = Some(self.hour.unwrap_or(0));
+        }
+        if self.hour.is_some() {
+            self.minute = Some(self.minute.unwrap_or(0));
+        }
+        if self.minute.is_some() {
+            self.second = Some(self.second.unwrap_or(0));
+        }
+        if let (Some(year), Some(month), Some(day)) = (self.year, self.month, self.day) {
+            self.weekday = Some(mod_floor(days_from_civil(year, month, day) + 4, 7));
+        }
+        Some(self)
+    }
+
+    /// Set the date to the one DAYS days after 1970-01-01.
+    fn set_days(&mut self, days: i64) {
+        let (year, month, day) = civil_from_days(days);
+        self.year = Some(year);
+        self.month = Some(month);
+        self.day = Some(day);
+    }
+
+    /// Add the fraction of a unit of UNIT seconds, TICKS / HZ, to the
+    /// fields less significant than that unit.
+    fn add_unit_fraction(&mut self, ticks: i64, hz: i64, unit: i64) {
+        let ticks = ticks * unit;
+        let seconds = ticks / hz;
+        if unit == 3600 {
+            self.minute = Some(seconds / 60);
+        }
+        self.second = Some(self.second.unwrap_or(0) + seconds % 60);
+        self.fraction = if ticks % hz == 0 {
+            None
+        } else {
+            Some((ticks % hz, hz))
+        };
+    }
+}
+
+fn is_digit(byte: u8) -> bool {
+    b'0' <= byte && byte <= b'9'
+}
+
+fn is_alpha(byte: u8) -> bool {
+    (b'a' <= byte && byte <= b'z') || (b'A' <= byte && byte <= b'Z')
+}
+
+fn is_space(byte: u8) -> bool {
+    byte == b' ' || byte == b'\t' || byte == b'\n' || byte == b'\r' || byte == b'\x0c'
+}
+
+struct Scanner<'a> {
+    text: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(text: &'a [u8]) -> Scanner<'a> {
+        Scanner { text, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.get(self.pos).cloned()
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos == self.text.len()
+    }
+
+    fn next_byte(&mut self) -> Option<u8> {
+        let byte = self.peek()?;
+        self.pos += 1;
+        Some(byte)
+    }
+
+    /// Skip BYTE, ignoring case, if it comes next.
+    fn eat(&mut self, byte: u8) -> bool {
+        if self.peek().map(|next| next.to_ascii_uppercase()) == Some(byte) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Skip the next byte if it is one of BYTES, and return it.
+    fn eat_any(&mut self, bytes: &[u8]) -> Option<u8> {
+        let byte = self.peek()?;
+        if bytes.contains(&byte) {
+            self.pos += 1;
+            Some(byte)
+        } else {
+            None
+        }
+    }
+
+    fn skip_spaces(&mut self) -> bool {
+        let start = self.pos;
+        while self.peek().map_or(false, is_space) {
+            self.pos += 1;
+        }
+        self.pos > start
+    }
+
+    /// Return the number of digits that come next.
+    fn digit_run(&self) -> usize {
+        self.text[self.pos..]
+            .iter()
+            .take_while(|&&byte| is_digit(byte))
+            .count()
+    }
+
+    /// Read a number of at least MIN and at most MAX digits.
+    fn number(&mut self, min: usize, max: usize) -> Option<i64> {
+        let len = self.digit_run().min(max);
+        if len < min {
+            return None;
+        }
+        let mut value: i64 = 0;
+        for &byte in &self.text[self.pos..self.pos + len] {
+            value = value.checked_mul(10)?.checked_add(i64::from(byte - b'0'))?;
+        }
+        self.pos += len;
+        Some(value)
+    }
+
+    /// Read a number with an optional sign.
+    fn signed_number(&mut self, min: usize, max: usize) -> Option<i64> {
+        let start = self.pos;
+        let sign = self.eat_any(b"+-");
+        match self.number(min, max) {
+            Some(value) if sign == Some(b'-') => Some(-value),
+            Some(value) => Some(value),
+            None => {
+                self.pos = start;
+                None
+            }
+        }
+    }
+
+    /// Read the digits of a decimal fraction, keeping at most MAX of
+    /// them, and return it as (TICKS, HZ).
+    fn fraction_digits(&mut self, max: usize) -> Option<(i64, i64)> {
+        let len = self.digit_run();
+        if len == 0 {
+            return None;
+        }
+        let (mut ticks, mut hz) = (0, 1);
+        for &byte in &self.text[self.pos..self.pos + len.min(max)] {
+            ticks = ticks * 10 + i64::from(byte - b'0');
+            hz *= 10;
+        }
+        self.pos += len;
+        Some((ticks, hz))
+    }
+
+    /// Read a decimal fraction, starting with a period or a comma.
+    fn fraction(&mut self, max: usize) -> Option<(i64, i64)> {
+        let start = self.pos;
+        if self.eat_any(b".,").is_some() {
+            if let Some(fraction) = self.fraction_digits(max) {
+                return Some(fraction);
+            }
+        }
+        self.pos = start;
+        None
+    }
+
+    /// Read the letters that come next.
+    fn word(&mut self) -> &'a [u8] {
+        let start = self.pos;
+        while self.peek().map_or(false, is_alpha) {
+            self.pos += 1;
+        }
+        &self.text[start..self.pos]
+    }
+}
+
+const MONTH_NAMES: [&str; 12] = [
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+const WEEKDAY_NAMES: [&str; 7] = [
+    "sunday",
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+];
+
+/// Return the index in NAMES of the name WORD, either full or
+/// abbreviated to three letters, ignoring case.
+fn name_index(names: &[&str], word: &[u8]) -> Option<i64> {
+    let word = word.to_ascii_lowercase();
+    let matches = |name: &&str| {
+        let name = name.as_bytes();
+        word == name || (word.len() == 3 && name.starts_with(&word))
+    };
+    names.iter().position(matches).map(|index| index as i64)
+}
+
+/// Return the UTC offset of the time zone abbreviation NAME, and
+/// whether it stands for daylight saving time.
+fn zone_name_offset(name: &[u8]) -> Option<(i64, bool)> {
+    let name = name.to_ascii_uppercase();
+    let (hours, dst) = match &name[..] {
+        b"UT" | b"UTC" | b"GMT" | b"Z" => (0, false),
+        b"EST" => (-5, false),
+        b"EDT" => (-4, true),
+        b"CST" => (-6, false),
+        b"CDT" => (-5, true),
+        b"MST" => (-7, false),
+        b"MDT" => (-6, true),
+        b"PST" => (-8, false),
+        b"PDT" => (-7, true),
+        // RFC 2822 says that military zones are to be taken as
+        // unknown offsets from UTC, but J isn't a zone.
+        _ if name.len() == 1 && name[0] != b'J' && is_alpha(name[0]) => (0, false),
+        _ => return None,
+    };
+    Some((hours * 3600, dst))
+}
+
+/// Read a numeric UTC offset: a sign and two digits of hours,
+/// optionally followed by two digits of minutes, with or without a
+/// colon.
+fn numeric_zone(sc: &mut Scanner) -> Option<i64> {
+    let sign = sc.eat_any(b"+-")?;
+    let hours = sc.number(2, 2)?;
+    let minutes = if sc.eat(b':') {
+        sc.number(2, 2)?
+    } else if sc.digit_run() >= 2 {
+        sc.number(2, 2)?
+    } else {
+        0
+    };
+    if hours > 23 || minutes > 59 {
+        return None;
+    }
+    let offset = hours * 3600 + minutes * 60;
+    Some(if sign == b'-' { -offset } else { offset })
+}
+
+/// Set DATE to day DAY of week WEEK of the ISO week-based year of DATE.
+fn set_week_date(date: &mut ParsedDate, week: i64, day: i64) -> Option<()> {
+    let year = date.year?;
+    if week < 1 || week > iso_weeks_in_year(year) || day < 1 || day > 7 {
+        return None;
+    }
+    // Week 1 is the one with January 4 in it.
+    let january_4 = days_from_civil(year, 1, 4);
+    let monday = january_4 - mod_floor(january_4 + 3, 7);
+    date.set_days(monday + (week - 1) * 7 + day - 1);
+    Some(())
+}
+
+/// Set DATE to day DAY of its year.
+fn set_ordinal_date(date: &mut ParsedDate, day: i64) -> Option<()> {
+    let year = date.year?;
+    let days_in_year = if is_leap_year(year) { 366 } else { 365 };
+    if day < 1 || day > days_in_year {
+        return None;
+    }
+    date.set_days(days_from_civil(year, 1, 1) + day - 1);
+    Some(())
+}
+
+/// Parse an ISO 8601 calendar, ordinal or week date, in the basic or
+/// the extended format.
+fn iso_date(sc: &mut Scanner, date: &mut ParsedDate) -> Option<()> {
+    let expanded = sc.peek() == Some(b'+') || sc.peek() == Some(b'-');
+    date.year = Some(if expanded {
+        sc.signed_number(4, 9)?
+    } else {
+        sc.number(4, 4)?
+    });
+    if sc.eat(b'-') {
+        if sc.eat(b'W') {
+            let week = sc.number(2, 2)?;
+            let day = if sc.eat(b'-') { sc.number(1, 1)? } else { 1 };
+            set_week_date(date, week, day)
+        } else if sc.digit_run() == 3 {
+            let day = sc.number(3, 3)?;
+            set_ordinal_date(date, day)
+        } else {
+            date.month = Some(sc.number(2, 2)?);
+            if sc.eat(b'-') {
+                date.day = Some(sc.number(2, 2)?);
+            }
+            Some(())
+        }
+    } else if sc.eat(b'W') {
+        let week = sc.number(2, 2)?;
+        let day = sc.number(1, 1).unwrap_or(1);
+        set_week_date(date, week, day)
+    } else if expanded {
+        None
+    } else {
+        match sc.digit_run() {
+            0 => Some(()),
+            3 => {
+                let day = sc.number(3, 3)?;
+                set_ordinal_date(date, day)
+            }
+            4 => {
+                date.month = Some(sc.number(2, 2)?);
+                date.day = Some(sc.number(2, 2)?);
+                Some(())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Parse an ISO 8601 time of day, with an optional UTC offset.  The
+/// last of its hours, minutes and seconds can have a decimal fraction.
+fn iso_time(sc: &mut Scanner, date: &mut ParsedDate) -> Option<()> {
+    date.hour = Some(sc.number(2, 2)?);
+    let extended = sc.peek() == Some(b':');
+    let mut unit = 3600;
+    let component = |sc: &mut Scanner| {
+        if extended {
+            if sc.eat(b':') {
+                return Some(sc.number(2, 2));
+            }
+        } else if sc.digit_run() >= 2 {
+            return Some(sc.number(2, 2));
+        }
+        None
+    };
+    if let Some(minute) = component(sc) {
+        date.minute = Some(minute?);
+        unit = 60;
+        if let Some(second) = component(sc) {
+            date.second = Some(second?);
+            unit = 1;
+        }
+    }
+    if unit == 1 {
+        date.fraction = sc.fraction(MAX_FRACTION_DIGITS);
+    } else if let Some((ticks, hz)) = sc.fraction(MAX_UNIT_FRACTION_DIGITS) {
+        date.add_unit_fraction(ticks, hz, unit);
+    }
+    if sc.eat(b'Z') {
+        date.utoff = Some(0);
+    } else if sc.peek() == Some(b'+') || sc.peek() == Some(b'-') {
+        date.utoff = Some(numeric_zone(sc)?);
+    }
+    Some(())
+}
+
+/// Parse an ISO 8601 duration, such as "P1Y2M10DT2H30M" or "P3W".
+fn iso_duration(sc: &mut Scanner) -> Option<ParsedDate> {
+    if !sc.eat(b'P') {
+        return None;
+    }
+    // The years, months, days, hours, minutes and seconds.
+    let mut values = [0i64; 6];
+    let mut fraction = None;
+    let mut in_time = false;
+    let mut components = 0;
+    // The designators seen so far must come in this order.
+    let mut rank = 0;
+    while !sc.at_end() {
+        if !in_time && sc.eat(b'T') {
+            in_time = true;
+            continue;
+        }
+        let value = sc.number(1, 18)?;
+        let value_fraction = sc.fraction(MAX_FRACTION_DIGITS);
+        let designator = sc.next_byte()?.to_ascii_uppercase();
+        let (new_rank, index) = match (in_time, designator) {
+            (false, b'Y') => (1, 0),
+            (false, b'M') => (2, 1),
+            (false, b'W') | (false, b'D') => (3, 2),
+            (true, b'H') => (4, 3),
+            (true, b'M') => (5, 4),
+            (true, b'S') => (6, 5),
+            _ => return None,
+        };
+        // Weeks can be followed by days, and only seconds can have a
+        // fraction.
+        if new_rank < rank || (new_rank == rank && designator != b'D')
+            || (value_fraction.is_some() && designator != b'S')
+        {
+            return None;
+        }
+        let value = if designator == b'W' {
+            value.checked_mul(7)?
+        } else {
+            value
+        };
+        values[index] = values[index].checked_add(value)?;
+        if value_fraction.is_some() {
+            fraction = value_fraction;
+        }
+        rank = new_rank;
+        components += 1;
+    }
+    // Neither "P" nor "PT" alone is a duration.
+    if components == 0 || (in_time && rank < 4) {
+        return None;
+    }
+    Some(ParsedDate {
+        second: Some(values[5]),
+        fraction,
+        minute: Some(values[4]),
+        hour: Some(values[3]),
+        day: Some(values[2]),
+        month: Some(values[1]),
+        year: Some(values[0]),
+        ..Default::default()
+    })
+}
+
+/// Parse TEXT as an ISO 8601 date and time, or duration.  A space can
+/// separate the date and the time, as RFC 3339 allows.
+pub fn parse_iso8601(text: &[u8]) -> Option<ParsedDate> {
+    let mut sc = Scanner::new(text);
+    if sc.peek() == Some(b'P') || sc.peek() == Some(b'p') {
+        let duration = iso_duration(&mut sc)?;
+        return if sc.at_end() { Some(duration) } else { None };
+    }
+    let mut date = ParsedDate::default();
+    if sc.eat(b'T') || text.get(2) == Some(&b':') {
+        iso_time(&mut sc, &mut date)?;
+    } else {
+        iso_date(&mut sc, &mut date)?;
+        let space_before_time = sc.peek() == Some(b' ')
+            && sc.text.get(sc.pos + 1).map_or(false, |&byte| is_digit(byte));
+        if sc.eat(b'T') || (space_before_time && sc.eat(b' ')) {
+            // A time needs a complete date.
+            date.day?;
+            iso_time(&mut sc, &mut date)?;
+        }
+    }
+    if !sc.at_end() {
+        return None;
+    }
+    date.finish()
+}
+
+/// Replace the comments of TEXT, which are between parentheses and can
+/// be nested, with spaces.
+fn strip_comments(text: &[u8]) -> Option<Vec<u8>> {
+    let mut stripped = Vec::with_capacity(text.len());
+    let mut depth = 0;
+    let mut escaped = false;
+    for &byte in text {
+        if depth > 0 {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'(' => depth += 1,
+                b')' => depth -= 1,
+                _ => (),
+            }
+            if depth == 0 {
+                stripped.push(b' ');
+            }
+        } else if byte == b'(' {
+            depth = 1;
+        } else {
+            stripped.push(byte);
+        }
+    }
+    if depth == 0 {
+        Some(stripped)
+    } else {
+        None
+    }
+}
+
+/// Parse TEXT as an RFC 2822 date and time, such as "Fri, 25 Mar 2016
+/// 16:24:56 +0100".  The obsolete forms of RFC 2822 are accepted too:
+/// two-digit years, time zone names and comments.
+pub fn parse_rfc2822(text: &[u8]) -> Option<ParsedDate> {
+    let text = strip_comments(text)?;
+    let mut sc = Scanner::new(&text);
+    let mut date = ParsedDate::default();
+    sc.skip_spaces();
+    if sc.peek().map_or(false, is_alpha) {
+        name_index(&WEEKDAY_NAMES, sc.word())?;
+        sc.skip_spaces();
+        sc.eat(b',');
+        sc.skip_spaces();
+    }
+    date.day = Some(sc.number(1, 2)?);
+    sc.skip_spaces();
+    date.month = Some(name_index(&MONTH_NAMES, sc.word())? + 1);
+    sc.skip_spaces();
+    let digits = sc.digit_run();
+    let year = sc.number(2, 9)?;
+    date.year = Some(match digits {
+        2 if year < 50 => year + 2000,
+        2 | 3 => year + 1900,
+        _ => year,
+    });
+    let mut spaced = sc.skip_spaces();
+    if spaced && sc.peek().map_or(false, is_digit) {
+        date.hour = Some(sc.number(1, 2)?);
+        if !sc.eat(b':') {
+            return None;
+        }
+        date.minute = Some(sc.number(2, 2)?);
+        if sc.eat(b':') {
+            date.second = Some(sc.number(2, 2)?);
+            date.fraction = sc.fraction(MAX_FRACTION_DIGITS);
+        }
+        spaced = sc.skip_spaces();
+    }
+    if spaced && (sc.peek() == Some(b'+') || sc.peek() == Some(b'-')) {
+        let sign = sc.next_byte()?;
+        let hours = sc.number(2, 2)?;
+        let minutes = sc.number(2, 2)?;
+        if minutes > 59 {
+            return None;
+        }
+        let offset = hours * 3600 + minutes * 60;
+        date.utoff = Some(if sign == b'-' { -offset } else { offset });
+    } else if spaced && sc.peek().map_or(false, is_alpha) {
+        let (offset, dst) = zone_name_offset(sc.word())?;
+        date.utoff = Some(offset);
+        date.dst = dst;
+    }
+    sc.skip_spaces();
+    if !sc.at_end() {
+        return None;
+    }
+    date.finish()
+}
+
+/// Parse TEXT as an ISO 8601 date, time or duration, an RFC 3339 date
+/// and time, or an RFC 2822 date and time.
+pub fn parse_date(text: &[u8]) -> Option<ParsedDate> {
+    let start = text.iter().position(|&byte| !is_space(byte)).unwrap_or(text.len());
+    let end = text.iter().rposition(|&byte| !is_space(byte)).map_or(start, |end| end + 1);
+    let text = &text[start..end];
+    parse_iso8601(text).or_else(|| parse_rfc2822(text))
+}
+
+/// The ways in which parsing a string with a format can fail.
+#[derive(Debug, PartialEq)]
+pub enum FormatError {
+    /// The string doesn't match the format.
+    Mismatch,
+    /// The format has a directive which isn't known.
+    Directive(u8),
+}
+
+/// The fields that a format can give, besides those of the date.
+#[derive(Default)]
+struct FormatFields {
+    date: ParsedDate,
+    century: Option<i64>,
+    year_of_century: Option<i64>,
+    hour12: Option<i64>,
+    pm: Option<bool>,
+    yearday: Option<i64>,
+    epoch: Option<i64>,
+}
+
+fn matched<T>(value: Option<T>) -> Result<T, FormatError> {
+    value.ok_or(FormatError::Mismatch)
+}
+
+/// Match SC against FORMAT, storing the fields found in FIELDS.
+fn match_format(
+    sc: &mut Scanner,
+    format: &[u8],
+    fields: &mut FormatFields,
+) -> Result<(), FormatError> {
+    let mut i = 0;
+    while i < format.len() {
+        let byte = format[i];
+        i += 1;
+        if is_space(byte) {
+            sc.skip_spaces();
+            continue;
+        }
+        if byte != b'%' {
+            if sc.next_byte() != Some(byte) {
+                return Err(FormatError::Mismatch);
+            }
+            continue;
+        }
+        // Flags and the E and O modifiers have no effect on parsing.
+        while i < format.len() && b"_-0^#EO".contains(&format[i]) {
+            i += 1;
+        }
+        let directive = *format.get(i).ok_or(FormatError::Directive(b'%'))?;
+        i += 1;
+        let composite: Option<&[u8]> = match directive {
+            b'T' => Some(b"%H:%M:%S"),
+            b'D' => Some(b"%m/%d/%y"),
+            b'F' => Some(b"%Y-%m-%d"),
+            b'R' => Some(b"%H:%M"),
+            b'r' => Some(b"%I:%M:%S %p"),
+            b'c' => Some(b"%a %b %e %H:%M:%S %Y"),
+            _ => None,
+        };
+        if let Some(composite) = composite {
+            match_format(sc, composite, fields)?;
+            continue;
+        }
+        // Numbers can be preceded by spaces, as with %e.
+        if b"CdegHIjklmMsSyY".contains(&directive) {
+            sc.skip_spaces();
+        }
+        let date = &mut fields.date;
+        match directive {
+            b'%' => {
+                if sc.next_byte() != Some(b'%') {
+                    return Err(FormatError::Mismatch);
+                }
+            }
+            b'n' | b't' => {
+                sc.skip_spaces();
+            }
+            b'Y' => date.year = Some(matched(sc.signed_number(1, 4))?),
+            b'C' => fields.century = Some(matched(sc.signed_number(1, 2))?),
+            b'y' => fields.year_of_century = Some(matched(sc.number(1, 2))?),
+            b'm' => date.month = Some(matched(sc.number(1, 2))?),
+            b'd' | b'e' => date.day = Some(matched(sc.number(1, 2))?),
+            b'H' | b'k' => date.hour = Some(matched(sc.number(1, 2))?),
+            b'I' | b'l' => {
+                let hour = matched(sc.number(1, 2))?;
+                if hour < 1 || hour > 12 {
+                    return Err(FormatError::Mismatch);
+                }
+                fields.hour12 = Some(hour);
+            }
+            b'M' => date.minute = Some(matched(sc.number(1, 2))?),
+            b'S' => date.second = Some(matched(sc.number(1, 2))?),
+            b'N' => date.fraction = Some(matched(sc.fraction_digits(MAX_FRACTION_DIGITS))?),
+            b'j' => fields.yearday = Some(matched(sc.number(1, 3))?),
+            b's' => fields.epoch = Some(matched(sc.signed_number(1, 18))?),
+            b'b' | b'B' | b'h' => {
+                date.month = Some(matched(name_index(&MONTH_NAMES, sc.word()))? + 1)
+            }
+            b'a' | b'A' => date.weekday = Some(matched(name_index(&WEEKDAY_NAMES, sc.word()))?),
+            b'p' => {
+                let word = sc.word().to_ascii_uppercase();
+                fields.pm = Some(match &word[..] {
+                    b"AM" => false,
+                    b"PM" => true,
+                    _ => return Err(FormatError::Mismatch),
+                });
+            }
+            b'z' => {
+                date.utoff = Some(if sc.eat(b'Z') {
+                    0
+                } else {
+                    matched(numeric_zone(sc))?
+                })
+            }
+            b'Z' => {
+                // Abbreviations which aren't known are skipped.
+                let word = sc.word();
+                if word.is_empty() {
+                    return Err(FormatError::Mismatch);
+                }
+                if let Some((offset, dst)) = zone_name_offset(word) {
+                    date.utoff = Some(offset);
+                    date.dst = dst;
+                }
+            }
+            _ => return Err(FormatError::Directive(directive)),
+        }
+    }
+    Ok(())
+}
+
+/// Parse TEXT according to FORMAT, whose directives are those of
+/// `format-time-string'.  Spaces in FORMAT match any amount of
+/// whitespace, including none.
+pub fn parse_with_format(text: &[u8], format: &[u8]) -> Result<ParsedDate, FormatError> {
+    let mut sc = Scanner::new(text);
+    let mut fields = FormatFields::default();
+    match_format(&mut sc, format, &mut fields)?;
+    sc.skip_spaces();
+    if !sc.at_end() {
+        return Err(FormatError::Mismatch);
+    }
+    let mut date = fields.date;
+    if let Some(epoch) = fields.epoch {
+        let seconds = mod_floor(epoch, DAY);
+        date.set_days(div_floor(epoch, DAY));
+        date.hour = Some(seconds / 3600);
+        date.minute = Some(seconds / 60 % 60);
+        date.second = Some(seconds % 60);
+        date.utoff = Some(0);
+    }
+    match (fields.century, fields.year_of_century) {
+        (Some(century), Some(year)) => date.year = Some(century * 100 + year),
+        // POSIX says that years 69 to 99 are in the 20th century.
+        (None, Some(year)) => date.year = Some(year + if year < 69 { 2000 } else { 1900 }),
+        (Some(century), None) if date.year.is_none() => date.year = Some(century * 100),
+        _ => (),
+    }
+    if let Some(hour) = fields.hour12 {
+        date.hour = Some(match fields.pm {
+            Some(pm) => hour % 12 + if pm { 12 } else { 0 },
+            None => hour,
+        });
+    }
+    if let Some(yearday) = fields.yearday {
+        if date.month.is_none() && date.day.is_none() {
+            let mut year_start = date.clone();
+            year_start.year = Some(date.year.unwrap_or(1900));
+            matched(set_ordinal_date(&mut year_start, yearday))?;
+            date.month = year_start.month;
+            date.day = year_start.day;
+        }
+    }
+    matched(date.finish())
+}
+
+fn make_fixnum_or_nil(value: Option<i64>) -> LispObject {
+    match value {
+        Some(value)
+            if value < MOST_NEGATIVE_FIXNUM as i64 || value > MOST_POSITIVE_FIXNUM as i64 =>
+        {
+            error!("Date is out of range")
+        }
+        Some(value) => LispObject::from_fixnum(value as EmacsInt),
+        None => LispObject::constant_nil(),
+    }
+}
+
+/// Parse the date and time in STRING, and return them as a list
+/// (SEC MINUTE HOUR DAY MONTH YEAR DOW DST UTCOFF) like the one of
+/// `decode-time'.  Return nil if STRING can't be parsed.
+///
+/// If FORMAT is nil, STRING can be an ISO 8601 date and time, such as
+/// "2016-12-01T23:35:06.5-05:00", an ISO 8601 ordinal or week date
+/// such as "2016-336" or "2016-W48-4", an RFC 3339 date and time such
+/// as "2016-12-01 23:35:06Z", or an RFC 2822 date and time such as
+/// "Thu, 01 Dec 2016 23:35:06 -0500".  It can also be an ISO 8601
+/// duration such as "P1Y2M3DT4H5M6S" or "P2W", in which case the list
+/// has the years, months, days, hours, minutes and seconds of the
+/// duration, and its other members are nil.
+///
+/// Otherwise, FORMAT is a string whose %-sequences match the fields
+/// that `format-time-string' produces with them, and STRING must match
+/// all of FORMAT.  The %-sequences that are understood are %Y, %C,
+/// %y, %m, %b, %B, %h, %d, %e, %j, %a, %A, %H, %k, %I, %l, %p, %M,
+/// %S, %N, %s, %z, %Z, %T, %D, %F, %R, %r, %c, %n, %t and %%.  Years
+/// from %y alone are from 1969 to 2068.  A space matches any amount
+/// of whitespace, including none.
+///
+/// Fields that STRING doesn't give are nil, except that those less
+/// significant than the ones given are set to their first value, so
+/// that the list can be passed to `encode-time': for example, the
+/// time of day of a date alone is midnight.  SEC is (TICKS . HZ),
+/// with HZ at least 65536, if the seconds have a fraction, and UTCOFF
+/// is nil if STRING has no time zone.  DST is t if the time zone is a
+/// daylight saving time zone such as "PDT".
+#[lisp_fn(min = "1")]
+pub fn parse_date_string(string: LispObject, format: LispObject) -> LispObject {
+    let text = string.as_string_or_error();
+    let parsed = if format.is_nil() {
+        parse_date(text.as_slice())
+    } else {
+        let format = format.as_string_or_error();
+        match parse_with_format(text.as_slice(), format.as_slice()) {
+            Ok(date) => Some(date),
+            Err(FormatError::Mismatch) => None,
+            Err(FormatError::Directive(directive)) => {
+                error!("Invalid format directive: %{}", directive as char)
+            }
+        }
+    };
+    let date = match parsed {
+        Some(date) => date,
+        None => return LispObject::constant_nil(),
+    };
+    let second = match (date.second, date.fraction) {
+        (Some(second), Some((ticks, hz))) => {
+            match second.checked_mul(hz).and_then(|whole| whole.checked_add(ticks)) {
+                Some(ticks) => make_lisp_ticks(ticks, hz),
+                None => error!("Date is out of range"),
+            }
+        }
+        (second, _) => make_fixnum_or_nil(second),
+    };
+    list!(
+        second,
+        make_fixnum_or_nil(date.minute),
+        make_fixnum_or_nil(date.hour),
+        make_fixnum_or_nil(date.day),
+        make_fixnum_or_nil(date.month),
+        make_fixnum_or_nil(date.year),
+        make_fixnum_or_nil(date.weekday),
+        LispObject::from_bool(date.dst),
+        make_fixnum_or_nil(date.utoff)
+    )
+}
+
+include!(concat!(env!("OUT_DIR"), "/parse_date_exports.rs"));
+
+#[cfg(test)]
+fn fields(date: Option<ParsedDate>) -> Option<[Option<i64>; 9]> {
+    let date = date?;
+    Some([
+        date.second,
+        date.minute,
+        date.hour,
+        date.day,
+        date.month,
+        date.year,
+        date.weekday,
+        Some(date.dst as i64),
+        date.utoff,
+    ])
+}
+
+#[cfg(test)]
+fn all(values: [i64; 6], weekday: Option<i64>, utoff: Option<i64>) -> Option<[Option<i64>; 9]> {
+    Some([
+        Some(values[0]),
+        Some(values[1]),
+        Some(values[2]),
+        Some(values[3]),
+        Some(values[4]),
+        Some(values[5]),
+        weekday,
+        Some(0),
+        utoff,
+    ])
+}
+
+#[test]
+fn test_iso8601() {
+    let parse = |text: &str| fields(parse_date(text.as_bytes()));
+    assert_eq!(
+        parse("2016-12-01T23:35:06-05:00"),
+        all([6, 35, 23, 1, 12, 2016], Some(4), Some(-18_000))
+    );
+    assert_eq!(
+        parse("20161201T233506Z"),
+        all([6, 35, 23, 1, 12, 2016], Some(4), Some(0))
+    );
+    assert_eq!(parse("2016-12-01"), all([0, 0, 0, 1, 12, 2016], Some(4), None));
+    assert_eq!(parse("2016-12"), all([0, 0, 0, 1, 12, 2016], Some(4), None));
+    assert_eq!(parse("2016"), all([0, 0, 0, 1, 1, 2016], Some(5), None));
+    assert_eq!(parse("2016-336"), parse("2016-12-01"));
+    assert_eq!(parse("2016336"), parse("2016-12-01"));
+    assert_eq!(parse("2016-W48-4"), parse("2016-12-01"));
+    assert_eq!(parse("2016W484"), parse("2016-12-01"));
+    assert_eq!(parse("2009-W01-1"), parse("2008-12-29"));
+    assert_eq!(parse("2004-W53-7"), parse("2005-01-02"));
+    assert_eq!(parse("2016-12-01 23:35"), all([0, 35, 23, 1, 12, 2016], Some(4), None));
+    assert_eq!(parse("2016-12-01t23:35:06z"), parse("2016-12-01T23:35:06Z"));
+    assert_eq!(parse("+12016-12-01"), all([0, 0, 0, 1, 12, 12_016], Some(4), None));
+    assert_eq!(
+        parse("T10:20"),
+        Some([Some(0), Some(20), Some(10), None, None, None, None, Some(0), None])
+    );
+    assert_eq!(parse("10:20:30+0530").unwrap()[8], Some(19_800));
+    assert_eq!(parse("1998-09-12T12:21:54-02").unwrap()[8], Some(-7200));
+
+    let date = parse_date(b"2016-12-01T23:35:06.123456789Z").unwrap();
+    assert_eq!(date.fraction, Some((123_456_789, 1_000_000_000)));
+    let date = parse_date(b"2016-12-01T23:35:06,5").unwrap();
+    assert_eq!(date.fraction, Some((5, 10)));
+    let date = parse_date(b"2016-12-01T23:35.5").unwrap();
+    assert_eq!((date.minute, date.second, date.fraction), (Some(35), Some(30), None));
+    let date = parse_date(b"2016-12-01T23.25").unwrap();
+    assert_eq!((date.minute, date.second), (Some(15), Some(0)));
+    let date = parse_date(b"2016-12-01T23:00:00.0000000000000000000001").unwrap();
+    assert_eq!(date.fraction, Some((0, 10_000_000_000_000_000)));
+    let date = parse_date(b"2016-12-31T23:59:60Z").unwrap();
+    assert_eq!(date.second, Some(60));
+    assert_eq!(fields(parse_date(b"2016-12-01T24:00")).unwrap()[2], Some(24));
+
+    for bad in &[
+        "2016-13-01",
+        "2015-02-29",
+        "2016-12-32",
+        "2016-00",
+        "2014-W53",
+        "2016-367",
+        "2016-12-01T24:30",
+        "2016-12-01T23:60",
+        "2016-12-01T23:35:06+24:00",
+        "2016-12T10:00",
+        "2016-12-01T",
+        "2016-12-01X",
+        "201612",
+        "",
+    ] {
+        assert_eq!(parse_date(bad.as_bytes()), None, "{}", bad);
+    }
+}
+
+#[test]
+fn test_iso8601_duration() {
+    let parse = |text: &str| fields(parse_date(text.as_bytes()));
+    let duration = |values: [i64; 6]| {
+        Some([
+            Some(values[0]),
+            Some(values[1]),
+            Some(values[2]),
+            Some(values[3]),
+            Some(values[4]),
+            Some(values[5]),
+            None,
+            Some(0),
+            None,
+        ])
+    };
+    assert_eq!(parse("P1Y2M3DT4H5M6S"), duration([6, 5, 4, 3, 2, 1]));
+    assert_eq!(parse("P2W"), duration([0, 0, 0, 14, 0, 0]));
+    assert_eq!(parse("P1W2D"), duration([0, 0, 0, 9, 0, 0]));
+    assert_eq!(parse("PT36H"), duration([0, 0, 36, 0, 0, 0]));
+    assert_eq!(parse("P1M"), duration([0, 0, 0, 0, 1, 0]));
+    assert_eq!(parse("PT1M"), duration([0, 1, 0, 0, 0, 0]));
+    let date = parse_date(b"PT1.25S").unwrap();
+    assert_eq!((date.second, date.fraction), (Some(1), Some((25, 100))));
+    for bad in &["P", "PT", "P1H", "PT1D", "P1D1Y", "P1.5D", "P1DT", "P1Y1Y"] {
+        assert_eq!(parse_date(bad.as_bytes()), None, "{}", bad);
+    }
+}
+
+#[test]
+fn test_rfc2822() {
+    let parse = |text: &str| fields(parse_date(text.as_bytes()));
+    let expected = all([42, 35, 19, 22, 2, 2016], Some(1), Some(3600));
+    assert_eq!(parse("Mon, 22 Feb 2016 19:35:42 +0100"), expected);
+    assert_eq!(parse("22 Feb 2016 19:35:42 +0100"), expected);
+    assert_eq!(parse("Mon, 22 Feb 16 19:35:42 +0100"), expected);
+    assert_eq!(parse("Monday, 22 february 2016 19:35:42 +0100"), expected);
+    assert_eq!(parse("Mon,22 Feb 2016 19:35:42 +0100 (CET)"), expected);
+    assert_eq!(parse(" Mon (day) , 22 Feb 2016 19:35:42 +0100 "), expected);
+    assert_eq!(parse("22 Feb 2016 +0100"), all([0, 0, 0, 22, 2, 2016], Some(1), Some(3600)));
+    assert_eq!(parse("22 Feb 2016 19:35 GMT"), all([0, 35, 19, 22, 2, 2016], Some(1), Some(0)));
+    assert_eq!(parse("1 Jan 99 00:00:00 -0000").unwrap()[5], Some(1999));
+    assert_eq!(parse("1 Jan 105 00:00:00 -0000").unwrap()[5], Some(2005));
+    let date = parse_date(b"Monday, 22 february 2016 19:35:42 PDT").unwrap();
+    assert_eq!((date.utoff, date.dst), (Some(-25_200), true));
+    for bad in &[
+        "Mon, 22 Foo 2016 19:35:42 +0100",
+        "Mon, 30 Feb 2016 19:35:42 +0100",
+        "Mon, 22 Feb 2016 19:35:42 XYZ",
+        "Mon, 22 Feb 2016 19:35:42 +0100 (CET",
+        "Mon, 22 Feb 2016 1935",
+    ] {
+        assert_eq!(parse_date(bad.as_bytes()), None, "{}", bad);
+    }
+}
+
+#[test]
+fn test_format() {
+    let parse = |text: &str, format: &str| parse_with_format(text.as_bytes(), format.as_bytes());
+    let date = parse("2016-12-01 23:35:06.25 +0100", "%F %T.%N %z").unwrap();
+    assert_eq!(
+        fields(Some(date.clone())),
+        all([6, 35, 23, 1, 12, 2016], Some(4), Some(3600))
+    );
+    assert_eq!(date.fraction, Some((25, 100)));
+    assert_eq!(
+        fields(parse("12/01/16 11:35:06 PM", "%D %r").ok()),
+        all([6, 35, 23, 1, 12, 2016], Some(4), None)
+    );
+    assert_eq!(
+        fields(parse("Thu Dec  1 23:35:06 2016", "%c").ok()),
+        all([6, 35, 23, 1, 12, 2016], Some(4), None)
+    );
+    assert_eq!(
+        fields(parse("1480635306", "%s").ok()),
+        all([6, 35, 23, 1, 12, 2016], Some(4), Some(0))
+    );
+    assert_eq!(fields(parse("2016 336", "%Y %j").ok()), fields(parse("2016-12-01", "%F").ok()));
+    assert_eq!(parse("12 AM", "%I %p").unwrap().hour, Some(0));
+    assert_eq!(parse("12 PM", "%I %p").unwrap().hour, Some(12));
+    assert_eq!(parse("68", "%y").unwrap().year, Some(2068));
+    assert_eq!(parse("69", "%y").unwrap().year, Some(1969));
+    assert_eq!(parse("20 16", "%C %y").unwrap().year, Some(2016));
+    assert_eq!(parse("20161201", "%Y%m%d").unwrap().day, Some(1));
+    assert_eq!(parse("1 December 2016 PDT", "%d %B %Y %Z").unwrap().utoff, Some(-25_200));
+    assert_eq!(parse("1 December 2016 XYZ", "%d %B %Y %Z").unwrap().utoff, None);
+    assert_eq!(parse("100%", "%Y%%").unwrap().year, Some(100));
+    assert_eq!(parse("10:20", "%H:%M").unwrap().day, None);
+    assert_eq!(parse("10:20", "%H %M"), Err(FormatError::Mismatch));
+    assert_eq!(parse("2016-13-01", "%F"), Err(FormatError::Mismatch));
+    assert_eq!(parse("2016-12-01 extra", "%F"), Err(FormatError::Mismatch));
+    assert_eq!(parse("2016", "%Q"), Err(FormatError::Directive(b'Q')));
+    assert_eq!(parse("2016", "%Y%"), Err(FormatError::Directive(b'%')));
+}
@@ -0,0 +1,455 @@
+//! Quoted-printable de- and encoding functions, as defined in RFC 2045.
+
+use libc::ptrdiff_t;
+
+use remacs_macros::lisp_fn;
+use remacs_sys::{find_symbol_value, EmacsInt, Fcoding_system_p, Lisp_Object};
+
+use buffers::validate_region;
+use codec::{self, Decoder, Encoder, Output};
+use hex::hex_digit_value;
+use lisp::{intern, LispObject};
+use lisp::defsubr;
+use multibyte::{multibyte_char_at, raw_byte_from_codepoint, Codepoint, MAX_5_BYTE_CHAR};
+use threads::ThreadState;
+
+/// The bytes which are not encoded by default: the printable ASCII
+/// characters except `=', and TAB, LF and FF.
+const DEFAULT_CLASS: &[u8] = b"\x08-\x0A\x0C\x20-\x3C\x3E-\x7F";
+
+/// The longest encoded line allowed by RFC 2045, not counting the
+/// newline.
+const MAX_LINE_LENGTH: usize = 76;
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+/// Parse CLASS, a set of characters in the form expected by
+/// `skip-chars-forward', and return which bytes it contains.  Ranges,
+/// `^' and backslash quoting are supported, but character classes such
+/// as `[:alpha:]' are not.  Characters which aren't bytes are ignored.
+fn parse_class(class: &[Codepoint]) -> [bool; 256] {
+    let (negate, class) = match class.first() {
+        Some(&c) if c == Codepoint::from(b'^') => (true, &class[1..]),
+        _ => (false, class),
+    };
+    let mut members = [negate; 256];
+    let mut idx = 0;
+    while idx < class.len() {
+        if class[idx] == Codepoint::from(b'\\') && idx + 1 < class.len() {
+            idx += 1;
+        }
+        let from = class[idx];
+        let mut to = from;
+        idx += 1;
+        if idx + 1 < class.len() && class[idx] == Codepoint::from(b'-') {
+            idx += 1;
+            if class[idx] == Codepoint::from(b'\\') && idx + 1 < class.len() {
+                idx += 1;
+            }
+            to = class[idx];
+            idx += 1;
+        }
+        for byte in from..to.min(0xFF) + 1 {
+            members[byte as usize] = !negate;
+        }
+    }
+    members
+}
+
+fn default_class() -> [bool; 256] {
+    let class: Vec<Codepoint> = DEFAULT_CLASS.iter().map(|&b| Codepoint::from(b)).collect();
+    parse_class(&class)
+}
+
+/// Return the characters of STRING, with raw 8-bit characters as the
+/// bytes they stand for.
+fn string_codepoints(string: LispObject) -> Vec<Codepoint> {
+    let string = string.as_string_or_error();
+    let text = string.as_slice();
+    if !string.is_multibyte() {
+        return text.iter().map(|&byte| Codepoint::from(byte)).collect();
+    }
+    let mut chars = Vec::with_capacity(text.len());
+    let mut idx = 0;
+    while idx < text.len() {
+        let (cp, len) = multibyte_char_at(&text[idx..]);
+        if cp > MAX_5_BYTE_CHAR {
+            chars.push(Codepoint::from(raw_byte_from_codepoint(cp)));
+        } else {
+            chars.push(cp);
+        }
+        idx += len;
+    }
+    chars
+}
+
+/// A quoted-printable encoder.
+///
+/// The bytes outside of the literal class are written as `=XX', and so
+/// is whitespace at the end of a line.  Lines are collected whole, so
+/// that they can be folded and their beginning can be protected.
+pub struct QpEncoder {
+    literal: [bool; 256],
+    fold: bool,
+    ultra_safe: bool,
+    /// The encoded text of the current line.
+    line: Vec<u8>,
+    /// Literal blanks not yet written, which must be encoded if they end
+    /// the line.
+    blanks: Vec<u8>,
+}
+
+impl QpEncoder {
+    /// Make an encoder which leaves the bytes in LITERAL alone.  If
+    /// FOLD, lines are folded so that they aren't longer than 76
+    /// characters.  If ULTRA_SAFE, they are always folded, and `From '
+    /// or `-' at the beginning of a line is encoded as well.
+    pub fn new(literal: [bool; 256], fold: bool, ultra_safe: bool) -> QpEncoder {
+        QpEncoder {
+            literal,
+            fold,
+            ultra_safe,
+            line: Vec::new(),
+            blanks: Vec::new(),
+        }
+    }
+
+    fn push_escaped(&mut self, byte: u8) {
+        self.line.push(b'=');
+        self.line.push(HEX_DIGITS[(byte >> 4) as usize]);
+        self.line.push(HEX_DIGITS[(byte & 0xF) as usize]);
+    }
+
+    /// Write the pending blanks, encoded if ESCAPE.
+    fn flush_blanks(&mut self, escape: bool) {
+        let blanks = ::std::mem::replace(&mut self.blanks, Vec::new());
+        for &byte in &blanks {
+            if escape {
+                self.push_escaped(byte);
+            } else {
+                self.line.push(byte);
+            }
+        }
+    }
+
+    /// Append the current line to OUT, folding it if necessary.
+    fn end_line(&mut self, out: &mut Vec<u8>) {
+        self.flush_blanks(true);
+        if self.ultra_safe {
+            if self.line.starts_with(b"From ") {
+                self.line.splice(4..5, b"=20".iter().cloned());
+            } else if self.line.starts_with(b"-") {
+                self.line.splice(0..1, b"=2D".iter().cloned());
+            }
+        }
+        {
+            let mut rest = &self.line[..];
+            if self.fold || self.ultra_safe {
+                while rest.len() > MAX_LINE_LENGTH {
+                    // Don't split an `=XX' sequence.
+                    let cut = if rest[74] == b'=' {
+                        74
+                    } else if rest[73] == b'=' {
+                        73
+                    } else {
+                        75
+                    };
+                    out.extend_from_slice(&rest[..cut]);
+                    out.extend_from_slice(b"=\n");
+                    rest = &rest[cut..];
+                }
+            }
+            out.extend_from_slice(rest);
+        }
+        self.line.clear();
+    }
+}
+
+impl Encoder for QpEncoder {
+    fn push(&mut self, byte: u8, out: &mut Vec<u8>) {
+        if !self.literal[byte as usize] {
+            self.flush_blanks(false);
+            self.push_escaped(byte);
+        } else if byte == b' ' || byte == b'\t' {
+            self.blanks.push(byte);
+        } else if byte == b'\n' {
+            self.end_line(out);
+            out.push(b'\n');
+        } else {
+            self.flush_blanks(false);
+            self.line.push(byte);
+        }
+    }
+
+    fn finish(mut self, out: &mut Vec<u8>) {
+        self.end_line(out);
+    }
+}
+
+enum QpState {
+    Text,
+    /// After an `='.
+    Equals,
+    /// After an `=' and a hex digit.
+    Digit(u8),
+}
+
+/// A quoted-printable decoder.
+///
+/// Hex digits of either case are accepted in `=XX' sequences.  A
+/// malformed sequence is left alone, so decoding never fails.
+pub struct QpDecoder {
+    state: QpState,
+}
+
+impl QpDecoder {
+    pub fn new() -> QpDecoder {
+        QpDecoder {
+            state: QpState::Text,
+        }
+    }
+}
+
+impl Decoder for QpDecoder {
+    fn push(&mut self, byte: u8, out: &mut Output) -> Option<()> {
+        match ::std::mem::replace(&mut self.state, QpState::Text) {
+            QpState::Text => {
+                if byte == b'=' {
+                    self.state = QpState::Equals;
+                } else {
+                    out.push_literal(byte);
+                }
+            }
+            // A soft line break.
+            QpState::Equals if byte == b'\n' => {}
+            QpState::Equals => {
+                if hex_digit_value(byte).is_some() {
+                    self.state = QpState::Digit(byte);
+                } else {
+                    out.push_literal(b'=');
+                    return self.push(byte, out);
+                }
+            }
+            QpState::Digit(digit) => match (hex_digit_value(digit), hex_digit_value(byte)) {
+                (Some(high), Some(low)) => out.push(high << 4 | low),
+                _ => {
+                    out.push_literal(b'=');
+                    out.push_literal(digit);
+                    return self.push(byte, out);
+                }
+            },
+        }
+        Some(())
+    }
+
+    fn finish(self, out: &mut Output) -> Option<()> {
+        match self.state {
+            QpState::Text => {}
+            QpState::Equals => out.push_literal(b'='),
+            QpState::Digit(digit) => {
+                out.push_literal(b'=');
+                out.push_literal(digit);
+            }
+        }
+        Some(())
+    }
+}
+
+#[cfg(test)]
+fn encode_all(input: &[u8], fold: bool, ultra_safe: bool) -> Vec<u8> {
+    let encoder = QpEncoder::new(default_class(), fold, ultra_safe);
+    codec::encode(encoder, &[input], false).unwrap()
+}
+
+#[cfg(test)]
+fn decode_all(input: &[u8]) -> Vec<u8> {
+    codec::decode(QpDecoder::new(), &[input], false).unwrap().bytes
+}
+
+#[test]
+fn test_parse_class() {
+    let parse = |class: &[u8]| {
+        let class: Vec<Codepoint> = class.iter().map(|&b| Codepoint::from(b)).collect();
+        let members = parse_class(&class);
+        (0..256).filter(|&b| members[b]).map(|b| b as u8).collect::<Vec<u8>>()
+    };
+    assert_eq!(parse(b"a-dx"), b"abcdx");
+    assert_eq!(parse(b"\\^a-"), b"-^a");
+    assert_eq!(parse(b"\\--/"), b"-./");
+    assert_eq!(parse(b"d-a"), b"");
+    assert_eq!(parse(b"^\x01-\xFF").len(), 1);
+    assert_eq!(parse(DEFAULT_CLASS).len(), 3 + 1 + 29 + 66);
+}
+
+#[test]
+fn test_qp_encode() {
+    assert_eq!(encode_all(b"caf\xE9 = ok", false, false), b"caf=E9 =3D ok");
+    // Whitespace at the end of lines.
+    assert_eq!(encode_all(b"a \t\nb \nc x ", false, false), b"a=20=09\nb=20\nc x=20");
+    assert_eq!(encode_all(b"a \r\n", false, false), b"a =0D\n");
+    assert_eq!(encode_all(b"From me\n-- \n", false, false), b"From me\n--=20\n");
+    assert_eq!(encode_all(b"From me\n-- \n", false, true), b"From=20me\n=2D-=20\n");
+
+    let long = [b'x'; 100];
+    assert_eq!(encode_all(&long, false, false), &long[..]);
+    let mut folded = encode_all(&long, true, false);
+    assert_eq!(&folded[75..77], b"=\n");
+    folded.retain(|&b| b == b'x');
+    assert_eq!(folded, &long[..]);
+    // Folding doesn't split `=XX'.
+    for offset in 72..76 {
+        let mut line = vec![b'x'; 100];
+        line[offset] = b'=';
+        let encoded = encode_all(&line, true, false);
+        let first = encoded.split(|&b| b == b'\n').next().unwrap();
+        assert!(first.len() <= MAX_LINE_LENGTH);
+        assert_eq!(decode_all(&encoded), line);
+    }
+}
+
+#[test]
+fn test_qp_decode() {
+    assert_eq!(decode_all(b"caf=E9 =3d ok=\n!"), b"caf\xE9 = ok!");
+    // Malformed sequences are left alone.
+    assert_eq!(decode_all(b"a=\r\nb=Gc=4=41=\x80"), b"a=\r\nb=Gc=4A=\x80");
+    assert_eq!(decode_all(b"a=="), b"a==");
+    assert_eq!(decode_all(b"a=4"), b"a=4");
+}
+
+#[test]
+fn test_qp_round_trip() {
+    for &(fold, ultra_safe) in &[(false, false), (true, false), (false, true)] {
+        codec::check_round_trip(
+            || QpEncoder::new(default_class(), fold, ultra_safe),
+            QpDecoder::new,
+        );
+    }
+}
+
+/// Return whether `mm-use-ultra-safe-encoding' is bound and non-nil.
+fn ultra_safe_encoding() -> bool {
+    let symbol = intern("mm-use-ultra-safe-encoding");
+    let val = unsafe { find_symbol_value(symbol.to_raw()) };
+    val != LispObject::constant_unbound().to_raw() && LispObject::from(val).is_not_nil()
+}
+
+/// Return CODING_SYSTEM if it is a coding system, else nil.
+fn valid_coding_system(coding_system: LispObject) -> LispObject {
+    if coding_system.is_nil()
+        || LispObject::from(unsafe { Fcoding_system_p(coding_system.to_raw()) }).is_nil()
+    {
+        // E.g. `ascii' from Gnus.
+        LispObject::constant_nil()
+    } else {
+        coding_system
+    }
+}
+
+/// Encode the STRING as quoted-printable and return the result.
+#[lisp_fn]
+fn quoted_printable_encode_string(string: LispObject) -> LispObject {
+    let encoder = QpEncoder::new(default_class(), false, ultra_safe_encoding());
+    codec::encode_string(string, encoder, "quoted-printable")
+}
+
+/// Decode the quoted-printable encoded STRING and return the result.
+/// If CODING-SYSTEM is non-nil, decode the string with coding-system.
+/// Use of CODING-SYSTEM is deprecated; this function should deal with
+/// raw bytes, and coding conversion should be done separately.
+#[lisp_fn(min = "1")]
+fn quoted_printable_decode_string(string: LispObject, coding_system: LispObject) -> LispObject {
+    let decoded = codec::decode_string(string, QpDecoder::new(), "quoted-printable");
+    let coding_system = valid_coding_system(coding_system);
+    if coding_system.is_nil() {
+        decoded
+    } else {
+        call!(intern("decode-coding-string"), decoded, coding_system)
+    }
+}
+
+/// Quoted-printable encode the region between FROM and TO per RFC 2045.
+///
+/// If FOLD, fold long lines at 76 characters (as required by the RFC).
+/// If CLASS is non-nil, translate the characters not matched by that
+/// regexp class, which is in the form expected by `skip-chars-forward'.
+/// Character classes such as `[:alpha:]' are not supported in CLASS.
+/// You should probably avoid non-ASCII characters in this arg.
+///
+/// If `mm-use-ultra-safe-encoding' is set, fold lines unconditionally and
+/// encode lines starting with \"From\".
+#[lisp_fn(min = "2", intspec = "r")]
+fn quoted_printable_encode_region(
+    from: LispObject,
+    to: LispObject,
+    fold: LispObject,
+    class: LispObject,
+) -> LispObject {
+    let literal = if class.is_nil() {
+        default_class()
+    } else {
+        parse_class(&string_codepoints(class))
+    };
+    let encoder = QpEncoder::new(literal, fold.is_not_nil(), ultra_safe_encoding());
+    codec::encode_region(from, to, encoder, "quoted-printable")
+}
+
+/// Decode quoted-printable in the region between FROM and TO, per RFC 2045.
+/// Return the length of the decoded text.
+/// If CODING-SYSTEM is non-nil, decode bytes into characters with that
+/// coding-system.
+///
+/// Interactively, you can supply the CODING-SYSTEM argument
+/// with \\[universal-coding-system-argument].
+///
+/// The CODING-SYSTEM argument is a historical hangover and is deprecated.
+/// QP encodes raw bytes and should be decoded into raw bytes.  Decoding
+/// them into characters should be done separately.
+#[lisp_fn(min = "2", intspec = "(list (region-beginning) (region-end) coding-system-for-read)")]
+fn quoted_printable_decode_region(
+    from: LispObject,
+    to: LispObject,
+    coding_system: LispObject,
+) -> LispObject {
+    let mut from_raw: Lisp_Object = from.to_raw();
+    let mut to_raw: Lisp_Object = to.to_raw();
+    validate_region(&mut from_raw, &mut to_raw);
+    let from = LispObject::from(from_raw).as_fixnum_or_error() as ptrdiff_t;
+    let mut to = LispObject::from(to_raw).as_fixnum_or_error() as ptrdiff_t;
+    let buffer = ThreadState::current_buffer();
+    let coding_system = valid_coding_system(coding_system);
+
+    // Do this in case we're called from Gnus, say, in a buffer which
+    // already contains non-ASCII characters which would then get
+    // doubly-decoded below.
+    if coding_system.is_not_nil() {
+        let old_z = buffer.z();
+        call!(
+            intern("encode-coding-region"),
+            LispObject::from_natnum(from as EmacsInt),
+            LispObject::from_natnum(to as EmacsInt),
+            coding_system
+        );
+        to += buffer.z() - old_z;
+    }
+
+    let nchars = codec::decode_region(
+        LispObject::from_natnum(from as EmacsInt),
+        LispObject::from_natnum(to as EmacsInt),
+        QpDecoder::new(),
+        "quoted-printable",
+    );
+    if coding_system.is_nil() {
+        return nchars;
+    }
+    let nchars = nchars.as_fixnum_or_error() as ptrdiff_t;
+    let old_z = buffer.z();
+    call!(
+        intern("decode-coding-region"),
+        LispObject::from_natnum(from as EmacsInt),
+        LispObject::from_natnum((from + nchars) as EmacsInt),
+        coding_system
+    );
+    LispObject::from_natnum((nchars + buffer.z() - old_z) as EmacsInt)
+}
+
+include!(concat!(env!("OUT_DIR"), "/quoted_printable_exports.rs"));
@@ -0,0 +1,364 @@
+//! Pseudo-random numbers and random states.
+//!
+//! All random numbers come from xoshiro256** generators, which are
+//! seeded through SplitMix64.  Both algorithms are part of the
+//! interface: a random state made from a given seed produces the same
+//! series of numbers in every Emacs release and on every platform.
+
+use libc;
+use libc::ptrdiff_t;
+use rand::{OsRng, Rng};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use remacs_macros::lisp_fn;
+use remacs_sys::{make_unibyte_string, EmacsInt, Faref, Frecord, Qsequencep};
+
+use lisp::{intern, LispCons, LispObject};
+use lisp::defsubr;
+use multibyte::LispStringRef;
+
+lazy_static! {
+    /// The generator used when no random state is given.
+    static ref RNG: Mutex<Xoshiro256> = Mutex::new(Xoshiro256::from_seed(entropy_seed()));
+}
+
+/// The size in bytes of the state of a generator.
+const STATE_LEN: usize = 32;
+
+/// A xoshiro256** generator, as described in "Scrambled Linear
+/// Pseudorandom Number Generators" by Blackman and Vigna.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Xoshiro256 {
+    s: [u64; 4],
+}
+
+impl Xoshiro256 {
+    /// Make a generator whose state is the first four outputs of a
+    /// SplitMix64 generator started at SEED.
+    pub fn from_seed(mut seed: u64) -> Xoshiro256 {
+        let mut s = [0; 4];
+        for word in &mut s {
+            seed = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = seed;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            *word = z ^ (z >> 31);
+        }
+        Xoshiro256 { s }
+    }
+
+    /// Make a generator from the little-endian words of BYTES, which
+    /// must not all be zero.
+    fn from_bytes(bytes: &[u8]) -> Xoshiro256 {
+        let mut s = [0; 4];
+        for (idx, byte) in bytes[..STATE_LEN].iter().enumerate() {
+            s[idx / 8] |= u64::from(*byte) << (8 * (idx % 8));
+        }
+        Xoshiro256 { s }
+    }
+
+    fn to_bytes(&self, bytes: &mut [u8]) {
+        for (idx, byte) in bytes[..STATE_LEN].iter_mut().enumerate() {
+            *byte = (self.s[idx / 8] >> (8 * (idx % 8))) as u8;
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let result = self.s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = self.s[1] << 17;
+        self.s[2] ^= self.s[0];
+        self.s[3] ^= self.s[1];
+        self.s[1] ^= self.s[2];
+        self.s[0] ^= self.s[3];
+        self.s[2] ^= t;
+        self.s[3] = self.s[3].rotate_left(45);
+        result
+    }
+
+    /// Return a number in [0, LIMIT), which must be positive, with all
+    /// numbers equally likely.
+    pub fn below(&mut self, limit: u64) -> u64 {
+        // Outputs below 2^64 mod LIMIT are drawn again, so that every
+        // remainder is reached by as many outputs.
+        let threshold = limit.wrapping_neg() % limit;
+        loop {
+            let value = self.next_u64();
+            if value >= threshold {
+                return value % limit;
+            }
+        }
+    }
+
+    /// Return a number in [0, 1), made of the 53 high bits of the next
+    /// output.
+    pub fn float(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Shuffle ITEMS with the Fisher-Yates algorithm, going from the
+    /// last item to the first.
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for idx in (1..items.len()).rev() {
+            let other = self.below(idx as u64 + 1) as usize;
+            items.swap(idx, other);
+        }
+    }
+}
+
+/// Return a seed from the system's entropy pool if available, otherwise
+/// from the time and the process ID.
+fn entropy_seed() -> u64 {
+    match OsRng::new() {
+        Ok(mut rng) => rng.next_u64(),
+        Err(_) => {
+            let time = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            let pid = unsafe { libc::getpid() } as u64;
+            time.as_secs() ^ (u64::from(time.subsec_nanos()) << 32) ^ pid
+        }
+    }
+}
+
+/// Return the seed for the string whose contents are BYTES: their
+/// 64-bit FNV-1a hash.
+fn string_seed(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01B3)
+    })
+}
+
+/// Return the generator seeded by SEED, an integer or a string, or from
+/// the system's entropy pool if SEED is t.
+fn seeded_generator(seed: LispObject) -> Option<Xoshiro256> {
+    if seed.is_t() {
+        Some(Xoshiro256::from_seed(entropy_seed()))
+    } else if let Some(n) = seed.as_fixnum() {
+        Some(Xoshiro256::from_seed(n as u64))
+    } else if let Some(string) = seed.as_string() {
+        Some(Xoshiro256::from_seed(string_seed(string.as_slice())))
+    } else {
+        None
+    }
+}
+
+fn record_slot(record: LispObject, idx: EmacsInt) -> LispObject {
+    LispObject::from(unsafe { Faref(record.to_raw(), LispObject::from_fixnum(idx).to_raw()) })
+}
+
+/// Return the string holding the state of the generator if OBJECT is a
+/// random state, i.e. a record of type `random-state' whose only slot
+/// is a unibyte string of 32 bytes, not all of them zero.
+fn random_state_string(object: LispObject) -> Option<LispStringRef> {
+    if !object.is_record() || object.as_vectorlike().unwrap().pseudovector_size() != 2 {
+        return None;
+    }
+    if record_slot(object, 0).to_raw() != intern("random-state").to_raw() {
+        return None;
+    }
+    let string = record_slot(object, 1).as_string()?;
+    if string.is_multibyte()
+        || string.len_bytes() as usize != STATE_LEN
+        || string.as_slice().iter().all(|&byte| byte == 0)
+    {
+        return None;
+    }
+    Some(string)
+}
+
+fn random_state_string_or_error(object: LispObject) -> LispStringRef {
+    match random_state_string(object) {
+        Some(string) => string,
+        None => wrong_type!(intern("random-state-p"), object),
+    }
+}
+
+/// Return a new random state holding the state of GENERATOR.
+fn make_state_object(generator: &Xoshiro256) -> LispObject {
+    let mut bytes = [0; STATE_LEN];
+    generator.to_bytes(&mut bytes);
+    let mut args = [intern("random-state").to_raw(), unsafe {
+        make_unibyte_string(bytes.as_ptr() as *const libc::c_char, STATE_LEN as ptrdiff_t)
+    }];
+    LispObject::from(unsafe { Frecord(args.len() as ptrdiff_t, args.as_mut_ptr()) })
+}
+
+/// Call FUNC with the generator of STATE, a random state, and store its
+/// new state back.  If STATE is nil, use the global generator.
+fn with_generator<F, T>(state: LispObject, func: F) -> T
+where
+    F: FnOnce(&mut Xoshiro256) -> T,
+{
+    if state.is_nil() {
+        let mut generator = RNG.lock().unwrap();
+        return func(&mut generator);
+    }
+    let string = random_state_string_or_error(state);
+    let mut generator = Xoshiro256::from_bytes(string.as_slice());
+    let result = func(&mut generator);
+    generator.to_bytes(string.as_mut_slice());
+    result
+}
+
+/// Return a pseudo-random number.
+/// All integers representable in Lisp, i.e. between `most-negative-fixnum'
+/// and `most-positive-fixnum', inclusive, are equally likely.
+///
+/// With positive integer LIMIT, return random number in interval [0,LIMIT).
+/// With argument t, set the random number seed from the system's entropy
+/// pool if available, otherwise from less-random volatile data such as the time.
+/// With a string argument, set the seed based on the string's contents.
+/// Other values of LIMIT are ignored.
+///
+/// Optional argument STATE is a random state to draw the number from,
+/// and to reseed if LIMIT is t or a string, instead of the global one.
+///
+/// See Info node `(elisp)Random Numbers' for more details.
+#[lisp_fn(min = "0")]
+fn random(limit: LispObject, state: LispObject) -> LispObject {
+    with_generator(state, |generator| {
+        if limit.is_t() || limit.is_string() {
+            *generator = seeded_generator(limit).unwrap();
+        }
+        match limit.as_fixnum() {
+            Some(limit) if limit > 0 => {
+                LispObject::from_fixnum(generator.below(limit as u64) as EmacsInt)
+            }
+            _ => LispObject::from_fixnum_truncated(generator.next_u64() as EmacsInt),
+        }
+    })
+}
+
+/// Return a pseudo-random float in the interval [0,1).
+/// The result is a multiple of 2^-53, all of them equally likely.
+/// Optional argument STATE is a random state to use instead of the
+/// global one.
+#[lisp_fn(min = "0")]
+fn random_float(state: LispObject) -> LispObject {
+    LispObject::from_float(with_generator(state, |generator| generator.float()))
+}
+
+/// Return a new random state.
+/// A random state holds the state of a pseudo-random number generator
+/// of its own, and can be passed to `random', `random-float' and
+/// `shuffle'.
+///
+/// If SEED is an integer or a string, the new state is derived from it,
+/// so that the same seed always yields the same series of numbers, in
+/// every Emacs release and on every platform.  If SEED is t, the state
+/// is seeded from the system's entropy pool.  If SEED is a random state,
+/// return a copy of it.  If SEED is nil or omitted, return a copy of the
+/// global state used when no state is given.
+#[lisp_fn(min = "0")]
+fn make_random_state(seed: LispObject) -> LispObject {
+    let generator = if seed.is_nil() {
+        RNG.lock().unwrap().clone()
+    } else if let Some(string) = random_state_string(seed) {
+        Xoshiro256::from_bytes(string.as_slice())
+    } else {
+        match seeded_generator(seed) {
+            Some(generator) => generator,
+            None => wrong_type!(intern("random-state-p"), seed),
+        }
+    };
+    make_state_object(&generator)
+}
+
+/// Return t if OBJECT is a random state made by `make-random-state'.
+#[lisp_fn]
+fn random_state_p(object: LispObject) -> LispObject {
+    LispObject::from_bool(random_state_string(object).is_some())
+}
+
+/// Shuffle SEQUENCE, a list or a vector, and return it.
+/// SEQUENCE is modified in place; all its permutations are equally
+/// likely.  Optional argument STATE is a random state to use instead of
+/// the global one.
+#[lisp_fn(min = "1")]
+fn shuffle(sequence: LispObject, state: LispObject) -> LispObject {
+    if let Some(vector) = sequence.as_vector() {
+        with_generator(state, |generator| generator.shuffle(vector.as_mut_slice()));
+    } else if sequence.is_list() {
+        let cells: Vec<LispCons> = sequence.iter_tails().collect();
+        let mut items: Vec<LispObject> = cells
+            .iter()
+            .map(|cell| {
+                cell.check_impure();
+                cell.car()
+            })
+            .collect();
+        with_generator(state, |generator| generator.shuffle(&mut items));
+        for (cell, item) in cells.iter().zip(items) {
+            cell.set_car(item);
+        }
+    } else {
+        wrong_type!(Qsequencep, sequence);
+    }
+    sequence
+}
+
+include!(concat!(env!("OUT_DIR"), "/random_exports.rs"));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xoshiro256() {
+        // The reference implementation, started from an arbitrary state.
+        let mut generator = Xoshiro256 { s: [1, 2, 3, 4] };
+        let outputs: Vec<u64> = (0..4).map(|_| generator.next_u64()).collect();
+        assert_eq!(outputs, [11520, 0, 1509978240, 1215971899390074240]);
+
+        let mut generator = Xoshiro256::from_seed(0);
+        assert_eq!(generator.s[0], 0xE220_A839_7B1D_CDAF);
+        assert_eq!(generator.s[3], 0xF88B_B8A8_724C_81EC);
+        assert_eq!(generator.next_u64(), 11091344671253066420);
+
+        let mut generator = Xoshiro256::from_seed(42);
+        let outputs: Vec<u64> = (0..5).map(|_| generator.below(100)).collect();
+        assert_eq!(outputs, [42, 2, 9, 93, 76]);
+
+        let mut bytes = [0; STATE_LEN];
+        generator.to_bytes(&mut bytes);
+        assert_eq!(Xoshiro256::from_bytes(&bytes), generator);
+
+        assert_eq!(string_seed(b""), 0xCBF2_9CE4_8422_2325);
+        assert_eq!(string_seed(b"a"), 0xAF63_DC4C_8601_EC8C);
+    }
+
+    #[test]
+    fn test_below_and_float() {
+        let mut generator = Xoshiro256::from_seed(1);
+        for limit in 1..50 {
+            assert!(generator.below(limit) < limit);
+        }
+        assert!(generator.below(u64::max_value()) < u64::max_value());
+        for _ in 0..1000 {
+            let value = generator.float();
+            assert!(0.0 <= value && value < 1.0);
+        }
+    }
+
+    #[test]
+    fn test_shuffle() {
+        let mut generator = Xoshiro256::from_seed(7);
+        let mut items: Vec<u32> = (0..20).collect();
+        generator.shuffle(&mut items);
+        assert_ne!(items, (0..20).collect::<Vec<u32>>());
+        items.sort();
+        assert_eq!(items, (0..20).collect::<Vec<u32>>());
+
+        // Every permutation of three items comes up.
+        let mut seen = Vec::new();
+        for _ in 0..100 {
+            let mut items = [0, 1, 2];
+            generator.shuffle(&mut items);
+            if !seen.contains(&items) {
+                seen.push(items);
+            }
+        }
+        assert_eq!(seen.len(), 6);
+    }
+}
@@ -1,17 +1,22 @@
 //! Functions operating on strings.
 
+use std::borrow::Cow;
 use std::ptr;
 
-use libc::{self, c_void};
+use libc::{self, c_char, c_void, ptrdiff_t};
 
 use remacs_macros::lisp_fn;
-use remacs_sys::{EmacsInt, SYMBOL_NAME};
-use remacs_sys::{make_unibyte_string, make_uninit_multibyte_string,
-                 string_to_multibyte as c_string_to_multibyte};
+use remacs_sys::{EmacsInt, Qnil, Qwrong_length_argument, SYMBOL_NAME};
+use remacs_sys::{add_text_properties_from_list, make_specified_string, make_unibyte_string,
+                 make_uninit_multibyte_string, string_to_multibyte as c_string_to_multibyte,
+                 text_property_list};
 
 use lisp::LispObject;
 use lisp::defsubr;
 use multibyte;
+use multibyte::{char_head_p, multibyte_char_at, multibyte_chars_in_text, raw_byte_codepoint,
+                raw_byte_from_codepoint_safe, write_codepoints, Codepoint, LispStringRef};
+use vectors::{elt, length};
 
 pub static MIME_LINE_LENGTH: isize = 76;
 
@@ -155,4 +160,527 @@ fn multibyte_string_p(object: LispObject) -> LispObject {
     LispObject::from_bool(object.as_string().map_or(false, |s| s.is_multibyte()))
 }
 
+/// The characters at which `string-split' splits when SEPARATOR is
+/// nil, and which it trims when TRIM is t: those of
+/// `split-string-default-separators'.
+const WHITESPACE: &[Codepoint] = &[0x20, 0x0C, 0x09, 0x0A, 0x0D, 0x0B];
+
+/// A part of a string, as character and byte positions.
+#[derive(Clone, Copy)]
+struct Span {
+    start: usize,
+    end: usize,
+    start_byte: usize,
+    end_byte: usize,
+}
+
+impl Span {
+    fn whole(string: &LispStringRef) -> Span {
+        Span {
+            start: 0,
+            end: string.len_chars() as usize,
+            start_byte: 0,
+            end_byte: string.len_bytes() as usize,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// Return the character at the byte position POS of BYTES, the
+/// contents of a string which is multibyte if MULTIBYTE, and its
+/// length.  The bytes of unibyte strings from 0x80 up are raw bytes,
+/// as `string-to-multibyte' converts them.
+fn char_at(bytes: &[u8], pos: usize, multibyte: bool) -> (Codepoint, usize) {
+    if multibyte {
+        multibyte_char_at(&bytes[pos..])
+    } else {
+        (raw_byte_codepoint(bytes[pos]), 1)
+    }
+}
+
+/// Return the byte position of the character before the byte
+/// position POS of BYTES.
+fn char_start_before(bytes: &[u8], pos: usize, multibyte: bool) -> usize {
+    let mut start = pos - 1;
+    if multibyte {
+        while !char_head_p(bytes[start]) {
+            start -= 1;
+        }
+    }
+    start
+}
+
+fn count_chars(bytes: &[u8], multibyte: bool) -> usize {
+    if multibyte {
+        multibyte_chars_in_text(bytes.as_ptr(), bytes.len() as ptrdiff_t) as usize
+    } else {
+        bytes.len()
+    }
+}
+
+/// Return the byte position of the character at the position POS of
+/// BYTES.
+fn char_to_byte(bytes: &[u8], pos: usize, multibyte: bool) -> usize {
+    if !multibyte {
+        return pos;
+    }
+    (0..pos).fold(0, |byte, _| byte + multibyte_char_at(&bytes[byte..]).1)
+}
+
+/// Return the characters of STRING.
+fn codepoints(string: &LispStringRef) -> Vec<Codepoint> {
+    let bytes = string.as_slice();
+    let multibyte = string.is_multibyte();
+    let mut chars = Vec::with_capacity(bytes.len());
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let (cp, len) = char_at(bytes, pos, multibyte);
+        chars.push(cp);
+        pos += len;
+    }
+    chars
+}
+
+/// Return the contents of STRING as they would be in a string which
+/// is multibyte if MULTIBYTE, or None if STRING has characters which
+/// a unibyte string can't hold.
+fn contents_as(string: &LispStringRef, multibyte: bool) -> Option<Cow<[u8]>> {
+    let bytes = string.as_slice();
+    if string.is_multibyte() == multibyte {
+        return Some(Cow::Borrowed(bytes));
+    }
+    let mut converted = Vec::with_capacity(bytes.len());
+    if multibyte {
+        write_codepoints(&mut converted, bytes.iter().map(|&byte| raw_byte_codepoint(byte)));
+    } else {
+        let mut pos = 0;
+        while pos < bytes.len() {
+            let (cp, len) = multibyte_char_at(&bytes[pos..]);
+            match raw_byte_from_codepoint_safe(cp) {
+                -1 => return None,
+                byte => converted.push(byte as u8),
+            }
+            pos += len;
+        }
+    }
+    Some(Cow::Owned(converted))
+}
+
+/// Return the byte position of the first occurrence of NEEDLE in
+/// HAYSTACK at or after the byte position FROM.  In multibyte
+/// contents, no character starts inside another one, so matches
+/// always begin and end at character boundaries.
+fn find_bytes(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    let first = match needle.first() {
+        Some(&first) => first,
+        None => return Some(from),
+    };
+    let last_start = haystack.len().checked_sub(needle.len())?;
+    let mut pos = from;
+    while pos <= last_start {
+        pos += haystack[pos..last_start + 1]
+            .iter()
+            .position(|&byte| byte == first)?;
+        if &haystack[pos..pos + needle.len()] == needle {
+            return Some(pos);
+        }
+        pos += 1;
+    }
+    None
+}
+
+/// Return the parts of BYTES between the non-overlapping occurrences
+/// of the non-empty SEPARATOR, from left to right.
+fn split_at(bytes: &[u8], separator: &[u8], multibyte: bool) -> Vec<Span> {
+    let separator_chars = count_chars(separator, multibyte);
+    let mut spans = Vec::new();
+    let (mut start, mut start_byte) = (0, 0);
+    while let Some(pos) = find_bytes(bytes, separator, start_byte) {
+        let end = start + count_chars(&bytes[start_byte..pos], multibyte);
+        spans.push(Span {
+            start,
+            end,
+            start_byte,
+            end_byte: pos,
+        });
+        start = end + separator_chars;
+        start_byte = pos + separator.len();
+    }
+    spans.push(Span {
+        start,
+        end: start + count_chars(&bytes[start_byte..], multibyte),
+        start_byte,
+        end_byte: bytes.len(),
+    });
+    spans
+}
+
+/// Return the characters of BYTES, between an empty part at each
+/// end, like `split-string' with an empty SEPARATOR returns them.
+fn split_chars(bytes: &[u8], multibyte: bool) -> Vec<Span> {
+    let mut spans = vec![
+        Span {
+            start: 0,
+            end: 0,
+            start_byte: 0,
+            end_byte: 0,
+        },
+    ];
+    let (mut start, mut start_byte) = (0, 0);
+    while start_byte < bytes.len() {
+        let len = char_at(bytes, start_byte, multibyte).1;
+        spans.push(Span {
+            start,
+            end: start + 1,
+            start_byte,
+            end_byte: start_byte + len,
+        });
+        start += 1;
+        start_byte += len;
+    }
+    if start > 0 {
+        spans.push(Span {
+            start,
+            end: start,
+            start_byte,
+            end_byte: start_byte,
+        });
+    }
+    spans
+}
+
+/// Return the runs of characters of BYTES which aren't in CHARS.
+fn split_at_runs(bytes: &[u8], chars: &[Codepoint], multibyte: bool) -> Vec<Span> {
+    let mut spans: Vec<Span> = Vec::new();
+    let mut in_run = false;
+    let (mut pos, mut pos_byte) = (0, 0);
+    while pos_byte < bytes.len() {
+        let (cp, len) = char_at(bytes, pos_byte, multibyte);
+        if chars.contains(&cp) {
+            in_run = false;
+        } else if in_run {
+            let last = spans.len() - 1;
+            spans[last].end += 1;
+            spans[last].end_byte += len;
+        } else {
+            in_run = true;
+            spans.push(Span {
+                start: pos,
+                end: pos + 1,
+                start_byte: pos_byte,
+                end_byte: pos_byte + len,
+            });
+        }
+        pos += 1;
+        pos_byte += len;
+    }
+    spans
+}
+
+/// Return SPAN of BYTES without the characters in CHARS at its ends.
+fn trim_span(bytes: &[u8], mut span: Span, chars: &[Codepoint], multibyte: bool) -> Span {
+    while !span.is_empty() {
+        let (cp, len) = char_at(bytes, span.start_byte, multibyte);
+        if !chars.contains(&cp) {
+            break;
+        }
+        span.start += 1;
+        span.start_byte += len;
+    }
+    while !span.is_empty() {
+        let pos = char_start_before(bytes, span.end_byte, multibyte);
+        if !chars.contains(&char_at(bytes, pos, multibyte).0) {
+            break;
+        }
+        span.end -= 1;
+        span.end_byte = pos;
+    }
+    span
+}
+
+/// Accumulates parts of strings into a new string, with their text
+/// properties, like `concat' of their substrings would.
+struct StringBuilder {
+    bytes: Vec<u8>,
+    nchars: usize,
+    multibyte: bool,
+    /// The parts with text properties, and where they went.
+    properties: Vec<(LispObject, Span, usize)>,
+}
+
+impl StringBuilder {
+    /// Start a string which is multibyte if MULTIBYTE, which must be
+    /// true if any part added is multibyte.
+    fn new(multibyte: bool) -> StringBuilder {
+        StringBuilder {
+            bytes: Vec::new(),
+            nchars: 0,
+            multibyte,
+            properties: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, string: LispObject, span: Span) {
+        let s = string.as_string().unwrap();
+        let bytes = &s.as_slice()[span.start_byte..span.end_byte];
+        if self.multibyte && !s.is_multibyte() {
+            write_codepoints(&mut self.bytes, bytes.iter().map(|&byte| raw_byte_codepoint(byte)));
+        } else {
+            self.bytes.extend_from_slice(bytes);
+        }
+        if !s.intervals.is_null() && !span.is_empty() {
+            self.properties.push((string, span, self.nchars));
+        }
+        self.nchars += span.end - span.start;
+    }
+
+    fn push_string(&mut self, string: LispObject) {
+        let span = Span::whole(&string.as_string().unwrap());
+        self.push(string, span);
+    }
+
+    fn finish(self) -> LispObject {
+        let val = LispObject::from(unsafe {
+            make_specified_string(
+                self.bytes.as_ptr() as *const c_char,
+                self.nchars as ptrdiff_t,
+                self.bytes.len() as ptrdiff_t,
+                self.multibyte,
+            )
+        });
+        for &(string, span, position) in &self.properties {
+            unsafe {
+                let props = text_property_list(
+                    string.to_raw(),
+                    LispObject::from_natnum(span.start as EmacsInt).to_raw(),
+                    LispObject::from_natnum(span.end as EmacsInt).to_raw(),
+                    Qnil,
+                );
+                add_text_properties_from_list(
+                    val.to_raw(),
+                    props,
+                    LispObject::from_fixnum(position as EmacsInt - span.start as EmacsInt)
+                        .to_raw(),
+                );
+            }
+        }
+        val
+    }
+}
+
+/// Search for the string NEEDLE in the string HAYSTACK.
+/// The return value is the position of the first occurrence of
+/// NEEDLE in HAYSTACK, or nil if no match was found.
+///
+/// The optional START argument specifies the character position in
+/// HAYSTACK where the search starts; it defaults to 0.
+///
+/// Case is always significant and text properties are ignored.  Unlike
+/// `string-match', this doesn't use regular expressions and doesn't
+/// change the match data.
+#[lisp_fn(min = "2")]
+pub fn string_search(needle: LispObject, haystack: LispObject, start: LispObject) -> LispObject {
+    let needle_string = needle.as_string_or_error();
+    let haystack_string = haystack.as_string_or_error();
+    let start_char = if start.is_nil() {
+        0
+    } else {
+        start.as_fixnum_or_error()
+    };
+    if start_char < 0 || start_char > haystack_string.len_chars() as EmacsInt {
+        args_out_of_range!(start);
+    }
+
+    let multibyte = haystack_string.is_multibyte();
+    let bytes = haystack_string.as_slice();
+    let start_byte = char_to_byte(bytes, start_char as usize, multibyte);
+    let found = contents_as(&needle_string, multibyte)
+        .and_then(|needle| find_bytes(bytes, &needle, start_byte));
+    match found {
+        Some(pos) => LispObject::from_natnum(
+            start_char + count_chars(&bytes[start_byte..pos], multibyte) as EmacsInt,
+        ),
+        None => LispObject::constant_nil(),
+    }
+}
+
+/// Replace FROM-STRING with TO-STRING in IN-STRING.
+/// This is a literal replacement of all the non-overlapping
+/// occurrences of FROM-STRING, from left to right, which is case
+/// sensitive.  The result keeps the text properties of IN-STRING and
+/// of TO-STRING.  If there is no occurrence, return IN-STRING itself.
+#[lisp_fn]
+pub fn string_replace(
+    from_string: LispObject,
+    to_string: LispObject,
+    in_string: LispObject,
+) -> LispObject {
+    let from = from_string.as_string_or_error();
+    let to = to_string.as_string_or_error();
+    let in_ = in_string.as_string_or_error();
+    if from.len_chars() == 0 {
+        xsignal!(Qwrong_length_argument, LispObject::from_natnum(0));
+    }
+
+    let multibyte = in_.is_multibyte();
+    let pieces = match contents_as(&from, multibyte) {
+        Some(needle) => split_at(in_.as_slice(), &needle, multibyte),
+        None => return in_string,
+    };
+    if pieces.len() == 1 {
+        return in_string;
+    }
+
+    // As in `concat', the result is multibyte if any part of it comes
+    // from a multibyte string.
+    let mut builder = StringBuilder::new(
+        to.is_multibyte() || (multibyte && pieces.iter().any(|piece| !piece.is_empty())),
+    );
+    for (i, &piece) in pieces.iter().enumerate() {
+        if i > 0 {
+            builder.push_string(to_string);
+        }
+        if !piece.is_empty() {
+            builder.push(in_string, piece);
+        }
+    }
+    builder.finish()
+}
+
+/// Split STRING into substrings bounded by occurrences of SEPARATOR.
+/// SEPARATOR is a string which is matched literally, not a regular
+/// expression.  The occurrences are found from left to right, and
+/// don't overlap.  If SEPARATOR is the empty string, the substrings
+/// are the characters of STRING, with an empty string at each end.
+///
+/// If SEPARATOR is nil, split at runs of the whitespace characters of
+/// `split-string-default-separators' instead, and OMIT-NULLS is
+/// forced to t.
+///
+/// If OMIT-NULLS is t, empty substrings are omitted from the result.
+///
+/// If TRIM is non-nil, it is either a string of the characters to
+/// remove from both ends of each substring, or t for whitespace.  A
+/// substring which this makes empty counts as empty.
+///
+/// The substrings keep the text properties of STRING.  Modifying them
+/// doesn't affect STRING.
+#[lisp_fn(min = "2")]
+pub fn string_split(
+    string: LispObject,
+    separator: LispObject,
+    omit_nulls: LispObject,
+    trim: LispObject,
+) -> LispObject {
+    let s = string.as_string_or_error();
+    let multibyte = s.is_multibyte();
+    let bytes = s.as_slice();
+    let mut omit_nulls = omit_nulls.is_not_nil();
+    let spans = if separator.is_nil() {
+        omit_nulls = true;
+        split_at_runs(bytes, WHITESPACE, multibyte)
+    } else {
+        let separator_string = separator.as_string_or_error();
+        if separator_string.len_chars() == 0 {
+            split_chars(bytes, multibyte)
+        } else {
+            match contents_as(&separator_string, multibyte) {
+                Some(separator) => split_at(bytes, &separator, multibyte),
+                None => vec![Span::whole(&s)],
+            }
+        }
+    };
+    let trim_chars = if trim.is_nil() {
+        Vec::new()
+    } else if trim.eq(LispObject::constant_t()) {
+        WHITESPACE.to_vec()
+    } else {
+        codepoints(&trim.as_string_or_error())
+    };
+
+    let spans: Vec<Span> = spans
+        .into_iter()
+        .map(|span| trim_span(bytes, span, &trim_chars, multibyte))
+        .filter(|span| !(omit_nulls && span.is_empty()))
+        .collect();
+    spans
+        .into_iter()
+        .rev()
+        .fold(LispObject::constant_nil(), |list, span| {
+            let mut builder = StringBuilder::new(multibyte);
+            builder.push(string, span);
+            LispObject::cons(builder.finish(), list)
+        })
+}
+
+/// Join all STRINGS using SEPARATOR.
+/// STRINGS is a sequence of strings, such as a list or a vector, and
+/// SEPARATOR a string, which defaults to the empty string.  The result
+/// keeps the text properties of the strings and of SEPARATOR.
+#[lisp_fn(min = "1")]
+pub fn string_join(strings: LispObject, separator: LispObject) -> LispObject {
+    let strings: Vec<LispObject> = if strings.is_cons() || strings.is_nil() {
+        strings.iter_tails().map(|tail| tail.car()).collect()
+    } else {
+        let len = length(strings).as_natnum_or_error();
+        (0..len)
+            .map(|i| elt(strings, LispObject::from_natnum(i)))
+            .collect()
+    };
+    for &string in &strings {
+        string.as_string_or_error();
+    }
+    let separator = if separator.is_nil() {
+        None
+    } else {
+        separator.as_string_or_error();
+        Some(separator)
+    };
+
+    let is_multibyte = |string: LispObject| string.as_string().unwrap().is_multibyte();
+    let multibyte = strings.iter().any(|&string| is_multibyte(string))
+        || (strings.len() > 1 && separator.map_or(false, is_multibyte));
+    let mut builder = StringBuilder::new(multibyte);
+    for (i, &string) in strings.iter().enumerate() {
+        if i > 0 {
+            if let Some(separator) = separator {
+                builder.push_string(separator);
+            }
+        }
+        builder.push_string(string);
+    }
+    builder.finish()
+}
+
 include!(concat!(env!("OUT_DIR"), "/strings_exports.rs"));
+
+#[test]
+fn test_find_bytes() {
+    assert_eq!(find_bytes(b"foobarbar", b"bar", 0), Some(3));
+    assert_eq!(find_bytes(b"foobarbar", b"bar", 4), Some(6));
+    assert_eq!(find_bytes(b"foobarbar", b"bar", 7), None);
+    assert_eq!(find_bytes(b"foo", b"", 2), Some(2));
+    assert_eq!(find_bytes(b"fo", b"foo", 0), None);
+    assert_eq!(find_bytes("a\u{f6}b\u{f6}".as_bytes(), "\u{f6}".as_bytes(), 3), Some(4));
+}
+
+#[test]
+fn test_split_at() {
+    let bytes = "a\u{f6}\u{f6}b\u{f6}".as_bytes();
+    let spans = split_at(bytes, "\u{f6}".as_bytes(), true);
+    let parts: Vec<_> = spans
+        .iter()
+        .map(|span| (span.start, span.end, span.start_byte, span.end_byte))
+        .collect();
+    assert_eq!(parts, vec![(0, 1, 0, 1), (2, 2, 3, 3), (3, 4, 5, 6), (5, 5, 8, 8)]);
+
+    let bytes = b" \xffa b\t";
+    let spans = split_at(bytes, b"a", false);
+    let span = trim_span(bytes, spans[0], &[0x20, raw_byte_codepoint(0xff)], false);
+    assert_eq!((span.start, span.end, span.start_byte), (2, 2, 2));
+    let span = trim_span(bytes, spans[1], WHITESPACE, false);
+    assert_eq!((span.start, span.end, span.end_byte), (4, 5, 5));
+}
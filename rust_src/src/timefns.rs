@@ -0,0 +1,1321 @@
+//! Time values, calendars and time zones.
+//!
+//! Time values are kept exactly, as a number of seconds since the
+//! epoch and a fraction of a second with any positive denominator, so
+//! that adding and comparing them never rounds.  Calendar fields are
+//! computed with the proleptic Gregorian calendar, and time zones come
+//! from the `tzfile` module rather than from the C library.
+
+use std::cmp::Ordering;
+use std::ffi::{CStr, CString};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use libc::{c_char, ptrdiff_t};
+
+use remacs_macros::lisp_fn;
+use remacs_sys::{code_convert_string, emacs_getenv_TZ, globals, make_string, string_to_multibyte,
+                 synchronize_system_time_locale, EmacsInt, MOST_NEGATIVE_FIXNUM,
+                 MOST_POSITIVE_FIXNUM, Qerror, Qintegerp, Qt, Qwall};
+
+use codec::make_unibyte;
+use lisp::LispObject;
+use lisp::defsubr;
+use tzfile::{wall_zone, zone_from_tz, LocalTimeType, Zone};
+
+/// The number of seconds in a day.
+pub const DAY: i64 = 86_400;
+
+/// The number of low-order bits of the seconds in the LOW part of a
+/// (HIGH LOW USEC PSEC) list.
+const LO_TIME_BITS: u32 = 16;
+
+/// The least HZ of a (TICKS . HZ) time value.  A cons of two integers
+/// with a smaller cdr is the obsolete (HIGH . LOW).
+const MIN_HZ: i64 = 1 << LO_TIME_BITS;
+
+/// The largest number of seconds from the epoch that is handled,
+/// which is about a billion years and keeps calendar computations
+/// from overflowing.
+const MAX_SECONDS: i64 = 1 << 55;
+
+/// The largest width of a field in a format-time-string directive.
+const MAX_FIELD_WIDTH: usize = 1 << 20;
+
+const PICOSECONDS: u64 = 1_000_000_000_000;
+
+pub fn div_floor(a: i64, b: i64) -> i64 {
+    let q = a / b;
+    if (a % b != 0) && ((a < 0) != (b < 0)) {
+        q - 1
+    } else {
+        q
+    }
+}
+
+pub fn mod_floor(a: i64, b: i64) -> i64 {
+    a - div_floor(a, b) * b
+}
+
+pub fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+/// Return the number of days of MONTH, from 1 to 12, in YEAR.
+pub fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Return the number of days from 1970-01-01 to the date YEAR-MONTH-DAY
+/// of the proleptic Gregorian calendar, MONTH being from 1 to 12.
+pub fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    // Count from March 1 of year 0, so that leap days end the years.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = div_floor(year, 400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Return the date (YEAR MONTH DAY) that is DAYS days after 1970-01-01.
+pub fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = div_floor(days, 146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+/// Return the number of weeks, 52 or 53, of the ISO 8601 week-based
+/// year YEAR.
+pub fn iso_weeks_in_year(year: i64) -> i64 {
+    // The day of the week of December 31, from 0 for Sunday.
+    let p = |y: i64| mod_floor(y + div_floor(y, 4) - div_floor(y, 100) + div_floor(y, 400), 7);
+    if p(year) == 4 || p(year - 1) == 3 {
+        53
+    } else {
+        52
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+    a
+}
+
+/// Return the product of A and B as its high and low 64 bits.
+fn mul_wide(a: u64, b: u64) -> (u64, u64) {
+    let (a_hi, a_lo) = (a >> 32, a & 0xffff_ffff);
+    let (b_hi, b_lo) = (b >> 32, b & 0xffff_ffff);
+    let lo_lo = a_lo * b_lo;
+    let hi_lo = a_hi * b_lo;
+    let lo_hi = a_lo * b_hi;
+    let hi_hi = a_hi * b_hi;
+    let middle = (lo_lo >> 32) + (hi_lo & 0xffff_ffff) + (lo_hi & 0xffff_ffff);
+    let low = (lo_lo & 0xffff_ffff) | (middle << 32);
+    let high = hi_hi + (hi_lo >> 32) + (lo_hi >> 32) + (middle >> 32);
+    (high, low)
+}
+
+/// An exact time: SECS seconds and TICKS / HZ of a second after the
+/// epoch, with TICKS less than HZ.
+#[derive(Clone, Copy, Debug)]
+pub struct Time {
+    secs: i64,
+    ticks: u64,
+    hz: u64,
+}
+
+impl Time {
+    pub fn from_secs(secs: i64) -> Option<Time> {
+        if secs < -MAX_SECONDS || secs > MAX_SECONDS {
+            return None;
+        }
+        Some(Time {
+            secs,
+            ticks: 0,
+            hz: 1,
+        })
+    }
+
+    /// Return the time TICKS / HZ seconds after the epoch.
+    pub fn from_ticks(ticks: i64, hz: i64) -> Option<Time> {
+        debug_assert!(hz > 0);
+        let mut time = Time::from_secs(div_floor(ticks, hz))?;
+        time.ticks = mod_floor(ticks, hz) as u64;
+        time.hz = hz as u64;
+        Some(time)
+    }
+
+    /// Return the time that is the float SECONDS, or None if it isn't
+    /// finite or is out of range.  The result is exact unless SECONDS
+    /// has bits below 2**-63, which are rounded down.
+    pub fn from_float(seconds: f64) -> Option<Time> {
+        if !seconds.is_finite() {
+            return None;
+        }
+        let bits = seconds.to_bits();
+        let negative = bits >> 63 != 0;
+        let exponent = ((bits >> 52) & 0x7ff) as i32;
+        let mut mantissa = bits & ((1 << 52) - 1);
+        if exponent != 0 {
+            mantissa |= 1 << 52;
+        }
+        // SECONDS is MANTISSA / 2**SHIFT.
+        let mut shift = 1075 - exponent.max(1);
+        if shift <= 0 {
+            if shift < -10 {
+                return None;
+            }
+            let secs = (mantissa << -shift) as i64;
+            return Time::from_secs(if negative { -secs } else { secs });
+        }
+        while shift > 0 && mantissa & 1 == 0 {
+            mantissa >>= 1;
+            shift -= 1;
+        }
+        if shift > 63 {
+            let lost = shift - 63;
+            let kept = if lost >= 64 { 0 } else { mantissa >> lost };
+            mantissa = kept + (negative as u64);
+            shift = 63;
+        }
+        let hz = 1 << shift;
+        let (whole, ticks) = ((mantissa >> shift) as i64, mantissa & (hz - 1));
+        let mut time = if !negative {
+            Time::from_secs(whole)?
+        } else if ticks == 0 {
+            return Time::from_secs(-whole);
+        } else {
+            Time::from_secs(-whole - 1)?
+        };
+        time.ticks = if negative { hz - ticks } else { ticks };
+        time.hz = hz;
+        Some(time)
+    }
+
+    pub fn now() -> Time {
+        let (secs, nanos) = match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(elapsed) => (elapsed.as_secs() as i64, i64::from(elapsed.subsec_nanos())),
+            Err(err) => {
+                let before = err.duration();
+                (-(before.as_secs() as i64), -i64::from(before.subsec_nanos()))
+            }
+        };
+        let nanos = Time::from_ticks(nanos, 1_000_000_000).unwrap();
+        Time::from_secs(secs)
+            .and_then(|secs| secs.add(nanos))
+            .unwrap_or_else(|| time_overflow())
+    }
+
+    pub fn secs(self) -> i64 {
+        self.secs
+    }
+
+    /// Return this time with a denominator of HZ, a multiple of its own.
+    fn rescale(self, hz: u64) -> Time {
+        Time {
+            secs: self.secs,
+            ticks: self.ticks * (hz / self.hz),
+            hz,
+        }
+    }
+
+    fn common_hz(self, other: Time) -> Option<(Time, Time)> {
+        let hz = (self.hz / gcd(self.hz, other.hz)).checked_mul(other.hz)?;
+        Some((self.rescale(hz), other.rescale(hz)))
+    }
+
+    pub fn add(self, other: Time) -> Option<Time> {
+        let (a, b) = self.common_hz(other)?;
+        let (mut ticks, carried) = a.ticks.overflowing_add(b.ticks);
+        let mut secs = a.secs.checked_add(b.secs)?;
+        if carried || ticks >= a.hz {
+            ticks = ticks.wrapping_sub(a.hz);
+            secs += 1;
+        }
+        let mut time = Time::from_secs(secs)?;
+        time.ticks = ticks;
+        time.hz = a.hz;
+        Some(time)
+    }
+
+    pub fn sub(self, other: Time) -> Option<Time> {
+        let (a, b) = self.common_hz(other)?;
+        let mut secs = a.secs.checked_sub(b.secs)?;
+        let ticks = if a.ticks >= b.ticks {
+            a.ticks - b.ticks
+        } else {
+            secs -= 1;
+            a.hz - (b.ticks - a.ticks)
+        };
+        let mut time = Time::from_secs(secs)?;
+        time.ticks = ticks;
+        time.hz = a.hz;
+        Some(time)
+    }
+
+    pub fn to_float(self) -> f64 {
+        self.secs as f64 + self.ticks as f64 / self.hz as f64
+    }
+
+    /// Return the number of ticks of a clock with frequency HZ, which
+    /// must be a multiple of the denominator of this time.
+    fn to_ticks(self, hz: u64) -> Option<i64> {
+        if hz > i64::max_value() as u64 {
+            return None;
+        }
+        let ticks = self.rescale(hz).ticks;
+        self.secs
+            .checked_mul(hz as i64)?
+            .checked_add(ticks as i64)
+    }
+
+    /// Return the first N decimal digits of the fraction of a second.
+    pub fn fraction_digits(self, n: usize) -> String {
+        let (mut ticks, mut hz) = (self.ticks, self.hz);
+        // Multiplying by 10 must not overflow; the bits dropped are far
+        // below the digits produced.
+        while hz > 1 << 59 {
+            ticks >>= 1;
+            hz >>= 1;
+        }
+        let mut digits = String::with_capacity(n);
+        for _ in 0..n {
+            ticks *= 10;
+            digits.push((b'0' + (ticks / hz) as u8) as char);
+            ticks %= hz;
+        }
+        digits
+    }
+}
+
+impl PartialEq for Time {
+    fn eq(&self, other: &Time) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Time {}
+
+impl PartialOrd for Time {
+    fn partial_cmp(&self, other: &Time) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Time {
+    fn cmp(&self, other: &Time) -> Ordering {
+        self.secs.cmp(&other.secs).then_with(|| {
+            mul_wide(self.ticks, other.hz).cmp(&mul_wide(other.ticks, self.hz))
+        })
+    }
+}
+
+/// A time broken down into calendar fields in some time zone.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedTime {
+    pub year: i64,
+    /// From 1 to 12.
+    pub month: i64,
+    pub day: i64,
+    pub hour: i64,
+    pub minute: i64,
+    pub second: i64,
+    /// From 0 for Sunday to 6.
+    pub weekday: i64,
+    /// From 0 to 365.
+    pub yearday: i64,
+    pub zone: LocalTimeType,
+    pub time: Time,
+}
+
+impl DecodedTime {
+    /// Break TIME down in ZONE.
+    pub fn new(time: Time, zone: &Zone) -> DecodedTime {
+        let zone = zone.local_type(time.secs).clone();
+        let local = time.secs + zone.utoff;
+        let days = div_floor(local, DAY);
+        let seconds = mod_floor(local, DAY);
+        let (year, month, day) = civil_from_days(days);
+        DecodedTime {
+            year,
+            month,
+            day,
+            hour: seconds / 3600,
+            minute: seconds / 60 % 60,
+            second: seconds % 60,
+            // 1970-01-01 was a Thursday.
+            weekday: mod_floor(days + 4, 7),
+            yearday: days - days_from_civil(year, 1, 1),
+            zone,
+            time,
+        }
+    }
+
+    /// Return the ISO 8601 week-based year and week number.
+    fn iso_week(&self) -> (i64, i64) {
+        let monday_based = (self.weekday + 6) % 7;
+        let week = (self.yearday - monday_based + 10) / 7;
+        if week < 1 {
+            (self.year - 1, iso_weeks_in_year(self.year - 1))
+        } else if week > iso_weeks_in_year(self.year) {
+            (self.year + 1, 1)
+        } else {
+            (self.year, week)
+        }
+    }
+}
+
+const WEEKDAY_NAMES: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+const MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// The flags, width and modifiers of a format-time-string directive.
+struct Spec {
+    /// One of b'_', b'-', b'0' and b'+', or 0 for the default.
+    pad: u8,
+    width: Option<usize>,
+    upcase: bool,
+    swapcase: bool,
+}
+
+fn push_padded(out: &mut Vec<u8>, spec: &Spec, text: &[u8], default_pad: u8) {
+    let pad = match spec.pad {
+        b'-' => 0,
+        b'_' => b' ',
+        b'0' | b'+' => b'0',
+        _ => default_pad,
+    };
+    let width = spec.width.unwrap_or(0);
+    if pad != 0 && text.len() < width {
+        let fill = width - text.len();
+        if pad == b'0' && (text.first() == Some(&b'-') || text.first() == Some(&b'+')) {
+            out.push(text[0]);
+            out.extend(::std::iter::repeat(b'0').take(fill));
+            out.extend_from_slice(&text[1..]);
+            return;
+        }
+        out.extend(::std::iter::repeat(pad).take(fill));
+    }
+    out.extend_from_slice(text);
+}
+
+/// Output the number N, padded to DIGITS digits with DEFAULT_PAD unless
+/// SPEC says otherwise.
+fn push_number(out: &mut Vec<u8>, spec: &Spec, n: i64, digits: usize, default_pad: u8) {
+    let text = n.to_string();
+    let mut spec_width = spec.width;
+    if spec_width.is_none() && spec.pad != b'-' {
+        // The default width counts digits, not the sign.
+        spec_width = Some(digits + (n < 0) as usize);
+    }
+    let spec = Spec {
+        width: spec_width,
+        ..*spec
+    };
+    push_padded(out, &spec, text.as_bytes(), default_pad);
+}
+
+/// Output TEXT, upcased for the ^ flag, and upcased or downcased for
+/// the # flag according to SWAP_TO_UPPER.
+fn push_text(out: &mut Vec<u8>, spec: &Spec, text: &[u8], swap_to_upper: bool) {
+    let text = if spec.upcase || (spec.swapcase && swap_to_upper) {
+        text.to_ascii_uppercase()
+    } else if spec.swapcase {
+        text.to_ascii_lowercase()
+    } else {
+        text.to_vec()
+    };
+    push_padded(out, spec, &text, b' ');
+}
+
+/// Output the UTC offset UTOFF as with %z, with COLONS colons.
+fn push_utc_offset(out: &mut Vec<u8>, spec: &Spec, utoff: i64, colons: usize) {
+    let sign = if utoff < 0 { '-' } else { '+' };
+    let (hours, minutes, seconds) = (utoff.abs() / 3600, utoff.abs() / 60 % 60, utoff.abs() % 60);
+    let text = match colons {
+        0 => format!("{}{:02}{:02}", sign, hours, minutes),
+        1 => format!("{}{:02}:{:02}", sign, hours, minutes),
+        2 => format!("{}{:02}:{:02}:{:02}", sign, hours, minutes, seconds),
+        _ if seconds != 0 => format!("{}{:02}:{:02}:{:02}", sign, hours, minutes, seconds),
+        _ if minutes != 0 => format!("{}{:02}:{:02}", sign, hours, minutes),
+        _ => format!("{}{:02}", sign, hours),
+    };
+    push_padded(out, spec, text.as_bytes(), b'0');
+}
+
+/// Return the text of the locale-dependent directive %CONVERSION for a
+/// time, or None to use the names and formats of the C locale.
+pub type LocaleText<'a> = Option<&'a Fn(u8) -> Option<Vec<u8>>>;
+
+/// The directives whose text depends on the locale.
+const LOCALE_CONVERSIONS: &[u8] = b"aAbhBcxXp";
+
+/// Format TM according to FORMAT, whose directives are those of
+/// `format-time-string', and append the result to OUT.  LOCALE gives
+/// the text of the locale-dependent directives.
+pub fn format_time(format: &[u8], tm: &DecodedTime, locale: LocaleText, out: &mut Vec<u8>) {
+    let mut i = 0;
+    while i < format.len() {
+        if format[i] != b'%' {
+            out.push(format[i]);
+            i += 1;
+            continue;
+        }
+        let start = i;
+        i += 1;
+        let mut spec = Spec {
+            pad: 0,
+            width: None,
+            upcase: false,
+            swapcase: false,
+        };
+        while i < format.len() {
+            match format[i] {
+                b'_' | b'-' | b'0' | b'+' => spec.pad = format[i],
+                b'^' => spec.upcase = true,
+                b'#' => spec.swapcase = true,
+                _ => break,
+            }
+            i += 1;
+        }
+        while i < format.len() && format[i] >= b'0' && format[i] <= b'9' {
+            let digit = (format[i] - b'0') as usize;
+            spec.width = Some(
+                spec.width
+                    .unwrap_or(0)
+                    .saturating_mul(10)
+                    .saturating_add(digit),
+            );
+            i += 1;
+        }
+        let mut colons = 0;
+        while i < format.len() {
+            match format[i] {
+                b'E' | b'O' => (),
+                b':' => colons += 1,
+                _ => break,
+            }
+            i += 1;
+        }
+        if i == format.len() || spec.width.map_or(false, |width| width > MAX_FIELD_WIDTH)
+            || (colons > 0 && format[i] != b'z')
+        {
+            let end = if i == format.len() { i } else { i + 1 };
+            out.extend_from_slice(&format[start..end]);
+            i = end;
+            continue;
+        }
+        let conversion = format[i];
+        i += 1;
+        let locale_conversion = if conversion == b'P' { b'p' } else { conversion };
+        if LOCALE_CONVERSIONS.contains(&locale_conversion) {
+            if let Some(text) = locale.and_then(|locale| locale(locale_conversion)) {
+                if conversion == b'P' {
+                    push_text(out, &spec, &text.to_ascii_lowercase(), false);
+                } else {
+                    push_text(out, &spec, &text, conversion != b'p');
+                }
+                continue;
+            }
+        }
+        let hour12 = (tm.hour + 11) % 12 + 1;
+        let weekday = WEEKDAY_NAMES[tm.weekday as usize];
+        let month = MONTH_NAMES[tm.month as usize - 1];
+        let composite = match conversion {
+            b'c' => Some(&b"%a %b %e %H:%M:%S %Y"[..]),
+            b'D' | b'x' => Some(&b"%m/%d/%y"[..]),
+            b'F' => Some(&b"%Y-%m-%d"[..]),
+            b'r' => Some(&b"%I:%M:%S %p"[..]),
+            b'R' => Some(&b"%H:%M"[..]),
+            b'T' | b'X' => Some(&b"%H:%M:%S"[..]),
+            _ => None,
+        };
+        if let Some(composite) = composite {
+            let mut text = Vec::new();
+            format_time(composite, tm, locale, &mut text);
+            push_text(out, &spec, &text, true);
+            continue;
+        }
+        match conversion {
+            b'%' => push_text(out, &spec, b"%", true),
+            b'n' => push_text(out, &spec, b"\n", true),
+            b't' => push_text(out, &spec, b"\t", true),
+            b'a' => push_text(out, &spec, weekday[..3].as_bytes(), true),
+            b'A' => push_text(out, &spec, weekday.as_bytes(), true),
+            b'b' | b'h' => push_text(out, &spec, month[..3].as_bytes(), true),
+            b'B' => push_text(out, &spec, month.as_bytes(), true),
+            b'p' => push_text(out, &spec, if tm.hour < 12 { b"AM" } else { b"PM" }, false),
+            b'P' => push_text(out, &spec, if tm.hour < 12 { b"am" } else { b"pm" }, false),
+            b'Z' => push_text(out, &spec, tm.zone.abbrev.as_bytes(), false),
+            b'z' => push_utc_offset(out, &spec, tm.zone.utoff, colons),
+            b'C' => push_number(out, &spec, div_floor(tm.year, 100), 2, b'0'),
+            b'd' => push_number(out, &spec, tm.day, 2, b'0'),
+            b'e' => push_number(out, &spec, tm.day, 2, b' '),
+            b'g' => push_number(out, &spec, mod_floor(tm.iso_week().0, 100), 2, b'0'),
+            b'G' => push_number(out, &spec, tm.iso_week().0, 1, b'0'),
+            b'H' => push_number(out, &spec, tm.hour, 2, b'0'),
+            b'I' => push_number(out, &spec, hour12, 2, b'0'),
+            b'j' => push_number(out, &spec, tm.yearday + 1, 3, b'0'),
+            b'k' => push_number(out, &spec, tm.hour, 2, b' '),
+            b'l' => push_number(out, &spec, hour12, 2, b' '),
+            b'm' => push_number(out, &spec, tm.month, 2, b'0'),
+            b'M' => push_number(out, &spec, tm.minute, 2, b'0'),
+            b'N' => {
+                let digits = tm.time.fraction_digits(spec.width.unwrap_or(9));
+                out.extend_from_slice(digits.as_bytes());
+            }
+            b'q' => push_number(out, &spec, (tm.month - 1) / 3 + 1, 1, b'0'),
+            b's' => push_number(out, &spec, tm.time.secs(), 1, b'0'),
+            b'S' => push_number(out, &spec, tm.second, 2, b'0'),
+            b'u' => push_number(out, &spec, (tm.weekday + 6) % 7 + 1, 1, b'0'),
+            b'U' => push_number(out, &spec, (tm.yearday + 7 - tm.weekday) / 7, 2, b'0'),
+            b'V' => push_number(out, &spec, tm.iso_week().1, 2, b'0'),
+            b'w' => push_number(out, &spec, tm.weekday, 1, b'0'),
+            b'W' => {
+                let monday_based = (tm.weekday + 6) % 7;
+                push_number(out, &spec, (tm.yearday + 7 - monday_based) / 7, 2, b'0')
+            }
+            b'y' => push_number(out, &spec, mod_floor(tm.year, 100), 2, b'0'),
+            b'Y' => push_number(out, &spec, tm.year, 1, b'0'),
+            _ => out.extend_from_slice(&format[start..i]),
+        }
+    }
+}
+
+/// The form of a Lisp time value, which decides the form of results.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Form {
+    Float,
+    /// (TICKS . HZ).
+    Ticks,
+    /// (HIGH LOW USEC PSEC) with this many elements; integers count
+    /// as (HIGH LOW).
+    List(usize),
+}
+
+fn time_overflow() -> ! {
+    error!("Specified time is not representable");
+}
+
+fn invalid_time() -> ! {
+    error!("Invalid time specification");
+}
+
+/// Decode the Lisp time value TIME, nil standing for the current time.
+fn lisp_time(time: LispObject) -> (Time, Form) {
+    if time.is_nil() {
+        return (Time::now(), Form::List(4));
+    }
+    if let Some(secs) = time.as_fixnum() {
+        return (
+            Time::from_secs(secs).unwrap_or_else(|| time_overflow()),
+            Form::List(2),
+        );
+    }
+    if let Some(seconds) = time.as_float() {
+        if seconds.is_nan() {
+            invalid_time();
+        }
+        return (
+            Time::from_float(seconds).unwrap_or_else(|| time_overflow()),
+            Form::Float,
+        );
+    }
+    let cons = time.as_cons().unwrap_or_else(|| invalid_time());
+    if let (Some(car), Some(cdr)) = (cons.car().as_fixnum(), cons.cdr().as_fixnum()) {
+        if cdr >= MIN_HZ {
+            return (
+                Time::from_ticks(car, cdr).unwrap_or_else(|| time_overflow()),
+                Form::Ticks,
+            );
+        }
+        // The obsolete (HIGH . LOW).
+        if cdr < 0 {
+            invalid_time();
+        }
+        let time = car.checked_mul(1 << LO_TIME_BITS)
+            .and_then(|secs| secs.checked_add(cdr))
+            .and_then(Time::from_secs)
+            .unwrap_or_else(|| time_overflow());
+        return (time, Form::List(2));
+    }
+    // (HIGH LOW USEC PSEC), with any further elements ignored.
+    let mut parts = [0; 4];
+    let mut len = 0;
+    let mut tail = time;
+    while len < 4 {
+        let cons = match tail.as_cons() {
+            Some(cons) => cons,
+            None => break,
+        };
+        parts[len] = cons.car().as_fixnum().unwrap_or_else(|| invalid_time());
+        tail = cons.cdr();
+        len += 1;
+    }
+    if len < 2 {
+        invalid_time();
+    }
+    // The obsolete (HIGH LOW . USEC).
+    if len == 2 && tail.is_not_nil() {
+        parts[2] = tail.as_fixnum().unwrap_or_else(|| invalid_time());
+        len = 3;
+    }
+    let (high, low, usec, psec) = (parts[0], parts[1], parts[2], parts[3]);
+    let secs = high.checked_mul(1 << LO_TIME_BITS)
+        .and_then(|secs| secs.checked_add(low));
+    let fraction = match len {
+        3 => Time::from_ticks(usec, 1_000_000),
+        _ => usec.checked_mul(1_000_000)
+            .and_then(|usec| usec.checked_add(psec))
+            .and_then(|ticks| Time::from_ticks(ticks, PICOSECONDS as i64)),
+    };
+    let time = secs.and_then(Time::from_secs)
+        .and_then(|secs| secs.add(fraction?))
+        .unwrap_or_else(|| time_overflow());
+    (time, Form::List(len))
+}
+
+/// Return N as a fixnum, signaling an error rather than wrapping if it
+/// is out of range, as it can be on 32-bit hosts.
+fn make_fixnum(n: i64) -> LispObject {
+    if n < MOST_NEGATIVE_FIXNUM as i64 || n > MOST_POSITIVE_FIXNUM as i64 {
+        time_overflow();
+    }
+    LispObject::from_fixnum(n as EmacsInt)
+}
+
+/// Return the least power-of-ten multiple of HZ that is at least
+/// `MIN_HZ', so that (TICKS . HZ) can't be read back as (HIGH . LOW).
+fn unambiguous_hz(hz: u64) -> u64 {
+    let mut scaled = hz;
+    while scaled < MIN_HZ as u64 {
+        scaled *= 10;
+    }
+    scaled
+}
+
+/// Return TICKS / HZ seconds after the epoch as a (TICKS . HZ) Lisp
+/// time value.  HZ must be positive.
+pub fn make_lisp_ticks(ticks: i64, hz: i64) -> LispObject {
+    let time = Time::from_ticks(ticks, hz).unwrap_or_else(|| time_overflow());
+    make_lisp_time(time, Form::Ticks)
+}
+
+/// Return TIME as a Lisp time value of the form FORM.  A list is
+/// lengthened if it can't hold the fraction of TIME, and replaced by
+/// (TICKS . HZ) if it can't hold it at all.
+fn make_lisp_time(time: Time, form: Form) -> LispObject {
+    let len = match form {
+        Form::Float => return LispObject::from_float(time.to_float()),
+        Form::List(len) if PICOSECONDS % time.hz == 0 => len,
+        _ => {
+            let hz = unambiguous_hz(time.hz);
+            let ticks = time.to_ticks(hz).unwrap_or_else(|| time_overflow());
+            return LispObject::cons(make_fixnum(ticks), make_fixnum(hz as i64));
+        }
+    };
+    let psec = time.rescale(PICOSECONDS).ticks as i64;
+    let len = if psec % 1_000_000 != 0 {
+        4
+    } else if psec != 0 {
+        len.max(3)
+    } else {
+        len
+    };
+    let mut list = LispObject::constant_nil();
+    if len >= 4 {
+        list = LispObject::cons(make_fixnum(psec % 1_000_000), list);
+    }
+    if len >= 3 {
+        list = LispObject::cons(make_fixnum(psec / 1_000_000), list);
+    }
+    list = LispObject::cons(make_fixnum(time.secs & ((1 << LO_TIME_BITS) - 1)), list);
+    LispObject::cons(make_fixnum(time.secs >> LO_TIME_BITS), list)
+}
+
+fn make_lisp_string(text: &[u8]) -> LispObject {
+    let length = text.len() as ptrdiff_t;
+    LispObject::from(unsafe { make_string(text.as_ptr() as *const c_char, length) })
+}
+
+/// Return the abbreviation of a zone that is UTOFF seconds east of
+/// Greenwich, such as "+0530".
+fn numeric_zone_name(utoff: i64) -> String {
+    let sign = if utoff < 0 { '-' } else { '+' };
+    let (hours, minutes, seconds) = (utoff.abs() / 3600, utoff.abs() / 60 % 60, utoff.abs() % 60);
+    if seconds != 0 {
+        format!("{}{:02}{:02}{:02}", sign, hours, minutes, seconds)
+    } else if minutes != 0 {
+        format!("{}{:02}{:02}", sign, hours, minutes)
+    } else {
+        format!("{}{:02}", sign, hours)
+    }
+}
+
+/// Return the time zone ZONE stands for: nil for Emacs local time, t
+/// for Universal Time, `wall' for the system wall clock time, a TZ
+/// string, or an offset in seconds, either alone or in a list with
+/// the abbreviation of the zone.
+pub fn lisp_zone(zone: LispObject) -> Arc<Zone> {
+    if zone.is_nil() {
+        let tz = unsafe { emacs_getenv_TZ() };
+        if tz.is_null() {
+            wall_zone()
+        } else {
+            zone_from_tz(&unsafe { CStr::from_ptr(tz) }.to_string_lossy())
+        }
+    } else if zone.is_t() {
+        Arc::new(Zone::utc())
+    } else if zone.eq(LispObject::from(Qwall)) {
+        wall_zone()
+    } else if let Some(tz) = zone.as_string() {
+        zone_from_tz(&String::from_utf8_lossy(tz.as_slice()))
+    } else if let Some(utoff) = zone.as_fixnum() {
+        Arc::new(Zone::fixed(utoff, numeric_zone_name(utoff)))
+    } else if let (Some(utoff), Some(abbrev)) = (
+        zone.as_cons().and_then(|cons| cons.car().as_fixnum()),
+        zone.as_cons()
+            .and_then(|cons| cons.cdr().as_cons())
+            .and_then(|cons| cons.car().as_string()),
+    ) {
+        let abbrev = String::from_utf8_lossy(abbrev.as_slice()).into_owned();
+        Arc::new(Zone::fixed(utoff, abbrev))
+    } else {
+        xsignal!(
+            Qerror,
+            make_lisp_string(b"Invalid time zone specification"),
+            zone
+        );
+    }
+}
+
+/// Return TM as a C `struct tm', or None if its year doesn't fit.
+/// ABBREV is the abbreviation of its zone, and must outlive the result.
+fn c_tm(tm: &DecodedTime, abbrev: &CStr) -> Option<libc::tm> {
+    let year = tm.year - 1900;
+    if year < libc::c_int::min_value() as i64 || year > libc::c_int::max_value() as i64 {
+        return None;
+    }
+    let mut c_tm: libc::tm = unsafe { ::std::mem::zeroed() };
+    c_tm.tm_sec = tm.second as libc::c_int;
+    c_tm.tm_min = tm.minute as libc::c_int;
+    c_tm.tm_hour = tm.hour as libc::c_int;
+    c_tm.tm_mday = tm.day as libc::c_int;
+    c_tm.tm_mon = (tm.month - 1) as libc::c_int;
+    c_tm.tm_year = year as libc::c_int;
+    c_tm.tm_wday = tm.weekday as libc::c_int;
+    c_tm.tm_yday = tm.yearday as libc::c_int;
+    c_tm.tm_isdst = tm.zone.is_dst as libc::c_int;
+    set_tm_zone(&mut c_tm, tm.zone.utoff, abbrev);
+    Some(c_tm)
+}
+
+#[cfg(unix)]
+fn set_tm_zone(tm: &mut libc::tm, utoff: i64, abbrev: &CStr) {
+    tm.tm_gmtoff = utoff as libc::c_long;
+    tm.tm_zone = abbrev.as_ptr();
+}
+
+#[cfg(not(unix))]
+fn set_tm_zone(_tm: &mut libc::tm, _utoff: i64, _abbrev: &CStr) {}
+
+/// Return the text of the directive %CONVERSION for TM in the current
+/// time locale, as strftime formats it, or None if it can't.
+fn strftime_text(conversion: u8, tm: &DecodedTime) -> Option<Vec<u8>> {
+    let abbrev = CString::new(tm.zone.abbrev.clone()).ok()?;
+    let c_tm = c_tm(tm, &abbrev)?;
+    let format = [b'%', conversion, 0];
+    let mut buf = vec![0u8; 256];
+    while buf.len() <= MAX_FIELD_WIDTH {
+        // strftime returns 0 both when the text is empty and when it
+        // doesn't fit.
+        buf[0] = 1;
+        let len = unsafe {
+            libc::strftime(
+                buf.as_mut_ptr() as *mut c_char,
+                buf.len(),
+                format.as_ptr() as *const c_char,
+                &c_tm,
+            )
+        };
+        if len > 0 || buf[0] == 0 {
+            buf.truncate(len);
+            return Some(buf);
+        }
+        let size = buf.len() * 2;
+        buf.resize(size, 0);
+    }
+    None
+}
+
+/// Return the text of the locale-dependent directive %CONVERSION for
+/// TM in the locale of `system-time-locale', decoded with
+/// `locale-coding-system' into the internal representation of
+/// multibyte text if MULTIBYTE.
+fn locale_time_text(conversion: u8, tm: &DecodedTime, multibyte: bool) -> Option<Vec<u8>> {
+    let text = strftime_text(conversion, tm)?;
+    if !multibyte || text.is_ascii() {
+        return Some(text);
+    }
+    let decoded = LispObject::from(unsafe {
+        let coding_system = globals.f_Vlocale_coding_system;
+        let decoded = code_convert_string(
+            make_unibyte(&text).to_raw(),
+            coding_system,
+            Qt,
+            false,
+            false,
+            true,
+        );
+        string_to_multibyte(decoded)
+    });
+    Some(decoded.as_string_or_error().as_slice().to_vec())
+}
+
+/// Return the current time, as the number of seconds since 1970-01-01 00:00:00.
+/// The time is returned as a list of integers (HIGH LOW USEC PSEC).
+/// HIGH has the most significant bits of the seconds, while LOW has the
+/// least significant 16 bits.  USEC and PSEC are the microsecond and
+/// picosecond counts.
+#[lisp_fn]
+pub fn current_time() -> LispObject {
+    make_lisp_time(Time::now(), Form::List(4))
+}
+
+/// Return the sum of two time values A and B, as a time value.
+/// The result is a float if either argument is, (TICKS . HZ) if either
+/// argument is, and otherwise a list as long as the longer argument.
+/// The sum is exact unless it is a float.
+/// A nil value for either argument stands for the current time.
+/// See `format-time-string' for the various forms of a time value.
+#[lisp_fn]
+pub fn time_add(a: LispObject, b: LispObject) -> LispObject {
+    time_arith(a, b, Time::add, |a, b| a + b)
+}
+
+/// Return the difference between two time values A and B, as a time value.
+/// The result has the same form as with `time-add'.
+/// Use `float-time' to convert the difference into elapsed seconds.
+/// A nil value for either argument stands for the current time.
+/// See `format-time-string' for the various forms of a time value.
+#[lisp_fn]
+pub fn time_subtract(a: LispObject, b: LispObject) -> LispObject {
+    time_arith(a, b, Time::sub, |a, b| a - b)
+}
+
+fn time_arith(
+    a: LispObject,
+    b: LispObject,
+    op: fn(Time, Time) -> Option<Time>,
+    float_op: fn(f64, f64) -> f64,
+) -> LispObject {
+    let (a, a_form) = lisp_time(a);
+    let (b, b_form) = lisp_time(b);
+    let form = match (a_form, b_form) {
+        (Form::Float, _) | (_, Form::Float) => {
+            return LispObject::from_float(float_op(a.to_float(), b.to_float()))
+        }
+        (Form::Ticks, _) | (_, Form::Ticks) => Form::Ticks,
+        (Form::List(a_len), Form::List(b_len)) => Form::List(a_len.max(b_len)),
+    };
+    make_lisp_time(op(a, b).unwrap_or_else(|| time_overflow()), form)
+}
+
+/// Return non-nil if time value T1 is earlier than time value T2.
+/// A nil value for either argument stands for the current time.
+/// See `format-time-string' for the various forms of a time value.
+#[lisp_fn]
+pub fn time_less_p(t1: LispObject, t2: LispObject) -> LispObject {
+    LispObject::from_bool(lisp_time(t1).0 < lisp_time(t2).0)
+}
+
+/// Return non-nil if A and B are equal time values.
+/// A nil value for either argument stands for the current time, and
+/// two nil arguments are equal.
+/// See `format-time-string' for the various forms of a time value.
+#[lisp_fn]
+pub fn time_equal_p(a: LispObject, b: LispObject) -> LispObject {
+    LispObject::from_bool((a.is_nil() && b.is_nil()) || lisp_time(a).0 == lisp_time(b).0)
+}
+
+/// Return the current time, as a float number of seconds since the epoch.
+/// If SPECIFIED-TIME is given, it is the time to convert to float
+/// instead of the current time.  See `format-time-string' for the
+/// various forms of a time value.
+///
+/// WARNING: Since the result is floating point, it may not be exact.
+/// If precise time stamps are required, use either `current-time',
+/// or (if you need time as a string) `format-time-string'.
+#[lisp_fn(min = "0")]
+pub fn float_time(specified_time: LispObject) -> LispObject {
+    LispObject::from_float(lisp_time(specified_time).0.to_float())
+}
+
+/// Use FORMAT-STRING to format the time TIME, or now if omitted or nil.
+/// TIME is a time value: an integer or float number of seconds since
+/// the epoch, a list (HIGH LOW USEC PSEC) as returned by `current-time'
+/// or `file-attributes', where the seconds are HIGH * 2**16 + LOW and
+/// USEC and PSEC may be omitted, or a pair (TICKS . HZ) standing for
+/// TICKS / HZ seconds, where HZ is an integer of at least 65536.  A
+/// pair of integers with a smaller cdr is the obsolete (HIGH . LOW).
+///
+/// The optional ZONE is omitted or nil for Emacs local time, t for
+/// Universal Time, `wall' for system wall clock time, or a string as in
+/// the TZ environment variable.  It can also be a list (as from
+/// `current-time-zone') or an integer (as from `decode-time') applied
+/// without consideration for daylight saving time.  Zones are read
+/// from the time zone database in /usr/share/zoneinfo.
+///
+/// The value is a copy of FORMAT-STRING, but with certain constructs replaced
+/// by text that describes the specified date and time in TIME, in the
+/// locale of `system-time-locale':
+///
+/// %Y is the year, %y within the century, %C the century.
+/// %G is the year corresponding to the ISO week, %g within the century.
+/// %m is the numeric month.
+/// %b and %h are the locale's abbreviated month name, %B the full name.
+/// %d is the day of the month, zero-padded, %e is blank-padded.
+/// %u is the numeric day of week from 1 (Monday) to 7, %w from 0 (Sunday) to 6.
+/// %a is the locale's abbreviated name of the day of week, %A the full name.
+/// %U is the week number starting on Sunday, %W starting on Monday,
+///  %V according to ISO 8601.
+/// %j is the day of the year.
+///
+/// %H is the hour on a 24-hour clock, %I is on a 12-hour clock, %k is like %H
+///  only blank-padded, %l is like %I blank-padded.
+/// %p is the locale's equivalent of either AM or PM, %P is it downcased.
+/// %q is the calendar quarter (1–4).
+/// %M is the minute.
+/// %S is the second.
+/// %N is the nanosecond, %6N the microsecond, %3N the millisecond, etc.
+/// %Z is the time zone name, %z is the numeric form, %:z has a colon,
+///  %::z has seconds, and %:::z is as short as possible.
+/// %s is the number of seconds since 1970-01-01 00:00:00 +0000.
+///
+/// %c is the locale's date and time format.
+/// %x is the locale's "preferred" date format.
+/// %D is like "%m/%d/%y".
+/// %F is the ISO 8601 date format (like "%Y-%m-%d").
+///
+/// %R is like "%H:%M", %T is like "%H:%M:%S", %r is like "%I:%M:%S %p".
+/// %X is the locale's "preferred" time format.
+///
+/// Finally, %n is a newline, %t is a tab, %% is a literal %.
+///
+/// Certain flags and modifiers are available with some format controls.
+/// The flags are `_', `-', `^' and `#'.  For certain characters X,
+/// %_X is like %X, but padded with blanks; %-X is like %X,
+/// but without padding.  %^X is like %X, but with all textual
+/// characters up-cased; %#X is like %X, but with letter-case of
+/// all textual characters reversed.
+/// %NX (where N stands for an integer) is like %X,
+/// but takes up at least N (a number) positions.
+/// The modifiers `E' and `O' are accepted and ignored.
+///
+/// For example, to produce full ISO 8601 format, use "%FT%T%z".
+///
+/// usage: (format-time-string FORMAT-STRING &optional TIME ZONE)
+#[lisp_fn(min = "1")]
+pub fn format_time_string(
+    format_string: LispObject,
+    timeval: LispObject,
+    zone: LispObject,
+) -> LispObject {
+    let format = format_string.as_string_or_error();
+    let (time, _) = lisp_time(timeval);
+    let tm = DecodedTime::new(time, &lisp_zone(zone));
+    let multibyte = format.is_multibyte() || format.as_slice().is_ascii();
+    unsafe { synchronize_system_time_locale() };
+    let locale = |conversion: u8| locale_time_text(conversion, &tm, multibyte);
+    let mut out = Vec::new();
+    format_time(format.as_slice(), &tm, Some(&locale), &mut out);
+    if multibyte {
+        make_lisp_string(&out)
+    } else {
+        make_unibyte(&out)
+    }
+}
+
+/// Decode a time value as (SEC MINUTE HOUR DAY MONTH YEAR DOW DST UTCOFF).
+/// The optional TIME is the time value to convert, nil standing for the
+/// current time.  Its fraction of a second is ignored.  See
+/// `format-time-string' for the various forms of a time value.
+///
+/// The optional ZONE is omitted or nil for Emacs local time, t for
+/// Universal Time, `wall' for system wall clock time, or a string as in
+/// the TZ environment variable.  It can also be a list (as from
+/// `current-time-zone') or an integer (the UTC offset in seconds) applied
+/// without consideration for daylight saving time.
+///
+/// The list has the following nine members: SEC is an integer between 0
+/// and 59.  MINUTE is an integer between 0 and 59.  HOUR is an integer
+/// between 0 and 23.  DAY is an integer between 1 and 31.  MONTH is an
+/// integer between 1 and 12.  YEAR is an integer indicating the
+/// four-digit year.  DOW is the day of week, an integer between 0 and 6,
+/// where 0 is Sunday.  DST is t if daylight saving time is in effect,
+/// otherwise nil.  UTCOFF is an integer indicating the UTC offset in
+/// seconds, i.e., the number of seconds east of Greenwich.  (Note that
+/// Common Lisp has different meanings for DOW and UTCOFF.)
+///
+/// usage: (decode-time &optional TIME ZONE)
+#[lisp_fn(min = "0")]
+pub fn decode_time(specified_time: LispObject, zone: LispObject) -> LispObject {
+    let (time, _) = lisp_time(specified_time);
+    let tm = DecodedTime::new(time, &lisp_zone(zone));
+    list!(
+        make_fixnum(tm.second),
+        make_fixnum(tm.minute),
+        make_fixnum(tm.hour),
+        make_fixnum(tm.day),
+        make_fixnum(tm.month),
+        make_fixnum(tm.year),
+        make_fixnum(tm.weekday),
+        LispObject::from_bool(tm.zone.is_dst),
+        make_fixnum(tm.zone.utoff)
+    )
+}
+
+/// Return the local time YEAR-MONTH-DAY HOUR:MINUTE:SECOND, normalizing
+/// out-of-range fields, as a number of seconds since the epoch as if
+/// the local time were UTC.
+pub fn local_seconds(fields: [i64; 6]) -> Option<i64> {
+    let (second, minute, hour) = (fields[0], fields[1], fields[2]);
+    let (day, month, year) = (fields[3], fields[4], fields[5]);
+    let year = year.checked_add(div_floor(month - 1, 12))?;
+    if year.abs() > MAX_SECONDS / DAY / 365 {
+        return None;
+    }
+    let days = days_from_civil(year, mod_floor(month - 1, 12) + 1, 1).checked_add(day - 1)?;
+    days.checked_mul(DAY)?
+        .checked_add(hour.checked_mul(3600)?)?
+        .checked_add(minute.checked_mul(60)?)?
+        .checked_add(second)
+}
+
+/// Convert SECOND, MINUTE, HOUR, DAY, MONTH, YEAR and ZONE to internal time.
+/// This is the reverse operation of `decode-time', which see.
+///
+/// The optional ZONE is omitted or nil for Emacs local time, t for
+/// Universal Time, `wall' for system wall clock time, or a string as in
+/// the TZ environment variable.  It can also be a list (as from
+/// `current-time-zone') or an integer (as from `decode-time') applied
+/// without consideration for daylight saving time.
+///
+/// You can pass more than 7 arguments; then the first six arguments
+/// are used as SECOND through YEAR, and the *last* argument is used as ZONE.
+/// The intervening arguments are ignored.
+/// This feature lets (apply \\='encode-time (decode-time ...)) work.
+///
+/// Out-of-range values for SECOND, MINUTE, HOUR, DAY, or MONTH are allowed;
+/// for example, a DAY of 0 means the day preceding the given month.
+/// Year numbers less than 100 are treated just like other year numbers.
+/// If you want them to stand for years in this century, you must do that yourself.
+///
+/// A local time that is skipped by a daylight saving time transition
+/// is taken with the offset in effect before the transition, and one
+/// that is repeated stands for its first occurrence.
+///
+/// SECOND can also be a time value with a fraction of a second, such
+/// as (TICKS . HZ) or a float, in which case the result has the same
+/// form as it.
+///
+/// usage: (encode-time SECOND MINUTE HOUR DAY MONTH YEAR &optional ZONE)
+#[lisp_fn(min = "6")]
+pub fn encode_time(args: &mut [LispObject]) -> LispObject {
+    let (second, form) = if args[0].is_nil() {
+        wrong_type!(Qintegerp, args[0])
+    } else {
+        lisp_time(args[0])
+    };
+    let mut fields = [second.secs; 6];
+    for (field, arg) in fields.iter_mut().zip(args[1..6].iter()) {
+        *field = arg.as_fixnum_or_error();
+    }
+    let zone = if args.len() > 6 {
+        args[args.len() - 1]
+    } else {
+        LispObject::constant_nil()
+    };
+    let zone = lisp_zone(zone);
+    let fraction = second.sub(Time::from_secs(second.secs).unwrap());
+    let time = local_seconds(fields)
+        .and_then(|local| zone.from_local(local))
+        .and_then(Time::from_secs)
+        .and_then(|time| time.add(fraction?))
+        .unwrap_or_else(|| time_overflow());
+    make_lisp_time(time, form)
+}
+
+include!(concat!(env!("OUT_DIR"), "/timefns_exports.rs"));
+
+#[test]
+fn test_civil() {
+    assert_eq!(days_from_civil(1970, 1, 1), 0);
+    assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+    assert_eq!(days_from_civil(1969, 12, 31), -1);
+    assert_eq!(days_from_civil(1, 1, 1), -719_162);
+    for &days in &[-1_000_000, -719_162, -1, 0, 59, 60, 11_016, 11_017, 1_000_000] {
+        let (year, month, day) = civil_from_days(days);
+        assert_eq!(days_from_civil(year, month, day), days);
+    }
+    assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+    assert_eq!(civil_from_days(-719_163), (0, 12, 31));
+    assert!(is_leap_year(2000) && is_leap_year(-4) && !is_leap_year(1900));
+}
+
+#[test]
+fn test_time_arith() {
+    let a = Time::from_ticks(3, 2).unwrap();
+    let b = Time::from_ticks(1, 3).unwrap();
+    let sum = a.add(b).unwrap();
+    assert_eq!((sum.secs, sum.ticks, sum.hz), (1, 5, 6));
+    let diff = b.sub(a).unwrap();
+    assert_eq!((diff.secs, diff.ticks, diff.hz), (-2, 5, 6));
+    assert_eq!(diff.to_ticks(6), Some(-7));
+    assert!(b < a);
+    assert_eq!(Time::from_ticks(2, 4).unwrap(), Time::from_ticks(1, 2).unwrap());
+    assert_eq!(
+        Time::from_float(1.5).unwrap(),
+        Time::from_ticks(3, 2).unwrap()
+    );
+    let tiny = Time::from_float(-1e-300).unwrap();
+    assert!(tiny < Time::from_secs(0).unwrap());
+    assert!(tiny > Time::from_ticks(-1, 1 << 62).unwrap());
+    assert_eq!(Time::from_float(-0.75).unwrap(), Time::from_ticks(-3, 4).unwrap());
+    assert_eq!(Time::from_float(-2.0).unwrap(), Time::from_secs(-2).unwrap());
+    assert_eq!(Time::from_float(1e16).unwrap(), Time::from_secs(10_000_000_000_000_000).unwrap());
+    assert_eq!(Time::from_float(0.1).unwrap().hz, 1 << 55);
+    assert!(Time::from_float(1e30).is_none());
+    assert!(Time::from_float(::std::f64::INFINITY).is_none());
+    let big = Time::from_ticks(1, 1 << 62).unwrap();
+    let odd = Time::from_ticks(1, 3).unwrap();
+    assert!(big.add(odd).is_some());
+    assert!(big.add(Time::from_ticks(1, (1 << 62) - 1).unwrap()).is_none());
+    assert_eq!(Time::from_ticks(1, 8).unwrap().fraction_digits(4), "1250");
+    assert_eq!(Time::from_ticks(2, 3).unwrap().fraction_digits(3), "666");
+    assert_eq!(mul_wide(u64::max_value(), u64::max_value()), (u64::max_value() - 1, 1));
+}
+
+#[test]
+fn test_unambiguous_hz() {
+    assert_eq!(unambiguous_hz(1), 100_000);
+    assert_eq!(unambiguous_hz(6), 600_000);
+    assert_eq!(unambiguous_hz(65_535), 655_350);
+    assert_eq!(unambiguous_hz(65_536), 65_536);
+    assert_eq!(unambiguous_hz(1 << 62), 1 << 62);
+}
+
+#[cfg(test)]
+fn format_utc(format: &str, secs: i64) -> String {
+    let tm = DecodedTime::new(Time::from_secs(secs).unwrap(), &Zone::utc());
+    let mut out = Vec::new();
+    format_time(format.as_bytes(), &tm, None, &mut out);
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn test_format_time() {
+    // 1972-06-30 23:59:59, a Friday.
+    let t = 78_796_799;
+    assert_eq!(format_utc("%Y-%m-%d %H:%M:%S %z (%Z)", t), "1972-06-30 23:59:59 +0000 (UTC)");
+    assert_eq!(format_utc("%c", t), "Fri Jun 30 23:59:59 1972");
+    assert_eq!(format_utc("%a %A %b %B %h", t), "Fri Friday Jun June Jun");
+    assert_eq!(format_utc("%^a %#A %#p %^B", t), "FRI FRIDAY pm JUNE");
+    assert_eq!(format_utc("%D %F %R %T %r", t), "06/30/72 1972-06-30 23:59 23:59:59 11:59:59 PM");
+    assert_eq!(format_utc("%C %y %j %u %w %q", t), "19 72 182 5 5 2");
+    assert_eq!(format_utc("%U %W %V %G %g", t), "26 26 26 1972 72");
+    assert_eq!(format_utc("%I %l %k %e %s", t), "11 11 23 30 78796799");
+    assert_eq!(format_utc("%_m|%-m|%5Y|%_5d|%-j|%3H", t), " 6|6|01972|   30|182|023");
+    assert_eq!(format_utc("%10A|%^10b|%%|%n|%t", t), "    Friday|       JUN|%|\n|\t");
+    assert_eq!(format_utc("%Ey %Od %:z %::z %:::z", t), "72 30 +00:00 +00:00:00 +00");
+    assert_eq!(format_utc("%Q %:y %", t), "%Q %:y %");
+    assert_eq!(format_utc("%Y %F", -62_198_755_200), "-1 -1-01-01");
+    assert_eq!(format_utc("%C%y", -62_198_755_200), "-0199");
+    // ISO weeks at the turn of a year.
+    assert_eq!(format_utc("%G-W%V-%u", 1_104_537_600), "2004-W53-6");
+    assert_eq!(format_utc("%G-W%V-%u", 1_230_768_000), "2009-W01-4");
+    assert_eq!(format_utc("%G-W%V-%u", 1_230_595_200), "2009-W01-2");
+
+    let time = Time::from_ticks(78_796_799_999_999_999, 1_000_000_000).unwrap();
+    let tm = DecodedTime::new(time, &Zone::parse_posix("IST-5:30").unwrap());
+    let mut out = Vec::new();
+    format_time(b"%Y-%m-%d %H:%M:%S.%3N %N %z %Z", &tm, None, &mut out);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "1972-07-01 05:29:59.999 999999999 +0530 IST"
+    );
+}
+
+#[test]
+fn test_format_time_locale() {
+    let tm = DecodedTime::new(Time::from_secs(78_796_799).unwrap(), &Zone::utc());
+    let locale = |conversion: u8| match conversion {
+        b'a' => Some(b"ven.".to_vec()),
+        b'p' => Some(b"XM".to_vec()),
+        b'c' => Some(b"C!".to_vec()),
+        _ => None,
+    };
+    let mut out = Vec::new();
+    format_time(b"%a %^a %A %p %P %r %c %x", &tm, Some(&locale), &mut out);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "ven. VEN. Friday XM xm 11:59:59 XM C! 06/30/72"
+    );
+}
+
+#[test]
+fn test_decode_and_local_seconds() {
+    let zone = Zone::parse_posix("EST5EDT").unwrap();
+    let tm = DecodedTime::new(Time::from_secs(1_636_264_799).unwrap(), &zone);
+    assert_eq!(
+        (tm.year, tm.month, tm.day, tm.hour, tm.minute, tm.second),
+        (2021, 11, 7, 1, 59, 59)
+    );
+    assert_eq!((tm.weekday, tm.yearday, tm.zone.is_dst), (0, 310, true));
+    assert_eq!(local_seconds([59, 59, 1, 7, 11, 2021]), Some(1_636_250_399));
+    // Out-of-range fields.
+    assert_eq!(local_seconds([0, 0, 0, 0, 3, 2000]), local_seconds([0, 0, 0, 29, 2, 2000]));
+    assert_eq!(local_seconds([0, 0, 0, 1, 13, 1999]), local_seconds([0, 0, 0, 1, 1, 2000]));
+    assert_eq!(local_seconds([-1, 0, 0, 1, 1, 1970]), Some(-1));
+    assert_eq!(local_seconds([0, 0, 0, 1, 1, 1 << 50]), None);
+    assert_eq!(local_seconds([0, 0, 0, 1, 1, i64::max_value()]), None);
+}
@@ -0,0 +1,634 @@
+//! Time zones: the TZif files of the time zone database, as described
+//! in RFC 8536, and the POSIX TZ rules they end with.
+//!
+//! Zones are read once and shared, so that converting times never
+//! needs to change the TZ environment variable or call tzset, neither
+//! of which is safe while other threads are running.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+
+use timefns::{civil_from_days, days_from_civil, div_floor, is_leap_year, mod_floor, DAY};
+
+/// The directory holding the time zone database.
+const ZONEINFO_DIR: &str = "/usr/share/zoneinfo";
+
+/// The file describing the system's wall clock time.
+const LOCALTIME_FILE: &str = "/etc/localtime";
+
+lazy_static! {
+    /// The zones read so far, by TZ string.
+    static ref ZONES: Mutex<HashMap<String, Arc<Zone>>> = Mutex::new(HashMap::new());
+}
+
+/// A local time type: an offset from Universal Time and its name.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LocalTimeType {
+    /// The number of seconds east of Greenwich.
+    pub utoff: i64,
+    pub is_dst: bool,
+    pub abbrev: String,
+}
+
+/// The day of the year on which a POSIX TZ rule changes the time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RuleDay {
+    /// Jn: the Nth day, from 1 to 365, not counting February 29.
+    Julian(i64),
+    /// n: the Nth day, from 0 to 365, counting February 29.
+    Zero(i64),
+    /// Mm.w.d: weekday D, 0 being Sunday, of week W of month M; week 5
+    /// is the last one.
+    MonthWeekDay(i64, i64, i64),
+}
+
+impl RuleDay {
+    /// Return the number of days from the epoch to this day in YEAR.
+    fn days(self, year: i64) -> i64 {
+        let jan1 = days_from_civil(year, 1, 1);
+        match self {
+            RuleDay::Julian(n) => jan1 + n - 1 + (is_leap_year(year) && n >= 60) as i64,
+            RuleDay::Zero(n) => jan1 + n,
+            RuleDay::MonthWeekDay(month, week, weekday) => {
+                let first = days_from_civil(year, month, 1);
+                // 1970-01-01 was a Thursday.
+                let first_weekday = mod_floor(first + 4, 7);
+                let mut day = first + mod_floor(weekday - first_weekday, 7) + (week - 1) * 7;
+                let next_month = if month == 12 {
+                    days_from_civil(year + 1, 1, 1)
+                } else {
+                    days_from_civil(year, month + 1, 1)
+                };
+                while day >= next_month {
+                    day -= 7;
+                }
+                day
+            }
+        }
+    }
+}
+
+/// The daylight saving time part of a POSIX TZ rule.
+#[derive(Clone, Debug, PartialEq)]
+struct DstRule {
+    dst: LocalTimeType,
+    start: RuleDay,
+    /// The local standard time of day at which DST starts, in seconds.
+    start_time: i64,
+    end: RuleDay,
+    /// The local daylight saving time of day at which DST ends.
+    end_time: i64,
+}
+
+/// A POSIX TZ rule, such as "EST5EDT,M3.2.0,M11.1.0".
+#[derive(Clone, Debug, PartialEq)]
+struct PosixRule {
+    std: LocalTimeType,
+    dst: Option<DstRule>,
+}
+
+impl PosixRule {
+    fn local_type(&self, time: i64) -> &LocalTimeType {
+        let dst = match self.dst {
+            Some(ref dst) => dst,
+            None => return &self.std,
+        };
+        let year = civil_from_days(div_floor(time.saturating_add(self.std.utoff), DAY)).0;
+        let start = dst.start.days(year) * DAY + dst.start_time - self.std.utoff;
+        let end = dst.end.days(year) * DAY + dst.end_time - dst.dst.utoff;
+        let in_dst = if start <= end {
+            start <= time && time < end
+        } else {
+            !(end <= time && time < start)
+        };
+        if in_dst {
+            &dst.dst
+        } else {
+            &self.std
+        }
+    }
+}
+
+/// A time zone: the local time types in effect between transitions,
+/// and an optional rule for the times after the last one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Zone {
+    transitions: Vec<i64>,
+    type_indices: Vec<usize>,
+    types: Vec<LocalTimeType>,
+    rule: Option<PosixRule>,
+}
+
+impl Zone {
+    /// Return a zone whose offset is always UTOFF seconds east of
+    /// Greenwich.
+    pub fn fixed(utoff: i64, abbrev: String) -> Zone {
+        Zone {
+            transitions: Vec::new(),
+            type_indices: Vec::new(),
+            types: vec![
+                LocalTimeType {
+                    utoff,
+                    is_dst: false,
+                    abbrev,
+                },
+            ],
+            rule: None,
+        }
+    }
+
+    pub fn utc() -> Zone {
+        Zone::fixed(0, "UTC".to_string())
+    }
+
+    /// Return the local time type in effect at TIME, a number of
+    /// seconds since the epoch.
+    pub fn local_type(&self, time: i64) -> &LocalTimeType {
+        if let Some(ref rule) = self.rule {
+            if self.transitions.last().map_or(true, |&last| last <= time) {
+                return rule.local_type(time);
+            }
+        }
+        match self.transitions.binary_search(&time) {
+            Ok(idx) => &self.types[self.type_indices[idx]],
+            Err(0) => &self.types[0],
+            Err(idx) => &self.types[self.type_indices[idx - 1]],
+        }
+    }
+
+    /// Return the time whose local time is LOCAL, counted in seconds
+    /// since the epoch as if the zone were UTC.  A local time skipped
+    /// by a transition is taken with the offset in effect before it,
+    /// and a repeated one stands for its first occurrence.
+    pub fn from_local(&self, local: i64) -> Option<i64> {
+        let before = self.local_type(local.checked_sub(DAY)?).utoff;
+        let after = self.local_type(local.checked_add(DAY)?).utoff;
+        for &utoff in &[before, after] {
+            let time = local.checked_sub(utoff)?;
+            if self.local_type(time).utoff == utoff {
+                return Some(time);
+            }
+        }
+        local.checked_sub(before)
+    }
+
+    /// Parse DATA, the contents of a TZif file.
+    pub fn parse_tzif(data: &[u8]) -> Option<Zone> {
+        let mut reader = Reader { data, pos: 0 };
+        let header = TzifHeader::read(&mut reader)?;
+        if header.version == 0 {
+            return header.read_body(&mut reader, 4);
+        }
+        // Skip the version 1 data, which has 32-bit times.
+        reader.take(header.body_len(4)?)?;
+        let header = TzifHeader::read(&mut reader)?;
+        let mut zone = header.read_body(&mut reader, 8)?;
+        if reader.byte()? != b'\n' {
+            return None;
+        }
+        let footer = reader.data[reader.pos..].split(|&b| b == b'\n').next()?;
+        if !footer.is_empty() {
+            zone.rule = Some(parse_posix_rule(::std::str::from_utf8(footer).ok()?)?);
+        }
+        Some(zone)
+    }
+
+    /// Parse TZ, a POSIX TZ rule such as "CET-1CEST,M3.5.0,M10.5.0/3".
+    pub fn parse_posix(tz: &str) -> Option<Zone> {
+        let rule = parse_posix_rule(tz)?;
+        Some(Zone {
+            transitions: Vec::new(),
+            type_indices: Vec::new(),
+            types: vec![rule.std.clone()],
+            rule: Some(rule),
+        })
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let end = self.pos.checked_add(len)?;
+        let bytes = self.data.get(self.pos..end)?;
+        self.pos = end;
+        Some(bytes)
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        self.take(1).map(|bytes| bytes[0])
+    }
+
+    /// Read a big-endian signed integer of SIZE bytes.
+    fn int(&mut self, size: usize) -> Option<i64> {
+        let bytes = self.take(size)?;
+        let first = if bytes[0] & 0x80 != 0 { -1 } else { 0 };
+        Some(bytes.iter().fold(first, |acc, &b| (acc << 8) | i64::from(b)))
+    }
+
+    fn count(&mut self) -> Option<usize> {
+        let n = self.int(4)?;
+        if n < 0 {
+            None
+        } else {
+            Some(n as usize)
+        }
+    }
+}
+
+struct TzifHeader {
+    version: u8,
+    isutcnt: usize,
+    isstdcnt: usize,
+    leapcnt: usize,
+    timecnt: usize,
+    typecnt: usize,
+    charcnt: usize,
+}
+
+impl TzifHeader {
+    fn read(reader: &mut Reader) -> Option<TzifHeader> {
+        if reader.take(4)? != b"TZif" {
+            return None;
+        }
+        let version = match reader.byte()? {
+            0 => 0,
+            b'2' => 2,
+            b'3'...b'9' => 3,
+            _ => return None,
+        };
+        reader.take(15)?;
+        let header = TzifHeader {
+            version,
+            isutcnt: reader.count()?,
+            isstdcnt: reader.count()?,
+            leapcnt: reader.count()?,
+            timecnt: reader.count()?,
+            typecnt: reader.count()?,
+            charcnt: reader.count()?,
+        };
+        if header.typecnt == 0 || header.charcnt == 0 {
+            return None;
+        }
+        Some(header)
+    }
+
+    /// Return the length of the data block, whose times are TIME_SIZE
+    /// bytes long.
+    fn body_len(&self, time_size: usize) -> Option<usize> {
+        let lens = [
+            self.timecnt.checked_mul(time_size + 1)?,
+            self.typecnt.checked_mul(6)?,
+            self.charcnt,
+            self.leapcnt.checked_mul(time_size + 4)?,
+            self.isstdcnt,
+            self.isutcnt,
+        ];
+        lens.iter()
+            .fold(Some(0usize), |acc, &len| acc.and_then(|acc| acc.checked_add(len)))
+    }
+
+    fn read_body(&self, reader: &mut Reader, time_size: usize) -> Option<Zone> {
+        if reader.data.len() - reader.pos < self.body_len(time_size)? {
+            return None;
+        }
+        let mut transitions = Vec::with_capacity(self.timecnt);
+        for _ in 0..self.timecnt {
+            transitions.push(reader.int(time_size)?);
+        }
+        let mut type_indices = Vec::with_capacity(self.timecnt);
+        for _ in 0..self.timecnt {
+            let idx = reader.byte()? as usize;
+            if idx >= self.typecnt {
+                return None;
+            }
+            type_indices.push(idx);
+        }
+        let mut raw_types = Vec::with_capacity(self.typecnt);
+        for _ in 0..self.typecnt {
+            let utoff = reader.int(4)?;
+            let is_dst = reader.byte()? != 0;
+            let abbrev_idx = reader.byte()? as usize;
+            raw_types.push((utoff, is_dst, abbrev_idx));
+        }
+        let chars = reader.take(self.charcnt)?;
+        let mut types = Vec::with_capacity(self.typecnt);
+        for &(utoff, is_dst, abbrev_idx) in &raw_types {
+            let abbrev = chars.get(abbrev_idx..)?.split(|&b| b == 0).next()?;
+            types.push(LocalTimeType {
+                utoff,
+                is_dst,
+                abbrev: String::from_utf8_lossy(abbrev).into_owned(),
+            });
+        }
+        // Leap second records and the standard/wall and UT/local
+        // indicators don't matter for converting times.
+        reader.take(self.leapcnt * (time_size + 4) + self.isstdcnt + self.isutcnt)?;
+        Some(Zone {
+            transitions,
+            type_indices,
+            types,
+            rule: None,
+        })
+    }
+}
+
+/// A parser for POSIX TZ rules.
+struct RuleParser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> RuleParser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).cloned()
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Parse a zone abbreviation, either at least three letters or
+    /// anything but '>' between '<' and '>'.
+    fn name(&mut self) -> Option<String> {
+        let start;
+        let end;
+        if self.eat(b'<') {
+            start = self.pos;
+            while self.peek().map_or(false, |b| b != b'>') {
+                self.pos += 1;
+            }
+            end = self.pos;
+            if !self.eat(b'>') {
+                return None;
+            }
+        } else {
+            start = self.pos;
+            while self.peek().map_or(false, |b| (b | 0x20) >= b'a' && (b | 0x20) <= b'z') {
+                self.pos += 1;
+            }
+            end = self.pos;
+        }
+        if end - start < 3 {
+            return None;
+        }
+        Some(String::from_utf8_lossy(&self.bytes[start..end]).into_owned())
+    }
+
+    fn number(&mut self, max: i64) -> Option<i64> {
+        let start = self.pos;
+        let mut n: i64 = 0;
+        while let Some(b @ b'0'...b'9') = self.peek() {
+            n = n * 10 + i64::from(b - b'0');
+            if n > max {
+                return None;
+            }
+            self.pos += 1;
+        }
+        if self.pos == start {
+            None
+        } else {
+            Some(n)
+        }
+    }
+
+    /// Parse [+-]hh[:mm[:ss]], with hours up to MAX_HOURS, as a number
+    /// of seconds.
+    fn time(&mut self, max_hours: i64) -> Option<i64> {
+        let sign = if self.eat(b'-') {
+            -1
+        } else {
+            self.eat(b'+');
+            1
+        };
+        let mut seconds = self.number(max_hours)? * 3600;
+        if self.eat(b':') {
+            seconds += self.number(59)? * 60;
+            if self.eat(b':') {
+                seconds += self.number(59)?;
+            }
+        }
+        Some(sign * seconds)
+    }
+
+    fn day(&mut self) -> Option<RuleDay> {
+        if self.eat(b'J') {
+            let n = self.number(365)?;
+            if n == 0 {
+                return None;
+            }
+            Some(RuleDay::Julian(n))
+        } else if self.eat(b'M') {
+            let month = self.number(12)?;
+            if month == 0 || !self.eat(b'.') {
+                return None;
+            }
+            let week = self.number(5)?;
+            if week == 0 || !self.eat(b'.') {
+                return None;
+            }
+            Some(RuleDay::MonthWeekDay(month, week, self.number(6)?))
+        } else {
+            self.number(365).map(RuleDay::Zero)
+        }
+    }
+
+    /// Parse a rule date and its optional time, which defaults to
+    /// 02:00.
+    fn day_and_time(&mut self) -> Option<(RuleDay, i64)> {
+        let day = self.day()?;
+        let time = if self.eat(b'/') {
+            self.time(167)?
+        } else {
+            2 * 3600
+        };
+        Some((day, time))
+    }
+}
+
+fn parse_posix_rule(tz: &str) -> Option<PosixRule> {
+    let mut parser = RuleParser {
+        bytes: tz.as_bytes(),
+        pos: 0,
+    };
+    // POSIX offsets count hours west of Greenwich.
+    let std = LocalTimeType {
+        abbrev: parser.name()?,
+        utoff: -parser.time(24)?,
+        is_dst: false,
+    };
+    if parser.peek().is_none() {
+        return Some(PosixRule { std, dst: None });
+    }
+    let abbrev = parser.name()?;
+    let utoff = match parser.peek() {
+        Some(b) if b != b',' => -parser.time(24)?,
+        _ => std.utoff + 3600,
+    };
+    let ((start, start_time), (end, end_time)) = if parser.eat(b',') {
+        let start = parser.day_and_time()?;
+        if !parser.eat(b',') {
+            return None;
+        }
+        (start, parser.day_and_time()?)
+    } else {
+        // The rules of the United States since 2007.
+        (
+            (RuleDay::MonthWeekDay(3, 2, 0), 2 * 3600),
+            (RuleDay::MonthWeekDay(11, 1, 0), 2 * 3600),
+        )
+    };
+    if parser.peek().is_some() {
+        return None;
+    }
+    Some(PosixRule {
+        std,
+        dst: Some(DstRule {
+            dst: LocalTimeType {
+                utoff,
+                is_dst: true,
+                abbrev,
+            },
+            start,
+            start_time,
+            end,
+            end_time,
+        }),
+    })
+}
+
+fn read_tzif_file(path: &str) -> Option<Zone> {
+    let mut data = Vec::new();
+    File::open(path).ok()?.read_to_end(&mut data).ok()?;
+    Zone::parse_tzif(&data)
+}
+
+/// Return the zone that TZ, a value of the TZ environment variable,
+/// stands for: a file of the time zone database, or else a POSIX TZ
+/// rule.  Invalid values stand for Universal Time.
+fn read_zone(tz: &str) -> Zone {
+    let (name, file_only) = if tz.starts_with(':') {
+        (&tz[1..], true)
+    } else {
+        (tz, false)
+    };
+    if name.is_empty() {
+        return Zone::utc();
+    }
+    let zone = if name.starts_with('/') {
+        read_tzif_file(name)
+    } else if name.split('/').any(|part| part == "..") {
+        None
+    } else {
+        read_tzif_file(&format!("{}/{}", ZONEINFO_DIR, name))
+    };
+    zone.or_else(|| if file_only { None } else { Zone::parse_posix(name) })
+        .unwrap_or_else(Zone::utc)
+}
+
+/// Return the zone that TZ stands for, reading it only the first time.
+pub fn zone_from_tz(tz: &str) -> Arc<Zone> {
+    if let Some(zone) = ZONES.lock().unwrap().get(tz) {
+        return Arc::clone(zone);
+    }
+    let zone = Arc::new(read_zone(tz));
+    ZONES
+        .lock()
+        .unwrap()
+        .insert(tz.to_string(), Arc::clone(&zone));
+    zone
+}
+
+/// Return the zone of the system's wall clock time.
+pub fn wall_zone() -> Arc<Zone> {
+    zone_from_tz(&format!(":{}", LOCALTIME_FILE))
+}
+
+#[cfg(test)]
+fn test_zone(name: &str) -> Zone {
+    let path = format!(
+        "{}/../test/data/zoneinfo/{}",
+        env!("CARGO_MANIFEST_DIR"),
+        name
+    );
+    read_tzif_file(&path).unwrap()
+}
+
+#[test]
+fn test_tzif() {
+    let zone = test_zone("America/New_York");
+    // Before the first transition, local mean time.
+    assert_eq!(zone.local_type(-3_000_000_000).abbrev, "LMT");
+    assert_eq!(zone.local_type(-3_000_000_000).utoff, -17762);
+    // 1972-06-30 and 1972-12-31.
+    assert_eq!(zone.local_type(78_796_799).abbrev, "EDT");
+    assert_eq!(zone.local_type(94_694_399).abbrev, "EST");
+    // The transitions of 2021, on 03-14 and 11-07.
+    assert_eq!(zone.local_type(1_615_705_199).utoff, -5 * 3600);
+    assert_eq!(zone.local_type(1_615_705_200).utoff, -4 * 3600);
+    assert!(zone.local_type(1_615_705_200).is_dst);
+    assert_eq!(zone.local_type(1_636_264_799).abbrev, "EDT");
+    assert_eq!(zone.local_type(1_636_264_800).abbrev, "EST");
+    // 2100-07-01, after the last transition of the file.
+    assert_eq!(zone.local_type(4_118_083_200).abbrev, "EDT");
+    assert_eq!(zone.local_type(4_102_444_800).abbrev, "EST");
+
+    let zone = test_zone("Australia/Sydney");
+    assert_eq!(zone.local_type(4_102_444_800).abbrev, "AEDT");
+    assert_eq!(zone.local_type(4_118_083_200).abbrev, "AEST");
+    assert_eq!(zone.local_type(4_118_083_200).utoff, 10 * 3600);
+
+    assert!(Zone::parse_tzif(b"TZif").is_none());
+    assert!(Zone::parse_tzif(b"").is_none());
+}
+
+#[test]
+fn test_posix_rules() {
+    let zone = Zone::parse_posix("EST5EDT,M3.2.0,M11.1.0").unwrap();
+    assert_eq!(zone, Zone::parse_posix("EST5EDT").unwrap());
+    assert_eq!(zone.local_type(1_615_705_199).abbrev, "EST");
+    assert_eq!(zone.local_type(1_615_705_200).abbrev, "EDT");
+    assert_eq!(zone.local_type(1_636_264_799).abbrev, "EDT");
+    assert_eq!(zone.local_type(1_636_264_800).abbrev, "EST");
+
+    let zone = Zone::parse_posix("IST-5:30").unwrap();
+    assert_eq!(zone.local_type(0).utoff, 5 * 3600 + 30 * 60);
+    let zone = Zone::parse_posix("<+0330>-3:30").unwrap();
+    assert_eq!(zone.local_type(0).abbrev, "+0330");
+    let zone = Zone::parse_posix("<-03>3<-02>,M3.5.0/-2,M10.5.0/-1").unwrap();
+    assert_eq!(zone.local_type(1_648_350_000).utoff, -2 * 3600);
+    // Southern hemisphere, and Julian days.
+    let zone = Zone::parse_posix("AEST-10AEDT,M10.1.0,M4.1.0/3").unwrap();
+    assert!(zone.local_type(1_609_459_200).is_dst);
+    assert!(!zone.local_type(1_625_097_600).is_dst);
+    let zone = Zone::parse_posix("XST3XDT,J60,300").unwrap();
+    assert!(!zone.local_type(5_097_600).is_dst);
+    assert!(zone.local_type(5_194_800).is_dst);
+
+    for tz in &["", "E5", "EST", "EST5EDT,M3.2.0", "EST5EDT,M13.1.0,M11.1.0", "EST25", "<EST5"] {
+        assert!(Zone::parse_posix(tz).is_none(), "{}", tz);
+    }
+}
+
+#[test]
+fn test_from_local() {
+    let zone = Zone::parse_posix("EST5EDT").unwrap();
+    // 2021-03-14 02:30 doesn't exist; it is taken as EST.
+    let local = 1_615_689_000;
+    assert_eq!(zone.from_local(local), Some(local + 5 * 3600));
+    // 2021-11-07 01:30 happens twice; the first one is EDT.
+    let local = 1_636_248_600;
+    assert_eq!(zone.from_local(local), Some(local + 4 * 3600));
+    let local = 1_636_282_800;
+    assert_eq!(zone.from_local(local), Some(local + 5 * 3600));
+}
@@ -0,0 +1,206 @@
+//! Unicode character properties.
+//!
+//! The tables are generated by build.rs from the Unicode Character
+//! Database files in admin/unidata.
+
+use std::cmp::Ordering;
+
+use multibyte::{Codepoint, MAX_5_BYTE_CHAR};
+
+/// Values of the Grapheme_Cluster_Break property, see Unicode
+/// Standard Annex #29.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphemeBreak {
+    Other,
+    Prepend,
+    CR,
+    LF,
+    Control,
+    Extend,
+    RegionalIndicator,
+    SpacingMark,
+    L,
+    V,
+    T,
+    LV,
+    LVT,
+    ZWJ,
+}
+
+/// The three kinds of case mapping.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Case {
+    Upper,
+    Lower,
+    Title,
+}
+
+/// The IDNA status of a character, see Unicode Technical Standard #46.
+/// Characters that are mapped are replaced by their mapping, ignored
+/// ones are removed, and disallowed ones make a domain name invalid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdnaStatus {
+    Valid,
+    Ignored,
+    Mapped,
+    Disallowed,
+}
+
+/// Values of the Bidi_Class property, see Unicode Standard Annex #9.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BidiClass {
+    L,
+    R,
+    AL,
+    EN,
+    ES,
+    ET,
+    AN,
+    CS,
+    NSM,
+    BN,
+    B,
+    S,
+    WS,
+    ON,
+    LRE,
+    LRO,
+    RLE,
+    RLO,
+    PDF,
+    LRI,
+    RLI,
+    FSI,
+    PDI,
+}
+
+include!(concat!(env!("OUT_DIR"), "/unicode_tables.rs"));
+
+/// Return the canonical combining class of character C.
+pub fn canonical_combining_class(c: Codepoint) -> u8 {
+    COMBINING_CLASSES
+        .binary_search_by_key(&c, |&(code, _)| code)
+        .map_or(0, |idx| COMBINING_CLASSES[idx].1)
+}
+
+/// Return the decomposition mapping of character C, and whether it is
+/// a compatibility mapping.  Hangul syllables are decomposed
+/// algorithmically and have no entry here.
+pub fn decomposition(c: Codepoint) -> Option<(&'static [Codepoint], bool)> {
+    DECOMPOSITIONS
+        .binary_search_by_key(&c, |&(code, _, _)| code)
+        .ok()
+        .map(|idx| (DECOMPOSITIONS[idx].2, DECOMPOSITIONS[idx].1))
+}
+
+/// Return the primary composite which canonically decomposes to
+/// FIRST followed by SECOND, if any.
+pub fn composition(first: Codepoint, second: Codepoint) -> Option<Codepoint> {
+    COMPOSITIONS
+        .binary_search_by_key(&(first, second), |&(a, b, _)| (a, b))
+        .ok()
+        .map(|idx| COMPOSITIONS[idx].2)
+}
+
+/// Compare the range START..END to the character C.
+fn range_ordering(start: Codepoint, end: Codepoint, c: Codepoint) -> Ordering {
+    if end < c {
+        Ordering::Less
+    } else if start > c {
+        Ordering::Greater
+    } else {
+        Ordering::Equal
+    }
+}
+
+/// Return true if C is in one of the sorted, disjoint RANGES.
+fn in_ranges(c: Codepoint, ranges: &[(u32, u32)]) -> bool {
+    ranges
+        .binary_search_by(|&(start, end)| range_ordering(start, end, c))
+        .is_ok()
+}
+
+/// Return the Grapheme_Cluster_Break property of character C.  Raw
+/// 8-bit bytes are treated like control characters, so that they are
+/// always clusters of their own.
+pub fn grapheme_break(c: Codepoint) -> GraphemeBreak {
+    if c > MAX_5_BYTE_CHAR {
+        return GraphemeBreak::Control;
+    }
+    GRAPHEME_BREAK_RANGES
+        .binary_search_by(|&(start, end, _)| range_ordering(start, end, c))
+        .map_or(GraphemeBreak::Other, |idx| GRAPHEME_BREAK_RANGES[idx].2)
+}
+
+pub fn is_extended_pictographic(c: Codepoint) -> bool {
+    in_ranges(c, EXTENDED_PICTOGRAPHIC_RANGES)
+}
+
+/// Return the simple (one-to-one) CASE mapping of character C, or C
+/// itself if it has none.
+pub fn simple_case_mapping(c: Codepoint, case: Case) -> Codepoint {
+    CASE_MAPPINGS
+        .binary_search_by_key(&c, |&(code, _, _, _)| code)
+        .map_or(c, |idx| {
+            let (_, upper, lower, title) = CASE_MAPPINGS[idx];
+            match case {
+                Case::Upper => upper,
+                Case::Lower => lower,
+                Case::Title => title,
+            }
+        })
+}
+
+/// Return the unconditional full CASE mapping of character C from
+/// SpecialCasing.txt, if there is one.  These are the mappings that
+/// may turn one character into several, like German sharp s.
+pub fn special_case_mapping(c: Codepoint, case: Case) -> Option<&'static [Codepoint]> {
+    SPECIAL_CASINGS
+        .binary_search_by_key(&c, |&(code, _, _, _)| code)
+        .ok()
+        .map(|idx| {
+            let (_, lower, title, upper) = SPECIAL_CASINGS[idx];
+            match case {
+                Case::Upper => upper,
+                Case::Lower => lower,
+                Case::Title => title,
+            }
+        })
+}
+
+/// Return the IDNA status of character C for nontransitional
+/// processing without the STD3 ASCII rules, and its mapping if it is
+/// mapped.
+pub fn idna_mapping(c: Codepoint) -> (IdnaStatus, &'static [Codepoint]) {
+    IDNA_MAPPINGS
+        .binary_search_by(|&(start, end, _, _)| range_ordering(start, end, c))
+        .map_or((IdnaStatus::Valid, &[][..]), |idx| {
+            let (_, _, status, mapping) = IDNA_MAPPINGS[idx];
+            (status, mapping)
+        })
+}
+
+/// Return the Bidi_Class property of character C.
+pub fn bidi_class(c: Codepoint) -> BidiClass {
+    BIDI_CLASS_RANGES
+        .binary_search_by(|&(start, end, _)| range_ordering(start, end, c))
+        .map_or(BidiClass::L, |idx| BIDI_CLASS_RANGES[idx].2)
+}
+
+/// Return true if C is a combining mark, i.e. its General_Category is
+/// Mn, Mc or Me.
+pub fn is_mark(c: Codepoint) -> bool {
+    in_ranges(c, MARK_RANGES)
+}
+
+/// Return true if C is a decimal digit, i.e. its General_Category is
+/// Nd.
+pub fn is_decimal_digit(c: Codepoint) -> bool {
+    in_ranges(c, DECIMAL_DIGIT_RANGES)
+}
+
+/// Return true if C is a letter, i.e. its General_Category is Lu, Ll,
+/// Lt, Lm or Lo.
+pub fn is_letter(c: Codepoint) -> bool {
+    in_ranges(c, LETTER_RANGES)
+}
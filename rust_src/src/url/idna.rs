@@ -0,0 +1,419 @@
+//! Internationalized domain names: Punycode, as described in RFC 3492,
+//! and the IDNA processing of Unicode Technical Standard #46, with the
+//! options used by the URL standard.
+
+use multibyte::Codepoint;
+use normalize::{normalize, NormalizationForm};
+use unidata::{bidi_class, canonical_combining_class, idna_mapping, is_mark, BidiClass, IdnaStatus};
+
+const BASE: u32 = 36;
+const T_MIN: u32 = 1;
+const T_MAX: u32 = 26;
+const SKEW: u32 = 38;
+const DAMP: u32 = 700;
+const INITIAL_BIAS: u32 = 72;
+const INITIAL_N: u32 = 0x80;
+
+const ZERO_WIDTH_NON_JOINER: Codepoint = 0x200C;
+const ZERO_WIDTH_JOINER: Codepoint = 0x200D;
+const VIRAMA_COMBINING_CLASS: u8 = 9;
+
+fn adapt(mut delta: u32, num_points: u32, first_time: bool) -> u32 {
+    delta /= if first_time { DAMP } else { 2 };
+    delta += delta / num_points;
+    let mut k = 0;
+    while delta > ((BASE - T_MIN) * T_MAX) / 2 {
+        delta /= BASE - T_MIN;
+        k += BASE;
+    }
+    k + (BASE - T_MIN + 1) * delta / (delta + SKEW)
+}
+
+fn threshold(k: u32, bias: u32) -> u32 {
+    if k <= bias {
+        T_MIN
+    } else if k >= bias + T_MAX {
+        T_MAX
+    } else {
+        k - bias
+    }
+}
+
+fn encode_digit(digit: u32) -> char {
+    let byte = if digit < 26 {
+        b'a' + digit as u8
+    } else {
+        b'0' + (digit - 26) as u8
+    };
+    byte as char
+}
+
+fn decode_digit(c: char) -> Option<u32> {
+    match c {
+        'a'...'z' => Some(c as u32 - 'a' as u32),
+        'A'...'Z' => Some(c as u32 - 'A' as u32),
+        '0'...'9' => Some(c as u32 - '0' as u32 + 26),
+        _ => None,
+    }
+}
+
+/// Return the Punycode encoding of INPUT, without the "xn--" prefix,
+/// or None if it is too long to be encoded.
+pub fn punycode_encode(input: &[Codepoint]) -> Option<String> {
+    let mut output: String = input
+        .iter()
+        .filter(|&&c| c < 0x80)
+        .map(|&c| c as u8 as char)
+        .collect();
+    let basic_length = output.len() as u32;
+    if basic_length > 0 {
+        output.push('-');
+    }
+    let mut n = INITIAL_N;
+    let mut delta: u32 = 0;
+    let mut bias = INITIAL_BIAS;
+    let mut handled = basic_length;
+    while (handled as usize) < input.len() {
+        let m = input.iter().cloned().filter(|&c| c >= n).min().unwrap();
+        delta = delta.checked_add((m - n).checked_mul(handled + 1)?)?;
+        n = m;
+        for &c in input {
+            if c < n {
+                delta = delta.checked_add(1)?;
+            }
+            if c == n {
+                let mut q = delta;
+                let mut k = BASE;
+                loop {
+                    let t = threshold(k, bias);
+                    if q < t {
+                        break;
+                    }
+                    output.push(encode_digit(t + (q - t) % (BASE - t)));
+                    q = (q - t) / (BASE - t);
+                    k += BASE;
+                }
+                output.push(encode_digit(q));
+                bias = adapt(delta, handled + 1, handled == basic_length);
+                delta = 0;
+                handled += 1;
+            }
+        }
+        delta += 1;
+        n += 1;
+    }
+    Some(output)
+}
+
+/// Return the characters encoded by INPUT, Punycode without the "xn--"
+/// prefix, or None if it is invalid.
+pub fn punycode_decode(input: &str) -> Option<Vec<Codepoint>> {
+    let (basic, extended) = match input.rfind('-') {
+        Some(idx) => (&input[..idx], &input[idx + 1..]),
+        None => ("", input),
+    };
+    if !basic.is_ascii() {
+        return None;
+    }
+    let mut output: Vec<Codepoint> = basic.chars().map(|c| c as Codepoint).collect();
+    let mut n = INITIAL_N;
+    let mut i: u32 = 0;
+    let mut bias = INITIAL_BIAS;
+    let mut digits = extended.chars();
+    while digits.as_str() != "" {
+        let old_i = i;
+        let mut w: u32 = 1;
+        let mut k = BASE;
+        loop {
+            let digit = decode_digit(digits.next()?)?;
+            i = i.checked_add(digit.checked_mul(w)?)?;
+            let t = threshold(k, bias);
+            if digit < t {
+                break;
+            }
+            w = w.checked_mul(BASE - t)?;
+            k += BASE;
+        }
+        let length = output.len() as u32 + 1;
+        bias = adapt(i - old_i, length, old_i == 0);
+        n = n.checked_add(i / length)?;
+        i %= length;
+        if n > 0x10FFFF || (n >= 0xD800 && n <= 0xDFFF) {
+            return None;
+        }
+        output.insert(i as usize, n);
+        i += 1;
+    }
+    Some(output)
+}
+
+/// Return true if CHARS start with "xn--", the prefix of labels encoded
+/// with Punycode.
+fn has_ace_prefix(chars: &[Codepoint]) -> bool {
+    chars.len() >= 4 && chars[..4] == ['x' as Codepoint, 'n' as Codepoint, 0x2D, 0x2D]
+}
+
+/// Return true if the non-empty LABEL satisfies the Bidi rule of RFC
+/// 5893, section 2.
+fn satisfies_bidi_rule(label: &[Codepoint]) -> bool {
+    let classes: Vec<BidiClass> = label.iter().map(|&c| bidi_class(c)).collect();
+    let last = classes
+        .iter()
+        .rev()
+        .find(|&&class| class != BidiClass::NSM)
+        .cloned();
+    match classes[0] {
+        BidiClass::R | BidiClass::AL => {
+            let allowed = classes.iter().all(|&class| match class {
+                BidiClass::R
+                | BidiClass::AL
+                | BidiClass::AN
+                | BidiClass::EN
+                | BidiClass::ES
+                | BidiClass::CS
+                | BidiClass::ET
+                | BidiClass::ON
+                | BidiClass::BN
+                | BidiClass::NSM => true,
+                _ => false,
+            });
+            let numbers = classes.contains(&BidiClass::EN) && classes.contains(&BidiClass::AN);
+            allowed && !numbers && match last {
+                Some(BidiClass::R) | Some(BidiClass::AL) | Some(BidiClass::EN)
+                | Some(BidiClass::AN) => true,
+                _ => false,
+            }
+        }
+        BidiClass::L => {
+            let allowed = classes.iter().all(|&class| match class {
+                BidiClass::L
+                | BidiClass::EN
+                | BidiClass::ES
+                | BidiClass::CS
+                | BidiClass::ET
+                | BidiClass::ON
+                | BidiClass::BN
+                | BidiClass::NSM => true,
+                _ => false,
+            });
+            allowed && match last {
+                Some(BidiClass::L) | Some(BidiClass::EN) => true,
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+/// Return true if LABEL meets the validity criteria of UTS #46,
+/// section 4.1, for nontransitional processing with CheckHyphens false
+/// and CheckJoiners true.
+fn is_valid_label(label: &[Codepoint]) -> bool {
+    if has_ace_prefix(label) || label.first().map_or(false, |&c| is_mark(c)) {
+        return false;
+    }
+    label.iter().enumerate().all(|(idx, &c)| {
+        if c == ZERO_WIDTH_NON_JOINER || c == ZERO_WIDTH_JOINER {
+            // A joiner is allowed after a virama.  The other context in
+            // which a non-joiner is allowed depends on the Joining_Type
+            // property, which we don't have, so it is rejected there.
+            idx > 0 && canonical_combining_class(label[idx - 1]) == VIRAMA_COMBINING_CLASS
+        } else {
+            c != '.' as Codepoint && idna_mapping(c).0 == IdnaStatus::Valid
+        }
+    })
+}
+
+/// Map and normalize DOMAIN, and split it into labels, decoding those
+/// encoded with Punycode, as described in UTS #46, section 4.  Return
+/// the labels and whether there was an error.
+fn process(domain: &str) -> (Vec<Vec<Codepoint>>, bool) {
+    let mut error = false;
+    let mut mapped = Vec::with_capacity(domain.len());
+    for c in domain.chars() {
+        match idna_mapping(c as Codepoint) {
+            (IdnaStatus::Valid, _) => mapped.push(c as Codepoint),
+            (IdnaStatus::Ignored, _) => (),
+            (IdnaStatus::Mapped, mapping) => mapped.extend_from_slice(mapping),
+            (IdnaStatus::Disallowed, _) => {
+                error = true;
+                mapped.push(c as Codepoint);
+            }
+        }
+    }
+    let normalized = normalize(&mapped, NormalizationForm::NFC);
+    let mut labels: Vec<Vec<Codepoint>> = normalized
+        .split(|&c| c == '.' as Codepoint)
+        .map(|label| label.to_vec())
+        .collect();
+    for label in &mut labels {
+        if has_ace_prefix(label) {
+            if label.iter().any(|&c| c >= 0x80) {
+                error = true;
+                continue;
+            }
+            let ascii: String = label[4..].iter().map(|&c| c as u8 as char).collect();
+            let decoded = match punycode_decode(&ascii) {
+                Some(decoded) => decoded,
+                None => {
+                    error = true;
+                    continue;
+                }
+            };
+            if decoded.iter().all(|&c| c < 0x80) {
+                error = true;
+                continue;
+            }
+            if normalize(&decoded, NormalizationForm::NFC) != decoded {
+                error = true;
+            }
+            *label = decoded;
+        }
+        if !is_valid_label(label) {
+            error = true;
+        }
+    }
+    let is_bidi_domain = labels.iter().any(|label| {
+        label.iter().any(|&c| match bidi_class(c) {
+            BidiClass::R | BidiClass::AL | BidiClass::AN => true,
+            _ => false,
+        })
+    });
+    if is_bidi_domain
+        && !labels
+            .iter()
+            .all(|label| label.is_empty() || satisfies_bidi_rule(label))
+    {
+        error = true;
+    }
+    (labels, error)
+}
+
+/// Return DOMAIN converted to ASCII as described in UTS #46, section
+/// 4.2, or None if it isn't a valid domain name.
+pub fn domain_to_ascii(domain: &str) -> Option<String> {
+    // ASCII labels are only mapped to lower case, unless they are
+    // encoded with Punycode and must be validated.
+    if domain.is_ascii()
+        && !domain
+            .split('.')
+            .any(|label| label.len() >= 4 && label[..4].eq_ignore_ascii_case("xn--"))
+    {
+        return Some(domain.to_ascii_lowercase());
+    }
+    let (labels, error) = process(domain);
+    if error {
+        return None;
+    }
+    let mut ascii = Vec::with_capacity(labels.len());
+    for label in labels {
+        if label.iter().all(|&c| c < 0x80) {
+            ascii.push(label.iter().map(|&c| c as u8 as char).collect());
+        } else {
+            ascii.push(format!("xn--{}", punycode_encode(&label)?));
+        }
+    }
+    Some(ascii.join("."))
+}
+
+/// Return DOMAIN converted to Unicode as described in UTS #46, section
+/// 4.3.  Labels which can't be decoded are left alone.
+pub fn domain_to_unicode(domain: &str) -> String {
+    let (labels, _) = process(domain);
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|label| {
+            label
+                .iter()
+                .map(|&c| ::std::char::from_u32(c).unwrap_or('\u{FFFD}'))
+                .collect()
+        })
+        .collect();
+    labels.join(".")
+}
+
+#[test]
+fn test_punycode() {
+    // The samples of RFC 3492, section 7.1.
+    let samples: &[(&str, &str)] = &[
+        (
+            "\u{644}\u{64A}\u{647}\u{645}\u{627}\u{628}\u{62A}\u{643}\u{644}\u{645}\u{648}\u{634}\
+             \u{639}\u{631}\u{628}\u{64A}\u{61F}",
+            "egbpdaj6bu4bxfgehfvwxn",
+        ),
+        (
+            "\u{4ED6}\u{4EEC}\u{4E3A}\u{4EC0}\u{4E48}\u{4E0D}\u{8BF4}\u{4E2D}\u{6587}",
+            "ihqwcrb4cv8a8dqg056pqjye",
+        ),
+        (
+            "\u{4ED6}\u{5011}\u{7232}\u{4EC0}\u{9EBD}\u{4E0D}\u{8AAA}\u{4E2D}\u{6587}",
+            "ihqwctvzc91f659drss3x8bo0yb",
+        ),
+        (
+            "Pro\u{10D}prost\u{11B}nemluv\u{ED}\u{10D}esky",
+            "Proprostnemluvesky-uyb24dma41a",
+        ),
+        (
+            "\u{5DC}\u{5DE}\u{5D4}\u{5D4}\u{5DD}\u{5E4}\u{5E9}\u{5D5}\u{5D8}\u{5DC}\u{5D0}\
+             \u{5DE}\u{5D3}\u{5D1}\u{5E8}\u{5D9}\u{5DD}\u{5E2}\u{5D1}\u{5E8}\u{5D9}\u{5EA}",
+            "4dbcagdahymbxekheh6e0a7fei0b",
+        ),
+        (
+            "\u{305D}\u{306E}\u{30B9}\u{30D4}\u{30FC}\u{30C9}\u{3067}",
+            "d9juau41awczczp",
+        ),
+        (
+            "\u{043F}\u{043E}\u{0447}\u{0435}\u{043C}\u{0443}\u{0436}\u{0435}\u{043E}\u{043D}\
+             \u{0438}\u{043D}\u{0435}\u{0433}\u{043E}\u{0432}\u{043E}\u{0440}\u{044F}\u{0442}\
+             \u{043F}\u{043E}\u{0440}\u{0443}\u{0441}\u{0441}\u{043A}\u{0438}",
+            "b1abfaaepdrnnbgefbadotcwatmq2g4l",
+        ),
+        (
+            "Porqu\u{E9}nopuedensimplementehablarenEspa\u{F1}ol",
+            "PorqunopuedensimplementehablarenEspaol-fmd56a",
+        ),
+        (
+            "T\u{1EA1}isaoh\u{1ECD}kh\u{F4}ngth\u{1EC3}ch\u{1EC9}n\u{F3}iti\u{1EBF}ngVi\u{1EC7}t",
+            "TisaohkhngthchnitingVit-kjcr8268qyxafd2f1b9g",
+        ),
+        ("3\u{5E74}B\u{7D44}\u{91D1}\u{516B}\u{5148}\u{751F}", "3B-ww4c5e180e575a65lsy2b"),
+        ("-> $1.00 <-", "-> $1.00 <--"),
+    ];
+    for &(decoded, encoded) in samples {
+        let chars: Vec<Codepoint> = decoded.chars().map(|c| c as Codepoint).collect();
+        assert_eq!(punycode_encode(&chars).unwrap(), encoded);
+        assert_eq!(punycode_decode(encoded).unwrap(), chars);
+    }
+    assert!(punycode_decode("a\u{E9}-b").is_none());
+    assert!(punycode_decode("99999999").is_none());
+    assert!(punycode_decode("x!").is_none());
+}
+
+#[test]
+fn test_domain_to_ascii() {
+    assert_eq!(domain_to_ascii("Example.COM").unwrap(), "example.com");
+    assert_eq!(domain_to_ascii("B\u{FC}cher.example").unwrap(), "xn--bcher-kva.example");
+    assert_eq!(domain_to_ascii("fa\u{DF}.de").unwrap(), "xn--fa-hia.de");
+    assert_eq!(domain_to_ascii("\u{FF27}\u{FF4F}.com").unwrap(), "go.com");
+    assert_eq!(domain_to_ascii("www.foo\u{3002}bar.com").unwrap(), "www.foo.bar.com");
+    assert_eq!(domain_to_ascii("a\u{AD}b").unwrap(), "ab");
+    assert_eq!(domain_to_ascii("XN--bcher-kva.de").unwrap(), "xn--bcher-kva.de");
+    assert!(domain_to_ascii("xn--").is_none());
+    assert!(domain_to_ascii("xn--abc").is_none());
+    assert!(domain_to_ascii("\u{FDD0}zyx.com").is_none());
+    assert!(domain_to_ascii("\u{301}a.com").is_none());
+    assert!(domain_to_ascii("a\u{200D}b").is_none());
+    // A Bidi domain name whose labels must all satisfy the Bidi rule.
+    assert_eq!(domain_to_ascii("\u{5D0}\u{5D1}.com").unwrap(), "xn--4dbc.com");
+    assert!(domain_to_ascii("\u{5D0}1a.com").is_none());
+}
+
+#[test]
+fn test_domain_to_unicode() {
+    assert_eq!(domain_to_unicode("xn--bcher-kva.example"), "b\u{FC}cher.example");
+    assert_eq!(domain_to_unicode("EXAMPLE.com"), "example.com");
+    assert_eq!(
+        domain_to_unicode("xn--ihqwcrb4cv8a8dqg056pqjye.xn--"),
+        "\u{4ED6}\u{4EEC}\u{4E3A}\u{4EC0}\u{4E48}\u{4E0D}\u{8BF4}\u{4E2D}\u{6587}.xn--"
+    );
+}
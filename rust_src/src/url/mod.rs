@@ -0,0 +1,174 @@
+//! URLs: parsing, percent-encoding and internationalized domain names.
+
+use libc::{c_char, ptrdiff_t};
+
+use remacs_macros::lisp_fn;
+use remacs_sys::{make_string, EmacsInt, Frecord, Qarrayp};
+
+use codec::make_unibyte;
+use coding::encode_utf_8_text;
+use lisp::{intern, LispObject};
+use lisp::defsubr;
+
+mod idna;
+mod parser;
+
+use self::parser::{percent_decode, percent_encode, Url};
+
+/// Return the text of STRING, with characters which can't be encoded
+/// in UTF-8 replaced by U+FFFD.
+fn string_text(string: LispObject) -> String {
+    let string = string.as_string_or_error();
+    let bytes = encode_utf_8_text(string.as_slice(), string.is_multibyte());
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn make_lisp_string(text: &str) -> LispObject {
+    let length = text.len() as ptrdiff_t;
+    LispObject::from(unsafe { make_string(text.as_ptr() as *const c_char, length) })
+}
+
+/// Return TEXT as a Lisp string, or nil if it is empty.
+fn make_lisp_string_or_nil(text: &str) -> LispObject {
+    if text.is_empty() {
+        LispObject::constant_nil()
+    } else {
+        make_lisp_string(text)
+    }
+}
+
+/// Return whether BYTE is an unreserved character of RFC 3986.
+fn is_unreserved(byte: u8) -> bool {
+    match byte {
+        b'A'...b'Z' | b'a'...b'z' | b'0'...b'9' | b'-' | b'_' | b'.' | b'~' => true,
+        _ => false,
+    }
+}
+
+/// Parse URL according to the URL Living Standard, the way web
+/// browsers do, and return a `url' struct of its parts, or nil if it
+/// isn't a valid URL.  If BASE is non-nil, it is the URL that URL is
+/// relative to.
+///
+/// Unlike `url-generic-parse-url', the result is normalized: the
+/// scheme and the host are downcased, dot segments are removed from
+/// the path, and characters which aren't allowed are
+/// percent-encoded.  The TYPE slot is the scheme, USER and PASSWORD
+/// are nil when empty, HOST is nil when the URL has none, PORTSPEC
+/// is nil when the port is the default one of the scheme, FILENAME
+/// is the path and the query, and TARGET is the fragment.  FULLNESS
+/// is t when the URL has a host.
+#[lisp_fn(min = "1")]
+pub fn url_parse_string(url: LispObject, base: LispObject) -> LispObject {
+    let url = string_text(url);
+    let url = if base.is_nil() {
+        Url::parse(&url, None)
+    } else {
+        Url::parse(&string_text(base), None).and_then(|base| Url::parse(&url, Some(&base)))
+    };
+    let url = match url {
+        Some(url) => url,
+        None => return LispObject::constant_nil(),
+    };
+    let mut filename = url.pathname();
+    if let Some(ref query) = url.query {
+        filename.push('?');
+        filename.push_str(query);
+    }
+    let nil = LispObject::constant_nil();
+    let host = url.host
+        .as_ref()
+        .map_or(nil, |host| make_lisp_string(&host.serialize()));
+    let port = url.port
+        .map_or(nil, |port| LispObject::from_natnum(EmacsInt::from(port)));
+    let target = url.fragment
+        .as_ref()
+        .map_or(nil, |fragment| make_lisp_string(fragment));
+    let mut args = [
+        intern("url").to_raw(),
+        make_lisp_string(&url.scheme).to_raw(),
+        make_lisp_string_or_nil(&url.username).to_raw(),
+        make_lisp_string_or_nil(&url.password).to_raw(),
+        host.to_raw(),
+        port.to_raw(),
+        make_lisp_string(&filename).to_raw(),
+        target.to_raw(),
+        nil.to_raw(),
+        LispObject::from_bool(url.host.is_some()).to_raw(),
+        nil.to_raw(),
+        LispObject::constant_t().to_raw(),
+        LispObject::constant_t().to_raw(),
+    ];
+    LispObject::from(unsafe { Frecord(args.len() as ptrdiff_t, args.as_mut_ptr()) })
+}
+
+/// Percent-encode STRING and return the result as a unibyte string.
+/// If STRING is multibyte, it is first encoded in UTF-8.  Each byte
+/// which is an allowed character is left as is, and all other bytes
+/// become "%" followed by two upper-case hex digits.
+///
+/// If ALLOWED-CHARS is nil, the allowed characters are the unreserved
+/// characters of RFC 3986: letters, digits, and "-_.~".  Otherwise,
+/// it is either a string of the allowed characters, or a vector whose
+/// Nth element is non-nil if byte N is allowed, such as those made by
+/// `url--allowed-chars'.
+#[lisp_fn(min = "1")]
+pub fn url_percent_encode(string: LispObject, allowed_chars: LispObject) -> LispObject {
+    let string = string.as_string_or_error();
+    let bytes = encode_utf_8_text(string.as_slice(), string.is_multibyte());
+    let mut allowed = [false; 256];
+    if allowed_chars.is_nil() {
+        for byte in 0..256 {
+            allowed[byte] = is_unreserved(byte as u8);
+        }
+    } else if let Some(vector) = allowed_chars.as_vector() {
+        for (byte, element) in vector.as_slice().iter().take(256).enumerate() {
+            allowed[byte] = element.is_not_nil();
+        }
+    } else if allowed_chars.is_string() {
+        for c in string_text(allowed_chars).chars() {
+            if (c as u32) < 0x80 {
+                allowed[c as usize] = true;
+            }
+        }
+    } else {
+        wrong_type!(Qarrayp, allowed_chars);
+    }
+    let mut encoded = String::with_capacity(bytes.len());
+    percent_encode(&bytes, |byte| !allowed[byte as usize], &mut encoded);
+    make_unibyte(encoded.as_bytes())
+}
+
+/// Decode the percent-encoded bytes of STRING, "%" followed by two hex
+/// digits, and return the result as a unibyte string.  A "%" which
+/// isn't followed by two hex digits is left as is.  If STRING is
+/// multibyte, it is first encoded in UTF-8; use `decode-coding-string'
+/// to decode the result.
+#[lisp_fn]
+pub fn url_percent_decode(string: LispObject) -> LispObject {
+    let string = string.as_string_or_error();
+    let bytes = encode_utf_8_text(string.as_slice(), string.is_multibyte());
+    make_unibyte(&percent_decode(&bytes))
+}
+
+/// Return the ASCII form of the domain name DOMAIN, with its labels
+/// mapped and normalized as described in Unicode Technical Standard
+/// #46, and those which aren't ASCII encoded with Punycode.  Return
+/// nil if DOMAIN isn't a valid internationalized domain name.
+#[lisp_fn]
+pub fn url_idna_encode(domain: LispObject) -> LispObject {
+    match idna::domain_to_ascii(&string_text(domain)) {
+        Some(ascii) => make_unibyte(ascii.as_bytes()),
+        None => LispObject::constant_nil(),
+    }
+}
+
+/// Return the Unicode form of the domain name DOMAIN, decoding the
+/// labels which are encoded with Punycode, the ones starting with
+/// "xn--".  Labels which can't be decoded are left as is.
+#[lisp_fn]
+pub fn url_idna_decode(domain: LispObject) -> LispObject {
+    make_lisp_string(&idna::domain_to_unicode(&string_text(domain)))
+}
+
+include!(concat!(env!("OUT_DIR"), "/url_exports.rs"));
@@ -0,0 +1,1160 @@
+//! Parsing URLs as described in the URL Living Standard,
+//! https://url.spec.whatwg.org/, the way web browsers do.  Only the
+//! basic URL parser is implemented; there is no state override.
+
+use super::idna::domain_to_ascii;
+
+/// The sets of bytes which are percent-encoded in the components of a
+/// URL.  Each set includes the ones before it, except that the query
+/// sets don't include each other.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EncodeSet {
+    C0Control,
+    Fragment,
+    Query,
+    SpecialQuery,
+    Path,
+    Userinfo,
+}
+
+impl EncodeSet {
+    pub fn contains(self, byte: u8) -> bool {
+        match self {
+            EncodeSet::C0Control => byte < 0x20 || byte > 0x7E,
+            EncodeSet::Fragment => {
+                EncodeSet::C0Control.contains(byte) || b" \"<>`".contains(&byte)
+            }
+            EncodeSet::Query => EncodeSet::C0Control.contains(byte) || b" \"#<>".contains(&byte),
+            EncodeSet::SpecialQuery => EncodeSet::Query.contains(byte) || byte == b'\'',
+            EncodeSet::Path => EncodeSet::Query.contains(byte) || b"?`{}".contains(&byte),
+            EncodeSet::Userinfo => {
+                EncodeSet::Path.contains(byte) || b"/:;=@[\\]^|".contains(&byte)
+            }
+        }
+    }
+}
+
+/// Append BYTES to OUT, percent-encoding those for which ENCODE
+/// returns true.
+pub fn percent_encode<F>(bytes: &[u8], encode: F, out: &mut String)
+where
+    F: Fn(u8) -> bool,
+{
+    const HEX_DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+    for &byte in bytes {
+        if encode(byte) {
+            out.push('%');
+            out.push(HEX_DIGITS[(byte >> 4) as usize] as char);
+            out.push(HEX_DIGITS[(byte & 0xF) as usize] as char);
+        } else {
+            out.push(byte as char);
+        }
+    }
+}
+
+/// Append the UTF-8 encoding of C to OUT, percent-encoding the bytes
+/// in SET.
+fn encode_char(c: char, set: EncodeSet, out: &mut String) {
+    let mut bytes = [0; 4];
+    percent_encode(c.encode_utf8(&mut bytes).as_bytes(), |byte| set.contains(byte), out);
+}
+
+fn is_alpha(c: char) -> bool {
+    match c {
+        'a'...'z' | 'A'...'Z' => true,
+        _ => false,
+    }
+}
+
+fn is_digit(c: char) -> bool {
+    c >= '0' && c <= '9'
+}
+
+fn hex_value(byte: u8) -> Option<u8> {
+    (byte as char).to_digit(16).map(|value| value as u8)
+}
+
+/// Return BYTES with the percent-encoded bytes decoded.  A "%" which
+/// isn't followed by two hex digits is left alone.
+pub fn percent_decode(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        if bytes[idx] == b'%' && idx + 2 < bytes.len() {
+            if let (Some(high), Some(low)) = (hex_value(bytes[idx + 1]), hex_value(bytes[idx + 2]))
+            {
+                out.push(high << 4 | low);
+                idx += 3;
+                continue;
+            }
+        }
+        out.push(bytes[idx]);
+        idx += 1;
+    }
+    out
+}
+
+/// The host of a URL.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Host {
+    Domain(String),
+    Ipv4(u32),
+    Ipv6([u16; 8]),
+    Opaque(String),
+    Empty,
+}
+
+impl Host {
+    pub fn serialize(&self) -> String {
+        match *self {
+            Host::Domain(ref name) | Host::Opaque(ref name) => name.clone(),
+            Host::Ipv4(address) => format!(
+                "{}.{}.{}.{}",
+                address >> 24,
+                address >> 16 & 0xFF,
+                address >> 8 & 0xFF,
+                address & 0xFF
+            ),
+            Host::Ipv6(ref pieces) => serialize_ipv6(pieces),
+            Host::Empty => String::new(),
+        }
+    }
+}
+
+/// Return the IPv6 address PIECES in brackets, with the first longest
+/// run of two or more zero pieces compressed.
+fn serialize_ipv6(pieces: &[u16; 8]) -> String {
+    let mut compress = None;
+    let mut longest = 1;
+    let mut idx = 0;
+    while idx < 8 {
+        let length = pieces[idx..].iter().take_while(|&&piece| piece == 0).count();
+        if length > longest {
+            compress = Some(idx);
+            longest = length;
+        }
+        idx += length.max(1);
+    }
+    let mut out = String::from("[");
+    let mut idx = 0;
+    while idx < 8 {
+        if compress == Some(idx) {
+            out.push_str(if idx == 0 { "::" } else { ":" });
+            idx += longest;
+            continue;
+        }
+        out.push_str(&format!("{:x}", pieces[idx]));
+        if idx != 7 {
+            out.push(':');
+        }
+        idx += 1;
+    }
+    out.push(']');
+    out
+}
+
+fn is_forbidden_host_char(c: char) -> bool {
+    match c {
+        '\0' | '\t' | '\n' | '\r' | ' ' | '#' | '/' | ':' | '<' | '>' | '?' | '@' | '[' | '\\'
+        | ']' | '^' | '|' => true,
+        _ => false,
+    }
+}
+
+fn is_forbidden_domain_char(c: char) -> bool {
+    is_forbidden_host_char(c) || c <= '\u{1F}' || c == '%' || c == '\u{7F}'
+}
+
+/// Parse INPUT as a host; if IS_OPAQUE, it is the host of a URL whose
+/// scheme isn't special.
+fn parse_host(input: &str, is_opaque: bool) -> Option<Host> {
+    if input.starts_with('[') {
+        if !input.ends_with(']') || input.len() < 2 {
+            return None;
+        }
+        return parse_ipv6(&input[1..input.len() - 1]).map(Host::Ipv6);
+    }
+    if is_opaque {
+        if input.chars().any(is_forbidden_host_char) {
+            return None;
+        }
+        let mut host = String::new();
+        percent_encode(input.as_bytes(), |byte| EncodeSet::C0Control.contains(byte), &mut host);
+        return Some(if host.is_empty() {
+            Host::Empty
+        } else {
+            Host::Opaque(host)
+        });
+    }
+    let domain = String::from_utf8_lossy(&percent_decode(input.as_bytes())).into_owned();
+    let ascii = domain_to_ascii(&domain)?;
+    if ascii.is_empty() || ascii.chars().any(is_forbidden_domain_char) {
+        return None;
+    }
+    if ends_in_number(&ascii) {
+        return parse_ipv4(&ascii).map(Host::Ipv4);
+    }
+    Some(Host::Domain(ascii))
+}
+
+/// Return the parts of the IPv4 address INPUT, without the empty part
+/// after a trailing dot.
+fn ipv4_parts(input: &str) -> Vec<&str> {
+    let mut parts: Vec<&str> = input.split('.').collect();
+    if parts.len() > 1 && parts.last() == Some(&"") {
+        parts.pop();
+    }
+    parts
+}
+
+/// Return true if the last label of the domain INPUT is a number, so
+/// that INPUT must be an IPv4 address.
+fn ends_in_number(input: &str) -> bool {
+    let last = ipv4_parts(input).pop().unwrap_or("");
+    !last.is_empty() && last.chars().all(is_digit)
+        || parse_ipv4_number(last).is_some()
+}
+
+/// Parse a part of an IPv4 address, a decimal, octal or hexadecimal
+/// number.  Numbers too large for a part are returned as u64::MAX.
+fn parse_ipv4_number(mut input: &str) -> Option<u64> {
+    if input.is_empty() {
+        return None;
+    }
+    let mut radix = 10;
+    if input.starts_with("0x") || input.starts_with("0X") {
+        radix = 16;
+        input = &input[2..];
+    } else if input.len() > 1 && input.starts_with('0') {
+        radix = 8;
+        input = &input[1..];
+    }
+    let mut number: u64 = 0;
+    for c in input.chars() {
+        let digit = c.to_digit(radix)?;
+        number = number
+            .saturating_mul(u64::from(radix))
+            .saturating_add(u64::from(digit));
+    }
+    Some(number)
+}
+
+fn parse_ipv4(input: &str) -> Option<u32> {
+    let parts = ipv4_parts(input);
+    if parts.len() > 4 {
+        return None;
+    }
+    let mut numbers = Vec::with_capacity(4);
+    for part in &parts {
+        numbers.push(parse_ipv4_number(part)?);
+    }
+    let last = numbers.pop().unwrap();
+    if numbers.iter().any(|&number| number > 255) || last >= 1 << (8 * (5 - parts.len())) {
+        return None;
+    }
+    let address = numbers
+        .iter()
+        .enumerate()
+        .fold(last, |address, (idx, &number)| address + (number << (8 * (3 - idx))));
+    Some(address as u32)
+}
+
+fn parse_ipv6(input: &str) -> Option<[u16; 8]> {
+    let input = input.as_bytes();
+    let mut address = [0u16; 8];
+    let mut piece_index = 0;
+    let mut compress = None;
+    let mut pointer = 0;
+    if input.first() == Some(&b':') {
+        if input.get(1) != Some(&b':') {
+            return None;
+        }
+        pointer += 2;
+        piece_index += 1;
+        compress = Some(piece_index);
+    }
+    while pointer < input.len() {
+        if piece_index == 8 {
+            return None;
+        }
+        if input[pointer] == b':' {
+            if compress.is_some() {
+                return None;
+            }
+            pointer += 1;
+            piece_index += 1;
+            compress = Some(piece_index);
+            continue;
+        }
+        let mut value: u16 = 0;
+        let mut length = 0;
+        while length < 4 && pointer < input.len() {
+            match hex_value(input[pointer]) {
+                Some(digit) => value = value * 0x10 + u16::from(digit),
+                None => break,
+            }
+            pointer += 1;
+            length += 1;
+        }
+        match input.get(pointer) {
+            Some(&b'.') => {
+                // An IPv4 address in the last two pieces.
+                if length == 0 || piece_index > 6 {
+                    return None;
+                }
+                pointer -= length;
+                let mut numbers_seen = 0;
+                while pointer < input.len() {
+                    if numbers_seen > 0 {
+                        if input[pointer] == b'.' && numbers_seen < 4 {
+                            pointer += 1;
+                        } else {
+                            return None;
+                        }
+                    }
+                    let mut ipv4_piece: Option<u16> = None;
+                    while pointer < input.len() && is_digit(input[pointer] as char) {
+                        let number = u16::from(input[pointer] - b'0');
+                        ipv4_piece = match ipv4_piece {
+                            None => Some(number),
+                            Some(0) => return None,
+                            Some(piece) => Some(piece * 10 + number),
+                        };
+                        if ipv4_piece > Some(255) {
+                            return None;
+                        }
+                        pointer += 1;
+                    }
+                    address[piece_index] = address[piece_index] * 0x100 + ipv4_piece?;
+                    numbers_seen += 1;
+                    if numbers_seen == 2 || numbers_seen == 4 {
+                        piece_index += 1;
+                    }
+                }
+                if numbers_seen != 4 {
+                    return None;
+                }
+                break;
+            }
+            Some(&b':') => {
+                pointer += 1;
+                if pointer == input.len() {
+                    return None;
+                }
+            }
+            Some(_) => return None,
+            None => (),
+        }
+        address[piece_index] = value;
+        piece_index += 1;
+    }
+    match compress {
+        Some(compress) => {
+            let mut swaps = piece_index - compress;
+            piece_index = 7;
+            while piece_index != 0 && swaps > 0 {
+                address.swap(piece_index, compress + swaps - 1);
+                piece_index -= 1;
+                swaps -= 1;
+            }
+        }
+        None if piece_index != 8 => return None,
+        None => (),
+    }
+    Some(address)
+}
+
+/// Return the default port of SCHEME if it is special, i.e. one of
+/// the schemes that the standard treats specially.  The port of "file"
+/// URLs is None.
+fn special_scheme_port(scheme: &str) -> Option<Option<u16>> {
+    match scheme {
+        "ftp" => Some(Some(21)),
+        "file" => Some(None),
+        "http" | "ws" => Some(Some(80)),
+        "https" | "wss" => Some(Some(443)),
+        _ => None,
+    }
+}
+
+/// Return true if CHARS start with a Windows drive letter, like "C:"
+/// or "c|", which is followed by nothing or by one of "/\?#".
+fn starts_with_drive_letter(chars: &[char]) -> bool {
+    chars.len() >= 2 && is_drive_letter(&chars[..2], false)
+        && (chars.len() == 2 || "/\\?#".contains(chars[2]))
+}
+
+/// Return true if CHARS are a Windows drive letter, or a normalized
+/// one ending in ":" if NORMALIZED.
+fn is_drive_letter(chars: &[char], normalized: bool) -> bool {
+    chars.len() == 2 && is_alpha(chars[0])
+        && (chars[1] == ':' || !normalized && chars[1] == '|')
+}
+
+fn is_drive_letter_str(string: &str, normalized: bool) -> bool {
+    is_drive_letter(&string.chars().collect::<Vec<char>>(), normalized)
+}
+
+fn is_single_dot(segment: &str) -> bool {
+    segment == "." || segment.eq_ignore_ascii_case("%2e")
+}
+
+fn is_double_dot(segment: &str) -> bool {
+    match segment.to_ascii_lowercase().as_str() {
+        ".." | ".%2e" | "%2e." | "%2e%2e" => true,
+        _ => false,
+    }
+}
+
+/// The path of a URL: an opaque string for URLs like "mailto:x@y", or
+/// a list of segments.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Path {
+    Opaque(String),
+    Segments(Vec<String>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    SchemeStart,
+    Scheme,
+    NoScheme,
+    SpecialRelativeOrAuthority,
+    PathOrAuthority,
+    Relative,
+    RelativeSlash,
+    SpecialAuthoritySlashes,
+    SpecialAuthorityIgnoreSlashes,
+    Authority,
+    Host,
+    Port,
+    File,
+    FileSlash,
+    FileHost,
+    PathStart,
+    Path,
+    OpaquePath,
+    Query,
+    Fragment,
+}
+
+/// A parsed URL.  The components are percent-encoded as needed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Url {
+    pub scheme: String,
+    pub username: String,
+    pub password: String,
+    pub host: Option<Host>,
+    /// The port, unless it is the default port of the scheme.
+    pub port: Option<u16>,
+    pub path: Path,
+    pub query: Option<String>,
+    pub fragment: Option<String>,
+}
+
+impl Url {
+    fn is_special(&self) -> bool {
+        special_scheme_port(&self.scheme).is_some()
+    }
+
+    fn segments(&mut self) -> &mut Vec<String> {
+        match self.path {
+            Path::Segments(ref mut segments) => segments,
+            Path::Opaque(_) => panic!("URL has an opaque path"),
+        }
+    }
+
+    fn shorten_path(&mut self) {
+        let is_file = self.scheme == "file";
+        let segments = self.segments();
+        if is_file && segments.len() == 1 && is_drive_letter_str(&segments[0], true) {
+            return;
+        }
+        segments.pop();
+    }
+
+    /// Parse INPUT as a URL, relative to BASE if it is given.  Return
+    /// None if it isn't a valid URL.
+    pub fn parse(input: &str, base: Option<&Url>) -> Option<Url> {
+        let input: Vec<char> = input
+            .trim_matches(|c| c <= ' ')
+            .chars()
+            .filter(|&c| c != '\t' && c != '\n' && c != '\r')
+            .collect();
+        let mut url = Url {
+            scheme: String::new(),
+            username: String::new(),
+            password: String::new(),
+            host: None,
+            port: None,
+            path: Path::Segments(Vec::new()),
+            query: None,
+            fragment: None,
+        };
+        let mut state = State::SchemeStart;
+        let mut buffer = String::new();
+        let mut at_sign_seen = false;
+        let mut inside_brackets = false;
+        let mut password_token_seen = false;
+        let mut pointer = 0;
+        loop {
+            let c = input.get(pointer).cloned();
+            let remaining = if pointer < input.len() {
+                &input[pointer + 1..]
+            } else {
+                &[]
+            };
+            let is_special = url.is_special();
+            // Whether C ends the authority, host or a path segment.
+            let is_delimiter = match c {
+                None | Some('/') | Some('?') | Some('#') => true,
+                Some('\\') => is_special,
+                _ => false,
+            };
+            // Whether to move on to the next character, rather than
+            // process this one again in the new state.
+            let mut advance = true;
+            match state {
+                State::SchemeStart => match c {
+                    Some(c) if is_alpha(c) => {
+                        buffer.push(c.to_ascii_lowercase());
+                        state = State::Scheme;
+                    }
+                    _ => {
+                        state = State::NoScheme;
+                        advance = false;
+                    }
+                },
+                State::Scheme => match c {
+                    Some(c) if is_alpha(c) || is_digit(c) || "+-.".contains(c) => {
+                        buffer.push(c.to_ascii_lowercase());
+                    }
+                    Some(':') => {
+                        url.scheme = buffer.clone();
+                        buffer.clear();
+                        if url.scheme == "file" {
+                            state = State::File;
+                        } else if url.is_special() {
+                            state = match base {
+                                Some(base) if base.scheme == url.scheme => {
+                                    State::SpecialRelativeOrAuthority
+                                }
+                                _ => State::SpecialAuthoritySlashes,
+                            };
+                        } else if remaining.first() == Some(&'/') {
+                            state = State::PathOrAuthority;
+                            pointer += 1;
+                        } else {
+                            url.path = Path::Opaque(String::new());
+                            state = State::OpaquePath;
+                        }
+                    }
+                    _ => {
+                        // Start over without a scheme.
+                        buffer.clear();
+                        state = State::NoScheme;
+                        pointer = 0;
+                        advance = false;
+                    }
+                },
+                State::NoScheme => {
+                    let base = base?;
+                    match base.path {
+                        Path::Opaque(_) if c == Some('#') => {
+                            url.scheme = base.scheme.clone();
+                            url.path = base.path.clone();
+                            url.query = base.query.clone();
+                            url.fragment = Some(String::new());
+                            state = State::Fragment;
+                        }
+                        Path::Opaque(_) => return None,
+                        Path::Segments(_) => {
+                            state = if base.scheme == "file" {
+                                State::File
+                            } else {
+                                State::Relative
+                            };
+                            advance = false;
+                        }
+                    }
+                }
+                State::SpecialRelativeOrAuthority => {
+                    if c == Some('/') && remaining.first() == Some(&'/') {
+                        state = State::SpecialAuthorityIgnoreSlashes;
+                        pointer += 1;
+                    } else {
+                        state = State::Relative;
+                        advance = false;
+                    }
+                }
+                State::PathOrAuthority => {
+                    if c == Some('/') {
+                        state = State::Authority;
+                    } else {
+                        state = State::Path;
+                        advance = false;
+                    }
+                }
+                State::Relative => {
+                    let base = base.unwrap();
+                    url.scheme = base.scheme.clone();
+                    if c == Some('/') || url.is_special() && c == Some('\\') {
+                        state = State::RelativeSlash;
+                    } else {
+                        url.username = base.username.clone();
+                        url.password = base.password.clone();
+                        url.host = base.host.clone();
+                        url.port = base.port;
+                        url.path = base.path.clone();
+                        url.query = base.query.clone();
+                        match c {
+                            Some('?') => {
+                                url.query = Some(String::new());
+                                state = State::Query;
+                            }
+                            Some('#') => {
+                                url.fragment = Some(String::new());
+                                state = State::Fragment;
+                            }
+                            Some(_) => {
+                                url.query = None;
+                                url.shorten_path();
+                                state = State::Path;
+                                advance = false;
+                            }
+                            None => (),
+                        }
+                    }
+                }
+                State::RelativeSlash => {
+                    if is_special && (c == Some('/') || c == Some('\\')) {
+                        state = State::SpecialAuthorityIgnoreSlashes;
+                    } else if c == Some('/') {
+                        state = State::Authority;
+                    } else {
+                        let base = base.unwrap();
+                        url.username = base.username.clone();
+                        url.password = base.password.clone();
+                        url.host = base.host.clone();
+                        url.port = base.port;
+                        state = State::Path;
+                        advance = false;
+                    }
+                }
+                State::SpecialAuthoritySlashes => {
+                    if c == Some('/') && remaining.first() == Some(&'/') {
+                        pointer += 1;
+                    } else {
+                        advance = false;
+                    }
+                    state = State::SpecialAuthorityIgnoreSlashes;
+                }
+                State::SpecialAuthorityIgnoreSlashes => {
+                    if c != Some('/') && c != Some('\\') {
+                        state = State::Authority;
+                        advance = false;
+                    }
+                }
+                State::Authority => {
+                    if c == Some('@') {
+                        if at_sign_seen {
+                            buffer.insert_str(0, "%40");
+                        }
+                        at_sign_seen = true;
+                        for c in buffer.chars() {
+                            if c == ':' && !password_token_seen {
+                                password_token_seen = true;
+                                continue;
+                            }
+                            let part = if password_token_seen {
+                                &mut url.password
+                            } else {
+                                &mut url.username
+                            };
+                            encode_char(c, EncodeSet::Userinfo, part);
+                        }
+                        buffer.clear();
+                    } else if is_delimiter {
+                        if at_sign_seen && buffer.is_empty() {
+                            return None;
+                        }
+                        // Go back to the start of the host.
+                        pointer -= buffer.chars().count();
+                        buffer.clear();
+                        state = State::Host;
+                        advance = false;
+                    } else {
+                        buffer.push(c.unwrap());
+                    }
+                }
+                State::Host => {
+                    if c == Some(':') && !inside_brackets {
+                        if buffer.is_empty() {
+                            return None;
+                        }
+                        url.host = Some(parse_host(&buffer, !is_special)?);
+                        buffer.clear();
+                        state = State::Port;
+                    } else if is_delimiter {
+                        if is_special && buffer.is_empty() {
+                            return None;
+                        }
+                        url.host = Some(parse_host(&buffer, !is_special)?);
+                        buffer.clear();
+                        state = State::PathStart;
+                        advance = false;
+                    } else {
+                        match c {
+                            Some('[') => inside_brackets = true,
+                            Some(']') => inside_brackets = false,
+                            _ => (),
+                        }
+                        buffer.push(c.unwrap());
+                    }
+                }
+                State::Port => match c {
+                    Some(c) if is_digit(c) => buffer.push(c),
+                    _ if is_delimiter => {
+                        if !buffer.is_empty() {
+                            let mut port: u32 = 0;
+                            for digit in buffer.bytes() {
+                                port = port * 10 + u32::from(digit - b'0');
+                                if port > 65535 {
+                                    return None;
+                                }
+                            }
+                            let port = port as u16;
+                            url.port = if special_scheme_port(&url.scheme) == Some(Some(port)) {
+                                None
+                            } else {
+                                Some(port)
+                            };
+                            buffer.clear();
+                        }
+                        state = State::PathStart;
+                        advance = false;
+                    }
+                    _ => return None,
+                },
+                State::File => {
+                    url.scheme = "file".to_string();
+                    url.host = Some(Host::Empty);
+                    match base {
+                        _ if c == Some('/') || c == Some('\\') => state = State::FileSlash,
+                        Some(base) if base.scheme == "file" => {
+                            url.host = base.host.clone();
+                            url.path = base.path.clone();
+                            url.query = base.query.clone();
+                            match c {
+                                Some('?') => {
+                                    url.query = Some(String::new());
+                                    state = State::Query;
+                                }
+                                Some('#') => {
+                                    url.fragment = Some(String::new());
+                                    state = State::Fragment;
+                                }
+                                Some(_) => {
+                                    url.query = None;
+                                    if starts_with_drive_letter(&input[pointer..]) {
+                                        url.path = Path::Segments(Vec::new());
+                                    } else {
+                                        url.shorten_path();
+                                    }
+                                    state = State::Path;
+                                    advance = false;
+                                }
+                                None => (),
+                            }
+                        }
+                        _ => {
+                            state = State::Path;
+                            advance = false;
+                        }
+                    }
+                }
+                State::FileSlash => {
+                    if c == Some('/') || c == Some('\\') {
+                        state = State::FileHost;
+                    } else {
+                        if let Some(base) = base {
+                            if base.scheme == "file" {
+                                url.host = base.host.clone();
+                                if let Path::Segments(ref segments) = base.path {
+                                    if !starts_with_drive_letter(&input[pointer..])
+                                        && segments
+                                            .first()
+                                            .map_or(false, |first| is_drive_letter_str(first, true))
+                                    {
+                                        url.segments().push(segments[0].clone());
+                                    }
+                                }
+                            }
+                        }
+                        state = State::Path;
+                        advance = false;
+                    }
+                }
+                State::FileHost => {
+                    if c.map_or(true, |c| "/\\?#".contains(c)) {
+                        advance = false;
+                        if is_drive_letter_str(&buffer, false) {
+                            // The buffer is the first segment of the path.
+                            state = State::Path;
+                        } else {
+                            let host = if buffer.is_empty() {
+                                Host::Empty
+                            } else {
+                                match parse_host(&buffer, false)? {
+                                    Host::Domain(ref name) if name == "localhost" => Host::Empty,
+                                    host => host,
+                                }
+                            };
+                            url.host = Some(host);
+                            buffer.clear();
+                            state = State::PathStart;
+                        }
+                    } else {
+                        buffer.push(c.unwrap());
+                    }
+                }
+                State::PathStart => {
+                    if is_special {
+                        state = State::Path;
+                        if c != Some('/') && c != Some('\\') {
+                            advance = false;
+                        }
+                    } else {
+                        match c {
+                            Some('?') => {
+                                url.query = Some(String::new());
+                                state = State::Query;
+                            }
+                            Some('#') => {
+                                url.fragment = Some(String::new());
+                                state = State::Fragment;
+                            }
+                            Some(c) => {
+                                state = State::Path;
+                                if c != '/' {
+                                    advance = false;
+                                }
+                            }
+                            None => (),
+                        }
+                    }
+                }
+                State::Path => {
+                    if is_delimiter {
+                        let slash = c == Some('/') || is_special && c == Some('\\');
+                        if is_double_dot(&buffer) {
+                            url.shorten_path();
+                            if !slash {
+                                url.segments().push(String::new());
+                            }
+                        } else if is_single_dot(&buffer) {
+                            if !slash {
+                                url.segments().push(String::new());
+                            }
+                        } else {
+                            if url.scheme == "file" && url.segments().is_empty()
+                                && is_drive_letter_str(&buffer, false)
+                            {
+                                buffer = format!("{}:", &buffer[..1]);
+                            }
+                            url.segments().push(buffer.clone());
+                        }
+                        buffer.clear();
+                        match c {
+                            Some('?') => {
+                                url.query = Some(String::new());
+                                state = State::Query;
+                            }
+                            Some('#') => {
+                                url.fragment = Some(String::new());
+                                state = State::Fragment;
+                            }
+                            _ => (),
+                        }
+                    } else {
+                        encode_char(c.unwrap(), EncodeSet::Path, &mut buffer);
+                    }
+                }
+                State::OpaquePath => match c {
+                    Some('?') => {
+                        url.query = Some(String::new());
+                        state = State::Query;
+                    }
+                    Some('#') => {
+                        url.fragment = Some(String::new());
+                        state = State::Fragment;
+                    }
+                    Some(c) => {
+                        if let Path::Opaque(ref mut path) = url.path {
+                            encode_char(c, EncodeSet::C0Control, path);
+                        }
+                    }
+                    None => (),
+                },
+                State::Query => match c {
+                    Some('#') => {
+                        url.fragment = Some(String::new());
+                        state = State::Fragment;
+                    }
+                    Some(c) => {
+                        let set = if is_special {
+                            EncodeSet::SpecialQuery
+                        } else {
+                            EncodeSet::Query
+                        };
+                        if let Some(ref mut query) = url.query {
+                            encode_char(c, set, query);
+                        }
+                    }
+                    None => (),
+                },
+                State::Fragment => {
+                    if let (Some(c), Some(fragment)) = (c, url.fragment.as_mut()) {
+                        encode_char(c, EncodeSet::Fragment, fragment);
+                    }
+                }
+            }
+            if advance {
+                if c.is_none() {
+                    break;
+                }
+                pointer += 1;
+            }
+        }
+        Some(url)
+    }
+
+    /// Return the path of the URL as it appears in it.
+    pub fn pathname(&self) -> String {
+        match self.path {
+            Path::Opaque(ref path) => path.clone(),
+            Path::Segments(ref segments) => {
+                segments.iter().map(|segment| format!("/{}", segment)).collect()
+            }
+        }
+    }
+
+    /// Return the URL as a string.
+    pub fn serialize(&self) -> String {
+        let mut out = format!("{}:", self.scheme);
+        if let Some(ref host) = self.host {
+            out.push_str("//");
+            if !self.username.is_empty() || !self.password.is_empty() {
+                out.push_str(&self.username);
+                if !self.password.is_empty() {
+                    out.push(':');
+                    out.push_str(&self.password);
+                }
+                out.push('@');
+            }
+            out.push_str(&host.serialize());
+            if let Some(port) = self.port {
+                out.push_str(&format!(":{}", port));
+            }
+        }
+        if let Path::Segments(ref segments) = self.path {
+            // Keep a path starting with "//" from being read as a host.
+            if self.host.is_none() && segments.len() > 1 && segments[0].is_empty() {
+                out.push_str("/.");
+            }
+        }
+        out.push_str(&self.pathname());
+        if let Some(ref query) = self.query {
+            out.push('?');
+            out.push_str(query);
+        }
+        if let Some(ref fragment) = self.fragment {
+            out.push('#');
+            out.push_str(fragment);
+        }
+        out
+    }
+}
+
+/// A JSON value, as read by `read_json`.
+#[cfg(test)]
+#[derive(Debug)]
+enum Json {
+    Null,
+    Bool(bool),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+#[cfg(test)]
+impl Json {
+    fn get(&self, key: &str) -> Option<&Json> {
+        match *self {
+            Json::Object(ref members) => members
+                .iter()
+                .find(|&&(ref name, _)| name == key)
+                .map(|&(_, ref value)| value),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match *self {
+            Json::String(ref string) => Some(string),
+            _ => None,
+        }
+    }
+}
+
+/// Read the JSON value at the start of INPUT, which holds no numbers,
+/// and return it and the rest of INPUT.  Unpaired surrogates are read
+/// as U+FFFD, as they are by web browsers for URLs.
+#[cfg(test)]
+fn read_json(input: &str) -> (Json, &str) {
+    let input = input.trim_left();
+    if input.starts_with("null") {
+        (Json::Null, &input[4..])
+    } else if input.starts_with("true") {
+        (Json::Bool(true), &input[4..])
+    } else if input.starts_with("false") {
+        (Json::Bool(false), &input[5..])
+    } else if input.starts_with('"') {
+        let mut string = String::new();
+        let mut chars = input[1..].char_indices();
+        let mut high_surrogate: Option<u32> = None;
+        loop {
+            let (idx, c) = chars.next().unwrap();
+            let code = match c {
+                '"' => {
+                    if high_surrogate.is_some() {
+                        string.push('\u{FFFD}');
+                    }
+                    return (Json::String(string), &input[idx + 2..]);
+                }
+                '\\' => match chars.next().unwrap().1 {
+                    'u' => {
+                        let hex: String = (0..4).map(|_| chars.next().unwrap().1).collect();
+                        u32::from_str_radix(&hex, 16).unwrap()
+                    }
+                    'n' => 0xA,
+                    'r' => 0xD,
+                    't' => 0x9,
+                    'b' => 0x8,
+                    'f' => 0xC,
+                    c => c as u32,
+                },
+                c => c as u32,
+            };
+            if let Some(high) = high_surrogate.take() {
+                if code >= 0xDC00 && code <= 0xDFFF {
+                    let c = 0x10000 + ((high - 0xD800) << 10) + (code - 0xDC00);
+                    string.push(::std::char::from_u32(c).unwrap());
+                    continue;
+                }
+                string.push('\u{FFFD}');
+            }
+            if code >= 0xD800 && code <= 0xDBFF {
+                high_surrogate = Some(code);
+            } else {
+                string.push(::std::char::from_u32(code).unwrap_or('\u{FFFD}'));
+            }
+        }
+    } else {
+        let array = input.starts_with('[');
+        let mut rest = &input[1..];
+        let mut elements = Vec::new();
+        let mut members = Vec::new();
+        loop {
+            rest = rest.trim_left();
+            if rest.starts_with(']') || rest.starts_with('}') {
+                rest = &rest[1..];
+                break;
+            }
+            if rest.starts_with(',') {
+                rest = &rest[1..];
+                continue;
+            }
+            let (value, after) = read_json(rest);
+            rest = after;
+            if array {
+                elements.push(value);
+            } else {
+                let name = match value {
+                    Json::String(name) => name,
+                    _ => panic!("Invalid JSON object"),
+                };
+                let after = rest.trim_left();
+                assert!(after.starts_with(':'));
+                let (value, after) = read_json(&after[1..]);
+                rest = after;
+                members.push((name, value));
+            }
+        }
+        let value = if array {
+            Json::Array(elements)
+        } else {
+            Json::Object(members)
+        };
+        (value, rest)
+    }
+}
+
+#[test]
+fn test_urltestdata() {
+    use std::fs::File;
+    use std::io::Read;
+
+    let path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../test/data/url/urltestdata.json"
+    );
+    let mut data = String::new();
+    File::open(path)
+        .unwrap()
+        .read_to_string(&mut data)
+        .unwrap();
+    let tests = match read_json(&data).0 {
+        Json::Array(tests) => tests,
+        _ => panic!("Invalid test data"),
+    };
+    let mut count = 0;
+    for test in tests.iter().filter(|test| test.get("input").is_some()) {
+        let input = test.get("input").and_then(Json::as_str).unwrap();
+        let base = test.get("base").and_then(Json::as_str);
+        let base_url = base.map(|base| Url::parse(base, None));
+        let url = match base_url {
+            Some(None) => None,
+            Some(Some(ref base_url)) => Url::parse(input, Some(base_url)),
+            None => Url::parse(input, None),
+        };
+        let context = format!("{:?} against {:?}", input, base);
+        match test.get("failure") {
+            Some(&Json::Bool(true)) => assert!(url.is_none(), "{}: {:?}", context, url),
+            _ => {
+                let url = url.unwrap_or_else(|| panic!("{}: failed", context));
+                let expected = |key| test.get(key).and_then(Json::as_str).unwrap();
+                let host = url.host.as_ref().map_or(String::new(), Host::serialize);
+                let port = url.port.map_or(String::new(), |port| port.to_string());
+                let search = match url.query {
+                    Some(ref query) if !query.is_empty() => format!("?{}", query),
+                    _ => String::new(),
+                };
+                let hash = match url.fragment {
+                    Some(ref fragment) if !fragment.is_empty() => format!("#{}", fragment),
+                    _ => String::new(),
+                };
+                assert_eq!(url.serialize(), expected("href"), "{}", context);
+                assert_eq!(url.username, expected("username"), "{}", context);
+                assert_eq!(url.password, expected("password"), "{}", context);
+                assert_eq!(host, expected("hostname"), "{}", context);
+                assert_eq!(port, expected("port"), "{}", context);
+                assert_eq!(url.pathname(), expected("pathname"), "{}", context);
+                assert_eq!(search, expected("search"), "{}", context);
+                assert_eq!(hash, expected("hash"), "{}", context);
+            }
+        }
+        count += 1;
+    }
+    assert!(count > 0);
+}
@@ -158,12 +158,45 @@ impl LispVectorRef {
         assert!(0 <= idx && idx < self.len() as ptrdiff_t);
         unsafe { self.get_unchecked(idx) }
     }
+
+    #[inline]
+    pub fn set(&self, idx: ptrdiff_t, value: LispObject) {
+        assert!(0 <= idx && idx < self.len() as ptrdiff_t);
+        self.as_mut_slice()[idx as usize] = value;
+    }
 }
 
 impl LispBoolVecRef {
     pub fn len(&self) -> usize {
         self.size as usize
     }
+
+    #[inline]
+    fn data_ptr(&self) -> *mut u8 {
+        self._data.as_ptr() as *mut u8
+    }
+
+    /// Same as `bool_vector_bitref` in C.
+    #[inline]
+    pub fn get(&self, idx: usize) -> bool {
+        assert!(idx < self.len());
+        let byte = unsafe { *self.data_ptr().offset((idx / 8) as isize) };
+        byte & (1 << (idx % 8)) != 0
+    }
+
+    /// Same as `bool_vector_set` in C.
+    #[inline]
+    pub fn set(&mut self, idx: usize, value: bool) {
+        assert!(idx < self.len());
+        unsafe {
+            let byte = self.data_ptr().offset((idx / 8) as isize);
+            if value {
+                *byte |= 1 << (idx % 8);
+            } else {
+                *byte &= !(1 << (idx % 8));
+            }
+        }
+    }
 }
 
 /// Return the length of vector, list or string SEQUENCE.
@@ -0,0 +1,132 @@
+//! The document trees built by the parsers.
+
+/// A node of a document.  Text is in the internal encoding of Emacs,
+/// which is UTF-8 for the characters parsers accept.
+#[derive(Debug, PartialEq)]
+pub enum Node {
+    Element(Element),
+    Text(Vec<u8>),
+    /// The text of an XML CDATA section, which is kept apart from the
+    /// text around it.
+    CData(Vec<u8>),
+    Comment(Vec<u8>),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Element {
+    pub name: Vec<u8>,
+    pub attributes: Vec<(Vec<u8>, Vec<u8>)>,
+    pub children: Vec<Node>,
+}
+
+impl Element {
+    pub fn new(name: Vec<u8>) -> Element {
+        Element {
+            name,
+            attributes: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Append TEXT to the children, merging it with text just before.
+    pub fn push_text(&mut self, text: &[u8]) {
+        if text.is_empty() {
+            return;
+        }
+        if let Some(&mut Node::Text(ref mut last)) = self.children.last_mut() {
+            last.extend_from_slice(text);
+            return;
+        }
+        self.children.push(Node::Text(text.to_vec()));
+    }
+}
+
+/// Return the line and column of byte position POS of the UTF-8 DATA,
+/// both counted from 1, with the column counted in characters.
+pub fn line_and_column(data: &[u8], pos: usize) -> (usize, usize) {
+    let before = &data[..pos.min(data.len())];
+    let line_start = before
+        .iter()
+        .rposition(|&byte| byte == b'\n')
+        .map_or(0, |newline| newline + 1);
+    let line = before.iter().filter(|&&byte| byte == b'\n').count() + 1;
+    let column = before[line_start..]
+        .iter()
+        .filter(|&&byte| byte & 0xC0 != 0x80)
+        .count() + 1;
+    (line, column)
+}
+
+/// Decode the UTF-8 character at the start of DATA, and return it and
+/// its length, or None if DATA doesn't start with a valid character.
+pub fn decode_char(data: &[u8]) -> Option<(u32, usize)> {
+    let first = *data.first()?;
+    let (len, min, initial) = match first {
+        0x00...0x7F => return Some((u32::from(first), 1)),
+        0xC2...0xDF => (2, 0x80, u32::from(first & 0x1F)),
+        0xE0...0xEF => (3, 0x800, u32::from(first & 0x0F)),
+        0xF0...0xF4 => (4, 0x1_0000, u32::from(first & 0x07)),
+        _ => return None,
+    };
+    if data.len() < len {
+        return None;
+    }
+    let mut c = initial;
+    for &byte in &data[1..len] {
+        if byte & 0xC0 != 0x80 {
+            return None;
+        }
+        c = c << 6 | u32::from(byte & 0x3F);
+    }
+    if c < min || c > 0x10_FFFF || (c >= 0xD800 && c <= 0xDFFF) {
+        return None;
+    }
+    Some((c, len))
+}
+
+/// Append the UTF-8 encoding of C to OUT.
+pub fn push_char(out: &mut Vec<u8>, c: u32) {
+    if c < 0x80 {
+        out.push(c as u8);
+    } else if c < 0x800 {
+        out.push(0xC0 | (c >> 6) as u8);
+        out.push(0x80 | (c & 0x3F) as u8);
+    } else if c < 0x1_0000 {
+        out.push(0xE0 | (c >> 12) as u8);
+        out.push(0x80 | (c >> 6 & 0x3F) as u8);
+        out.push(0x80 | (c & 0x3F) as u8);
+    } else {
+        out.push(0xF0 | (c >> 18) as u8);
+        out.push(0x80 | (c >> 12 & 0x3F) as u8);
+        out.push(0x80 | (c >> 6 & 0x3F) as u8);
+        out.push(0x80 | (c & 0x3F) as u8);
+    }
+}
+
+#[test]
+fn test_line_and_column() {
+    let data = "ab\ncdé\nf".as_bytes();
+    assert_eq!(line_and_column(data, 0), (1, 1));
+    assert_eq!(line_and_column(data, 2), (1, 3));
+    assert_eq!(line_and_column(data, 3), (2, 1));
+    // After the two bytes of é.
+    assert_eq!(line_and_column(data, 7), (2, 4));
+    assert_eq!(line_and_column(data, 8), (3, 1));
+}
+
+#[test]
+fn test_decode_char() {
+    assert_eq!(decode_char(b"a"), Some((0x61, 1)));
+    assert_eq!(decode_char("é".as_bytes()), Some((0xE9, 2)));
+    assert_eq!(decode_char("😀".as_bytes()), Some((0x1_F600, 4)));
+    assert_eq!(decode_char(b"\xC0\x80"), None);
+    assert_eq!(decode_char(b"\xE0\x80\x80"), None);
+    assert_eq!(decode_char(b"\xED\xA0\x80"), None);
+    assert_eq!(decode_char(b"\xE2\x82"), None);
+    assert_eq!(decode_char(b""), None);
+    let mut out = Vec::new();
+    for &c in &[0x61, 0xE9, 0x20AC, 0x1_F600] {
+        push_char(&mut out, c);
+    }
+    assert_eq!(out, "aé€😀".as_bytes());
+}
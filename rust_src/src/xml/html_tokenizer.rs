@@ -0,0 +1,1296 @@
+//! The tokenizer of the HTML parser, as described in the "Tokenization"
+//! section of the HTML Living Standard.
+//!
+//! The tree builder drives the tokenizer, and switches it to the
+//! states for the text of elements like `title' and `script' as it
+//! sees their start tags.
+
+use std::collections::VecDeque;
+use std::mem;
+
+use super::dom::decode_char;
+
+include!(concat!(env!("OUT_DIR"), "/html_entities.rs"));
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tag {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub self_closing: bool,
+}
+
+impl Tag {
+    fn new() -> Tag {
+        Tag {
+            name: String::new(),
+            attributes: Vec::new(),
+            self_closing: false,
+        }
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|&&(ref attribute, _)| attribute == name)
+            .map(|&(_, ref value)| value.as_str())
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Doctype {
+    pub name: Option<String>,
+    pub public_id: Option<String>,
+    pub system_id: Option<String>,
+    pub force_quirks: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Token {
+    Doctype(Doctype),
+    StartTag(Tag),
+    EndTag(Tag),
+    Comment(String),
+    /// A run of characters.
+    Characters(String),
+    Eof,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum State {
+    Data,
+    Rcdata,
+    Rawtext,
+    ScriptData,
+    Plaintext,
+    TagOpen,
+    EndTagOpen,
+    TagName,
+    RcdataLessThanSign,
+    RcdataEndTagOpen,
+    RcdataEndTagName,
+    RawtextLessThanSign,
+    RawtextEndTagOpen,
+    RawtextEndTagName,
+    ScriptDataLessThanSign,
+    ScriptDataEndTagOpen,
+    ScriptDataEndTagName,
+    ScriptDataEscapeStart,
+    ScriptDataEscapeStartDash,
+    ScriptDataEscaped,
+    ScriptDataEscapedDash,
+    ScriptDataEscapedDashDash,
+    ScriptDataEscapedLessThanSign,
+    ScriptDataEscapedEndTagOpen,
+    ScriptDataEscapedEndTagName,
+    ScriptDataDoubleEscapeStart,
+    ScriptDataDoubleEscaped,
+    ScriptDataDoubleEscapedDash,
+    ScriptDataDoubleEscapedDashDash,
+    ScriptDataDoubleEscapedLessThanSign,
+    ScriptDataDoubleEscapeEnd,
+    BeforeAttributeName,
+    AttributeName,
+    AfterAttributeName,
+    BeforeAttributeValue,
+    AttributeValueDoubleQuoted,
+    AttributeValueSingleQuoted,
+    AttributeValueUnquoted,
+    AfterAttributeValueQuoted,
+    SelfClosingStartTag,
+    BogusComment,
+    MarkupDeclarationOpen,
+    CommentStart,
+    CommentStartDash,
+    Comment,
+    CommentLessThanSign,
+    CommentLessThanSignBang,
+    CommentLessThanSignBangDash,
+    CommentLessThanSignBangDashDash,
+    CommentEndDash,
+    CommentEnd,
+    CommentEndBang,
+    Doctype,
+    BeforeDoctypeName,
+    DoctypeName,
+    AfterDoctypeName,
+    AfterDoctypePublicKeyword,
+    BeforeDoctypePublicIdentifier,
+    DoctypePublicIdentifierDoubleQuoted,
+    DoctypePublicIdentifierSingleQuoted,
+    AfterDoctypePublicIdentifier,
+    BetweenDoctypePublicAndSystemIdentifiers,
+    AfterDoctypeSystemKeyword,
+    BeforeDoctypeSystemIdentifier,
+    DoctypeSystemIdentifierDoubleQuoted,
+    DoctypeSystemIdentifierSingleQuoted,
+    AfterDoctypeSystemIdentifier,
+    BogusDoctype,
+    CdataSection,
+    CdataSectionBracket,
+    CdataSectionEnd,
+}
+
+const REPLACEMENT_CHARACTER: char = '\u{FFFD}';
+
+/// The characters which numeric character references for the C1
+/// controls stand for, as in windows-1252.
+const C1_REPLACEMENTS: [(u32, char); 27] = [
+    (0x80, '\u{20AC}'),
+    (0x82, '\u{201A}'),
+    (0x83, '\u{0192}'),
+    (0x84, '\u{201E}'),
+    (0x85, '\u{2026}'),
+    (0x86, '\u{2020}'),
+    (0x87, '\u{2021}'),
+    (0x88, '\u{02C6}'),
+    (0x89, '\u{2030}'),
+    (0x8A, '\u{0160}'),
+    (0x8B, '\u{2039}'),
+    (0x8C, '\u{0152}'),
+    (0x8E, '\u{017D}'),
+    (0x91, '\u{2018}'),
+    (0x92, '\u{2019}'),
+    (0x93, '\u{201C}'),
+    (0x94, '\u{201D}'),
+    (0x95, '\u{2022}'),
+    (0x96, '\u{2013}'),
+    (0x97, '\u{2014}'),
+    (0x98, '\u{02DC}'),
+    (0x99, '\u{2122}'),
+    (0x9A, '\u{0161}'),
+    (0x9B, '\u{203A}'),
+    (0x9C, '\u{0153}'),
+    (0x9E, '\u{017E}'),
+    (0x9F, '\u{0178}'),
+];
+
+pub fn is_whitespace(c: char) -> bool {
+    c == ' ' || c == '\t' || c == '\n' || c == '\x0C' || c == '\r'
+}
+
+fn is_ascii_alpha(c: char) -> bool {
+    (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z')
+}
+
+fn is_ascii_alnum(c: char) -> bool {
+    is_ascii_alpha(c) || (c >= '0' && c <= '9')
+}
+
+/// Decode the UTF-8 DATA into characters, replacing invalid bytes with
+/// U+FFFD and normalizing line ends to newlines.
+pub fn preprocess(data: &[u8]) -> Vec<char> {
+    let mut input = Vec::with_capacity(data.len());
+    let mut idx = 0;
+    while idx < data.len() {
+        let (c, len) = match decode_char(&data[idx..]) {
+            Some((c, len)) => (::std::char::from_u32(c).unwrap(), len),
+            // A raw byte in the internal encoding of Emacs.
+            None if (data[idx] == 0xC0 || data[idx] == 0xC1)
+                && data.get(idx + 1).map_or(false, |&byte| byte & 0xC0 == 0x80) =>
+            {
+                (REPLACEMENT_CHARACTER, 2)
+            }
+            None => (REPLACEMENT_CHARACTER, 1),
+        };
+        idx += len;
+        if c == '\r' {
+            if data.get(idx) == Some(&b'\n') {
+                idx += 1;
+            }
+            input.push('\n');
+        } else if c != '\u{FEFF}' || !input.is_empty() {
+            input.push(c);
+        }
+    }
+    input
+}
+
+pub struct Tokenizer {
+    input: Vec<char>,
+    /// The position of the next character.  It goes one past the end of
+    /// the input when the end is consumed, so that it can be
+    /// reconsumed like any character.
+    pos: usize,
+    pub state: State,
+    tokens: VecDeque<Token>,
+    /// Characters which are yet to be emitted as one token.
+    chars: String,
+    tag: Tag,
+    end_tag: bool,
+    comment: String,
+    doctype: Doctype,
+    temporary_buffer: String,
+    last_start_tag: String,
+    /// Whether CDATA sections are allowed, which they are in foreign
+    /// content.  The tree builder keeps this up to date.
+    pub allow_cdata: bool,
+}
+
+impl Tokenizer {
+    pub fn new(input: Vec<char>) -> Tokenizer {
+        Tokenizer {
+            input,
+            pos: 0,
+            state: State::Data,
+            tokens: VecDeque::new(),
+            chars: String::new(),
+            tag: Tag::new(),
+            end_tag: false,
+            comment: String::new(),
+            doctype: Doctype::default(),
+            temporary_buffer: String::new(),
+            last_start_tag: String::new(),
+            allow_cdata: false,
+        }
+    }
+
+    pub fn next_token(&mut self) -> Token {
+        while self.tokens.is_empty() {
+            self.step();
+        }
+        self.tokens.pop_front().unwrap()
+    }
+
+    fn consume(&mut self) -> Option<char> {
+        let c = self.input.get(self.pos).cloned();
+        self.pos += 1;
+        c
+    }
+
+    fn reconsume(&mut self, state: State) {
+        self.pos -= 1;
+        self.state = state;
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input.get(self.pos).cloned()
+    }
+
+    /// Return whether the input at the next character starts with
+    /// PREFIX, and consume it if it does.
+    fn eat(&mut self, prefix: &str, ignore_case: bool) -> bool {
+        let mut pos = self.pos;
+        for expected in prefix.chars() {
+            match self.input.get(pos) {
+                Some(&c) if c == expected || ignore_case && c.to_ascii_lowercase() == expected => {
+                    pos += 1
+                }
+                _ => return false,
+            }
+        }
+        self.pos = pos;
+        true
+    }
+
+    fn emit(&mut self, token: Token) {
+        if !self.chars.is_empty() {
+            let chars = mem::replace(&mut self.chars, String::new());
+            self.tokens.push_back(Token::Characters(chars));
+        }
+        self.tokens.push_back(token);
+    }
+
+    fn emit_eof(&mut self) {
+        self.emit(Token::Eof);
+    }
+
+    fn new_tag(&mut self, end_tag: bool) {
+        self.tag = Tag::new();
+        self.end_tag = end_tag;
+    }
+
+    fn emit_tag(&mut self) {
+        self.finish_attribute();
+        let tag = mem::replace(&mut self.tag, Tag::new());
+        if self.end_tag {
+            self.emit(Token::EndTag(tag));
+        } else {
+            self.last_start_tag = tag.name.clone();
+            self.emit(Token::StartTag(tag));
+        }
+    }
+
+    /// Drop the last attribute of the current tag if an earlier one has
+    /// the same name.
+    fn finish_attribute(&mut self) {
+        if let Some((last, earlier)) = self.tag.attributes.split_last() {
+            if earlier.iter().any(|&(ref name, _)| *name == last.0) {
+                self.tag.attributes.pop();
+            }
+        }
+    }
+
+    fn start_attribute(&mut self, name: &str) {
+        self.finish_attribute();
+        self.tag
+            .attributes
+            .push((name.to_string(), String::new()));
+    }
+
+    fn attribute_name(&mut self) -> &mut String {
+        &mut self.tag.attributes.last_mut().unwrap().0
+    }
+
+    fn attribute_value(&mut self) -> &mut String {
+        &mut self.tag.attributes.last_mut().unwrap().1
+    }
+
+    fn emit_comment(&mut self) {
+        let comment = mem::replace(&mut self.comment, String::new());
+        self.emit(Token::Comment(comment));
+    }
+
+    fn emit_doctype(&mut self) {
+        let doctype = mem::replace(&mut self.doctype, Doctype::default());
+        self.emit(Token::Doctype(doctype));
+    }
+
+    fn new_doctype(&mut self) {
+        self.doctype = Doctype::default();
+    }
+
+    fn is_appropriate_end_tag(&self) -> bool {
+        self.end_tag && self.tag.name == self.last_start_tag
+    }
+
+    /// Handle the next character of the name of a possible end tag in
+    /// text, and go back to the TEXT state if it isn't one.
+    fn text_end_tag_name(&mut self, c: Option<char>, text: State) {
+        match c {
+            Some(c) if is_whitespace(c) && self.is_appropriate_end_tag() => {
+                self.state = State::BeforeAttributeName
+            }
+            Some('/') if self.is_appropriate_end_tag() => self.state = State::SelfClosingStartTag,
+            Some('>') if self.is_appropriate_end_tag() => {
+                self.state = State::Data;
+                self.emit_tag();
+            }
+            Some(c) if is_ascii_alpha(c) => {
+                self.tag.name.push(c.to_ascii_lowercase());
+                self.temporary_buffer.push(c);
+            }
+            _ => {
+                self.chars.push_str("</");
+                let buffer = mem::replace(&mut self.temporary_buffer, String::new());
+                self.chars.push_str(&buffer);
+                self.reconsume(text);
+            }
+        }
+    }
+
+    /// Handle the character after "<" in text, when it can only be the
+    /// start of an end tag which goes to END_TAG_OPEN.
+    fn text_less_than_sign(&mut self, c: Option<char>, text: State, end_tag_open: State) {
+        if c == Some('/') {
+            self.temporary_buffer.clear();
+            self.state = end_tag_open;
+        } else {
+            self.chars.push('<');
+            self.reconsume(text);
+        }
+    }
+
+    fn text_end_tag_open(&mut self, c: Option<char>, text: State, end_tag_name: State) {
+        match c {
+            Some(c) if is_ascii_alpha(c) => {
+                self.new_tag(true);
+                self.reconsume(end_tag_name);
+            }
+            _ => {
+                self.chars.push_str("</");
+                self.reconsume(text);
+            }
+        }
+    }
+
+    /// Consume a character reference after its "&", and return the
+    /// text it stands for, which is the text consumed if it isn't a
+    /// character reference.
+    fn character_reference(&mut self, in_attribute: bool) -> String {
+        match self.peek() {
+            Some(c) if is_ascii_alnum(c) => self.named_character_reference(in_attribute),
+            Some('#') => {
+                self.pos += 1;
+                self.numeric_character_reference()
+            }
+            _ => "&".to_string(),
+        }
+    }
+
+    fn named_character_reference(&mut self, in_attribute: bool) -> String {
+        let candidate: String = self.input[self.pos..]
+            .iter()
+            .take(MAX_NAMED_REFERENCE_LENGTH)
+            .take_while(|&&c| is_ascii_alnum(c) || c == ';')
+            .collect();
+        // The names are ASCII, so byte and character lengths agree.
+        let mut found = (1..candidate.len() + 1).rev().filter_map(|len| {
+            let name = &candidate[..len];
+            NAMED_REFERENCES
+                .binary_search_by(|&(other, _)| other.cmp(name))
+                .ok()
+                .map(|idx| NAMED_REFERENCES[idx])
+        });
+        match found.next() {
+            Some((name, value)) => {
+                self.pos += name.len();
+                let historical = in_attribute && !name.ends_with(';')
+                    && self.peek()
+                        .map_or(false, |c| c == '=' || is_ascii_alnum(c));
+                if historical {
+                    format!("&{}", name)
+                } else {
+                    value.to_string()
+                }
+            }
+            None => "&".to_string(),
+        }
+    }
+
+    fn numeric_character_reference(&mut self) -> String {
+        let mut prefix = "&#".to_string();
+        let radix = match self.peek() {
+            Some(x) if x == 'x' || x == 'X' => {
+                self.pos += 1;
+                prefix.push(x);
+                16
+            }
+            _ => 10,
+        };
+        let mut value: u32 = 0;
+        let mut digits = 0;
+        while let Some(digit) = self.peek().and_then(|c| c.to_digit(radix)) {
+            value = value.saturating_mul(radix).saturating_add(digit);
+            digits += 1;
+            self.pos += 1;
+        }
+        if digits == 0 {
+            return prefix;
+        }
+        if self.peek() == Some(';') {
+            self.pos += 1;
+        }
+        let c = match value {
+            0 | 0xD800...0xDFFF => REPLACEMENT_CHARACTER,
+            0x80...0x9F => C1_REPLACEMENTS
+                .iter()
+                .find(|&&(code, _)| code == value)
+                .map_or_else(|| ::std::char::from_u32(value).unwrap(), |&(_, c)| c),
+            _ => ::std::char::from_u32(value).unwrap_or(REPLACEMENT_CHARACTER),
+        };
+        c.to_string()
+    }
+
+    /// Run the state machine for one character, or for a run of plain
+    /// characters in the data state.
+    fn step(&mut self) {
+        if self.state == State::Data {
+            let start = self.pos;
+            while let Some(&c) = self.input.get(self.pos) {
+                if c == '&' || c == '<' || c == '\0' {
+                    break;
+                }
+                self.pos += 1;
+            }
+            if self.pos > start {
+                let chars: String = self.input[start..self.pos].iter().collect();
+                self.chars.push_str(&chars);
+                return;
+            }
+        }
+
+        let c = self.consume();
+        match self.state {
+            State::Data => match c {
+                Some('&') => {
+                    let text = self.character_reference(false);
+                    self.chars.push_str(&text);
+                }
+                Some('<') => self.state = State::TagOpen,
+                Some(c) => self.chars.push(c),
+                None => self.emit_eof(),
+            },
+            State::Rcdata => match c {
+                Some('&') => {
+                    let text = self.character_reference(false);
+                    self.chars.push_str(&text);
+                }
+                Some('<') => self.state = State::RcdataLessThanSign,
+                Some('\0') => self.chars.push(REPLACEMENT_CHARACTER),
+                Some(c) => self.chars.push(c),
+                None => self.emit_eof(),
+            },
+            State::Rawtext => match c {
+                Some('<') => self.state = State::RawtextLessThanSign,
+                Some('\0') => self.chars.push(REPLACEMENT_CHARACTER),
+                Some(c) => self.chars.push(c),
+                None => self.emit_eof(),
+            },
+            State::ScriptData => match c {
+                Some('<') => self.state = State::ScriptDataLessThanSign,
+                Some('\0') => self.chars.push(REPLACEMENT_CHARACTER),
+                Some(c) => self.chars.push(c),
+                None => self.emit_eof(),
+            },
+            State::Plaintext => match c {
+                Some('\0') => self.chars.push(REPLACEMENT_CHARACTER),
+                Some(c) => self.chars.push(c),
+                None => self.emit_eof(),
+            },
+            State::TagOpen => match c {
+                Some('!') => self.state = State::MarkupDeclarationOpen,
+                Some('/') => self.state = State::EndTagOpen,
+                Some(c) if is_ascii_alpha(c) => {
+                    self.new_tag(false);
+                    self.reconsume(State::TagName);
+                }
+                Some('?') => {
+                    self.comment.clear();
+                    self.reconsume(State::BogusComment);
+                }
+                None => {
+                    self.chars.push('<');
+                    self.emit_eof();
+                }
+                Some(_) => {
+                    self.chars.push('<');
+                    self.reconsume(State::Data);
+                }
+            },
+            State::EndTagOpen => match c {
+                Some(c) if is_ascii_alpha(c) => {
+                    self.new_tag(true);
+                    self.reconsume(State::TagName);
+                }
+                Some('>') => self.state = State::Data,
+                None => {
+                    self.chars.push_str("</");
+                    self.emit_eof();
+                }
+                Some(_) => {
+                    self.comment.clear();
+                    self.reconsume(State::BogusComment);
+                }
+            },
+            State::TagName => match c {
+                Some(c) if is_whitespace(c) => self.state = State::BeforeAttributeName,
+                Some('/') => self.state = State::SelfClosingStartTag,
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_tag();
+                }
+                Some('\0') => self.tag.name.push(REPLACEMENT_CHARACTER),
+                Some(c) => self.tag.name.push(c.to_ascii_lowercase()),
+                None => self.emit_eof(),
+            },
+            State::RcdataLessThanSign => {
+                self.text_less_than_sign(c, State::Rcdata, State::RcdataEndTagOpen)
+            }
+            State::RcdataEndTagOpen => {
+                self.text_end_tag_open(c, State::Rcdata, State::RcdataEndTagName)
+            }
+            State::RcdataEndTagName => self.text_end_tag_name(c, State::Rcdata),
+            State::RawtextLessThanSign => {
+                self.text_less_than_sign(c, State::Rawtext, State::RawtextEndTagOpen)
+            }
+            State::RawtextEndTagOpen => {
+                self.text_end_tag_open(c, State::Rawtext, State::RawtextEndTagName)
+            }
+            State::RawtextEndTagName => self.text_end_tag_name(c, State::Rawtext),
+            State::ScriptDataLessThanSign => match c {
+                Some('/') => {
+                    self.temporary_buffer.clear();
+                    self.state = State::ScriptDataEndTagOpen;
+                }
+                Some('!') => {
+                    self.state = State::ScriptDataEscapeStart;
+                    self.chars.push_str("<!");
+                }
+                _ => {
+                    self.chars.push('<');
+                    self.reconsume(State::ScriptData);
+                }
+            },
+            State::ScriptDataEndTagOpen => {
+                self.text_end_tag_open(c, State::ScriptData, State::ScriptDataEndTagName)
+            }
+            State::ScriptDataEndTagName => self.text_end_tag_name(c, State::ScriptData),
+            State::ScriptDataEscapeStart => match c {
+                Some('-') => {
+                    self.state = State::ScriptDataEscapeStartDash;
+                    self.chars.push('-');
+                }
+                _ => self.reconsume(State::ScriptData),
+            },
+            State::ScriptDataEscapeStartDash => match c {
+                Some('-') => {
+                    self.state = State::ScriptDataEscapedDashDash;
+                    self.chars.push('-');
+                }
+                _ => self.reconsume(State::ScriptData),
+            },
+            State::ScriptDataEscaped => match c {
+                Some('-') => {
+                    self.state = State::ScriptDataEscapedDash;
+                    self.chars.push('-');
+                }
+                Some('<') => self.state = State::ScriptDataEscapedLessThanSign,
+                Some('\0') => self.chars.push(REPLACEMENT_CHARACTER),
+                Some(c) => self.chars.push(c),
+                None => self.emit_eof(),
+            },
+            State::ScriptDataEscapedDash | State::ScriptDataEscapedDashDash => match c {
+                Some('-') => {
+                    self.state = State::ScriptDataEscapedDashDash;
+                    self.chars.push('-');
+                }
+                Some('<') => self.state = State::ScriptDataEscapedLessThanSign,
+                Some('>') if self.state == State::ScriptDataEscapedDashDash => {
+                    self.state = State::ScriptData;
+                    self.chars.push('>');
+                }
+                Some('\0') => {
+                    self.state = State::ScriptDataEscaped;
+                    self.chars.push(REPLACEMENT_CHARACTER);
+                }
+                Some(c) => {
+                    self.state = State::ScriptDataEscaped;
+                    self.chars.push(c);
+                }
+                None => self.emit_eof(),
+            },
+            State::ScriptDataEscapedLessThanSign => match c {
+                Some('/') => {
+                    self.temporary_buffer.clear();
+                    self.state = State::ScriptDataEscapedEndTagOpen;
+                }
+                Some(c) if is_ascii_alpha(c) => {
+                    self.temporary_buffer.clear();
+                    self.chars.push('<');
+                    self.reconsume(State::ScriptDataDoubleEscapeStart);
+                }
+                _ => {
+                    self.chars.push('<');
+                    self.reconsume(State::ScriptDataEscaped);
+                }
+            },
+            State::ScriptDataEscapedEndTagOpen => self.text_end_tag_open(
+                c,
+                State::ScriptDataEscaped,
+                State::ScriptDataEscapedEndTagName,
+            ),
+            State::ScriptDataEscapedEndTagName => {
+                self.text_end_tag_name(c, State::ScriptDataEscaped)
+            }
+            State::ScriptDataDoubleEscapeStart | State::ScriptDataDoubleEscapeEnd => {
+                let (matched, otherwise) = if self.state == State::ScriptDataDoubleEscapeStart {
+                    (State::ScriptDataDoubleEscaped, State::ScriptDataEscaped)
+                } else {
+                    (State::ScriptDataEscaped, State::ScriptDataDoubleEscaped)
+                };
+                match c {
+                    Some(c) if is_whitespace(c) || c == '/' || c == '>' => {
+                        self.state = if self.temporary_buffer == "script" {
+                            matched
+                        } else {
+                            otherwise
+                        };
+                        self.chars.push(c);
+                    }
+                    Some(c) if is_ascii_alpha(c) => {
+                        self.temporary_buffer.push(c.to_ascii_lowercase());
+                        self.chars.push(c);
+                    }
+                    _ => self.reconsume(otherwise),
+                }
+            }
+            State::ScriptDataDoubleEscaped => match c {
+                Some('-') => {
+                    self.state = State::ScriptDataDoubleEscapedDash;
+                    self.chars.push('-');
+                }
+                Some('<') => {
+                    self.state = State::ScriptDataDoubleEscapedLessThanSign;
+                    self.chars.push('<');
+                }
+                Some('\0') => self.chars.push(REPLACEMENT_CHARACTER),
+                Some(c) => self.chars.push(c),
+                None => self.emit_eof(),
+            },
+            State::ScriptDataDoubleEscapedDash | State::ScriptDataDoubleEscapedDashDash => {
+                match c {
+                    Some('-') => {
+                        self.state = State::ScriptDataDoubleEscapedDashDash;
+                        self.chars.push('-');
+                    }
+                    Some('<') => {
+                        self.state = State::ScriptDataDoubleEscapedLessThanSign;
+                        self.chars.push('<');
+                    }
+                    Some('>') if self.state == State::ScriptDataDoubleEscapedDashDash => {
+                        self.state = State::ScriptData;
+                        self.chars.push('>');
+                    }
+                    Some('\0') => {
+                        self.state = State::ScriptDataDoubleEscaped;
+                        self.chars.push(REPLACEMENT_CHARACTER);
+                    }
+                    Some(c) => {
+                        self.state = State::ScriptDataDoubleEscaped;
+                        self.chars.push(c);
+                    }
+                    None => self.emit_eof(),
+                }
+            }
+            State::ScriptDataDoubleEscapedLessThanSign => match c {
+                Some('/') => {
+                    self.temporary_buffer.clear();
+                    self.state = State::ScriptDataDoubleEscapeEnd;
+                    self.chars.push('/');
+                }
+                _ => self.reconsume(State::ScriptDataDoubleEscaped),
+            },
+            State::BeforeAttributeName => match c {
+                Some(c) if is_whitespace(c) => {}
+                Some('/') | Some('>') | None => self.reconsume(State::AfterAttributeName),
+                Some('=') => {
+                    self.start_attribute("=");
+                    self.state = State::AttributeName;
+                }
+                Some(_) => {
+                    self.start_attribute("");
+                    self.reconsume(State::AttributeName);
+                }
+            },
+            State::AttributeName => match c {
+                Some(c) if is_whitespace(c) || c == '/' || c == '>' => {
+                    self.reconsume(State::AfterAttributeName)
+                }
+                None => self.reconsume(State::AfterAttributeName),
+                Some('=') => self.state = State::BeforeAttributeValue,
+                Some('\0') => self.attribute_name().push(REPLACEMENT_CHARACTER),
+                Some(c) => self.attribute_name().push(c.to_ascii_lowercase()),
+            },
+            State::AfterAttributeName => match c {
+                Some(c) if is_whitespace(c) => {}
+                Some('/') => self.state = State::SelfClosingStartTag,
+                Some('=') => self.state = State::BeforeAttributeValue,
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_tag();
+                }
+                None => self.emit_eof(),
+                Some(_) => {
+                    self.start_attribute("");
+                    self.reconsume(State::AttributeName);
+                }
+            },
+            State::BeforeAttributeValue => match c {
+                Some(c) if is_whitespace(c) => {}
+                Some('"') => self.state = State::AttributeValueDoubleQuoted,
+                Some('\'') => self.state = State::AttributeValueSingleQuoted,
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_tag();
+                }
+                _ => self.reconsume(State::AttributeValueUnquoted),
+            },
+            State::AttributeValueDoubleQuoted | State::AttributeValueSingleQuoted => {
+                let quote = if self.state == State::AttributeValueDoubleQuoted {
+                    '"'
+                } else {
+                    '\''
+                };
+                match c {
+                    Some(c) if c == quote => self.state = State::AfterAttributeValueQuoted,
+                    Some('&') => {
+                        let text = self.character_reference(true);
+                        self.attribute_value().push_str(&text);
+                    }
+                    Some('\0') => self.attribute_value().push(REPLACEMENT_CHARACTER),
+                    Some(c) => self.attribute_value().push(c),
+                    None => self.emit_eof(),
+                }
+            }
+            State::AttributeValueUnquoted => match c {
+                Some(c) if is_whitespace(c) => self.state = State::BeforeAttributeName,
+                Some('&') => {
+                    let text = self.character_reference(true);
+                    self.attribute_value().push_str(&text);
+                }
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_tag();
+                }
+                Some('\0') => self.attribute_value().push(REPLACEMENT_CHARACTER),
+                Some(c) => self.attribute_value().push(c),
+                None => self.emit_eof(),
+            },
+            State::AfterAttributeValueQuoted => match c {
+                Some(c) if is_whitespace(c) => self.state = State::BeforeAttributeName,
+                Some('/') => self.state = State::SelfClosingStartTag,
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_tag();
+                }
+                None => self.emit_eof(),
+                Some(_) => self.reconsume(State::BeforeAttributeName),
+            },
+            State::SelfClosingStartTag => match c {
+                Some('>') => {
+                    self.tag.self_closing = true;
+                    self.state = State::Data;
+                    self.emit_tag();
+                }
+                None => self.emit_eof(),
+                Some(_) => self.reconsume(State::BeforeAttributeName),
+            },
+            State::BogusComment => match c {
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_comment();
+                }
+                Some('\0') => self.comment.push(REPLACEMENT_CHARACTER),
+                Some(c) => self.comment.push(c),
+                None => {
+                    self.emit_comment();
+                    self.emit_eof();
+                }
+            },
+            State::MarkupDeclarationOpen => {
+                self.pos -= 1;
+                self.comment.clear();
+                if self.eat("--", false) {
+                    self.state = State::CommentStart;
+                } else if self.eat("doctype", true) {
+                    self.state = State::Doctype;
+                } else if self.eat("[CDATA[", false) {
+                    if self.allow_cdata {
+                        self.state = State::CdataSection;
+                    } else {
+                        self.comment.push_str("[CDATA[");
+                        self.state = State::BogusComment;
+                    }
+                } else {
+                    self.state = State::BogusComment;
+                }
+            }
+            State::CommentStart => match c {
+                Some('-') => self.state = State::CommentStartDash,
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_comment();
+                }
+                _ => self.reconsume(State::Comment),
+            },
+            State::CommentStartDash => match c {
+                Some('-') => self.state = State::CommentEnd,
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_comment();
+                }
+                None => {
+                    self.emit_comment();
+                    self.emit_eof();
+                }
+                Some(_) => {
+                    self.comment.push('-');
+                    self.reconsume(State::Comment);
+                }
+            },
+            State::Comment => match c {
+                Some('<') => {
+                    self.comment.push('<');
+                    self.state = State::CommentLessThanSign;
+                }
+                Some('-') => self.state = State::CommentEndDash,
+                Some('\0') => self.comment.push(REPLACEMENT_CHARACTER),
+                Some(c) => self.comment.push(c),
+                None => {
+                    self.emit_comment();
+                    self.emit_eof();
+                }
+            },
+            State::CommentLessThanSign => match c {
+                Some('!') => {
+                    self.comment.push('!');
+                    self.state = State::CommentLessThanSignBang;
+                }
+                Some('<') => self.comment.push('<'),
+                _ => self.reconsume(State::Comment),
+            },
+            State::CommentLessThanSignBang => match c {
+                Some('-') => self.state = State::CommentLessThanSignBangDash,
+                _ => self.reconsume(State::Comment),
+            },
+            State::CommentLessThanSignBangDash => match c {
+                Some('-') => self.state = State::CommentLessThanSignBangDashDash,
+                _ => self.reconsume(State::CommentEndDash),
+            },
+            State::CommentLessThanSignBangDashDash => self.reconsume(State::CommentEnd),
+            State::CommentEndDash => match c {
+                Some('-') => self.state = State::CommentEnd,
+                None => {
+                    self.emit_comment();
+                    self.emit_eof();
+                }
+                Some(_) => {
+                    self.comment.push('-');
+                    self.reconsume(State::Comment);
+                }
+            },
+            State::CommentEnd => match c {
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_comment();
+                }
+                Some('!') => self.state = State::CommentEndBang,
+                Some('-') => self.comment.push('-'),
+                None => {
+                    self.emit_comment();
+                    self.emit_eof();
+                }
+                Some(_) => {
+                    self.comment.push_str("--");
+                    self.reconsume(State::Comment);
+                }
+            },
+            State::CommentEndBang => match c {
+                Some('-') => {
+                    self.comment.push_str("--!");
+                    self.state = State::CommentEndDash;
+                }
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_comment();
+                }
+                None => {
+                    self.emit_comment();
+                    self.emit_eof();
+                }
+                Some(_) => {
+                    self.comment.push_str("--!");
+                    self.reconsume(State::Comment);
+                }
+            },
+            State::Doctype => match c {
+                Some(c) if is_whitespace(c) => self.state = State::BeforeDoctypeName,
+                None => {
+                    self.new_doctype();
+                    self.doctype.force_quirks = true;
+                    self.emit_doctype();
+                    self.emit_eof();
+                }
+                Some(_) => self.reconsume(State::BeforeDoctypeName),
+            },
+            State::BeforeDoctypeName => match c {
+                Some(c) if is_whitespace(c) => {}
+                Some('>') => {
+                    self.new_doctype();
+                    self.doctype.force_quirks = true;
+                    self.state = State::Data;
+                    self.emit_doctype();
+                }
+                None => {
+                    self.new_doctype();
+                    self.doctype.force_quirks = true;
+                    self.emit_doctype();
+                    self.emit_eof();
+                }
+                Some(c) => {
+                    self.new_doctype();
+                    let c = if c == '\0' {
+                        REPLACEMENT_CHARACTER
+                    } else {
+                        c.to_ascii_lowercase()
+                    };
+                    self.doctype.name = Some(c.to_string());
+                    self.state = State::DoctypeName;
+                }
+            },
+            State::DoctypeName => match c {
+                Some(c) if is_whitespace(c) => self.state = State::AfterDoctypeName,
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_doctype();
+                }
+                None => {
+                    self.doctype.force_quirks = true;
+                    self.emit_doctype();
+                    self.emit_eof();
+                }
+                Some(c) => {
+                    let c = if c == '\0' {
+                        REPLACEMENT_CHARACTER
+                    } else {
+                        c.to_ascii_lowercase()
+                    };
+                    self.doctype.name.as_mut().unwrap().push(c);
+                }
+            },
+            State::AfterDoctypeName => match c {
+                Some(c) if is_whitespace(c) => {}
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_doctype();
+                }
+                None => {
+                    self.doctype.force_quirks = true;
+                    self.emit_doctype();
+                    self.emit_eof();
+                }
+                Some(_) => {
+                    self.pos -= 1;
+                    if self.eat("public", true) {
+                        self.state = State::AfterDoctypePublicKeyword;
+                    } else if self.eat("system", true) {
+                        self.state = State::AfterDoctypeSystemKeyword;
+                    } else {
+                        self.doctype.force_quirks = true;
+                        self.state = State::BogusDoctype;
+                    }
+                }
+            },
+            State::AfterDoctypePublicKeyword
+            | State::BeforeDoctypePublicIdentifier
+            | State::AfterDoctypeSystemKeyword
+            | State::BeforeDoctypeSystemIdentifier => {
+                let system = self.state == State::AfterDoctypeSystemKeyword
+                    || self.state == State::BeforeDoctypeSystemIdentifier;
+                let keyword = self.state == State::AfterDoctypePublicKeyword
+                    || self.state == State::AfterDoctypeSystemKeyword;
+                match c {
+                    Some(c) if is_whitespace(c) => if keyword {
+                        self.state = if system {
+                            State::BeforeDoctypeSystemIdentifier
+                        } else {
+                            State::BeforeDoctypePublicIdentifier
+                        };
+                    },
+                    Some(quote) if quote == '"' || quote == '\'' => {
+                        self.start_doctype_identifier(system, quote)
+                    }
+                    Some('>') => {
+                        self.doctype.force_quirks = true;
+                        self.state = State::Data;
+                        self.emit_doctype();
+                    }
+                    None => {
+                        self.doctype.force_quirks = true;
+                        self.emit_doctype();
+                        self.emit_eof();
+                    }
+                    Some(_) => {
+                        self.doctype.force_quirks = true;
+                        self.reconsume(State::BogusDoctype);
+                    }
+                }
+            }
+            State::DoctypePublicIdentifierDoubleQuoted
+            | State::DoctypePublicIdentifierSingleQuoted
+            | State::DoctypeSystemIdentifierDoubleQuoted
+            | State::DoctypeSystemIdentifierSingleQuoted => {
+                let (system, quote) = match self.state {
+                    State::DoctypePublicIdentifierDoubleQuoted => (false, '"'),
+                    State::DoctypePublicIdentifierSingleQuoted => (false, '\''),
+                    State::DoctypeSystemIdentifierDoubleQuoted => (true, '"'),
+                    _ => (true, '\''),
+                };
+                match c {
+                    Some(c) if c == quote => {
+                        self.state = if system {
+                            State::AfterDoctypeSystemIdentifier
+                        } else {
+                            State::AfterDoctypePublicIdentifier
+                        };
+                    }
+                    Some('>') => {
+                        self.doctype.force_quirks = true;
+                        self.state = State::Data;
+                        self.emit_doctype();
+                    }
+                    None => {
+                        self.doctype.force_quirks = true;
+                        self.emit_doctype();
+                        self.emit_eof();
+                    }
+                    Some(c) => {
+                        let c = if c == '\0' { REPLACEMENT_CHARACTER } else { c };
+                        let identifier = if system {
+                            &mut self.doctype.system_id
+                        } else {
+                            &mut self.doctype.public_id
+                        };
+                        identifier.as_mut().unwrap().push(c);
+                    }
+                }
+            }
+            State::AfterDoctypePublicIdentifier
+            | State::BetweenDoctypePublicAndSystemIdentifiers => match c {
+                Some(c) if is_whitespace(c) => {
+                    self.state = State::BetweenDoctypePublicAndSystemIdentifiers
+                }
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_doctype();
+                }
+                Some(quote) if quote == '"' || quote == '\'' => {
+                    self.start_doctype_identifier(true, quote)
+                }
+                None => {
+                    self.doctype.force_quirks = true;
+                    self.emit_doctype();
+                    self.emit_eof();
+                }
+                Some(_) => {
+                    self.doctype.force_quirks = true;
+                    self.reconsume(State::BogusDoctype);
+                }
+            },
+            State::AfterDoctypeSystemIdentifier => match c {
+                Some(c) if is_whitespace(c) => {}
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_doctype();
+                }
+                None => {
+                    self.doctype.force_quirks = true;
+                    self.emit_doctype();
+                    self.emit_eof();
+                }
+                Some(_) => self.reconsume(State::BogusDoctype),
+            },
+            State::BogusDoctype => match c {
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_doctype();
+                }
+                None => {
+                    self.emit_doctype();
+                    self.emit_eof();
+                }
+                Some(_) => {}
+            },
+            State::CdataSection => match c {
+                Some(']') => self.state = State::CdataSectionBracket,
+                Some(c) => self.chars.push(c),
+                None => self.emit_eof(),
+            },
+            State::CdataSectionBracket => match c {
+                Some(']') => self.state = State::CdataSectionEnd,
+                _ => {
+                    self.chars.push(']');
+                    self.reconsume(State::CdataSection);
+                }
+            },
+            State::CdataSectionEnd => match c {
+                Some(']') => self.chars.push(']'),
+                Some('>') => self.state = State::Data,
+                _ => {
+                    self.chars.push_str("]]");
+                    self.reconsume(State::CdataSection);
+                }
+            },
+        }
+    }
+
+    fn start_doctype_identifier(&mut self, system: bool, quote: char) {
+        let double = quote == '"';
+        if system {
+            self.doctype.system_id = Some(String::new());
+            self.state = if double {
+                State::DoctypeSystemIdentifierDoubleQuoted
+            } else {
+                State::DoctypeSystemIdentifierSingleQuoted
+            };
+        } else {
+            self.doctype.public_id = Some(String::new());
+            self.state = if double {
+                State::DoctypePublicIdentifierDoubleQuoted
+            } else {
+                State::DoctypePublicIdentifierSingleQuoted
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+fn tokenize(data: &str) -> Vec<Token> {
+    let mut tokenizer = Tokenizer::new(preprocess(data.as_bytes()));
+    let mut tokens = Vec::new();
+    loop {
+        match tokenizer.next_token() {
+            Token::Eof => return tokens,
+            token => tokens.push(token),
+        }
+    }
+}
+
+#[cfg(test)]
+fn start_tag(name: &str, attributes: &[(&str, &str)], self_closing: bool) -> Token {
+    Token::StartTag(Tag {
+        name: name.to_string(),
+        attributes: attributes
+            .iter()
+            .map(|&(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+        self_closing,
+    })
+}
+
+#[test]
+fn test_preprocess() {
+    let chars: String = preprocess(b"\xEF\xBB\xBFa\r\nb\rc\xFFd\xC0\x80").into_iter().collect();
+    assert_eq!(chars, "a\nb\nc\u{FFFD}d\u{FFFD}");
+    let chars: String = preprocess("x\u{FEFF}".as_bytes()).into_iter().collect();
+    assert_eq!(chars, "x\u{FEFF}");
+}
+
+#[test]
+fn test_tokens() {
+    assert_eq!(
+        tokenize("<!DOCTYPE html><P Class=a class=b id='c'>x&amp;y</p><br/><!--z-->"),
+        vec![
+            Token::Doctype(Doctype {
+                name: Some("html".to_string()),
+                ..Doctype::default()
+            }),
+            start_tag("p", &[("class", "a"), ("id", "c")], false),
+            Token::Characters("x&y".to_string()),
+            Token::EndTag(Tag {
+                name: "p".to_string(),
+                attributes: Vec::new(),
+                self_closing: false,
+            }),
+            start_tag("br", &[], true),
+            Token::Comment("z".to_string()),
+        ]
+    );
+    assert_eq!(
+        tokenize("<!doctype html public \"-//W3C//DTD HTML 4.01//EN\">"),
+        vec![
+            Token::Doctype(Doctype {
+                name: Some("html".to_string()),
+                public_id: Some("-//W3C//DTD HTML 4.01//EN".to_string()),
+                ..Doctype::default()
+            }),
+        ]
+    );
+    assert_eq!(tokenize("a<1"), vec![Token::Characters("a<1".to_string())]);
+    // A tag which isn't finished at the end of the input is dropped.
+    assert_eq!(tokenize("a<b"), vec![Token::Characters("a".to_string())]);
+}
+
+#[test]
+fn test_character_references() {
+    assert_eq!(
+        tokenize("&lt;&notin;&notit;&#x26;&#38;&#x80;&#0;&bogus;&"),
+        vec![Token::Characters("<\u{2209}\u{AC}it;&&\u{20AC}\u{FFFD}&bogus;&".to_string())]
+    );
+    // In attributes, legacy references followed by "=" or an
+    // alphanumeric character are left alone.
+    assert_eq!(
+        tokenize("<a href='?a=1&copy=2&copyx&copy'>"),
+        vec![start_tag("a", &[("href", "?a=1&copy=2&copyx\u{A9}")], false)]
+    );
+}
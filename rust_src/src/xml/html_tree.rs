@@ -0,0 +1,2551 @@
+//! The tree construction stage of the HTML parser, as described in the
+//! "Tree construction" section of the HTML Living Standard.
+//!
+//! The document is built in an arena of nodes, since the algorithms of
+//! the standard move nodes around, and converted to `dom::Node's at the
+//! end.  Scripting is taken to be disabled, so `noscript' elements are
+//! parsed like the others.
+
+use std::mem;
+
+use super::dom::{Element, Node};
+use super::html_tokenizer::{is_whitespace, preprocess, State, Tag, Token, Tokenizer};
+use super::html_url;
+
+/// How deeply elements are nested in the returned tree.  Elements in
+/// the document which are nested more deeply are made siblings of
+/// their parents, so that the tree can be walked recursively.
+const MAX_DEPTH: usize = 512;
+
+/// How many equal formatting elements can be in the list of active
+/// formatting elements after the last marker (the "Noah's Ark"
+/// clause).
+const MAX_EQUAL_FORMATTING_ELEMENTS: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Namespace {
+    Html,
+    MathMl,
+    Svg,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mode {
+    Initial,
+    BeforeHtml,
+    BeforeHead,
+    InHead,
+    InHeadNoscript,
+    AfterHead,
+    InBody,
+    Text,
+    InTable,
+    InTableText,
+    InCaption,
+    InColumnGroup,
+    InTableBody,
+    InRow,
+    InCell,
+    InSelect,
+    InSelectInTable,
+    InTemplate,
+    AfterBody,
+    InFrameset,
+    AfterFrameset,
+    AfterAfterBody,
+    AfterAfterFrameset,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Quirks {
+    NoQuirks,
+    LimitedQuirks,
+    Quirks,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Scope {
+    Default,
+    ListItem,
+    Button,
+    Table,
+    Select,
+}
+
+#[derive(Clone, Debug)]
+pub struct Attribute {
+    /// The qualified name, like "xlink:href".
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug)]
+pub enum NodeKind {
+    Document,
+    Doctype {
+        name: String,
+        public_id: Option<String>,
+        system_id: Option<String>,
+    },
+    Element {
+        name: String,
+        namespace: Namespace,
+        attributes: Vec<Attribute>,
+    },
+    Text(String),
+    Comment(String),
+}
+
+pub struct TreeNode {
+    pub kind: NodeKind,
+    parent: Option<usize>,
+    pub children: Vec<usize>,
+}
+
+type NodeId = usize;
+
+const DOCUMENT: NodeId = 0;
+
+/// An entry of the list of active formatting elements: the element
+/// and the tag it was made from, or None for a marker.
+type FormattingEntry = Option<(NodeId, Tag)>;
+
+const SVG_TAG_NAMES: &[(&str, &str)] = &[
+    ("altglyph", "altGlyph"),
+    ("altglyphdef", "altGlyphDef"),
+    ("altglyphitem", "altGlyphItem"),
+    ("animatecolor", "animateColor"),
+    ("animatemotion", "animateMotion"),
+    ("animatetransform", "animateTransform"),
+    ("clippath", "clipPath"),
+    ("feblend", "feBlend"),
+    ("fecolormatrix", "feColorMatrix"),
+    ("fecomponenttransfer", "feComponentTransfer"),
+    ("fecomposite", "feComposite"),
+    ("feconvolvematrix", "feConvolveMatrix"),
+    ("fediffuselighting", "feDiffuseLighting"),
+    ("fedisplacementmap", "feDisplacementMap"),
+    ("fedistantlight", "feDistantLight"),
+    ("fedropshadow", "feDropShadow"),
+    ("feflood", "feFlood"),
+    ("fefunca", "feFuncA"),
+    ("fefuncb", "feFuncB"),
+    ("fefuncg", "feFuncG"),
+    ("fefuncr", "feFuncR"),
+    ("fegaussianblur", "feGaussianBlur"),
+    ("feimage", "feImage"),
+    ("femerge", "feMerge"),
+    ("femergenode", "feMergeNode"),
+    ("femorphology", "feMorphology"),
+    ("feoffset", "feOffset"),
+    ("fepointlight", "fePointLight"),
+    ("fespecularlighting", "feSpecularLighting"),
+    ("fespotlight", "feSpotLight"),
+    ("fetile", "feTile"),
+    ("feturbulence", "feTurbulence"),
+    ("foreignobject", "foreignObject"),
+    ("glyphref", "glyphRef"),
+    ("lineargradient", "linearGradient"),
+    ("radialgradient", "radialGradient"),
+    ("textpath", "textPath"),
+];
+
+const SVG_ATTRIBUTE_NAMES: &[(&str, &str)] = &[
+    ("attributename", "attributeName"),
+    ("attributetype", "attributeType"),
+    ("basefrequency", "baseFrequency"),
+    ("baseprofile", "baseProfile"),
+    ("calcmode", "calcMode"),
+    ("clippathunits", "clipPathUnits"),
+    ("diffuseconstant", "diffuseConstant"),
+    ("edgemode", "edgeMode"),
+    ("filterunits", "filterUnits"),
+    ("glyphref", "glyphRef"),
+    ("gradienttransform", "gradientTransform"),
+    ("gradientunits", "gradientUnits"),
+    ("kernelmatrix", "kernelMatrix"),
+    ("kernelunitlength", "kernelUnitLength"),
+    ("keypoints", "keyPoints"),
+    ("keysplines", "keySplines"),
+    ("keytimes", "keyTimes"),
+    ("lengthadjust", "lengthAdjust"),
+    ("limitingconeangle", "limitingConeAngle"),
+    ("markerheight", "markerHeight"),
+    ("markerunits", "markerUnits"),
+    ("markerwidth", "markerWidth"),
+    ("maskcontentunits", "maskContentUnits"),
+    ("maskunits", "maskUnits"),
+    ("numoctaves", "numOctaves"),
+    ("pathlength", "pathLength"),
+    ("patterncontentunits", "patternContentUnits"),
+    ("patterntransform", "patternTransform"),
+    ("patternunits", "patternUnits"),
+    ("pointsatx", "pointsAtX"),
+    ("pointsaty", "pointsAtY"),
+    ("pointsatz", "pointsAtZ"),
+    ("preservealpha", "preserveAlpha"),
+    ("preserveaspectratio", "preserveAspectRatio"),
+    ("primitiveunits", "primitiveUnits"),
+    ("refx", "refX"),
+    ("refy", "refY"),
+    ("repeatcount", "repeatCount"),
+    ("repeatdur", "repeatDur"),
+    ("requiredextensions", "requiredExtensions"),
+    ("requiredfeatures", "requiredFeatures"),
+    ("specularconstant", "specularConstant"),
+    ("specularexponent", "specularExponent"),
+    ("spreadmethod", "spreadMethod"),
+    ("startoffset", "startOffset"),
+    ("stddeviation", "stdDeviation"),
+    ("stitchtiles", "stitchTiles"),
+    ("surfacescale", "surfaceScale"),
+    ("systemlanguage", "systemLanguage"),
+    ("tablevalues", "tableValues"),
+    ("targetx", "targetX"),
+    ("targety", "targetY"),
+    ("textlength", "textLength"),
+    ("viewbox", "viewBox"),
+    ("viewtarget", "viewTarget"),
+    ("xchannelselector", "xChannelSelector"),
+    ("ychannelselector", "yChannelSelector"),
+    ("zoomandpan", "zoomAndPan"),
+];
+
+/// The public identifiers of document types which start quirks mode,
+/// or prefixes of them.
+const QUIRKS_PUBLIC_ID_PREFIXES: &[&str] = &[
+    "+//silmaril//dtd html pro v0r11 19970101//",
+    "-//as//dtd html 3.0 aswedit + extensions//",
+    "-//advasoft ltd//dtd html 3.0 aswedit + extensions//",
+    "-//ietf//dtd html 2.0 level 1//",
+    "-//ietf//dtd html 2.0 level 2//",
+    "-//ietf//dtd html 2.0 strict level 1//",
+    "-//ietf//dtd html 2.0 strict level 2//",
+    "-//ietf//dtd html 2.0 strict//",
+    "-//ietf//dtd html 2.0//",
+    "-//ietf//dtd html 2.1e//",
+    "-//ietf//dtd html 3.0//",
+    "-//ietf//dtd html 3.2 final//",
+    "-//ietf//dtd html 3.2//",
+    "-//ietf//dtd html 3//",
+    "-//ietf//dtd html level 0//",
+    "-//ietf//dtd html level 1//",
+    "-//ietf//dtd html level 2//",
+    "-//ietf//dtd html level 3//",
+    "-//ietf//dtd html strict level 0//",
+    "-//ietf//dtd html strict level 1//",
+    "-//ietf//dtd html strict level 2//",
+    "-//ietf//dtd html strict level 3//",
+    "-//ietf//dtd html strict//",
+    "-//ietf//dtd html//",
+    "-//metrius//dtd metrius presentational//",
+    "-//microsoft//dtd internet explorer 2.0 html strict//",
+    "-//microsoft//dtd internet explorer 2.0 html//",
+    "-//microsoft//dtd internet explorer 2.0 tables//",
+    "-//microsoft//dtd internet explorer 3.0 html strict//",
+    "-//microsoft//dtd internet explorer 3.0 html//",
+    "-//microsoft//dtd internet explorer 3.0 tables//",
+    "-//netscape comm. corp.//dtd html//",
+    "-//netscape comm. corp.//dtd strict html//",
+    "-//o'reilly and associates//dtd html 2.0//",
+    "-//o'reilly and associates//dtd html extended 1.0//",
+    "-//o'reilly and associates//dtd html extended relaxed 1.0//",
+    "-//sq//dtd html 2.0 hotmetal + extensions//",
+    "-//softquad software//dtd hotmetal pro 6.0::19990601::extensions to html 4.0//",
+    "-//softquad//dtd hotmetal pro 4.0::19971010::extensions to html 4.0//",
+    "-//spyglass//dtd html 2.0 extended//",
+    "-//sun microsystems corp.//dtd hotjava html//",
+    "-//sun microsystems corp.//dtd hotjava strict html//",
+    "-//w3c//dtd html 3 1995-03-24//",
+    "-//w3c//dtd html 3.2 draft//",
+    "-//w3c//dtd html 3.2 final//",
+    "-//w3c//dtd html 3.2//",
+    "-//w3c//dtd html 3.2s draft//",
+    "-//w3c//dtd html 4.0 frameset//",
+    "-//w3c//dtd html 4.0 transitional//",
+    "-//w3c//dtd html experimental 19960712//",
+    "-//w3c//dtd html experimental 970421//",
+    "-//w3c//dtd w3 html//",
+    "-//w3o//dtd w3 html 3.0//",
+    "-//webtechs//dtd mozilla html 2.0//",
+    "-//webtechs//dtd mozilla html//",
+];
+
+/// Return the quirks mode of a document with the document type
+/// declaration DOCTYPE.
+fn doctype_quirks(
+    name: Option<&str>,
+    public_id: Option<&str>,
+    system_id: Option<&str>,
+    force_quirks: bool,
+) -> Quirks {
+    let public = public_id.map(|id| id.to_ascii_lowercase());
+    let public = public.as_ref().map_or("", |id| id.as_str());
+    let system = system_id.map(|id| id.to_ascii_lowercase());
+    let starts = |prefixes: &[&str]| prefixes.iter().any(|prefix| public.starts_with(prefix));
+    let html4 = ["-//w3c//dtd html 4.01 frameset//", "-//w3c//dtd html 4.01 transitional//"];
+    if force_quirks || name != Some("html")
+        || public == "-//w3o//dtd w3 html strict 3.0//en//"
+        || public == "-/w3c/dtd html 4.0 transitional/en" || public == "html"
+        || system.as_ref().map_or(false, |id| {
+            id == "http://www.ibm.com/data/dtd/v11/ibmxhtml1-transitional.dtd"
+        }) || starts(QUIRKS_PUBLIC_ID_PREFIXES) || system.is_none() && starts(&html4)
+    {
+        Quirks::Quirks
+    } else if starts(&[
+        "-//w3c//dtd xhtml 1.0 frameset//",
+        "-//w3c//dtd xhtml 1.0 transitional//",
+    ]) || system.is_some() && starts(&html4)
+    {
+        Quirks::LimitedQuirks
+    } else {
+        Quirks::NoQuirks
+    }
+}
+
+fn is_blank(text: &str) -> bool {
+    text.chars().all(is_whitespace)
+}
+
+pub struct TreeBuilder {
+    pub nodes: Vec<TreeNode>,
+    mode: Mode,
+    original_mode: Mode,
+    template_modes: Vec<Mode>,
+    open: Vec<NodeId>,
+    formatting: Vec<FormattingEntry>,
+    head: Option<NodeId>,
+    form: Option<NodeId>,
+    frameset_ok: bool,
+    quirks: Quirks,
+    foster_parenting: bool,
+    pending_table_text: Vec<String>,
+    /// Whether a newline at the start of the next characters is dropped,
+    /// as it is after the start tags of `pre', `listing' and `textarea'.
+    ignore_newline: bool,
+    /// The state the tokenizer is switched to after the current token.
+    tokenizer_state: Option<State>,
+    stopped: bool,
+}
+
+impl TreeBuilder {
+    fn new() -> TreeBuilder {
+        TreeBuilder {
+            nodes: vec![
+                TreeNode {
+                    kind: NodeKind::Document,
+                    parent: None,
+                    children: Vec::new(),
+                },
+            ],
+            mode: Mode::Initial,
+            original_mode: Mode::Initial,
+            template_modes: Vec::new(),
+            open: Vec::new(),
+            formatting: Vec::new(),
+            head: None,
+            form: None,
+            frameset_ok: true,
+            quirks: Quirks::NoQuirks,
+            foster_parenting: false,
+            pending_table_text: Vec::new(),
+            ignore_newline: false,
+            tokenizer_state: None,
+            stopped: false,
+        }
+    }
+
+    /// Parse the UTF-8 HTML document DATA.
+    pub fn build(data: &[u8]) -> TreeBuilder {
+        let mut tokenizer = Tokenizer::new(preprocess(data));
+        let mut builder = TreeBuilder::new();
+        while !builder.stopped {
+            let token = tokenizer.next_token();
+            builder.process(token);
+            if let Some(state) = builder.tokenizer_state.take() {
+                tokenizer.state = state;
+            }
+            tokenizer.allow_cdata = builder
+                .open
+                .last()
+                .map_or(false, |&node| builder.namespace(node) != Namespace::Html);
+        }
+        builder
+    }
+
+    // Nodes.
+
+    fn new_node(&mut self, kind: NodeKind) -> NodeId {
+        self.nodes.push(TreeNode {
+            kind,
+            parent: None,
+            children: Vec::new(),
+        });
+        self.nodes.len() - 1
+    }
+
+    /// Return the name of the element NODE, or "" for other nodes.
+    fn name(&self, node: NodeId) -> &str {
+        match self.nodes[node].kind {
+            NodeKind::Element { ref name, .. } => &name[..],
+            _ => "",
+        }
+    }
+
+    fn namespace(&self, node: NodeId) -> Namespace {
+        match self.nodes[node].kind {
+            NodeKind::Element { namespace, .. } => namespace,
+            _ => Namespace::Html,
+        }
+    }
+
+    fn attributes(&self, node: NodeId) -> &[Attribute] {
+        match self.nodes[node].kind {
+            NodeKind::Element { ref attributes, .. } => &attributes[..],
+            _ => &[],
+        }
+    }
+
+    fn is_html(&self, node: NodeId, name: &str) -> bool {
+        self.namespace(node) == Namespace::Html && self.name(node) == name
+    }
+
+    fn is_html_one_of(&self, node: NodeId, names: &[&str]) -> bool {
+        self.namespace(node) == Namespace::Html && names.contains(&self.name(node))
+    }
+
+    fn is_special(&self, node: NodeId) -> bool {
+        match self.namespace(node) {
+            Namespace::Html => [
+                "address", "applet", "area", "article", "aside", "base", "basefont",
+                "bgsound", "blockquote", "body", "br", "button", "caption", "center", "col",
+                "colgroup", "dd", "details", "dir", "div", "dl", "dt", "embed", "fieldset",
+                "figcaption", "figure", "footer", "form", "frame", "frameset", "h1", "h2",
+                "h3", "h4", "h5", "h6", "head", "header", "hgroup", "hr", "html", "iframe",
+                "img", "input", "keygen", "li", "link", "listing", "main", "marquee", "menu",
+                "meta", "nav", "noembed", "noframes", "noscript", "object", "ol", "p",
+                "param", "plaintext", "pre", "script", "search", "section", "select",
+                "source", "style", "summary", "table", "tbody", "td", "template", "textarea",
+                "tfoot", "th", "thead", "title", "tr", "track", "ul", "wbr", "xmp",
+            ].contains(&self.name(node)),
+            Namespace::MathMl => {
+                ["mi", "mo", "mn", "ms", "mtext", "annotation-xml"].contains(&self.name(node))
+            }
+            Namespace::Svg => ["foreignObject", "desc", "title"].contains(&self.name(node)),
+        }
+    }
+
+    fn is_mathml_text_integration_point(&self, node: NodeId) -> bool {
+        self.namespace(node) == Namespace::MathMl
+            && ["mi", "mo", "mn", "ms", "mtext"].contains(&self.name(node))
+    }
+
+    fn is_html_integration_point(&self, node: NodeId) -> bool {
+        match self.namespace(node) {
+            Namespace::MathMl => {
+                self.name(node) == "annotation-xml"
+                    && self.attributes(node).iter().any(|attribute| {
+                        attribute.name == "encoding" && {
+                            let encoding = attribute.value.to_ascii_lowercase();
+                            encoding == "text/html" || encoding == "application/xhtml+xml"
+                        }
+                    })
+            }
+            Namespace::Svg => ["foreignObject", "desc", "title"].contains(&self.name(node)),
+            Namespace::Html => false,
+        }
+    }
+
+    fn append_child(&mut self, parent: NodeId, child: NodeId) {
+        self.nodes[child].parent = Some(parent);
+        self.nodes[parent].children.push(child);
+    }
+
+    fn insert_before(&mut self, parent: NodeId, child: NodeId, before: NodeId) {
+        let idx = self.nodes[parent]
+            .children
+            .iter()
+            .position(|&node| node == before)
+            .unwrap();
+        self.nodes[child].parent = Some(parent);
+        self.nodes[parent].children.insert(idx, child);
+    }
+
+    fn detach(&mut self, node: NodeId) {
+        if let Some(parent) = self.nodes[node].parent.take() {
+            self.nodes[parent].children.retain(|&child| child != node);
+        }
+    }
+
+    fn current_node(&self) -> NodeId {
+        *self.open.last().unwrap()
+    }
+
+    /// Return the place to insert a node at, as its parent and the node
+    /// to insert it before, if any.
+    fn appropriate_place(&self, target: Option<NodeId>) -> (NodeId, Option<NodeId>) {
+        let target = target.unwrap_or_else(|| self.current_node());
+        if !self.foster_parenting
+            || !self.is_html_one_of(target, &["table", "tbody", "tfoot", "thead", "tr"])
+        {
+            return (target, None);
+        }
+        let last_template = self.open
+            .iter()
+            .rposition(|&node| self.is_html(node, "template"));
+        let last_table = self.open.iter().rposition(|&node| self.is_html(node, "table"));
+        match (last_template, last_table) {
+            (Some(template), table) if table.map_or(true, |table| template > table) => {
+                (self.open[template], None)
+            }
+            (_, None) => (self.open[0], None),
+            (_, Some(table)) => match self.nodes[self.open[table]].parent {
+                Some(parent) => (parent, Some(self.open[table])),
+                None => (self.open[table - 1], None),
+            },
+        }
+    }
+
+    fn insert_at(&mut self, place: (NodeId, Option<NodeId>), node: NodeId) {
+        match place {
+            (parent, Some(before)) => self.insert_before(parent, node, before),
+            (parent, None) => self.append_child(parent, node),
+        }
+    }
+
+    fn insert_characters(&mut self, text: &str) {
+        let (parent, before) = self.appropriate_place(None);
+        if parent == DOCUMENT {
+            return;
+        }
+        let previous = {
+            let children = &self.nodes[parent].children;
+            match before {
+                Some(before) => {
+                    let idx = children.iter().position(|&node| node == before).unwrap();
+                    if idx > 0 {
+                        Some(children[idx - 1])
+                    } else {
+                        None
+                    }
+                }
+                None => children.last().cloned(),
+            }
+        };
+        if let Some(previous) = previous {
+            if let NodeKind::Text(ref mut existing) = self.nodes[previous].kind {
+                existing.push_str(text);
+                return;
+            }
+        }
+        let node = self.new_node(NodeKind::Text(text.to_string()));
+        self.insert_at((parent, before), node);
+    }
+
+    fn insert_comment(&mut self, text: String, parent: Option<NodeId>) {
+        let node = self.new_node(NodeKind::Comment(text));
+        let place = match parent {
+            Some(parent) => (parent, None),
+            None => self.appropriate_place(None),
+        };
+        self.insert_at(place, node);
+    }
+
+    fn create_element(&mut self, tag: &Tag, namespace: Namespace) -> NodeId {
+        let attributes = tag.attributes
+            .iter()
+            .map(|&(ref name, ref value)| Attribute {
+                name: name.clone(),
+                value: value.clone(),
+            })
+            .collect();
+        self.create_element_with(tag.name.clone(), namespace, attributes)
+    }
+
+    fn create_element_with(
+        &mut self,
+        name: String,
+        namespace: Namespace,
+        attributes: Vec<Attribute>,
+    ) -> NodeId {
+        self.new_node(NodeKind::Element {
+            name,
+            namespace,
+            attributes,
+        })
+    }
+
+    fn insert_html_element(&mut self, tag: &Tag) -> NodeId {
+        let node = self.create_element(tag, Namespace::Html);
+        let place = self.appropriate_place(None);
+        self.insert_at(place, node);
+        self.open.push(node);
+        node
+    }
+
+    /// Insert an HTML element for a start tag NAME without attributes.
+    fn insert_html_element_named(&mut self, name: &str) -> NodeId {
+        self.insert_html_element(&Tag {
+            name: name.to_string(),
+            attributes: Vec::new(),
+            self_closing: false,
+        })
+    }
+
+    fn insert_foreign_element(&mut self, tag: &Tag, namespace: Namespace) -> NodeId {
+        let mut name = tag.name.clone();
+        if namespace == Namespace::Svg {
+            if let Some(&(_, adjusted)) = SVG_TAG_NAMES.iter().find(|&&(from, _)| from == name) {
+                name = adjusted.to_string();
+            }
+        }
+        let attributes = tag.attributes
+            .iter()
+            .map(|&(ref name, ref value)| Attribute {
+                name: adjust_foreign_attribute(name, namespace),
+                value: value.clone(),
+            })
+            .collect();
+        let node = self.create_element_with(name, namespace, attributes);
+        let place = self.appropriate_place(None);
+        self.insert_at(place, node);
+        self.open.push(node);
+        node
+    }
+
+    // The stack of open elements.
+
+    fn in_specific_scope<F: Fn(&TreeBuilder, NodeId) -> bool>(
+        &self,
+        target: F,
+        scope: Scope,
+    ) -> bool {
+        for &node in self.open.iter().rev() {
+            if target(self, node) {
+                return true;
+            }
+            let html = self.namespace(node) == Namespace::Html;
+            let name = self.name(node);
+            let boundary = match scope {
+                Scope::Table => html && ["html", "table", "template"].contains(&name),
+                Scope::Select => !(html && ["optgroup", "option"].contains(&name)),
+                _ => {
+                    let default = match self.namespace(node) {
+                        Namespace::Html => [
+                            "applet", "caption", "html", "table", "td", "th", "marquee",
+                            "object", "template",
+                        ].contains(&name),
+                        Namespace::MathMl => {
+                            ["mi", "mo", "mn", "ms", "mtext", "annotation-xml"].contains(&name)
+                        }
+                        Namespace::Svg => ["foreignObject", "desc", "title"].contains(&name),
+                    };
+                    default || html && match scope {
+                        Scope::ListItem => name == "ol" || name == "ul",
+                        Scope::Button => name == "button",
+                        _ => false,
+                    }
+                }
+            };
+            if boundary {
+                return false;
+            }
+        }
+        false
+    }
+
+    fn in_scope(&self, name: &str, scope: Scope) -> bool {
+        self.in_specific_scope(|builder, node| builder.is_html(node, name), scope)
+    }
+
+    fn in_scope_one_of(&self, names: &[&str], scope: Scope) -> bool {
+        self.in_specific_scope(|builder, node| builder.is_html_one_of(node, names), scope)
+    }
+
+    fn node_in_scope(&self, target: NodeId) -> bool {
+        self.in_specific_scope(|_, node| node == target, Scope::Default)
+    }
+
+    fn has_template(&self) -> bool {
+        self.open.iter().any(|&node| self.is_html(node, "template"))
+    }
+
+    fn pop_until_one_of(&mut self, names: &[&str]) {
+        while let Some(node) = self.open.pop() {
+            if self.is_html_one_of(node, names) {
+                break;
+            }
+        }
+    }
+
+    fn pop_until(&mut self, name: &str) {
+        self.pop_until_one_of(&[name]);
+    }
+
+    fn pop_until_node(&mut self, target: NodeId) {
+        while let Some(node) = self.open.pop() {
+            if node == target {
+                break;
+            }
+        }
+    }
+
+    fn remove_from_stack(&mut self, target: NodeId) {
+        self.open.retain(|&node| node != target);
+    }
+
+    fn generate_implied_end_tags(&mut self, except: &str) {
+        while let Some(&node) = self.open.last() {
+            if self.name(node) == except
+                || !self.is_html_one_of(
+                    node,
+                    &[
+                        "dd", "dt", "li", "optgroup", "option", "p", "rb", "rp", "rt", "rtc"
+                    ],
+                ) {
+                break;
+            }
+            self.open.pop();
+        }
+    }
+
+    fn generate_all_implied_end_tags(&mut self) {
+        while let Some(&node) = self.open.last() {
+            if !self.is_html_one_of(
+                node,
+                &[
+                    "caption", "colgroup", "dd", "dt", "li", "optgroup", "option", "p", "rb",
+                    "rp", "rt", "rtc", "tbody", "td", "tfoot", "th", "thead", "tr",
+                ],
+            ) {
+                break;
+            }
+            self.open.pop();
+        }
+    }
+
+    fn clear_stack_back_to(&mut self, names: &[&str]) {
+        while !self.is_html_one_of(self.current_node(), names) {
+            self.open.pop();
+        }
+    }
+
+    fn close_p_element(&mut self) {
+        self.generate_implied_end_tags("p");
+        self.pop_until("p");
+    }
+
+    fn close_p_in_button_scope(&mut self) {
+        if self.in_scope("p", Scope::Button) {
+            self.close_p_element();
+        }
+    }
+
+    fn reset_insertion_mode(&mut self) {
+        for (idx, &node) in self.open.iter().enumerate().rev() {
+            let last = idx == 0;
+            if self.namespace(node) != Namespace::Html {
+                if last {
+                    break;
+                }
+                continue;
+            }
+            self.mode = match self.name(node) {
+                "select" => {
+                    let in_table = self.open[..idx]
+                        .iter()
+                        .rev()
+                        .take_while(|&&ancestor| !self.is_html(ancestor, "template"))
+                        .any(|&ancestor| self.is_html(ancestor, "table"));
+                    if in_table {
+                        Mode::InSelectInTable
+                    } else {
+                        Mode::InSelect
+                    }
+                }
+                "td" | "th" if !last => Mode::InCell,
+                "tr" => Mode::InRow,
+                "tbody" | "thead" | "tfoot" => Mode::InTableBody,
+                "caption" => Mode::InCaption,
+                "colgroup" => Mode::InColumnGroup,
+                "table" => Mode::InTable,
+                "template" => *self.template_modes.last().unwrap(),
+                "head" if !last => Mode::InHead,
+                "body" => Mode::InBody,
+                "frameset" => Mode::InFrameset,
+                "html" => if self.head.is_none() {
+                    Mode::BeforeHead
+                } else {
+                    Mode::AfterHead
+                },
+                _ if last => Mode::InBody,
+                _ => continue,
+            };
+            return;
+        }
+        self.mode = Mode::InBody;
+    }
+
+    // The list of active formatting elements.
+
+    fn push_formatting_element(&mut self, node: NodeId, tag: &Tag) {
+        let mut equal = Vec::new();
+        for (idx, entry) in self.formatting.iter().enumerate().rev() {
+            match *entry {
+                None => break,
+                Some((_, ref other)) => {
+                    let mut attributes = other.attributes.clone();
+                    let mut expected = tag.attributes.clone();
+                    attributes.sort();
+                    expected.sort();
+                    if other.name == tag.name && attributes == expected {
+                        equal.push(idx);
+                    }
+                }
+            }
+        }
+        if equal.len() >= MAX_EQUAL_FORMATTING_ELEMENTS {
+            self.formatting.remove(*equal.last().unwrap());
+        }
+        self.formatting.push(Some((node, tag.clone())));
+    }
+
+    fn formatting_position(&self, node: NodeId) -> Option<usize> {
+        self.formatting.iter().position(|entry| match *entry {
+            Some((other, _)) => other == node,
+            None => false,
+        })
+    }
+
+    fn reconstruct_formatting_elements(&mut self) {
+        let is_open = |builder: &TreeBuilder, entry: &FormattingEntry| match *entry {
+            Some((node, _)) => builder.open.contains(&node),
+            None => true,
+        };
+        match self.formatting.last() {
+            None => return,
+            Some(entry) if is_open(self, entry) => return,
+            _ => {}
+        }
+        let mut idx = self.formatting.len() - 1;
+        while idx > 0 && !is_open(self, &self.formatting[idx - 1]) {
+            idx -= 1;
+        }
+        for idx in idx..self.formatting.len() {
+            let tag = self.formatting[idx].as_ref().unwrap().1.clone();
+            let node = self.insert_html_element(&tag);
+            self.formatting[idx] = Some((node, tag));
+        }
+    }
+
+    fn clear_formatting_to_last_marker(&mut self) {
+        while let Some(entry) = self.formatting.pop() {
+            if entry.is_none() {
+                break;
+            }
+        }
+    }
+
+    /// Run the adoption agency algorithm for the end tag NAME, and
+    /// return false if the end tag should be handled like any other
+    /// end tag instead.
+    fn adoption_agency(&mut self, name: &str) -> bool {
+        let current = self.current_node();
+        if self.is_html(current, name) && self.formatting_position(current).is_none() {
+            self.open.pop();
+            return true;
+        }
+        for _ in 0..8 {
+            let position = self.formatting
+                .iter()
+                .rposition(|entry| match *entry {
+                    Some((node, _)) => self.name(node) == name,
+                    None => true,
+                })
+                .and_then(|idx| self.formatting[idx].as_ref().map(|_| idx));
+            let mut bookmark = match position {
+                Some(idx) => idx,
+                None => return false,
+            };
+            let (element, element_tag) = self.formatting[bookmark].clone().unwrap();
+            let element_idx = match self.open.iter().position(|&node| node == element) {
+                Some(idx) => idx,
+                None => {
+                    self.formatting.remove(bookmark);
+                    return true;
+                }
+            };
+            if !self.node_in_scope(element) {
+                return true;
+            }
+            let furthest_block_idx = match (element_idx + 1..self.open.len())
+                .find(|&idx| self.is_special(self.open[idx]))
+            {
+                Some(idx) => idx,
+                None => {
+                    self.pop_until_node(element);
+                    self.formatting.remove(bookmark);
+                    return true;
+                }
+            };
+            let furthest_block = self.open[furthest_block_idx];
+            let common_ancestor = self.open[element_idx - 1];
+
+            let mut node_idx = furthest_block_idx;
+            let mut last_node = furthest_block;
+            let mut inner = 0;
+            loop {
+                inner += 1;
+                node_idx -= 1;
+                let node = self.open[node_idx];
+                if node == element {
+                    break;
+                }
+                let mut position = self.formatting_position(node);
+                if inner > 3 {
+                    if let Some(idx) = position {
+                        self.formatting.remove(idx);
+                        if idx < bookmark {
+                            bookmark -= 1;
+                        }
+                        position = None;
+                    }
+                }
+                let position = match position {
+                    Some(idx) => idx,
+                    None => {
+                        self.open.remove(node_idx);
+                        continue;
+                    }
+                };
+                let tag = self.formatting[position].as_ref().unwrap().1.clone();
+                let new_node = self.create_element(&tag, Namespace::Html);
+                self.formatting[position] = Some((new_node, tag));
+                self.open[node_idx] = new_node;
+                if last_node == furthest_block {
+                    bookmark = position + 1;
+                }
+                self.detach(last_node);
+                self.append_child(new_node, last_node);
+                last_node = new_node;
+            }
+
+            self.detach(last_node);
+            let place = self.appropriate_place(Some(common_ancestor));
+            self.insert_at(place, last_node);
+
+            let new_element = self.create_element(&element_tag, Namespace::Html);
+            let children = mem::replace(&mut self.nodes[furthest_block].children, Vec::new());
+            for child in children {
+                self.append_child(new_element, child);
+            }
+            self.append_child(furthest_block, new_element);
+
+            let old_position = self.formatting_position(element).unwrap();
+            self.formatting.remove(old_position);
+            if old_position < bookmark {
+                bookmark -= 1;
+            }
+            self.formatting
+                .insert(bookmark, Some((new_element, element_tag)));
+            self.remove_from_stack(element);
+            let furthest_block_idx = self.open
+                .iter()
+                .position(|&node| node == furthest_block)
+                .unwrap();
+            self.open.insert(furthest_block_idx + 1, new_element);
+        }
+        true
+    }
+
+    // The insertion modes.
+
+    fn process(&mut self, token: Token) {
+        let token = match token {
+            Token::Characters(text) => {
+                let text = if self.ignore_newline && text.starts_with('\n') {
+                    text[1..].to_string()
+                } else {
+                    text
+                };
+                self.ignore_newline = false;
+                self.process_characters(&text);
+                return;
+            }
+            token => token,
+        };
+        self.ignore_newline = false;
+        self.dispatch(token);
+    }
+
+    /// Process TEXT in runs of white space, of other characters, and
+    /// single NUL characters, since the insertion modes handle each
+    /// differently.
+    fn process_characters(&mut self, text: &str) {
+        let mut rest = text;
+        while let Some(first) = rest.chars().next() {
+            let len = if first == '\0' {
+                1
+            } else {
+                let blank = is_whitespace(first);
+                rest.find(|c: char| c == '\0' || is_whitespace(c) != blank)
+                    .unwrap_or_else(|| rest.len())
+            };
+            self.dispatch(Token::Characters(rest[..len].to_string()));
+            rest = &rest[len..];
+        }
+    }
+
+    fn dispatch(&mut self, token: Token) {
+        let html_rules = match self.open.last() {
+            None => true,
+            Some(&node) => {
+                self.namespace(node) == Namespace::Html || match token {
+                    Token::StartTag(ref tag) => {
+                        (self.is_mathml_text_integration_point(node) && tag.name != "mglyph"
+                            && tag.name != "malignmark")
+                            || (self.namespace(node) == Namespace::MathMl
+                                && self.name(node) == "annotation-xml"
+                                && tag.name == "svg")
+                            || self.is_html_integration_point(node)
+                    }
+                    Token::Characters(_) => {
+                        self.is_mathml_text_integration_point(node)
+                            || self.is_html_integration_point(node)
+                    }
+                    Token::Eof => true,
+                    _ => false,
+                }
+            }
+        };
+        if html_rules {
+            let mode = self.mode;
+            self.process_in(mode, token);
+        } else {
+            self.foreign_content(token);
+        }
+    }
+
+    fn process_in(&mut self, mode: Mode, token: Token) {
+        match mode {
+            Mode::Initial => self.initial(token),
+            Mode::BeforeHtml => self.before_html(token),
+            Mode::BeforeHead => self.before_head(token),
+            Mode::InHead => self.in_head(token),
+            Mode::InHeadNoscript => self.in_head_noscript(token),
+            Mode::AfterHead => self.after_head(token),
+            Mode::InBody => self.in_body(token),
+            Mode::Text => self.text(token),
+            Mode::InTable => self.in_table(token),
+            Mode::InTableText => self.in_table_text(token),
+            Mode::InCaption => self.in_caption(token),
+            Mode::InColumnGroup => self.in_column_group(token),
+            Mode::InTableBody => self.in_table_body(token),
+            Mode::InRow => self.in_row(token),
+            Mode::InCell => self.in_cell(token),
+            Mode::InSelect => self.in_select(token),
+            Mode::InSelectInTable => self.in_select_in_table(token),
+            Mode::InTemplate => self.in_template(token),
+            Mode::AfterBody => self.after_body(token),
+            Mode::InFrameset => self.in_frameset(token),
+            Mode::AfterFrameset => self.after_frameset(token),
+            Mode::AfterAfterBody => self.after_after_body(token),
+            Mode::AfterAfterFrameset => self.after_after_frameset(token),
+        }
+    }
+
+    fn reprocess(&mut self, mode: Mode, token: Token) {
+        self.mode = mode;
+        self.process_in(mode, token);
+    }
+
+    fn stop(&mut self) {
+        self.open.clear();
+        self.stopped = true;
+    }
+
+    /// Parse the text of an element started by TAG, as RCDATA or
+    /// RAWTEXT.
+    fn parse_text_element(&mut self, tag: &Tag, state: State) {
+        self.insert_html_element(tag);
+        self.tokenizer_state = Some(state);
+        self.original_mode = self.mode;
+        self.mode = Mode::Text;
+    }
+
+    fn initial(&mut self, token: Token) {
+        match token {
+            Token::Characters(ref text) if is_blank(text) => {}
+            Token::Comment(text) => self.insert_comment(text, Some(DOCUMENT)),
+            Token::Doctype(doctype) => {
+                self.quirks = doctype_quirks(
+                    doctype.name.as_ref().map(|name| name.as_str()),
+                    doctype.public_id.as_ref().map(|id| id.as_str()),
+                    doctype.system_id.as_ref().map(|id| id.as_str()),
+                    doctype.force_quirks,
+                );
+                let node = self.new_node(NodeKind::Doctype {
+                    name: doctype.name.unwrap_or_default(),
+                    public_id: doctype.public_id,
+                    system_id: doctype.system_id,
+                });
+                self.append_child(DOCUMENT, node);
+                self.mode = Mode::BeforeHtml;
+            }
+            token => {
+                self.quirks = Quirks::Quirks;
+                self.reprocess(Mode::BeforeHtml, token);
+            }
+        }
+    }
+
+    fn before_html(&mut self, token: Token) {
+        match token {
+            Token::Doctype(_) => {}
+            Token::Comment(text) => self.insert_comment(text, Some(DOCUMENT)),
+            Token::Characters(ref text) if is_blank(text) => {}
+            Token::StartTag(ref tag) if tag.name == "html" => {
+                let node = self.create_element(tag, Namespace::Html);
+                self.append_child(DOCUMENT, node);
+                self.open.push(node);
+                self.mode = Mode::BeforeHead;
+            }
+            Token::EndTag(ref tag)
+                if !["head", "body", "html", "br"].contains(&tag.name.as_str()) => {}
+            token => {
+                let node = self.create_element_with("html".to_string(), Namespace::Html, vec![]);
+                self.append_child(DOCUMENT, node);
+                self.open.push(node);
+                self.reprocess(Mode::BeforeHead, token);
+            }
+        }
+    }
+
+    fn before_head(&mut self, token: Token) {
+        match token {
+            Token::Characters(ref text) if is_blank(text) => {}
+            Token::Comment(text) => self.insert_comment(text, None),
+            Token::Doctype(_) => {}
+            Token::StartTag(ref tag) if tag.name == "html" => {
+                self.in_body(Token::StartTag(tag.clone()))
+            }
+            Token::StartTag(ref tag) if tag.name == "head" => {
+                self.head = Some(self.insert_html_element(tag));
+                self.mode = Mode::InHead;
+            }
+            Token::EndTag(ref tag)
+                if !["head", "body", "html", "br"].contains(&tag.name.as_str()) => {}
+            token => {
+                self.head = Some(self.insert_html_element_named("head"));
+                self.reprocess(Mode::InHead, token);
+            }
+        }
+    }
+
+    fn in_head(&mut self, token: Token) {
+        match token {
+            Token::Characters(ref text) if is_blank(text) => self.insert_characters(text),
+            Token::Comment(text) => self.insert_comment(text, None),
+            Token::Doctype(_) => {}
+            Token::StartTag(tag) => match tag.name.clone().as_str() {
+                "html" => self.in_body(Token::StartTag(tag)),
+                "base" | "basefont" | "bgsound" | "link" | "meta" => {
+                    self.insert_html_element(&tag);
+                    self.open.pop();
+                }
+                "title" => self.parse_text_element(&tag, State::Rcdata),
+                "noframes" | "style" => self.parse_text_element(&tag, State::Rawtext),
+                "noscript" => {
+                    self.insert_html_element(&tag);
+                    self.mode = Mode::InHeadNoscript;
+                }
+                "script" => self.parse_text_element(&tag, State::ScriptData),
+                "template" => {
+                    self.insert_html_element(&tag);
+                    self.formatting.push(None);
+                    self.frameset_ok = false;
+                    self.mode = Mode::InTemplate;
+                    self.template_modes.push(Mode::InTemplate);
+                }
+                "head" => {}
+                _ => {
+                    self.open.pop();
+                    self.reprocess(Mode::AfterHead, Token::StartTag(tag));
+                }
+            },
+            Token::EndTag(tag) => match tag.name.clone().as_str() {
+                "head" => {
+                    self.open.pop();
+                    self.mode = Mode::AfterHead;
+                }
+                "template" => {
+                    if !self.has_template() {
+                        return;
+                    }
+                    self.generate_all_implied_end_tags();
+                    self.pop_until("template");
+                    self.clear_formatting_to_last_marker();
+                    self.template_modes.pop();
+                    self.reset_insertion_mode();
+                }
+                "body" | "html" | "br" => {
+                    self.open.pop();
+                    self.reprocess(Mode::AfterHead, Token::EndTag(tag));
+                }
+                _ => {}
+            },
+            token => {
+                self.open.pop();
+                self.reprocess(Mode::AfterHead, token);
+            }
+        }
+    }
+
+    fn in_head_noscript(&mut self, token: Token) {
+        match token {
+            Token::Doctype(_) => {}
+            Token::StartTag(ref tag) if tag.name == "html" => {
+                self.in_body(Token::StartTag(tag.clone()))
+            }
+            Token::EndTag(ref tag) if tag.name == "noscript" => {
+                self.open.pop();
+                self.mode = Mode::InHead;
+            }
+            Token::Characters(ref text) if is_blank(text) => {
+                self.in_head(Token::Characters(text.clone()))
+            }
+            Token::Comment(text) => self.in_head(Token::Comment(text)),
+            Token::StartTag(ref tag)
+                if ["basefont", "bgsound", "link", "meta", "noframes", "style"]
+                    .contains(&tag.name.as_str()) =>
+            {
+                self.in_head(Token::StartTag(tag.clone()))
+            }
+            Token::StartTag(ref tag) if tag.name == "head" || tag.name == "noscript" => {}
+            Token::EndTag(ref tag) if tag.name != "br" => {}
+            token => {
+                self.open.pop();
+                self.reprocess(Mode::InHead, token);
+            }
+        }
+    }
+
+    fn after_head(&mut self, token: Token) {
+        match token {
+            Token::Characters(ref text) if is_blank(text) => self.insert_characters(text),
+            Token::Comment(text) => self.insert_comment(text, None),
+            Token::Doctype(_) => {}
+            Token::StartTag(tag) => match tag.name.clone().as_str() {
+                "html" => self.in_body(Token::StartTag(tag)),
+                "body" => {
+                    self.insert_html_element(&tag);
+                    self.frameset_ok = false;
+                    self.mode = Mode::InBody;
+                }
+                "frameset" => {
+                    self.insert_html_element(&tag);
+                    self.mode = Mode::InFrameset;
+                }
+                "base" | "basefont" | "bgsound" | "link" | "meta" | "noframes" | "script"
+                | "style" | "template" | "title" => {
+                    let head = self.head.unwrap();
+                    self.open.push(head);
+                    self.in_head(Token::StartTag(tag));
+                    self.remove_from_stack(head);
+                }
+                "head" => {}
+                _ => {
+                    self.insert_html_element_named("body");
+                    self.reprocess(Mode::InBody, Token::StartTag(tag));
+                }
+            },
+            Token::EndTag(ref tag) if tag.name == "template" => {
+                self.in_head(Token::EndTag(tag.clone()))
+            }
+            Token::EndTag(ref tag)
+                if !["body", "html", "br"].contains(&tag.name.as_str()) => {}
+            token => {
+                self.insert_html_element_named("body");
+                self.reprocess(Mode::InBody, token);
+            }
+        }
+    }
+
+    fn in_body(&mut self, token: Token) {
+        match token {
+            Token::Characters(text) => {
+                if text == "\0" {
+                    return;
+                }
+                self.reconstruct_formatting_elements();
+                self.insert_characters(&text);
+                if !is_blank(&text) {
+                    self.frameset_ok = false;
+                }
+            }
+            Token::Comment(text) => self.insert_comment(text, None),
+            Token::Doctype(_) => {}
+            Token::StartTag(tag) => self.in_body_start_tag(tag),
+            Token::EndTag(tag) => self.in_body_end_tag(tag),
+            Token::Eof => {
+                if !self.template_modes.is_empty() {
+                    self.in_template(Token::Eof);
+                } else {
+                    self.stop();
+                }
+            }
+        }
+    }
+
+    /// Add the attributes of TAG which NODE doesn't have to it.
+    fn merge_attributes(&mut self, node: NodeId, tag: &Tag) {
+        if let NodeKind::Element {
+            ref mut attributes,
+            ..
+        } = self.nodes[node].kind
+        {
+            for &(ref name, ref value) in &tag.attributes {
+                if !attributes.iter().any(|attribute| attribute.name == *name) {
+                    attributes.push(Attribute {
+                        name: name.clone(),
+                                value: value.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    fn in_body_start_tag(&mut self, mut tag: Tag) {
+        match tag.name.clone().as_str() {
+            "html" => if !self.has_template() {
+                let html = self.open[0];
+                self.merge_attributes(html, &tag);
+            },
+            "base" | "basefont" | "bgsound" | "link" | "meta" | "noframes" | "script"
+            | "style" | "template" | "title" => self.in_head(Token::StartTag(tag)),
+            "body" => {
+                if self.open.len() > 1 && self.is_html(self.open[1], "body")
+                    && !self.has_template()
+                {
+                    self.frameset_ok = false;
+                    let body = self.open[1];
+                    self.merge_attributes(body, &tag);
+                }
+            }
+            "frameset" => {
+                if self.open.len() > 1 && self.is_html(self.open[1], "body") && self.frameset_ok {
+                    let body = self.open[1];
+                    self.detach(body);
+                    self.open.truncate(1);
+                    self.insert_html_element(&tag);
+                    self.mode = Mode::InFrameset;
+                }
+            }
+            "address" | "article" | "aside" | "blockquote" | "center" | "details" | "dialog"
+            | "dir" | "div" | "dl" | "fieldset" | "figcaption" | "figure" | "footer"
+            | "header" | "hgroup" | "main" | "menu" | "nav" | "ol" | "p" | "search"
+            | "section" | "summary" | "ul" => {
+                self.close_p_in_button_scope();
+                self.insert_html_element(&tag);
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.close_p_in_button_scope();
+                let current = self.current_node();
+                if self.is_html_one_of(current, &["h1", "h2", "h3", "h4", "h5", "h6"]) {
+                    self.open.pop();
+                }
+                self.insert_html_element(&tag);
+            }
+            "pre" | "listing" => {
+                self.close_p_in_button_scope();
+                self.insert_html_element(&tag);
+                self.ignore_newline = true;
+                self.frameset_ok = false;
+            }
+            "form" => {
+                let has_template = self.has_template();
+                if self.form.is_some() && !has_template {
+                    return;
+                }
+                self.close_p_in_button_scope();
+                let node = self.insert_html_element(&tag);
+                if !has_template {
+                    self.form = Some(node);
+                }
+            }
+            "li" | "dd" | "dt" => {
+                self.frameset_ok = false;
+                let closes: &[&str] = if tag.name == "li" {
+                    &["li"]
+                } else {
+                    &["dd", "dt"]
+                };
+                for idx in (0..self.open.len()).rev() {
+                    let node = self.open[idx];
+                    if self.is_html_one_of(node, closes) {
+                        let name = self.name(node).to_string();
+                        self.generate_implied_end_tags(&name);
+                        self.pop_until(&name);
+                        break;
+                    }
+                    if self.is_special(node)
+                        && !self.is_html_one_of(node, &["address", "div", "p"])
+                    {
+                        break;
+                    }
+                }
+                self.close_p_in_button_scope();
+                self.insert_html_element(&tag);
+            }
+            "plaintext" => {
+                self.close_p_in_button_scope();
+                self.insert_html_element(&tag);
+                self.tokenizer_state = Some(State::Plaintext);
+            }
+            "button" => {
+                if self.in_scope("button", Scope::Default) {
+                    self.generate_implied_end_tags("");
+                    self.pop_until("button");
+                }
+                self.reconstruct_formatting_elements();
+                self.insert_html_element(&tag);
+                self.frameset_ok = false;
+            }
+            "a" => {
+                let active_a = self.formatting
+                    .iter()
+                    .rev()
+                    .take_while(|entry| entry.is_some())
+                    .filter_map(|entry| entry.as_ref().map(|&(node, _)| node))
+                    .find(|&node| self.name(node) == "a");
+                if let Some(node) = active_a {
+                    if !self.adoption_agency("a") {
+                        self.any_other_end_tag("a");
+                    }
+                    if let Some(idx) = self.formatting_position(node) {
+                        self.formatting.remove(idx);
+                    }
+                    self.remove_from_stack(node);
+                }
+                self.reconstruct_formatting_elements();
+                let node = self.insert_html_element(&tag);
+                self.push_formatting_element(node, &tag);
+            }
+            "b" | "big" | "code" | "em" | "font" | "i" | "s" | "small" | "strike" | "strong"
+            | "tt" | "u" => {
+                self.reconstruct_formatting_elements();
+                let node = self.insert_html_element(&tag);
+                self.push_formatting_element(node, &tag);
+            }
+            "nobr" => {
+                self.reconstruct_formatting_elements();
+                if self.in_scope("nobr", Scope::Default) {
+                    if !self.adoption_agency("nobr") {
+                        self.any_other_end_tag("nobr");
+                    }
+                    self.reconstruct_formatting_elements();
+                }
+                let node = self.insert_html_element(&tag);
+                self.push_formatting_element(node, &tag);
+            }
+            "applet" | "marquee" | "object" => {
+                self.reconstruct_formatting_elements();
+                self.insert_html_element(&tag);
+                self.formatting.push(None);
+                self.frameset_ok = false;
+            }
+            "table" => {
+                if self.quirks != Quirks::Quirks {
+                    self.close_p_in_button_scope();
+                }
+                self.insert_html_element(&tag);
+                self.frameset_ok = false;
+                self.mode = Mode::InTable;
+            }
+            "area" | "br" | "embed" | "img" | "keygen" | "wbr" => {
+                self.reconstruct_formatting_elements();
+                self.insert_html_element(&tag);
+                self.open.pop();
+                self.frameset_ok = false;
+            }
+            "input" => {
+                self.reconstruct_formatting_elements();
+                self.insert_html_element(&tag);
+                self.open.pop();
+                let hidden = tag.attribute("type")
+                    .map_or(false, |kind| kind.eq_ignore_ascii_case("hidden"));
+                if !hidden {
+                    self.frameset_ok = false;
+                }
+            }
+            "param" | "source" | "track" => {
+                self.insert_html_element(&tag);
+                self.open.pop();
+            }
+            "hr" => {
+                self.close_p_in_button_scope();
+                self.insert_html_element(&tag);
+                self.open.pop();
+                self.frameset_ok = false;
+            }
+            "image" => {
+                tag.name = "img".to_string();
+                self.in_body_start_tag(tag);
+            }
+            "textarea" => {
+                self.insert_html_element(&tag);
+                self.ignore_newline = true;
+                self.tokenizer_state = Some(State::Rcdata);
+                self.original_mode = self.mode;
+                self.frameset_ok = false;
+                self.mode = Mode::Text;
+            }
+            "xmp" => {
+                self.close_p_in_button_scope();
+                self.reconstruct_formatting_elements();
+                self.frameset_ok = false;
+                self.parse_text_element(&tag, State::Rawtext);
+            }
+            "iframe" => {
+                self.frameset_ok = false;
+                self.parse_text_element(&tag, State::Rawtext);
+            }
+            "noembed" => self.parse_text_element(&tag, State::Rawtext),
+            "select" => {
+                self.reconstruct_formatting_elements();
+                self.insert_html_element(&tag);
+                self.frameset_ok = false;
+                self.mode = match self.mode {
+                    Mode::InTable | Mode::InCaption | Mode::InTableBody | Mode::InRow
+                    | Mode::InCell => Mode::InSelectInTable,
+                    _ => Mode::InSelect,
+                };
+            }
+            "optgroup" | "option" => {
+                if self.is_html(self.current_node(), "option") {
+                    self.open.pop();
+                }
+                self.reconstruct_formatting_elements();
+                self.insert_html_element(&tag);
+            }
+            "rb" | "rtc" => {
+                if self.in_scope("ruby", Scope::Default) {
+                    self.generate_implied_end_tags("");
+                }
+                self.insert_html_element(&tag);
+            }
+            "rp" | "rt" => {
+                if self.in_scope("ruby", Scope::Default) {
+                    self.generate_implied_end_tags("rtc");
+                }
+                self.insert_html_element(&tag);
+            }
+            "math" | "svg" => {
+                self.reconstruct_formatting_elements();
+                let namespace = if tag.name == "math" {
+                    Namespace::MathMl
+                } else {
+                    Namespace::Svg
+                };
+                self.insert_foreign_element(&tag, namespace);
+                if tag.self_closing {
+                    self.open.pop();
+                }
+            }
+            "caption" | "col" | "colgroup" | "frame" | "head" | "tbody" | "td" | "tfoot"
+            | "th" | "thead" | "tr" => {}
+            _ => {
+                self.reconstruct_formatting_elements();
+                self.insert_html_element(&tag);
+            }
+        }
+    }
+
+    fn in_body_end_tag(&mut self, tag: Tag) {
+        match tag.name.clone().as_str() {
+            "template" => self.in_head(Token::EndTag(tag)),
+            "body" => if self.in_scope("body", Scope::Default) {
+                self.mode = Mode::AfterBody;
+            },
+            "html" => if self.in_scope("body", Scope::Default) {
+                self.reprocess(Mode::AfterBody, Token::EndTag(tag));
+            },
+            "address" | "article" | "aside" | "blockquote" | "button" | "center" | "details"
+            | "dialog" | "dir" | "div" | "dl" | "fieldset" | "figcaption" | "figure"
+            | "footer" | "header" | "hgroup" | "listing" | "main" | "menu" | "nav" | "ol"
+            | "pre" | "search" | "section" | "summary" | "ul" => {
+                if self.in_scope(&tag.name, Scope::Default) {
+                    self.generate_implied_end_tags("");
+                    self.pop_until(&tag.name);
+                }
+            }
+            "form" => if !self.has_template() {
+                let node = self.form.take();
+                match node {
+                    Some(node) if self.node_in_scope(node) => {
+                        self.generate_implied_end_tags("");
+                        self.remove_from_stack(node);
+                    }
+                    _ => {}
+                }
+            } else if self.in_scope("form", Scope::Default) {
+                self.generate_implied_end_tags("");
+                self.pop_until("form");
+            },
+            "p" => {
+                if !self.in_scope("p", Scope::Button) {
+                    self.insert_html_element_named("p");
+                }
+                self.close_p_element();
+            }
+            "li" => if self.in_scope("li", Scope::ListItem) {
+                self.generate_implied_end_tags("li");
+                self.pop_until("li");
+            },
+            "dd" | "dt" => if self.in_scope(&tag.name, Scope::Default) {
+                self.generate_implied_end_tags(&tag.name);
+                self.pop_until(&tag.name);
+            },
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let headings = ["h1", "h2", "h3", "h4", "h5", "h6"];
+                if self.in_scope_one_of(&headings, Scope::Default) {
+                    self.generate_implied_end_tags("");
+                    self.pop_until_one_of(&headings);
+                }
+            }
+            "a" | "b" | "big" | "code" | "em" | "font" | "i" | "nobr" | "s" | "small"
+            | "strike" | "strong" | "tt" | "u" => if !self.adoption_agency(&tag.name) {
+                self.any_other_end_tag(&tag.name);
+            },
+            "applet" | "marquee" | "object" => if self.in_scope(&tag.name, Scope::Default) {
+                self.generate_implied_end_tags("");
+                self.pop_until(&tag.name);
+                self.clear_formatting_to_last_marker();
+            },
+            "br" => self.in_body_start_tag(Tag {
+                name: "br".to_string(),
+                attributes: Vec::new(),
+                self_closing: false,
+            }),
+            _ => self.any_other_end_tag(&tag.name),
+        }
+    }
+
+    fn any_other_end_tag(&mut self, name: &str) {
+        for idx in (0..self.open.len()).rev() {
+            let node = self.open[idx];
+            if self.is_html(node, name) {
+                self.generate_implied_end_tags(name);
+                self.open.truncate(idx);
+                return;
+            }
+            if self.is_special(node) {
+                return;
+            }
+        }
+    }
+
+    fn text(&mut self, token: Token) {
+        match token {
+            Token::Characters(text) => self.insert_characters(&text),
+            Token::Eof => {
+                self.open.pop();
+                let mode = self.original_mode;
+                self.reprocess(mode, Token::Eof);
+            }
+            _ => {
+                self.open.pop();
+                self.mode = self.original_mode;
+            }
+        }
+    }
+
+    fn in_table(&mut self, token: Token) {
+        match token {
+            Token::Characters(text) => {
+                let current = self.current_node();
+                if self.is_html_one_of(
+                    current,
+                    &["table", "tbody", "template", "tfoot", "thead", "tr"],
+                ) {
+                    self.pending_table_text.clear();
+                    self.original_mode = self.mode;
+                    self.reprocess(Mode::InTableText, Token::Characters(text));
+                } else {
+                    self.in_table_anything_else(Token::Characters(text));
+                }
+            }
+            Token::Comment(text) => self.insert_comment(text, None),
+            Token::Doctype(_) => {}
+            Token::StartTag(tag) => match tag.name.clone().as_str() {
+                "caption" => {
+                    self.clear_stack_back_to(&["table", "template", "html"]);
+                    self.formatting.push(None);
+                    self.insert_html_element(&tag);
+                    self.mode = Mode::InCaption;
+                }
+                "colgroup" => {
+                    self.clear_stack_back_to(&["table", "template", "html"]);
+                    self.insert_html_element(&tag);
+                    self.mode = Mode::InColumnGroup;
+                }
+                "col" => {
+                    self.clear_stack_back_to(&["table", "template", "html"]);
+                    self.insert_html_element_named("colgroup");
+                    self.reprocess(Mode::InColumnGroup, Token::StartTag(tag));
+                }
+                "tbody" | "tfoot" | "thead" => {
+                    self.clear_stack_back_to(&["table", "template", "html"]);
+                    self.insert_html_element(&tag);
+                    self.mode = Mode::InTableBody;
+                }
+                "td" | "th" | "tr" => {
+                    self.clear_stack_back_to(&["table", "template", "html"]);
+                    self.insert_html_element_named("tbody");
+                    self.reprocess(Mode::InTableBody, Token::StartTag(tag));
+                }
+                "table" => if self.in_scope("table", Scope::Table) {
+                    self.pop_until("table");
+                    self.reset_insertion_mode();
+                    let mode = self.mode;
+                    self.process_in(mode, Token::StartTag(tag));
+                },
+                "style" | "script" | "template" => self.in_head(Token::StartTag(tag)),
+                "input"
+                    if tag.attribute("type")
+                        .map_or(false, |kind| kind.eq_ignore_ascii_case("hidden")) =>
+                {
+                    self.insert_html_element(&tag);
+                    self.open.pop();
+                }
+                "form" => if !self.has_template() && self.form.is_none() {
+                    self.form = Some(self.insert_html_element(&tag));
+                    self.open.pop();
+                },
+                _ => self.in_table_anything_else(Token::StartTag(tag)),
+            },
+            Token::EndTag(tag) => match tag.name.clone().as_str() {
+                "table" => if self.in_scope("table", Scope::Table) {
+                    self.pop_until("table");
+                    self.reset_insertion_mode();
+                },
+                "body" | "caption" | "col" | "colgroup" | "html" | "tbody" | "td" | "tfoot"
+                | "th" | "thead" | "tr" => {}
+                "template" => self.in_head(Token::EndTag(tag)),
+                _ => self.in_table_anything_else(Token::EndTag(tag)),
+            },
+            Token::Eof => self.in_body(Token::Eof),
+        }
+    }
+
+    fn in_table_anything_else(&mut self, token: Token) {
+        self.foster_parenting = true;
+        self.in_body(token);
+        self.foster_parenting = false;
+    }
+
+    fn in_table_text(&mut self, token: Token) {
+        match token {
+            Token::Characters(ref text) if text == "\0" => {}
+            Token::Characters(text) => self.pending_table_text.push(text),
+            token => {
+                let pending = mem::replace(&mut self.pending_table_text, Vec::new());
+                if pending.iter().any(|text| !is_blank(text)) {
+                    for text in pending {
+                        self.in_table_anything_else(Token::Characters(text));
+                    }
+                } else {
+                    for text in pending {
+                        self.insert_characters(&text);
+                    }
+                }
+                let mode = self.original_mode;
+                self.reprocess(mode, token);
+            }
+        }
+    }
+
+    /// Close the caption, and return whether there was one.
+    fn close_caption(&mut self) -> bool {
+        if !self.in_scope("caption", Scope::Table) {
+            return false;
+        }
+        self.generate_implied_end_tags("");
+        self.pop_until("caption");
+        self.clear_formatting_to_last_marker();
+        self.mode = Mode::InTable;
+        true
+    }
+
+    fn in_caption(&mut self, token: Token) {
+        match token {
+            Token::EndTag(ref tag) if tag.name == "caption" => {
+                self.close_caption();
+            }
+            Token::StartTag(ref tag)
+                if [
+                    "caption", "col", "colgroup", "tbody", "td", "tfoot", "th", "thead", "tr"
+                ].contains(&tag.name.as_str()) =>
+            {
+                if self.close_caption() {
+                    self.in_table(Token::StartTag(tag.clone()));
+                }
+            }
+            Token::EndTag(ref tag) if tag.name == "table" => if self.close_caption() {
+                self.in_table(Token::EndTag(tag.clone()));
+            },
+            Token::EndTag(ref tag)
+                if [
+                    "body", "col", "colgroup", "html", "tbody", "td", "tfoot", "th", "thead",
+                    "tr",
+                ].contains(&tag.name.as_str()) => {}
+            token => self.in_body(token),
+        }
+    }
+
+    fn in_column_group(&mut self, token: Token) {
+        match token {
+            Token::Characters(ref text) if is_blank(text) => self.insert_characters(text),
+            Token::Comment(text) => self.insert_comment(text, None),
+            Token::Doctype(_) => {}
+            Token::StartTag(ref tag) if tag.name == "html" => {
+                self.in_body(Token::StartTag(tag.clone()))
+            }
+            Token::StartTag(ref tag) if tag.name == "col" => {
+                self.insert_html_element(tag);
+                self.open.pop();
+            }
+            Token::EndTag(ref tag) if tag.name == "colgroup" => {
+                if self.is_html(self.current_node(), "colgroup") {
+                    self.open.pop();
+                    self.mode = Mode::InTable;
+                }
+            }
+            Token::EndTag(ref tag) if tag.name == "col" => {}
+            Token::StartTag(ref tag) if tag.name == "template" => {
+                self.in_head(Token::StartTag(tag.clone()))
+            }
+            Token::EndTag(ref tag) if tag.name == "template" => {
+                self.in_head(Token::EndTag(tag.clone()))
+            }
+            Token::Eof => self.in_body(Token::Eof),
+            token => if self.is_html(self.current_node(), "colgroup") {
+                self.open.pop();
+                self.reprocess(Mode::InTable, token);
+            },
+        }
+    }
+
+    fn in_table_body(&mut self, token: Token) {
+        let body_context = ["tbody", "tfoot", "thead", "template", "html"];
+        match token {
+            Token::StartTag(ref tag) if tag.name == "tr" => {
+                self.clear_stack_back_to(&body_context);
+                self.insert_html_element(tag);
+                self.mode = Mode::InRow;
+            }
+            Token::StartTag(ref tag) if tag.name == "th" || tag.name == "td" => {
+                self.clear_stack_back_to(&body_context);
+                self.insert_html_element_named("tr");
+                self.reprocess(Mode::InRow, Token::StartTag(tag.clone()));
+            }
+            Token::EndTag(ref tag) if ["tbody", "tfoot", "thead"].contains(&tag.name.as_str()) => {
+                if self.in_scope(&tag.name, Scope::Table) {
+                    self.clear_stack_back_to(&body_context);
+                    self.open.pop();
+                    self.mode = Mode::InTable;
+                }
+            }
+            Token::StartTag(ref tag)
+                if ["caption", "col", "colgroup", "tbody", "tfoot", "thead"]
+                    .contains(&tag.name.as_str()) =>
+            {
+                self.end_table_body(Token::StartTag(tag.clone()))
+            }
+            Token::EndTag(ref tag) if tag.name == "table" => {
+                self.end_table_body(Token::EndTag(tag.clone()))
+            }
+            Token::EndTag(ref tag)
+                if ["body", "caption", "col", "colgroup", "html", "td", "th", "tr"]
+                    .contains(&tag.name.as_str()) => {}
+            token => self.in_table(token),
+        }
+    }
+
+    fn end_table_body(&mut self, token: Token) {
+        if self.in_scope_one_of(&["tbody", "thead", "tfoot"], Scope::Table) {
+            self.clear_stack_back_to(&["tbody", "tfoot", "thead", "template", "html"]);
+            self.open.pop();
+            self.reprocess(Mode::InTable, token);
+        }
+    }
+
+    /// Close the row, and return whether there was one.
+    fn close_row(&mut self) -> bool {
+        if !self.in_scope("tr", Scope::Table) {
+            return false;
+        }
+        self.clear_stack_back_to(&["tr", "template", "html"]);
+        self.open.pop();
+        self.mode = Mode::InTableBody;
+        true
+    }
+
+    fn in_row(&mut self, token: Token) {
+        match token {
+            Token::StartTag(ref tag) if tag.name == "th" || tag.name == "td" => {
+                self.clear_stack_back_to(&["tr", "template", "html"]);
+                self.insert_html_element(tag);
+                self.mode = Mode::InCell;
+                self.formatting.push(None);
+            }
+            Token::EndTag(ref tag) if tag.name == "tr" => {
+                self.close_row();
+            }
+            Token::StartTag(ref tag)
+                if ["caption", "col", "colgroup", "tbody", "tfoot", "thead", "tr"]
+                    .contains(&tag.name.as_str()) =>
+            {
+                if self.close_row() {
+                    self.in_table_body(Token::StartTag(tag.clone()));
+                }
+            }
+            Token::EndTag(ref tag) if tag.name == "table" => if self.close_row() {
+                self.in_table_body(Token::EndTag(tag.clone()));
+            },
+            Token::EndTag(ref tag) if ["tbody", "tfoot", "thead"].contains(&tag.name.as_str()) => {
+                if self.in_scope(&tag.name, Scope::Table) && self.close_row() {
+                    self.in_table_body(Token::EndTag(tag.clone()));
+                }
+            }
+            Token::EndTag(ref tag)
+                if ["body", "caption", "col", "colgroup", "html", "td", "th"]
+                    .contains(&tag.name.as_str()) => {}
+            token => self.in_table(token),
+        }
+    }
+
+    fn close_cell(&mut self) {
+        self.generate_implied_end_tags("");
+        self.pop_until_one_of(&["td", "th"]);
+        self.clear_formatting_to_last_marker();
+        self.mode = Mode::InRow;
+    }
+
+    fn in_cell(&mut self, token: Token) {
+        match token {
+            Token::EndTag(ref tag) if tag.name == "td" || tag.name == "th" => {
+                if self.in_scope(&tag.name, Scope::Table) {
+                    self.generate_implied_end_tags("");
+                    self.pop_until(&tag.name);
+                    self.clear_formatting_to_last_marker();
+                    self.mode = Mode::InRow;
+                }
+            }
+            Token::StartTag(ref tag)
+                if [
+                    "caption", "col", "colgroup", "tbody", "td", "tfoot", "th", "thead", "tr"
+                ].contains(&tag.name.as_str()) =>
+            {
+                if self.in_scope_one_of(&["td", "th"], Scope::Table) {
+                    self.close_cell();
+                    self.in_row(Token::StartTag(tag.clone()));
+                }
+            }
+            Token::EndTag(ref tag)
+                if ["body", "caption", "col", "colgroup", "html"].contains(&tag.name.as_str()) => {}
+            Token::EndTag(ref tag)
+                if ["table", "tbody", "tfoot", "thead", "tr"].contains(&tag.name.as_str()) =>
+            {
+                if self.in_scope(&tag.name, Scope::Table) {
+                    self.close_cell();
+                    self.in_row(Token::EndTag(tag.clone()));
+                }
+            }
+            token => self.in_body(token),
+        }
+    }
+
+    fn in_select(&mut self, token: Token) {
+        match token {
+            Token::Characters(ref text) if text == "\0" => {}
+            Token::Characters(text) => self.insert_characters(&text),
+            Token::Comment(text) => self.insert_comment(text, None),
+            Token::Doctype(_) => {}
+            Token::StartTag(tag) => match tag.name.clone().as_str() {
+                "html" => self.in_body(Token::StartTag(tag)),
+                "option" => {
+                    if self.is_html(self.current_node(), "option") {
+                        self.open.pop();
+                    }
+                    self.insert_html_element(&tag);
+                }
+                "optgroup" | "hr" => {
+                    if self.is_html(self.current_node(), "option") {
+                        self.open.pop();
+                    }
+                    if self.is_html(self.current_node(), "optgroup") {
+                        self.open.pop();
+                    }
+                    self.insert_html_element(&tag);
+                    if tag.name == "hr" {
+                        self.open.pop();
+                    }
+                }
+                "select" => if self.in_scope("select", Scope::Select) {
+                    self.pop_until("select");
+                    self.reset_insertion_mode();
+                },
+                "input" | "keygen" | "textarea" => if self.in_scope("select", Scope::Select) {
+                    self.pop_until("select");
+                    self.reset_insertion_mode();
+                    let mode = self.mode;
+                    self.process_in(mode, Token::StartTag(tag));
+                },
+                "script" | "template" => self.in_head(Token::StartTag(tag)),
+                _ => {}
+            },
+            Token::EndTag(tag) => match tag.name.clone().as_str() {
+                "optgroup" => {
+                    let len = self.open.len();
+                    if self.is_html(self.current_node(), "option") && len > 1
+                        && self.is_html(self.open[len - 2], "optgroup")
+                    {
+                        self.open.pop();
+                    }
+                    if self.is_html(self.current_node(), "optgroup") {
+                        self.open.pop();
+                    }
+                }
+                "option" => if self.is_html(self.current_node(), "option") {
+                    self.open.pop();
+                },
+                "select" => if self.in_scope("select", Scope::Select) {
+                    self.pop_until("select");
+                    self.reset_insertion_mode();
+                },
+                "template" => self.in_head(Token::EndTag(tag)),
+                _ => {}
+            },
+            Token::Eof => self.in_body(Token::Eof),
+        }
+    }
+
+    fn in_select_in_table(&mut self, token: Token) {
+        let table_tags = ["caption", "table", "tbody", "tfoot", "thead", "tr", "td", "th"];
+        match token {
+            Token::StartTag(ref tag) if table_tags.contains(&tag.name.as_str()) => {
+                self.pop_until("select");
+                self.reset_insertion_mode();
+                let mode = self.mode;
+                self.process_in(mode, Token::StartTag(tag.clone()));
+            }
+            Token::EndTag(ref tag) if table_tags.contains(&tag.name.as_str()) => {
+                if self.in_scope(&tag.name, Scope::Table) {
+                    self.pop_until("select");
+                    self.reset_insertion_mode();
+                    let mode = self.mode;
+                    self.process_in(mode, Token::EndTag(tag.clone()));
+                }
+            }
+            token => self.in_select(token),
+        }
+    }
+
+    fn in_template(&mut self, token: Token) {
+        match token {
+            Token::Characters(_) | Token::Comment(_) | Token::Doctype(_) => self.in_body(token),
+            Token::StartTag(tag) => {
+                let mode = match tag.name.clone().as_str() {
+                    "base" | "basefont" | "bgsound" | "link" | "meta" | "noframes" | "script"
+                    | "style" | "template" | "title" => {
+                        self.in_head(Token::StartTag(tag));
+                        return;
+                    }
+                    "caption" | "colgroup" | "tbody" | "tfoot" | "thead" => Mode::InTable,
+                    "col" => Mode::InColumnGroup,
+                    "tr" => Mode::InTableBody,
+                    "td" | "th" => Mode::InRow,
+                    _ => Mode::InBody,
+                };
+                self.template_modes.pop();
+                self.template_modes.push(mode);
+                self.reprocess(mode, Token::StartTag(tag));
+            }
+            Token::EndTag(tag) => if tag.name == "template" {
+                self.in_head(Token::EndTag(tag));
+            },
+            Token::Eof => {
+                if !self.has_template() {
+                    self.stop();
+                    return;
+                }
+                self.pop_until("template");
+                self.clear_formatting_to_last_marker();
+                self.template_modes.pop();
+                self.reset_insertion_mode();
+                let mode = self.mode;
+                self.process_in(mode, Token::Eof);
+            }
+        }
+    }
+
+    fn after_body(&mut self, token: Token) {
+        match token {
+            Token::Characters(ref text) if is_blank(text) => {
+                self.in_body(Token::Characters(text.clone()))
+            }
+            Token::Comment(text) => {
+                let html = self.open[0];
+                self.insert_comment(text, Some(html));
+            }
+            Token::Doctype(_) => {}
+            Token::StartTag(ref tag) if tag.name == "html" => {
+                self.in_body(Token::StartTag(tag.clone()))
+            }
+            Token::EndTag(ref tag) if tag.name == "html" => self.mode = Mode::AfterAfterBody,
+            Token::Eof => self.stop(),
+            token => self.reprocess(Mode::InBody, token),
+        }
+    }
+
+    fn in_frameset(&mut self, token: Token) {
+        match token {
+            Token::Characters(ref text) if is_blank(text) => self.insert_characters(text),
+            Token::Comment(text) => self.insert_comment(text, None),
+            Token::StartTag(ref tag) if tag.name == "html" => {
+                self.in_body(Token::StartTag(tag.clone()))
+            }
+            Token::StartTag(ref tag) if tag.name == "frameset" => {
+                self.insert_html_element(tag);
+            }
+            Token::EndTag(ref tag) if tag.name == "frameset" => {
+                if self.open.len() > 1 {
+                    self.open.pop();
+                    if !self.is_html(self.current_node(), "frameset") {
+                        self.mode = Mode::AfterFrameset;
+                    }
+                }
+            }
+            Token::StartTag(ref tag) if tag.name == "frame" => {
+                self.insert_html_element(tag);
+                self.open.pop();
+            }
+            Token::StartTag(ref tag) if tag.name == "noframes" => {
+                self.in_head(Token::StartTag(tag.clone()))
+            }
+            Token::Eof => self.stop(),
+            _ => {}
+        }
+    }
+
+    fn after_frameset(&mut self, token: Token) {
+        match token {
+            Token::Characters(ref text) if is_blank(text) => self.insert_characters(text),
+            Token::Comment(text) => self.insert_comment(text, None),
+            Token::StartTag(ref tag) if tag.name == "html" => {
+                self.in_body(Token::StartTag(tag.clone()))
+            }
+            Token::EndTag(ref tag) if tag.name == "html" => {
+                self.mode = Mode::AfterAfterFrameset
+            }
+            Token::StartTag(ref tag) if tag.name == "noframes" => {
+                self.in_head(Token::StartTag(tag.clone()))
+            }
+            Token::Eof => self.stop(),
+            _ => {}
+        }
+    }
+
+    fn after_after_body(&mut self, token: Token) {
+        match token {
+            Token::Comment(text) => self.insert_comment(text, Some(DOCUMENT)),
+            Token::Doctype(_) => self.in_body(token),
+            Token::Characters(ref text) if is_blank(text) => {
+                self.in_body(Token::Characters(text.clone()))
+            }
+            Token::StartTag(ref tag) if tag.name == "html" => {
+                self.in_body(Token::StartTag(tag.clone()))
+            }
+            Token::Eof => self.stop(),
+            token => self.reprocess(Mode::InBody, token),
+        }
+    }
+
+    fn after_after_frameset(&mut self, token: Token) {
+        match token {
+            Token::Comment(text) => self.insert_comment(text, Some(DOCUMENT)),
+            Token::Doctype(_) => self.in_body(token),
+            Token::Characters(ref text) if is_blank(text) => {
+                self.in_body(Token::Characters(text.clone()))
+            }
+            Token::StartTag(ref tag) if tag.name == "html" => {
+                self.in_body(Token::StartTag(tag.clone()))
+            }
+            Token::Eof => self.stop(),
+            Token::StartTag(ref tag) if tag.name == "noframes" => {
+                self.in_head(Token::StartTag(tag.clone()))
+            }
+            _ => {}
+        }
+    }
+
+    fn foreign_content(&mut self, token: Token) {
+        match token {
+            Token::Characters(ref text) if text == "\0" => self.insert_characters("\u{FFFD}"),
+            Token::Characters(text) => {
+                self.insert_characters(&text);
+                if !is_blank(&text) {
+                    self.frameset_ok = false;
+                }
+            }
+            Token::Comment(text) => self.insert_comment(text, None),
+            Token::Doctype(_) => {}
+            Token::StartTag(ref tag) if breaks_out_of_foreign_content(tag) => {
+                while {
+                    let node = self.current_node();
+                    !(self.is_mathml_text_integration_point(node)
+                        || self.is_html_integration_point(node)
+                        || self.namespace(node) == Namespace::Html)
+                } {
+                    self.open.pop();
+                }
+                let mode = self.mode;
+                self.process_in(mode, Token::StartTag(tag.clone()));
+            }
+            Token::StartTag(tag) => {
+                let namespace = self.namespace(self.current_node());
+                self.insert_foreign_element(&tag, namespace);
+                if tag.self_closing {
+                    self.open.pop();
+                }
+            }
+            Token::EndTag(ref tag) if tag.name == "br" || tag.name == "p" => {
+                while {
+                    let node = self.current_node();
+                    !(self.is_mathml_text_integration_point(node)
+                        || self.is_html_integration_point(node)
+                        || self.namespace(node) == Namespace::Html)
+                } {
+                    self.open.pop();
+                }
+                let mode = self.mode;
+                self.process_in(mode, Token::EndTag(tag.clone()));
+            }
+            Token::EndTag(tag) => {
+                let mut idx = self.open.len() - 1;
+                loop {
+                    if idx == 0 {
+                        return;
+                    }
+                    let node = self.open[idx];
+                    if self.name(node).to_ascii_lowercase() == tag.name {
+                        self.open.truncate(idx);
+                        return;
+                    }
+                    idx -= 1;
+                    if self.namespace(self.open[idx]) == Namespace::Html {
+                        let mode = self.mode;
+                        self.process_in(mode, Token::EndTag(tag));
+                        return;
+                    }
+                }
+            }
+            Token::Eof => {
+                let mode = self.mode;
+                self.process_in(mode, Token::Eof);
+            }
+        }
+    }
+
+    // Conversion to the DOM.
+
+    /// Return the element NODE without its children.
+    fn empty_element(&self, node: NodeId) -> Element {
+        let mut element = Element::new(self.name(node).as_bytes().to_vec());
+        element.attributes = self.attributes(node)
+            .iter()
+            .map(|attribute| {
+                (
+                    attribute.name.clone().into_bytes(),
+                    attribute.value.clone().into_bytes(),
+                )
+            })
+            .collect();
+        element
+    }
+
+    fn to_dom(&self, node: NodeId, depth: usize) -> Option<Node> {
+        match self.nodes[node].kind {
+            NodeKind::Element { .. } => {
+                let mut element = self.empty_element(node);
+                self.children_to_dom(node, depth, &mut element);
+                Some(Node::Element(element))
+            }
+            NodeKind::Text(ref text) => Some(Node::Text(text.clone().into_bytes())),
+            NodeKind::Comment(ref text) => Some(Node::Comment(text.clone().into_bytes())),
+            _ => None,
+        }
+    }
+
+    fn children_to_dom(&self, node: NodeId, depth: usize, element: &mut Element) {
+        if depth < MAX_DEPTH {
+            for &child in &self.nodes[node].children {
+                match self.to_dom(child, depth + 1) {
+                    Some(Node::Text(text)) => element.push_text(&text),
+                    Some(child) => element.children.push(child),
+                    None => {}
+                }
+            }
+            return;
+        }
+        // Flatten the rest of the subtree, in document order.
+        let mut pending: Vec<NodeId> = self.nodes[node].children.iter().rev().cloned().collect();
+        while let Some(child) = pending.pop() {
+            match self.nodes[child].kind {
+                NodeKind::Element { .. } => {
+                    let copy = self.empty_element(child);
+                    element.children.push(Node::Element(copy));
+                    pending.extend(self.nodes[child].children.iter().rev());
+                }
+                NodeKind::Text(ref text) => element.push_text(text.as_bytes()),
+                NodeKind::Comment(ref text) => element
+                    .children
+                    .push(Node::Comment(text.clone().into_bytes())),
+                _ => {}
+            }
+        }
+    }
+
+    /// Resolve the URL of the first `base' element with one relative to
+    /// BASE_URL, the URL of the document, so that it is the base URL of
+    /// the document.  The URLs in other attributes are left as they are.
+    pub fn resolve_base_url(&mut self, base_url: &str) {
+        let base = (0..self.nodes.len()).find(|&node| {
+            self.is_html(node, "base")
+                && self.attributes(node).iter().any(|attribute| attribute.name == "href")
+        });
+        if let Some(base) = base {
+            if let NodeKind::Element { ref mut attributes, .. } = self.nodes[base].kind {
+                for attribute in attributes {
+                    if attribute.name == "href" {
+                        attribute.value = html_url::resolve(base_url, &attribute.value);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Return the nodes at the top level of the document, which are the
+    /// root element and the comments around it.
+    pub fn into_nodes(self) -> Vec<Node> {
+        self.nodes[DOCUMENT]
+            .children
+            .iter()
+            .filter_map(|&node| self.to_dom(node, 1))
+            .collect()
+    }
+}
+
+/// Return the name of an attribute of a foreign element in NAMESPACE
+/// with the name NAME as adjusted by the standard.
+fn adjust_foreign_attribute(name: &str, namespace: Namespace) -> String {
+    let adjusted = match namespace {
+        Namespace::MathMl if name == "definitionurl" => Some("definitionURL"),
+        Namespace::Svg => SVG_ATTRIBUTE_NAMES
+            .iter()
+            .find(|&&(from, _)| from == name)
+            .map(|&(_, to)| to),
+        _ => None,
+    };
+    adjusted.unwrap_or(name).to_string()
+}
+
+/// Return whether the start tag TAG ends foreign content.
+fn breaks_out_of_foreign_content(tag: &Tag) -> bool {
+    match tag.name.as_str() {
+        "b" | "big" | "blockquote" | "body" | "br" | "center" | "code" | "dd" | "div" | "dl"
+        | "dt" | "em" | "embed" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "head" | "hr"
+        | "i" | "img" | "li" | "listing" | "menu" | "meta" | "nobr" | "ol" | "p" | "pre"
+        | "ruby" | "s" | "small" | "span" | "strong" | "strike" | "sub" | "sup" | "table"
+        | "tt" | "u" | "ul" | "var" => true,
+        "font" => ["color", "face", "size"]
+            .iter()
+            .any(|&name| tag.attribute(name).is_some()),
+        _ => false,
+    }
+}
+
+/// Append NODE and its descendants to OUT in the format of the
+/// html5lib tree construction tests.
+#[cfg(test)]
+fn serialize(builder: &TreeBuilder, node: NodeId, depth: usize, out: &mut Vec<String>) {
+    let indent = format!("|{}", " ".repeat((depth * 2).saturating_sub(1)));
+    match builder.nodes[node].kind {
+        NodeKind::Document => {}
+        NodeKind::Doctype {
+            ref name,
+            ref public_id,
+            ref system_id,
+        } => {
+            if public_id.is_some() || system_id.is_some() {
+                out.push(format!(
+                    "{}<!DOCTYPE {} \"{}\" \"{}\">",
+                    indent,
+                    name,
+                    public_id.as_ref().map_or("", |id| id.as_str()),
+                    system_id.as_ref().map_or("", |id| id.as_str())
+                ));
+            } else {
+                out.push(format!("{}<!DOCTYPE {}>", indent, name));
+            }
+        }
+        NodeKind::Element {
+            ref name,
+            namespace,
+            ref attributes,
+        } => {
+            let prefix = match namespace {
+                Namespace::Html => "",
+                Namespace::MathMl => "math ",
+                Namespace::Svg => "svg ",
+            };
+            out.push(format!("{}<{}{}>", indent, prefix, name));
+            let mut attributes: Vec<String> = attributes
+                .iter()
+                .map(|attribute| {
+                    // Attributes in the XLink, XML and XMLNS namespaces
+                    // are printed with the prefix of their namespace.
+                    let prefix = match attribute.name.as_str() {
+                        "xlink:actuate" | "xlink:arcrole" | "xlink:href" | "xlink:role"
+                        | "xlink:show" | "xlink:title" | "xlink:type" => "xlink ",
+                        "xml:lang" | "xml:space" => "xml ",
+                        "xmlns" | "xmlns:xlink" => "xmlns ",
+                        _ => "",
+                    };
+                    if namespace == Namespace::Html || prefix.is_empty() {
+                        format!("{}=\"{}\"", attribute.name, attribute.value)
+                    } else {
+                        let local = attribute.name.rsplit(':').next().unwrap();
+                        format!("{}{}=\"{}\"", prefix, local, attribute.value)
+                    }
+                })
+                .collect();
+            attributes.sort();
+            for attribute in attributes {
+                out.push(format!("{}  {}", indent, attribute));
+            }
+            if namespace == Namespace::Html && name == "template" {
+                out.push(format!("{}  content", indent));
+                for &child in &builder.nodes[node].children {
+                    serialize(builder, child, depth + 2, out);
+                }
+                return;
+            }
+        }
+        NodeKind::Text(ref text) => out.push(format!("{}\"{}\"", indent, text)),
+        NodeKind::Comment(ref text) => out.push(format!("{}<!-- {} -->", indent, text)),
+    }
+    for &child in &builder.nodes[node].children {
+        serialize(builder, child, depth + 1, out);
+    }
+}
+
+#[test]
+fn test_html5lib_tree_construction() {
+    use std::fs::{read_dir, File};
+    use std::io::Read;
+
+    let dir = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../test/data/html5lib/tree-construction"
+    );
+    let mut count = 0;
+    for entry in read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().map_or(true, |extension| extension != "dat") {
+            continue;
+        }
+        let mut contents = String::new();
+        File::open(&path)
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        // Each test is a list of sections, which start with a line
+        // "#NAME", separated by empty lines.
+        for test in contents.split("\n\n#data\n") {
+            let test = test.trim_left_matches("#data\n");
+            let mut sections = Vec::new();
+            for line in test.lines() {
+                if line.starts_with('#') && !line.starts_with("#[") && !line.contains(' ') {
+                    sections.push((&line[1..], Vec::new()));
+                } else if let Some(&mut (_, ref mut lines)) = sections.last_mut() {
+                    lines.push(line);
+                } else {
+                    sections.push(("data", vec![line]));
+                }
+            }
+            let section = |name: &str| {
+                sections
+                    .iter()
+                    .find(|&&(section, _)| section == name)
+                    .map(|&(_, ref lines)| lines.join("\n"))
+            };
+            if section("document-fragment").is_some() || section("script-on").is_some() {
+                continue;
+            }
+            let data = section("data").unwrap_or_default();
+            let expected = section("document").unwrap();
+            let builder = TreeBuilder::build(data.as_bytes());
+            let mut actual = Vec::new();
+            serialize(&builder, DOCUMENT, 0, &mut actual);
+            assert_eq!(
+                actual.join("\n"),
+                expected.trim_right_matches('\n'),
+                "{}: {:?}",
+                path.display(),
+                data
+            );
+            count += 1;
+        }
+    }
+    assert!(count > 0);
+}
+
+#[test]
+fn test_deep_nesting() {
+    let depth = MAX_DEPTH * 4;
+    let mut data = "<div>".repeat(depth);
+    data.push_str("x");
+    let nodes = TreeBuilder::build(data.as_bytes()).into_nodes();
+    let mut node = &nodes[0];
+    let mut levels = 1;
+    while let Node::Element(ref element) = *node {
+        match element.children.last() {
+            Some(child) => node = child,
+            None => break,
+        }
+        levels += 1;
+    }
+    assert!(levels <= MAX_DEPTH + 1);
+    match *node {
+        Node::Text(ref text) => assert_eq!(text, b"x"),
+        _ => panic!("{:?}", node),
+    }
+}
+
+#[test]
+fn test_resolve_base_url() {
+    let mut builder = TreeBuilder::build(
+        b"<base target='_top'><base href='/docs/'><base href='/other/'>\
+          <a href='x.html#y'>a</a><img src='../i.png'>",
+    );
+    builder.resolve_base_url("http://example.org/index.html");
+    let mut out = Vec::new();
+    serialize(&builder, DOCUMENT, 0, &mut out);
+    let out = out.join("\n");
+    assert!(out.contains("href=\"http://example.org/docs/\""), "{}", out);
+    assert!(out.contains("href=\"/other/\""), "{}", out);
+    assert!(out.contains("href=\"x.html#y\""), "{}", out);
+    assert!(out.contains("src=\"../i.png\""), "{}", out);
+}
@@ -0,0 +1,186 @@
+//! Resolving the URLs of HTML attributes, as described in RFC 3986.
+
+/// The components of a URI reference, as split by the regular
+/// expression of RFC 3986, appendix B.
+struct Reference<'a> {
+    scheme: Option<&'a str>,
+    authority: Option<&'a str>,
+    path: &'a str,
+    query: Option<&'a str>,
+    fragment: Option<&'a str>,
+}
+
+impl<'a> Reference<'a> {
+    fn parse(mut reference: &'a str) -> Reference<'a> {
+        let mut fragment = None;
+        if let Some(idx) = reference.find('#') {
+            fragment = Some(&reference[idx + 1..]);
+            reference = &reference[..idx];
+        }
+        let mut query = None;
+        if let Some(idx) = reference.find('?') {
+            query = Some(&reference[idx + 1..]);
+            reference = &reference[..idx];
+        }
+        let mut scheme = None;
+        if let Some(idx) = reference.find(|c: char| c == ':' || c == '/') {
+            if idx > 0 && reference.as_bytes()[idx] == b':' {
+                scheme = Some(&reference[..idx]);
+                reference = &reference[idx + 1..];
+            }
+        }
+        let mut authority = None;
+        if reference.starts_with("//") {
+            let end = reference[2..].find('/').map_or(reference.len(), |idx| idx + 2);
+            authority = Some(&reference[2..end]);
+            reference = &reference[end..];
+        }
+        Reference {
+            scheme,
+            authority,
+            path: reference,
+            query,
+            fragment,
+        }
+    }
+}
+
+/// Return PATH with the "." and ".." segments removed, as described in
+/// RFC 3986, section 5.2.4.
+fn remove_dot_segments(path: &str) -> String {
+    let mut output: Vec<&str> = Vec::new();
+    let mut input = path;
+    while !input.is_empty() {
+        if input.starts_with("../") {
+            input = &input[3..];
+        } else if input.starts_with("./") || input.starts_with("/./") {
+            input = &input[2..];
+        } else if input == "/." {
+            input = "/";
+        } else if input.starts_with("/../") || input == "/.." {
+            input = if input == "/.." { "/" } else { &input[3..] };
+            output.pop();
+        } else if input == "." || input == ".." {
+            input = "";
+        } else {
+            let start = if input.starts_with('/') { 1 } else { 0 };
+            let end = input[start..].find('/').map_or(input.len(), |idx| idx + start);
+            output.push(&input[..end]);
+            input = &input[end..];
+        }
+    }
+    output.concat()
+}
+
+/// Resolve the URI reference REFERENCE relative to the absolute URI
+/// BASE, as described in RFC 3986, section 5.2.
+pub fn resolve(base: &str, reference: &str) -> String {
+    let base = Reference::parse(base);
+    let reference = Reference::parse(reference.trim());
+    let (scheme, authority, path, query);
+    if reference.scheme.is_some() {
+        scheme = reference.scheme;
+        authority = reference.authority;
+        path = remove_dot_segments(reference.path);
+        query = reference.query;
+    } else {
+        scheme = base.scheme;
+        if reference.authority.is_some() {
+            authority = reference.authority;
+            path = remove_dot_segments(reference.path);
+            query = reference.query;
+        } else {
+            authority = base.authority;
+            if reference.path.is_empty() {
+                path = base.path.to_string();
+                query = reference.query.or(base.query);
+            } else {
+                let merged = if reference.path.starts_with('/') {
+                    reference.path.to_string()
+                } else if base.authority.is_some() && base.path.is_empty() {
+                    format!("/{}", reference.path)
+                } else {
+                    let dir = base.path.rfind('/').map_or(0, |idx| idx + 1);
+                    format!("{}{}", &base.path[..dir], reference.path)
+                };
+                path = remove_dot_segments(&merged);
+                query = reference.query;
+            }
+        }
+    }
+    let mut result = String::new();
+    if let Some(scheme) = scheme {
+        result.push_str(scheme);
+        result.push(':');
+    }
+    if let Some(authority) = authority {
+        result.push_str("//");
+        result.push_str(authority);
+    }
+    result.push_str(&path);
+    if let Some(query) = query {
+        result.push('?');
+        result.push_str(query);
+    }
+    if let Some(fragment) = reference.fragment {
+        result.push('#');
+        result.push_str(fragment);
+    }
+    result
+}
+
+#[test]
+fn test_resolve() {
+    // The examples of RFC 3986, section 5.4.
+    let base = "http://a/b/c/d;p?q";
+    let examples = [
+        ("g:h", "g:h"),
+        ("g", "http://a/b/c/g"),
+        ("./g", "http://a/b/c/g"),
+        ("g/", "http://a/b/c/g/"),
+        ("/g", "http://a/g"),
+        ("//g", "http://g"),
+        ("?y", "http://a/b/c/d;p?y"),
+        ("g?y", "http://a/b/c/g?y"),
+        ("#s", "http://a/b/c/d;p?q#s"),
+        ("g#s", "http://a/b/c/g#s"),
+        ("g?y#s", "http://a/b/c/g?y#s"),
+        (";x", "http://a/b/c/;x"),
+        ("g;x", "http://a/b/c/g;x"),
+        ("g;x?y#s", "http://a/b/c/g;x?y#s"),
+        ("", "http://a/b/c/d;p?q"),
+        (".", "http://a/b/c/"),
+        ("./", "http://a/b/c/"),
+        ("..", "http://a/b/"),
+        ("../", "http://a/b/"),
+        ("../g", "http://a/b/g"),
+        ("../..", "http://a/"),
+        ("../../", "http://a/"),
+        ("../../g", "http://a/g"),
+        ("../../../g", "http://a/g"),
+        ("../../../../g", "http://a/g"),
+        ("/./g", "http://a/g"),
+        ("/../g", "http://a/g"),
+        ("g.", "http://a/b/c/g."),
+        (".g", "http://a/b/c/.g"),
+        ("g..", "http://a/b/c/g.."),
+        ("..g", "http://a/b/c/..g"),
+        ("./../g", "http://a/b/g"),
+        ("./g/.", "http://a/b/c/g/"),
+        ("g/./h", "http://a/b/c/g/h"),
+        ("g/../h", "http://a/b/c/h"),
+        ("g;x=1/./y", "http://a/b/c/g;x=1/y"),
+        ("g;x=1/../y", "http://a/b/c/y"),
+        ("g?y/./x", "http://a/b/c/g?y/./x"),
+        ("g?y/../x", "http://a/b/c/g?y/../x"),
+        ("g#s/./x", "http://a/b/c/g#s/./x"),
+        ("g#s/../x", "http://a/b/c/g#s/../x"),
+        ("http:g", "http:g"),
+    ];
+    for &(reference, expected) in &examples {
+        assert_eq!(resolve(base, reference), expected, "{}", reference);
+    }
+    assert_eq!(resolve("http://a", "b"), "http://a/b");
+    assert_eq!(resolve("http://a/b", " c\n"), "http://a/c");
+    assert_eq!(resolve("http://a/b", "mailto:x@y"), "mailto:x@y");
+}
@@ -0,0 +1,174 @@
+//! Parsing XML into Lisp data.
+
+use libc::{c_char, ptrdiff_t};
+
+use remacs_macros::lisp_fn;
+use remacs_sys::{make_string, move_gap_both};
+
+use codec::region_bounds;
+use lisp::{intern, LispObject};
+use lisp::defsubr;
+use obarray::LispObarrayRef;
+use threads::ThreadState;
+
+mod dom;
+mod html_tokenizer;
+mod html_tree;
+mod html_url;
+mod parser;
+
+use self::dom::{line_and_column, Element, Node};
+use self::html_tree::TreeBuilder;
+
+fn make_lisp_string(text: &[u8]) -> LispObject {
+    let length = text.len() as ptrdiff_t;
+    LispObject::from(unsafe { make_string(text.as_ptr() as *const c_char, length) })
+}
+
+fn make_symbol(name: &[u8]) -> LispObject {
+    LispObarrayRef::constant_obarray().intern(make_lisp_string(name))
+}
+
+/// Return NODE as a list `(TAG ATTRIBUTES . CHILDREN)', or a string
+/// for text.
+fn node_to_lisp(node: &Node) -> LispObject {
+    match *node {
+        Node::Element(ref element) => element_to_lisp(element),
+        Node::Text(ref text) | Node::CData(ref text) => make_lisp_string(text),
+        Node::Comment(ref text) => list!(
+            intern("comment"),
+            LispObject::constant_nil(),
+            make_lisp_string(text)
+        ),
+    }
+}
+
+fn element_to_lisp(element: &Element) -> LispObject {
+    let attributes = element
+        .attributes
+        .iter()
+        .rev()
+        .fold(LispObject::constant_nil(), |tail, &(ref name, ref value)| {
+            LispObject::cons(
+                LispObject::cons(make_symbol(name), make_lisp_string(value)),
+                tail,
+            )
+        });
+    let children = element
+        .children
+        .iter()
+        .rev()
+        .fold(LispObject::constant_nil(), |tail, child| {
+            LispObject::cons(node_to_lisp(child), tail)
+        });
+    LispObject::cons(make_symbol(&element.name), LispObject::cons(attributes, children))
+}
+
+/// Return the parse tree of a document with the top-level NODES, which
+/// are the root element and comments around it.
+fn document_to_lisp(mut nodes: Vec<Node>, discard_comments: LispObject) -> LispObject {
+    if discard_comments.is_not_nil() || nodes.len() == 1 {
+        nodes.retain(|node| match *node {
+            Node::Element(_) => true,
+            _ => false,
+        });
+        return node_to_lisp(&nodes[0]);
+    }
+    let nodes = nodes
+        .iter()
+        .rev()
+        .fold(LispObject::constant_nil(), |tail, node| {
+            LispObject::cons(node_to_lisp(node), tail)
+        });
+    LispObject::cons(intern("top"), LispObject::cons(LispObject::constant_nil(), nodes))
+}
+
+/// Return the text of the region between START and END of the current
+/// buffer, moving the gap out of the way if it is in the region.  The
+/// text is only valid until the buffer is changed.
+fn region_text<'a>(start: LispObject, end: LispObject) -> &'a [u8] {
+    let (_, beg_byte, end, end_byte) = region_bounds(start, end);
+    let buffer = ThreadState::current_buffer();
+    if beg_byte < buffer.gpt_byte() && buffer.gpt_byte() < end_byte {
+        unsafe { move_gap_both(end, end_byte) };
+    }
+    let (before_gap, after_gap) = buffer.byte_slices(beg_byte, end_byte);
+    if before_gap.is_empty() {
+        after_gap
+    } else {
+        before_gap
+    }
+}
+
+/// Parse the region as an XML document and return the parse tree.
+/// The document is read as UTF-8.  Each element is returned as a list
+/// (TAG ATTRIBUTES CHILDREN...), where TAG is a symbol, ATTRIBUTES an
+/// alist mapping attribute names to their values, and CHILDREN are
+/// elements, strings for text and CDATA sections, and lists (comment
+/// nil TEXT) for comments.  Names whose namespace prefix is declared
+/// lose the prefix.
+/// If the document has comments outside its root element, the result
+/// is (top nil NODES...), with NODES the root element and those
+/// comments, unless DISCARD-COMMENTS is non-nil.
+/// Entities declared in the document type declaration are expanded,
+/// but external entities aren't loaded.
+/// An error is signaled, with the line and column where parsing failed,
+/// if the document isn't well-formed.
+/// BASE-URL, if non-nil, must be a string; it is accepted for
+/// compatibility with `libxml-parse-html-region', and isn't used.
+#[lisp_fn(min = "2")]
+pub fn libxml_parse_xml_region(
+    start: LispObject,
+    end: LispObject,
+    base_url: LispObject,
+    discard_comments: LispObject,
+) -> LispObject {
+    if base_url.is_not_nil() {
+        base_url.as_string_or_error();
+    }
+    let text = region_text(start, end);
+    let document = match parser::parse(text) {
+        Ok(document) => document,
+        Err(err) => {
+            let (line, column) = line_and_column(text, err.position);
+            error!(
+                "XML parse error at line {}, column {}: {}",
+                line, column, err.message
+            );
+        }
+    };
+    document_to_lisp(document.nodes, discard_comments)
+}
+
+/// Parse the region as an HTML document and return the parse tree.
+/// The document is read as UTF-8 and parsed as described in the HTML
+/// Living Standard, so that the tree is the one web browsers build.
+/// The tree has the same format as that of `libxml-parse-xml-region'.
+/// Elements of SVG and MathML keep the case of their names, like
+/// `clipPath', and the prefixes of names, like `xlink:href'.
+/// If BASE-URL is non-nil, it is the URL of the document, and the URL
+/// of the `base' element of the document, if any, is expanded relative
+/// to it.  Other URLs in the document are left as they are.
+/// If the document has comments outside its root element, the result
+/// is (top nil NODES...), with NODES the root element and those
+/// comments, unless DISCARD-COMMENTS is non-nil.
+#[lisp_fn(min = "2")]
+pub fn libxml_parse_html_region(
+    start: LispObject,
+    end: LispObject,
+    base_url: LispObject,
+    discard_comments: LispObject,
+) -> LispObject {
+    let base_url = if base_url.is_nil() {
+        None
+    } else {
+        Some(base_url.as_string_or_error())
+    };
+    let mut builder = TreeBuilder::build(region_text(start, end));
+    if let Some(base_url) = base_url {
+        builder.resolve_base_url(&String::from_utf8_lossy(base_url.as_slice()));
+    }
+    document_to_lisp(builder.into_nodes(), discard_comments)
+}
+
+include!(concat!(env!("OUT_DIR"), "/xml_exports.rs"));
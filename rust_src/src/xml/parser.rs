@@ -0,0 +1,1027 @@
+//! A non-validating XML 1.0 parser with namespaces.
+//!
+//! Like libxml2 without `XML_PARSE_NOENT', it doesn't load external
+//! entities, but it does expand the entities declared in the internal
+//! subset of the document type declaration.  The expansion is limited,
+//! so that documents which expand to huge amounts of text, like the
+//! "billion laughs" attack, are rejected.
+
+use std::collections::HashMap;
+
+use super::dom::{decode_char, push_char, Element, Node};
+
+/// How deeply entity references can be nested.
+const MAX_ENTITY_DEPTH: usize = 40;
+
+/// How deeply elements can be nested, which is the limit of libxml2
+/// too.  The parser recurses for each element, and so do the functions
+/// which convert the tree to Lisp.
+const MAX_ELEMENT_DEPTH: usize = 256;
+
+/// The number of bytes of entity replacement text which can be parsed
+/// for a document is this many times the size of the document, or
+/// `MIN_EXPANSION_LIMIT' if that is more.
+const EXPANSION_FACTOR: usize = 10;
+const MIN_EXPANSION_LIMIT: usize = 1 << 20;
+
+/// An error in a document, at byte position POSITION.
+#[derive(Debug, PartialEq)]
+pub struct Error {
+    pub position: usize,
+    pub message: String,
+}
+
+type Result<T> = ::std::result::Result<T, Error>;
+
+/// The nodes at the top level of a document, which are the root
+/// element and the comments around it.
+pub struct Document {
+    pub nodes: Vec<Node>,
+}
+
+/// An entity declared in the internal subset.
+enum Entity {
+    Internal(Vec<u8>),
+    /// An external entity, which isn't loaded.
+    External,
+}
+
+fn is_space(byte: u8) -> bool {
+    byte == b' ' || byte == b'\t' || byte == b'\n' || byte == b'\r'
+}
+
+fn is_blank(text: &[u8]) -> bool {
+    text.iter().all(|&byte| is_space(byte))
+}
+
+fn is_char(c: u32) -> bool {
+    match c {
+        0x9 | 0xA | 0xD | 0x20...0xD7FF | 0xE000...0xFFFD | 0x1_0000...0x10_FFFF => true,
+        _ => false,
+    }
+}
+
+fn is_name_start_char(c: u32) -> bool {
+    match c {
+        0x3A | 0x41...0x5A | 0x5F | 0x61...0x7A | 0xC0...0xD6 | 0xD8...0xF6 | 0xF8...0x2FF
+        | 0x370...0x37D | 0x37F...0x1FFF | 0x200C...0x200D | 0x2070...0x218F
+        | 0x2C00...0x2FEF | 0x3001...0xD7FF | 0xF900...0xFDCF | 0xFDF0...0xFFFD
+        | 0x1_0000...0xE_FFFF => true,
+        _ => false,
+    }
+}
+
+fn is_name_char(c: u32) -> bool {
+    is_name_start_char(c) || match c {
+        0x2D | 0x2E | 0x30...0x39 | 0xB7 | 0x300...0x36F | 0x203F...0x2040 => true,
+        _ => false,
+    }
+}
+
+/// Return the offset of the first character of TEXT which isn't
+/// allowed in XML documents, and the error for it.
+fn invalid_char(text: &[u8]) -> Option<(usize, String)> {
+    let mut idx = 0;
+    while idx < text.len() {
+        let byte = text[idx];
+        if byte < 0x80 {
+            if byte < 0x20 && !is_space(byte) {
+                return Some((idx, format!("Char 0x{:X} out of allowed range", byte)));
+            }
+            idx += 1;
+            continue;
+        }
+        match decode_char(&text[idx..]) {
+            Some((c, _)) if !is_char(c) => {
+                return Some((idx, format!("Char 0x{:X} out of allowed range", c)))
+            }
+            Some((_, len)) => idx += len,
+            None => return Some((idx, "Input is not proper UTF-8".to_string())),
+        }
+    }
+    None
+}
+
+/// Append TEXT to OUT with line ends normalized to newlines.
+fn push_normalized(out: &mut Vec<u8>, text: &[u8]) {
+    let mut bytes = text.iter().peekable();
+    while let Some(&byte) = bytes.next() {
+        if byte == b'\r' {
+            if bytes.peek() == Some(&&b'\n') {
+                bytes.next();
+            }
+            out.push(b'\n');
+        } else {
+            out.push(byte);
+        }
+    }
+}
+
+/// A position in some text, either the document or the replacement
+/// text of an entity.
+#[derive(Clone, Copy)]
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(data: &'a [u8]) -> Cursor<'a> {
+        Cursor { data, pos: 0 }
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).cloned()
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        let data = self.data;
+        &data[self.pos..]
+    }
+
+    fn starts_with(&self, prefix: &[u8]) -> bool {
+        self.rest().starts_with(prefix)
+    }
+
+    fn eat(&mut self, prefix: &[u8]) -> bool {
+        let found = self.starts_with(prefix);
+        if found {
+            self.pos += prefix.len();
+        }
+        found
+    }
+
+    /// Skip white space, and return whether there was any.
+    fn skip_space(&mut self) -> bool {
+        let start = self.pos;
+        while self.peek().map_or(false, is_space) {
+            self.pos += 1;
+        }
+        self.pos > start
+    }
+
+    /// Return the offset of NEEDLE in the rest of the text.
+    fn find(&self, needle: &[u8]) -> Option<usize> {
+        self.rest()
+            .windows(needle.len())
+            .position(|window| window == needle)
+    }
+
+    /// Return the text up to NEEDLE, and move past NEEDLE.
+    fn take_until(&mut self, needle: &[u8]) -> Option<&'a [u8]> {
+        let len = self.find(needle)?;
+        let text = &self.rest()[..len];
+        self.pos += len + needle.len();
+        Some(text)
+    }
+
+    fn name(&mut self) -> Option<&'a [u8]> {
+        let start = self.pos;
+        while let Some(byte) = self.peek() {
+            let (c, len) = if byte < 0x80 {
+                (u32::from(byte), 1)
+            } else {
+                match decode_char(self.rest()) {
+                    Some(decoded) => decoded,
+                    None => break,
+                }
+            };
+            let valid = if self.pos == start {
+                is_name_start_char(c)
+            } else {
+                is_name_char(c)
+            };
+            if !valid {
+                break;
+            }
+            self.pos += len;
+        }
+        if self.pos == start {
+            return None;
+        }
+        let data = self.data;
+        Some(&data[start..self.pos])
+    }
+
+    /// Parse a character reference, after its "&#".
+    fn char_ref(&mut self) -> ::std::result::Result<u32, String> {
+        let radix = if self.eat(b"x") { 16 } else { 10 };
+        let mut value = 0u32;
+        let mut digits = 0;
+        while let Some(digit) = self.peek().and_then(|byte| (byte as char).to_digit(radix)) {
+            value = value.saturating_mul(radix).saturating_add(digit);
+            digits += 1;
+            self.pos += 1;
+        }
+        if digits == 0 || !self.eat(b";") {
+            return Err("CharRef: invalid decimal value".to_string());
+        }
+        if !is_char(value) {
+            return Err(format!("xmlParseCharRef: invalid xmlChar value {}", value));
+        }
+        Ok(value)
+    }
+
+    /// Parse a quoted literal, and return its text without the quotes.
+    fn quoted(&mut self) -> Option<&'a [u8]> {
+        let quote = self.peek()?;
+        if quote != b'"' && quote != b'\'' {
+            return None;
+        }
+        self.pos += 1;
+        self.take_until(&[quote])
+    }
+
+    /// Parse a comment, after its "<!--".
+    fn comment(&mut self) -> ::std::result::Result<Vec<u8>, String> {
+        let len = self.find(b"--")
+            .ok_or_else(|| "Comment not terminated".to_string())?;
+        let text = &self.rest()[..len];
+        if let Some((idx, message)) = invalid_char(text) {
+            self.pos += idx;
+            return Err(message);
+        }
+        self.pos += len;
+        if !self.eat(b"-->") {
+            return Err("Double hyphen within comment".to_string());
+        }
+        let mut comment = Vec::with_capacity(text.len());
+        push_normalized(&mut comment, text);
+        Ok(comment)
+    }
+
+    /// Skip a processing instruction, after its "<?".
+    fn processing_instruction(&mut self) -> ::std::result::Result<(), String> {
+        let target = self.name()
+            .ok_or_else(|| "xmlParsePI : no target name".to_string())?;
+        if target.eq_ignore_ascii_case(b"xml") {
+            return Err("XML declaration allowed only at the start of the document".to_string());
+        }
+        if !self.skip_space() && !self.starts_with(b"?>") {
+            return Err("ParsePI: PI xml space expected".to_string());
+        }
+        self.take_until(b"?>")
+            .map(|_| ())
+            .ok_or_else(|| "PI not terminated".to_string())
+    }
+}
+
+/// The state of the parser of the content of a document.
+struct Parser<'a> {
+    input: Cursor<'a>,
+    /// The entities being expanded, innermost last: the input to go
+    /// back to after each, its name and the position of the reference
+    /// to it.
+    entity_stack: Vec<(Cursor<'a>, &'a [u8], usize)>,
+    entities: &'a HashMap<Vec<u8>, Entity>,
+    /// Whether references to undeclared entities are allowed, because
+    /// they may be declared in a part of the DTD which isn't read.
+    undeclared_entities_ok: bool,
+    expanded: usize,
+    expansion_limit: usize,
+    /// The namespace prefixes in scope and their URIs.
+    namespaces: Vec<(&'a [u8], Vec<u8>)>,
+    /// Whether white space is preserved, as given by `xml:space'.
+    preserve_space: Vec<bool>,
+}
+
+impl<'a> Parser<'a> {
+    fn error<S: Into<String>>(&self, message: S) -> Error {
+        let position = self.entity_stack
+            .first()
+            .map_or(self.input.pos, |&(_, _, position)| position);
+        Error {
+            position,
+            message: message.into(),
+        }
+    }
+
+    fn check_chars(&mut self, text: &[u8]) -> Result<()> {
+        match invalid_char(text) {
+            Some((idx, message)) => {
+                self.input.pos += idx;
+                Err(self.error(message))
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn enter_entity(&mut self, name: &'a [u8], start: usize) -> Result<()> {
+        let value = match self.entities.get(name) {
+            Some(&Entity::Internal(ref value)) => value,
+            _ => unreachable!(),
+        };
+        if self.entity_stack.len() >= MAX_ENTITY_DEPTH
+            || self.entity_stack.iter().any(|&(_, entity, _)| entity == name)
+        {
+            return Err(self.error(format!(
+                "Detected an entity reference loop in '{}'",
+                String::from_utf8_lossy(name)
+            )));
+        }
+        self.expanded += value.len();
+        if self.expanded > self.expansion_limit {
+            return Err(self.error("Maximum entity amplification factor exceeded"));
+        }
+        self.entity_stack.push((self.input, name, start));
+        self.input = Cursor::new(value);
+        Ok(())
+    }
+
+    fn leave_entity(&mut self) {
+        let (input, _, _) = self.entity_stack.pop().unwrap();
+        self.input = input;
+    }
+
+    /// Parse an entity reference after its "&", and return the entity
+    /// if it should be expanded, or the character it stands for.
+    fn entity_ref(&mut self) -> Result<Option<::std::result::Result<&'a [u8], u8>>> {
+        let name = match self.input.name() {
+            Some(name) => name,
+            None => return Err(self.error("xmlParseEntityRef: no name")),
+        };
+        if !self.input.eat(b";") {
+            return Err(self.error("EntityRef: expecting ';'"));
+        }
+        let predefined = match name {
+            b"lt" => Some(b'<'),
+            b"gt" => Some(b'>'),
+            b"amp" => Some(b'&'),
+            b"apos" => Some(b'\''),
+            b"quot" => Some(b'"'),
+            _ => None,
+        };
+        if let Some(byte) = predefined {
+            return Ok(Some(Err(byte)));
+        }
+        match self.entities.get(name) {
+            Some(&Entity::Internal(_)) => Ok(Some(Ok(name))),
+            Some(&Entity::External) => Ok(None),
+            None if self.undeclared_entities_ok => Ok(None),
+            None => Err(self.error(format!(
+                "Entity '{}' not defined",
+                String::from_utf8_lossy(name)
+            ))),
+        }
+    }
+
+    /// Append the text of an attribute value to VALUE, up to the
+    /// closing QUOTE, or up to the end of the replacement text of an
+    /// entity if QUOTE is None.
+    fn attribute_text(&mut self, value: &mut Vec<u8>, quote: Option<u8>) -> Result<()> {
+        loop {
+            let rest = self.input.rest();
+            match rest.first() {
+                None if quote.is_none() => return Ok(()),
+                None => return Err(self.error("AttValue: ' expected")),
+                Some(&byte) if Some(byte) == quote => {
+                    self.input.pos += 1;
+                    return Ok(());
+                }
+                Some(&b'<') => {
+                    return Err(self.error("Unescaped '<' not allowed in attributes values"))
+                }
+                Some(&b'&') => {
+                    let start = self.input.pos;
+                    self.input.pos += 1;
+                    if self.input.eat(b"#") {
+                        let c = self.input.char_ref().map_err(|message| self.error(message))?;
+                        push_char(value, c);
+                        continue;
+                    }
+                    match self.entity_ref()? {
+                        Some(Err(byte)) => value.push(byte),
+                        Some(Ok(name)) => {
+                            self.enter_entity(name, start)?;
+                            self.attribute_text(value, None)?;
+                            self.leave_entity();
+                        }
+                        None => {
+                            let name = &self.input.data[start + 1..self.input.pos - 1];
+                            if let Some(&Entity::External) = self.entities.get(name) {
+                                return Err(self.error(format!(
+                                    "Attribute references external entity '{}'",
+                                    String::from_utf8_lossy(name)
+                                )));
+                            }
+                        }
+                    }
+                }
+                Some(_) => {
+                    let len = rest.iter()
+                        .position(|&byte| Some(byte) == quote || byte == b'<' || byte == b'&')
+                        .unwrap_or_else(|| rest.len());
+                    let text = &rest[..len];
+                    self.check_chars(text)?;
+                    let mut bytes = text.iter().peekable();
+                    while let Some(&byte) = bytes.next() {
+                        if byte == b'\r' && bytes.peek() == Some(&&b'\n') {
+                            bytes.next();
+                        }
+                        value.push(if is_space(byte) { b' ' } else { byte });
+                    }
+                    self.input.pos += len;
+                }
+            }
+        }
+    }
+
+    /// Return the namespace URI bound to PREFIX, if any.
+    fn namespace(&self, prefix: &[u8]) -> Option<Vec<u8>> {
+        if prefix == b"xml" {
+            return Some(b"http://www.w3.org/XML/1998/namespace".to_vec());
+        }
+        self.namespaces
+            .iter()
+            .rev()
+            .find(|&&(bound, _)| bound == prefix)
+            .map(|&(_, ref uri)| uri.clone())
+    }
+
+    /// Return the namespace URI and the local part of the qualified
+    /// NAME.  If its prefix isn't bound, it is kept in the local part,
+    /// as libxml2 does.
+    fn resolve(&self, name: &'a [u8]) -> (Option<Vec<u8>>, &'a [u8]) {
+        if let Some(colon) = name.iter().position(|&byte| byte == b':') {
+            if let Some(uri) = self.namespace(&name[..colon]) {
+                return (Some(uri), &name[colon + 1..]);
+            }
+        }
+        (None, name)
+    }
+
+    /// Parse an element, starting at its "<".
+    fn element(&mut self) -> Result<Element> {
+        self.input.pos += 1;
+        let name = match self.input.name() {
+            Some(name) => name,
+            None => return Err(self.error("StartTag: invalid element name")),
+        };
+
+        let mut attributes: Vec<(&'a [u8], Vec<u8>)> = Vec::new();
+        let empty = loop {
+            let space = self.input.skip_space();
+            if self.input.eat(b">") {
+                break false;
+            }
+            if self.input.eat(b"/>") {
+                break true;
+            }
+            if self.input.at_end() {
+                return Err(self.error(format!(
+                    "Premature end of data in tag {}",
+                    String::from_utf8_lossy(name)
+                )));
+            }
+            if !space {
+                return Err(self.error("attributes construct error"));
+            }
+            let attribute = match self.input.name() {
+                Some(attribute) => attribute,
+                None => return Err(self.error("attributes construct error")),
+            };
+            self.input.skip_space();
+            if !self.input.eat(b"=") {
+                return Err(self.error(format!(
+                    "Specification mandates value for attribute {}",
+                    String::from_utf8_lossy(attribute)
+                )));
+            }
+            self.input.skip_space();
+            let quote = match self.input.peek() {
+                Some(quote) if quote == b'"' || quote == b'\'' => quote,
+                _ => return Err(self.error("AttValue: \" or ' expected")),
+            };
+            self.input.pos += 1;
+            let mut value = Vec::new();
+            self.attribute_text(&mut value, Some(quote))?;
+            if attributes.iter().any(|&(other, _)| other == attribute) {
+                return Err(self.error(format!(
+                    "Attribute {} redefined",
+                    String::from_utf8_lossy(attribute)
+                )));
+            }
+            attributes.push((attribute, value));
+        };
+
+        let scope = self.namespaces.len();
+        let mut preserve_space = self.preserve_space.last().cloned().unwrap_or(false);
+        for &(attribute, ref value) in &attributes {
+            if attribute.starts_with(b"xmlns:") {
+                self.namespaces.push((&attribute[6..], value.clone()));
+            } else if attribute == b"xml:space" {
+                if &value[..] == b"preserve" {
+                    preserve_space = true;
+                } else if &value[..] == b"default" {
+                    preserve_space = false;
+                }
+            }
+        }
+
+        let mut element = Element::new(self.resolve(name).1.to_vec());
+        let mut expanded_names: Vec<(Option<Vec<u8>>, &[u8])> = Vec::new();
+        for (attribute, value) in attributes {
+            if attribute == b"xmlns" || attribute.starts_with(b"xmlns:") {
+                continue;
+            }
+            let (uri, local) = self.resolve(attribute);
+            if let Some(ref uri) = uri {
+                if expanded_names
+                    .iter()
+                    .any(|&(ref other, name)| other.as_ref() == Some(uri) && name == local)
+                {
+                    return Err(self.error(format!(
+                        "Namespaced Attribute {} in '{}' redefined",
+                        String::from_utf8_lossy(local),
+                        String::from_utf8_lossy(uri)
+                    )));
+                }
+            }
+            expanded_names.push((uri, local));
+            element.attributes.push((local.to_vec(), value));
+        }
+
+        if !empty {
+            if self.preserve_space.len() >= MAX_ELEMENT_DEPTH {
+                return Err(self.error("Excessive depth in document"));
+            }
+            self.preserve_space.push(preserve_space);
+            self.content(&mut element)?;
+            self.preserve_space.pop();
+            if !self.input.eat(b"</") {
+                return Err(if self.entity_stack.is_empty() {
+                    self.error(format!(
+                        "Premature end of data in tag {}",
+                        String::from_utf8_lossy(name)
+                    ))
+                } else {
+                    self.error("Entity content is not well balanced")
+                });
+            }
+            let end = self.input.name();
+            self.input.skip_space();
+            if end != Some(name) || !self.input.eat(b">") {
+                return Err(self.error(format!(
+                    "Opening and ending tag mismatch: {} and {}",
+                    String::from_utf8_lossy(name),
+                    String::from_utf8_lossy(end.unwrap_or(b""))
+                )));
+            }
+        }
+        self.namespaces.truncate(scope);
+        Ok(element)
+    }
+
+    /// Add TEXT to the children of ELEMENT and clear it, unless it is
+    /// white space which libxml2 would drop with `XML_PARSE_NOBLANKS'.
+    /// BEFORE_TAG says whether TEXT is followed by a tag, and
+    /// BEFORE_END_TAG whether that is the end tag of ELEMENT.
+    fn flush_text(
+        &self,
+        element: &mut Element,
+        text: &mut Vec<u8>,
+        before_tag: bool,
+        before_end_tag: bool,
+    ) {
+        let ignorable = is_blank(text) && before_tag
+            && !self.preserve_space.last().cloned().unwrap_or(false)
+            && !(element.children.is_empty() && before_end_tag)
+            && match (element.children.first(), element.children.last()) {
+                (_, Some(&Node::Text(_))) | (Some(&Node::Text(_)), _) => false,
+                _ => true,
+            };
+        if !ignorable {
+            element.push_text(text);
+        }
+        text.clear();
+    }
+
+    /// Parse the content of ELEMENT, up to its end tag or the end of the
+    /// input.
+    fn content(&mut self, element: &mut Element) -> Result<()> {
+        let mut text = Vec::new();
+        loop {
+            let rest = self.input.rest();
+            match rest.first() {
+                None => {
+                    self.flush_text(element, &mut text, false, false);
+                    return Ok(());
+                }
+                Some(&b'<') => {
+                    let end_tag = rest.starts_with(b"</");
+                    self.flush_text(element, &mut text, true, end_tag);
+                    if end_tag {
+                        return Ok(());
+                    }
+                    if self.input.eat(b"<!--") {
+                        let comment = self.input.comment().map_err(|message| self.error(message))?;
+                        element.children.push(Node::Comment(comment));
+                    } else if self.input.eat(b"<![CDATA[") {
+                        let data = match self.input.take_until(b"]]>") {
+                            Some(data) => data,
+                            None => return Err(self.error("CData section not finished")),
+                        };
+                        self.input.pos -= data.len() + 3;
+                        self.check_chars(data)?;
+                        self.input.pos += data.len() + 3;
+                        let mut cdata = Vec::with_capacity(data.len());
+                        push_normalized(&mut cdata, data);
+                        element.children.push(Node::CData(cdata));
+                    } else if self.input.eat(b"<?") {
+                        self.input
+                            .processing_instruction()
+                            .map_err(|message| self.error(message))?;
+                    } else {
+                        let child = self.element()?;
+                        element.children.push(Node::Element(child));
+                    }
+                }
+                Some(&b'&') => {
+                    let start = self.input.pos;
+                    self.input.pos += 1;
+                    if self.input.eat(b"#") {
+                        let c = self.input.char_ref().map_err(|message| self.error(message))?;
+                        push_char(&mut text, c);
+                        continue;
+                    }
+                    match self.entity_ref()? {
+                        Some(Err(byte)) => text.push(byte),
+                        Some(Ok(name)) => {
+                            self.flush_text(element, &mut text, false, false);
+                            self.enter_entity(name, start)?;
+                            self.content(element)?;
+                            if !self.input.at_end() {
+                                return Err(self.error("Entity content is not well balanced"));
+                            }
+                            self.leave_entity();
+                        }
+                        None => {}
+                    }
+                }
+                Some(_) => {
+                    let len = rest.iter()
+                        .position(|&byte| byte == b'<' || byte == b'&')
+                        .unwrap_or_else(|| rest.len());
+                    let chunk = &rest[..len];
+                    if let Some(idx) = chunk.windows(3).position(|window| window == b"]]>") {
+                        self.input.pos += idx;
+                        return Err(self.error("Sequence ']]>' not allowed in content"));
+                    }
+                    self.check_chars(chunk)?;
+                    push_normalized(&mut text, chunk);
+                    self.input.pos += len;
+                }
+            }
+        }
+    }
+}
+
+fn error_at<T>(cursor: &Cursor, message: &str) -> Result<T> {
+    Err(Error {
+        position: cursor.pos,
+        message: message.to_string(),
+    })
+}
+
+/// Parse an entity declaration in the internal subset, after its
+/// "<!ENTITY", and add it to ENTITIES unless it is already declared.
+fn entity_declaration(
+    cursor: &mut Cursor,
+    entities: &mut HashMap<Vec<u8>, Entity>,
+) -> Result<()> {
+    if !cursor.skip_space() {
+        return error_at(cursor, "Space required after '<!ENTITY'");
+    }
+    if cursor.eat(b"%") {
+        // Parameter entities are only used in declarations, which
+        // aren't read, so they are skipped.
+        return skip_declaration(cursor);
+    }
+    let name = match cursor.name() {
+        Some(name) => name.to_vec(),
+        None => return error_at(cursor, "xmlParseEntityDecl: no name"),
+    };
+    if !cursor.skip_space() {
+        return error_at(cursor, "Space required after the entity name");
+    }
+    let quote = cursor.peek();
+    let entity = if quote == Some(b'"') || quote == Some(b'\'') {
+        let quote = quote.unwrap();
+        cursor.pos += 1;
+        let mut value = Vec::new();
+        loop {
+            match cursor.peek() {
+                None => return error_at(cursor, "EntityValue: \" or ' expected"),
+                Some(byte) if byte == quote => {
+                    cursor.pos += 1;
+                    break;
+                }
+                Some(b'%') => {
+                    return error_at(cursor, "PEReferences forbidden in internal subset")
+                }
+                Some(b'&') if cursor.starts_with(b"&#") => {
+                    cursor.pos += 2;
+                    match cursor.char_ref() {
+                        Ok(c) => push_char(&mut value, c),
+                        Err(message) => return error_at(cursor, &message),
+                    }
+                }
+                Some(_) => {
+                    let rest = cursor.rest();
+                    // References to general entities are kept as they are.
+                    let len = 1 + rest[1..]
+                        .iter()
+                        .position(|&byte| byte == quote || byte == b'%' || byte == b'&')
+                        .unwrap_or_else(|| rest.len() - 1);
+                    if let Some((idx, message)) = invalid_char(&rest[..len]) {
+                        cursor.pos += idx;
+                        return error_at(cursor, &message);
+                    }
+                    push_normalized(&mut value, &rest[..len]);
+                    cursor.pos += len;
+                }
+            }
+        }
+        Entity::Internal(value)
+    } else {
+        external_id(cursor)?;
+        Entity::External
+    };
+    skip_declaration(cursor)?;
+    entities.entry(name).or_insert(entity);
+    Ok(())
+}
+
+/// Parse an external identifier, and return whether there was one.
+fn external_id(cursor: &mut Cursor) -> Result<bool> {
+    let literals = if cursor.eat(b"SYSTEM") {
+        1
+    } else if cursor.eat(b"PUBLIC") {
+        2
+    } else {
+        return Ok(false);
+    };
+    for _ in 0..literals {
+        cursor.skip_space();
+        if cursor.quoted().is_none() {
+            return error_at(cursor, "SYSTEM or PUBLIC, the URI is missing");
+        }
+    }
+    Ok(true)
+}
+
+/// Skip the rest of a markup declaration, up to its ">".
+fn skip_declaration(cursor: &mut Cursor) -> Result<()> {
+    loop {
+        match cursor.peek() {
+            None => return error_at(cursor, "Couldn't find end of declaration"),
+            Some(b'>') => {
+                cursor.pos += 1;
+                return Ok(());
+            }
+            Some(b'"') | Some(b'\'') => {
+                if cursor.quoted().is_none() {
+                    return error_at(cursor, "Unfinished literal in declaration");
+                }
+            }
+            Some(_) => cursor.pos += 1,
+        }
+    }
+}
+
+/// Parse a document type declaration, after its "<!DOCTYPE", and
+/// return whether it refers to declarations which aren't read.
+fn doctype(cursor: &mut Cursor, entities: &mut HashMap<Vec<u8>, Entity>) -> Result<bool> {
+    cursor.skip_space();
+    if cursor.name().is_none() {
+        return error_at(cursor, "xmlParseDocTypeDecl : no DOCTYPE name !");
+    }
+    cursor.skip_space();
+    let mut incomplete = external_id(cursor)?;
+    cursor.skip_space();
+    if cursor.eat(b"[") {
+        loop {
+            cursor.skip_space();
+            if cursor.eat(b"]") {
+                break;
+            } else if cursor.eat(b"<!--") {
+                if let Err(message) = cursor.comment() {
+                    return error_at(cursor, &message);
+                }
+            } else if cursor.eat(b"<?") {
+                if let Err(message) = cursor.processing_instruction() {
+                    return error_at(cursor, &message);
+                }
+            } else if cursor.eat(b"<!ENTITY") {
+                entity_declaration(cursor, entities)?;
+            } else if cursor.eat(b"<!ELEMENT") || cursor.eat(b"<!ATTLIST")
+                || cursor.eat(b"<!NOTATION")
+            {
+                skip_declaration(cursor)?;
+            } else if cursor.eat(b"%") {
+                if cursor.name().is_none() || !cursor.eat(b";") {
+                    return error_at(cursor, "PEReference: expecting ';'");
+                }
+                incomplete = true;
+            } else {
+                return error_at(cursor, "Error detected in markup declaration");
+            }
+        }
+        cursor.skip_space();
+    }
+    if !cursor.eat(b">") {
+        return error_at(cursor, "DOCTYPE improperly terminated");
+    }
+    Ok(incomplete)
+}
+
+/// Parse the comments, processing instructions and white space at the
+/// top level of a document, adding the comments to NODES.
+fn misc(cursor: &mut Cursor, nodes: &mut Vec<Node>) -> Result<()> {
+    loop {
+        cursor.skip_space();
+        if cursor.eat(b"<!--") {
+            match cursor.comment() {
+                Ok(comment) => nodes.push(Node::Comment(comment)),
+                Err(message) => return error_at(cursor, &message),
+            }
+        } else if cursor.eat(b"<?") {
+            if let Err(message) = cursor.processing_instruction() {
+                return error_at(cursor, &message);
+            }
+        } else {
+            return Ok(());
+        }
+    }
+}
+
+/// Parse the XML document DATA.
+pub fn parse(data: &[u8]) -> Result<Document> {
+    let mut cursor = Cursor::new(data);
+    cursor.eat(b"\xEF\xBB\xBF");
+    if cursor.starts_with(b"<?xml") && cursor.data.get(5).map_or(false, |&byte| is_space(byte)) {
+        if cursor.take_until(b"?>").is_none() {
+            return error_at(&cursor, "parsing XML declaration: '?>' expected");
+        }
+    }
+
+    let mut nodes = Vec::new();
+    let mut entities = HashMap::new();
+    let mut incomplete_dtd = false;
+    misc(&mut cursor, &mut nodes)?;
+    if cursor.eat(b"<!DOCTYPE") {
+        incomplete_dtd = doctype(&mut cursor, &mut entities)?;
+        misc(&mut cursor, &mut nodes)?;
+    }
+    if cursor.at_end() {
+        return error_at(&cursor, "Document is empty");
+    }
+    if cursor.peek() != Some(b'<') {
+        return error_at(&cursor, "Start tag expected, '<' not found");
+    }
+
+    let root = {
+        let mut parser = Parser {
+            input: cursor,
+            entity_stack: Vec::new(),
+            entities: &entities,
+            undeclared_entities_ok: incomplete_dtd,
+            expanded: 0,
+            expansion_limit: ::std::cmp::max(data.len() * EXPANSION_FACTOR, MIN_EXPANSION_LIMIT),
+            namespaces: Vec::new(),
+            preserve_space: Vec::new(),
+        };
+        let root = parser.element()?;
+        cursor = parser.input;
+        root
+    };
+    nodes.push(Node::Element(root));
+    misc(&mut cursor, &mut nodes)?;
+    if !cursor.at_end() {
+        return error_at(&cursor, "Extra content at the end of the document");
+    }
+    Ok(Document { nodes })
+}
+
+#[cfg(test)]
+fn root(data: &str) -> Element {
+    match parse(data.as_bytes()).unwrap().nodes.pop() {
+        Some(Node::Element(element)) => element,
+        _ => panic!("no root element"),
+    }
+}
+
+#[cfg(test)]
+fn error(data: &str) -> Error {
+    parse(data.as_bytes()).err().unwrap()
+}
+
+#[test]
+fn test_parse_blanks() {
+    let a = root("<a>\n  <b/>\n  <c>x</c>\n</a>");
+    assert_eq!(a.children.len(), 2);
+    assert_eq!(root("<a> </a>").children, vec![Node::Text(b" ".to_vec())]);
+    assert_eq!(root("<a>x<b/> </a>").children.len(), 3);
+    assert_eq!(root("<a xml:space='preserve'> <b/> </a>").children.len(), 3);
+    assert_eq!(
+        root("<a>\r\nx\ry</a>").children,
+        vec![Node::Text(b"\nx\ny".to_vec())]
+    );
+}
+
+#[test]
+fn test_parse_namespaces() {
+    let r = root("<r xmlns:s='u' xmlns='d'><s:x s:y='1' z='2'/><q:y/></r>");
+    assert!(r.attributes.is_empty());
+    match (&r.children[0], &r.children[1]) {
+        (&Node::Element(ref x), &Node::Element(ref y)) => {
+            assert_eq!(x.name, b"x");
+            assert_eq!(
+                x.attributes,
+                vec![(b"y".to_vec(), b"1".to_vec()), (b"z".to_vec(), b"2".to_vec())]
+            );
+            assert_eq!(y.name, b"q:y");
+        }
+        _ => panic!("expected elements"),
+    }
+    assert_eq!(
+        error("<a x:b='1' y:b='2' xmlns:x='u' xmlns:y='u'/>").message,
+        "Namespaced Attribute b in 'u' redefined"
+    );
+}
+
+#[test]
+fn test_parse_entities() {
+    let a = root("<!DOCTYPE a [<!ENTITY e 'hi <b>&#x41;</b>'>]><a t='&e2;&amp;'>&e;!</a>"
+        .replace("&e2;", "&lt;")
+        .as_str());
+    assert_eq!(a.attributes, vec![(b"t".to_vec(), b"<&".to_vec())]);
+    assert_eq!(a.children.len(), 3);
+    assert_eq!(a.children[2], Node::Text(b"!".to_vec()));
+    // Undeclared entities are only allowed if the DTD isn't all read.
+    assert!(root("<!DOCTYPE a SYSTEM 'a.dtd'><a>&x;</a>").children.is_empty());
+    assert_eq!(error("<a>&x;</a>").message, "Entity 'x' not defined");
+    assert_eq!(
+        error("<!DOCTYPE a [<!ENTITY a '&b;'><!ENTITY b '&a;'>]><a>&a;</a>"),
+        Error {
+            position: 52,
+            message: "Detected an entity reference loop in 'a'".to_string(),
+        }
+    );
+    assert_eq!(
+        error("<!DOCTYPE a [<!ENTITY e '<b>'>]><a>&e;</b></a>").message,
+        "Entity content is not well balanced"
+    );
+}
+
+#[test]
+fn test_parse_billion_laughs() {
+    let mut data = "<!DOCTYPE lolz [<!ENTITY lol0 'lol'>".to_string();
+    for level in 1..10 {
+        data.push_str(&format!("<!ENTITY lol{} '", level));
+        for _ in 0..10 {
+            data.push_str(&format!("&lol{};", level - 1));
+        }
+        data.push_str("'>");
+    }
+    data.push_str("]><lolz>&lol9;</lolz>");
+    let position = data.len() - 13;
+    assert_eq!(
+        error(&data),
+        Error {
+            position,
+            message: "Maximum entity amplification factor exceeded".to_string(),
+        }
+    );
+}
+
+#[test]
+fn test_parse_depth() {
+    let nested = |depth| format!("{}{}", "<a>".repeat(depth), "</a>".repeat(depth));
+    assert!(parse(nested(MAX_ELEMENT_DEPTH).as_bytes()).is_ok());
+    assert_eq!(
+        error(&nested(MAX_ELEMENT_DEPTH + 1)).message,
+        "Excessive depth in document"
+    );
+}
+
+#[test]
+fn test_parse_errors() {
+    assert_eq!(error("").message, "Document is empty");
+    assert_eq!(error("<a>\n<b></c></a>").position, 10);
+    assert_eq!(error("<a/><b/>").position, 4);
+    assert_eq!(error("<a>]]></a>").position, 3);
+    assert_eq!(error("<a b='1' b='2'/>").message, "Attribute b redefined");
+    assert_eq!(error("<a>\u{1}</a>").position, 3);
+    assert_eq!(
+        parse(b"<a>x\xFF</a>").err(),
+        Some(Error {
+            position: 4,
+            message: "Input is not proper UTF-8".to_string(),
+        })
+    );
+}
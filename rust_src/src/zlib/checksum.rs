@@ -0,0 +1,73 @@
+//! The CRC-32 and Adler-32 checksums of the gzip and zlib formats.
+
+lazy_static! {
+    /// The CRC of each byte, for the reflected polynomial 0xEDB88320.
+    static ref CRC32_TABLE: [u32; 256] = {
+        let mut table = [0; 256];
+        for (byte, entry) in table.iter_mut().enumerate() {
+            let mut crc = byte as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 { 0xEDB8_8320 ^ crc >> 1 } else { crc >> 1 };
+            }
+            *entry = crc;
+        }
+        table
+    };
+}
+
+/// Return the CRC-32 of DATA, continuing from the CRC of the data
+/// before it, which is 0 at the start.
+pub fn crc32(crc: u32, data: &[u8]) -> u32 {
+    let table = &*CRC32_TABLE;
+    let mut crc = !crc;
+    for &byte in data {
+        crc = table[((crc ^ u32::from(byte)) & 0xFF) as usize] ^ crc >> 8;
+    }
+    !crc
+}
+
+/// The largest number of bytes which can be summed before the sums
+/// must be reduced, so that they don't overflow.
+const ADLER_NMAX: usize = 5552;
+const ADLER_MOD: u32 = 65_521;
+
+/// Return the Adler-32 of DATA, continuing from the checksum of the
+/// data before it, which is 1 at the start.
+pub fn adler32(adler: u32, data: &[u8]) -> u32 {
+    let mut a = adler & 0xFFFF;
+    let mut b = adler >> 16;
+    for chunk in data.chunks(ADLER_NMAX) {
+        for &byte in chunk {
+            a += u32::from(byte);
+            b += a;
+        }
+        a %= ADLER_MOD;
+        b %= ADLER_MOD;
+    }
+    b << 16 | a
+}
+
+#[test]
+fn test_crc32() {
+    assert_eq!(crc32(0, b""), 0);
+    assert_eq!(crc32(0, b"123456789"), 0xCBF4_3926);
+    assert_eq!(crc32(crc32(0, b"1234"), b"56789"), 0xCBF4_3926);
+    assert_eq!(
+        crc32(0, b"The quick brown fox jumps over the lazy dog"),
+        0x414F_A339
+    );
+}
+
+#[test]
+fn test_adler32() {
+    assert_eq!(adler32(1, b""), 1);
+    assert_eq!(adler32(1, b"Wikipedia"), 0x11E6_0398);
+    assert_eq!(adler32(adler32(1, b"Wiki"), b"pedia"), 0x11E6_0398);
+    // Enough to need reducing the sums on the way.
+    let data = vec![0xFF; 100_000];
+    let expected = data.iter().fold((1u64, 0u64), |(a, b), &byte| {
+        let a = (a + u64::from(byte)) % 65_521;
+        (a, (b + a) % 65_521)
+    });
+    assert_eq!(adler32(1, &data), (expected.1 << 16 | expected.0) as u32);
+}
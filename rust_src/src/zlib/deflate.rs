@@ -0,0 +1,649 @@
+//! Compression to the deflate format of RFC 1951, and to the zlib and
+//! gzip formats which wrap it.
+
+use std::cmp::max;
+
+use super::checksum::{adler32, crc32};
+use super::inflate::{canonical_codes, fixed_lengths, CODE_LENGTH_ORDER, DIST_BASE, DIST_EXTRA,
+                     LENGTH_BASE, LENGTH_EXTRA, MAX_CODE_LENGTH};
+
+/// The size of the window which matches are found in.
+const WINDOW_SIZE: usize = 32_768;
+const WINDOW_MASK: usize = WINDOW_SIZE - 1;
+const HASH_BITS: usize = 15;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+/// Matches of the minimum length further away than this don't pay.
+const TOO_FAR: usize = 4096;
+/// The number of symbols in a block with a code of its own.
+const BLOCK_SYMBOLS: usize = 16_384;
+const MAX_STORED: usize = 65_535;
+const NIL: usize = !0;
+
+/// The number of match candidates to try, and the length of a match
+/// which is good enough to stop looking, for each compression level.
+const MAX_CHAIN: [usize; 10] = [0, 4, 8, 32, 16, 32, 128, 256, 1024, 4096];
+const NICE_LENGTH: [usize; 10] = [0, 8, 16, 32, 16, 32, 128, 128, 258, 258];
+/// The lowest level which looks for a longer match at the next byte
+/// before taking one.
+const MIN_LAZY_LEVEL: u32 = 4;
+
+pub const DEFAULT_LEVEL: u32 = 6;
+
+/// Writes bits to a byte vector, least significant first.
+struct BitWriter {
+    out: Vec<u8>,
+    bits: u64,
+    count: u32,
+}
+
+impl BitWriter {
+    fn new(out: Vec<u8>) -> BitWriter {
+        BitWriter {
+            out,
+            bits: 0,
+            count: 0,
+        }
+    }
+
+    fn write(&mut self, value: u32, n: u32) {
+        self.bits |= u64::from(value) << self.count;
+        self.count += n;
+        while self.count >= 8 {
+            self.out.push(self.bits as u8);
+            self.bits >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    /// Pad the output with zero bits to the next byte boundary.
+    fn align(&mut self) {
+        if self.count > 0 {
+            self.out.push(self.bits as u8);
+            self.bits = 0;
+            self.count = 0;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.align();
+        self.out
+    }
+}
+
+/// A literal byte, or a match of LENGTH bytes DISTANCE bytes back.
+#[derive(Clone, Copy)]
+struct Symbol {
+    /// The literal byte if DISTANCE is 0.
+    length: u16,
+    distance: u16,
+}
+
+fn length_symbol(length: usize) -> usize {
+    // The length 258 has a symbol of its own, rather than being the
+    // longest length of the symbol before it.
+    LENGTH_BASE.iter().rposition(|&base| base as usize <= length).unwrap()
+}
+
+fn distance_symbol(distance: usize) -> usize {
+    DIST_BASE.iter().rposition(|&base| base as usize <= distance).unwrap()
+}
+
+/// Finds repeated strings with chains of the positions of the strings
+/// which hash alike, and collects the symbols of a block.
+struct Matcher<'a> {
+    data: &'a [u8],
+    head: Vec<usize>,
+    /// The position before each position of the window in its chain.
+    prev: Vec<usize>,
+    max_chain: usize,
+    nice_length: usize,
+}
+
+impl<'a> Matcher<'a> {
+    fn new(data: &'a [u8], level: u32) -> Matcher<'a> {
+        Matcher {
+            data,
+            head: vec![NIL; 1 << HASH_BITS],
+            prev: vec![NIL; WINDOW_SIZE],
+            max_chain: MAX_CHAIN[level as usize],
+            nice_length: NICE_LENGTH[level as usize],
+        }
+    }
+
+    /// Add the string at POS to its chain, and return the position of
+    /// the last string which hashed alike.
+    fn insert(&mut self, pos: usize) -> usize {
+        if pos + MIN_MATCH > self.data.len() {
+            return NIL;
+        }
+        let bytes = &self.data[pos..pos + MIN_MATCH];
+        let hash = (usize::from(bytes[0]) << 10 ^ usize::from(bytes[1]) << 5
+            ^ usize::from(bytes[2])) & ((1 << HASH_BITS) - 1);
+        let last = self.head[hash];
+        self.prev[pos & WINDOW_MASK] = last;
+        self.head[hash] = pos;
+        last
+    }
+
+    /// Return the length and distance of the longest match for POS,
+    /// trying CANDIDATE and the positions before it in its chain.
+    /// The length is 0 if there is none worth having.
+    fn longest_match(&self, pos: usize, mut candidate: usize) -> (usize, usize) {
+        let data = self.data;
+        let max_length = MAX_MATCH.min(data.len() - pos);
+        let (mut best_length, mut best_distance) = (MIN_MATCH - 1, 0);
+        let mut chain = self.max_chain;
+        while candidate != NIL && pos - candidate <= WINDOW_SIZE && chain > 0 {
+            if data[candidate + best_length] == data[pos + best_length] {
+                let length = data[candidate..candidate + max_length]
+                    .iter()
+                    .zip(&data[pos..pos + max_length])
+                    .take_while(|&(a, b)| a == b)
+                    .count();
+                if length > best_length {
+                    best_length = length;
+                    best_distance = pos - candidate;
+                    if length >= self.nice_length.min(max_length) {
+                        break;
+                    }
+                }
+            }
+            let next = self.prev[candidate & WINDOW_MASK];
+            // The entry may have been reused by a later position.
+            if next == NIL || next >= candidate {
+                break;
+            }
+            candidate = next;
+            chain -= 1;
+        }
+        if best_length < MIN_MATCH || best_length == MIN_MATCH && best_distance > TOO_FAR {
+            (0, 0)
+        } else {
+            (best_length, best_distance)
+        }
+    }
+
+    /// Add the strings after POS up to END to their chains.
+    fn skip(&mut self, pos: usize, end: usize) {
+        for pos in pos + 1..end {
+            self.insert(pos);
+        }
+    }
+}
+
+/// Return the LZ77 symbols of DATA, taking the longest match at each
+/// position, or with LAZY, a longer one at the next position.
+fn find_symbols(data: &[u8], level: u32) -> Vec<Symbol> {
+    let lazy = level >= MIN_LAZY_LEVEL;
+    let mut matcher = Matcher::new(data, level);
+    let mut symbols = Vec::with_capacity(data.len() / 2);
+    let literal = |byte: u8| Symbol {
+        length: u16::from(byte),
+        distance: 0,
+    };
+    let matched = |(length, distance): (usize, usize)| Symbol {
+        length: length as u16,
+        distance: distance as u16,
+    };
+    // A match found at the position before POS, held back in case a
+    // longer one starts at POS.
+    let mut pending: Option<(usize, usize)> = None;
+    let mut pos = 0;
+    while pos < data.len() {
+        let candidate = matcher.insert(pos);
+        let found = if pos + MIN_MATCH <= data.len() {
+            matcher.longest_match(pos, candidate)
+        } else {
+            (0, 0)
+        };
+        if let Some(previous) = pending.take() {
+            if found.0 <= previous.0 {
+                symbols.push(matched(previous));
+                matcher.skip(pos, pos - 1 + previous.0);
+                pos += previous.0 - 1;
+                continue;
+            }
+            symbols.push(literal(data[pos - 1]));
+        }
+        if found.0 == 0 {
+            symbols.push(literal(data[pos]));
+            pos += 1;
+        } else if lazy && found.0 < matcher.nice_length {
+            pending = Some(found);
+            pos += 1;
+        } else {
+            symbols.push(matched(found));
+            matcher.skip(pos, pos + found.0);
+            pos += found.0;
+        }
+    }
+    if let Some(previous) = pending {
+        symbols.push(matched(previous));
+    }
+    symbols
+}
+
+/// Return the lengths of a Huffman code for symbols of the given
+/// FREQUENCIES, which is no longer than LIMIT.  At least two symbols
+/// get codes, so that the code is complete.
+fn huffman_lengths(frequencies: &[u32], limit: usize) -> Vec<u8> {
+    let mut frequencies = frequencies.to_vec();
+    while frequencies.iter().filter(|&&frequency| frequency > 0).count() < 2 {
+        let unused = frequencies.iter().position(|&frequency| frequency == 0).unwrap();
+        frequencies[unused] = 1;
+    }
+    loop {
+        let lengths = unlimited_huffman_lengths(&frequencies);
+        if lengths.iter().all(|&length| length as usize <= limit) {
+            return lengths;
+        }
+        // Flatten the distribution until the code fits.
+        for frequency in &mut frequencies {
+            if *frequency > 0 {
+                *frequency = (*frequency + 1) / 2;
+            }
+        }
+    }
+}
+
+/// Return the lengths of an optimal Huffman code for symbols of the
+/// given FREQUENCIES, of which at least two aren't 0.
+fn unlimited_huffman_lengths(frequencies: &[u32]) -> Vec<u8> {
+    let mut leaves: Vec<usize> = (0..frequencies.len())
+        .filter(|&symbol| frequencies[symbol] > 0)
+        .collect();
+    leaves.sort_by_key(|&symbol| frequencies[symbol]);
+    let nleaves = leaves.len();
+
+    // The nodes are the leaves in order, then the inner nodes in the
+    // order they are made, which is also by weight.
+    let mut weight: Vec<u64> = leaves
+        .iter()
+        .map(|&symbol| u64::from(frequencies[symbol]))
+        .collect();
+    let mut parent = vec![0; 2 * nleaves - 1];
+    let (mut next_leaf, mut next_inner) = (0, nleaves);
+    for node in nleaves..2 * nleaves - 1 {
+        let mut children = [0; 2];
+        for child in &mut children {
+            let take_leaf = next_leaf < nleaves
+                && (next_inner == node || weight[next_leaf] <= weight[next_inner]);
+            if take_leaf {
+                *child = next_leaf;
+                next_leaf += 1;
+            } else {
+                *child = next_inner;
+                next_inner += 1;
+            }
+            parent[*child] = node;
+        }
+        let node_weight = weight[children[0]] + weight[children[1]];
+        weight.push(node_weight);
+    }
+
+    let mut depth = vec![0u8; 2 * nleaves - 1];
+    for node in (0..2 * nleaves - 2).rev() {
+        depth[node] = depth[parent[node]] + 1;
+    }
+    let mut lengths = vec![0; frequencies.len()];
+    for (node, &symbol) in leaves.iter().enumerate() {
+        lengths[symbol] = depth[node];
+    }
+    lengths
+}
+
+/// The code lengths of the literal/length and distance codes, run
+/// length encoded with the symbols of the code length code: each item
+/// is a symbol and the value of its extra bits.
+fn encode_code_lengths(lengths: &[u8]) -> Vec<(u8, u8)> {
+    let mut encoded = Vec::new();
+    let mut idx = 0;
+    while idx < lengths.len() {
+        let length = lengths[idx];
+        let run = lengths[idx..].iter().take_while(|&&l| l == length).count();
+        if length == 0 && run >= 11 {
+            let run = run.min(138);
+            encoded.push((18, (run - 11) as u8));
+            idx += run;
+        } else if length == 0 && run >= 3 {
+            encoded.push((17, (run - 3) as u8));
+            idx += run;
+        } else if length != 0 && run >= 4 {
+            // The length itself, then a repeat of it.
+            let run = (run - 1).min(6);
+            encoded.push((length, 0));
+            encoded.push((16, (run - 3) as u8));
+            idx += 1 + run;
+        } else {
+            encoded.push((length, 0));
+            idx += 1;
+        }
+    }
+    encoded
+}
+
+fn extra_bits_of_code_length(symbol: u8) -> u32 {
+    match symbol {
+        16 => 2,
+        17 => 3,
+        18 => 7,
+        _ => 0,
+    }
+}
+
+/// Compresses DATA into blocks.
+struct Compressor<'a> {
+    data: &'a [u8],
+    writer: BitWriter,
+}
+
+impl<'a> Compressor<'a> {
+    /// Return the number of bits the SYMBOLS take with the code LENGTHS.
+    fn data_cost(
+        symbols: &[Symbol],
+        literal_lengths: &[u8],
+        distance_lengths: &[u8],
+    ) -> usize {
+        let mut cost = literal_lengths[256] as usize;
+        for symbol in symbols {
+            if symbol.distance == 0 {
+                cost += literal_lengths[symbol.length as usize] as usize;
+            } else {
+                let length = length_symbol(symbol.length as usize);
+                let distance = distance_symbol(symbol.distance as usize);
+                cost += (literal_lengths[257 + length] + LENGTH_EXTRA[length]) as usize
+                    + (distance_lengths[distance] + DIST_EXTRA[distance]) as usize;
+            }
+        }
+        cost
+    }
+
+    fn write_code(&mut self, codes: &[u16], lengths: &[u8], symbol: usize) {
+        self.writer.write(u32::from(codes[symbol]), u32::from(lengths[symbol]));
+    }
+
+    fn write_symbols(
+        &mut self,
+        symbols: &[Symbol],
+        literal_lengths: &[u8],
+        distance_lengths: &[u8],
+    ) {
+        let literal_codes = canonical_codes(literal_lengths);
+        let distance_codes = canonical_codes(distance_lengths);
+        for symbol in symbols {
+            if symbol.distance == 0 {
+                self.write_code(&literal_codes, literal_lengths, symbol.length as usize);
+                continue;
+            }
+            let length = symbol.length as usize;
+            let length_code = length_symbol(length);
+            self.write_code(&literal_codes, literal_lengths, 257 + length_code);
+            self.writer.write(
+                (length - LENGTH_BASE[length_code] as usize) as u32,
+                u32::from(LENGTH_EXTRA[length_code]),
+            );
+            let distance = symbol.distance as usize;
+            let distance_code = distance_symbol(distance);
+            self.write_code(&distance_codes, distance_lengths, distance_code);
+            self.writer.write(
+                (distance - DIST_BASE[distance_code] as usize) as u32,
+                u32::from(DIST_EXTRA[distance_code]),
+            );
+        }
+        self.write_code(&literal_codes, literal_lengths, 256);
+    }
+
+    fn write_stored(&mut self, bytes: &[u8], last: bool) {
+        let mut chunks = bytes.chunks(MAX_STORED).peekable();
+        if bytes.is_empty() {
+            self.write_stored_chunk(&[], last);
+        }
+        while let Some(chunk) = chunks.next() {
+            let last_chunk = last && chunks.peek().is_none();
+            self.write_stored_chunk(chunk, last_chunk);
+        }
+    }
+
+    fn write_stored_chunk(&mut self, chunk: &[u8], last: bool) {
+        self.writer.write(last as u32, 3);
+        self.writer.align();
+        let len = chunk.len() as u16;
+        for &half in &[len, !len] {
+            self.writer.out.push(half as u8);
+            self.writer.out.push((half >> 8) as u8);
+        }
+        self.writer.out.extend_from_slice(chunk);
+    }
+
+    /// Write the SYMBOLS of the bytes from START to END of the data as
+    /// a block, whichever way takes the fewest bits.
+    fn write_block(&mut self, symbols: &[Symbol], start: usize, end: usize, last: bool) {
+        let mut literal_frequencies = [0u32; 286];
+        let mut distance_frequencies = [0u32; 30];
+        literal_frequencies[256] = 1;
+        for symbol in symbols {
+            if symbol.distance == 0 {
+                literal_frequencies[symbol.length as usize] += 1;
+            } else {
+                literal_frequencies[257 + length_symbol(symbol.length as usize)] += 1;
+                distance_frequencies[distance_symbol(symbol.distance as usize)] += 1;
+            }
+        }
+        let literal_lengths = huffman_lengths(&literal_frequencies, MAX_CODE_LENGTH);
+        let distance_lengths = huffman_lengths(&distance_frequencies, MAX_CODE_LENGTH);
+
+        let nliterals = max(257, literal_lengths.iter().rposition(|&l| l > 0).unwrap() + 1);
+        let ndistances = max(1, distance_lengths.iter().rposition(|&l| l > 0).unwrap() + 1);
+        let mut all_lengths = literal_lengths[..nliterals].to_vec();
+        all_lengths.extend_from_slice(&distance_lengths[..ndistances]);
+        let encoded_lengths = encode_code_lengths(&all_lengths);
+        let mut code_length_frequencies = [0u32; 19];
+        for &(symbol, _) in &encoded_lengths {
+            code_length_frequencies[symbol as usize] += 1;
+        }
+        let code_length_lengths = huffman_lengths(&code_length_frequencies, 7);
+        let ncode_lengths = max(
+            4,
+            CODE_LENGTH_ORDER
+                .iter()
+                .rposition(|&symbol| code_length_lengths[symbol] > 0)
+                .unwrap() + 1,
+        );
+
+        let dynamic_cost = 14 + 3 * ncode_lengths
+            + encoded_lengths
+                .iter()
+                .map(|&(symbol, _)| {
+                    code_length_lengths[symbol as usize] as usize
+                        + extra_bits_of_code_length(symbol) as usize
+                })
+                .sum::<usize>()
+            + Compressor::data_cost(symbols, &literal_lengths, &distance_lengths);
+        let (fixed_literal_lengths, fixed_distance_lengths) = fixed_lengths();
+        let fixed_cost =
+            Compressor::data_cost(symbols, &fixed_literal_lengths, &fixed_distance_lengths);
+        let stored_cost = (end - start + 5 * ((end - start) / MAX_STORED + 1)) * 8;
+
+        if stored_cost <= dynamic_cost.min(fixed_cost) + 3 {
+            let data = self.data;
+            self.write_stored(&data[start..end], last);
+        } else if fixed_cost <= dynamic_cost {
+            self.writer.write(last as u32 | 1 << 1, 3);
+            self.write_symbols(symbols, &fixed_literal_lengths, &fixed_distance_lengths);
+        } else {
+            self.writer.write(last as u32 | 2 << 1, 3);
+            self.writer.write((nliterals - 257) as u32, 5);
+            self.writer.write((ndistances - 1) as u32, 5);
+            self.writer.write((ncode_lengths - 4) as u32, 4);
+            for &symbol in &CODE_LENGTH_ORDER[..ncode_lengths] {
+                self.writer.write(u32::from(code_length_lengths[symbol]), 3);
+            }
+            let code_length_codes = canonical_codes(&code_length_lengths);
+            for &(symbol, extra) in &encoded_lengths {
+                let symbol = symbol as usize;
+                self.writer.write(
+                    u32::from(code_length_codes[symbol]),
+                    u32::from(code_length_lengths[symbol]),
+                );
+                self.writer.write(
+                    u32::from(extra),
+                    extra_bits_of_code_length(symbol as u8),
+                );
+            }
+            self.write_symbols(symbols, &literal_lengths, &distance_lengths);
+        }
+    }
+}
+
+/// Compress DATA to a raw deflate stream at compression LEVEL, from 0
+/// for none to 9 for the most, appending it to OUT.
+pub fn deflate(data: &[u8], level: u32, out: Vec<u8>) -> Vec<u8> {
+    let mut compressor = Compressor {
+        data,
+        writer: BitWriter::new(out),
+    };
+    if level == 0 {
+        compressor.write_stored(data, true);
+        return compressor.writer.finish();
+    }
+    let symbols = find_symbols(data, level);
+    let mut start = 0;
+    let mut blocks = symbols.chunks(BLOCK_SYMBOLS).peekable();
+    if symbols.is_empty() {
+        compressor.write_block(&[], 0, 0, true);
+    }
+    while let Some(block) = blocks.next() {
+        let end = start
+            + block
+                .iter()
+                .map(|symbol| if symbol.distance == 0 { 1 } else { symbol.length as usize })
+                .sum::<usize>();
+        compressor.write_block(block, start, end, blocks.peek().is_none());
+        start = end;
+    }
+    compressor.writer.finish()
+}
+
+/// Compress DATA to the zlib format at compression LEVEL.
+pub fn zlib_compress(data: &[u8], level: u32) -> Vec<u8> {
+    let cmf = 0x78;
+    let flevel = match level {
+        0...1 => 0,
+        2...5 => 1,
+        6 => 2,
+        _ => 3,
+    };
+    let flags = flevel << 6;
+    let flags = flags + 31 - (cmf << 8 | flags) % 31;
+    let mut out = deflate(data, level, vec![cmf as u8, flags as u8]);
+    let adler = adler32(1, data);
+    for &shift in &[24, 16, 8, 0] {
+        out.push((adler >> shift) as u8);
+    }
+    out
+}
+
+/// Compress DATA to the gzip format at compression LEVEL, with no file
+/// name or modification time.
+pub fn gzip_compress(data: &[u8], level: u32) -> Vec<u8> {
+    let extra_flags = match level {
+        9 => 2,
+        1 => 4,
+        _ => 0,
+    };
+    // The OS is Unix, as it conventionally is.
+    let header = vec![0x1F, 0x8B, 8, 0, 0, 0, 0, 0, extra_flags, 3];
+    let mut out = deflate(data, level, header);
+    for &value in &[crc32(0, data), data.len() as u32] {
+        for &shift in &[0, 8, 16, 24] {
+            out.push((value >> shift) as u8);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+use super::inflate::{decompress, gunzip, inflate};
+
+#[test]
+fn test_huffman_lengths() {
+    let lengths = huffman_lengths(&[10, 1, 1, 2, 0, 6], 15);
+    assert_eq!(lengths, [1, 4, 4, 3, 0, 2]);
+    // Too skewed for the limit.
+    let frequencies: Vec<u32> = (0..20).map(|idx| 1 << idx).collect();
+    let lengths = huffman_lengths(&frequencies, 7);
+    assert!(lengths.iter().all(|&length| length > 0 && length <= 7));
+    let kraft: f64 = lengths.iter().map(|&length| 0.5f64.powi(length as i32)).sum();
+    assert_eq!(kraft, 1.0);
+    // Codes for fewer than two symbols are made up.
+    assert_eq!(huffman_lengths(&[0, 0, 5], 15), [1, 0, 1]);
+    assert_eq!(huffman_lengths(&[0, 0, 0], 15), [1, 1, 0]);
+}
+
+#[test]
+fn test_encode_code_lengths() {
+    let mut lengths = vec![8; 10];
+    lengths.extend_from_slice(&[0; 150]);
+    lengths.extend_from_slice(&[5, 5, 0, 0, 7]);
+    assert_eq!(
+        encode_code_lengths(&lengths),
+        vec![
+            (8, 0),
+            (16, 3),
+            (8, 0),
+            (8, 0),
+            (8, 0),
+            (18, 127),
+            (18, 1),
+            (5, 0),
+            (5, 0),
+            (0, 0),
+            (0, 0),
+            (7, 0),
+        ]
+    );
+}
+
+#[test]
+fn test_deflate_round_trip() {
+    use codec::TestRng;
+
+    let mut rng = TestRng::new();
+    let mut inputs: Vec<Vec<u8>> = vec![
+        Vec::new(),
+        b"a".to_vec(),
+        vec![0; 100_000],
+        (0..200_000).map(|_| rng.next() as u8).collect(),
+    ];
+    let text: Vec<u8> = (0..30_000)
+        .map(|_| b"the quick brown fox jumps over a lazy dog\n"[rng.next() as usize % 42])
+        .collect();
+    inputs.push(text);
+    for _ in 0..50 {
+        inputs.push(rng.bytes());
+    }
+    for input in &inputs {
+        for level in 0..10 {
+            let compressed = deflate(input, level, Vec::new());
+            let mut out = Vec::new();
+            assert_eq!(inflate(&compressed, 0, &mut out), Some(compressed.len()));
+            assert_eq!(&out, input, "level {}", level);
+            if level > 0 && input.len() > 1000 && input[1..] == input[..input.len() - 1] {
+                assert!(compressed.len() < input.len() / 100);
+            }
+        }
+        assert_eq!(&decompress(&zlib_compress(input, 6)).unwrap(), input);
+        assert_eq!(&gunzip(&gzip_compress(input, 9)).unwrap(), input);
+    }
+}
+
+#[test]
+fn test_zlib_compress_header() {
+    // The same header and trailer as zlib.compress gives.
+    let compressed = zlib_compress(b"hello hello hello hello\n", 6);
+    assert_eq!(&compressed[..2], &[0x78, 0x9C]);
+    assert_eq!(&compressed[compressed.len() - 4..], &[0x70, 0xBE, 0x08, 0xBB]);
+    assert_eq!(&zlib_compress(b"", 9)[..2], &[0x78, 0xDA]);
+    assert_eq!(&zlib_compress(b"", 1)[..2], &[0x78, 0x01]);
+}
@@ -0,0 +1,496 @@
+//! Decompression of the deflate format of RFC 1951, and of the zlib
+//! and gzip formats of RFCs 1950 and 1952 which wrap it.
+
+use super::checksum::{adler32, crc32};
+
+/// The base length of each length symbol from 257 on, and the number
+/// of extra bits following it.
+pub const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+pub const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0
+];
+
+/// The base distance of each distance symbol, and the number of extra
+/// bits following it.
+pub const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+pub const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// The order in which the lengths of the code length code are stored.
+pub const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15
+];
+
+pub const MAX_CODE_LENGTH: usize = 15;
+
+/// Return the code lengths of the fixed literal/length code, and of
+/// the fixed distance code.  Both have two symbols more than can be
+/// used, to make them complete.
+pub fn fixed_lengths() -> ([u8; 288], [u8; 32]) {
+    let mut lengths = [8; 288];
+    for length in &mut lengths[144..256] {
+        *length = 9;
+    }
+    for length in &mut lengths[256..280] {
+        *length = 7;
+    }
+    (lengths, [5; 32])
+}
+
+/// Return the canonical codes given by code LENGTHS, with their bits
+/// reversed, as they are read from and written to the stream.
+pub fn canonical_codes(lengths: &[u8]) -> Vec<u16> {
+    let mut count = [0u16; MAX_CODE_LENGTH + 1];
+    for &length in lengths {
+        count[length as usize] += 1;
+    }
+    count[0] = 0;
+    let mut next = [0u16; MAX_CODE_LENGTH + 1];
+    for length in 1..MAX_CODE_LENGTH + 1 {
+        next[length] = (next[length - 1] + count[length - 1]) << 1;
+    }
+    lengths
+        .iter()
+        .map(|&length| {
+            if length == 0 {
+                return 0;
+            }
+            let code = next[length as usize];
+            next[length as usize] += 1;
+            reverse_bits(code, length)
+        })
+        .collect()
+}
+
+/// Return the LENGTH low bits of CODE in reverse order.
+fn reverse_bits(code: u16, length: u8) -> u16 {
+    (0..length).fold(0, |reversed, bit| reversed << 1 | (code >> bit & 1))
+}
+
+/// Reads the bits of a deflate stream, least significant first.
+struct BitReader<'a> {
+    data: &'a [u8],
+    /// The position of the next byte to be loaded into BITS.
+    pos: usize,
+    bits: u64,
+    count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8], pos: usize) -> BitReader<'a> {
+        BitReader {
+            data,
+            pos,
+            bits: 0,
+            count: 0,
+        }
+    }
+
+    fn refill(&mut self) {
+        while self.count <= 56 && self.pos < self.data.len() {
+            self.bits |= u64::from(self.data[self.pos]) << self.count;
+            self.pos += 1;
+            self.count += 8;
+        }
+    }
+
+    /// Return the next N bits, without consuming them, and the number
+    /// of bits which are actually left if that is less.
+    fn peek(&mut self, n: u32) -> (u32, u32) {
+        if self.count < n {
+            self.refill();
+        }
+        ((self.bits & ((1 << n) - 1)) as u32, self.count.min(n))
+    }
+
+    fn consume(&mut self, n: u32) {
+        self.bits >>= n;
+        self.count -= n;
+    }
+
+    fn bits(&mut self, n: u32) -> Option<u32> {
+        let (value, available) = self.peek(n);
+        if available < n {
+            return None;
+        }
+        self.consume(n);
+        Some(value)
+    }
+
+    /// Skip to the next byte boundary, and return the position of the
+    /// byte there.  The bytes already loaded are given back.
+    fn align(&mut self) -> usize {
+        self.pos -= (self.count / 8) as usize;
+        self.bits = 0;
+        self.count = 0;
+        self.pos
+    }
+}
+
+/// A Huffman code, decoded by looking up as many bits as its longest
+/// code has.  Each entry of the table holds a symbol and the length
+/// of its code, or 0 if no code starts with those bits.
+struct Huffman {
+    table: Vec<u16>,
+    bits: u32,
+}
+
+impl Huffman {
+    /// Make the code given by code LENGTHS.  Return None if there are
+    /// too many codes of some length, or too few if the code must be
+    /// COMPLETE; an incomplete code may only have a single code of a
+    /// single bit, as zlib allows.
+    fn new(lengths: &[u8], complete: bool) -> Option<Huffman> {
+        let mut count = [0i32; MAX_CODE_LENGTH + 1];
+        for &length in lengths {
+            count[length as usize] += 1;
+        }
+        let mut left = 1;
+        for length in 1..MAX_CODE_LENGTH + 1 {
+            left = (left << 1) - count[length];
+            if left < 0 {
+                return None;
+            }
+        }
+        let max = (1..MAX_CODE_LENGTH + 1)
+            .rev()
+            .find(|&length| count[length] > 0)
+            .unwrap_or(1);
+        if left > 0 && (complete || max > 1) {
+            return None;
+        }
+
+        let bits = max as u32;
+        let mut table = vec![0; 1 << bits];
+        let codes = canonical_codes(lengths);
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length == 0 {
+                continue;
+            }
+            let entry = (symbol as u16) << 4 | u16::from(length);
+            let mut idx = codes[symbol] as usize;
+            while idx < table.len() {
+                table[idx] = entry;
+                idx += 1 << length;
+            }
+        }
+        Some(Huffman { table, bits })
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Option<usize> {
+        let (peeked, available) = reader.peek(self.bits);
+        let entry = self.table[peeked as usize];
+        let length = u32::from(entry & 0xF);
+        if length == 0 || length > available {
+            return None;
+        }
+        reader.consume(length);
+        Some((entry >> 4) as usize)
+    }
+}
+
+/// Read the code lengths of a dynamic block, and return its
+/// literal/length and distance codes.
+fn read_dynamic_codes(reader: &mut BitReader) -> Option<(Huffman, Huffman)> {
+    let nlen = reader.bits(5)? as usize + 257;
+    let ndist = reader.bits(5)? as usize + 1;
+    let ncode = reader.bits(4)? as usize + 4;
+    if nlen > 286 || ndist > 30 {
+        return None;
+    }
+
+    let mut code_lengths = [0u8; 19];
+    for &symbol in &CODE_LENGTH_ORDER[..ncode] {
+        code_lengths[symbol] = reader.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths, true)?;
+
+    let mut lengths = [0u8; 286 + 30];
+    let mut idx = 0;
+    while idx < nlen + ndist {
+        let symbol = code_length_code.decode(reader)?;
+        let (length, repeat) = match symbol {
+            0...15 => (symbol as u8, 1),
+            16 => {
+                if idx == 0 {
+                    return None;
+                }
+                (lengths[idx - 1], 3 + reader.bits(2)?)
+            }
+            17 => (0, 3 + reader.bits(3)?),
+            _ => (0, 11 + reader.bits(7)?),
+        };
+        let repeat = repeat as usize;
+        if idx + repeat > nlen + ndist {
+            return None;
+        }
+        for length_ref in &mut lengths[idx..idx + repeat] {
+            *length_ref = length;
+        }
+        idx += repeat;
+    }
+    // A block without an end of block code can't end.
+    if lengths[256] == 0 {
+        return None;
+    }
+    Some((
+        Huffman::new(&lengths[..nlen], false)?,
+        Huffman::new(&lengths[nlen..nlen + ndist], false)?,
+    ))
+}
+
+/// Decode the symbols of a block with codes LITERALS and DISTANCES,
+/// appending them to OUT, of which the current stream starts at
+/// STREAM_START.
+fn inflate_block(
+    reader: &mut BitReader,
+    literals: &Huffman,
+    distances: &Huffman,
+    out: &mut Vec<u8>,
+    stream_start: usize,
+) -> Option<()> {
+    loop {
+        let symbol = literals.decode(reader)?;
+        if symbol < 256 {
+            out.push(symbol as u8);
+            continue;
+        }
+        if symbol == 256 {
+            return Some(());
+        }
+        let symbol = symbol - 257;
+        if symbol >= LENGTH_BASE.len() {
+            return None;
+        }
+        let length = LENGTH_BASE[symbol] as usize
+            + reader.bits(u32::from(LENGTH_EXTRA[symbol]))? as usize;
+        let symbol = distances.decode(reader)?;
+        if symbol >= DIST_BASE.len() {
+            return None;
+        }
+        let distance =
+            DIST_BASE[symbol] as usize + reader.bits(u32::from(DIST_EXTRA[symbol]))? as usize;
+        if distance > out.len() - stream_start {
+            return None;
+        }
+        // The copy may overlap its own output, so it goes byte by byte.
+        let from = out.len() - distance;
+        out.reserve(length);
+        for idx in from..from + length {
+            let byte = out[idx];
+            out.push(byte);
+        }
+    }
+}
+
+/// Decompress the raw deflate stream starting at POS of DATA,
+/// appending the result to OUT.  Return the position of the byte
+/// after the stream.
+pub fn inflate(data: &[u8], pos: usize, out: &mut Vec<u8>) -> Option<usize> {
+    let stream_start = out.len();
+    let mut reader = BitReader::new(data, pos);
+    let (fixed_literals, fixed_distances) = fixed_lengths();
+    let fixed_literals = Huffman::new(&fixed_literals, true)?;
+    let fixed_distances = Huffman::new(&fixed_distances, true)?;
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                let pos = reader.align();
+                if pos + 4 > data.len() {
+                    return None;
+                }
+                let len = usize::from(data[pos]) | usize::from(data[pos + 1]) << 8;
+                let nlen = usize::from(data[pos + 2]) | usize::from(data[pos + 3]) << 8;
+                if len != !nlen & 0xFFFF || pos + 4 + len > data.len() {
+                    return None;
+                }
+                out.extend_from_slice(&data[pos + 4..pos + 4 + len]);
+                reader = BitReader::new(data, pos + 4 + len);
+            }
+            1 => inflate_block(
+                &mut reader,
+                &fixed_literals,
+                &fixed_distances,
+                out,
+                stream_start,
+            )?,
+            2 => {
+                let (literals, distances) = read_dynamic_codes(&mut reader)?;
+                inflate_block(&mut reader, &literals, &distances, out, stream_start)?;
+            }
+            _ => return None,
+        }
+        if last {
+            return Some(reader.align());
+        }
+    }
+}
+
+fn read_u32_le(bytes: &[u8]) -> u32 {
+    bytes.iter().rev().fold(0, |value, &byte| value << 8 | u32::from(byte))
+}
+
+/// Decompress the zlib stream at the start of DATA, appending the
+/// result to OUT.  Return the position of the byte after the stream.
+fn zlib_stream(data: &[u8], out: &mut Vec<u8>) -> Option<usize> {
+    if data.len() < 2 {
+        return None;
+    }
+    let (cmf, flags) = (data[0], data[1]);
+    if cmf & 0xF != 8 || cmf >> 4 > 7 || (u16::from(cmf) << 8 | u16::from(flags)) % 31 != 0 {
+        return None;
+    }
+    // There's no way to give a preset dictionary.
+    if flags & 0x20 != 0 {
+        return None;
+    }
+    let start = out.len();
+    let end = inflate(data, 2, out)?;
+    let trailer = data.get(end..end + 4)?;
+    let expected = trailer.iter().fold(0, |value, &byte| value << 8 | u32::from(byte));
+    if adler32(1, &out[start..]) != expected {
+        return None;
+    }
+    Some(end + 4)
+}
+
+const GZIP_FHCRC: u8 = 2;
+const GZIP_FEXTRA: u8 = 4;
+const GZIP_FNAME: u8 = 8;
+const GZIP_FCOMMENT: u8 = 16;
+
+/// Decompress the gzip member at the start of DATA, appending the
+/// result to OUT.  Return the position of the byte after the member.
+fn gzip_member(data: &[u8], out: &mut Vec<u8>) -> Option<usize> {
+    if data.len() < 10 || data[..3] != [0x1F, 0x8B, 8] {
+        return None;
+    }
+    let flags = data[3];
+    if flags & 0xE0 != 0 {
+        return None;
+    }
+    let mut pos = 10;
+    if flags & GZIP_FEXTRA != 0 {
+        let extra = data.get(pos..pos + 2)?;
+        pos += 2 + (usize::from(extra[0]) | usize::from(extra[1]) << 8);
+    }
+    for &flag in &[GZIP_FNAME, GZIP_FCOMMENT] {
+        if flags & flag != 0 {
+            pos += data.get(pos..)?.iter().position(|&byte| byte == 0)? + 1;
+        }
+    }
+    if flags & GZIP_FHCRC != 0 {
+        let header_crc = data.get(pos..pos + 2)?;
+        let expected = u32::from(header_crc[0]) | u32::from(header_crc[1]) << 8;
+        if crc32(0, &data[..pos]) & 0xFFFF != expected {
+            return None;
+        }
+        pos += 2;
+    }
+    if pos > data.len() {
+        return None;
+    }
+
+    let start = out.len();
+    let end = inflate(data, pos, out)?;
+    let trailer = data.get(end..end + 8)?;
+    if crc32(0, &out[start..]) != read_u32_le(&trailer[..4])
+        || (out.len() - start) as u32 != read_u32_le(&trailer[4..])
+    {
+        return None;
+    }
+    Some(end + 8)
+}
+
+/// Decompress the gzip DATA.  Members concatenated to the first one
+/// are decompressed too, and anything else after it is ignored.
+pub fn gunzip(data: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(data.len() * 3);
+    let mut pos = gzip_member(data, &mut out)?;
+    while data[pos..].starts_with(&[0x1F, 0x8B]) {
+        pos += gzip_member(&data[pos..], &mut out)?;
+    }
+    Some(out)
+}
+
+/// Decompress the zlib or gzip DATA, telling them apart as zlib does.
+/// Anything after the compressed data is ignored.
+pub fn decompress(data: &[u8]) -> Option<Vec<u8>> {
+    if data.starts_with(&[0x1F, 0x8B]) {
+        gunzip(data)
+    } else {
+        let mut out = Vec::with_capacity(data.len() * 3);
+        zlib_stream(data, &mut out)?;
+        Some(out)
+    }
+}
+
+#[cfg(test)]
+pub fn from_hex(hex: &str) -> Vec<u8> {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(::std::str::from_utf8(pair).unwrap(), 16).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_inflate_zlib() {
+    // "hello hello hello hello\n" compressed by zlib.
+    let data = from_hex("789ccb48cdc9c957c84027b90070be08bb");
+    assert_eq!(decompress(&data).unwrap(), b"hello hello hello hello\n");
+    // Trailing data is ignored, but not a bad checksum.
+    let mut trailing = data.clone();
+    trailing.extend_from_slice(b"junk");
+    assert_eq!(decompress(&trailing).unwrap(), b"hello hello hello hello\n");
+    let mut corrupt = data.clone();
+    *corrupt.last_mut().unwrap() ^= 1;
+    assert!(decompress(&corrupt).is_none());
+    for len in 0..data.len() {
+        assert!(decompress(&data[..len]).is_none());
+    }
+    // A preset dictionary.
+    assert!(decompress(&from_hex("78f9024d01274b4c4a0600024d0127")).is_none());
+}
+
+#[test]
+fn test_inflate_blocks() {
+    // A stored block, and an empty fixed block.
+    let mut out = Vec::new();
+    let data = from_hex("000300fcff6162630300");
+    assert_eq!(inflate(&data, 0, &mut out), Some(10));
+    assert_eq!(out, b"abc");
+    // NLEN must be the complement of LEN.
+    assert!(inflate(&from_hex("010300fdff616263"), 0, &mut Vec::new()).is_none());
+    // A reserved block type.
+    assert!(inflate(&from_hex("07"), 0, &mut Vec::new()).is_none());
+    // A distance before the start of the output.
+    assert!(inflate(&from_hex("23440300"), 0, &mut Vec::new()).is_none());
+}
+
+#[test]
+fn test_gunzip() {
+    // "foo\n" gzipped with the file name "foo".
+    let data = from_hex("1f8b08080000000002ff666f6f004bcbcfe70200a865327e04000000");
+    assert_eq!(gunzip(&data).unwrap(), b"foo\n");
+    assert_eq!(decompress(&data).unwrap(), b"foo\n");
+    // Concatenated members.
+    let mut twice = data.clone();
+    twice.extend_from_slice(&data);
+    assert_eq!(gunzip(&twice).unwrap(), b"foo\nfoo\n");
+    let mut corrupt = data.clone();
+    corrupt[data.len() - 1] = 5;
+    assert!(gunzip(&corrupt).is_none());
+    // Reserved flags.
+    let mut reserved = data.clone();
+    reserved[3] |= 0x20;
+    assert!(gunzip(&reserved).is_none());
+}
@@ -0,0 +1,163 @@
+//! Compression and decompression in the zlib and gzip formats.
+
+use libc::ptrdiff_t;
+
+use remacs_macros::lisp_fn;
+use remacs_sys::EmacsInt;
+
+use codec::{make_unibyte, region_bounds, replace_region, string_bytes};
+use lisp::LispObject;
+use lisp::defsubr;
+use threads::ThreadState;
+
+pub mod checksum;
+mod deflate;
+pub mod inflate;
+
+use self::deflate::{gzip_compress, zlib_compress, DEFAULT_LEVEL};
+use self::inflate::{decompress, gunzip};
+
+/// Return the compression level given by LEVEL, or the default one if
+/// it is nil.
+fn compression_level(level: LispObject) -> u32 {
+    if level.is_nil() {
+        return DEFAULT_LEVEL;
+    }
+    match level.as_fixnum_or_error() {
+        value @ 0...9 => value as u32,
+        _ => args_out_of_range!(level, LispObject::from_fixnum(0), LispObject::from_fixnum(9)),
+    }
+}
+
+/// Return the bytes of STRING, which must not have characters which
+/// aren't bytes.  NAME is the name of the operation for the error.
+fn string_bytes_or_error(string: LispObject, name: &str) -> Vec<u8> {
+    match string_bytes(string.as_string_or_error()) {
+        Some(bytes) => bytes,
+        None => error!("Multibyte character in data for {}", name),
+    }
+}
+
+/// Validate the region between START and END of the current buffer,
+/// which must be unibyte, and return its bounds and text.
+fn unibyte_region(
+    start: LispObject,
+    end: LispObject,
+) -> ((ptrdiff_t, ptrdiff_t, ptrdiff_t, ptrdiff_t), Vec<u8>) {
+    let bounds = region_bounds(start, end);
+    let buffer = ThreadState::current_buffer();
+    if LispObject::from(buffer.enable_multibyte_characters).is_not_nil() {
+        error!("This function can be called only in unibyte buffers");
+    }
+    let (before_gap, after_gap) = buffer.byte_slices(bounds.1, bounds.3);
+    (bounds, [before_gap, after_gap].concat())
+}
+
+/// Return t if zlib compression and decompression are available.
+/// They always are; this function is kept for code which checks.
+#[lisp_fn]
+pub fn zlib_available_p() -> LispObject {
+    LispObject::constant_t()
+}
+
+/// Decompress a gzip- or zlib-compressed region.
+/// Replace the text in the region by the decompressed data.
+/// On failure, return nil and leave the data in place.
+/// Concatenated gzip members are all decompressed, and any other data
+/// after the compressed data is discarded.
+/// This function can be called only in unibyte buffers.
+#[lisp_fn]
+pub fn zlib_decompress_region(start: LispObject, end: LispObject) -> LispObject {
+    let (bounds, data) = unibyte_region(start, end);
+    match decompress(&data) {
+        Some(decompressed) => {
+            replace_region(bounds, &decompressed, decompressed.len() as ptrdiff_t);
+            LispObject::constant_t()
+        }
+        None => LispObject::constant_nil(),
+    }
+}
+
+/// Compress the region between START and END in the zlib format.
+/// Replace the text in the region by the compressed data, and return
+/// its length.  If GZIP is non-nil, use the gzip format instead.
+/// Optional argument LEVEL is the compression level, from 0 for no
+/// compression to 9 for the best; it defaults to 6.
+/// This function can be called only in unibyte buffers.
+#[lisp_fn(min = "2")]
+pub fn zlib_compress_region(
+    start: LispObject,
+    end: LispObject,
+    gzip: LispObject,
+    level: LispObject,
+) -> LispObject {
+    let level = compression_level(level);
+    let (bounds, data) = unibyte_region(start, end);
+    let compressed = if gzip.is_nil() {
+        zlib_compress(&data, level)
+    } else {
+        gzip_compress(&data, level)
+    };
+    replace_region(bounds, &compressed, compressed.len() as ptrdiff_t)
+}
+
+/// Compress STRING in the gzip format and return the result.
+/// STRING must be unibyte, or have only ASCII and eight-bit characters.
+/// Optional argument LEVEL is the compression level, from 0 for no
+/// compression to 9 for the best; it defaults to 6.
+#[lisp_fn(min = "1")]
+pub fn gzip_compress_string(string: LispObject, level: LispObject) -> LispObject {
+    let level = compression_level(level);
+    make_unibyte(&gzip_compress(&string_bytes_or_error(string, "gzip"), level))
+}
+
+/// Decompress the gzip data in STRING and return the result as a
+/// unibyte string.  Concatenated gzip members are all decompressed,
+/// and any other data after the compressed data is ignored.
+#[lisp_fn]
+pub fn gzip_decompress_string(string: LispObject) -> LispObject {
+    match gunzip(&string_bytes_or_error(string, "gzip")) {
+        Some(decompressed) => make_unibyte(&decompressed),
+        None => error!("Invalid gzip data"),
+    }
+}
+
+/// Return the initial value of a checksum given by VALUE, or DEFAULT
+/// if it is nil.
+fn initial_checksum(value: LispObject, default: u32) -> u32 {
+    if value.is_nil() {
+        return default;
+    }
+    match value.as_natnum_or_error() {
+        n @ 0...0xFFFF_FFFF => n as u32,
+        _ => args_out_of_range!(
+            value,
+            LispObject::from_fixnum(0),
+            LispObject::from_natnum(0xFFFF_FFFF)
+        ),
+    }
+}
+
+/// Return the CRC-32 of STRING, as used by gzip and zip.
+/// STRING must be unibyte, or have only ASCII and eight-bit characters.
+/// Optional argument CRC is the CRC of data preceding STRING, to
+/// compute the CRC of the concatenation piece by piece.
+#[lisp_fn(min = "1")]
+pub fn crc32(string: LispObject, crc: LispObject) -> LispObject {
+    let crc = initial_checksum(crc, 0);
+    let bytes = string_bytes_or_error(string, "crc32");
+    LispObject::from_natnum(EmacsInt::from(checksum::crc32(crc, &bytes)))
+}
+
+/// Return the Adler-32 checksum of STRING, as used by zlib.
+/// STRING must be unibyte, or have only ASCII and eight-bit characters.
+/// Optional argument ADLER is the checksum of data preceding STRING, to
+/// compute the checksum of the concatenation piece by piece.
+#[lisp_fn(min = "1")]
+pub fn adler32(string: LispObject, adler: LispObject) -> LispObject {
+    let adler = initial_checksum(adler, 1);
+    let bytes = string_bytes_or_error(string, "adler32");
+    LispObject::from_natnum(EmacsInt::from(checksum::adler32(adler, &bytes)))
+}
+
+include!(concat!(env!("OUT_DIR"), "/zlib_exports.rs"));
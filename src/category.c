@@ -54,27 +54,6 @@ static int category_table_version;
 
 /* Category set staff.  */
 
-static Lisp_Object
-hash_get_category_set (Lisp_Object table, Lisp_Object category_set)
-{
-  struct Lisp_Hash_Table *h;
-  ptrdiff_t i;
-  EMACS_UINT hash;
-
-  if (NILP (XCHAR_TABLE (table)->extras[1]))
-    set_char_table_extras
-      (table, 1,
-       make_hash_table (hashtest_equal, DEFAULT_HASH_SIZE,
-			DEFAULT_REHASH_SIZE, DEFAULT_REHASH_THRESHOLD,
-			Qnil, false));
-  h = XHASH_TABLE (XCHAR_TABLE (table)->extras[1]);
-  i = hash_lookup (h, category_set, &hash);
-  if (i >= 0)
-    return HASH_KEY (h, i);
-  hash_put (h, category_set, Qnil, hash);
-  return category_set;
-}
-
 /* Make CATEGORY_SET include (if VAL) or exclude (if !VAL) CATEGORY.  */
 
 static void
@@ -116,29 +95,6 @@ those categories.  */)
 
 static Lisp_Object check_category_table (Lisp_Object table);
 
-DEFUN ("define-category", Fdefine_category, Sdefine_category, 2, 3, 0,
-       doc: /* Define CATEGORY as a category which is described by DOCSTRING.
-CATEGORY should be an ASCII printing character in the range ` ' to `~'.
-DOCSTRING is the documentation string of the category.  The first line
-should be a terse text (preferably less than 16 characters),
-and the rest lines should be the full description.
-The category is defined only in category table TABLE, which defaults to
-the current buffer's category table.  */)
-  (Lisp_Object category, Lisp_Object docstring, Lisp_Object table)
-{
-  CHECK_CATEGORY (category);
-  CHECK_STRING (docstring);
-  table = check_category_table (table);
-
-  if (!NILP (CATEGORY_DOCSTRING (table, XFASTINT (category))))
-    error ("Category `%c' is already defined", (int) XFASTINT (category));
-  if (!NILP (Vpurify_flag))
-    docstring = Fpurecopy (docstring);
-  SET_CATEGORY_DOCSTRING (table, XFASTINT (category), docstring);
-
-  return Qnil;
-}
-
 DEFUN ("category-docstring", Fcategory_docstring, Scategory_docstring, 1, 2, 0,
        doc: /* Return the documentation string of CATEGORY, as defined in TABLE.
 TABLE should be a category table and defaults to the current buffer's
@@ -197,67 +153,6 @@ This is the one used for new buffers.  */)
 }
 
 
-static void
-copy_category_entry (Lisp_Object table, Lisp_Object c, Lisp_Object val)
-{
-  val = Fcopy_sequence (val);
-  if (CONSP (c))
-    char_table_set_range (table, XINT (XCAR (c)), XINT (XCDR (c)), val);
-  else
-    char_table_set (table, XINT (c), val);
-}
-
-/* Return a copy of category table TABLE.  We can't simply use the
-   function copy-sequence because no contents should be shared between
-   the original and the copy.  This function is called recursively by
-   binding TABLE to a sub char table.  */
-
-static Lisp_Object
-copy_category_table (Lisp_Object table)
-{
-  table = copy_char_table (table);
-
-  if (! NILP (XCHAR_TABLE (table)->defalt))
-    set_char_table_defalt (table,
-			   Fcopy_sequence (XCHAR_TABLE (table)->defalt));
-  set_char_table_extras
-    (table, 0, Fcopy_sequence (XCHAR_TABLE (table)->extras[0]));
-  map_char_table (copy_category_entry, Qnil, table, table);
-
-  return table;
-}
-
-DEFUN ("copy-category-table", Fcopy_category_table, Scopy_category_table,
-       0, 1, 0,
-       doc: /* Construct a new category table and return it.
-It is a copy of the TABLE, which defaults to the standard category table.  */)
-  (Lisp_Object table)
-{
-  if (!NILP (table))
-    check_category_table (table);
-  else
-    table = Vstandard_category_table;
-
-  return copy_category_table (table);
-}
-
-DEFUN ("make-category-table", Fmake_category_table, Smake_category_table,
-       0, 0, 0,
-       doc: /* Construct a new and empty category table and return it.  */)
-  (void)
-{
-  Lisp_Object val;
-  int i;
-
-  val = Fmake_char_table (Qcategory_table, Qnil);
-  set_char_table_defalt (val, MAKE_CATEGORY_SET);
-  for (i = 0; i < (1 << CHARTAB_SIZE_BITS_0); i++)
-    set_char_table_contents (val, i, MAKE_CATEGORY_SET);
-  Fset_char_table_extra_slot (val, make_number (0),
-			      Fmake_vector (make_number (95), Qnil));
-  return val;
-}
-
 DEFUN ("set-category-table", Fset_category_table, Sset_category_table, 1, 1, 0,
        doc: /* Specify TABLE as the category table for the current buffer.
 Return TABLE.  */)
@@ -272,100 +167,6 @@ Return TABLE.  */)
   return table;
 }
 
-
-Lisp_Object
-char_category_set (int c)
-{
-  return CHAR_TABLE_REF (BVAR (current_buffer, category_table), c);
-}
-
-DEFUN ("char-category-set", Fchar_category_set, Schar_category_set, 1, 1, 0,
-       doc: /* Return the category set of CHAR.
-usage: (char-category-set CHAR)  */)
-  (Lisp_Object ch)
-{
-  CHECK_CHARACTER (ch);
-  return CATEGORY_SET (XFASTINT (ch));
-}
-
-DEFUN ("category-set-mnemonics", Fcategory_set_mnemonics,
-       Scategory_set_mnemonics, 1, 1, 0,
-       doc: /* Return a string containing mnemonics of the categories in CATEGORY-SET.
-CATEGORY-SET is a bool-vector, and the categories \"in\" it are those
-that are indexes where t occurs in the bool-vector.
-The return value is a string containing those same categories.  */)
-  (Lisp_Object category_set)
-{
-  int i, j;
-  char str[96];
-
-  CHECK_CATEGORY_SET (category_set);
-
-  j = 0;
-  for (i = 32; i < 127; i++)
-    if (CATEGORY_MEMBER (i, category_set))
-      str[j++] = i;
-  str[j] = '\0';
-
-  return build_string (str);
-}
-
-DEFUN ("modify-category-entry", Fmodify_category_entry,
-       Smodify_category_entry, 2, 4, 0,
-       doc: /* Modify the category set of CHARACTER by adding CATEGORY to it.
-The category is changed only for table TABLE, which defaults to
-the current buffer's category table.
-CHARACTER can be either a single character or a cons representing the
-lower and upper ends of an inclusive character range to modify.
-CATEGORY must be a category name (a character between ` ' and `~').
-Use `describe-categories' to see existing category names.
-If optional fourth argument RESET is non-nil,
-then delete CATEGORY from the category set instead of adding it.  */)
-  (Lisp_Object character, Lisp_Object category, Lisp_Object table, Lisp_Object reset)
-{
-  bool set_value;	/* Actual value to be set in category sets.  */
-  Lisp_Object category_set;
-  int start, end;
-  int from, to;
-
-  if (INTEGERP (character))
-    {
-      CHECK_CHARACTER (character);
-      start = end = XFASTINT (character);
-    }
-  else
-    {
-      CHECK_CONS (character);
-      CHECK_CHARACTER_CAR (character);
-      CHECK_CHARACTER_CDR (character);
-      start = XFASTINT (XCAR (character));
-      end = XFASTINT (XCDR (character));
-    }
-
-  CHECK_CATEGORY (category);
-  table = check_category_table (table);
-
-  if (NILP (CATEGORY_DOCSTRING (table, XFASTINT (category))))
-    error ("Undefined category: %c", (int) XFASTINT (category));
-
-  set_value = NILP (reset);
-
-  while (start <= end)
-    {
-      from = start, to = end;
-      category_set = char_table_ref_and_range (table, start, &from, &to);
-      if (CATEGORY_MEMBER (XFASTINT (category), category_set) != NILP (reset))
-	{
-	  category_set = Fcopy_sequence (category_set);
-	  set_category_set (category_set, XFASTINT (category), set_value);
-	  category_set = hash_get_category_set (table, category_set);
-	  char_table_set_range (table, start, to, category_set);
-	}
-      start = to + 1;
-    }
-
-  return Qnil;
-}
 
 /* Return true if there is a word boundary between two word-constituent
    characters C1 and C2 if they appear in this order.
@@ -483,16 +284,10 @@ See the documentation of the variable `word-combining-categories'.  */);
   Vword_separating_categories = Qnil;
 
   defsubr (&Smake_category_set);
-  defsubr (&Sdefine_category);
   defsubr (&Scategory_docstring);
   defsubr (&Sget_unused_category);
   defsubr (&Sstandard_category_table);
-  defsubr (&Scopy_category_table);
-  defsubr (&Smake_category_table);
   defsubr (&Sset_category_table);
-  defsubr (&Schar_category_set);
-  defsubr (&Scategory_set_mnemonics);
-  defsubr (&Smodify_category_entry);
 
   category_table_version = 0;
 }
@@ -88,8 +88,7 @@ CATEGORY_MEMBER (EMACS_INT category, Lisp_Object category_set)
 INLINE bool
 CHAR_HAS_CATEGORY (int ch, int category)
 {
-  Lisp_Object category_set = CATEGORY_SET (ch);
-  return CATEGORY_MEMBER (category, category_set);
+  return char_has_category (ch, category);
 }
 
 /* The standard category table is stored where it will automatically
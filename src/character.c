@@ -67,92 +67,6 @@ translate_char (Lisp_Object table, int c)
   return c;
 }
 
-/* Return width (columns) of C considering the buffer display table DP. */
-
-static ptrdiff_t
-char_width (int c, struct Lisp_Char_Table *dp)
-{
-  ptrdiff_t width = CHARACTER_WIDTH (c);
-
-  if (dp)
-    {
-      Lisp_Object disp = DISP_CHAR_VECTOR (dp, c), ch;
-      int i;
-
-      if (VECTORP (disp))
-	for (i = 0, width = 0; i < ASIZE (disp); i++)
-	  {
-	    ch = AREF (disp, i);
-	    if (CHARACTERP (ch))
-	      {
-		int w = CHARACTER_WIDTH (XFASTINT (ch));
-		if (INT_ADD_WRAPV (width, w, &width))
-		  string_overflow ();
-	      }
-	  }
-    }
-  return width;
-}
-
-
-DEFUN ("char-width", Fchar_width, Schar_width, 1, 1, 0,
-       doc: /* Return width of CHAR when displayed in the current buffer.
-The width is measured by how many columns it occupies on the screen.
-Tab is taken to occupy `tab-width' columns.
-usage: (char-width CHAR)  */)
-  (Lisp_Object ch)
-{
-  int c;
-  ptrdiff_t width;
-
-  CHECK_CHARACTER (ch);
-  c = XINT (ch);
-  width = char_width (c, buffer_display_table ());
-  return make_number (width);
-}
-
-/* Return width of string STR of length LEN when displayed in the
-   current buffer.  The width is measured by how many columns it
-   occupies on the screen.  If PRECISION > 0, return the width of
-   longest substring that doesn't exceed PRECISION, and set number of
-   characters and bytes of the substring in *NCHARS and *NBYTES
-   respectively.  */
-
-ptrdiff_t
-c_string_width (const unsigned char *str, ptrdiff_t len, int precision,
-		ptrdiff_t *nchars, ptrdiff_t *nbytes)
-{
-  ptrdiff_t i = 0, i_byte = 0;
-  ptrdiff_t width = 0;
-  struct Lisp_Char_Table *dp = buffer_display_table ();
-
-  while (i_byte < len)
-    {
-      int bytes;
-      int c = STRING_CHAR_AND_LENGTH (str + i_byte, bytes);
-      ptrdiff_t thiswidth = char_width (c, dp);
-
-      if (0 < precision && precision - width < thiswidth)
-	{
-	  *nchars = i;
-	  *nbytes = i_byte;
-	  return width;
-	}
-      if (INT_ADD_WRAPV (thiswidth, width, &width))
-	string_overflow ();
-      i++;
-      i_byte += bytes;
-  }
-
-  if (precision > 0)
-    {
-      *nchars = i;
-      *nbytes = i_byte;
-    }
-
-  return width;
-}
-
 /* Return width of string STR of length LEN when displayed in the
    current buffer.  The width is measured by how many columns it
    occupies on the screen.  */
@@ -163,96 +77,6 @@ strwidth (const char *str, ptrdiff_t len)
   return c_string_width ((const unsigned char *) str, len, -1, NULL, NULL);
 }
 
-/* Return width of Lisp string STRING when displayed in the current
-   buffer.  The width is measured by how many columns it occupies on
-   the screen while paying attention to compositions.  If PRECISION >
-   0, return the width of longest substring that doesn't exceed
-   PRECISION, and set number of characters and bytes of the substring
-   in *NCHARS and *NBYTES respectively.  */
-
-ptrdiff_t
-lisp_string_width (Lisp_Object string, ptrdiff_t precision,
-		   ptrdiff_t *nchars, ptrdiff_t *nbytes)
-{
-  ptrdiff_t len = SCHARS (string);
-  /* This set multibyte to 0 even if STRING is multibyte when it
-     contains only ascii and eight-bit-graphic, but that's
-     intentional.  */
-  bool multibyte = len < SBYTES (string);
-  unsigned char *str = SDATA (string);
-  ptrdiff_t i = 0, i_byte = 0;
-  ptrdiff_t width = 0;
-  struct Lisp_Char_Table *dp = buffer_display_table ();
-
-  while (i < len)
-    {
-      ptrdiff_t chars, bytes, thiswidth;
-      Lisp_Object val;
-      ptrdiff_t cmp_id;
-      ptrdiff_t ignore, end;
-
-      if (find_composition (i, -1, &ignore, &end, &val, string)
-	  && ((cmp_id = get_composition_id (i, i_byte, end - i, val, string))
-	      >= 0))
-	{
-	  thiswidth = composition_table[cmp_id]->width;
-	  chars = end - i;
-	  bytes = string_char_to_byte (string, end) - i_byte;
-	}
-      else
-	{
-	  int c;
-
-	  if (multibyte)
-	    {
-	      int cbytes;
-	      c = STRING_CHAR_AND_LENGTH (str + i_byte, cbytes);
-	      bytes = cbytes;
-	    }
-	  else
-	    c = str[i_byte], bytes = 1;
-	  chars = 1;
-	  thiswidth = char_width (c, dp);
-	}
-
-      if (0 < precision && precision - width < thiswidth)
-	{
-	  *nchars = i;
-	  *nbytes = i_byte;
-	  return width;
-	}
-      if (INT_ADD_WRAPV (thiswidth, width, &width))
-	string_overflow ();
-      i += chars;
-      i_byte += bytes;
-    }
-
-  if (precision > 0)
-    {
-      *nchars = i;
-      *nbytes = i_byte;
-    }
-
-  return width;
-}
-
-DEFUN ("string-width", Fstring_width, Sstring_width, 1, 1, 0,
-       doc: /* Return width of STRING when displayed in the current buffer.
-Width is measured by how many columns it occupies on the screen.
-When calculating width of a multibyte character in STRING,
-only the base leading-code is considered; the validity of
-the following bytes is not checked.  Tabs in STRING are always
-taken to occupy `tab-width' columns.
-usage: (string-width STRING)  */)
-  (Lisp_Object str)
-{
-  Lisp_Object val;
-
-  CHECK_STRING (str);
-  XSETFASTINT (val, lisp_string_width (str, -1, NULL, NULL));
-  return val;
-}
-
 /* Return the number of characters in the NBYTES bytes at PTR.
    This works by looking at the contents and checking for multibyte
    sequences while assuming that there's no invalid sequence.
@@ -610,8 +434,6 @@ syms_of_character (void)
   staticpro (&Vchar_unify_table);
   Vchar_unify_table = Qnil;
 
-  defsubr (&Schar_width);
-  defsubr (&Sstring_width);
   defsubr (&Sstring);
   defsubr (&Sunibyte_string);
   defsubr (&Schar_resolve_modifiers);
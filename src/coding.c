@@ -6758,6 +6758,54 @@ detect_coding (struct coding_system *coding)
   coding->mode = saved_mode;
 }
 
+/* Don't look at more than this many bytes to guess the coding system
+   statistically.  */
+#define CONFIDENCE_DETECTION_MAX_BYTES 65536
+
+/* CODING was set up for the undecided coding system UNDECIDED_ID, but
+   detect_coding found nothing better than raw-text or no-conversion
+   for the NBYTES unibyte bytes at SRC.  If they are confidently
+   recognized by detect_coding_confidence, i.e. the best candidate has
+   a confidence of at least `coding-detection-confidence-threshold',
+   set up CODING for that candidate and return true.  Otherwise, leave
+   CODING alone and return false.  */
+
+static bool
+detect_coding_by_confidence (struct coding_system *coding,
+			     ptrdiff_t undecided_id,
+			     const unsigned char *src, ptrdiff_t nbytes)
+{
+  Lisp_Object eol_type = CODING_ID_EOL_TYPE (undecided_id);
+  Lisp_Object candidates, found;
+  int specified_eol;
+
+  if (! NUMBERP (Vcoding_detection_confidence_threshold)
+      || ! EQ (CODING_ATTR_TYPE (CODING_ID_ATTRS (undecided_id)), Qundecided)
+      || ! EQ (CODING_ATTR_TYPE (CODING_ID_ATTRS (coding->id)), Qraw_text))
+    return false;
+
+  candidates = detect_coding_confidence (src,
+					 min (nbytes,
+					      CONFIDENCE_DETECTION_MAX_BYTES),
+					 false);
+  if (! CONSP (candidates)
+      || (XFLOAT_DATA (XCDR (XCAR (candidates)))
+	  < XFLOATINT (Vcoding_detection_confidence_threshold)))
+    return false;
+  found = XCAR (XCAR (candidates));
+  if (EQ (found, Qundecided) || NILP (Fcoding_system_p (found)))
+    return false;
+
+  specified_eol = (VECTORP (eol_type) ? EOL_SEEN_NONE
+		   : EQ (eol_type, Qdos) ? EOL_SEEN_CRLF
+		   : EQ (eol_type, Qmac) ? EOL_SEEN_CR
+		   : EOL_SEEN_LF);
+  setup_coding_system (found, coding);
+  if (specified_eol != EOL_SEEN_NONE)
+    adjust_coding_eol_type (coding, specified_eol);
+  return true;
+}
+
 
 static void
 decode_eol (struct coding_system *coding)
@@ -7788,6 +7836,130 @@ static Lisp_Object Vcode_conversion_reused_workbuf;
 static bool reused_workbuf_in_use;
 
 
+/* If CODING can be handled by the native coders, fill SPEC with its
+   parameters and return true.  Return false if CODING needs the
+   generic machinery, i.e. it is not fully detected yet, or it has
+   pre/post conversion functions or a translation table.  */
+
+static bool
+fast_coding_spec_for (struct coding_system *coding, bool encodep,
+		      struct fast_coding_spec *spec)
+{
+  Lisp_Object attrs = CODING_ID_ATTRS (coding->id);
+  Lisp_Object coding_type = CODING_ATTR_TYPE (attrs);
+  Lisp_Object eol_type;
+
+  if (! NILP (encodep ? CODING_ATTR_PRE_WRITE (attrs)
+	      : CODING_ATTR_POST_READ (attrs))
+      || ! NILP (get_translation_table (attrs, encodep, NULL)))
+    return false;
+
+  spec->bom = utf_without_bom;
+  spec->endian = utf_16_big_endian;
+  if (EQ (coding_type, Qraw_text))
+    spec->type = fast_coding_raw_text;
+  else if (EQ (coding_type, Qcharset))
+    {
+      Lisp_Object charsets = CODING_ATTR_CHARSET_LIST (attrs);
+
+      if (! CONSP (charsets) || ! NILP (XCDR (charsets))
+	  || ! EQ (CHARSET_NAME (CHARSET_FROM_ID (XINT (XCAR (charsets)))),
+		   Qiso_8859_1))
+	return false;
+      spec->type = fast_coding_latin_1;
+    }
+  else if (EQ (coding_type, Qutf_8))
+    {
+      if (CODING_UTF_8_BOM (coding) == utf_detect_bom)
+	return false;
+      spec->type = fast_coding_utf_8;
+      spec->bom = CODING_UTF_8_BOM (coding);
+    }
+  else if (EQ (coding_type, Qutf_16))
+    {
+      if (CODING_UTF_16_BOM (coding) == utf_detect_bom)
+	return false;
+      spec->type = fast_coding_utf_16;
+      spec->bom = CODING_UTF_16_BOM (coding);
+      spec->endian = CODING_UTF_16_ENDIAN (coding);
+    }
+  else
+    return false;
+
+  eol_type = inhibit_eol_conversion ? Qunix : CODING_ID_EOL_TYPE (coding->id);
+  if (VECTORP (eol_type))
+    /* Undecided EOL is detected on decoding, and means Unix on
+       encoding, like in encode_coding.  */
+    spec->eol = encodep ? fast_coding_eol_unix : fast_coding_eol_undecided;
+  else if (EQ (eol_type, Qdos))
+    spec->eol = fast_coding_eol_dos;
+  else if (EQ (eol_type, Qmac))
+    spec->eol = fast_coding_eol_mac;
+  else
+    spec->eol = fast_coding_eol_unix;
+  return true;
+}
+
+/* Decode the NBYTES bytes at SOURCE by the native decoder for CODING.
+   Return the decoded string, which is multibyte if MULTIBYTE, or Qnil
+   if there is no native decoder for CODING.  */
+
+static Lisp_Object
+decode_coding_fast (struct coding_system *coding, const unsigned char *source,
+		    ptrdiff_t nbytes, bool multibyte)
+{
+  struct fast_coding_spec spec;
+  Lisp_Object decoded;
+
+  if (! fast_coding_spec_for (coding, false, &spec)
+      /* Only raw bytes can be put in a unibyte destination as is.  */
+      || (! multibyte && spec.type != fast_coding_raw_text))
+    return Qnil;
+
+  decoded = fast_decode_string (source, nbytes, &spec, multibyte);
+  if (spec.eol == fast_coding_eol_unix)
+    adjust_coding_eol_type (coding, EOL_SEEN_LF);
+  else if (spec.eol == fast_coding_eol_dos)
+    adjust_coding_eol_type (coding, EOL_SEEN_CRLF);
+  else if (spec.eol == fast_coding_eol_mac)
+    adjust_coding_eol_type (coding, EOL_SEEN_CR);
+  coding->produced = SBYTES (decoded);
+  coding->produced_char = SCHARS (decoded);
+  return decoded;
+}
+
+/* Encode STRING by the native encoder for CODING.  Return the encoded
+   unibyte string, or Qnil if there is no native encoder for CODING or
+   STRING contains characters it can't encode.  */
+
+static Lisp_Object
+encode_coding_fast (struct coding_system *coding, Lisp_Object string)
+{
+  struct fast_coding_spec spec;
+  Lisp_Object encoded;
+
+  if (! fast_coding_spec_for (coding, true, &spec))
+    return Qnil;
+
+  encoded = fast_encode_string (string, &spec);
+  if (! NILP (encoded))
+    coding->produced = coding->produced_char = SBYTES (encoded);
+  return encoded;
+}
+
+/* If CODING_SYSTEM can be encoded by the native encoder, fill SPEC
+   with its parameters and return true.  This lets callers encode text
+   piece by piece, without making a string of all of it first.  */
+
+bool
+fast_encoding_spec (Lisp_Object coding_system, struct fast_coding_spec *spec)
+{
+  struct coding_system coding;
+
+  setup_coding_system (coding_system, &coding);
+  return fast_coding_spec_for (&coding, true, spec);
+}
+
 /* Return a working buffer of code conversion.  MULTIBYTE specifies the
    multibyteness of returning buffer.  */
 
@@ -7884,7 +8056,17 @@ decode_coding_gap (struct coding_system *coding,
   coding->detected_utf8_bytes = coding->detected_utf8_chars = -1;
   coding->eol_seen = EOL_SEEN_NONE;
   if (CODING_REQUIRE_DETECTION (coding))
-    detect_coding (coding);
+    {
+      ptrdiff_t undecided_id = coding->id;
+
+      detect_coding (coding);
+      /* Only guess statistically when the priorities of
+	 `coding-category-list' give nothing but raw bytes, e.g. for
+	 UTF-16 text without a BOM.  */
+      if (! coding->src_multibyte)
+	detect_coding_by_confidence (coding, undecided_id,
+				     GAP_END_ADDR - bytes, bytes);
+    }
   attrs = CODING_ID_ATTRS (coding->id);
   if (! disable_ascii_optimization
       && ! coding->src_multibyte
@@ -7963,6 +8145,23 @@ decode_coding_gap (struct coding_system *coding,
 	  return;
 	}
     }
+  if (! coding->src_multibyte)
+    {
+      Lisp_Object decoded
+	= decode_coding_fast (coding, GAP_END_ADDR - coding->src_bytes,
+			      coding->src_bytes, coding->dst_multibyte);
+
+      if (! NILP (decoded))
+	{
+	  bytes = SBYTES (decoded);
+	  if (GAP_SIZE < bytes)
+	    make_gap (bytes - GAP_SIZE);
+	  memcpy (GPT_ADDR, SDATA (decoded), bytes);
+	  insert_from_gap (SCHARS (decoded), bytes, false);
+	  return;
+	}
+    }
+
   code_conversion_save (0, 0);
 
   coding->mode |= CODING_MODE_LAST_BLOCK;
@@ -8887,7 +9086,14 @@ or its subsidiary coding system according to a detected end-of-line
 format.
 
 If optional argument HIGHEST is non-nil, return the coding system of
-highest priority.  */)
+highest priority.
+
+If HIGHEST is `:confidence', guess the coding system statistically
+instead, and return an alist of (CODING-SYSTEM . CONFIDENCE), best
+first, where CONFIDENCE is a float between 0 and 1.  Only the
+encodings utf-8, utf-16le, utf-16be, windows-1252, shift_jis, euc-jp
+and gb18030 are considered.  ASCII-only text gives ((undecided . 1.0)),
+and binary data gives nil.  */)
   (Lisp_Object start, Lisp_Object end, Lisp_Object highest)
 {
   ptrdiff_t from, to;
@@ -8901,6 +9107,11 @@ highest priority.  */)
   if (from < GPT && to >= GPT)
     move_gap_both (to, to_byte);
 
+  if (EQ (highest, QCconfidence))
+    return detect_coding_confidence (BYTE_POS_ADDR (from_byte),
+				     to_byte - from_byte,
+				     !NILP (BVAR (current_buffer,
+						  enable_multibyte_characters)));
   return detect_coding_system (BYTE_POS_ADDR (from_byte),
 			       to - from, to_byte - from_byte,
 			       !NILP (highest),
@@ -8922,11 +9133,21 @@ or its subsidiary coding system according to a detected end-of-line
 format.
 
 If optional argument HIGHEST is non-nil, return the coding system of
-highest priority.  */)
+highest priority.
+
+If HIGHEST is `:confidence', guess the coding system statistically
+instead, and return an alist of (CODING-SYSTEM . CONFIDENCE), best
+first, where CONFIDENCE is a float between 0 and 1.  Only the
+encodings utf-8, utf-16le, utf-16be, windows-1252, shift_jis, euc-jp
+and gb18030 are considered.  ASCII-only text gives ((undecided . 1.0)),
+and binary data gives nil.  */)
   (Lisp_Object string, Lisp_Object highest)
 {
   CHECK_STRING (string);
 
+  if (EQ (highest, QCconfidence))
+    return detect_coding_confidence (SDATA (string), SBYTES (string),
+				     STRING_MULTIBYTE (string));
   return detect_coding_system (SDATA (string),
 			       SCHARS (string), SBYTES (string),
 			       !NILP (highest), STRING_MULTIBYTE (string),
@@ -9455,6 +9676,22 @@ code_convert_string (Lisp_Object string, Lisp_Object coding_system,
       invalidate_buffer_caches (buf, buf_pt, buf_pt);
     }
 
+  if (EQ (dst_object, Qt))
+    {
+      Lisp_Object converted
+	= (encodep ? encode_coding_fast (&coding, string)
+	   : STRING_MULTIBYTE (string) ? Qnil
+	   : decode_coding_fast (&coding, SDATA (string), bytes,
+				 ! CODING_FOR_UNIBYTE (&coding)));
+
+      if (! NILP (converted))
+	{
+	  if (! norecord)
+	    Vlast_coding_system_used = CODING_ID_NAME (coding.id);
+	  return converted;
+	}
+    }
+
   if (encodep)
     encode_coding_object (&coding, string, 0, 0, chars, bytes, dst_object);
   else
@@ -10878,6 +11115,7 @@ syms_of_coding (void)
   DEFSYM (Qemacs_mule, "emacs-mule");
 
   DEFSYM (QCcategory, ":category");
+  DEFSYM (QCconfidence, ":confidence");
   DEFSYM (QCmnemonic, ":mnemonic");
   DEFSYM (QCdefault_char, ":default-char");
   DEFSYM (QCdecode_translation_table, ":decode-translation-table");
@@ -11257,6 +11495,21 @@ from GNU Find and GNU Grep.  Emacs will then ignore the null bytes and
 decode text as usual.  */);
   inhibit_null_byte_detection = 0;
 
+  DEFVAR_LISP ("coding-detection-confidence-threshold",
+	       Vcoding_detection_confidence_threshold,
+	       doc: /* Confidence needed to trust the statistical coding detection.
+When decoding text read from a file with an undecided coding system,
+Emacs detects its coding system following `coding-system-priority-list'.
+If that finds nothing but raw bytes, as for UTF-16 text without a byte
+order mark, Emacs guesses the coding system by scoring the text with
+statistical models of some common encodings, as `detect-coding-region'
+does with HIGHEST set to `:confidence'.  If the best candidate has a
+confidence of at least this number, it is used to decode the text.
+
+The value should be a number between 0 and 1, or nil to disable the
+statistical detection.  */);
+  Vcoding_detection_confidence_threshold = make_float (0.95);
+
   DEFVAR_BOOL ("disable-ascii-optimization", disable_ascii_optimization,
 	       doc: /* If non-nil, Emacs does not optimize code decoder for ASCII files.
 Internal use only.  Remove after the experimental optimizer becomes stable.  */);
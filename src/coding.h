@@ -380,6 +380,35 @@ struct utf_16_spec
   int surrogate;
 };
 
+/* Coding systems that have a native decoder and encoder, see
+   fast_decode_string and fast_encode_string.  */
+enum fast_coding_type
+  {
+    fast_coding_none,
+    fast_coding_raw_text,
+    fast_coding_latin_1,
+    fast_coding_utf_8,
+    fast_coding_utf_16
+  };
+
+enum fast_coding_eol
+  {
+    /* Detect the EOL format while decoding; the format found, if
+       any, is stored back.  */
+    fast_coding_eol_undecided,
+    fast_coding_eol_unix,
+    fast_coding_eol_dos,
+    fast_coding_eol_mac
+  };
+
+struct fast_coding_spec
+{
+  enum fast_coding_type type;
+  enum utf_bom_type bom;
+  enum utf_16_endian_type endian;
+  enum fast_coding_eol eol;
+};
+
 struct coding_detection_info
 {
   /* Values of these members are bitwise-OR of CATEGORY_MASK_XXXs.  */
@@ -687,6 +716,15 @@ extern void decode_coding_object (struct coding_system *,
 extern void encode_coding_object (struct coding_system *,
                                   Lisp_Object, ptrdiff_t, ptrdiff_t,
                                   ptrdiff_t, ptrdiff_t, Lisp_Object);
+extern bool fast_encoding_spec (Lisp_Object, struct fast_coding_spec *);
+
+/* Defined in Rust.  */
+extern Lisp_Object fast_decode_string (const unsigned char *, ptrdiff_t,
+				       struct fast_coding_spec *, bool);
+extern Lisp_Object fast_encode_string (Lisp_Object,
+				       const struct fast_coding_spec *);
+extern Lisp_Object detect_coding_confidence (const unsigned char *,
+					     ptrdiff_t, bool);
 
 #if defined (WINDOWSNT) || defined (CYGWIN)
 
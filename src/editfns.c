@@ -45,10 +45,8 @@ along with GNU Emacs.  If not, see <https://www.gnu.org/licenses/>.  */
 #endif
 
 #include <errno.h>
-#include <float.h>
 #include <limits.h>
 
-#include <c-ctype.h>
 #include <intprops.h>
 #include <stdlib.h>
 #include <strftime.h>
@@ -74,7 +72,6 @@ static Lisp_Object format_time_string (char const *, ptrdiff_t, struct timespec,
 static long int tm_gmtoff (struct tm *);
 static int tm_diff (struct tm *, struct tm *);
 static void update_buffer_properties (ptrdiff_t, ptrdiff_t);
-static Lisp_Object styled_format (ptrdiff_t, Lisp_Object *, bool);
 
 #ifndef HAVE_TM_GMTOFF
 # define HAVE_TM_GMTOFF false
@@ -106,16 +103,6 @@ emacs_localtime_rz (timezone_t tz, time_t const *t, struct tm *tm)
   return tm;
 }
 
-static time_t
-emacs_mktime_z (timezone_t tz, struct tm *tm)
-{
-  errno = 0;
-  time_t t = mktime_z (tz, tm);
-  if (t == (time_t) -1 && errno == ENOMEM)
-    memory_full (SIZE_MAX);
-  return t;
-}
-
 /* Allocate a timezone, signaling on failure.  */
 static timezone_t
 xtzalloc (char const *name)
@@ -1314,114 +1301,6 @@ lo_time (time_t t)
   return t & ((1 << LO_TIME_BITS) - 1);
 }
 
-DEFUN ("current-time", Fcurrent_time, Scurrent_time, 0, 0, 0,
-       doc: /* Return the current time, as the number of seconds since 1970-01-01 00:00:00.
-The time is returned as a list of integers (HIGH LOW USEC PSEC).
-HIGH has the most significant bits of the seconds, while LOW has the
-least significant 16 bits.  USEC and PSEC are the microsecond and
-picosecond counts.  */)
-  (void)
-{
-  return make_lisp_time (current_timespec ());
-}
-
-static struct lisp_time
-time_add (struct lisp_time ta, struct lisp_time tb)
-{
-  EMACS_INT hi = ta.hi + tb.hi;
-  int lo = ta.lo + tb.lo;
-  int us = ta.us + tb.us;
-  int ps = ta.ps + tb.ps;
-  us += (1000000 <= ps);
-  ps -= (1000000 <= ps) * 1000000;
-  lo += (1000000 <= us);
-  us -= (1000000 <= us) * 1000000;
-  hi += (1 << LO_TIME_BITS <= lo);
-  lo -= (1 << LO_TIME_BITS <= lo) << LO_TIME_BITS;
-  return (struct lisp_time) { hi, lo, us, ps };
-}
-
-static struct lisp_time
-time_subtract (struct lisp_time ta, struct lisp_time tb)
-{
-  EMACS_INT hi = ta.hi - tb.hi;
-  int lo = ta.lo - tb.lo;
-  int us = ta.us - tb.us;
-  int ps = ta.ps - tb.ps;
-  us -= (ps < 0);
-  ps += (ps < 0) * 1000000;
-  lo -= (us < 0);
-  us += (us < 0) * 1000000;
-  hi -= (lo < 0);
-  lo += (lo < 0) << LO_TIME_BITS;
-  return (struct lisp_time) { hi, lo, us, ps };
-}
-
-static Lisp_Object
-time_arith (Lisp_Object a, Lisp_Object b,
-	    struct lisp_time (*op) (struct lisp_time, struct lisp_time))
-{
-  int alen, blen;
-  struct lisp_time ta = lisp_time_struct (a, &alen);
-  struct lisp_time tb = lisp_time_struct (b, &blen);
-  struct lisp_time t = op (ta, tb);
-  if (FIXNUM_OVERFLOW_P (t.hi))
-    time_overflow ();
-  Lisp_Object val = Qnil;
-
-  switch (max (alen, blen))
-    {
-    default:
-      val = Fcons (make_number (t.ps), val);
-      FALLTHROUGH;
-    case 3:
-      val = Fcons (make_number (t.us), val);
-      FALLTHROUGH;
-    case 2:
-      val = Fcons (make_number (t.lo), val);
-      val = Fcons (make_number (t.hi), val);
-      break;
-    }
-
-  return val;
-}
-
-DEFUN ("time-add", Ftime_add, Stime_add, 2, 2, 0,
-       doc: /* Return the sum of two time values A and B, as a time value.
-A nil value for either argument stands for the current time.
-See `current-time-string' for the various forms of a time value.  */)
-  (Lisp_Object a, Lisp_Object b)
-{
-  return time_arith (a, b, time_add);
-}
-
-DEFUN ("time-subtract", Ftime_subtract, Stime_subtract, 2, 2, 0,
-       doc: /* Return the difference between two time values A and B, as a time value.
-Use `float-time' to convert the difference into elapsed seconds.
-A nil value for either argument stands for the current time.
-See `current-time-string' for the various forms of a time value.  */)
-  (Lisp_Object a, Lisp_Object b)
-{
-  return time_arith (a, b, time_subtract);
-}
-
-DEFUN ("time-less-p", Ftime_less_p, Stime_less_p, 2, 2, 0,
-       doc: /* Return non-nil if time value T1 is earlier than time value T2.
-A nil value for either argument stands for the current time.
-See `current-time-string' for the various forms of a time value.  */)
-  (Lisp_Object t1, Lisp_Object t2)
-{
-  int t1len, t2len;
-  struct lisp_time a = lisp_time_struct (t1, &t1len);
-  struct lisp_time b = lisp_time_struct (t2, &t2len);
-  return ((a.hi != b.hi ? a.hi < b.hi
-	   : a.lo != b.lo ? a.lo < b.lo
-	   : a.us != b.us ? a.us < b.us
-	   : a.ps < b.ps)
-	  ? Qt : Qnil);
-}
-
-
 DEFUN ("get-internal-run-time", Fget_internal_run_time, Sget_internal_run_time,
        0, 0, 0,
        doc: /* Return the current run time used by Emacs.
@@ -1513,7 +1392,18 @@ disassemble_lisp_time (Lisp_Object specified_time, Lisp_Object *phigh,
 	    len = 2;
 	}
       else
-	len = 2;
+	{
+	  /* (TICKS . HZ), which stands for TICKS / HZ seconds.  */
+	  if (! (INTEGERP (high) && INTEGERP (low) && 0 < XINT (low)))
+	    return 0;
+	  EMACS_INT ticks = XINT (high), hz = XINT (low);
+	  EMACS_INT secs = ticks / hz - (ticks % hz < 0);
+	  EMACS_INT ps = (ticks - secs * hz) * 1e12L / hz;
+	  high = make_number (secs >> LO_TIME_BITS);
+	  low = make_number (secs & ((1 << LO_TIME_BITS) - 1));
+	  usec = make_number (ps / 1000000);
+	  psec = make_number (ps % 1000000);
+	}
 
       /* When combining components, require LOW to be an integer,
 	 as otherwise it would be a pain to add up times.  */
@@ -1713,28 +1603,6 @@ lisp_seconds_argument (Lisp_Object specified_time)
   return (t.hi << LO_TIME_BITS) + t.lo;
 }
 
-DEFUN ("float-time", Ffloat_time, Sfloat_time, 0, 1, 0,
-       doc: /* Return the current time, as a float number of seconds since the epoch.
-If SPECIFIED-TIME is given, it is the time to convert to float
-instead of the current time.  The argument should have the form
-\(HIGH LOW) or (HIGH LOW USEC) or (HIGH LOW USEC PSEC).  Thus,
-you can use times from `current-time' and from `file-attributes'.
-SPECIFIED-TIME can also have the form (HIGH . LOW), but this is
-considered obsolete.
-
-WARNING: Since the result is floating point, it may not be exact.
-If precise time stamps are required, use either `current-time',
-or (if you need time as a string) `format-time-string'.  */)
-  (Lisp_Object specified_time)
-{
-  double t;
-  Lisp_Object high, low, usec, psec;
-  if (! (disassemble_lisp_time (specified_time, &high, &low, &usec, &psec)
-	 && decode_time_components (high, low, usec, psec, 0, &t)))
-    invalid_time ();
-  return make_float (t);
-}
-
 /* Write information into buffer S of size MAXSIZE, according to the
    FORMAT of length FORMAT_LEN, using time information taken from *TP.
    Use the time zone specified by TZ.
@@ -1785,81 +1653,6 @@ emacs_nmemftime (char *s, size_t maxsize, const char *format,
     }
 }
 
-DEFUN ("format-time-string", Fformat_time_string, Sformat_time_string, 1, 3, 0,
-       doc: /* Use FORMAT-STRING to format the time TIME, or now if omitted or nil.
-TIME is specified as (HIGH LOW USEC PSEC), as returned by
-`current-time' or `file-attributes'.  It can also be a single integer
-number of seconds since the epoch.  The obsolete form (HIGH . LOW) is
-also still accepted.
-
-The optional ZONE is omitted or nil for Emacs local time, t for
-Universal Time, `wall' for system wall clock time, or a string as in
-the TZ environment variable.  It can also be a list (as from
-`current-time-zone') or an integer (as from `decode-time') applied
-without consideration for daylight saving time.
-
-The value is a copy of FORMAT-STRING, but with certain constructs replaced
-by text that describes the specified date and time in TIME:
-
-%Y is the year, %y within the century, %C the century.
-%G is the year corresponding to the ISO week, %g within the century.
-%m is the numeric month.
-%b and %h are the locale's abbreviated month name, %B the full name.
- (%h is not supported on MS-Windows.)
-%d is the day of the month, zero-padded, %e is blank-padded.
-%u is the numeric day of week from 1 (Monday) to 7, %w from 0 (Sunday) to 6.
-%a is the locale's abbreviated name of the day of week, %A the full name.
-%U is the week number starting on Sunday, %W starting on Monday,
- %V according to ISO 8601.
-%j is the day of the year.
-
-%H is the hour on a 24-hour clock, %I is on a 12-hour clock, %k is like %H
- only blank-padded, %l is like %I blank-padded.
-%p is the locale's equivalent of either AM or PM.
-%q is the calendar quarter (1–4).
-%M is the minute.
-%S is the second.
-%N is the nanosecond, %6N the microsecond, %3N the millisecond, etc.
-%Z is the time zone name, %z is the numeric form.
-%s is the number of seconds since 1970-01-01 00:00:00 +0000.
-
-%c is the locale's date and time format.
-%x is the locale's "preferred" date format.
-%D is like "%m/%d/%y".
-%F is the ISO 8601 date format (like "%Y-%m-%d").
-
-%R is like "%H:%M", %T is like "%H:%M:%S", %r is like "%I:%M:%S %p".
-%X is the locale's "preferred" time format.
-
-Finally, %n is a newline, %t is a tab, %% is a literal %.
-
-Certain flags and modifiers are available with some format controls.
-The flags are `_', `-', `^' and `#'.  For certain characters X,
-%_X is like %X, but padded with blanks; %-X is like %X,
-but without padding.  %^X is like %X, but with all textual
-characters up-cased; %#X is like %X, but with letter-case of
-all textual characters reversed.
-%NX (where N stands for an integer) is like %X,
-but takes up at least N (a number) positions.
-The modifiers are `E' and `O'.  For certain characters X,
-%EX is a locale's alternative version of %X;
-%OX is like %X, but uses the locale's number symbols.
-
-For example, to produce full ISO 8601 format, use "%FT%T%z".
-
-usage: (format-time-string FORMAT-STRING &optional TIME ZONE)  */)
-  (Lisp_Object format_string, Lisp_Object timeval, Lisp_Object zone)
-{
-  struct timespec t = lisp_time_argument (timeval);
-  struct tm tm;
-
-  CHECK_STRING (format_string);
-  format_string = code_convert_string_norecord (format_string,
-						Vlocale_coding_system, 1);
-  return format_time_string (SSDATA (format_string), SBYTES (format_string),
-			     t, zone, &tm);
-}
-
 static Lisp_Object
 format_time_string (char const *format, ptrdiff_t formatlen,
 		    struct timespec t, Lisp_Object zone, struct tm *tmp)
@@ -1910,124 +1703,6 @@ format_time_string (char const *format, ptrdiff_t formatlen,
   return result;
 }
 
-DEFUN ("decode-time", Fdecode_time, Sdecode_time, 0, 2, 0,
-       doc: /* Decode a time value as (SEC MINUTE HOUR DAY MONTH YEAR DOW DST UTCOFF).
-The optional TIME should be a list of (HIGH LOW . IGNORED),
-as from `current-time' and `file-attributes', or nil to use the
-current time.  It can also be a single integer number of seconds since
-the epoch.  The obsolete form (HIGH . LOW) is also still accepted.
-
-The optional ZONE is omitted or nil for Emacs local time, t for
-Universal Time, `wall' for system wall clock time, or a string as in
-the TZ environment variable.  It can also be a list (as from
-`current-time-zone') or an integer (the UTC offset in seconds) applied
-without consideration for daylight saving time.
-
-The list has the following nine members: SEC is an integer between 0
-and 60; SEC is 60 for a leap second, which only some operating systems
-support.  MINUTE is an integer between 0 and 59.  HOUR is an integer
-between 0 and 23.  DAY is an integer between 1 and 31.  MONTH is an
-integer between 1 and 12.  YEAR is an integer indicating the
-four-digit year.  DOW is the day of week, an integer between 0 and 6,
-where 0 is Sunday.  DST is t if daylight saving time is in effect,
-otherwise nil.  UTCOFF is an integer indicating the UTC offset in
-seconds, i.e., the number of seconds east of Greenwich.  (Note that
-Common Lisp has different meanings for DOW and UTCOFF.)
-
-usage: (decode-time &optional TIME ZONE)  */)
-  (Lisp_Object specified_time, Lisp_Object zone)
-{
-  time_t time_spec = lisp_seconds_argument (specified_time);
-  struct tm local_tm, gmt_tm;
-  timezone_t tz = tzlookup (zone, false);
-  struct tm *tm = emacs_localtime_rz (tz, &time_spec, &local_tm);
-  xtzfree (tz);
-
-  if (! (tm
-	 && MOST_NEGATIVE_FIXNUM - TM_YEAR_BASE <= local_tm.tm_year
-	 && local_tm.tm_year <= MOST_POSITIVE_FIXNUM - TM_YEAR_BASE))
-    time_overflow ();
-
-  /* Avoid overflow when INT_MAX < EMACS_INT_MAX.  */
-  EMACS_INT tm_year_base = TM_YEAR_BASE;
-
-  return CALLN (Flist,
-		make_number (local_tm.tm_sec),
-		make_number (local_tm.tm_min),
-		make_number (local_tm.tm_hour),
-		make_number (local_tm.tm_mday),
-		make_number (local_tm.tm_mon + 1),
-		make_number (local_tm.tm_year + tm_year_base),
-		make_number (local_tm.tm_wday),
-		local_tm.tm_isdst ? Qt : Qnil,
-		(HAVE_TM_GMTOFF
-		 ? make_number (tm_gmtoff (&local_tm))
-		 : gmtime_r (&time_spec, &gmt_tm)
-		 ? make_number (tm_diff (&local_tm, &gmt_tm))
-		 : Qnil));
-}
-
-/* Return OBJ - OFFSET, checking that OBJ is a valid fixnum and that
-   the result is representable as an int.  */
-static int
-check_tm_member (Lisp_Object obj, int offset)
-{
-  CHECK_NUMBER (obj);
-  EMACS_INT n = XINT (obj);
-  int result;
-  if (INT_SUBTRACT_WRAPV (n, offset, &result))
-    time_overflow ();
-  return result;
-}
-
-DEFUN ("encode-time", Fencode_time, Sencode_time, 6, MANY, 0,
-       doc: /* Convert SECOND, MINUTE, HOUR, DAY, MONTH, YEAR and ZONE to internal time.
-This is the reverse operation of `decode-time', which see.
-
-The optional ZONE is omitted or nil for Emacs local time, t for
-Universal Time, `wall' for system wall clock time, or a string as in
-the TZ environment variable.  It can also be a list (as from
-`current-time-zone') or an integer (as from `decode-time') applied
-without consideration for daylight saving time.
-
-You can pass more than 7 arguments; then the first six arguments
-are used as SECOND through YEAR, and the *last* argument is used as ZONE.
-The intervening arguments are ignored.
-This feature lets (apply \\='encode-time (decode-time ...)) work.
-
-Out-of-range values for SECOND, MINUTE, HOUR, DAY, or MONTH are allowed;
-for example, a DAY of 0 means the day preceding the given month.
-Year numbers less than 100 are treated just like other year numbers.
-If you want them to stand for years in this century, you must do that yourself.
-
-Years before 1970 are not guaranteed to work.  On some systems,
-year values as low as 1901 do work.
-
-usage: (encode-time SECOND MINUTE HOUR DAY MONTH YEAR &optional ZONE)  */)
-  (ptrdiff_t nargs, Lisp_Object *args)
-{
-  time_t value;
-  struct tm tm;
-  Lisp_Object zone = (nargs > 6 ? args[nargs - 1] : Qnil);
-
-  tm.tm_sec  = check_tm_member (args[0], 0);
-  tm.tm_min  = check_tm_member (args[1], 0);
-  tm.tm_hour = check_tm_member (args[2], 0);
-  tm.tm_mday = check_tm_member (args[3], 0);
-  tm.tm_mon  = check_tm_member (args[4], 1);
-  tm.tm_year = check_tm_member (args[5], TM_YEAR_BASE);
-  tm.tm_isdst = -1;
-
-  timezone_t tz = tzlookup (zone, false);
-  value = emacs_mktime_z (tz, &tm);
-  xtzfree (tz);
-
-  if (value == (time_t) -1)
-    time_overflow ();
-
-  return list2i (hi_time (value), lo_time (value));
-}
-
 DEFUN ("current-time-string", Fcurrent_time_string, Scurrent_time_string,
        0, 2, 0,
        doc: /* Return the current local time, as a human-readable string.
@@ -3780,902 +3455,6 @@ DEFUN ("current-message", Fcurrent_message, Scurrent_message, 0, 0, 0,
   return current_message ();
 }
 
-/* Convert the prefix of STR from ASCII decimal digits to a number.
-   Set *STR_END to the address of the first non-digit.  Return the
-   number, or PTRDIFF_MAX on overflow.  Return 0 if there is no number.
-   This is like strtol for ptrdiff_t and base 10 and C locale,
-   except without negative numbers or errno.  */
-
-static ptrdiff_t
-str2num (char *str, char **str_end)
-{
-  ptrdiff_t n = 0;
-  for (; c_isdigit (*str); str++)
-    if (INT_MULTIPLY_WRAPV (n, 10, &n) || INT_ADD_WRAPV (n, *str - '0', &n))
-      n = PTRDIFF_MAX;
-  *str_end = str;
-  return n;
-}
-
-DEFUN ("format", Fformat, Sformat, 1, MANY, 0,
-       doc: /* Format a string out of a format-string and arguments.
-The first argument is a format control string.
-The other arguments are substituted into it to make the result, a string.
-
-The format control string may contain %-sequences meaning to substitute
-the next available argument, or the argument explicitly specified:
-
-%s means print a string argument.  Actually, prints any object, with `princ'.
-%d means print as signed number in decimal.
-%o means print as unsigned number in octal, %x as unsigned number in hex.
-%X is like %x, but uses upper case.
-%e means print a number in exponential notation.
-%f means print a number in decimal-point notation.
-%g means print a number in exponential notation if the exponent would be
-   less than -4 or greater than or equal to the precision (default: 6);
-   otherwise it prints in decimal-point notation.
-%c means print a number as a single character.
-%S means print any object as an s-expression (using `prin1').
-
-The argument used for %d, %o, %x, %e, %f, %g or %c must be a number.
-Use %% to put a single % into the output.
-
-A %-sequence other than %% may contain optional field number, flag,
-width, and precision specifiers, as follows:
-
-  %<field><flags><width><precision>character
-
-where field is [0-9]+ followed by a literal dollar "$", flags is
-[+ #-0]+, width is [0-9]+, and precision is a literal period "."
-followed by [0-9]+.
-
-If a %-sequence is numbered with a field with positive value N, the
-Nth argument is substituted instead of the next one.  A format can
-contain either numbered or unnumbered %-sequences but not both, except
-that %% can be mixed with numbered %-sequences.
-
-The + flag character inserts a + before any positive number, while a
-space inserts a space before any positive number; these flags only
-affect %d, %e, %f, and %g sequences, and the + flag takes precedence.
-The - and 0 flags affect the width specifier, as described below.
-
-The # flag means to use an alternate display form for %o, %x, %X, %e,
-%f, and %g sequences: for %o, it ensures that the result begins with
-\"0\"; for %x and %X, it prefixes the result with \"0x\" or \"0X\";
-for %e and %f, it causes a decimal point to be included even if the
-the precision is zero; for %g, it causes a decimal point to be
-included even if the the precision is zero, and also forces trailing
-zeros after the decimal point to be left in place.
-
-The width specifier supplies a lower limit for the length of the
-printed representation.  The padding, if any, normally goes on the
-left, but it goes on the right if the - flag is present.  The padding
-character is normally a space, but it is 0 if the 0 flag is present.
-The 0 flag is ignored if the - flag is present, or the format sequence
-is something other than %d, %e, %f, and %g.
-
-For %e and %f sequences, the number after the "." in the precision
-specifier says how many decimal places to show; if zero, the decimal
-point itself is omitted.  For %g, the precision specifies how many
-significant digits to print; zero or omitted are treated as 1.
-For %s and %S, the precision specifier truncates the string to the
-given width.
-
-Text properties, if any, are copied from the format-string to the
-produced text.
-
-usage: (format STRING &rest OBJECTS)  */)
-  (ptrdiff_t nargs, Lisp_Object *args)
-{
-  return styled_format (nargs, args, false);
-}
-
-DEFUN ("format-message", Fformat_message, Sformat_message, 1, MANY, 0,
-       doc: /* Format a string out of a format-string and arguments.
-The first argument is a format control string.
-The other arguments are substituted into it to make the result, a string.
-
-This acts like `format', except it also replaces each grave accent (\\=`)
-by a left quote, and each apostrophe (\\=') by a right quote.  The left
-and right quote replacement characters are specified by
-`text-quoting-style'.
-
-usage: (format-message STRING &rest OBJECTS)  */)
-  (ptrdiff_t nargs, Lisp_Object *args)
-{
-  return styled_format (nargs, args, true);
-}
-
-/* Implement ‘format-message’ if MESSAGE is true, ‘format’ otherwise.  */
-
-static Lisp_Object
-styled_format (ptrdiff_t nargs, Lisp_Object *args, bool message)
-{
-  ptrdiff_t n;		/* The number of the next arg to substitute.  */
-  char initial_buffer[4000];
-  char *buf = initial_buffer;
-  ptrdiff_t bufsize = sizeof initial_buffer;
-  ptrdiff_t max_bufsize = STRING_BYTES_BOUND + 1;
-  char *p;
-  ptrdiff_t buf_save_value_index UNINIT;
-  char *format, *end;
-  ptrdiff_t nchars;
-  /* When we make a multibyte string, we must pay attention to the
-     byte combining problem, i.e., a byte may be combined with a
-     multibyte character of the previous string.  This flag tells if we
-     must consider such a situation or not.  */
-  bool maybe_combine_byte;
-  bool arg_intervals = false;
-  USE_SAFE_ALLOCA;
-  sa_avail -= sizeof initial_buffer;
-
-  /* Information recorded for each format spec.  */
-  struct info
-  {
-    /* The corresponding argument, converted to string if conversion
-       was needed.  */
-    Lisp_Object argument;
-
-    /* The start and end bytepos in the output string.  */
-    ptrdiff_t start, end;
-
-    /* Whether the argument is a string with intervals.  */
-    bool_bf intervals : 1;
-  } *info;
-
-  CHECK_STRING (args[0]);
-  char *format_start = SSDATA (args[0]);
-  bool multibyte_format = STRING_MULTIBYTE (args[0]);
-  ptrdiff_t formatlen = SBYTES (args[0]);
-
-  /* Upper bound on number of format specs.  Each uses at least 2 chars.  */
-  ptrdiff_t nspec_bound = SCHARS (args[0]) >> 1;
-
-  /* Allocate the info and discarded tables.  */
-  ptrdiff_t alloca_size;
-  if (INT_MULTIPLY_WRAPV (nspec_bound, sizeof *info, &alloca_size)
-      || INT_ADD_WRAPV (formatlen, alloca_size, &alloca_size)
-      || SIZE_MAX < alloca_size)
-    memory_full (SIZE_MAX);
-  info = SAFE_ALLOCA (alloca_size);
-  /* discarded[I] is 1 if byte I of the format
-     string was not copied into the output.
-     It is 2 if byte I was not the first byte of its character.  */
-  char *discarded = (char *) &info[nspec_bound];
-  memset (discarded, 0, formatlen);
-
-  /* Try to determine whether the result should be multibyte.
-     This is not always right; sometimes the result needs to be multibyte
-     because of an object that we will pass through prin1.
-     or because a grave accent or apostrophe is requoted,
-     and in that case, we won't know it here.  */
-
-  /* True if the output should be a multibyte string,
-     which is true if any of the inputs is one.  */
-  bool multibyte = multibyte_format;
-  for (ptrdiff_t i = 1; !multibyte && i < nargs; i++)
-    if (STRINGP (args[i]) && STRING_MULTIBYTE (args[i]))
-      multibyte = true;
-
-  int quoting_style = message ? text_quoting_style () : -1;
-
-  ptrdiff_t ispec;
-  ptrdiff_t nspec = 0;
-
-  /* If we start out planning a unibyte result,
-     then discover it has to be multibyte, we jump back to retry.  */
- retry:
-
-  p = buf;
-  nchars = 0;
-
-  /* N is the argument index, ISPEC is the specification index.  */
-  n = 0;
-  ispec = 0;
-
-  /* Scan the format and store result in BUF.  */
-  format = format_start;
-  end = format + formatlen;
-  maybe_combine_byte = false;
-
-  while (format != end)
-    {
-      /* The values of N, ISPEC, and FORMAT when the loop body is
-         entered.  */
-      ptrdiff_t n0 = n;
-      ptrdiff_t ispec0 = ispec;
-      char *format0 = format;
-      char const *convsrc = format;
-      unsigned char format_char = *format++;
-
-      /* Bytes needed to represent the output of this conversion.  */
-      ptrdiff_t convbytes = 1;
-
-      if (format_char == '%')
-	{
-	  /* General format specifications look like
-
-	     '%' [field-number] [flags] [field-width] [precision] format
-
-	     where
-
-             field-number ::= [0-9]+ '$'
-	     flags ::= [-+0# ]+
-	     field-width ::= [0-9]+
-	     precision ::= '.' [0-9]*
-
-	     If present, a field-number specifies the argument number
-	     to substitute.  Otherwise, the next argument is taken.
-
-	     If a field-width is specified, it specifies to which width
-	     the output should be padded with blanks, if the output
-	     string is shorter than field-width.
-
-	     If precision is specified, it specifies the number of
-	     digits to print after the '.' for floats, or the max.
-	     number of chars to print from a string.  */
-
-	  ptrdiff_t num;
-	  char *num_end;
-	  if (c_isdigit (*format))
-	    {
-	      num = str2num (format, &num_end);
-	      if (*num_end == '$')
-		{
-		  n = num - 1;
-		  format = num_end + 1;
-		}
-	    }
-
-	  bool minus_flag = false;
-	  bool  plus_flag = false;
-	  bool space_flag = false;
-	  bool sharp_flag = false;
-	  bool  zero_flag = false;
-
-	  for (; ; format++)
-	    {
-	      switch (*format)
-		{
-		case '-': minus_flag = true; continue;
-		case '+':  plus_flag = true; continue;
-		case ' ': space_flag = true; continue;
-		case '#': sharp_flag = true; continue;
-		case '0':  zero_flag = true; continue;
-		}
-	      break;
-	    }
-
-	  /* Ignore flags when sprintf ignores them.  */
-	  space_flag &= ! plus_flag;
-	  zero_flag &= ! minus_flag;
-
-	  num = str2num (format, &num_end);
-	  if (max_bufsize <= num)
-	    string_overflow ();
-	  ptrdiff_t field_width = num;
-
-	  bool precision_given = *num_end == '.';
-	  ptrdiff_t precision = (precision_given
-				 ? str2num (num_end + 1, &num_end)
-				 : PTRDIFF_MAX);
-	  format = num_end;
-
-	  if (format == end)
-	    error ("Format string ends in middle of format specifier");
-
-	  char conversion = *format++;
-	  memset (&discarded[format0 - format_start], 1,
-		  format - format0 - (conversion == '%'));
-	  if (conversion == '%')
-	    goto copy_char;
-
-	  ++n;
-	  if (! (n < nargs))
-	    error ("Not enough arguments for format string");
-
-	  struct info *spec = &info[ispec++];
-	  if (nspec < ispec)
-	    {
-	      spec->argument = args[n];
-	      spec->intervals = false;
-	      nspec = ispec;
-	    }
-	  Lisp_Object arg = spec->argument;
-
-	  /* For 'S', prin1 the argument, and then treat like 's'.
-	     For 's', princ any argument that is not a string or
-	     symbol.  But don't do this conversion twice, which might
-	     happen after retrying.  */
-	  if ((conversion == 'S'
-	       || (conversion == 's'
-		   && ! STRINGP (arg) && ! SYMBOLP (arg))))
-	    {
-	      if (EQ (arg, args[n]))
-		{
-		  Lisp_Object noescape = conversion == 'S' ? Qnil : Qt;
-		  spec->argument = arg = Fprin1_to_string (arg, noescape);
-		  if (STRING_MULTIBYTE (arg) && ! multibyte)
-		    {
-		      multibyte = true;
-		      goto retry;
-		    }
-		}
-	      conversion = 's';
-	    }
-	  else if (conversion == 'c')
-	    {
-	      if (INTEGERP (arg) && ! ASCII_CHAR_P (XINT (arg)))
-		{
-		  if (!multibyte)
-		    {
-		      multibyte = true;
-		      goto retry;
-		    }
-		  spec->argument = arg = Fchar_to_string (arg);
-		}
-
-	      if (!EQ (arg, args[n]))
-		conversion = 's';
-	      zero_flag = false;
-	    }
-
-	  if (SYMBOLP (arg))
-	    {
-	      spec->argument = arg = SYMBOL_NAME (arg);
-	      if (STRING_MULTIBYTE (arg) && ! multibyte)
-		{
-		  multibyte = true;
-		  goto retry;
-		}
-	    }
-
-	  bool float_conversion
-	    = conversion == 'e' || conversion == 'f' || conversion == 'g';
-
-	  if (conversion == 's')
-	    {
-	      /* handle case (precision[n] >= 0) */
-
-	      ptrdiff_t prec = -1;
-	      if (precision_given)
-		prec = precision;
-
-	      /* lisp_string_width ignores a precision of 0, but GNU
-		 libc functions print 0 characters when the precision
-		 is 0.  Imitate libc behavior here.  Changing
-		 lisp_string_width is the right thing, and will be
-		 done, but meanwhile we work with it. */
-
-	      ptrdiff_t width, nbytes;
-	      ptrdiff_t nchars_string;
-	      if (prec == 0)
-		width = nchars_string = nbytes = 0;
-	      else
-		{
-		  ptrdiff_t nch, nby;
-		  width = lisp_string_width (arg, prec, &nch, &nby);
-		  if (prec < 0)
-		    {
-		      nchars_string = SCHARS (arg);
-		      nbytes = SBYTES (arg);
-		    }
-		  else
-		    {
-		      nchars_string = nch;
-		      nbytes = nby;
-		    }
-		}
-
-	      convbytes = nbytes;
-	      if (convbytes && multibyte && ! STRING_MULTIBYTE (arg))
-		convbytes = count_size_as_multibyte (SDATA (arg), nbytes);
-
-	      ptrdiff_t padding
-		= width < field_width ? field_width - width : 0;
-
-	      if (max_bufsize - padding <= convbytes)
-		string_overflow ();
-	      convbytes += padding;
-	      if (convbytes <= buf + bufsize - p)
-		{
-		  if (! minus_flag)
-		    {
-		      memset (p, ' ', padding);
-		      p += padding;
-		      nchars += padding;
-		    }
-		  spec->start = nchars;
-
-		  if (p > buf
-		      && multibyte
-		      && !ASCII_CHAR_P (*((unsigned char *) p - 1))
-		      && STRING_MULTIBYTE (arg)
-		      && !CHAR_HEAD_P (SREF (arg, 0)))
-		    maybe_combine_byte = true;
-
-		  p += copy_text (SDATA (arg), (unsigned char *) p,
-				  nbytes,
-				  STRING_MULTIBYTE (arg), multibyte);
-
-		  nchars += nchars_string;
-
-		  if (minus_flag)
-		    {
-		      memset (p, ' ', padding);
-		      p += padding;
-		      nchars += padding;
-		    }
-		  spec->end = nchars;
-
-		  /* If this argument has text properties, record where
-		     in the result string it appears.  */
-		  if (string_intervals (arg))
-		    spec->intervals = arg_intervals = true;
-
-		  continue;
-		}
-	    }
-	  else if (! (conversion == 'c' || conversion == 'd'
-		      || float_conversion || conversion == 'i'
-		      || conversion == 'o' || conversion == 'x'
-		      || conversion == 'X'))
-	    error ("Invalid format operation %%%c",
-		   STRING_CHAR ((unsigned char *) format - 1));
-	  else if (! (INTEGERP (arg) || (FLOATP (arg) && conversion != 'c')))
-	    error ("Format specifier doesn't match argument type");
-	  else
-	    {
-	      enum
-	      {
-		/* Lower bound on the number of bits per
-		   base-FLT_RADIX digit.  */
-		DIG_BITS_LBOUND = FLT_RADIX < 16 ? 1 : 4,
-
-		/* 1 if integers should be formatted as long doubles,
-		   because they may be so large that there is a rounding
-		   error when converting them to double, and long doubles
-		   are wider than doubles.  */
-		INT_AS_LDBL = (DIG_BITS_LBOUND * DBL_MANT_DIG < FIXNUM_BITS - 1
-			       && DBL_MANT_DIG < LDBL_MANT_DIG),
-
-		/* Maximum precision for a %f conversion such that the
-		   trailing output digit might be nonzero.  Any precision
-		   larger than this will not yield useful information.  */
-		USEFUL_PRECISION_MAX =
-		  ((1 - LDBL_MIN_EXP)
-		   * (FLT_RADIX == 2 || FLT_RADIX == 10 ? 1
-		      : FLT_RADIX == 16 ? 4
-		      : -1)),
-
-		/* Maximum number of bytes generated by any format, if
-		   precision is no more than USEFUL_PRECISION_MAX.
-		   On all practical hosts, %f is the worst case.  */
-		SPRINTF_BUFSIZE =
-		  sizeof "-." + (LDBL_MAX_10_EXP + 1) + USEFUL_PRECISION_MAX,
-
-		/* Length of pM (that is, of pMd without the
-		   trailing "d").  */
-		pMlen = sizeof pMd - 2
-	      };
-	      verify (USEFUL_PRECISION_MAX > 0);
-
-	      /* Avoid undefined behavior in underlying sprintf.  */
-	      if (conversion == 'd' || conversion == 'i')
-		sharp_flag = false;
-
-	      /* Create the copy of the conversion specification, with
-		 any width and precision removed, with ".*" inserted,
-		 with "L" possibly inserted for floating-point formats,
-		 and with pM inserted for integer formats.
-		 At most two flags F can be specified at once.  */
-	      char convspec[sizeof "%FF.*d" + max (INT_AS_LDBL, pMlen)];
-	      {
-		char *f = convspec;
-		*f++ = '%';
-		/* MINUS_FLAG and ZERO_FLAG are dealt with later.  */
-		*f = '+'; f +=  plus_flag;
-		*f = ' '; f += space_flag;
-		*f = '#'; f += sharp_flag;
-                *f++ = '.';
-                *f++ = '*';
-		if (float_conversion)
-		  {
-		    if (INT_AS_LDBL)
-		      {
-			*f = 'L';
-			f += INTEGERP (arg);
-		      }
-		  }
-		else if (conversion != 'c')
-		  {
-		    memcpy (f, pMd, pMlen);
-		    f += pMlen;
-		    zero_flag &= ! precision_given;
-		  }
-		*f++ = conversion;
-		*f = '\0';
-	      }
-
-	      int prec = -1;
-	      if (precision_given)
-		prec = min (precision, USEFUL_PRECISION_MAX);
-
-	      /* Use sprintf to format this number into sprintf_buf.  Omit
-		 padding and excess precision, though, because sprintf limits
-		 output length to INT_MAX.
-
-		 There are four types of conversion: double, unsigned
-		 char (passed as int), wide signed int, and wide
-		 unsigned int.  Treat them separately because the
-		 sprintf ABI is sensitive to which type is passed.  Be
-		 careful about integer overflow, NaNs, infinities, and
-		 conversions; for example, the min and max macros are
-		 not suitable here.  */
-	      char sprintf_buf[SPRINTF_BUFSIZE];
-	      ptrdiff_t sprintf_bytes;
-	      if (float_conversion)
-		{
-		  if (INT_AS_LDBL && INTEGERP (arg))
-		    {
-		      /* Although long double may have a rounding error if
-			 DIG_BITS_LBOUND * LDBL_MANT_DIG < FIXNUM_BITS - 1,
-			 it is more accurate than plain 'double'.  */
-		      long double x = XINT (arg);
-		      sprintf_bytes = sprintf (sprintf_buf, convspec, prec, x);
-		    }
-		  else
-		    sprintf_bytes = sprintf (sprintf_buf, convspec, prec,
-					     XFLOATINT (arg));
-		}
-	      else if (conversion == 'c')
-		{
-		  /* Don't use sprintf here, as it might mishandle prec.  */
-		  sprintf_buf[0] = XINT (arg);
-		  sprintf_bytes = prec != 0;
-		}
-	      else if (conversion == 'd' || conversion == 'i')
-		{
-		  /* For float, maybe we should use "%1.0f"
-		     instead so it also works for values outside
-		     the integer range.  */
-		  printmax_t x;
-		  if (INTEGERP (arg))
-		    x = XINT (arg);
-		  else
-		    {
-		      double d = XFLOAT_DATA (arg);
-		      if (d < 0)
-			{
-			  x = TYPE_MINIMUM (printmax_t);
-			  if (x < d)
-			    x = d;
-			}
-		      else
-			{
-			  x = TYPE_MAXIMUM (printmax_t);
-			  if (d < x)
-			    x = d;
-			}
-		    }
-		  sprintf_bytes = sprintf (sprintf_buf, convspec, prec, x);
-		}
-	      else
-		{
-		  /* Don't sign-extend for octal or hex printing.  */
-		  uprintmax_t x;
-		  if (INTEGERP (arg))
-		    x = XUINT (arg);
-		  else
-		    {
-		      double d = XFLOAT_DATA (arg);
-		      if (d < 0)
-			x = 0;
-		      else
-			{
-			  x = TYPE_MAXIMUM (uprintmax_t);
-			  if (d < x)
-			    x = d;
-			}
-		    }
-		  sprintf_bytes = sprintf (sprintf_buf, convspec, prec, x);
-		}
-
-	      /* Now the length of the formatted item is known, except it omits
-		 padding and excess precision.  Deal with excess precision
-		 first.  This happens only when the format specifies
-		 ridiculously large precision.  */
-	      ptrdiff_t excess_precision
-		= precision_given ? precision - prec : 0;
-	      ptrdiff_t leading_zeros = 0, trailing_zeros = 0;
-	      if (excess_precision)
-		{
-		  if (float_conversion)
-		    {
-		      if ((conversion == 'g' && ! sharp_flag)
-			  || ! ('0' <= sprintf_buf[sprintf_bytes - 1]
-				&& sprintf_buf[sprintf_bytes - 1] <= '9'))
-			excess_precision = 0;
-		      else
-			{
-			  if (conversion == 'g')
-			    {
-			      char *dot = strchr (sprintf_buf, '.');
-			      if (!dot)
-				excess_precision = 0;
-			    }
-			}
-		      trailing_zeros = excess_precision;
-		    }
-		  else
-		    leading_zeros = excess_precision;
-		}
-
-	      /* Compute the total bytes needed for this item, including
-		 excess precision and padding.  */
-	      ptrdiff_t numwidth;
-	      if (INT_ADD_WRAPV (sprintf_bytes, excess_precision, &numwidth))
-		numwidth = PTRDIFF_MAX;
-	      ptrdiff_t padding
-		= numwidth < field_width ? field_width - numwidth : 0;
-	      if (max_bufsize - sprintf_bytes <= excess_precision
-		  || max_bufsize - padding <= numwidth)
-		string_overflow ();
-	      convbytes = numwidth + padding;
-
-	      if (convbytes <= buf + bufsize - p)
-		{
-		  /* Copy the formatted item from sprintf_buf into buf,
-		     inserting padding and excess-precision zeros.  */
-
-                  char *src = sprintf_buf;
-		  char src0 = src[0];
-		  int exponent_bytes = 0;
-		  bool signedp = src0 == '-' || src0 == '+' || src0 == ' ';
-		  unsigned char after_sign = src[signedp];
-		  if (zero_flag && 0 <= char_hexdigit (after_sign))
-		    {
-		      leading_zeros += padding;
-		      padding = 0;
-		    }
-
-		  if (excess_precision
-		      && (conversion == 'e' || conversion == 'g'))
-		    {
-		      char *e = strchr (src, 'e');
-		      if (e)
-			exponent_bytes = src + sprintf_bytes - e;
-		    }
-
-		  spec->start = nchars;
-		  if (! minus_flag)
-		    {
-		      memset (p, ' ', padding);
-		      p += padding;
-		      nchars += padding;
-		    }
-
-		  *p = src0;
-		  src += signedp;
-		  p += signedp;
-		  memset (p, '0', leading_zeros);
-		  p += leading_zeros;
-		  int significand_bytes
-		    = sprintf_bytes - signedp - exponent_bytes;
-		  memcpy (p, src, significand_bytes);
-                  p += significand_bytes;
-		  src += significand_bytes;
-		  memset (p, '0', trailing_zeros);
-		  p += trailing_zeros;
-		  memcpy (p, src, exponent_bytes);
-		  p += exponent_bytes;
-
-		  nchars += leading_zeros + sprintf_bytes + trailing_zeros;
-
-		  if (minus_flag)
-		    {
-		      memset (p, ' ', padding);
-		      p += padding;
-		      nchars += padding;
-		    }
-		  spec->end = nchars;
-
-		  continue;
-		}
-	    }
-	}
-      else
-	{
-	  unsigned char str[MAX_MULTIBYTE_LENGTH];
-
-	  if ((format_char == '`' || format_char == '\'')
-	      && quoting_style == CURVE_QUOTING_STYLE)
-	    {
-	      if (! multibyte)
-		{
-		  multibyte = true;
-		  goto retry;
-		}
-	      convsrc = format_char == '`' ? uLSQM : uRSQM;
-	      convbytes = 3;
-	    }
-	  else if (format_char == '`' && quoting_style == STRAIGHT_QUOTING_STYLE)
-	    convsrc = "'";
-	  else
-	    {
-	      /* Copy a single character from format to buf.  */
-	      if (multibyte_format)
-		{
-		  /* Copy a whole multibyte character.  */
-		  if (p > buf
-		      && !ASCII_CHAR_P (*((unsigned char *) p - 1))
-		      && !CHAR_HEAD_P (format_char))
-		    maybe_combine_byte = true;
-
-		  while (! CHAR_HEAD_P (*format))
-		    format++;
-
-		  convbytes = format - format0;
-		  memset (&discarded[format0 + 1 - format_start], 2,
-			  convbytes - 1);
-		}
-	      else if (multibyte && !ASCII_CHAR_P (format_char))
-		{
-		  int c = BYTE8_TO_CHAR (format_char);
-		  convbytes = CHAR_STRING (c, str);
-		  convsrc = (char *) str;
-		}
-	    }
-
-	copy_char:
-	  if (convbytes <= buf + bufsize - p)
-	    {
-	      memcpy (p, convsrc, convbytes);
-	      p += convbytes;
-	      nchars++;
-	      continue;
-	    }
-	}
-
-      /* There wasn't enough room to store this conversion or single
-	 character.  CONVBYTES says how much room is needed.  Allocate
-	 enough room (and then some) and do it again.  */
-
-      ptrdiff_t used = p - buf;
-      if (max_bufsize - used < convbytes)
-	string_overflow ();
-      bufsize = used + convbytes;
-      bufsize = bufsize < max_bufsize / 2 ? bufsize * 2 : max_bufsize;
-
-      if (buf == initial_buffer)
-	{
-	  buf = xmalloc (bufsize);
-	  sa_must_free = true;
-	  buf_save_value_index = SPECPDL_INDEX ();
-	  record_unwind_protect_ptr (xfree, buf);
-	  memcpy (buf, initial_buffer, used);
-	}
-      else
-	{
-	  buf = xrealloc (buf, bufsize);
-	  set_unwind_protect_ptr (buf_save_value_index, xfree, buf);
-	}
-
-      p = buf + used;
-      format = format0;
-      n = n0;
-      ispec = ispec0;
-    }
-
-  if (bufsize < p - buf)
-    emacs_abort ();
-
-  if (maybe_combine_byte)
-    nchars = multibyte_chars_in_text ((unsigned char *) buf, p - buf);
-  Lisp_Object val = make_specified_string (buf, nchars, p - buf, multibyte);
-
-  /* If the format string has text properties, or any of the string
-     arguments has text properties, set up text properties of the
-     result string.  */
-
-  if (string_intervals (args[0]) || arg_intervals)
-    {
-      /* Add text properties from the format string.  */
-      Lisp_Object len = make_number (SCHARS (args[0]));
-      Lisp_Object props = text_property_list (args[0], make_number (0),
-					      len, Qnil);
-      if (CONSP (props))
-	{
-	  ptrdiff_t bytepos = 0, position = 0, translated = 0;
-	  ptrdiff_t fieldn = 0;
-
-	  /* Adjust the bounds of each text property
-	     to the proper start and end in the output string.  */
-
-	  /* Put the positions in PROPS in increasing order, so that
-	     we can do (effectively) one scan through the position
-	     space of the format string.  */
-	  props = Fnreverse (props);
-
-	  /* BYTEPOS is the byte position in the format string,
-	     POSITION is the untranslated char position in it,
-	     TRANSLATED is the translated char position in BUF,
-	     and ARGN is the number of the next arg we will come to.  */
-	  for (Lisp_Object list = props; CONSP (list); list = XCDR (list))
-	    {
-	      Lisp_Object item = XCAR (list);
-
-	      /* First adjust the property start position.  */
-	      ptrdiff_t pos = XINT (XCAR (item));
-
-	      /* Advance BYTEPOS, POSITION, TRANSLATED and ARGN
-		 up to this position.  */
-	      for (; position < pos; bytepos++)
-		{
-		  if (! discarded[bytepos])
-		    position++, translated++;
-		  else if (discarded[bytepos] == 1)
-		    {
-		      position++;
-		      if (translated == info[fieldn].start)
-			{
-			  translated += info[fieldn].end - info[fieldn].start;
-			  fieldn++;
-			}
-		    }
-		}
-
-	      XSETCAR (item, make_number (translated));
-
-	      /* Likewise adjust the property end position.  */
-	      pos = XINT (XCAR (XCDR (item)));
-
-	      for (; position < pos; bytepos++)
-		{
-		  if (! discarded[bytepos])
-		    position++, translated++;
-		  else if (discarded[bytepos] == 1)
-		    {
-		      position++;
-		      if (translated == info[fieldn].start)
-			{
-			  translated += info[fieldn].end - info[fieldn].start;
-			  fieldn++;
-			}
-		    }
-		}
-
-	      XSETCAR (XCDR (item), make_number (translated));
-	    }
-
-	  add_text_properties_from_list (val, props, make_number (0));
-	}
-
-      /* Add text properties from arguments.  */
-      if (arg_intervals)
-	for (ptrdiff_t i = 0; i < nspec; i++)
-	  if (info[i].intervals)
-	    {
-	      len = make_number (SCHARS (info[i].argument));
-	      Lisp_Object new_len = make_number (info[i].end - info[i].start);
-	      props = text_property_list (info[i].argument,
-                                          make_number (0), len, Qnil);
-	      props = extend_property_ranges (props, len, new_len);
-	      /* If successive arguments have properties, be sure that
-		 the value of `composition' property be the copy.  */
-	      if (1 < i && info[i - 1].end)
-		make_composition_value_copy (props);
-	      add_text_properties_from_list (val, props,
-					     make_number (info[i].start));
-	    }
-    }
-
-  /* If we allocated BUF or INFO with malloc, free it too.  */
-  SAFE_FREE ();
-
-  return val;
-}
-
 DEFUN ("char-equal", Fchar_equal, Schar_equal, 2, 2, 0,
        doc: /* Return t if two characters match, optionally ignoring case.
 Both arguments must be characters (i.e. integers).
@@ -5204,15 +3983,7 @@ functions if all the text being accessed has this property.  */);
   defsubr (&Sgroup_real_gid);
   defsubr (&Suser_full_name);
   defsubr (&Semacs_pid);
-  defsubr (&Scurrent_time);
-  defsubr (&Stime_add);
-  defsubr (&Stime_subtract);
-  defsubr (&Stime_less_p);
   defsubr (&Sget_internal_run_time);
-  defsubr (&Sformat_time_string);
-  defsubr (&Sfloat_time);
-  defsubr (&Sdecode_time);
-  defsubr (&Sencode_time);
   defsubr (&Scurrent_time_string);
   defsubr (&Scurrent_time_zone);
   defsubr (&Sset_time_zone_rule);
@@ -5221,8 +3992,6 @@ functions if all the text being accessed has this property.  */);
   defsubr (&Smessage_box);
   defsubr (&Smessage_or_box);
   defsubr (&Scurrent_message);
-  defsubr (&Sformat);
-  defsubr (&Sformat_message);
 
   defsubr (&Sinsert_buffer_substring);
   defsubr (&Scompare_buffer_substrings);
@@ -1500,18 +1500,10 @@ Using an Emacs configured with --with-x-toolkit=lucid does not have this problem
 #endif
 #endif /* HAVE_X_WINDOWS */
 
-#ifdef HAVE_LIBXML2
-      syms_of_xml ();
-#endif
-
 #ifdef HAVE_LCMS2
       syms_of_lcms2 ();
 #endif
 
-#ifdef HAVE_ZLIB
-      syms_of_decompress ();
-#endif
-
       syms_of_menu ();
 
 #ifdef HAVE_NTGUI
@@ -2065,10 +2057,6 @@ shut_down_emacs (int sig, Lisp_Object stuff)
   ns_term_shutdown (sig);
 #endif
 
-#ifdef HAVE_LIBXML2
-  xml_cleanup_parser ();
-#endif
-
 #ifdef WINDOWSNT
   term_ntproc (0);
 #endif
@@ -3493,6 +3493,15 @@ set_unwind_protect_ptr (ptrdiff_t count, void (*func) (void *), void *arg)
   p->unwind_ptr.arg = arg;
 }
 
+/* Return the current depth of the unwind-protect stack, for Rust
+   code, which can't use the inline SPECPDL_INDEX.  */
+
+ptrdiff_t
+c_specpdl_index (void)
+{
+  return SPECPDL_INDEX ();
+}
+
 /* Pop and execute entries from the unwind-protect stack until the
    depth COUNT is reached.  Return VALUE.  */
 
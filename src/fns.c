@@ -22,7 +22,6 @@ along with GNU Emacs.  If not, see <https://www.gnu.org/licenses/>.  */
 
 #include <stdlib.h>
 #include <unistd.h>
-#include <filevercmp.h>
 #include <intprops.h>
 #include <vla.h>
 #include <errno.h>
@@ -130,143 +129,6 @@ If string STR1 is greater, the value is a positive number N;
   return Qt;
 }
 
-DEFUN ("string-version-lessp", Fstring_version_lessp,
-       Sstring_version_lessp, 2, 2, 0,
-       doc: /* Return non-nil if S1 is less than S2, as version strings.
-
-This function compares version strings S1 and S2:
-   1) By prefix lexicographically.
-   2) Then by version (similarly to version comparison of Debian's dpkg).
-      Leading zeros in version numbers are ignored.
-   3) If both prefix and version are equal, compare as ordinary strings.
-
-For example, \"foo2.png\" compares less than \"foo12.png\".
-Case is significant.
-Symbols are also allowed; their print names are used instead.  */)
-  (Lisp_Object string1, Lisp_Object string2)
-{
-  if (SYMBOLP (string1))
-    string1 = SYMBOL_NAME (string1);
-  if (SYMBOLP (string2))
-    string2 = SYMBOL_NAME (string2);
-  CHECK_STRING (string1);
-  CHECK_STRING (string2);
-
-  char *p1 = SSDATA (string1);
-  char *p2 = SSDATA (string2);
-  char *lim1 = p1 + SBYTES (string1);
-  char *lim2 = p2 + SBYTES (string2);
-  int cmp;
-
-  while ((cmp = filevercmp (p1, p2)) == 0)
-    {
-      /* If the strings are identical through their first null bytes,
-	 skip past identical prefixes and try again.  */
-      ptrdiff_t size = strlen (p1) + 1;
-      p1 += size;
-      p2 += size;
-      if (lim1 < p1)
-	return lim2 < p2 ? Qnil : Qt;
-      if (lim2 < p2)
-	return Qnil;
-    }
-
-  return cmp < 0 ? Qt : Qnil;
-}
-
-DEFUN ("string-collate-lessp", Fstring_collate_lessp, Sstring_collate_lessp, 2, 4, 0,
-       doc: /* Return t if first arg string is less than second in collation order.
-Symbols are also allowed; their print names are used instead.
-
-This function obeys the conventions for collation order in your
-locale settings.  For example, punctuation and whitespace characters
-might be considered less significant for sorting:
-
-\(sort \\='("11" "12" "1 1" "1 2" "1.1" "1.2") \\='string-collate-lessp)
-  => ("11" "1 1" "1.1" "12" "1 2" "1.2")
-
-The optional argument LOCALE, a string, overrides the setting of your
-current locale identifier for collation.  The value is system
-dependent; a LOCALE \"en_US.UTF-8\" is applicable on POSIX systems,
-while it would be, e.g., \"enu_USA.1252\" on MS-Windows systems.
-
-If IGNORE-CASE is non-nil, characters are converted to lower-case
-before comparing them.
-
-To emulate Unicode-compliant collation on MS-Windows systems,
-bind `w32-collate-ignore-punctuation' to a non-nil value, since
-the codeset part of the locale cannot be \"UTF-8\" on MS-Windows.
-
-If your system does not support a locale environment, this function
-behaves like `string-lessp'.  */)
-  (Lisp_Object s1, Lisp_Object s2, Lisp_Object locale, Lisp_Object ignore_case)
-{
-#if defined __STDC_ISO_10646__ || defined WINDOWSNT
-  /* Check parameters.  */
-  if (SYMBOLP (s1))
-    s1 = SYMBOL_NAME (s1);
-  if (SYMBOLP (s2))
-    s2 = SYMBOL_NAME (s2);
-  CHECK_STRING (s1);
-  CHECK_STRING (s2);
-  if (!NILP (locale))
-    CHECK_STRING (locale);
-
-  return (str_collate (s1, s2, locale, ignore_case) < 0) ? Qt : Qnil;
-
-#else  /* !__STDC_ISO_10646__, !WINDOWSNT */
-  return Fstring_lessp (s1, s2);
-#endif /* !__STDC_ISO_10646__, !WINDOWSNT */
-}
-
-DEFUN ("string-collate-equalp", Fstring_collate_equalp, Sstring_collate_equalp, 2, 4, 0,
-       doc: /* Return t if two strings have identical contents.
-Symbols are also allowed; their print names are used instead.
-
-This function obeys the conventions for collation order in your locale
-settings.  For example, characters with different coding points but
-the same meaning might be considered as equal, like different grave
-accent Unicode characters:
-
-\(string-collate-equalp (string ?\\uFF40) (string ?\\u1FEF))
-  => t
-
-The optional argument LOCALE, a string, overrides the setting of your
-current locale identifier for collation.  The value is system
-dependent; a LOCALE \"en_US.UTF-8\" is applicable on POSIX systems,
-while it would be \"enu_USA.1252\" on MS Windows systems.
-
-If IGNORE-CASE is non-nil, characters are converted to lower-case
-before comparing them.
-
-To emulate Unicode-compliant collation on MS-Windows systems,
-bind `w32-collate-ignore-punctuation' to a non-nil value, since
-the codeset part of the locale cannot be \"UTF-8\" on MS-Windows.
-
-If your system does not support a locale environment, this function
-behaves like `string-equal'.
-
-Do NOT use this function to compare file names for equality.  */)
-  (Lisp_Object s1, Lisp_Object s2, Lisp_Object locale, Lisp_Object ignore_case)
-{
-#if defined __STDC_ISO_10646__ || defined WINDOWSNT
-  /* Check parameters.  */
-  if (SYMBOLP (s1))
-    s1 = SYMBOL_NAME (s1);
-  if (SYMBOLP (s2))
-    s2 = SYMBOL_NAME (s2);
-  CHECK_STRING (s1);
-  CHECK_STRING (s2);
-  if (!NILP (locale))
-    CHECK_STRING (locale);
-
-  return (str_collate (s1, s2, locale, ignore_case) == 0) ? Qt : Qnil;
-
-#else  /* !__STDC_ISO_10646__, !WINDOWSNT */
-  return Fstring_equal (s1, s2);
-#endif /* !__STDC_ISO_10646__, !WINDOWSNT */
-}
-
 static Lisp_Object concat (ptrdiff_t nargs, Lisp_Object *args,
 			   enum Lisp_Type target_type, bool last_special);
 
@@ -2125,138 +1987,6 @@ The data read from the system are decoded using `locale-coding-system'.  */)
   return Qnil;
 }
 
-/* base64 encode/decode functions (RFC 2045).
-   Based on code from GNU recode. */
-
-#define MIME_LINE_LENGTH 76
-
-ptrdiff_t base64_encode_1 (const char *, char *, ptrdiff_t, bool, bool);
-ptrdiff_t base64_decode_1 (const char *, char *, ptrdiff_t, bool,
-				  ptrdiff_t *);
-
-DEFUN ("base64-encode-region", Fbase64_encode_region, Sbase64_encode_region,
-       2, 3, "r",
-       doc: /* Base64-encode the region between BEG and END.
-Return the length of the encoded text.
-Optional third argument NO-LINE-BREAK means do not break long lines
-into shorter lines.  */)
-  (Lisp_Object beg, Lisp_Object end, Lisp_Object no_line_break)
-{
-  char *encoded;
-  ptrdiff_t allength, length;
-  ptrdiff_t ibeg, iend, encoded_length;
-  ptrdiff_t old_pos = PT;
-  USE_SAFE_ALLOCA;
-
-  validate_region (&beg, &end);
-
-  ibeg = CHAR_TO_BYTE (XFASTINT (beg));
-  iend = CHAR_TO_BYTE (XFASTINT (end));
-  move_gap_both (XFASTINT (beg), ibeg);
-
-  /* We need to allocate enough room for encoding the text.
-     We need 33 1/3% more space, plus a newline every 76
-     characters, and then we round up. */
-  length = iend - ibeg;
-  allength = length + length/3 + 1;
-  allength += allength / MIME_LINE_LENGTH + 1 + 6;
-
-  encoded = SAFE_ALLOCA (allength);
-  encoded_length = base64_encode_1 ((char *) BYTE_POS_ADDR (ibeg),
-				    encoded, length, NILP (no_line_break),
-				    !NILP (BVAR (current_buffer, enable_multibyte_characters)));
-  if (encoded_length > allength)
-    emacs_abort ();
-
-  if (encoded_length < 0)
-    {
-      /* The encoding wasn't possible. */
-      SAFE_FREE ();
-      error ("Multibyte character in data for base64 encoding");
-    }
-
-  /* Now we have encoded the region, so we insert the new contents
-     and delete the old.  (Insert first in order to preserve markers.)  */
-  SET_PT_BOTH (XFASTINT (beg), ibeg);
-  insert (encoded, encoded_length);
-  SAFE_FREE ();
-  del_range_byte (ibeg + encoded_length, iend + encoded_length);
-
-  /* If point was outside of the region, restore it exactly; else just
-     move to the beginning of the region.  */
-  if (old_pos >= XFASTINT (end))
-    old_pos += encoded_length - (XFASTINT (end) - XFASTINT (beg));
-  else if (old_pos > XFASTINT (beg))
-    old_pos = XFASTINT (beg);
-  SET_PT (old_pos);
-
-  /* We return the length of the encoded text. */
-  return make_number (encoded_length);
-}
-
-DEFUN ("base64-decode-region", Fbase64_decode_region, Sbase64_decode_region,
-       2, 2, "r",
-       doc: /* Base64-decode the region between BEG and END.
-Return the length of the decoded text.
-If the region can't be decoded, signal an error and don't modify the buffer.  */)
-  (Lisp_Object beg, Lisp_Object end)
-{
-  ptrdiff_t ibeg, iend, length, allength;
-  char *decoded;
-  ptrdiff_t old_pos = PT;
-  ptrdiff_t decoded_length;
-  ptrdiff_t inserted_chars;
-  bool multibyte = !NILP (BVAR (current_buffer, enable_multibyte_characters));
-  USE_SAFE_ALLOCA;
-
-  validate_region (&beg, &end);
-
-  ibeg = CHAR_TO_BYTE (XFASTINT (beg));
-  iend = CHAR_TO_BYTE (XFASTINT (end));
-
-  length = iend - ibeg;
-
-  /* We need to allocate enough room for decoding the text.  If we are
-     working on a multibyte buffer, each decoded code may occupy at
-     most two bytes.  */
-  allength = multibyte ? length * 2 : length;
-  decoded = SAFE_ALLOCA (allength);
-
-  move_gap_both (XFASTINT (beg), ibeg);
-  decoded_length = base64_decode_1 ((char *) BYTE_POS_ADDR (ibeg),
-				    decoded, length,
-				    multibyte, &inserted_chars);
-  if (decoded_length > allength)
-    emacs_abort ();
-
-  if (decoded_length < 0)
-    {
-      /* The decoding wasn't possible. */
-      error ("Invalid base64 data");
-    }
-
-  /* Now we have decoded the region, so we insert the new contents
-     and delete the old.  (Insert first in order to preserve markers.)  */
-  TEMP_SET_PT_BOTH (XFASTINT (beg), ibeg);
-  insert_1_both (decoded, inserted_chars, decoded_length, 0, 1, 0);
-  SAFE_FREE ();
-
-  /* Delete the original text.  */
-  del_range_both (PT, PT_BYTE, XFASTINT (end) + inserted_chars,
-		  iend + decoded_length, 1);
-
-  /* If point was outside of the region, restore it exactly; else just
-     move to the beginning of the region.  */
-  if (old_pos >= XFASTINT (end))
-    old_pos += inserted_chars - (XFASTINT (end) - XFASTINT (beg));
-  else if (old_pos > XFASTINT (beg))
-    old_pos = XFASTINT (beg);
-  SET_PT (old_pos > ZV ? ZV : old_pos);
-
-  return make_number (inserted_chars);
-}
-
-
 
 /***********************************************************************
  *****                                                             *****
@@ -3347,20 +3077,6 @@ DEFUN ("hash-table-rehash-size", Fhash_table_rehash_size,
     return make_float (rehash_size + 1);
 }
 
-DEFUN ("secure-hash-algorithms", Fsecure_hash_algorithms,
-       Ssecure_hash_algorithms, 0, 0, 0,
-       doc: /* Return a list of all the supported `secure_hash' algorithms. */)
-  (void)
-{
-  return listn (CONSTYPE_HEAP, 6,
-                Qmd5,
-                Qsha1,
-                Qsha224,
-                Qsha256,
-                Qsha384,
-                Qsha512);
-}
-
 /* Extract data from a string or a buffer. SPEC is a list of
 (BUFFER-OR-STRING-OR-SYMBOL START END CODING-SYSTEM NOERROR) which behave as
 specified with `secure-hash' and in Info node
@@ -3586,6 +3302,12 @@ syms_of_fns (void)
   DEFSYM (Qsha256, "sha256");
   DEFSYM (Qsha384, "sha384");
   DEFSYM (Qsha512, "sha512");
+  DEFSYM (Qsha3_224, "sha3-224");
+  DEFSYM (Qsha3_256, "sha3-256");
+  DEFSYM (Qsha3_384, "sha3-384");
+  DEFSYM (Qsha3_512, "sha3-512");
+  DEFSYM (Qblake2b, "blake2b");
+  DEFSYM (Qblake2s, "blake2s");
 
   /* Miscellaneous stuff.  */
 
@@ -3647,9 +3369,6 @@ this variable.  */);
   use_file_dialog = 1;
 
   defsubr (&Scompare_strings);
-  defsubr (&Sstring_version_lessp);
-  defsubr (&Sstring_collate_lessp);
-  defsubr (&Sstring_collate_equalp);
   defsubr (&Sappend);
   defsubr (&Sconcat);
   defsubr (&Svconcat);
@@ -3676,8 +3395,5 @@ this variable.  */);
   defsubr (&Swidget_put);
   defsubr (&Swidget_get);
   defsubr (&Swidget_apply);
-  defsubr (&Sbase64_encode_region);
-  defsubr (&Sbase64_decode_region);
-  defsubr (&Ssecure_hash_algorithms);
   defsubr (&Slocale_info);
 }
@@ -561,6 +561,10 @@ extern bool initialized;
 
 /* Defined in Rust.  */
 extern double extract_float (Lisp_Object);
+extern int float_to_shortest_string (char *, double);
+extern double string_to_float (char const *, ptrdiff_t);
+extern Lisp_Object char_category_set (int);
+extern bool char_has_category (int, int);
 
 
 /* Low-level conversion and type checking.  */
@@ -3840,6 +3844,7 @@ extern void record_unwind_protect_nothing (void);
 extern void clear_unwind_protect (ptrdiff_t);
 extern void set_unwind_protect (ptrdiff_t, void (*) (Lisp_Object), Lisp_Object);
 extern void set_unwind_protect_ptr (ptrdiff_t, void (*) (void *), void *);
+extern ptrdiff_t c_specpdl_index (void);
 extern Lisp_Object unbind_to (ptrdiff_t, Lisp_Object);
 extern void rebind_for_thread_switch (void);
 extern void unbind_for_thread_switch (struct thread_state *);
@@ -4273,7 +4278,6 @@ extern ptrdiff_t emacs_write_sig (int, void const *, ptrdiff_t);
 extern ptrdiff_t emacs_write_quit (int, void const *, ptrdiff_t);
 extern void emacs_perror (char const *);
 extern int renameat_noreplace (int, char const *, int, char const *);
-extern int str_collate (Lisp_Object, Lisp_Object, Lisp_Object, Lisp_Object);
 
 /* Defined in filelock.c.  */
 extern void lock_file (Lisp_Object);
@@ -4287,7 +4291,6 @@ extern void syms_of_sound (void);
 
 /* Defined in category.c.  */
 extern void init_category_once (void);
-extern Lisp_Object char_category_set (int);
 extern void syms_of_category (void);
 
 /* Defined in ccl.c.  */
@@ -4364,22 +4367,11 @@ extern void syms_of_xterm (void);
 extern char *x_get_keysym_name (int);
 #endif /* HAVE_WINDOW_SYSTEM */
 
-#ifdef HAVE_LIBXML2
-/* Defined in xml.c.  */
-extern void syms_of_xml (void);
-extern void xml_cleanup_parser (void);
-#endif
-
 #ifdef HAVE_LCMS2
 /* Defined in lcms.c.  */
 extern void syms_of_lcms2 (void);
 #endif
 
-#ifdef HAVE_ZLIB
-/* Defined in decompress.c.  */
-extern void syms_of_decompress (void);
-#endif
-
 #ifdef HAVE_DBUS
 /* Defined in dbusbind.c.  */
 void init_dbusbind (void);
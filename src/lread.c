@@ -3659,8 +3659,7 @@ string_to_number (char const *string, int base, bool ignore_trailing)
   double value = 0;
 
   /* Negate the value ourselves.  This treats 0, NaNs, and infinity properly on
-     IEEE floating point hosts, and works around a formerly-common bug where
-     atof ("-0.0") drops the sign.  */
+     IEEE floating point hosts.  */
   bool negative = *cp == '-';
 
   bool signedp = negative || *cp == '+';
@@ -3713,19 +3712,11 @@ string_to_number (char const *string, int base, bool ignore_trailing)
 	      while ('0' <= *cp && *cp <= '9');
 	    }
 	  else if (cp[-1] == '+'
-		   && cp[0] == 'I' && cp[1] == 'N' && cp[2] == 'F')
+		   && ((cp[0] == 'I' && cp[1] == 'N' && cp[2] == 'F')
+		       || (cp[0] == 'N' && cp[1] == 'a' && cp[2] == 'N')))
 	    {
 	      state |= E_EXP;
 	      cp += 3;
-	      value = INFINITY;
-	    }
-	  else if (cp[-1] == '+'
-		   && cp[0] == 'N' && cp[1] == 'a' && cp[2] == 'N')
-	    {
-	      state |= E_EXP;
-	      cp += 3;
-	      /* NAN is a "positive" NaN on all known Emacs hosts.  */
-	      value = NAN;
 	    }
 	  else
 	    cp = ecp;
@@ -3764,11 +3755,12 @@ string_to_number (char const *string, int base, bool ignore_trailing)
     }
 
   /* Either the number uses float syntax, or it does not fit into a fixnum.
-     Convert it from string to floating point, unless the value is already
-     known because it is an infinity, a NAN, or its absolute value fits in
-     uintmax_t.  */
+     Convert it from string to the nearest floating point number, unless
+     the value is already known because its absolute value fits in
+     uintmax_t.  An exponent of +INF is an infinity, and +NaN is a NaN
+     whose payload is the integer part.  */
   if (! value)
-    value = atof (string + signedp);
+    value = string_to_float (string + signedp, cp - (string + signedp));
 
   return make_float (negative ? -value : value);
 }
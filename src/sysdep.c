@@ -3799,208 +3799,3 @@ system_process_attributes (Lisp_Object pid)
 }
 
 #endif	/* !defined (WINDOWSNT) */
-
-/* Wide character string collation.  */
-
-#ifdef __STDC_ISO_10646__
-# include <wchar.h>
-# include <wctype.h>
-
-# if defined HAVE_NEWLOCALE || defined HAVE_SETLOCALE
-#  include <locale.h>
-# endif
-# ifndef LC_COLLATE
-#  define LC_COLLATE 0
-# endif
-# ifndef LC_COLLATE_MASK
-#  define LC_COLLATE_MASK 0
-# endif
-# ifndef LC_CTYPE
-#  define LC_CTYPE 0
-# endif
-# ifndef LC_CTYPE_MASK
-#  define LC_CTYPE_MASK 0
-# endif
-
-# ifndef HAVE_NEWLOCALE
-#  undef freelocale
-#  undef locale_t
-#  undef newlocale
-#  undef wcscoll_l
-#  undef towlower_l
-#  define freelocale emacs_freelocale
-#  define locale_t emacs_locale_t
-#  define newlocale emacs_newlocale
-#  define wcscoll_l emacs_wcscoll_l
-#  define towlower_l emacs_towlower_l
-
-typedef char const *locale_t;
-
-static locale_t
-newlocale (int category_mask, char const *locale, locale_t loc)
-{
-  return locale;
-}
-
-static void
-freelocale (locale_t loc)
-{
-}
-
-static char *
-emacs_setlocale (int category, char const *locale)
-{
-#  ifdef HAVE_SETLOCALE
-  errno = 0;
-  char *loc = setlocale (category, locale);
-  if (loc || errno)
-    return loc;
-  errno = EINVAL;
-#  else
-  errno = ENOTSUP;
-#  endif
-  return 0;
-}
-
-static int
-wcscoll_l (wchar_t const *a, wchar_t const *b, locale_t loc)
-{
-  int result = 0;
-  char *oldloc = emacs_setlocale (LC_COLLATE, NULL);
-  int err;
-
-  if (! oldloc)
-    err = errno;
-  else
-    {
-      USE_SAFE_ALLOCA;
-      char *oldcopy = SAFE_ALLOCA (strlen (oldloc) + 1);
-      strcpy (oldcopy, oldloc);
-      if (! emacs_setlocale (LC_COLLATE, loc))
-	err = errno;
-      else
-	{
-	  errno = 0;
-	  result = wcscoll (a, b);
-	  err = errno;
-	  if (! emacs_setlocale (LC_COLLATE, oldcopy))
-	    err = errno;
-	}
-      SAFE_FREE ();
-    }
-
-  errno = err;
-  return result;
-}
-
-static wint_t
-towlower_l (wint_t wc, locale_t loc)
-{
-  wint_t result = wc;
-  char *oldloc = emacs_setlocale (LC_CTYPE, NULL);
-
-  if (oldloc)
-    {
-      USE_SAFE_ALLOCA;
-      char *oldcopy = SAFE_ALLOCA (strlen (oldloc) + 1);
-      strcpy (oldcopy, oldloc);
-      if (emacs_setlocale (LC_CTYPE, loc))
-	{
-	  result = towlower (wc);
-	  emacs_setlocale (LC_COLLATE, oldcopy);
-	}
-      SAFE_FREE ();
-    }
-
-  return result;
-}
-# endif
-
-int
-str_collate (Lisp_Object s1, Lisp_Object s2,
-	     Lisp_Object locale, Lisp_Object ignore_case)
-{
-  int res, err;
-  ptrdiff_t len, i, i_byte;
-  wchar_t *p1, *p2;
-
-  USE_SAFE_ALLOCA;
-
-  /* Convert byte stream to code points.  */
-  len = SCHARS (s1); i = i_byte = 0;
-  SAFE_NALLOCA (p1, 1, len + 1);
-  while (i < len)
-    FETCH_STRING_CHAR_ADVANCE (*(p1+i-1), s1, i, i_byte);
-  *(p1+len) = 0;
-
-  len = SCHARS (s2); i = i_byte = 0;
-  SAFE_NALLOCA (p2, 1, len + 1);
-  while (i < len)
-    FETCH_STRING_CHAR_ADVANCE (*(p2+i-1), s2, i, i_byte);
-  *(p2+len) = 0;
-
-  if (STRINGP (locale))
-    {
-      locale_t loc = newlocale (LC_COLLATE_MASK | LC_CTYPE_MASK,
-				SSDATA (locale), 0);
-      if (!loc)
-	error ("Invalid locale %s: %s", SSDATA (locale), emacs_strerror (errno));
-
-      if (! NILP (ignore_case))
-	for (int i = 1; i < 3; i++)
-	  {
-	    wchar_t *p = (i == 1) ? p1 : p2;
-	    for (; *p; p++)
-	      *p = towlower_l (*p, loc);
-	  }
-
-      errno = 0;
-      res = wcscoll_l (p1, p2, loc);
-      err = errno;
-      freelocale (loc);
-    }
-  else
-    {
-      if (! NILP (ignore_case))
-	for (int i = 1; i < 3; i++)
-	  {
-	    wchar_t *p = (i == 1) ? p1 : p2;
-	    for (; *p; p++)
-	      *p = towlower (*p);
-	  }
-
-      errno = 0;
-      res = wcscoll (p1, p2);
-      err = errno;
-    }
-#  ifndef HAVE_NEWLOCALE
-  if (err)
-    error ("Invalid locale or string for collation: %s", emacs_strerror (err));
-#  else
-  if (err)
-    error ("Invalid string for collation: %s", emacs_strerror (err));
-#  endif
-
-  SAFE_FREE ();
-  return res;
-}
-#endif  /* __STDC_ISO_10646__ */
-
-#ifdef WINDOWSNT
-int
-str_collate (Lisp_Object s1, Lisp_Object s2,
-	     Lisp_Object locale, Lisp_Object ignore_case)
-{
-
-  char *loc = STRINGP (locale) ? SSDATA (locale) : NULL;
-  int res, err = errno;
-
-  errno = 0;
-  res = w32_compare_strings (SSDATA (s1), SSDATA (s2), loc, !NILP (ignore_case));
-  if (errno)
-    error ("Invalid string for collation: %s", strerror (errno));
-
-  errno = err;
-  return res;
-}
-#endif	/* WINDOWSNT */
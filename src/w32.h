@@ -221,9 +221,6 @@ extern int sys_link (const char *, const char *);
 extern int w32_memory_info (unsigned long long *, unsigned long long *,
 			    unsigned long long *, unsigned long long *);
 
-/* Compare 2 UTF-8 strings in locale-dependent fashion.  */
-extern int w32_compare_strings (const char *, const char *, char *, int);
-
 /* Return a cryptographically secure seed for PRNG.  */
 extern int w32_init_random (void *, ptrdiff_t);
 
@@ -68,7 +68,6 @@ along with GNU Emacs.  If not, see <http://www.gnu.org/licenses/>.  */
 	    + ((DWORD_PTR)(var) - (section)->VirtualAddress)		\
 	    + (filedata).file_base))
 
-extern BOOL g_b_init_compare_string_w;
 extern BOOL g_b_init_debug_break_process;
 
 int sys_select (int, SELECT_TYPE *, SELECT_TYPE *, SELECT_TYPE *,
@@ -3502,195 +3501,6 @@ If successful, the new layout id is returned, otherwise nil.  */)
   return Fw32_get_keyboard_layout ();
 }
 
-/* Two variables to interface between get_lcid and the EnumLocales
-   callback function below.  */
-#ifndef LOCALE_NAME_MAX_LENGTH
-# define LOCALE_NAME_MAX_LENGTH 85
-#endif
-static LCID found_lcid;
-static char lname[3 * LOCALE_NAME_MAX_LENGTH + 1 + 1];
-
-/* Callback function for EnumLocales.  */
-static BOOL CALLBACK
-get_lcid_callback (LPTSTR locale_num_str)
-{
-  char *endp;
-  char locval[2 * LOCALE_NAME_MAX_LENGTH + 1 + 1];
-  LCID try_lcid = strtoul (locale_num_str, &endp, 16);
-
-  if (GetLocaleInfo (try_lcid, LOCALE_SABBREVLANGNAME,
-		     locval, LOCALE_NAME_MAX_LENGTH))
-    {
-      size_t locval_len;
-
-      /* This is for when they only specify the language, as in "ENU".  */
-      if (stricmp (locval, lname) == 0)
-	{
-	  found_lcid = try_lcid;
-	  return FALSE;
-	}
-      locval_len = strlen (locval);
-      strcpy (locval + locval_len, "_");
-      if (GetLocaleInfo (try_lcid, LOCALE_SABBREVCTRYNAME,
-			 locval + locval_len + 1, LOCALE_NAME_MAX_LENGTH))
-	{
-	  locval_len = strlen (locval);
-	  if (strnicmp (locval, lname, locval_len) == 0
-	      && (lname[locval_len] == '.'
-		  || lname[locval_len] == '\0'))
-	    {
-	      found_lcid = try_lcid;
-	      return FALSE;
-	    }
-	}
-    }
-  return TRUE;
-}
-
-/* Return the Locale ID (LCID) number given the locale's name, a
-   string, in LOCALE_NAME.  This works by enumerating all the locales
-   supported by the system, until we find one whose name matches
-   LOCALE_NAME.  */
-static LCID
-get_lcid (const char *locale_name)
-{
-  /* A simple cache.  */
-  static LCID last_lcid;
-  static char last_locale[1000];
-
-  /* The code below is not thread-safe, as it uses static variables.
-     But this function is called only from the Lisp thread.  */
-  if (last_lcid > 0 && strcmp (locale_name, last_locale) == 0)
-    return last_lcid;
-
-  strncpy (lname, locale_name, sizeof (lname) - 1);
-  lname[sizeof (lname) - 1] = '\0';
-  found_lcid = 0;
-  EnumSystemLocales (get_lcid_callback, LCID_SUPPORTED);
-  if (found_lcid > 0)
-    {
-      last_lcid = found_lcid;
-      strcpy (last_locale, locale_name);
-    }
-  return found_lcid;
-}
-
-#ifndef _NLSCMPERROR
-# define _NLSCMPERROR INT_MAX
-#endif
-#ifndef LINGUISTIC_IGNORECASE
-# define LINGUISTIC_IGNORECASE  0x00000010
-#endif
-
-typedef int (WINAPI *CompareStringW_Proc)
-  (LCID, DWORD, LPCWSTR, int, LPCWSTR, int);
-
-int
-w32_compare_strings (const char *s1, const char *s2, char *locname,
-		     int ignore_case)
-{
-  LCID lcid = GetThreadLocale ();
-  wchar_t *string1_w, *string2_w;
-  int val, needed;
-  static CompareStringW_Proc pCompareStringW;
-  DWORD flags = 0;
-
-  USE_SAFE_ALLOCA;
-
-  /* The LCID machinery doesn't seem to support the "C" locale, so we
-     need to do that by hand.  */
-  if (locname
-      && ((locname[0] == 'C' && (locname[1] == '\0' || locname[1] == '.'))
-	  || strcmp (locname, "POSIX") == 0))
-    return (ignore_case ? stricmp (s1, s2) : strcmp (s1, s2));
-
-  if (!g_b_init_compare_string_w)
-    {
-      if (os_subtype == OS_9X)
-	{
-	  pCompareStringW =
-            (CompareStringW_Proc) GetProcAddress (LoadLibrary ("Unicows.dll"),
-                                                  "CompareStringW");
-	  if (!pCompareStringW)
-	    {
-	      errno = EINVAL;
-	      /* This return value is compatible with wcscoll and
-		 other MS CRT functions.  */
-	      return _NLSCMPERROR;
-	    }
-	}
-      else
-	pCompareStringW = CompareStringW;
-
-      g_b_init_compare_string_w = 1;
-    }
-
-  needed = pMultiByteToWideChar (CP_UTF8, MB_ERR_INVALID_CHARS, s1, -1, NULL, 0);
-  if (needed > 0)
-    {
-      SAFE_NALLOCA (string1_w, 1, needed + 1);
-      pMultiByteToWideChar (CP_UTF8, MB_ERR_INVALID_CHARS, s1, -1,
-			    string1_w, needed);
-    }
-  else
-    {
-      errno = EINVAL;
-      return _NLSCMPERROR;
-    }
-
-  needed = pMultiByteToWideChar (CP_UTF8, MB_ERR_INVALID_CHARS, s2, -1, NULL, 0);
-  if (needed > 0)
-    {
-      SAFE_NALLOCA (string2_w, 1, needed + 1);
-      pMultiByteToWideChar (CP_UTF8, MB_ERR_INVALID_CHARS, s2, -1,
-			    string2_w, needed);
-    }
-  else
-    {
-      SAFE_FREE ();
-      errno = EINVAL;
-      return _NLSCMPERROR;
-    }
-
-  if (locname)
-    {
-      /* Convert locale name string to LCID.  We don't want to use
-	 LocaleNameToLCID because (a) it is only available since
-	 Vista, and (b) it doesn't accept locale names returned by
-	 'setlocale' and 'GetLocaleInfo'.  */
-      LCID new_lcid = get_lcid (locname);
-
-      if (new_lcid > 0)
-	lcid = new_lcid;
-      else
-	error ("Invalid locale %s: Invalid argument", locname);
-    }
-
-  if (ignore_case)
-    {
-      /* NORM_IGNORECASE ignores any tertiary distinction, not just
-	 case variants.  LINGUISTIC_IGNORECASE is more selective, and
-	 is sensitive to the locale's language, but it is not
-	 available before Vista.  */
-      if (w32_major_version >= 6)
-	flags |= LINGUISTIC_IGNORECASE;
-      else
-	flags |= NORM_IGNORECASE;
-    }
-  /* This approximates what glibc collation functions do when the
-     locale's codeset is UTF-8.  */
-  if (!NILP (Vw32_collate_ignore_punctuation))
-    flags |= NORM_IGNORESYMBOLS;
-  val = pCompareStringW (lcid, flags, string1_w, -1, string2_w, -1);
-  SAFE_FREE ();
-  if (!val)
-    {
-      errno = EINVAL;
-      return _NLSCMPERROR;
-    }
-  return val - 2;
-}
-
 
 void
 syms_of_ntproc (void)
@@ -3812,20 +3622,6 @@ Any other non-nil value means do this even on remote and removable drives
 where the performance impact may be noticeable even on modern hardware.  */);
   Vw32_get_true_file_attributes = Qlocal;
 
-  DEFVAR_LISP ("w32-collate-ignore-punctuation",
-	       Vw32_collate_ignore_punctuation,
-	       doc: /* Non-nil causes string collation functions ignore punctuation on MS-Windows.
-On Posix platforms, `string-collate-lessp' and `string-collate-equalp'
-ignore punctuation characters when they compare strings, if the
-locale's codeset is UTF-8, as in \"en_US.UTF-8\".  Binding this option
-to a non-nil value will achieve a similar effect on MS-Windows, where
-locales with UTF-8 codeset are not supported.
-
-Note that setting this to non-nil will also ignore blanks and symbols
-in the strings.  So do NOT use this option when comparing file names
-for equality, only when you need to sort them.  */);
-  Vw32_collate_ignore_punctuation = Qnil;
-
   staticpro (&Vw32_valid_locale_ids);
   staticpro (&Vw32_valid_codepages);
 }
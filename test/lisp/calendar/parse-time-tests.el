@@ -57,6 +57,10 @@
                  '(13818 19266)))
   (should (equal (parse-iso8601-time-string "1998-09-12T12:21:54Z")
                  '(13818 26466)))
+  (should (equal (parse-iso8601-time-string "1998-09-12T12:21:54.5Z")
+                 '(9056029145 . 10)))
+  (should (equal (parse-iso8601-time-string "1998-W37-6T12:21:54Z")
+                 '(13818 26466)))
   (should (equal (parse-iso8601-time-string "1998-09-12T12:21:54")
                  (encode-time 54 21 12 12 9 1998))))
 
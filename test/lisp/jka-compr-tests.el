@@ -0,0 +1,58 @@
+;;; jka-compr-tests.el --- Tests for jka-compr.el  -*- lexical-binding: t; -*-
+
+;; Copyright (C) 2017 Free Software Foundation, Inc.
+
+;; This file is part of GNU Emacs.
+
+;; GNU Emacs is free software: you can redistribute it and/or modify
+;; it under the terms of the GNU General Public License as published by
+;; the Free Software Foundation, either version 3 of the License, or
+;; (at your option) any later version.
+
+;; GNU Emacs is distributed in the hope that it will be useful,
+;; but WITHOUT ANY WARRANTY; without even the implied warranty of
+;; MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+;; GNU General Public License for more details.
+
+;; You should have received a copy of the GNU General Public License
+;; along with GNU Emacs.  If not, see <https://www.gnu.org/licenses/>.
+
+;;; Code:
+
+(require 'ert)
+(require 'jka-compr)
+
+(ert-deftest jka-compr-tests-builtin-gzip ()
+  "Test writing and reading a .gz file without running gzip."
+  (let ((file (make-temp-file "jka-compr-tests" nil ".gz"))
+        (text "Grüße\n")
+        (exec-path nil)
+        (jka-compr-shell "jka-compr-tests-no-shell")
+        (coding-system-for-write 'utf-8-unix)
+        (jka-compr-verbose nil))
+    (unwind-protect
+        (with-auto-compression-mode
+          (with-temp-buffer
+            (insert text)
+            (write-region nil nil file nil 'silent))
+          (should (equal (with-temp-buffer
+                           (insert-file-contents-literally file)
+                           (gzip-decompress-string (buffer-string)))
+                         (encode-coding-string text 'utf-8)))
+          (with-temp-buffer
+            (insert-file-contents file)
+            (should (equal (buffer-string) text)))
+          (with-temp-buffer
+            (insert-file-contents file nil 1 4)
+            (should (equal (buffer-string) "rü"))))
+      (delete-file file))))
+
+(ert-deftest jka-compr-tests-builtin-gzip-p ()
+  (should (jka-compr-builtin-gzip-p "gzip" '("-c" "-q" "-d")))
+  (should-not (jka-compr-builtin-gzip-p "gzip" '("-c" "-9")))
+  (should-not (jka-compr-builtin-gzip-p "bzip2" '("-c")))
+  (let ((jka-compr-use-builtin-gzip nil))
+    (should-not (jka-compr-builtin-gzip-p "gzip" '("-c")))))
+
+(provide 'jka-compr-tests)
+;;; jka-compr-tests.el ends here
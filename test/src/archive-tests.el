@@ -0,0 +1,134 @@
+;;; archive-tests.el --- Tests for reading zip and tar archives  -*- lexical-binding: t; -*-
+
+;; Copyright (C) 2017 Free Software Foundation, Inc.
+
+;; This file is part of GNU Emacs.
+
+;; GNU Emacs is free software: you can redistribute it and/or modify
+;; it under the terms of the GNU General Public License as published by
+;; the Free Software Foundation, either version 3 of the License, or
+;; (at your option) any later version.
+
+;; GNU Emacs is distributed in the hope that it will be useful,
+;; but WITHOUT ANY WARRANTY; without even the implied warranty of
+;; MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+;; GNU General Public License for more details.
+
+;; You should have received a copy of the GNU General Public License
+;; along with GNU Emacs.  If not, see <https://www.gnu.org/licenses/>.
+
+;;; Code:
+
+(require 'ert)
+
+(defvar archive-tests-data-directory
+  (expand-file-name "data/archive" (getenv "EMACS_TEST_DIRECTORY"))
+  "Directory containing archive test data.")
+
+(defun archive-tests-file (name)
+  (expand-file-name name archive-tests-data-directory))
+
+(defun archive-tests-numbers ()
+  (mapconcat (lambda (n) (format "%d\n" n)) (number-sequence 0 499) ""))
+
+(ert-deftest archive-tests-list-zip ()
+  (let ((entries (archive-list-entries (archive-tests-file "test.zip"))))
+    (should (equal (mapcar #'car entries)
+                   (list "hello.txt" "dir/numbers.txt"
+                         (encode-coding-string "grüße.txt" 'utf-8)
+                         "bzip2.txt")))
+    (should (equal (mapcar #'cadr entries) '(14 1890 8 6)))
+    (should (equal (mapcar (lambda (entry) (nth 4 entry)) entries)
+                   '(1121 1176 1237 1294)))
+    (dolist (entry entries)
+      (should-not (multibyte-string-p (car entry)))
+      (should (= (nth 3 entry) #o100644))
+      (should (equal (format-time-string "%F %T" (nth 2 entry))
+                     "2017-10-22 12:00:00")))))
+
+(ert-deftest archive-tests-list-tar-gz ()
+  (let ((entries (archive-list-entries (archive-tests-file "test.tar.gz"))))
+    (should (equal (car entries) '("dir/" 0 (23020 34880) #o40755 0)))
+    (should (equal (cadr entries) '("hello.txt" 14 (23020 34880) #o100644 512)))
+    (should (= (length entries) 4))))
+
+(ert-deftest archive-tests-extract-entry ()
+  (dolist (file '("test.zip" "test.tar.gz"))
+    (let ((file (archive-tests-file file)))
+      (with-temp-buffer
+        (insert "<>")
+        (goto-char 2)
+        (should (eq (archive-extract-entry file "hello.txt") t))
+        (should (equal (buffer-string) "<Hello, world!\n>"))
+        (should (= (point) 16)))
+      (with-temp-buffer
+        (archive-extract-entry file "dir/numbers.txt")
+        (should (equal (buffer-string) (archive-tests-numbers))))
+      (with-temp-buffer
+        (set-buffer-multibyte nil)
+        (archive-extract-entry file "grüße.txt")
+        (should (equal (decode-coding-string (buffer-string) 'utf-8)
+                       "Grüße\n")))
+      (with-temp-buffer
+        (should-error (archive-extract-entry file "missing.txt"))))))
+
+(ert-deftest archive-tests-unsupported ()
+  ;; Members compressed with bzip2 are left to external programs.
+  (with-temp-buffer
+    (should-not (archive-extract-entry (archive-tests-file "test.zip")
+                                       "bzip2.txt"))
+    (should (= (buffer-size) 0)))
+  (let ((file (make-temp-file "archive-tests")))
+    (unwind-protect
+        (progn
+          (with-temp-file file
+            (insert "This is not an archive.\n"))
+          (should-not (archive-list-entries file))
+          (should-not (archive-extract-entry file "hello.txt")))
+      (delete-file file))))
+
+(ert-deftest archive-tests-corrupt ()
+  (let ((file (make-temp-file "archive-tests" nil ".zip")))
+    (unwind-protect
+        (progn
+          (with-temp-buffer
+            (set-buffer-multibyte nil)
+            (insert-file-contents-literally (archive-tests-file "test.zip"))
+            ;; Change the stored contents of "hello.txt".
+            (goto-char (point-min))
+            (let ((case-fold-search nil))
+              (search-forward "Hello"))
+            (replace-match "Jello")
+            (write-region nil nil file nil 'silent))
+          (should (= (length (archive-list-entries file)) 4))
+          (with-temp-buffer
+            (should-error (archive-extract-entry file "hello.txt"))))
+      (delete-file file))))
+
+(ert-deftest archive-tests-arc-mode ()
+  (require 'arc-mode)
+  (dolist (file '("test.zip" "test.tar.gz"))
+    (with-temp-buffer
+      (set-buffer-multibyte nil)
+      (insert-file-contents-literally (archive-tests-file file) t)
+      ;; Archive mode sees a gzipped tar archive decompressed, as
+      ;; `auto-compression-mode' leaves it.
+      (when (string-suffix-p ".gz" file)
+        (should (zlib-decompress-region (point-min) (point-max)))
+        (set-buffer-modified-p nil))
+      (archive-mode)
+      (should (eq archive-subtype
+                  (if (string-suffix-p ".zip" file) 'zip 'tar)))
+      (goto-char archive-file-list-start)
+      (search-forward "hello.txt")
+      (let ((descr (archive-get-descr)))
+        (should (equal (aref descr 0) "hello.txt"))
+        (should (= (aref descr 3) #o100644)))
+      (let ((extract (archive-name "extract"))
+            (archive buffer-file-name))
+        (with-temp-buffer
+          (should (funcall extract archive "hello.txt"))
+          (should (equal (buffer-string) "Hello, world!\n")))))))
+
+(provide 'archive-tests)
+;;; archive-tests.el ends here
@@ -0,0 +1,51 @@
+;;; base32-tests.el --- Tests for base32.rs
+
+;; Copyright (C) 2017 Free Software Foundation, Inc.
+
+;; This program is free software; you can redistribute it and/or modify
+;; it under the terms of the GNU General Public License as published by
+;; the Free Software Foundation, either version 3 of the License, or
+;; (at your option) any later version.
+
+;; This program is distributed in the hope that it will be useful,
+;; but WITHOUT ANY WARRANTY; without even the implied warranty of
+;; MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+;; GNU General Public License for more details.
+
+;; You should have received a copy of the GNU General Public License
+;; along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+;;; Code:
+
+(require 'ert)
+
+(ert-deftest base32-tests-strings ()
+  (should (equal (base32-encode-string "foobar") "MZXW6YTBOI======"))
+  (should (equal (base32-encode-string "foobar" t) "MZXW6YTBOI"))
+  (should (equal (base32hex-encode-string "foobar") "CPNMUOJ1E8======"))
+  (should (equal (base32-decode-string "MZXW6YTBOI======") "foobar"))
+  (should (equal (base32-decode-string "mzxw 6ytb\noi") "foobar"))
+  (should (equal (base32-decode-string "CPNMUOJ1E8======" t t) "foobar"))
+  (should (equal (base32-encode-string "\u00e9") "5E======"))
+  (should-error (base32-encode-string "\u20ac"))
+  (should-error (base32-decode-string "MZXW6YTBOI" nil t))
+  (should-error (base32-decode-string "mzxw6ytboi======" nil t))
+  (should-error (base32-decode-string "MZXW6YTBO=======")))
+
+(ert-deftest base32-tests-regions ()
+  (with-temp-buffer
+    (insert "xxfoobaryy")
+    (should (= (base32-encode-region 3 9) 16))
+    (should (equal (buffer-string) "xxMZXW6YTBOI======yy"))
+    (should (= (base32-decode-region 3 19) 6))
+    (should (equal (buffer-string) "xxfoobaryy"))
+    (should (= (base32hex-encode-region 3 9 t) 10))
+    (should (equal (buffer-string) "xxCPNMUOJ1E8yy"))
+    (should (= (base32-decode-region 3 13 t) 6))
+    (should (equal (buffer-string) "xxfoobaryy"))
+    (should-error (base32-decode-region 1 3 nil t))
+    (should (equal (buffer-string) "xxfoobaryy"))))
+
+(provide 'base32-tests)
+
+;;; base32-tests.el ends here
@@ -0,0 +1,88 @@
+;;; base64-tests.el --- Tests for base64.rs
+
+;; Copyright (C) 2017 Free Software Foundation, Inc.
+
+;; This program is free software; you can redistribute it and/or modify
+;; it under the terms of the GNU General Public License as published by
+;; the Free Software Foundation, either version 3 of the License, or
+;; (at your option) any later version.
+
+;; This program is distributed in the hope that it will be useful,
+;; but WITHOUT ANY WARRANTY; without even the implied warranty of
+;; MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+;; GNU General Public License for more details.
+
+;; You should have received a copy of the GNU General Public License
+;; along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+;;; Code:
+
+(require 'ert)
+
+(ert-deftest base64-tests-strings ()
+  (should (equal (base64-encode-string "hello world") "aGVsbG8gd29ybGQ="))
+  (should (equal (base64-decode-string "aGVsbG8gd29ybGQ=") "hello world"))
+  (should (equal (base64-encode-string "\u00e9") "6Q=="))
+  (should (equal (base64-encode-string (make-string 60 0))
+                 (concat (make-string 76 ?A) "\n" (make-string 4 ?A))))
+  (should (equal (base64-encode-string (make-string 60 0) t)
+                 (make-string 80 ?A)))
+  (should-error (base64-encode-string "\u20ac")))
+
+(ert-deftest base64-tests-base64url ()
+  (should (equal (base64url-encode-string "\373\377\277?") "-_-_Pw=="))
+  (should (equal (base64url-encode-string "\373\377\277?" t) "-_-_Pw"))
+  (should (equal (base64-encode-string "\373\377\277?") "+/+/Pw=="))
+  (should (equal (base64-decode-string "-_-_Pw" t) "\373\377\277?"))
+  (should (equal (base64-decode-string "-_-_Pw==" t t) "\373\377\277?"))
+  (should-error (base64-decode-string "-_-_Pw=="))
+  (should-error (base64-decode-string "+/+/Pw==" t)))
+
+(ert-deftest base64-tests-strict ()
+  (should (equal (base64-decode-string "YW\nJj YQ") "abca"))
+  (should (equal (base64-decode-string "YWJjYQ==" nil t) "abca"))
+  (should-error (base64-decode-string "YW\nJjYQ==" nil t))
+  (should-error (base64-decode-string "YWJjYQ" nil t))
+  (should-error (base64-decode-string "YWJjYR==" nil t))
+  (should-error (base64-decode-string "YW!j")))
+
+(ert-deftest base64-tests-regions ()
+  (with-temp-buffer
+    (insert "xx" (make-string 100 ?a) "yy")
+    ;; Split the region by the gap.
+    (goto-char 50)
+    (insert "a")
+    (goto-char (point-max))
+    (should (= (base64-encode-region 3 104) 137))
+    (should (equal (buffer-substring 1 3) "xx"))
+    (should (equal (buffer-substring 140 142) "yy"))
+    (should (= (point) 142))
+    (goto-char 3)
+    (should (= (base64-decode-region 3 140) 101))
+    (should (equal (buffer-string) (concat "xx" (make-string 101 ?a) "yy")))
+    (should (= (base64url-encode-region 1 3 t) 3))
+    (should (equal (buffer-substring 1 4) "eHg"))
+    (should (= (base64-decode-region 1 4 t) 2))
+    (should (equal (buffer-substring 1 3) "xx"))))
+
+(ert-deftest base64-tests-region-multibyte ()
+  (with-temp-buffer
+    (insert "\u00e9\377")
+    (base64-encode-region (point-min) (point-max))
+    (should (equal (buffer-string) "6f8="))
+    (base64-decode-region (point-min) (point-max))
+    ;; Decoded bytes are inserted as raw 8-bit characters.
+    (should (equal (buffer-string) (string (unibyte-char-to-multibyte ?\351)
+                                           (unibyte-char-to-multibyte ?\377)))))
+  (with-temp-buffer
+    (insert "\u20ac")
+    (should-error (base64-encode-region (point-min) (point-max)))
+    (should (equal (buffer-string) "\u20ac"))
+    (erase-buffer)
+    (insert "not base64!")
+    (should-error (base64-decode-region (point-min) (point-max)))
+    (should (equal (buffer-string) "not base64!"))))
+
+(provide 'base64-tests)
+
+;;; base64-tests.el ends here
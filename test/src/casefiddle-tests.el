@@ -260,4 +260,37 @@
       (should (eq tc (upcase-initials ch))))))
 
 
+;; Special casing applies unless the case table overrides it.
+(ert-deftest casefiddle-tests-case-table-precedence ()
+  (with-temp-buffer
+    (let ((tab (copy-case-table (standard-case-table))))
+      (set-case-table tab)
+      (should (equal (upcase "stra\u00DFe") "STRASSE"))
+      (should (equal (downcase "\u0130") "i\u0307"))
+      (set-case-syntax-pair ?\u1E9E ?\u00DF tab)
+      (should (equal (upcase "stra\u00DFe") "STRA\u1E9EE"))
+      (should (equal (capitalize "\u00DFa") "\u1E9Ea"))
+      ;; A Turkish case table pairs dotless and dotted i with their
+      ;; own capitals.
+      (set-case-syntax-pair ?I ?\u0131 tab)
+      (set-case-syntax-pair ?\u0130 ?i tab)
+      (should (equal (downcase "I\u0130") "\u0131i"))
+      (should (equal (upcase "\u0131i") "I\u0130"))
+      (should (equal (capitalize "istanbul") "\u0130stanbul")))))
+
+(ert-deftest casefiddle-tests-region-special-casing ()
+  (with-temp-buffer
+    (insert "ma\u00DF \u03A3\u039F\u03A3 end")
+    (goto-char (point-max))
+    (upcase-region (point-min) 4)
+    (should (equal (buffer-string) "MASS \u03A3\u039F\u03A3 end"))
+    ;; Point moves with the text after the replaced character.
+    (should (= (point) (point-max)))
+    (downcase-region 6 9)
+    (should (equal (buffer-string) "MASS \u03C3\u03BF\u03C2 end"))
+    (goto-char (point-min))
+    (capitalize-word 1)
+    (should (equal (buffer-string) "Mass \u03C3\u03BF\u03C2 end"))
+    (should (= (point) 5))))
+
 ;;; casefiddle-tests.el ends here
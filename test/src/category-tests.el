@@ -0,0 +1,72 @@
+;;; category-tests.el --- Tests for category.c and category.rs
+
+;; Copyright (C) 2017 Free Software Foundation, Inc.
+
+;; This program is free software; you can redistribute it and/or modify
+;; it under the terms of the GNU General Public License as published by
+;; the Free Software Foundation, either version 3 of the License, or
+;; (at your option) any later version.
+
+;; This program is distributed in the hope that it will be useful,
+;; but WITHOUT ANY WARRANTY; without even the implied warranty of
+;; MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+;; GNU General Public License for more details.
+
+;; You should have received a copy of the GNU General Public License
+;; along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+;;; Code:
+
+(require 'ert)
+
+(ert-deftest category-tests-make-category-table ()
+  (let ((table (make-category-table)))
+    (should (category-table-p table))
+    (should (equal (category-set-mnemonics (aref table ?a)) ""))))
+
+(ert-deftest category-tests-define-category ()
+  (let ((table (make-category-table)))
+    (define-category ?X "Test category" table)
+    (should (equal (category-docstring ?X table) "Test category"))
+    (should-error (define-category ?X "Again" table))
+    (should-error (define-category 200 "Out of range" table)
+                  :type 'wrong-type-argument)))
+
+(ert-deftest category-tests-modify-category-entry ()
+  (let ((table (make-category-table)))
+    (define-category ?X "Test category" table)
+    (should-error (modify-category-entry ?a ?Y table))
+    (modify-category-entry '(?a . ?f) ?X table)
+    (with-temp-buffer
+      (set-category-table table)
+      (should (equal (category-set-mnemonics (char-category-set ?a)) "X"))
+      (should (equal (category-set-mnemonics (char-category-set ?f)) "X"))
+      (should (equal (category-set-mnemonics (char-category-set ?g)) ""))
+      ;; Identical category sets are shared.
+      (should (eq (char-category-set ?a) (char-category-set ?f)))
+      (should (string-match "\\cX+" "zzabcdefg"))
+      (should (equal (match-string 0 "zzabcdefg") "abcdef"))
+      (modify-category-entry ?c ?X table t)
+      (should (equal (category-set-mnemonics (char-category-set ?c)) ""))
+      (should (equal (category-set-mnemonics (char-category-set ?d)) "X")))))
+
+(ert-deftest category-tests-copy-category-table ()
+  (let* ((table (make-category-table))
+         (copy nil))
+    (define-category ?X "Test category" table)
+    (modify-category-entry ?a ?X table)
+    (setq copy (copy-category-table table))
+    (should (category-table-p copy))
+    (define-category ?Y "Only in the copy" copy)
+    (should-not (category-docstring ?Y table))
+    (modify-category-entry ?a ?Y copy)
+    (should (equal (category-set-mnemonics (aref table ?a)) "X"))
+    (should (equal (category-set-mnemonics (aref copy ?a)) "XY"))))
+
+(ert-deftest category-tests-category-set-mnemonics ()
+  (should (equal (category-set-mnemonics (make-category-set "cba")) "abc"))
+  (should-error (category-set-mnemonics (make-bool-vector 10 nil))
+                :type 'wrong-type-argument))
+
+(provide 'category-tests)
+;;; category-tests.el ends here
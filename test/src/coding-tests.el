@@ -292,6 +292,148 @@
     (coding-tests-remove-files)))
 
 
+;;; Check the native decoders and encoders.
+
+(ert-deftest coding-tests-native-utf-8 ()
+  (should (equal (decode-coding-string "a\303\251\342\202\254" 'utf-8)
+                 "a\u00e9\u20ac"))
+  ;; Invalid bytes are preserved as raw bytes.
+  (let ((decoded (decode-coding-string "a\300\257\355\240\200" 'utf-8)))
+    (should (equal (append decoded nil)
+                   '(?a #x3fffc0 #x3fffaf #x3fffed #x3fffa0 #x3fff80)))
+    (should (equal (encode-coding-string decoded 'utf-8)
+                   "a\300\257\355\240\200")))
+  (should (equal (decode-coding-string "\357\273\277x" 'utf-8-with-signature)
+                 "x"))
+  (should (equal (decode-coding-string "\357\273\277x" 'utf-8) "\ufeffx"))
+  (should (equal (encode-coding-string "x" 'utf-8-with-signature)
+                 "\357\273\277x")))
+
+(ert-deftest coding-tests-native-utf-16 ()
+  (should (equal (decode-coding-string "\0a\330\075\336\000" 'utf-16be)
+                 "a\U0001f600"))
+  (should (equal (decode-coding-string "\377\376a\0" 'utf-16le-with-signature)
+                 "a"))
+  ;; Unpaired surrogates are decoded like the generic decoder does.
+  (should (equal (decode-coding-string "\330\075\0a" 'utf-16be) "\330=a"))
+  (should (equal (decode-coding-string "\334\000\0a" 'utf-16be) "\udc00a"))
+  (should (equal (decode-coding-string "\0a\330\075" 'utf-16be) "a"))
+  (should (equal (encode-coding-string "a\U0001f600" 'utf-16le)
+                 "a\0\075\330\000\336"))
+  (should (equal (encode-coding-string "a" 'utf-16be-with-signature)
+                 "\376\377\0a")))
+
+(ert-deftest coding-tests-native-latin-1 ()
+  (should (equal (decode-coding-string "caf\351" 'latin-1) "caf\u00e9"))
+  (should (equal (encode-coding-string "caf\u00e9" 'latin-1) "caf\351"))
+  ;; Unencodable characters are still handled by the generic encoder.
+  (should (= (length (encode-coding-string "\u20ac" 'latin-1)) 1))
+  (should (equal (decode-coding-string "caf\351" 'raw-text) "caf\351"))
+  (should-not (multibyte-string-p (decode-coding-string "caf\351" 'raw-text))))
+
+(ert-deftest coding-tests-native-eol ()
+  (should (equal (decode-coding-string "a\r\nb\r\n" 'utf-8) "a\nb\n"))
+  (should (eq last-coding-system-used 'utf-8-dos))
+  (should (equal (decode-coding-string "a\rb" 'utf-8) "a\nb"))
+  (should (eq last-coding-system-used 'utf-8-mac))
+  (should (equal (decode-coding-string "a\r\nb\n" 'utf-8) "a\r\nb\n"))
+  (should (eq last-coding-system-used 'utf-8-unix))
+  (should (equal (decode-coding-string "a\r\nb" 'utf-8-unix) "a\r\nb"))
+  (should (equal (encode-coding-string "a\nb\u00e9\n" 'utf-8-dos)
+                 "a\r\nb\303\251\r\n"))
+  (should (equal (encode-coding-string "a\n" 'utf-16be-mac) "\0a\0\r")))
+
+(ert-deftest coding-tests-native-insert-file-contents ()
+  (let ((file (make-temp-file "coding-tests")))
+    (unwind-protect
+        (progn
+          (let ((coding-system-for-write 'no-conversion))
+            (write-region "caf\303\251\r\n\377\r\n" nil file))
+          (with-temp-buffer
+            (let ((coding-system-for-read 'utf-8))
+              (insert-file-contents file))
+            (should (equal (buffer-string) "caf\u00e9\n\377\n"))
+            (should (eq last-coding-system-used 'utf-8-dos)))
+          (with-temp-buffer
+            (let ((coding-system-for-read 'iso-latin-1-unix))
+              (insert-file-contents file))
+            (should (equal (buffer-string) "caf\u00c3\u00a9\r\n\u00ff\r\n"))))
+      (delete-file file))))
+
+;;; Check the statistical coding system detection.
+
+(defvar coding-tests-chardet-directory
+  (expand-file-name "data/chardet" (getenv "EMACS_TEST_DIRECTORY"))
+  "Directory of sample files named LANGUAGE.CODING-SYSTEM.txt.")
+
+(defun coding-tests-chardet-files ()
+  (directory-files coding-tests-chardet-directory t "\\.txt\\'"))
+
+(defun coding-tests-chardet-expected (file)
+  (intern (file-name-extension (file-name-sans-extension file))))
+
+(ert-deftest coding-tests-detect-confidence ()
+  (dolist (file (coding-tests-chardet-files))
+    (let* ((text (with-temp-buffer
+                   (set-buffer-multibyte nil)
+                   (insert-file-contents-literally file)
+                   (buffer-string)))
+           (candidates (detect-coding-string text :confidence)))
+      (should (eq (coding-system-base (caar candidates))
+                  (coding-system-base (coding-tests-chardet-expected file))))
+      (should (>= (cdar candidates) 0.95))
+      ;; The text as read into a multibyte buffer gives the same result.
+      (with-temp-buffer
+        (let ((coding-system-for-read 'raw-text-unix))
+          (insert-file-contents file))
+        (should (equal (detect-coding-region (point-min) (point-max)
+                                             :confidence)
+                       candidates)))))
+  (should (equal (detect-coding-string "ASCII" :confidence)
+                 '((undecided . 1.0))))
+  (should (eq (caar (detect-coding-string "caf\351" :confidence))
+              'windows-1252)))
+
+(defun coding-tests-chardet-insert (file threshold)
+  "Insert FILE with `coding-detection-confidence-threshold' set to THRESHOLD.
+Return the coding system used and whether the text reads back as
+the same bytes."
+  (with-temp-buffer
+    (let ((coding-system-for-read 'undecided)
+          (coding-detection-confidence-threshold threshold))
+      (insert-file-contents file))
+    (list last-coding-system-used
+          (equal (encode-coding-string (buffer-string)
+                                       last-coding-system-used)
+                 (with-temp-buffer
+                   (set-buffer-multibyte nil)
+                   (insert-file-contents-literally file)
+                   (buffer-string))))))
+
+(ert-deftest coding-tests-detect-confidence-insert-file-contents ()
+  (dolist (file (coding-tests-chardet-files))
+    (let ((by-priority (car (coding-tests-chardet-insert file nil)))
+          (detected (coding-tests-chardet-insert file 0.95)))
+      (if (memq (coding-system-base by-priority) '(no-conversion raw-text))
+          ;; Only text which the priorities can't decode is guessed
+          ;; statistically.
+          (progn
+            (should (eq (coding-system-base (car detected))
+                        (coding-system-base
+                         (coding-tests-chardet-expected file))))
+            (should (cadr detected)))
+        (should (eq (car detected) by-priority)))))
+  ;; Without the statistical detection, UTF-16 text without a BOM
+  ;; looks like binary data.
+  (let ((file (expand-file-name "english.utf-16le.txt"
+                                coding-tests-chardet-directory)))
+    (should (eq (coding-system-base
+                 (car (coding-tests-chardet-insert file nil)))
+                'no-conversion))
+    (should (eq (coding-system-base
+                 (car (coding-tests-chardet-insert file 0.95)))
+                'utf-16le))))
+
 ;;; The following is for benchmark testing of the new optimized
 ;;; decoder, not for regression testing.
 
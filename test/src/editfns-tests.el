@@ -208,6 +208,148 @@
                  '(error "Invalid format operation %$")))
   (should (equal (format "%1$c %1$s" ?±) "± 177")))
 
+(ert-deftest format-integer-conversions ()
+  (should (equal (format "%d %i %o %x %X" 42 -42 8 255 255) "42 -42 10 ff FF"))
+  (should (equal (format "%+d|% d|%+ d|%05d|%-5d|%.3d" 7 7 7 -7 7 7)
+                 "+7| 7|+7|-0007|7    |007"))
+  (should (equal (format "%.0d|%.0x|%5.0d|" 0 0 0) "||     |"))
+  (should (equal (format "%#o %#o %#x %#X %#x" 8 0 255 255 0)
+                 "010 0 0xff 0XFF 0"))
+  ;; The 0 flag pads after the sign, and is ignored with a precision.
+  (should (equal (format "%08.3d|%08d" -5 -5) "    -005|-0000005"))
+  ;; Floats are truncated toward zero.
+  (should (equal (format "%d %d %x" 2.9 -2.9 255.5) "2 -2 ff"))
+  (should (equal (format "%c%c%3c" ?a ?é ?b) "aé  b")))
+
+(ert-deftest format-float-conversions ()
+  (should (equal (format "%f %e %g" 3.5 3.5 3.5) "3.500000 3.500000e+00 3.5"))
+  (should (equal (format "%.2f %.3e %.3g" 3.14159 12345.678 0.00012345)
+                 "3.14 1.235e+04 0.000123"))
+  (should (equal (format "%g %g %g %g" 100000.0 1e6 0.0001 1e-5)
+                 "100000 1e+06 0.0001 1e-05"))
+  ;; Exact ties round to even, and other numbers by their exact value.
+  (should (equal (format "%.0f %.0f %.0f %.2f" 0.5 1.5 2.5 0.125)
+                 "0 2 2 0.12"))
+  (should (equal (format "%.20f" 0.1) "0.10000000000000000555"))
+  (should (equal (format "%.3e" 5e-324) "4.941e-324"))
+  (should (equal (length (format "%.0f" 1e300)) 301))
+  (should (equal (format "%#.0f %#.0e %#.3g %#g" 2.0 3.0 1.0 0.5)
+                 "2. 3.e+00 1.00 0.500000"))
+  (should (equal (format "%010.2f|%-10.2f|%+.1f|% .1f" -1.5 1.5 1.5 1.5)
+                 "-000001.50|1.50      |+1.5| 1.5"))
+  (should (equal (format "%f %e %5g %+f" 1.0e+INF -1.0e+INF 1.0e+INF 0.0e+NaN)
+                 "inf -inf   inf +nan"))
+  (should (equal (format "%f %.1e" -0.0 -0.0) "-0.000000 -0.0e+00"))
+  (should (equal (format "%.2f %e" 5 -12345) "5.00 -1.234500e+04"))
+  ;; A precision too large for the C library still works.
+  (should (equal (length (format "%.20000f" 0.5)) 20002))
+  (should (equal (format "%.20000g" 1.5) "1.5")))
+
+(ert-deftest format-string-conversions ()
+  (should (equal (format "%s|%S|%s|%s" "a" "a" 'sym 1.5) "a|\"a\"|sym|1.5"))
+  (should (equal (format "%5s|%-5s|%.2s|%.0s|" "abc" "abc" "abc" "abc")
+                 "  abc|abc  |ab||"))
+  (should (equal (format "%%|%5%|%s" 1) "%|%|1"))
+  ;; Widths and precisions count columns, not characters.
+  (should (equal (format "%5s|%.3s|" "日本" "日本") " 日本|日|"))
+  (should (multibyte-string-p (format "%c" ?é)))
+  (should (multibyte-string-p (format "%s" "é")))
+  (should-not (multibyte-string-p (format "%s %d" "abc" 1)))
+  ;; Bytes of unibyte arguments become raw bytes in multibyte results.
+  (should (equal (format "é%s" "\351") (string ?é #x3fffe9))))
+
+(ert-deftest format-errors ()
+  (should (equal (should-error (format "%d"))
+                 '(error "Not enough arguments for format string")))
+  (should (equal (should-error (format "%d %" 1))
+                 '(error "Format string ends in middle of format specifier")))
+  (should (equal (should-error (format "%y" 1))
+                 '(error "Invalid format operation %y")))
+  (should (equal (should-error (format "%d" "1"))
+                 '(error "Format specifier doesn't match argument type")))
+  ;; Errors are signaled in the order of the format string.
+  (should (equal (should-error (format "%d %" "1"))
+                 '(error "Format specifier doesn't match argument type")))
+  (should (equal (should-error (format "%099999999999999999999d" 1))
+                 '(error "Maximum string size exceeded"))))
+
+(ert-deftest format-message-quotes ()
+  (let ((text-quoting-style 'curve))
+    (should (equal (format-message "`%s' isn't" "x") "‘x’ isn’t"))
+    (should (equal (format-message "%s" "`x'") "`x'"))
+    (should (equal (format "`%s'" "x") "`x'")))
+  (let ((text-quoting-style 'straight))
+    (should (equal (format-message "`%s'" "x") "'x'")))
+  (let ((text-quoting-style 'grave))
+    (should (equal (format-message "`%s'" "x") "`x'")))
+  (let ((text-quoting-style 'curve))
+    (should (ert-equal-including-properties
+             (format-message (propertize "`%s'" 'face 'bold) "x")
+             #("‘x’" 0 3 (face bold))))))
+
+(ert-deftest format-properties-of-sequences ()
+  ;; A string padded on the left gets the property of its %-sequence
+  ;; only on the first column of the padding.
+  (should (ert-equal-including-properties
+           (format (propertize "%5s|" 'face 'bold) "x")
+           #("    x|" 0 1 (face bold))))
+  (should (ert-equal-including-properties
+           (format (propertize "%-5s|" 'face 'bold) "x")
+           #("x    |" 0 5 (face bold))))
+  (should (ert-equal-including-properties
+           (format (concat (propertize "%%" 'p 1) (propertize "%s" 'q 2)) "ab")
+           #("%ab" 0 1 (p 1) 1 3 (q 2))))
+  (should (ert-equal-including-properties
+           (format (concat "%s" (propertize "-" 'p 1) "%s") "ab" "cd")
+           #("ab-cd" 2 3 (p 1))))
+  (should (ert-equal-including-properties
+           (format "%2$s%1$s" (propertize "a" 'p 1) (propertize "b" 'q 2))
+           #("ba" 0 1 (q 2) 1 2 (p 1)))))
+
+(defvar editfns-tests-data-directory
+  (expand-file-name "data/editfns" (getenv "EMACS_TEST_DIRECTORY"))
+  "Directory of the data files for these tests.")
+
+(defun editfns-tests--multibyte (string)
+  "Return a multibyte copy of STRING with the same text properties."
+  (let ((copy (string-to-multibyte string))
+        (pos 0))
+    (while pos
+      (let ((next (next-property-change pos string)))
+        (set-text-properties pos (or next (length string))
+                             (text-properties-at pos string) copy)
+        (setq pos next)))
+    copy))
+
+(ert-deftest format-baseline ()
+  "Compare `format' and `format-message' with their C implementation.
+The results in format-baseline.eld were captured from it."
+  (with-temp-buffer
+    (insert-file-contents
+     (expand-file-name "format-baseline.eld" editfns-tests-data-directory))
+    (let (case)
+      (while (setq case (condition-case nil
+                            (read (current-buffer))
+                          (end-of-file nil)))
+        (pcase-let* ((`(,function ,style ,args ,expected ,multibyte) case)
+                     (text-quoting-style style)
+                     (args (mapcar (lambda (arg)
+                                     (if (eq (car-safe arg) 'multibyte)
+                                         (editfns-tests--multibyte (cadr arg))
+                                       arg))
+                                   args)))
+          (ert-info ((prin1-to-string case) :prefix "Case: ")
+            (if (eq (car-safe expected) 'error)
+                (should (equal (should-error (apply function args))
+                               expected))
+              (let ((result (apply function args)))
+                (should (ert-equal-including-properties
+                         result
+                         (if multibyte
+                             (editfns-tests--multibyte expected)
+                           expected)))
+                (should (eq (multibyte-string-p result) multibyte))))))))))
+
 (ert-deftest replace-buffer-contents-1 ()
   (with-temp-buffer
     (insert #("source" 2 4 (prop 7)))
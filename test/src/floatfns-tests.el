@@ -34,4 +34,30 @@
   (should-error (ftruncate 0) :type 'wrong-type-argument)
   (should-error (fround 0) :type 'wrong-type-argument))
 
+;; Printing and reading a float must give back the same float, so
+;; compare printed representations, which also tell zeros and NaNs
+;; apart.
+(ert-deftest float-print-read-round-trip ()
+  (dolist (text '("0.0" "-0.0" "1.0" "0.1" "0.30000000000000004" "100.0"
+                  "100000000000000.0" "1e+15" "0.0001" "1e-05" "5e-324"
+                  "2.225073858507201e-308" "1.7976931348623157e+308"
+                  "1.0e+INF" "-1.0e+INF" "0.0e+NaN" "-0.0e+NaN" "3.0e+NaN"
+                  "-2251799813685249.0e+NaN"))
+    (should (equal (prin1-to-string (read text)) text))
+    (should (equal (number-to-string (string-to-number text)) text))
+    (should (equal (format "%S" (read text)) text))))
+
+(ert-deftest float-read-correctly-rounded ()
+  (should (= (read "9007199254740993.0") 9007199254740992.0))
+  (should (= (read "1.00000000000000011102230246251565404236316680908203125")
+             1.0))
+  (should (> (read "1.000000000000000111022302462515654042363166809082031251")
+             1.0))
+  (should (= (string-to-number "2.2250738585072011e-308") 2.225073858507201e-308))
+  (should (= (string-to-number "2.4703282292062327e-324") 0.0))
+  (should (= (string-to-number "2.4703282292062328e-324") 5e-324))
+  (should (= (string-to-number "1.7976931348623159e308") 1.0e+INF))
+  (should (= (string-to-number "-1e-400") 0.0))
+  (should (equal (number-to-string (string-to-number "-1e-400")) "-0.0")))
+
 (provide 'floatfns-tests)
@@ -100,35 +100,64 @@
   (should (= (compare-strings "んにちはｺﾝﾆﾁﾊこ" nil nil "こんにちはｺﾝﾆﾁﾊ" nil nil) 1))
   (should (= (compare-strings "こんにちはｺﾝﾆﾁﾊ" nil nil "んにちはｺﾝﾆﾁﾊこ" nil nil) -1)))
 
-(defun fns-tests--collate-enabled-p ()
-  "Check whether collation functions are enabled."
-  (and
-   ;; When there is no collation library, collation functions fall back
-   ;; to their lexicographic counterparts.  We don't need to test then.
-   (not (ignore-errors (string-collate-equalp "" "" t)))
-   ;; We use a locale, which might not be installed.  Check it.
-   (ignore-errors
-     (string-collate-equalp
-      "" "" (if (eq system-type 'windows-nt) "enu_USA" "en_US.UTF-8")))))
-
 (ert-deftest fns-tests-collate-strings ()
-  (skip-unless (fns-tests--collate-enabled-p))
-
   (should (string-collate-equalp "xyzzy" "xyzzy"))
   (should-not (string-collate-equalp "xyzzy" "XYZZY"))
+  (should (string-collate-equalp 'xyzzy "xyzzy"))
 
   ;; In POSIX or C locales, collation order is lexicographic.
   (should (string-collate-lessp "XYZZY" "xyzzy" "POSIX"))
-  ;; In a language specific locale, collation order is different.
-  (should (string-collate-lessp
-	   "xyzzy" "XYZZY"
-	   (if (eq system-type 'windows-nt) "enu_USA" "en_US.UTF-8")))
+  (should (string-collate-lessp "XYZZY" "xyzzy" "C.UTF-8"))
+  ;; Otherwise, lowercase letters come first.
+  (should (string-collate-lessp "xyzzy" "XYZZY"))
+  (should (string-collate-lessp "xyzzy" "XYZZY" "en_US.UTF-8"))
 
   ;; Ignore case.
   (should (string-collate-equalp "xyzzy" "XYZZY" nil t))
+  (should (string-collate-equalp "xyzzy" "XYZZY" "POSIX" t))
+
+  ;; Characters with the same meaning are equal.
+  (should (string-collate-equalp (string ?\uFF40) (string ?\u1FEF)))
+  (should (string-collate-equalp "\u00E9" "e\u0301"))
 
   ;; Locale must be valid.
-  (should-error (string-collate-equalp "xyzzy" "xyzzy" "en_DE.UTF-8")))
+  (should-error (string-collate-equalp "xyzzy" "xyzzy" "not a locale"))
+  (should-error (string-collate-equalp "xyzzy" "xyzzy" 'en_US)))
+
+(ert-deftest fns-tests-collate-tailorings ()
+  ;; Swedish sorts "ä" after "z", German with the default order.
+  (should (string-collate-lessp "ä" "z"))
+  (should (string-collate-lessp "ä" "z" "de_DE.UTF-8"))
+  (should (string-collate-lessp "z" "ä" "sv"))
+  (should (string-collate-lessp "z" "ä" "sv_SE.UTF-8"))
+  ;; The German phonebook order sorts "ä" like "ae".
+  (should (string-collate-lessp "ä" "ad"))
+  (should (string-collate-lessp "ad" "ä" "de_phone"))
+  ;; "ch" is a letter of its own in Czech.
+  (should
+   (equal (sort '("i" "ch" "h") (lambda (a b) (string-collate-lessp a b "cs")))
+          '("h" "ch" "i")))
+  ;; Danish sorts uppercase letters first.
+  (should (string-collate-lessp "A" "a" "da"))
+  (should (string-collate-lessp "a" "A" "en")))
+
+(ert-deftest fns-tests-collate-ignore-accents ()
+  (should-not (string-collate-equalp "résumé" "resume"))
+  (should (string-collate-equalp "résumé" "resume" nil nil t))
+  (should-not (string-collate-equalp "Résumé" "resume" nil nil t))
+  (should (string-collate-equalp "Résumé" "resume" nil t t)))
+
+(ert-deftest fns-tests-collate-numeric ()
+  (let ((files '("file10.txt" "file9.txt" "file1.txt" "file010.txt")))
+    (should
+     (equal (sort (copy-sequence files) #'string-collate-lessp)
+            '("file010.txt" "file10.txt" "file1.txt" "file9.txt")))
+    (should
+     (equal (sort (copy-sequence files)
+                  (lambda (a b) (string-collate-lessp a b nil nil nil t)))
+            '("file1.txt" "file9.txt" "file10.txt" "file010.txt"))))
+  (should (string-collate-lessp "2" "10" nil nil nil t))
+  (should (string-collate-equalp "10" "10" nil nil nil t)))
 
 ;; There must be a check for valid codepoints.  (Check not implemented yet)
 ;  (should-error
@@ -154,10 +183,6 @@
 	    (9 . "aaa") (9 . "zzz") (9 . "ppp") (9 . "fff")])))
 
 (ert-deftest fns-tests-collate-sort ()
-  ;; See https://lists.gnu.org/archive/html/emacs-devel/2015-10/msg02505.html.
-  :expected-result (if (eq system-type 'cygwin) :failed :passed)
-  (skip-unless (fns-tests--collate-enabled-p))
-
   ;; Punctuation and whitespace characters are relevant for POSIX.
   (should
    (equal
@@ -169,10 +194,11 @@
   (should
    (equal
     (sort '("11" "12" "1 1" "1 2" "1.1" "1.2")
-	  (lambda (a b)
-	    (let ((w32-collate-ignore-punctuation t))
-	      (string-collate-lessp
-	       a b (if (eq system-type 'windows-nt) "enu_USA" "en_US.UTF-8")))))
+	  (lambda (a b) (string-collate-lessp a b "en_US.UTF-8")))
+    '("11" "1 1" "1.1" "12" "1 2" "1.2")))
+  (should
+   (equal
+    (sort '("11" "12" "1 1" "1 2" "1.1" "1.2") #'string-collate-lessp)
     '("11" "1 1" "1.1" "12" "1 2" "1.2")))
 
   ;; Diacritics are different letters for POSIX, they sort lexicographical.
@@ -185,10 +211,7 @@
   (should
    (equal
     (sort '("Ævar" "Agustín" "Adrian" "Eli")
-	  (lambda (a b)
-	    (let ((w32-collate-ignore-punctuation t))
-	      (string-collate-lessp
-	       a b (if (eq system-type 'windows-nt) "enu_USA" "en_US.UTF-8")))))
+	  (lambda (a b) (string-collate-lessp a b "en_US.UTF-8")))
     '("Adrian" "Ævar" "Agustín" "Eli"))))
 
 (ert-deftest fns-tests-string-version-lessp ()
@@ -206,7 +229,15 @@
   (should (string-version-lessp "foo.png" "foo2"))
   (should (string-version-lessp "foo1.25.5.png" "foo1.125.5"))
   (should (string-version-lessp "2" "1245"))
-  (should (not (string-version-lessp "1245" "2"))))
+  (should (not (string-version-lessp "1245" "2")))
+  (should (string-version-lessp 'emacs-25.9 "emacs-25.10"))
+  (should (string-version-lessp "v9" "v010"))
+  (should (string-version-lessp "v10" "v010"))
+  (should (string-version-lessp "foo\u0663" "foo12"))
+  (should (string-version-lessp "r\u00e9sum\u00e9" "resumes"))
+  (should (not (string-version-lessp "foo1" "foo1")))
+  (should (equal (sort '("1.0" "1.0-rc1" "1.0.1" "0.9") #'string-version-lessp)
+                 '("0.9" "1.0" "1.0-rc1" "1.0.1"))))
 
 (ert-deftest fns-tests-func-arity ()
   (should (equal (func-arity 'car) '(1 . 1)))
@@ -235,6 +266,112 @@
                    (buffer-hash))
                  (sha1 "foo"))))
 
+(ert-deftest fns-tests-hash-buffer-algorithm ()
+  (with-temp-buffer
+    (insert "foo")
+    (goto-char 2)
+    (insert " ")
+    (backward-delete-char 1)
+    (should (equal (buffer-hash nil 'sha256) (secure-hash 'sha256 "foo")))
+    (should (equal (buffer-hash (current-buffer) 'md5) (md5 "foo")))
+    (should-error (buffer-hash nil 'sha4))))
+
+(ert-deftest fns-tests-hash-buffer-coding ()
+  ;; The text is longer than the chunks in which buffer text is
+  ;; encoded, and the gap is in the middle of it.
+  (let ((text (concat (make-string 70000 ?\u00e9) "\n"
+                      (make-string 70000 ?\u3042) "\n")))
+    (with-temp-buffer
+      (insert text)
+      (goto-char 1000)
+      (insert "x")
+      (backward-delete-char 1)
+      (dolist (coding '(utf-8 utf-8-dos utf-8-with-signature utf-16 utf-16le
+                        latin-1 iso-2022-jp raw-text))
+        (should (equal (md5 (current-buffer) nil nil coding)
+                       (md5 (encode-coding-string text coding)))))
+      (should (equal (md5 (current-buffer) 5 70010 'utf-16)
+                     (md5 (encode-coding-string (substring text 4 70009) 'utf-16))))
+      (let ((coding-system-for-write 'utf-8-unix))
+        (should (equal (secure-hash 'sha256 (current-buffer))
+                       (secure-hash 'sha256 (encode-coding-string text 'utf-8))))))))
+
+(ert-deftest fns-tests-secure-hash ()
+  (should (equal (secure-hash-algorithms)
+                 '(md5 sha1 sha224 sha256 sha384 sha512
+                       sha3-224 sha3-256 sha3-384 sha3-512 blake2b blake2s)))
+  (should (equal (secure-hash 'sha3-256 "abc")
+                 "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532"))
+  (should (equal (secure-hash 'blake2s "abc")
+                 "508c5e8c327c14e2e1a72ba34eeb452f37458b209ed63a294d999b4c86675982"))
+  (should (equal (substring (secure-hash 'blake2b "abc") 0 16) "ba80a53f981c4d0d"))
+  (should (= (length (secure-hash 'sha3-512 "abc" nil nil t)) 64))
+  (should-error (secure-hash 'sha4 "abc")))
+
+(ert-deftest fns-tests-secure-hmac ()
+  (let ((message "The quick brown fox jumps over the lazy dog"))
+    (should (equal (secure-hmac 'md5 "key" message)
+                   "80070713463e7749b90c2dc24911e275"))
+    (should (equal (secure-hmac 'sha256 "key" message)
+                   "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"))
+    (should (equal (secure-hmac 'sha3-256 "key" message)
+                   "8c6e0683409427f8931711b10ca92a506eb1fafa48fadd66d76126f47ac2c333"))
+    (should (= (length (secure-hmac 'sha256 "key" message nil nil t)) 32))
+    (with-temp-buffer
+      (insert "xx" message)
+      (should (equal (secure-hmac 'sha1 "key" (current-buffer) 3)
+                     "de7c9b85b8b78aa6bc8a7a36f70a90701c9db4d9"))))
+  ;; Keys longer than a block are hashed first.
+  (should (equal (substring (secure-hmac 'sha512 (make-string 200 ?k) "msg") 0 16)
+                 "b5245971beb52a5a")))
+
+(defun fns-tests--file-hash-timers ()
+  "Return the timers which poll background `file-hash' jobs."
+  (cl-remove-if-not (lambda (timer) (eq (timer--function timer) 'file-hash--poll))
+                    timer-list))
+
+(ert-deftest fns-tests-file-hash ()
+  (let ((file (make-temp-file "fns-tests"))
+        (contents (concat (make-string 100000 ?a) "\377")))
+    (unwind-protect
+        (progn
+          (let ((coding-system-for-write 'no-conversion))
+            (write-region contents nil file nil 'silent))
+          (should (equal (file-hash 'sha256 file)
+                         (secure-hash 'sha256 (string-as-unibyte contents))))
+          (should (equal (file-hash 'md5 file t)
+                         (secure-hash 'md5 (string-as-unibyte contents) nil nil t)))
+          (let (result)
+            (should (natnump (file-hash 'sha1 file nil
+                                        (lambda (&rest args) (setq result args)))))
+            (with-timeout (10 (ert-fail "file-hash callback not called"))
+              (while (not result)
+                (accept-process-output nil 0.05)))
+            (should (equal result
+                           (list (secure-hash 'sha1 (string-as-unibyte contents))
+                                 nil))))
+          ;; A cancelled job never calls its callback.
+          (let* ((called nil)
+                 (job (file-hash 'sha1 file nil (lambda (&rest _) (setq called t)))))
+            (should (eq (file-hash-cancel job) t))
+            (should-not (file-hash-cancel job))
+            (accept-process-output nil 0.2)
+            (should-not called)
+            (should-not (fns-tests--file-hash-timers)))
+          ;; The job stops polling even if its callback signals an error.
+          (let ((debug-on-error nil)
+                called)
+            (file-hash 'sha1 file nil (lambda (&rest _)
+                                        (setq called t)
+                                        (error "Callback failed")))
+            (with-timeout (10 (ert-fail "file-hash callback not called"))
+              (while (not called)
+                (accept-process-output nil 0.05)))
+            (should-not (fns-tests--file-hash-timers))))
+      (delete-file file))
+    (should-error (file-hash 'sha1 file) :type 'file-missing)
+    (should-error (file-hash 'sha4 file))))
+
 (ert-deftest fns-tests-mapcan ()
   (should-error (mapcan))
   (should-error (mapcan #'identity))
@@ -575,4 +712,86 @@
                                :type 'wrong-type-argument)
                  '(wrong-type-argument plistp (:foo 1 . :bar)))))
 
+;; `string-search', `string-replace', `string-split' and `string-join'.
+(ert-deftest fns-tests-string-search ()
+  (should (equal (string-search "bar" "foobarbar") 3))
+  (should (equal (string-search "bar" "foobarbar" 4) 6))
+  (should-not (string-search "bar" "foobarbar" 7))
+  (should-not (string-search "Bar" "foobarbar"))
+  (should (equal (string-search "" "foo") 0))
+  (should (equal (string-search "" "foo" 3) 3))
+  (should-error (string-search "" "foo" 4) :type 'args-out-of-range)
+  (should-error (string-search "" "foo" -1) :type 'args-out-of-range)
+  (should (equal (string-search "ö" "fööö" 2) 2))
+  (should (equal (string-search "b" "äöb") 2))
+  ;; Raw bytes match across unibyte and multibyte strings, but other
+  ;; non-ASCII characters never match the bytes of unibyte strings.
+  (should (equal (string-search "\377" (string-to-multibyte "a\377")) 1))
+  (should (equal (string-search (string-to-multibyte "\377") "a\377") 1))
+  (should-not (string-search "ö" (encode-coding-string "aö" 'latin-1)))
+  (should-not (string-search (encode-coding-string "ö" 'latin-1) "aö"))
+  (let ((data (match-data)))
+    (string-search "b" "abc")
+    (should (equal (match-data) data))))
+
+(ert-deftest fns-tests-string-replace ()
+  (should (equal (string-replace "foo" "bar" "foozot") "barzot"))
+  (should (equal (string-replace "a" "bb" "xaxax") "xbbxbbx"))
+  (should (equal (string-replace "aa" "b" "aaa") "ba"))
+  (should (equal (string-replace "ö" "o" "föö") "foo"))
+  (should (equal (string-replace "x" "ü" "x\377x")
+                 (concat "ü" (string-to-multibyte "\377") "ü")))
+  (let ((string "no match"))
+    (should (eq (string-replace "x" "y" string) string)))
+  (should-error (string-replace "" "x" "abc") :type 'wrong-length-argument)
+  (let ((result (string-replace "x" (propertize "Y" 'face 'bold)
+                                (propertize "axb" 'face 'italic))))
+    (should (equal result "aYb"))
+    (should (equal (get-text-property 0 'face result) 'italic))
+    (should (equal (get-text-property 1 'face result) 'bold))
+    (should (equal (get-text-property 2 'face result) 'italic))))
+
+(ert-deftest fns-tests-string-split ()
+  (should (equal (string-split "a,b,,c" ",") '("a" "b" "" "c")))
+  (should (equal (string-split "a,b,,c" "," t) '("a" "b" "c")))
+  (should (equal (string-split ",a," ",") '("" "a" "")))
+  (should (equal (string-split "a::b" "::") '("a" "b")))
+  (should (equal (string-split "a:::b" "::") '("a" ":b")))
+  (should (equal (string-split "abc" "") '("" "a" "b" "c" "")))
+  (should (equal (string-split "abc" "" t) '("a" "b" "c")))
+  (should (equal (string-split "" "") '("")))
+  (should (equal (string-split "  two words\n" nil) '("two" "words")))
+  (should (equal (string-split "" nil) nil))
+  (should (equal (string-split " a , b ,  , c" "," t t) '("a" "b" "c")))
+  (should (equal (string-split " a , b ,  , c" "," nil t) '("a" "b" "" "c")))
+  (should (equal (string-split "-a-:--b" ":" nil "-") '("a" "b")))
+  (should (equal (string-split "äöüöä" "ö") '("ä" "ü" "ä")))
+  ;; Like `split-string' with a literal separator.
+  (dolist (string '("" "," "a,b" ",,a,,b,," "ä,ö"))
+    (should (equal (string-split string ",")
+                   (split-string string ",")))
+    (should (equal (string-split string "," t " ")
+                   (split-string string "," t " "))))
+  (let ((parts (string-split (propertize "ab,cd" 'face 'bold) ",")))
+    (should (equal parts '("ab" "cd")))
+    (should (equal (get-text-property 1 'face (nth 1 parts)) 'bold))))
+
+(ert-deftest fns-tests-string-join ()
+  (should (equal (string-join '("foo" "bar" "zot") ", ") "foo, bar, zot"))
+  (should (equal (string-join '("foo" "bar")) "foobar"))
+  (should (equal (string-join '("foo") "ö") "foo"))
+  (should-not (multibyte-string-p (string-join '("foo") "ö")))
+  (should (equal (string-join nil ", ") ""))
+  (should (equal (string-join ["foo" "bar"] "-") "foo-bar"))
+  (should (equal (string-join [] "-") ""))
+  (should (equal (string-join '("\377" "ö") "")
+                 (concat (string-to-multibyte "\377") "ö")))
+  (should-error (string-join '("a" 1)) :type 'wrong-type-argument)
+  (let ((result (string-join (list (propertize "a" 'face 'bold) "b")
+                             (propertize "-" 'face 'italic))))
+    (should (equal result "a-b"))
+    (should (equal (get-text-property 0 'face result) 'bold))
+    (should (equal (get-text-property 1 'face result) 'italic))
+    (should-not (get-text-property 2 'face result))))
+
 (provide 'fns-tests)
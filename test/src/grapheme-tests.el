@@ -0,0 +1,119 @@
+;;; grapheme-tests.el --- Tests for grapheme.rs and character widths
+
+;; Copyright (C) 2017 Free Software Foundation, Inc.
+
+;; This program is free software; you can redistribute it and/or modify
+;; it under the terms of the GNU General Public License as published by
+;; the Free Software Foundation, either version 3 of the License, or
+;; (at your option) any later version.
+
+;; This program is distributed in the hope that it will be useful,
+;; but WITHOUT ANY WARRANTY; without even the implied warranty of
+;; MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+;; GNU General Public License for more details.
+
+;; You should have received a copy of the GNU General Public License
+;; along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+;;; Commentary:
+
+;; The conformance tests against GraphemeBreakTest.txt are Rust unit
+;; tests in rust_src/src/grapheme.rs.
+
+;;; Code:
+
+(require 'ert)
+
+(defconst grapheme-tests-family "\U0001F468\u200D\U0001F469\u200D\U0001F467"
+  "An emoji ZWJ sequence.")
+
+(ert-deftest grapheme-tests-string-clusters ()
+  (should (equal (string-grapheme-clusters "") nil))
+  (should (equal (string-grapheme-clusters "abc") '("a" "b" "c")))
+  (should (equal (string-grapheme-clusters "e\u0301x") '("e\u0301" "x")))
+  (should (equal (string-grapheme-clusters "\r\n\n") '("\r\n" "\n")))
+  ;; Regional indicators pair up into flags.
+  (should (equal (string-grapheme-clusters "\U0001F1EB\U0001F1F7\U0001F1E9")
+                 '("\U0001F1EB\U0001F1F7" "\U0001F1E9")))
+  (should (equal (string-grapheme-clusters (concat grapheme-tests-family "!"))
+                 (list grapheme-tests-family "!"))))
+
+(ert-deftest grapheme-tests-movement ()
+  (with-temp-buffer
+    (insert "ae\u0301" grapheme-tests-family "\nb")
+    (goto-char (point-min))
+    (should (= (forward-grapheme 2) 0))
+    (should (= (point) 4))
+    (forward-grapheme)
+    (should (= (point) (+ 4 (length grapheme-tests-family))))
+    (should (= (backward-grapheme 2) 0))
+    (should (= (point) 2))
+    (should (= (forward-grapheme 10) 6))
+    (should (= (point) (point-max)))
+    (should (= (backward-grapheme 10) 5))
+    (should (= (point) (point-min)))))
+
+(ert-deftest grapheme-tests-movement-regional-indicators ()
+  ;; Whether a regional indicator starts a flag depends on how many
+  ;; come before it.
+  (with-temp-buffer
+    (insert "a" (make-string 6 #x1F1EB) "b")
+    (goto-char 4)
+    (forward-grapheme)
+    (should (= (point) 6))
+    (backward-grapheme 2)
+    (should (= (point) 2))
+    (goto-char 5)
+    (forward-grapheme)
+    (should (= (point) 6))
+    (goto-char 5)
+    (backward-grapheme)
+    (should (= (point) 4))))
+
+(ert-deftest grapheme-tests-char-width ()
+  (should (= (char-width ?a) 1))
+  (should (= (char-width ?\u4E2D) 2))
+  (should (= (char-width ?\uFF21) 2))
+  (should (= (char-width ?\u0301) 0))
+  (should (= (char-width ?\U0001F600) 2))
+  (should (= (char-width (unibyte-char-to-multibyte #xC0)) 4))
+  (with-temp-buffer
+    (setq tab-width 4)
+    (should (= (char-width ?\t) 4))))
+
+(ert-deftest grapheme-tests-char-width-table ()
+  ;; `char-width-table' decides the width of every non-ASCII character.
+  (let ((char-width-table (copy-sequence char-width-table)))
+    (set-char-table-range char-width-table ?\u4E2D 1)
+    (set-char-table-range char-width-table ?\u00E9 2)
+    (should (= (char-width ?\u4E2D) 1))
+    (should (= (char-width ?\u00E9) 2))
+    (should (= (string-width "\u4E2D\u00E9") 3))))
+
+(ert-deftest grapheme-tests-char-width-display-table ()
+  ;; Glyph codes with a face count as their character.
+  (with-temp-buffer
+    (setq buffer-display-table (make-display-table))
+    (aset buffer-display-table ?a
+          (vector (logior ?\u4E2D (ash 1 22)) (cons ?b 1)))
+    (should (= (char-width ?a) 3))))
+
+(ert-deftest grapheme-tests-string-width ()
+  (should (= (string-width "abc") 3))
+  (should (= (string-width "e\u0301") 1))
+  (should (= (string-width "\u4E2D\u6587") 4))
+  (should (= (string-width grapheme-tests-family) 2))
+  (should (= (string-width "\U0001F1EB\U0001F1F7") 2))
+  ;; VARIATION SELECTOR-16 requests the emoji presentation.
+  (should (= (string-width "\u2764") 1))
+  (should (= (string-width "\u2764\uFE0F") 2)))
+
+(ert-deftest grapheme-tests-format-width ()
+  ;; `format' measures padding and precision like `string-width'.
+  (should (equal (format "%-3s|" "\u2764\uFE0F") "\u2764\uFE0F |"))
+  (should (equal (format "%.2s" "\u2764\uFE0Fa") "\u2764\uFE0F"))
+  (should (equal (format "%.1s" "e\u0301x") "e\u0301"))
+  (should (equal (format "%4s" "\u4E2D") "  \u4E2D")))
+
+(provide 'grapheme-tests)
+;;; grapheme-tests.el ends here
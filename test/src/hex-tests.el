@@ -0,0 +1,35 @@
+;;; hex-tests.el --- Tests for hex.rs
+
+;; Copyright (C) 2017 Free Software Foundation, Inc.
+
+;; This program is free software; you can redistribute it and/or modify
+;; it under the terms of the GNU General Public License as published by
+;; the Free Software Foundation, either version 3 of the License, or
+;; (at your option) any later version.
+
+;; This program is distributed in the hope that it will be useful,
+;; but WITHOUT ANY WARRANTY; without even the implied warranty of
+;; MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+;; GNU General Public License for more details.
+
+;; You should have received a copy of the GNU General Public License
+;; along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+;;; Code:
+
+(require 'ert)
+
+(ert-deftest hex-tests-strings ()
+  (should (equal (hex-encode-string "") ""))
+  (should (equal (hex-encode-string "\0\177\200\377") "007f80ff"))
+  (should (equal (hex-encode-string "\u00e9") "e9"))
+  (should (equal (hex-decode-string "007F80ff") "\0\177\200\377"))
+  (should-not (multibyte-string-p (hex-decode-string "e9")))
+  (should-error (hex-encode-string "\u20ac"))
+  (should-error (hex-decode-string "007"))
+  (should-error (hex-decode-string "00 7f"))
+  (should-error (hex-decode-string "0g")))
+
+(provide 'hex-tests)
+
+;;; hex-tests.el ends here
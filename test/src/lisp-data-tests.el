@@ -0,0 +1,123 @@
+;;; lisp-data-tests.el --- Tests for serializing Lisp data  -*- lexical-binding: t; -*-
+
+;; Copyright (C) 2017 Free Software Foundation, Inc.
+
+;; This file is part of GNU Emacs.
+
+;; GNU Emacs is free software: you can redistribute it and/or modify
+;; it under the terms of the GNU General Public License as published by
+;; the Free Software Foundation, either version 3 of the License, or
+;; (at your option) any later version.
+
+;; GNU Emacs is distributed in the hope that it will be useful,
+;; but WITHOUT ANY WARRANTY; without even the implied warranty of
+;; MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+;; GNU General Public License for more details.
+
+;; You should have received a copy of the GNU General Public License
+;; along with GNU Emacs.  If not, see <https://www.gnu.org/licenses/>.
+
+;;; Code:
+
+(require 'ert)
+
+(defun lisp-data-tests-round-trip (object)
+  (let ((string (lisp-data-serialize object)))
+    (should-not (multibyte-string-p string))
+    (lisp-data-deserialize string)))
+
+(ert-deftest lisp-data-tests-atoms ()
+  (dolist (object (list nil t 'foo :keyword 0 1 -1 most-positive-fixnum
+                        most-negative-fixnum 1.5 -0.0 1.0e+INF
+                        "" "ascii" "Grüße" (string-to-unibyte "\377\0")
+                        (string ?a (max-char) #x3FFF80)))
+    (let ((copy (lisp-data-tests-round-trip object)))
+      (should (equal copy object))
+      (should (eq (multibyte-string-p copy) (multibyte-string-p object)))))
+  (should (eq (lisp-data-tests-round-trip 'foo) 'foo))
+  (let ((nan (lisp-data-tests-round-trip 0.0e+NaN)))
+    (should (isnan nan))))
+
+(ert-deftest lisp-data-tests-sequences ()
+  (dolist (object (list '(1 2 3) '(1 . 2) '(a (b (c)) . "d")
+                        [] [1 [2 "three"] (4)] (make-bool-vector 0 nil)
+                        (make-bool-vector 13 t) (bool-vector t nil nil t)
+                        (record 'foo 1 "two") (make-list 100000 'x)))
+    (should (equal (lisp-data-tests-round-trip object) object)))
+  (let ((copy (lisp-data-tests-round-trip (record 'foo 1 2))))
+    (should (recordp copy))
+    (should (eq (type-of copy) 'foo))))
+
+(ert-deftest lisp-data-tests-text-properties ()
+  (let* ((string (concat (propertize "abc" 'face 'bold 'help-echo "tip")
+                         "def"
+                         (propertize "Grüße" 'invisible t)))
+         (copy (lisp-data-tests-round-trip string)))
+    (should (equal-including-properties copy string))))
+
+(ert-deftest lisp-data-tests-shared-structure ()
+  (let* ((tail (list 2 3))
+         (string "shared")
+         (copy (lisp-data-tests-round-trip
+                (list (cons 1 tail) tail string (vector string)))))
+    (should (eq (cdr (nth 0 copy)) (nth 1 copy)))
+    (should (eq (nth 2 copy) (aref (nth 3 copy) 0))))
+  (let* ((list (list 1 2 3))
+         (vector (vector 'a nil)))
+    (setcdr (last list) list)
+    (aset vector 1 vector)
+    (let ((copy (lisp-data-tests-round-trip (cons list vector))))
+      (should (eq (nthcdr 3 (car copy)) (car copy)))
+      (should (equal (list (nth 0 (car copy)) (nth 1 (car copy)) (nth 2 (car copy)))
+                     '(1 2 3)))
+      (should (eq (aref (cdr copy) 1) (cdr copy))))))
+
+(ert-deftest lisp-data-tests-uninterned-symbols ()
+  (let* ((symbol (make-symbol "foo"))
+         (copy (lisp-data-tests-round-trip (list symbol symbol 'foo))))
+    (should-not (eq (nth 0 copy) 'foo))
+    (should (equal (symbol-name (nth 0 copy)) "foo"))
+    (should-not (intern-soft (nth 0 copy)))
+    (should (eq (nth 0 copy) (nth 1 copy)))
+    (should (eq (nth 2 copy) 'foo))))
+
+(ert-deftest lisp-data-tests-hash-tables ()
+  (let ((table (make-hash-table :test 'equal :weakness 'key
+                                :rehash-size 3.0)))
+    (puthash "one" 1 table)
+    (puthash '(2) [two] table)
+    (puthash 'self table table)
+    (let ((copy (lisp-data-tests-round-trip table)))
+      (should (eq (hash-table-test copy) 'equal))
+      (should (eq (hash-table-weakness copy) 'key))
+      (should (= (hash-table-rehash-size copy) 3.0))
+      (should (= (hash-table-count copy) 3))
+      (should (= (gethash "one" copy) 1))
+      (should (equal (gethash (list 2) copy) [two]))
+      (should (eq (gethash 'self copy) copy)))))
+
+(ert-deftest lisp-data-tests-errors ()
+  (with-temp-buffer
+    (should-error (lisp-data-serialize (list 1 (current-buffer))))
+    (should-error (lisp-data-serialize (point-marker))))
+  (let ((string (lisp-data-serialize '(1 2 3))))
+    (should-error (lisp-data-deserialize ""))
+    (should-error (lisp-data-deserialize "(1 2 3)"))
+    ;; A different version of the format.
+    (let ((other (copy-sequence string)))
+      (aset other 4 (1+ (aref other 4)))
+      (should-error (lisp-data-deserialize other)))
+    ;; Truncated and trailing data.
+    (should-error (lisp-data-deserialize (substring string 0 -1)))
+    (should-error (lisp-data-deserialize (concat string "x")))
+    ;; Corrupting the data signals errors, not crashes.
+    (dotimes (i (length string))
+      (dolist (byte '(0 #x7f #xff))
+        (let ((corrupt (copy-sequence string)))
+          (aset corrupt i byte)
+          (condition-case nil
+              (lisp-data-deserialize corrupt)
+            (error nil)))))))
+
+(provide 'lisp-data-tests)
+;;; lisp-data-tests.el ends here
@@ -399,5 +399,43 @@
   (minibuf-tests--test-completion-regexp
    #'minibuf-tests--strings-to-symbol-hashtable))
 
+
+(ert-deftest completion-fuzzy-score ()
+  (should (equal (completion-fuzzy-score "" "foo") '(0)))
+  (should-not (completion-fuzzy-score "oof" "foo"))
+  (should (equal (cdr (completion-fuzzy-score "fb" "foo-bar")) '(0 4)))
+  (should (equal (cdr (completion-fuzzy-score "bar" "b_a_r_bar")) '(6 7 8)))
+  (should (> (car (completion-fuzzy-score "fb" "foo-bar"))
+             (car (completion-fuzzy-score "fb" "foobar"))))
+  (should (> (car (completion-fuzzy-score "fb" "fooBar"))
+             (car (completion-fuzzy-score "fb" "foobar"))))
+  ;; Smart case.
+  (should (equal (cdr (completion-fuzzy-score "fb" "FooBar")) '(0 3)))
+  (should-not (completion-fuzzy-score "FB" "foobar"))
+  (let ((completion-ignore-case t))
+    (should (completion-fuzzy-score "FB" "foobar")))
+  (should (equal (cdr (completion-fuzzy-score 'fb 'find-buffer)) '(0 5))))
+
+(ert-deftest completion-fuzzy-filter ()
+  (let ((collection '("diff-mode" "find-file" "buffer" "fundamental-mode")))
+    (should (equal (completion-fuzzy-filter "ff" collection)
+                   '("find-file" "buffer" "diff-mode")))
+    (should (equal (completion-fuzzy-filter "ff" collection 1) '("find-file")))
+    (should (equal (completion-fuzzy-filter "" collection) collection))
+    (should-not (completion-fuzzy-filter "xyz" collection))
+    (let ((match (car (completion-fuzzy-filter "ff" collection))))
+      (should (eq (get-text-property 0 'face match)
+                  'completions-common-part))
+      (should-not (get-text-property 1 'face match))
+      (should (eq (get-text-property 5 'face match)
+                  'completions-common-part))
+      ;; The collection is left alone.
+      (should-not (text-properties-at 0 (nth 1 collection)))))
+  (should (equal (completion-fuzzy-filter
+                  "ab" (minibuf-tests--strings-to-obarray '("xaxb" "abc")))
+                 '("abc" "xaxb")))
+  (should (equal (completion-fuzzy-filter "ab" '(("xaxb" . 1) ("abc" . 2)))
+                 '("abc" "xaxb"))))
+
 
 ;;; minibuf-tests.el ends here
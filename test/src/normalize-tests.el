@@ -0,0 +1,64 @@
+;;; normalize-tests.el --- Tests for normalize.rs
+
+;; Copyright (C) 2017 Free Software Foundation, Inc.
+
+;; This program is free software; you can redistribute it and/or modify
+;; it under the terms of the GNU General Public License as published by
+;; the Free Software Foundation, either version 3 of the License, or
+;; (at your option) any later version.
+
+;; This program is distributed in the hope that it will be useful,
+;; but WITHOUT ANY WARRANTY; without even the implied warranty of
+;; MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+;; GNU General Public License for more details.
+
+;; You should have received a copy of the GNU General Public License
+;; along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+;;; Commentary:
+
+;; The conformance tests against NormalizationTest.txt are Rust unit
+;; tests in rust_src/src/normalize.rs.
+
+;;; Code:
+
+(require 'ert)
+
+(ert-deftest normalize-tests-strings ()
+  (should (equal (string-to-nfd "\u00C5ngstr\u00F6m") "A\u030Angstro\u0308m"))
+  (should (equal (string-to-nfc "A\u030Angstro\u0308m") "\u00C5ngstr\u00F6m"))
+  ;; ANGSTROM SIGN is a singleton decomposition.
+  (should (equal (string-to-nfc "\u212B") "\u00C5"))
+  (should (equal (string-to-nfkd "\uFB01") "fi"))
+  (should (equal (string-to-nfd "\uFB01") "\uFB01"))
+  (should (equal (string-to-nfkc "\u2460") "1"))
+  ;; Hangul syllables are composed algorithmically.
+  (should (equal (string-to-nfc "\u1100\u1161\u11A8") "\uAC01"))
+  (should (equal (string-to-nfd "\uAC01") "\u1100\u1161\u11A8"))
+  ;; Combining marks are put into canonical order.
+  (should (equal (string-to-nfd "a\u0301\u0323") "a\u0323\u0301")))
+
+(ert-deftest normalize-tests-unchanged ()
+  (let ((s "plain ASCII"))
+    (should (eq (string-to-nfc s) s))
+    (should (eq (string-to-nfkd s) s)))
+  (let ((s (string-to-multibyte "a\377b")))
+    (should (equal (string-to-nfd s) s)))
+  (let ((s "\300\301"))
+    (should (eq (string-to-nfc s) s))))
+
+(ert-deftest normalize-tests-raw-bytes ()
+  (let ((s (concat "A" (string (unibyte-char-to-multibyte #xC3)) "\u030A")))
+    (should (equal (string-to-nfc s) s))
+    (should (equal (string-to-nfd s) s))))
+
+(ert-deftest normalize-tests-region ()
+  (with-temp-buffer
+    (insert "x A\u030A y")
+    (region-to-nfc (point-min) (point-max))
+    (should (equal (buffer-string) "x \u00C5 y"))
+    (region-to-nfd 3 4)
+    (should (equal (buffer-string) "x A\u030A y"))))
+
+(provide 'normalize-tests)
+;;; normalize-tests.el ends here
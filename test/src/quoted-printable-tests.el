@@ -0,0 +1,68 @@
+;;; quoted-printable-tests.el --- Tests for quoted_printable.rs
+
+;; Copyright (C) 2017 Free Software Foundation, Inc.
+
+;; This program is free software; you can redistribute it and/or modify
+;; it under the terms of the GNU General Public License as published by
+;; the Free Software Foundation, either version 3 of the License, or
+;; (at your option) any later version.
+
+;; This program is distributed in the hope that it will be useful,
+;; but WITHOUT ANY WARRANTY; without even the implied warranty of
+;; MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+;; GNU General Public License for more details.
+
+;; You should have received a copy of the GNU General Public License
+;; along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+;;; Code:
+
+(require 'ert)
+
+(ert-deftest quoted-printable-tests-strings ()
+  (should (equal (quoted-printable-encode-string "caf\u00e9 = ok")
+                 "caf=E9 =3D ok"))
+  (should (equal (quoted-printable-encode-string "a \t\nb ") "a=20=09\nb=20"))
+  (should (equal (quoted-printable-decode-string "caf=E9 =3d ok=\n!")
+                 "caf\351 = ok!"))
+  (should (equal (quoted-printable-decode-string "caf=C3=A9" 'utf-8)
+                 "caf\u00e9"))
+  ;; Malformed sequences are left alone.
+  (should (equal (quoted-printable-decode-string "a=Gb=4") "a=Gb=4"))
+  (should-error (quoted-printable-encode-string "\u20ac")))
+
+(ert-deftest quoted-printable-tests-encode-region ()
+  (with-temp-buffer
+    (insert (make-string 100 ?x) "\n-- \n")
+    (should (= (quoted-printable-encode-region (point-min) (point-max) t)
+                109))
+    (should (equal (buffer-string)
+                   (concat (make-string 75 ?x) "=\n" (make-string 25 ?x)
+                           "\n--=20\n")))
+    (erase-buffer)
+    (insert "From me\n-x\n")
+    (let ((mm-use-ultra-safe-encoding t))
+      (quoted-printable-encode-region (point-min) (point-max)))
+    (should (equal (buffer-string) "From=20me\n=2Dx\n"))
+    (erase-buffer)
+    (insert "abc")
+    (quoted-printable-encode-region (point-min) (point-max) nil "^b")
+    (should (equal (buffer-string) "a=62c"))))
+
+(ert-deftest quoted-printable-tests-decode-region ()
+  (with-temp-buffer
+    (insert "xx=E9=\nyy")
+    (goto-char (point-max))
+    (should (= (quoted-printable-decode-region 3 8) 1))
+    (should (equal (buffer-string)
+                   (string ?x ?x (unibyte-char-to-multibyte ?\351) ?y ?y)))
+    (should (= (point) 6))
+    (erase-buffer)
+    (insert "caf=C3=A9")
+    (should (= (quoted-printable-decode-region (point-min) (point-max) 'utf-8)
+               4))
+    (should (equal (buffer-string) "caf\u00e9"))))
+
+(provide 'quoted-printable-tests)
+
+;;; quoted-printable-tests.el ends here
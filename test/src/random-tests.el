@@ -0,0 +1,79 @@
+;;; random-tests.el --- Tests for random.rs
+
+;; Copyright (C) 2017 Free Software Foundation, Inc.
+
+;; This program is free software; you can redistribute it and/or modify
+;; it under the terms of the GNU General Public License as published by
+;; the Free Software Foundation, either version 3 of the License, or
+;; (at your option) any later version.
+
+;; This program is distributed in the hope that it will be useful,
+;; but WITHOUT ANY WARRANTY; without even the implied warranty of
+;; MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+;; GNU General Public License for more details.
+
+;; You should have received a copy of the GNU General Public License
+;; along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+;;; Code:
+
+(require 'ert)
+
+(ert-deftest random-tests-random ()
+  (dotimes (_ 100)
+    (let ((n (random 7)))
+      (should (and (<= 0 n) (< n 7)))))
+  (should (integerp (random)))
+  (should (integerp (random -3)))
+  (should (= (random 1) 0)))
+
+(ert-deftest random-tests-random-state ()
+  (let ((state (make-random-state 42)))
+    (should (random-state-p state))
+    (should-not (random-state-p [random-state 1]))
+    (should-not (random-state-p (record 'random-state "")))
+    ;; The numbers derived from a seed are stable across releases.
+    (should (equal (mapcar (lambda (_) (random 100 state)) '(1 2 3 4 5))
+                   '(42 2 9 93 76)))
+    (let ((copy (make-random-state state)))
+      (should (= (random 1000 state) (random 1000 copy))))
+    ;; A state reads back.
+    (let ((copy (car (read-from-string (prin1-to-string state)))))
+      (should (random-state-p copy))
+      (should (= (random 1000 state) (random 1000 copy)))))
+  (let ((state (make-random-state "emacs")))
+    (should (equal (list (random 1000 state) (random 1000 state)
+                         (random 1000 state))
+                   '(146 214 846)))
+    (random "emacs" state)
+    (should (= (random 1000 state) 146)))
+  (should (random-state-p (make-random-state)))
+  (should (random-state-p (make-random-state t)))
+  (should-error (make-random-state 1.5) :type 'wrong-type-argument)
+  (should-error (random 10 'foo) :type 'wrong-type-argument))
+
+(ert-deftest random-tests-random-float ()
+  (should (= (random-float (make-random-state 1)) 0.7029218331588505))
+  (dotimes (_ 100)
+    (let ((x (random-float)))
+      (should (and (<= 0.0 x) (< x 1.0))))))
+
+(ert-deftest random-tests-shuffle ()
+  (should (equal (shuffle (list 1 2 3 4 5) (make-random-state 7))
+                 '(2 4 1 3 5)))
+  (should (equal (shuffle (vector 1 2 3 4 5) (make-random-state 7))
+                 [2 4 1 3 5]))
+  (let ((list (number-sequence 1 50)))
+    (should (eq (shuffle list) list))
+    (should (equal (sort (copy-sequence list) #'<) (number-sequence 1 50))))
+  (should (equal (shuffle nil) nil))
+  (should (equal (shuffle (vector)) []))
+  (should-error (shuffle "abc") :type 'wrong-type-argument)
+  (should-error (shuffle (cons 1 2)) :type 'wrong-type-argument)
+  (let ((list (list 1 2 3)))
+    (setcdr (last list) list)
+    (should-error (shuffle list) :type 'circular-list)))
+
+(provide 'random-tests)
+
+;;; random-tests.el ends here
@@ -0,0 +1,129 @@
+;;; timefns-tests.el --- Tests for timefns.rs
+
+;; Copyright (C) 2017 Free Software Foundation, Inc.
+
+;; This program is free software; you can redistribute it and/or modify
+;; it under the terms of the GNU General Public License as published by
+;; the Free Software Foundation, either version 3 of the License, or
+;; (at your option) any later version.
+
+;; This program is distributed in the hope that it will be useful,
+;; but WITHOUT ANY WARRANTY; without even the implied warranty of
+;; MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+;; GNU General Public License for more details.
+
+;; You should have received a copy of the GNU General Public License
+;; along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+;;; Code:
+
+(require 'ert)
+
+(ert-deftest timefns-tests-arith ()
+  (should (equal (time-add '(1 2) '(3 4)) '(4 6)))
+  (should (equal (time-add '(0 65535 999999) '(0 0 1)) '(1 0 0)))
+  (should (equal (time-subtract '(0 0 0 1) '(0 1)) '(-1 65535 0 1)))
+  (should (equal (time-add 1 '(0 1 500000)) '(0 2 500000)))
+  (should (equal (time-add 1.5 1) 2.5))
+  (should (equal (time-subtract '(300000 . 200000) '(100000 . 300000))
+                 '(700000 . 600000)))
+  (should (equal (time-add '(100000 . 300000) '(0 0 0 1))
+                 '(1000000000003 . 3000000000000)))
+  (should (equal (time-add '(100000 . 400000) 0) '(100000 . 400000)))
+  (should (equal (time-add '(-3 . 65536) 0) '(-3 . 65536)))
+  ;; A cons with a cdr below 65536 is the obsolete (HIGH . LOW).
+  (should (equal (time-add '(1 . 2) 0) '(1 2)))
+  (should (equal (time-add '(1 . 0) 0.5) 65536.5))
+  (should-error (time-add '(1 . -1) 0))
+  (should-error (time-add 'foo 0))
+  (should-error (time-add 1.0e+INF 0)))
+
+(ert-deftest timefns-tests-compare ()
+  (should (time-less-p '(100000 . 300000) '(0 0 333334)))
+  (should-not (time-less-p '(0 0 333334) '(100000 . 300000)))
+  (should (time-less-p -1 '(0 0)))
+  (should (time-equal-p 1.5 '(300000 . 200000)))
+  (should (time-equal-p '(1 . 2) '(1 2)))
+  (should (time-equal-p '(0 1 500000) '(0 1 500000 0)))
+  (should (time-equal-p nil nil))
+  (should-not (time-equal-p nil '(0 0)))
+  (should-not (time-equal-p '(0 1) '(0 1 0 1))))
+
+(ert-deftest timefns-tests-float-time ()
+  (should (= (float-time '(0 1 500000)) 1.5))
+  (should (= (float-time '(-300000 . 200000)) -1.5))
+  (should (floatp (float-time))))
+
+(ert-deftest timefns-tests-decode-encode ()
+  (should (equal (decode-time 0 t) '(0 0 0 1 1 1970 4 nil 0)))
+  (should (equal (decode-time -1 t) '(59 59 23 31 12 1969 3 nil 0)))
+  (should (equal (decode-time '(1202 22527 999999) "IST-5:30")
+                 '(59 29 5 1 7 1972 6 nil 19800)))
+  (should (equal (decode-time 1636264799 "EST5EDT,M3.2.0,M11.1.0")
+                 '(59 59 1 7 11 2021 0 t -14400)))
+  (should (equal (encode-time 0 0 0 1 1 1970 t) '(0 0)))
+  (should (equal (encode-time 0 0 0 0 3 2000 t) (encode-time 0 0 0 29 2 2000 t)))
+  (should (equal (encode-time 0 0 0 1 1 1600 t) '(-178164 59904)))
+  (should (equal (apply #'encode-time (decode-time 1000000000 "PST8PDT"))
+                 '(15258 51712)))
+  ;; A skipped local time, and a repeated one.
+  (should (equal (encode-time 30 30 2 14 3 2021 "EST5EDT") '(24653 48022)))
+  (should (equal (encode-time 30 30 1 7 11 2021 "EST5EDT") '(24967 25718)))
+  (should-error (encode-time 0 0 0 1 1 most-positive-fixnum t))
+  (should-error (decode-time 0 'not-a-zone)))
+
+(ert-deftest timefns-tests-zoneinfo ()
+  (skip-unless (file-exists-p "/usr/share/zoneinfo/America/New_York"))
+  (should (equal (format-time-string "%F %T %Z" 1000000000 "America/New_York")
+                 "2001-09-08 21:46:40 EDT"))
+  (should (equal (format-time-string "%F %T %z" 0 ":America/New_York")
+                 "1969-12-31 19:00:00 -0500")))
+
+(ert-deftest timefns-tests-format-time-string ()
+  (let ((look '(1202 22527 999999 999999)))
+    (should (equal (format-time-string "%c" look t) "Fri Jun 30 23:59:59 1972"))
+    (should (equal (format-time-string "%^a %#A %_m %-d %5Y %G-W%V" look t)
+                   "FRI FRIDAY  6 30 01972 1972-W26"))
+    (should (equal (format-time-string "%N %6N %:z %::z" look t)
+                   "999999999 999999 +00:00 +00:00:00"))
+    (should (equal (format-time-string "%s %%" look 3600) "78796799 %"))
+    (should (equal (format-time-string "%Z" look 3600) "+01"))
+    (should (equal (format-time-string "%Z" look -19800) "-0530"))
+    (should (equal (format-time-string "%3N" '(200000 . 300000) t) "666"))
+    (should (equal (format-time-string "é %Y" 0 t) "é 1970"))
+    (should (equal (format-time-string "a\0b" 0 t) "a\0b"))))
+
+;; parse-date-string is in parse_date.rs.
+(ert-deftest timefns-tests-parse-date-string ()
+  (should (equal (parse-date-string "2016-12-01T23:35:06.5-05:00")
+                 '((650000 . 100000) 35 23 1 12 2016 4 nil -18000)))
+  (should (equal (parse-date-string "20161201T233506Z")
+                 '(6 35 23 1 12 2016 4 nil 0)))
+  (should (equal (parse-date-string "2016-12-01 23:35:06+05:30")
+                 '(6 35 23 1 12 2016 4 nil 19800)))
+  (should (equal (parse-date-string "2016-W48-4") '(0 0 0 1 12 2016 4 nil nil)))
+  (should (equal (parse-date-string "2016-336") '(0 0 0 1 12 2016 4 nil nil)))
+  (should (equal (parse-date-string "Mon, 22 Feb 2016 19:35:42 +0100")
+                 '(42 35 19 22 2 2016 1 nil 3600)))
+  (should (equal (parse-date-string "Monday, 22 february 2016 19:35:42 PDT")
+                 '(42 35 19 22 2 2016 1 t -25200)))
+  (should (equal (parse-date-string "P1Y2M3DT4H5M6S")
+                 '(6 5 4 3 2 1 nil nil nil)))
+  (should (equal (parse-date-string "P2W") '(0 0 0 14 0 0 nil nil nil)))
+  (should-not (parse-date-string "2016-02-30"))
+  (should-not (parse-date-string "not a date"))
+  (should (equal (parse-date-string "01/12/2016 11:35 PM" "%d/%m/%Y %I:%M %p")
+                 '(0 35 23 1 12 2016 4 nil nil)))
+  (should (equal (parse-date-string "1480635306.25" "%s.%N")
+                 '((625000 . 100000) 35 23 1 12 2016 4 nil 0)))
+  (should-not (parse-date-string "2016-12-01" "%d/%m/%Y"))
+  (should-error (parse-date-string "2016" "%Q"))
+  (should (equal (apply #'encode-time (parse-date-string "2016-12-01T23:35:06.5Z"))
+                 '(148063530650000 . 100000)))
+  (should (time-equal-p (apply #'encode-time
+                               (parse-date-string "1998-09-12T12:21:54.25-02:00"))
+                        (time-add '(13818 33666) 0.25))))
+
+(provide 'timefns-tests)
+
+;;; timefns-tests.el ends here
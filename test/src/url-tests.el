@@ -0,0 +1,97 @@
+;;; url-tests.el --- Tests for url/mod.rs
+
+;; Copyright (C) 2017 Free Software Foundation, Inc.
+
+;; This program is free software; you can redistribute it and/or modify
+;; it under the terms of the GNU General Public License as published by
+;; the Free Software Foundation, either version 3 of the License, or
+;; (at your option) any later version.
+
+;; This program is distributed in the hope that it will be useful,
+;; but WITHOUT ANY WARRANTY; without even the implied warranty of
+;; MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+;; GNU General Public License for more details.
+
+;; You should have received a copy of the GNU General Public License
+;; along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+;;; Code:
+
+(require 'ert)
+(require 'url-parse)
+
+(ert-deftest url-tests-parse ()
+  (let ((url (url-parse-string "HTTP://user:pw@Example.ORG:8080/a/./b/../c?q=1#frag")))
+    (should (url-p url))
+    (should (equal (url-type url) "http"))
+    (should (equal (url-user url) "user"))
+    (should (equal (url-password url) "pw"))
+    (should (equal (url-host url) "example.org"))
+    (should (equal (url-portspec url) 8080))
+    (should (equal (url-filename url) "/a/c?q=1"))
+    (should (equal (url-target url) "frag"))
+    (should (url-fullness url))
+    (should (url-use-cookies url))
+    (should (equal (url-recreate-url url)
+                   "http://user:pw@example.org:8080/a/c?q=1#frag")))
+  (let ((url (url-parse-string "https://example.org:443")))
+    (should-not (url-user url))
+    (should-not (url-portspec url))
+    (should (equal (url-port url) 443))
+    (should (equal (url-filename url) "/")))
+  (let ((url (url-parse-string "mailto:someone@example.org")))
+    (should-not (url-host url))
+    (should-not (url-fullness url))
+    (should (equal (url-filename url) "someone@example.org")))
+  (should (equal (url-host (url-parse-string "http://[::1]/")) "[::1]"))
+  (should (equal (url-host (url-parse-string "http://0x7f.1/")) "127.0.0.1"))
+  (should (equal (url-host (url-parse-string "http://Bücher.example/"))
+                 "xn--bcher-kva.example"))
+  (should (equal (url-filename (url-parse-string "http://h/é x"))
+                 "/%C3%A9%20x"))
+  (should-not (url-parse-string "http://exa mple.org/"))
+  (should-not (url-parse-string "relative/path")))
+
+(ert-deftest url-tests-parse-base ()
+  (let ((base "http://example.org/a/b/c"))
+    (should (equal (url-filename (url-parse-string "../d?q" base)) "/a/d?q"))
+    (should (equal (url-filename (url-parse-string "//other/x" base)) "/x"))
+    (should (equal (url-host (url-parse-string "//other/x" base)) "other"))
+    (should (equal (url-target (url-parse-string "#top" base)) "top"))
+    (should-not (url-parse-string "x" "not a url"))))
+
+(ert-deftest url-tests-percent-encode ()
+  (should (equal (url-percent-encode "") ""))
+  (should (equal (url-percent-encode "a b/c~") "a%20b%2Fc~"))
+  (should (equal (url-percent-encode "é") "%C3%A9"))
+  (should (equal (url-percent-encode "a b/c" "/") "a%20b/c"))
+  (should (equal (url-percent-encode "a/b" (url--allowed-chars '(?/)))
+                 "%61/%62"))
+  (should-not (multibyte-string-p (url-percent-encode "é")))
+  (should (equal (url-hexify-string "café au lait")
+                 "caf%C3%A9%20au%20lait"))
+  (should-error (url-percent-encode "a" 1)))
+
+(ert-deftest url-tests-percent-decode ()
+  (should (equal (url-percent-decode "a%20b") "a b"))
+  (should (equal (url-percent-decode "%C3%a9") "\303\251"))
+  (should (equal (url-percent-decode "100%") "100%"))
+  (should (equal (url-percent-decode "%zz%4") "%zz%4"))
+  (should-not (multibyte-string-p (url-percent-decode "%C3%A9")))
+  (should (equal (decode-coding-string (url-percent-decode "%C3%A9") 'utf-8)
+                 "é")))
+
+(ert-deftest url-tests-idna ()
+  (should (equal (url-idna-encode "Bücher.example") "xn--bcher-kva.example"))
+  (should (equal (url-idna-encode "Example.COM") "example.com"))
+  (should (equal (url-idna-encode "faß.de") "xn--fa-hia.de"))
+  (should-not (url-idna-encode "xn--"))
+  (should-not (url-idna-encode "a\u200db"))
+  (should (equal (url-idna-decode "xn--bcher-kva.example") "bücher.example"))
+  (should (equal (url-idna-decode "xn--ihqwcrb4cv8a8dqg056pqjye.org")
+                 "他们为什么不说中文.org"))
+  (should (equal (url-idna-decode "xn--.org") "xn--.org")))
+
+(provide 'url-tests)
+
+;;; url-tests.el ends here
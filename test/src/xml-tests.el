@@ -71,4 +71,133 @@
         (should (equal (cdr test)
                        (libxml-parse-xml-region (point-min) (point-max) nil t)))))))
 
+(defun libxml-tests--parse (xml &rest args)
+  (with-temp-buffer
+    (insert xml)
+    (apply #'libxml-parse-xml-region (point-min) (point-max) args)))
+
+(defun libxml-tests--error (xml)
+  (cadr (should-error (libxml-tests--parse xml))))
+
+(ert-deftest libxml-tests-blanks ()
+  (should (equal (libxml-tests--parse "<a>\n  <b/>\n  <c> </c>\n</a>")
+                 '(a nil (b nil) (c nil " "))))
+  (should (equal (libxml-tests--parse "<a>x <b/> </a>")
+                 '(a nil "x " (b nil) " ")))
+  (should (equal (libxml-tests--parse "<a>\r\nx\ry</a>")
+                 '(a nil "\nx\ny"))))
+
+(ert-deftest libxml-tests-namespaces ()
+  (should (equal (libxml-tests--parse
+                  (concat "<svg xmlns='http://www.w3.org/2000/svg'"
+                          " xmlns:xlink='http://www.w3.org/1999/xlink'>"
+                          "<image xlink:href='a.png' x:y='1'/></svg>"))
+                 '(svg nil (image ((href . "a.png") (x:y . "1"))))))
+  (should (equal (libxml-tests--parse "<a xml:lang='de'/>")
+                 '(a ((lang . "de"))))))
+
+(ert-deftest libxml-tests-cdata-and-entities ()
+  (should (equal (libxml-tests--parse
+                  "<a>x<![CDATA[<b>&amp;]]>&lt;&#x263A;&#65;</a>")
+                 '(a nil "x" "<b>&amp;" "<\u263aA")))
+  (should (equal (libxml-tests--parse
+                  (concat "<!DOCTYPE a [<!ENTITY e 'one <b>two</b>'>]>"
+                          "<a t='&lt;&amp;'>&e;!</a>"))
+                 '(a ((t . "<&")) "one " (b nil "two") "!")))
+  ;; External entities are never loaded.
+  (should (equal (libxml-tests--parse
+                  (concat "<!DOCTYPE a [<!ENTITY e SYSTEM '/etc/passwd'>]>"
+                          "<a>&e;</a>"))
+                 '(a nil)))
+  (should (equal (libxml-tests--parse "<a>Grüße</a>")
+                 '(a nil "Grüße"))))
+
+(ert-deftest libxml-tests-billion-laughs ()
+  (let ((xml "<!DOCTYPE lolz [<!ENTITY lol0 'lol'>"))
+    (dotimes (i 9)
+      (setq xml (concat xml (format "<!ENTITY lol%d '" (1+ i))
+                        (apply #'concat (make-list 10 (format "&lol%d;" i)))
+                        "'>")))
+    (setq xml (concat xml "]><lolz>&lol9;</lolz>"))
+    (should (string-match-p "amplification" (libxml-tests--error xml))))
+  (should (string-match-p
+           "loop"
+           (libxml-tests--error
+            "<!DOCTYPE a [<!ENTITY a '&b;'><!ENTITY b '&a;'>]><a>&a;</a>"))))
+
+(defun libxml-tests--parse-html (html &rest args)
+  (with-temp-buffer
+    (insert html)
+    (apply #'libxml-parse-html-region (point-min) (point-max) args)))
+
+(ert-deftest libxml-tests-html ()
+  (should (equal (libxml-tests--parse-html
+                  (concat "<html><head></head><body width=101>"
+                          "<div class=thing>Foo<div>Yes"))
+                 '(html nil
+                        (head nil)
+                        (body ((width . "101"))
+                              (div ((class . "thing"))
+                                   "Foo"
+                                   (div nil "Yes"))))))
+  (should (equal (libxml-tests--parse-html "<p>a<table><td>b</table>")
+                 '(html nil
+                        (head nil)
+                        (body nil
+                              (p nil "a"
+                                 (table nil
+                                        (tbody nil (tr nil (td nil "b")))))))))
+  (should (equal (libxml-tests--parse-html
+                  "<svg viewbox='0 0 1 1'><clippath/></svg>")
+                 '(html nil
+                        (head nil)
+                        (body nil
+                              (svg ((viewBox . "0 0 1 1")) (clipPath nil)))))))
+
+(ert-deftest libxml-tests-html-comments ()
+  (let ((html "<!-- a --><p>b<!-- c -->"))
+    (should (equal (libxml-tests--parse-html html)
+                   '(top nil
+                         (comment nil " a ")
+                         (html nil
+                               (head nil)
+                               (body nil (p nil "b" (comment nil " c ")))))))
+    ;; Only the comments outside the root element are discarded.
+    (should (equal (libxml-tests--parse-html html nil t)
+                   '(html nil
+                          (head nil)
+                          (body nil (p nil "b" (comment nil " c "))))))))
+
+(ert-deftest libxml-tests-html-base-url ()
+  (should (equal (libxml-tests--parse-html
+                  "<a href='../c?d'>x</a><img src='//e/f'>"
+                  "http://a/b/g")
+                 '(html nil (head nil)
+                        (body nil (a ((href . "../c?d")) "x")
+                              (img ((src . "//e/f")))))))
+  (should (equal (libxml-tests--parse-html
+                  "<base href='x/'><a href='y'></a>" "http://a/b")
+                 '(html nil (head nil (base ((href . "http://a/x/"))))
+                        (body nil (a ((href . "y"))))))))
+
+(ert-deftest libxml-tests-errors ()
+  (should (equal (libxml-tests--error "<a>\n  <b></c>\n</a>")
+                 (concat "XML parse error at line 2, column 9: "
+                         "Opening and ending tag mismatch: b and c")))
+  (should (string-match-p "line 1, column 1: Document is empty"
+                          (libxml-tests--error "")))
+  (should (string-match-p "line 1, column 5: Extra content"
+                          (libxml-tests--error "<a/><b/>")))
+  (should (string-match-p "not defined" (libxml-tests--error "<a>&x;</a>")))
+  (should (string-match-p "redefined" (libxml-tests--error "<a b='1' b='2'/>")))
+  (should-error (libxml-tests--parse "<a/>" 'not-a-string)))
+
+(ert-deftest libxml-tests-region ()
+  (with-temp-buffer
+    (insert "junk<a>x</a>junk")
+    ;; Move the gap into the region.
+    (goto-char 8)
+    (insert "y")
+    (should (equal (libxml-parse-xml-region 5 14) '(a nil "yx")))))
+
 ;;; libxml-tests.el ends here
@@ -0,0 +1,109 @@
+;;; zlib-tests.el --- Test suite for zlib and gzip compression.
+
+;; Copyright (C) 2013-2017 Free Software Foundation, Inc.
+
+;; Author: Lars Ingebrigtsen <larsi@gnus.org>
+
+;; This file is part of GNU Emacs.
+
+;; GNU Emacs is free software: you can redistribute it and/or modify
+;; it under the terms of the GNU General Public License as published by
+;; the Free Software Foundation, either version 3 of the License, or
+;; (at your option) any later version.
+
+;; GNU Emacs is distributed in the hope that it will be useful,
+;; but WITHOUT ANY WARRANTY; without even the implied warranty of
+;; MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+;; GNU General Public License for more details.
+
+;; You should have received a copy of the GNU General Public License
+;; along with GNU Emacs.  If not, see <https://www.gnu.org/licenses/>.
+
+;;; Code:
+
+(require 'ert)
+
+(defvar zlib-tests-data-directory
+  (expand-file-name "data/decompress" (getenv "EMACS_TEST_DIRECTORY"))
+  "Directory containing zlib test data.")
+
+(ert-deftest zlib--decompress ()
+  "Test decompressing a gzipped file."
+  (when (and (fboundp 'zlib-available-p)
+	     (zlib-available-p))
+    (should (string=
+	     (with-temp-buffer
+	       (set-buffer-multibyte nil)
+	       (insert-file-contents-literally
+		(expand-file-name "foo.gz" zlib-tests-data-directory))
+	       (zlib-decompress-region (point-min) (point-max))
+	       (buffer-string))
+	     "foo\n"))))
+
+(ert-deftest zlib-tests-decompress-region-in-place ()
+  (with-temp-buffer
+    (set-buffer-multibyte nil)
+    (insert "before" (gzip-compress-string "hello\n") "after")
+    (goto-char (point-max))
+    (should (eq (zlib-decompress-region 7 (- (point-max) 5)) t))
+    (should (equal (buffer-string) "beforehello\nafter"))
+    (should (= (point) (point-max)))
+    ;; Invalid data is left alone.
+    (should-not (zlib-decompress-region (point-min) (point-max)))
+    (should (equal (buffer-string) "beforehello\nafter"))))
+
+(ert-deftest zlib-tests-compress-region ()
+  (let ((text (apply #'unibyte-string
+                     (mapcar (lambda (i) (% (* i i) 251))
+                             (number-sequence 0 5000)))))
+    (dolist (gzip '(nil t))
+      (dolist (level '(nil 0 1 9))
+        (with-temp-buffer
+          (set-buffer-multibyte nil)
+          (insert text)
+          (let ((length (zlib-compress-region (point-min) (point-max)
+                                              gzip level)))
+            (should (= length (buffer-size)))
+            (should (equal (= (char-after 1) #x1f) gzip)))
+          (should (zlib-decompress-region (point-min) (point-max)))
+          (should (equal (buffer-string) text)))))
+    (with-temp-buffer
+      (should-error (zlib-compress-region (point-min) (point-max))))
+    (with-temp-buffer
+      (set-buffer-multibyte nil)
+      (should-error (zlib-compress-region (point-min) (point-max) nil 10)
+                    :type 'args-out-of-range))))
+
+(ert-deftest zlib-tests-gzip-string ()
+  (dolist (string (list "" "a" (make-string 10000 ?x) "\xff\x00\x80"
+                        (string-to-multibyte "\xe9t\xe9")))
+    (let ((compressed (gzip-compress-string string)))
+      (should-not (multibyte-string-p compressed))
+      (should (equal (substring compressed 0 2) "\x1f\x8b"))
+      (should (equal (gzip-decompress-string compressed)
+                     (string-to-unibyte string)))))
+  (should (< (length (gzip-compress-string (make-string 10000 ?x))) 100))
+  ;; Concatenated members.
+  (should (equal (gzip-decompress-string
+                  (concat (gzip-compress-string "foo")
+                          (gzip-compress-string "bar")))
+                 "foobar"))
+  (should-error (gzip-decompress-string "not gzip data"))
+  (should-error (gzip-decompress-string
+                 (substring (gzip-compress-string "truncated") 0 -1)))
+  (should-error (gzip-compress-string "\u00e9t\u00e9")))
+
+(ert-deftest zlib-tests-checksums ()
+  (should (= (crc32 "") 0))
+  (should (= (crc32 "123456789") #xcbf43926))
+  (should (= (crc32 "56789" (crc32 "1234")) #xcbf43926))
+  (should (= (adler32 "") 1))
+  (should (= (adler32 "Wikipedia") #x11e60398))
+  (should (= (adler32 "pedia" (adler32 "Wiki")) #x11e60398))
+  (should (= (crc32 (string-to-multibyte "\xff")) (crc32 "\xff")))
+  (should-error (crc32 "\u00e9"))
+  (should-error (crc32 "" (ash 1 32)) :type 'args-out-of-range))
+
+(provide 'zlib-tests)
+
+;;; zlib-tests.el ends here.